@@ -3,13 +3,13 @@
 
 use std::env;
 
-use clap::{Parser, Subcommand};
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand};
 use git_internal::{
     errors::GitError,
     hash::{HashKind, set_hash_kind},
 };
 
-use crate::{command, utils};
+use crate::{command, internal::config_defaults::apply_config_defaults, utils};
 
 /// Reads the repository's configuration and sets the global hash kind.
 /// This must be called for any command that operates within an existing repository.
@@ -78,10 +78,16 @@ enum Commands {
     Stash(Stash),
     #[command(subcommand, about = "Large File Storage")]
     Lfs(command::lfs::LfsCmds),
+    #[command(subcommand, about = "Queue and run AI agent jobs")]
+    Ai(command::ai::AiCmds),
     #[command(about = "Show commit logs")]
     Log(command::log::LogArgs),
     #[command(about = "Summarize 'git log' output")]
     Shortlog(command::shortlog::ShortlogArgs),
+    #[command(about = "Rank files by how often they change")]
+    Churn(command::churn::ChurnArgs),
+    #[command(about = "Show repository statistics")]
+    Stats(command::stats::StatsArgs),
     #[command(about = "Show various types of objects")]
     Show(command::show::ShowArgs),
     #[command(about = "List, create, or delete branches")]
@@ -114,6 +120,12 @@ enum Commands {
     Blame(command::blame::BlameArgs),
     #[command(about = "Revert some existing commits")]
     Revert(command::revert::RevertArgs),
+    #[command(about = "Rewrite history to remove paths, fix authors or edit messages")]
+    FilterRepo(command::filter_repo::FilterRepoArgs),
+    #[command(about = "Import branches, tags and history from a Git repository")]
+    ImportGit(command::import_git::ImportGitArgs),
+    #[command(about = "Export branches, tags and history as a Git fast-import stream")]
+    ExportGit(command::export_git::ExportGitArgs),
     #[command(subcommand, about = "Manage set of tracked repositories")]
     Remote(command::remote::RemoteCmds),
     #[command(about = "Open the repository in the browser")]
@@ -145,6 +157,11 @@ pub enum Stash {
     Push {
         #[arg(short, long, help = "The message to display for the stash")]
         message: Option<String>,
+        #[arg(
+            long,
+            help = "Also record the active AI intent and session so that pop restores them"
+        )]
+        include_ai: bool,
     },
     #[command(about = "Remove a single stashed state from the stash list")]
     Pop {
@@ -252,20 +269,48 @@ fn is_short_number_flag(arg: &str) -> bool {
     rest.chars().all(|c| c.is_ascii_digit())
 }
 
+/// Fill flags the user did not pass from config-driven defaults (e.g. `shortlog.numbered`).
+async fn apply_command_config_defaults(
+    command: &mut Commands,
+    matches: &ArgMatches,
+) -> Result<(), String> {
+    let Some((_, sub_matches)) = matches.subcommand() else {
+        return Ok(());
+    };
+    match command {
+        Commands::Shortlog(args) => apply_config_defaults(args, sub_matches).await,
+        Commands::Log(args) => apply_config_defaults(args, sub_matches).await,
+        Commands::Status(args) => apply_config_defaults(args, sub_matches).await,
+        Commands::Ai(command::ai::AiCmds::PreCommitReview(args)) => {
+            match sub_matches.subcommand() {
+                Some((_, review_matches)) => apply_config_defaults(args, review_matches).await,
+                None => Ok(()),
+            }
+        }
+        Commands::Ai(command::ai::AiCmds::Work(args)) => match sub_matches.subcommand() {
+            Some((_, work_matches)) => apply_config_defaults(args, work_matches).await,
+            None => Ok(()),
+        },
+        _ => Ok(()),
+    }
+}
+
 /// `async` version of the [parse] function
 pub async fn parse_async(args: Option<&[&str]>) -> Result<(), GitError> {
-    let args = match args {
-        Some(args) => {
-            let argv = args.iter().map(|s| s.to_string()).collect::<Vec<_>>();
-            let argv = rewrite_log_short_number_args(argv);
-            Cli::try_parse_from(argv).map_err(|e| GitError::InvalidArgument(e.to_string()))?
-        }
-        None => {
-            let argv = env::args().collect::<Vec<_>>();
-            let argv = rewrite_log_short_number_args(argv);
-            Cli::parse_from(argv)
-        }
+    let embedded = args.is_some();
+    let argv = match args {
+        Some(args) => args.iter().map(|s| s.to_string()).collect::<Vec<_>>(),
+        None => env::args().collect::<Vec<_>>(),
     };
+    let argv = rewrite_log_short_number_args(argv);
+    // Keep the raw matches around so config defaults can tell explicit flags from clap defaults.
+    let matches = match Cli::command().try_get_matches_from(argv) {
+        Ok(matches) => matches,
+        Err(e) if embedded => return Err(GitError::InvalidArgument(e.to_string())),
+        Err(e) => e.exit(),
+    };
+    let mut args =
+        Cli::from_arg_matches(&matches).map_err(|e| GitError::InvalidArgument(e.to_string()))?;
     match &args.command {
         Commands::Init(_) | Commands::Clone(_) => {}
         // Config global/system scopes don't require a repository
@@ -275,6 +320,9 @@ pub async fn parse_async(args: Option<&[&str]>) -> Result<(), GitError> {
                 return Err(GitError::RepoNotFound);
             }
             set_local_hash_kind().await?;
+            apply_command_config_defaults(&mut args.command, &matches)
+                .await
+                .map_err(GitError::InvalidArgument)?;
         }
     }
     // parse the command and execute the corresponding function with it's args
@@ -294,8 +342,11 @@ pub async fn parse_async(args: Option<&[&str]>) -> Result<(), GitError> {
         Commands::Clean(args) => command::clean::execute(args).await,
         Commands::Stash(cmd) => command::stash::execute(cmd).await,
         Commands::Lfs(cmd) => command::lfs::execute(cmd).await,
+        Commands::Ai(cmd) => command::ai::execute(cmd).await,
         Commands::Log(args) => command::log::execute(args).await,
         Commands::Shortlog(args) => command::shortlog::execute(args).await,
+        Commands::Churn(args) => command::churn::execute(args).await,
+        Commands::Stats(args) => command::stats::execute(args).await,
         Commands::Show(args) => command::show::execute(args).await,
         Commands::Branch(args) => command::branch::execute(args).await,
         Commands::Tag(args) => command::tag::execute(args).await,
@@ -316,6 +367,9 @@ pub async fn parse_async(args: Option<&[&str]>) -> Result<(), GitError> {
         Commands::Diff(args) => command::diff::execute(args).await,
         Commands::Blame(args) => command::blame::execute(args).await,
         Commands::Revert(args) => command::revert::execute(args).await,
+        Commands::FilterRepo(args) => command::filter_repo::execute(args).await,
+        Commands::ImportGit(args) => command::import_git::execute(args).await,
+        Commands::ExportGit(args) => command::export_git::execute(args).await,
         Commands::Remote(cmd) => command::remote::execute(cmd).await,
         Commands::Open(args) => command::open::execute(args).await,
         Commands::Pull(args) => command::pull::execute(args).await,
@@ -10,7 +10,7 @@ use git_internal::internal::{
 
 use crate::{
     command::status,
-    utils::{ignore::IgnorePolicy, lfs, object_ext::BlobExt, path, util},
+    utils::{ignore::IgnorePolicy, index_lock::IndexLock, lfs, object_ext::BlobExt, path, util},
 };
 
 #[derive(Parser, Debug)]
@@ -111,6 +111,9 @@ pub async fn execute(args: AddArgs) {
         }
 
         let index_file = path::index();
+        let Some(lock) = lock_index(&index_file) else {
+            return;
+        };
         let mut index = Index::load(&index_file).unwrap();
         for file in &files {
             if index
@@ -122,7 +125,9 @@ pub async fn execute(args: AddArgs) {
             }
         }
 
-        index.save(&index_file).unwrap();
+        if let Err(e) = lock.commit(&mut index) {
+            eprintln!("fatal: {e}");
+        }
         return;
     }
 
@@ -158,6 +163,10 @@ pub async fn execute(args: AddArgs) {
     }
 
     let index_file = path::index();
+    // hold the lock across load and save so concurrent adds cannot lose each other's entries
+    let Some(lock) = lock_index(&index_file) else {
+        return;
+    };
     let mut index = Index::load(&index_file).unwrap();
     for file in &files {
         match add_a_file(file, &mut index, args.verbose).await {
@@ -171,7 +180,15 @@ pub async fn execute(args: AddArgs) {
             }
         }
     }
-    index.save(&index_file).unwrap();
+    if let Err(e) = lock.commit(&mut index) {
+        eprintln!("fatal: {e}");
+    }
+}
+
+fn lock_index(index_file: &Path) -> Option<IndexLock> {
+    IndexLock::acquire(index_file)
+        .inspect_err(|e| eprintln!("fatal: {e}"))
+        .ok()
 }
 
 /// `file` path must relative to the working directory
@@ -0,0 +1,1016 @@
+//! AI subcommands.
+//!
+//! `ai job` queues agent operations under `.libra/ai/jobs` and runs them later, one at a
+//! time, with their output captured to per-job logs. A job is any of the `ai` subcommands
+//! below (`work`, `explain`, `fix-build`, `eval`, `pre-commit-review`), or `prompt`, a
+//! single agent prompt without tools:
+//!
+//! ```text
+//! libra ai job submit -- explain main~3..main --provider openai
+//! libra ai job submit -- prompt --provider openai "Summarize the release notes"
+//! libra ai job run
+//! libra ai job show <id>
+//! ```
+//!
+//! Queued subcommands run through the same code as when invoked directly, so their side
+//! effects (task status, applied patches, recorded reviews) land in the same places; what
+//! they would print goes to the job log, and a one-line summary becomes the job's result.
+//! Config-driven defaults such as `ai.work.profile` are read when the job runs.
+//!
+//! Prompt jobs start their preamble with a snapshot of the repository (branch, HEAD, dirty
+//! files, recent authors; see [`crate::internal::ai::repo_context`]) taken when the job
+//! runs. `--no-repo-context` leaves it out.
+//!
+//! `ai eval` runs the agent evaluation scenarios (see [`crate::internal::ai::eval`]);
+//! `--live` also runs the ones marked `live: true` against a provider.
+//!
+//! `ai pre-commit-review` reviews the staged diff and is meant to be called from
+//! `.libra/hooks/pre-commit.sh` (see [`crate::internal::ai::precommit`]). It exits with 1
+//! when findings reach `ai.precommit.severity`, unless `--advisory` is given. Without a
+//! provider (`ai.precommit.provider`), with `LIBRA_SKIP_AI_REVIEW` set, or when the review
+//! itself fails, it prints why and exits with 0 so commits still go through.
+//!
+//! `ai work <task-id>` has an agent carry out a planned task and marks it done or failed
+//! (blocked) by what the agent reports (see [`crate::internal::ai::work`]). `--dry-run`
+//! only offers read-only tools and changes nothing.
+//!
+//! `ai explain <rev|path|range>` explains a commit, a file or a range of commits with the
+//! `explainer` profile (see [`crate::internal::ai::explain`]), as text, Markdown or JSON.
+//!
+//! `ai fix-build` runs `cargo check` and has the `build_error_resolver` profile propose a
+//! patch for the first errors (see [`crate::internal::ai::fix_build`]). The patch is only
+//! shown unless `--apply` is given.
+//!
+//! Agents answer in the language and tone their profile sets (see
+//! [`crate::internal::ai::agent::profile::style`]). `--language` overrides the profile's
+//! language, and `ai.language` applies when neither sets one.
+//!
+//! `--deadline <duration>` bounds the whole agent run, tool calls included. When it passes,
+//! the command stops, prints what the agent had produced so far (its last text, or the tool
+//! calls it completed) and fails with a deadline error.
+//!
+//! A provider refusing a request on content-policy grounds is reported as such, with the
+//! provider's explanation and a hint to rephrase, rather than as a generic failure.
+//!
+//! `ai work` and `ai fix-build` take `--events <path|->` to stream the agent's progress as
+//! JSON lines (see [`crate::internal::ai::event_stream`]) for tools that embed libra.
+
+use std::{io::Write, path::PathBuf, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    command::{
+        code::CodeProvider,
+        status::{changes_to_be_committed, changes_to_be_staged},
+    },
+    internal::{
+        ai::{
+            AgentBuilder, CompletionModel, Prompt,
+            agent::{
+                ToolLoopObserver,
+                profile::{
+                    AgentProfile, load_profiles,
+                    style::{LANGUAGE_KEY, normalize_language, style_instruction},
+                },
+                runtime::tool_loop::NoopObserver,
+            },
+            client::CompletionClient,
+            completion::CompletionError,
+            eval::{EvalRunner, load_scenarios},
+            event_stream::EventStreamObserver,
+            explain::{self, ExplainFormat, ExplainTarget},
+            fix_build::{self, FixBuild},
+            history::HistoryManager,
+            jobs::{self, Job, JobContext, JobError, JobExecutor, JobStore},
+            precommit::{
+                self, FindingSeverity, PRECOMMIT_MODEL_KEY, PRECOMMIT_PROVIDER_KEY,
+                PRECOMMIT_RECORD_KEY, PRECOMMIT_SEVERITY_KEY, PrecommitReview, SKIP_REVIEW_ENV,
+            },
+            providers::{
+                anthropic::{CLAUDE_3_5_SONNET, Client as AnthropicClient},
+                deepseek::client::Client as DeepSeekClient,
+                gemini::{Client as GeminiClient, GEMINI_2_5_FLASH},
+                openai::{Client as OpenAIClient, GPT_4O_MINI},
+                zhipu::{Client as ZhipuClient, GLM_5},
+            },
+            repo_context::RepoContextMiddleware,
+            tools::handlers::{CargoCheckHandler, cargo_check::DEFAULT_ERROR_LIMIT},
+            work::{self, TaskWork, WORK_PROFILE, WORK_PROFILE_KEY},
+        },
+        config_defaults::{
+            self, ApplyConfigDefaults, ConfigDefault, apply_config_defaults, parse_bool,
+        },
+        head::Head,
+    },
+    utils::{storage::local::LocalStorage, util},
+};
+
+/// Bind `$model` to a completion model for `$provider` (using `$id`, or the provider's
+/// default model) and evaluate `$body`. Returns early with an error when the provider's
+/// API key is not set.
+macro_rules! with_provider_model {
+    ($provider:expr, $id:expr, |$model:ident| $body:expr) => {
+        match $provider {
+            CodeProvider::Gemini => {
+                let client = GeminiClient::from_env()
+                    .map_err(|_| anyhow::anyhow!("GEMINI_API_KEY is not set"))?;
+                let $model = client.completion_model($id.unwrap_or(GEMINI_2_5_FLASH));
+                $body
+            }
+            CodeProvider::Openai => {
+                let client = OpenAIClient::from_env()
+                    .map_err(|_| anyhow::anyhow!("OPENAI_API_KEY is not set"))?;
+                let $model = client.completion_model($id.unwrap_or(GPT_4O_MINI));
+                $body
+            }
+            CodeProvider::Anthropic => {
+                let client = AnthropicClient::from_env()
+                    .map_err(|_| anyhow::anyhow!("ANTHROPIC_API_KEY is not set"))?;
+                let $model = client.completion_model($id.unwrap_or(CLAUDE_3_5_SONNET));
+                $body
+            }
+            CodeProvider::Deepseek => {
+                let client = DeepSeekClient::from_env()
+                    .map_err(|_| anyhow::anyhow!("DEEPSEEK_API_KEY is not set"))?;
+                let $model = client.completion_model($id.unwrap_or("deepseek-chat"));
+                $body
+            }
+            CodeProvider::Zhipu => {
+                let client = ZhipuClient::from_env()
+                    .map_err(|_| anyhow::anyhow!("ZHIPU_API_KEY is not set"))?;
+                let $model = client.completion_model($id.unwrap_or(GLM_5));
+                $body
+            }
+        }
+    };
+}
+
+#[derive(Subcommand, Debug)]
+pub enum AiCmds {
+    /// Queue agent operations and run them in the background
+    #[command(subcommand)]
+    Job(JobCmds),
+    /// Run agent evaluation scenarios and report which assertions failed
+    Eval(EvalArgs),
+    /// Review the staged changes; meant to be run from the pre-commit hook
+    PreCommitReview(PrecommitReviewArgs),
+    /// Have an agent carry out a planned task and mark it done or blocked
+    Work(WorkArgs),
+    /// Explain a commit, a file or a range of commits
+    Explain(ExplainArgs),
+    /// Run `cargo check` and have an agent propose a patch for the errors
+    FixBuild(FixBuildArgs),
+}
+
+#[derive(Parser, Debug)]
+pub struct FixBuildArgs {
+    /// Number of errors to send to the agent, first reported first
+    #[arg(long, default_value_t = DEFAULT_ERROR_LIMIT)]
+    limit: usize,
+
+    /// Apply the patch instead of only showing it; `PreToolUse` hooks may still block it
+    #[arg(long)]
+    apply: bool,
+
+    /// Give up after this long (e.g. `90s`, `5m`) and print whatever partial result the
+    /// agent has; a bare number is seconds
+    #[arg(long, value_name = "DURATION", value_parser = parse_deadline)]
+    deadline: Option<Duration>,
+
+    /// AI provider backend
+    #[arg(long, value_enum, default_value_t = CodeProvider::Gemini)]
+    provider: CodeProvider,
+
+    /// Model id (provider-specific)
+    #[arg(long)]
+    model: Option<String>,
+
+    /// Language the agent answers in; overrides the profile and `ai.language`
+    #[arg(long)]
+    language: Option<String>,
+
+    /// Write the agent's progress as JSON lines to a file, or to stderr with `-`
+    #[arg(long, value_name = "PATH|-")]
+    events: Option<String>,
+
+    /// Leave tool arguments and results out of the `--events` stream
+    #[arg(long, requires = "events")]
+    events_redact: bool,
+}
+
+#[derive(Parser, Debug)]
+pub struct ExplainArgs {
+    /// A revision, a path, or a range like `main..feature`
+    target: String,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t = ExplainFormat::Text)]
+    format: ExplainFormat,
+
+    /// Give up after this long (e.g. `90s`, `5m`) and print whatever partial result the
+    /// agent has; a bare number is seconds
+    #[arg(long, value_name = "DURATION", value_parser = parse_deadline)]
+    deadline: Option<Duration>,
+
+    /// AI provider backend
+    #[arg(long, value_enum, default_value_t = CodeProvider::Gemini)]
+    provider: CodeProvider,
+
+    /// Model id (provider-specific)
+    #[arg(long)]
+    model: Option<String>,
+
+    /// Language the agent answers in; overrides the profile and `ai.language`
+    #[arg(long)]
+    language: Option<String>,
+}
+
+#[derive(Parser, Debug)]
+pub struct WorkArgs {
+    /// Task id or a unique prefix of it
+    task: String,
+
+    /// Agent profile that carries out the task
+    #[arg(long, default_value = WORK_PROFILE)]
+    profile: String,
+
+    /// AI provider backend
+    #[arg(long, value_enum, default_value_t = CodeProvider::Gemini)]
+    provider: CodeProvider,
+
+    /// Model id (provider-specific)
+    #[arg(long)]
+    model: Option<String>,
+
+    /// Language the agent answers in; overrides the profile and `ai.language`
+    #[arg(long)]
+    language: Option<String>,
+
+    /// Maximum number of model round-trips
+    #[arg(long, default_value_t = 30)]
+    max_steps: usize,
+
+    /// Give up after this long (e.g. `90s`, `5m`) and print whatever partial result the
+    /// agent has; a bare number is seconds
+    #[arg(long, value_name = "DURATION", value_parser = parse_deadline)]
+    deadline: Option<Duration>,
+
+    /// Offer only read-only tools; change no task status and record nothing
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Write the agent's progress as JSON lines to a file, or to stderr with `-`
+    #[arg(long, value_name = "PATH|-")]
+    events: Option<String>,
+
+    /// Leave tool arguments and results out of the `--events` stream
+    #[arg(long, requires = "events")]
+    events_redact: bool,
+}
+
+impl ApplyConfigDefaults for WorkArgs {
+    const CONFIG_DEFAULTS: &'static [ConfigDefault] = &[ConfigDefault {
+        key: WORK_PROFILE_KEY,
+        arg: "profile",
+        negation: None,
+    }];
+
+    fn apply_config_default(&mut self, arg: &str, value: &str) -> Result<(), String> {
+        match arg {
+            "profile" => self.profile = value.to_string(),
+            _ => unreachable!("unknown work config default: {arg}"),
+        }
+        Ok(())
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct PrecommitReviewArgs {
+    /// Print findings but never fail the commit
+    #[arg(long)]
+    advisory: bool,
+
+    /// Lowest severity that fails the commit: low, medium, high or critical
+    #[arg(long, value_name = "LEVEL", default_value_t = FindingSeverity::High)]
+    severity: FindingSeverity,
+
+    /// AI provider backend; without one the review is skipped
+    #[arg(long, value_enum)]
+    provider: Option<CodeProvider>,
+
+    /// Model id (provider-specific)
+    #[arg(long)]
+    model: Option<String>,
+
+    /// Language the agent answers in; overrides the profile and `ai.language`
+    #[arg(long)]
+    language: Option<String>,
+
+    /// Record the review on the AI history branch, linked to the commit once it is made
+    #[arg(long)]
+    record: bool,
+
+    /// Skip the review, without blocking the commit, when it takes longer than this
+    #[arg(long, value_name = "DURATION", value_parser = parse_deadline)]
+    deadline: Option<Duration>,
+}
+
+impl ApplyConfigDefaults for PrecommitReviewArgs {
+    const CONFIG_DEFAULTS: &'static [ConfigDefault] = &[
+        ConfigDefault {
+            key: PRECOMMIT_SEVERITY_KEY,
+            arg: "severity",
+            negation: None,
+        },
+        ConfigDefault {
+            key: PRECOMMIT_PROVIDER_KEY,
+            arg: "provider",
+            negation: None,
+        },
+        ConfigDefault {
+            key: PRECOMMIT_MODEL_KEY,
+            arg: "model",
+            negation: None,
+        },
+        ConfigDefault {
+            key: PRECOMMIT_RECORD_KEY,
+            arg: "record",
+            negation: None,
+        },
+    ];
+
+    fn apply_config_default(&mut self, arg: &str, value: &str) -> Result<(), String> {
+        match arg {
+            "severity" => self.severity = value.parse()?,
+            "provider" => self.provider = Some(CodeProvider::from_str(value, true)?),
+            "model" => self.model = Some(value.to_string()),
+            "record" => self.record = parse_bool(value)?,
+            _ => unreachable!("unknown pre-commit-review config default: {arg}"),
+        }
+        Ok(())
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct EvalArgs {
+    /// Directory of scenario files
+    #[arg(long, default_value = "tests/ai_scenarios")]
+    dir: PathBuf,
+
+    /// Also run `live: true` scenarios against the provider
+    #[arg(long)]
+    live: bool,
+
+    /// AI provider backend for live scenarios
+    #[arg(long, value_enum, default_value_t = CodeProvider::Gemini)]
+    provider: CodeProvider,
+
+    /// Model id for live scenarios (provider-specific)
+    #[arg(long)]
+    model: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum JobCmds {
+    /// Queue an `ai` subcommand or a prompt, e.g. `submit -- explain HEAD --provider openai`
+    Submit {
+        /// The operation and its arguments
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        operation: Vec<String>,
+    },
+    /// Run all pending jobs sequentially
+    Run,
+    /// List jobs, oldest first
+    List,
+    /// Show a job's state and captured output
+    Show {
+        /// Job id or a unique prefix of it
+        id: String,
+    },
+    /// Cancel a pending job, or ask a running one to stop
+    Cancel {
+        /// Job id or a unique prefix of it
+        id: String,
+    },
+}
+
+/// Operations a job can run; parsed from [`Job::args`].
+#[derive(Parser, Debug)]
+#[command(no_binary_name = true)]
+enum JobOperation {
+    /// Send one prompt to an agent and record the answer
+    Prompt(PromptJobArgs),
+    /// Have an agent carry out a planned task and mark it done or blocked
+    Work(WorkArgs),
+    /// Explain a commit, a file or a range of commits
+    Explain(ExplainArgs),
+    /// Run `cargo check` and have an agent propose a patch for the errors
+    FixBuild(FixBuildArgs),
+    /// Run agent evaluation scenarios and report which assertions failed
+    Eval(EvalArgs),
+    /// Review the staged changes as they are when the job runs
+    PreCommitReview(PrecommitReviewArgs),
+}
+
+impl JobOperation {
+    /// Parse a job's arguments and fill the flags it leaves unset from config, as the
+    /// command line does for the same subcommands.
+    async fn parse(args: &[String]) -> anyhow::Result<Self> {
+        let matches = Self::command().try_get_matches_from(args)?;
+        let mut operation = Self::from_arg_matches(&matches)?;
+        if let Some((_, sub_matches)) = matches.subcommand() {
+            match &mut operation {
+                JobOperation::Work(args) => apply_config_defaults(args, sub_matches).await,
+                JobOperation::PreCommitReview(args) => {
+                    apply_config_defaults(args, sub_matches).await
+                }
+                _ => Ok(()),
+            }
+            .map_err(anyhow::Error::msg)?;
+        }
+        Ok(operation)
+    }
+}
+
+/// Where a command writes: the terminal when run directly, the job log when queued.
+struct Console<'a> {
+    out: &'a mut (dyn Write + Send),
+    err: &'a mut (dyn Write + Send),
+}
+
+#[derive(Parser, Debug)]
+struct PromptJobArgs {
+    /// AI provider backend
+    #[arg(long, value_enum, default_value_t = CodeProvider::Gemini)]
+    provider: CodeProvider,
+
+    /// Model id (provider-specific)
+    #[arg(long)]
+    model: Option<String>,
+
+    /// Language the agent answers in; overrides `ai.language`
+    #[arg(long)]
+    language: Option<String>,
+
+    /// System prompt for the agent
+    #[arg(long)]
+    preamble: Option<String>,
+
+    /// Do not prepend the repository context (branch, HEAD, dirty files) to the preamble
+    #[arg(long)]
+    no_repo_context: bool,
+
+    /// Give up after this long (e.g. `90s`, `5m`) and print whatever partial result the
+    /// agent has; a bare number is seconds
+    #[arg(long, value_name = "DURATION", value_parser = parse_deadline)]
+    deadline: Option<Duration>,
+
+    /// Prompt text
+    #[arg(required = true)]
+    text: Vec<String>,
+}
+
+pub async fn execute(cmd: AiCmds) {
+    let (mut stdout, mut stderr) = (std::io::stdout(), std::io::stderr());
+    let console = &mut Console {
+        out: &mut stdout,
+        err: &mut stderr,
+    };
+    let result = match cmd {
+        AiCmds::Job(cmd) => execute_job(cmd).await,
+        AiCmds::Eval(args) => execute_eval(args, console).await.map(drop),
+        AiCmds::PreCommitReview(args) => {
+            let (code, _) = execute_precommit_review(args, console).await;
+            if code != 0 {
+                std::process::exit(code);
+            }
+            Ok(())
+        }
+        AiCmds::Work(args) => execute_work(args, console).await.map(drop),
+        AiCmds::Explain(args) => execute_explain(args, console).await.map(drop),
+        AiCmds::FixBuild(args) => execute_fix_build(args, console).await.map(drop),
+    };
+    if let Err(e) = result {
+        if let Some(message) = refusal_message(&e) {
+            eprintln!("error: the provider declined the request: {message}");
+            eprintln!("{REFUSAL_HINT}");
+        } else if let Some(partial) = partial_result(&e) {
+            if !partial.is_empty() {
+                println!("{}", partial.trim_end());
+            }
+            eprintln!("fatal: {e}");
+        } else {
+            eprintln!("fatal: {e}");
+        }
+    }
+}
+
+const REFUSAL_HINT: &str =
+    "hint: rephrase the request, or narrow it to the code in question, and try again";
+
+/// The provider's explanation if `error` comes from a content-policy refusal.
+fn refusal_message(error: &anyhow::Error) -> Option<&str> {
+    error
+        .chain()
+        .find_map(|cause| match cause.downcast_ref::<CompletionError>() {
+            Some(CompletionError::Refused { message }) => Some(message.as_str()),
+            _ => None,
+        })
+}
+
+/// What the agent had produced if `error` comes from an expired `--deadline`.
+fn partial_result(error: &anyhow::Error) -> Option<&str> {
+    error
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<CompletionError>()?.partial_result())
+}
+
+/// Parse a `--deadline` like `500ms`, `90s`, `5m` or `1h`; a bare number is seconds.
+fn parse_deadline(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration '{value}': expected e.g. 90s or 5m"))?;
+    let duration = match unit {
+        "ms" => Duration::from_millis(number),
+        "" | "s" => Duration::from_secs(number),
+        "m" => Duration::from_secs(number * 60),
+        "h" => Duration::from_secs(number * 60 * 60),
+        _ => {
+            return Err(format!("invalid duration unit '{unit}': use ms, s, m or h"));
+        }
+    };
+    if duration.is_zero() {
+        return Err("the deadline must be greater than zero".to_string());
+    }
+    Ok(duration)
+}
+
+async fn execute_job(cmd: JobCmds) -> anyhow::Result<()> {
+    let store = JobStore::new(jobs::jobs_dir(&util::storage_path()));
+    match cmd {
+        JobCmds::Submit { operation } => {
+            // Reject malformed operations now rather than when the queue runs.
+            JobOperation::parse(&operation).await?;
+            let job = store.submit(operation)?;
+            println!("Submitted job {}", job.id);
+        }
+        JobCmds::Run => {
+            let token = CancellationToken::new();
+            let on_interrupt = token.clone();
+            tokio::spawn(async move {
+                if tokio::signal::ctrl_c().await.is_ok() {
+                    on_interrupt.cancel();
+                }
+            });
+            let finished = store.run_pending(&CliJobExecutor, &token).await?;
+            if finished.is_empty() {
+                println!("No pending jobs");
+            }
+            for job in finished {
+                println!("{} {}", job.id, job.status);
+            }
+        }
+        JobCmds::List => {
+            for job in store.list()? {
+                println!(
+                    "{}  {:<9}  {}  {}",
+                    job.id,
+                    job.status.to_string(),
+                    job.created_at.format("%Y-%m-%d %H:%M:%S"),
+                    job.args.join(" ")
+                );
+            }
+        }
+        JobCmds::Show { id } => {
+            let job = store.get(&id)?;
+            print_job(&job);
+            let log = jobs::read_log(&store, &job.id)?;
+            if !log.is_empty() {
+                println!("\n{}", log.trim_end());
+            }
+        }
+        JobCmds::Cancel { id } => {
+            let job = store.cancel(&id).await?;
+            if job.cancel_requested {
+                println!("Requested cancellation of running job {}", job.id);
+            } else {
+                println!("Cancelled job {}", job.id);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Each of these commands returns a one-line summary, kept as the result of a queued job.
+async fn execute_eval(args: EvalArgs, console: &mut Console<'_>) -> anyhow::Result<String> {
+    let scenarios = load_scenarios(&args.dir)?;
+    let working_dir = std::env::current_dir()?;
+    let runner = EvalRunner::new(load_profiles(&working_dir), working_dir);
+    let report = if args.live {
+        with_provider_model!(args.provider, args.model.as_deref(), |model| {
+            runner.run_all(&scenarios, Some(&model)).await
+        })
+    } else {
+        runner.run_replay(&scenarios).await
+    };
+
+    writeln!(console.out, "{report}")?;
+    if report.failed() > 0 {
+        anyhow::bail!("{} scenario(s) failed", report.failed());
+    }
+    Ok(format!(
+        "{} passed, {} failed, {} skipped",
+        report.passed(),
+        report.failed(),
+        report.skipped()
+    ))
+}
+
+/// Runs the review and returns the hook's exit status with a summary. Anything that keeps
+/// the review from running is reported and yields 0, so the hook never blocks a commit by
+/// accident.
+async fn execute_precommit_review(
+    args: PrecommitReviewArgs,
+    console: &mut Console<'_>,
+) -> (i32, String) {
+    if precommit::review_skipped() {
+        let summary = format!("AI review skipped: {SKIP_REVIEW_ENV} is set");
+        let _ = writeln!(console.out, "{summary}");
+        return (0, summary);
+    }
+    let Some(provider) = args.provider else {
+        let summary = "AI review skipped: no provider configured".to_string();
+        let _ = writeln!(console.out, "{summary} (set {PRECOMMIT_PROVIDER_KEY})");
+        return (0, summary);
+    };
+    match review_staged_changes(provider, &args, console).await {
+        Ok(outcome) => outcome,
+        Err(e) => {
+            let _ = match refusal_message(&e) {
+                Some(message) => writeln!(
+                    console.err,
+                    "warning: the provider declined to review the staged changes, not blocking the commit: {message}"
+                ),
+                None => writeln!(
+                    console.err,
+                    "warning: AI review did not run, not blocking the commit: {e}"
+                ),
+            };
+            (0, format!("AI review did not run: {e}"))
+        }
+    }
+}
+
+async fn review_staged_changes(
+    provider: CodeProvider,
+    args: &PrecommitReviewArgs,
+    console: &mut Console<'_>,
+) -> anyhow::Result<(i32, String)> {
+    let diff = precommit::staged_diff().await.map_err(anyhow::Error::msg)?;
+    if diff.trim().is_empty() {
+        let summary = "AI review skipped: nothing staged".to_string();
+        writeln!(console.out, "{summary}")?;
+        return Ok((0, summary));
+    }
+    let profile = precommit::review_profile(load_profiles(&util::working_dir()));
+    let profile = with_language(profile, args.language.as_deref()).await;
+    let review = PrecommitReview {
+        threshold: args.severity,
+        advisory: args.advisory,
+        deadline: args.deadline,
+    };
+    let outcome = with_provider_model!(provider, args.model.as_deref(), |model| {
+        review.run(model, &profile, &diff).await?
+    });
+
+    for finding in &outcome.report.findings {
+        if finding.severity >= review.threshold {
+            writeln!(console.err, "{finding}")?;
+        } else {
+            writeln!(console.out, "{finding}")?;
+        }
+    }
+    if args.record {
+        let head = Head::current_commit().await;
+        precommit::save_pending_review(&util::storage_path(), head.as_ref(), &outcome)?;
+    }
+    let summary = format!(
+        "AI review: {} finding(s), {} at or above '{}'",
+        outcome.report.findings.len(),
+        outcome.blocking,
+        review.threshold
+    );
+    if outcome.exit_code != 0 {
+        writeln!(
+            console.err,
+            "AI review: {} finding(s) at or above '{}'; set {SKIP_REVIEW_ENV}=1 or use --no-verify to commit anyway",
+            outcome.blocking, review.threshold
+        )?;
+    } else if outcome.blocking > 0 {
+        writeln!(
+            console.out,
+            "AI review: {} finding(s) at or above '{}' (advisory, not blocking)",
+            outcome.blocking, review.threshold
+        )?;
+    }
+    Ok((outcome.exit_code, summary))
+}
+
+async fn execute_work(args: WorkArgs, console: &mut Console<'_>) -> anyhow::Result<String> {
+    let working_dir = util::working_dir();
+    let profile = work::work_profile(load_profiles(&working_dir), &args.profile)
+        .ok_or_else(|| anyhow::anyhow!("unknown profile '{}'", args.profile))?;
+    let profile = with_language(profile, args.language.as_deref()).await;
+    if !args.dry_run
+        && (!changes_to_be_committed().await.is_empty() || !changes_to_be_staged().is_empty())
+    {
+        writeln!(
+            console.err,
+            "warning: the working tree has uncommitted changes; the agent's edits will be mixed with them"
+        )?;
+    }
+
+    let libra_dir = util::storage_path();
+    let storage = Arc::new(LocalStorage::new(libra_dir.join("objects")));
+    let history = Arc::new(HistoryManager::new(storage, libra_dir));
+    let task_work = TaskWork {
+        dry_run: args.dry_run,
+        max_steps: Some(args.max_steps),
+        deadline: args.deadline,
+    };
+    let mut observer = event_observer(args.events.as_deref(), args.events_redact)?;
+    let outcome = with_provider_model!(args.provider, args.model.as_deref(), |model| {
+        task_work
+            .run_with_observer(
+                &model,
+                &profile,
+                history,
+                working_dir,
+                &args.task,
+                observer.as_mut(),
+            )
+            .await?
+    });
+
+    if !outcome.answer.is_empty() {
+        writeln!(console.out, "{}\n", outcome.answer.trim_end())?;
+    }
+    let summary = match outcome.status {
+        Some(status) => format!(
+            "Task {} {} ({status}): {}",
+            outcome.task_id, outcome.completion.status, outcome.completion.summary
+        ),
+        None => format!(
+            "Dry run: task {} would be {}: {}",
+            outcome.task_id, outcome.completion.status, outcome.completion.summary
+        ),
+    };
+    writeln!(console.out, "{summary}")?;
+    Ok(summary)
+}
+
+async fn execute_explain(args: ExplainArgs, console: &mut Console<'_>) -> anyhow::Result<String> {
+    let target = ExplainTarget::resolve(&args.target)
+        .await
+        .map_err(anyhow::Error::msg)?;
+    let profile = explain::explain_profile(load_profiles(&util::working_dir()));
+    let profile = with_language(profile, args.language.as_deref()).await;
+    let explanation = with_provider_model!(args.provider, args.model.as_deref(), |model| {
+        explain::explain(model, &profile, target, args.deadline).await?
+    });
+    write!(console.out, "{}", explanation.render(args.format))?;
+    let summary = explanation
+        .report
+        .summary
+        .lines()
+        .next()
+        .unwrap_or_default();
+    Ok(format!("{}: {summary}", explanation.target))
+}
+
+async fn execute_fix_build(
+    args: FixBuildArgs,
+    console: &mut Console<'_>,
+) -> anyhow::Result<String> {
+    let working_dir = util::working_dir();
+    let profile = fix_build::fix_build_profile(load_profiles(&working_dir));
+    let profile = with_language(profile, args.language.as_deref()).await;
+    let fix = FixBuild {
+        limit: args.limit,
+        apply: args.apply,
+        deadline: args.deadline,
+    };
+    let mut observer = event_observer(args.events.as_deref(), args.events_redact)?;
+    let outcome = with_provider_model!(args.provider, args.model.as_deref(), |model| {
+        fix.run_with_observer(
+            &model,
+            &profile,
+            working_dir,
+            CargoCheckHandler::default(),
+            observer.as_mut(),
+        )
+        .await?
+    });
+
+    if outcome.errors.is_empty() {
+        let summary = "cargo check reports no errors".to_string();
+        writeln!(console.out, "{summary}")?;
+        return Ok(summary);
+    }
+    writeln!(
+        console.out,
+        "Sent {} of {} errors to {}",
+        outcome.errors.len(),
+        outcome.total_errors,
+        profile.name
+    )?;
+    let summary = match (&outcome.patch_report, outcome.applied) {
+        (Some(report), true) => {
+            writeln!(console.out, "{}\nPatch applied", report.trim_end())?;
+            "Patch applied"
+        }
+        (Some(report), false) => {
+            writeln!(
+                console.out,
+                "{}\nRun with --apply to apply it",
+                report.trim_end()
+            )?;
+            "Patch proposed, not applied"
+        }
+        (None, _) => {
+            writeln!(
+                console.out,
+                "No patch in the answer:\n{}",
+                outcome.answer.trim_end()
+            )?;
+            "No patch in the answer"
+        }
+    };
+    Ok(summary.to_string())
+}
+
+/// `profile` answering in the language of `flag`, else its own, else `ai.language`.
+async fn with_language(profile: AgentProfile, flag: Option<&str>) -> AgentProfile {
+    let config_default = config_defaults::lookup(LANGUAGE_KEY).await;
+    profile.with_language(flag, config_default.as_deref())
+}
+
+/// The observer behind `--events`: a JSON-lines stream to `target`, stderr for `-`, or none.
+fn event_observer(
+    target: Option<&str>,
+    redact_tool_io: bool,
+) -> anyhow::Result<Box<dyn ToolLoopObserver>> {
+    let writer: Box<dyn Write + Send> = match target {
+        None => return Ok(Box::new(NoopObserver)),
+        Some("-") => Box::new(std::io::stderr()),
+        Some(path) => Box::new(
+            std::fs::File::create(path)
+                .map_err(|e| anyhow::anyhow!("cannot write events to '{path}': {e}"))?,
+        ),
+    };
+    Ok(Box::new(
+        EventStreamObserver::new(writer).redact_tool_io(redact_tool_io),
+    ))
+}
+
+fn print_job(job: &Job) {
+    println!("job {}", job.id);
+    println!("Status:    {}", job.status);
+    println!("Operation: {}", job.args.join(" "));
+    println!("Created:   {}", job.created_at.to_rfc3339());
+    println!("Updated:   {}", job.updated_at.to_rfc3339());
+    if let Some(result) = &job.result {
+        println!("Result:    {result}");
+    }
+    if let Some(error) = &job.error {
+        println!("Error:     {error}");
+    }
+}
+
+/// Runs job operations against the provider named in each job.
+struct CliJobExecutor;
+
+#[async_trait]
+impl JobExecutor for CliJobExecutor {
+    async fn execute(
+        &self,
+        args: &[String],
+        ctx: &mut JobContext<'_>,
+    ) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+        let operation = JobOperation::parse(args).await?;
+        if let JobOperation::Prompt(args) = operation {
+            return with_provider_model!(args.provider, args.model.as_deref(), |model| {
+                run_prompt(model, &args, ctx).await
+            });
+        }
+
+        ctx.checkpoint()?;
+        let token = ctx.cancellation_token().clone();
+        let mut err = ctx.log().try_clone()?;
+        let console = &mut Console {
+            out: ctx.log(),
+            err: &mut err,
+        };
+        let result = tokio::select! {
+            result = run_subcommand(operation, console) => result,
+            _ = token.cancelled() => return Err(JobError::Cancelled.into()),
+        };
+        match result {
+            Ok(summary) => {
+                ctx.checkpoint()?;
+                Ok(Some(summary))
+            }
+            Err(e) => {
+                if let Some(partial) = partial_result(&e).filter(|partial| !partial.is_empty()) {
+                    writeln!(ctx.log(), "{}", partial.trim_end())?;
+                }
+                Err(e.into())
+            }
+        }
+    }
+}
+
+/// Run a queued `ai` subcommand the way the command line would.
+async fn run_subcommand(
+    operation: JobOperation,
+    console: &mut Console<'_>,
+) -> anyhow::Result<String> {
+    match operation {
+        JobOperation::Prompt(_) => unreachable!("prompt jobs run through run_prompt"),
+        JobOperation::Work(args) => execute_work(args, console).await,
+        JobOperation::Explain(args) => execute_explain(args, console).await,
+        JobOperation::FixBuild(args) => execute_fix_build(args, console).await,
+        JobOperation::Eval(args) => execute_eval(args, console).await,
+        JobOperation::PreCommitReview(args) => {
+            match execute_precommit_review(args, console).await {
+                (0, summary) => Ok(summary),
+                (_, summary) => Err(anyhow::anyhow!("{summary}")),
+            }
+        }
+    }
+}
+
+async fn run_prompt<M: CompletionModel>(
+    model: M,
+    args: &PromptJobArgs,
+    ctx: &mut JobContext<'_>,
+) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut builder = AgentBuilder::new(model);
+    if let Some(preamble) = &args.preamble {
+        builder = builder.preamble(preamble);
+    }
+    if !args.no_repo_context {
+        builder = builder.context_middleware(RepoContextMiddleware::default());
+    }
+    let language = match &args.language {
+        Some(language) => normalize_language(language),
+        None => config_defaults::lookup(LANGUAGE_KEY)
+            .await
+            .as_deref()
+            .and_then(normalize_language),
+    };
+    if let Some(instruction) = style_instruction(language.as_deref(), None) {
+        builder = builder.response_style(instruction);
+    }
+    if let Some(deadline) = args.deadline {
+        builder = builder.deadline(deadline);
+    }
+    let agent = builder.build();
+    let text = args.text.join(" ");
+
+    writeln!(ctx.log(), "> {text}")?;
+    ctx.checkpoint()?;
+    let token = ctx.cancellation_token().clone();
+    let answer = tokio::select! {
+        answer = agent.prompt(text) => answer?,
+        _ = token.cancelled() => return Err(JobError::Cancelled.into()),
+    };
+    ctx.checkpoint()?;
+    writeln!(ctx.log(), "{answer}")?;
+    Ok(Some(answer.lines().next().unwrap_or_default().to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_deadline() {
+        assert_eq!(parse_deadline("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_deadline("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_deadline("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_deadline("5m"), Ok(Duration::from_secs(300)));
+        assert_eq!(parse_deadline("1h"), Ok(Duration::from_secs(3600)));
+        assert!(parse_deadline("0s").is_err());
+        assert!(parse_deadline("5d").is_err());
+        assert!(parse_deadline("soon").is_err());
+    }
+}
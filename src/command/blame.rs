@@ -1,4 +1,14 @@
 //! Runs blame for a file at a given commit by walking trees/diffs, attributing lines to commits, and streaming formatted output.
+//!
+//! The lines two versions of the file have in common are kept in `.libra/info/blame-cache`,
+//! keyed by the blob ids of both versions, so blaming the file again (another `-L` range, or
+//! after a commit that touched other files) diffs only version pairs it has not seen. Blob
+//! ids name the content, so a cached mapping can never describe different lines; the
+//! least recently used mappings are dropped past [`CACHE_ENTRIES`]. `--no-cache` diffs
+//! everything and leaves the cache alone.
+//!
+//! With `--follow`, a file missing from a parent is looked up under the name it was renamed
+//! from (see [`crate::utils::rename`]), so lines keep their attribution across moves.
 
 #[cfg(unix)]
 use std::{
@@ -6,17 +16,21 @@ use std::{
     process::{Command, Stdio},
 };
 
+use std::path::{Path, PathBuf};
+
 use chrono::DateTime;
 use clap::Parser;
 use git_internal::{
+    diff::DiffOperation,
     diff::compute_diff,
     hash::ObjectHash,
     internal::object::{blob::Blob, commit::Commit, tree::Tree},
 };
+use serde::{Deserialize, Serialize};
 
 use crate::{
     command::{get_target_commit, load_object},
-    utils::{object_ext::TreeExt, util},
+    utils::{object_ext::TreeExt, rename, util},
 };
 
 #[derive(Parser, Debug)]
@@ -32,14 +46,42 @@ pub struct BlameArgs {
     /// The line range to blame
     #[clap(short = 'L', value_name = "RANGE")]
     pub line_range: Option<String>,
+
+    /// Diff every file version instead of reusing line mappings from the blame cache
+    #[clap(long = "no-cache")]
+    pub no_cache: bool,
+
+    /// Keep attributing lines to older commits across renames of the file
+    #[clap(long)]
+    pub follow: bool,
+}
+
+/// File below `.libra/info` holding the line mappings of recently diffed version pairs.
+const CACHE_FILE: &str = "blame-cache";
+
+/// How many version pairs [`CACHE_FILE`] keeps mappings for.
+const CACHE_ENTRIES: usize = 1024;
+
+/// Attribution of one line of the blamed file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineBlame {
+    pub line_number: usize,
+    pub commit_id: ObjectHash,
+    /// Path of the file in `commit_id`, which differs from the blamed path across renames.
+    pub path: PathBuf,
+    pub author: String,
+    pub date: String,
+    pub content: String,
 }
 
-struct LineBlame {
-    line_number: usize,
-    commit_id: ObjectHash,
-    author: String,
-    date: String,
-    content: String,
+/// The result of [`blame_file`].
+#[derive(Debug)]
+pub struct BlameResult {
+    pub lines: Vec<LineBlame>,
+    /// Number of file version pairs that had to be diffed, i.e. were not in the blame cache.
+    pub diffs: usize,
+    /// Renames the walk followed, as `(old, new)` paths from newest to oldest.
+    pub renames: Vec<(PathBuf, PathBuf)>,
 }
 
 pub async fn execute(args: BlameArgs) {
@@ -48,104 +90,19 @@ pub async fn execute(args: BlameArgs) {
         return;
     }
 
-    let commit_id = match get_target_commit(&args.commit).await {
-        Ok(id) => id,
-        Err(e) => {
-            eprintln!("Error: {}", e);
-            return;
-        }
-    };
-
-    let commit_obj = match load_object::<Commit>(&commit_id) {
-        Ok(obj) => obj,
-        Err(e) => {
-            eprintln!("Failed to load commit: {}", e);
-            return;
-        }
-    };
-
-    // get the final file content (the version we're blaming)
-    let target_lines = match get_file_lines(&commit_obj, &args.file) {
-        Ok(lines) => lines,
+    let (blame_lines, renamed) = match blame_file(&args).await {
+        Ok(result) => (result.lines, !result.renames.is_empty()),
         Err(e) => {
             eprintln!("{}", e);
             return;
         }
     };
 
-    if target_lines.is_empty() {
+    if blame_lines.is_empty() {
         println!("File is empty");
         return;
     }
 
-    // Initialize blame: assume all lines come from the target commit initially
-    let mut blame_lines: Vec<LineBlame> = target_lines
-        .iter()
-        .enumerate()
-        .map(|(idx, content)| LineBlame {
-            line_number: idx + 1,
-            commit_id,
-            author: commit_obj.author.name.clone(),
-            date: commit_obj.author.timestamp.to_string(),
-            content: content.clone(),
-        })
-        .collect();
-
-    // walk backwards through commit history, handling all parents (for merges)
-    use std::collections::VecDeque;
-    let mut queue: VecDeque<(ObjectHash, Commit, Vec<String>)> = VecDeque::new();
-    queue.push_back((commit_id, commit_obj, target_lines));
-
-    while let Some((current_id, current_commit, current_lines)) = queue.pop_front() {
-        // if no lines are blamed to the current commit, stop traversing this branch
-        if !blame_lines.iter().any(|b| b.commit_id == current_id) {
-            continue;
-        }
-
-        for parent_id in &current_commit.parent_commit_ids {
-            let parent_commit = match load_object::<Commit>(parent_id) {
-                Ok(obj) => obj,
-                Err(_) => continue,
-            };
-
-            let parent_lines = match get_file_lines(&parent_commit, &args.file) {
-                Ok(lines) => {
-                    if lines.is_empty() {
-                        continue;
-                    }
-                    lines
-                }
-                Err(_) => continue, // file was created in current commit
-            };
-
-            // compute diff operations between parent and current
-            let operations = compute_diff(&parent_lines, &current_lines);
-
-            for op in operations {
-                use git_internal::diff::DiffOperation;
-                match op {
-                    DiffOperation::Insert { .. } => {}
-                    DiffOperation::Equal { old_line, new_line } => {
-                        let final_idx = new_line - 1;
-                        if let Some(blame) = blame_lines.get_mut(final_idx) {
-                            // move blame if it currently belongs to the current commit
-                            if blame.commit_id == current_id {
-                                let parent_content = parent_lines.get(old_line - 1);
-                                if Some(&blame.content) == parent_content {
-                                    blame.commit_id = *parent_id;
-                                    blame.author = parent_commit.author.name.clone();
-                                    blame.date = parent_commit.author.timestamp.to_string();
-                                }
-                            }
-                        }
-                    }
-                    DiffOperation::Delete { .. } => {}
-                }
-            }
-            queue.push_back((*parent_id, parent_commit, parent_lines));
-        }
-    }
-
     // line range if specified
     let filtered_lines = if let Some(ref range) = args.line_range {
         match parse_line_range(range, blame_lines.len()) {
@@ -189,9 +146,15 @@ pub async fn execute(args: BlameArgs) {
             blame.date.clone()
         };
 
+        // Like git, name the file each line comes from once the walk crossed a rename.
+        let path = if renamed {
+            format!(" {}", blame.path.display())
+        } else {
+            String::new()
+        };
         output.push_str(&format!(
-            "{} ({:19} {} {}) {}\n",
-            short_hash, author_short, date_formatted, blame.line_number, blame.content
+            "{}{} ({:19} {} {}) {}\n",
+            short_hash, path, author_short, date_formatted, blame.line_number, blame.content
         ));
     }
 
@@ -226,23 +189,292 @@ pub async fn execute(args: BlameArgs) {
         print!("{}", output);
     }
 }
-fn get_file_lines(commit: &Commit, file_path: &str) -> Result<Vec<String>, String> {
+
+/// Attribute every line of `args.file` at `args.commit` to the commit that last changed it.
+pub async fn blame_file(args: &BlameArgs) -> Result<BlameResult, String> {
+    let commit_id = get_target_commit(&args.commit)
+        .await
+        .map_err(|e| format!("Error: {}", e))?;
+    let commit_obj =
+        load_object::<Commit>(&commit_id).map_err(|e| format!("Failed to load commit: {}", e))?;
+
+    // get the final file content (the version we're blaming)
+    let target_path = util::to_workdir_path(&args.file);
+    let (target_blob, target_lines) = get_file_lines(&commit_obj, &target_path)?;
+
+    // Initialize blame: assume all lines come from the target commit initially
+    let mut blame_lines: Vec<LineBlame> = target_lines
+        .iter()
+        .enumerate()
+        .map(|(idx, content)| LineBlame {
+            line_number: idx + 1,
+            commit_id,
+            path: target_path.clone(),
+            author: commit_obj.author.name.clone(),
+            date: commit_obj.author.timestamp.to_string(),
+            content: content.clone(),
+        })
+        .collect();
+
+    let mut cache = (!args.no_cache).then(BlameCache::load);
+    let mut diffs = 0;
+    let mut renames = Vec::new();
+
+    // walk backwards through commit history, handling all parents (for merges)
+    use std::collections::VecDeque;
+    let mut queue: VecDeque<(ObjectHash, Commit, PathBuf, ObjectHash, Vec<String>)> =
+        VecDeque::new();
+    queue.push_back((
+        commit_id,
+        commit_obj,
+        target_path,
+        target_blob,
+        target_lines,
+    ));
+
+    while let Some((current_id, current_commit, current_path, current_blob, current_lines)) =
+        queue.pop_front()
+    {
+        // if no lines are blamed to the current commit, stop traversing this branch
+        if !blame_lines.iter().any(|b| b.commit_id == current_id) {
+            continue;
+        }
+
+        for parent_id in &current_commit.parent_commit_ids {
+            let parent_commit = match load_object::<Commit>(parent_id) {
+                Ok(obj) => obj,
+                Err(_) => continue,
+            };
+
+            let mut parent_path = current_path.clone();
+            let mut parent_file = get_file_lines(&parent_commit, &parent_path);
+            if parent_file.is_err()
+                && args.follow
+                && let Some(old_path) = find_rename(&parent_commit, &current_commit, &current_path)
+            {
+                parent_file = get_file_lines(&parent_commit, &old_path);
+                renames.push((old_path.clone(), current_path.clone()));
+                parent_path = old_path;
+            }
+            let (parent_blob, parent_lines) = match parent_file {
+                Ok((blob, lines)) => {
+                    if lines.is_empty() {
+                        continue;
+                    }
+                    (blob, lines)
+                }
+                Err(_) => continue, // file was created in current commit
+            };
+
+            let cached = cache.as_mut().and_then(|cache| {
+                cache.get(
+                    &parent_blob,
+                    &current_blob,
+                    parent_lines.len(),
+                    current_lines.len(),
+                )
+            });
+            let mapping = match cached {
+                Some(mapping) => mapping,
+                None => {
+                    diffs += 1;
+                    let mapping = LineMapping::compute(&parent_lines, &current_lines);
+                    if let Some(cache) = cache.as_mut() {
+                        cache.insert(&parent_blob, &current_blob, mapping.clone());
+                    }
+                    mapping
+                }
+            };
+
+            for (old_line, new_line) in mapping.pairs() {
+                let final_idx = new_line - 1;
+                if let Some(blame) = blame_lines.get_mut(final_idx) {
+                    // move blame if it currently belongs to the current commit
+                    if blame.commit_id == current_id {
+                        let parent_content = parent_lines.get(old_line - 1);
+                        if Some(&blame.content) == parent_content {
+                            blame.commit_id = *parent_id;
+                            blame.path = parent_path.clone();
+                            blame.author = parent_commit.author.name.clone();
+                            blame.date = parent_commit.author.timestamp.to_string();
+                        }
+                    }
+                }
+            }
+            queue.push_back((
+                *parent_id,
+                parent_commit,
+                parent_path,
+                parent_blob,
+                parent_lines,
+            ));
+        }
+    }
+
+    if let Some(cache) = cache.as_mut() {
+        cache.save();
+    }
+    Ok(BlameResult {
+        lines: blame_lines,
+        diffs,
+        renames,
+    })
+}
+
+/// The path `path` of `commit` had in `parent`, if the file was renamed between them.
+fn find_rename(parent: &Commit, commit: &Commit, path: &Path) -> Option<PathBuf> {
+    let tree = load_object::<Tree>(&commit.tree_id).ok()?;
+    let parent_tree = load_object::<Tree>(&parent.tree_id).ok()?;
+    rename::find_rename_source(&parent_tree, &tree, path)
+}
+
+/// The blob id and lines of the file at `target_path` (relative to the workdir) in `commit`.
+fn get_file_lines(
+    commit: &Commit,
+    target_path: &Path,
+) -> Result<(ObjectHash, Vec<String>), String> {
     let tree =
         load_object::<Tree>(&commit.tree_id).map_err(|e| format!("Failed to load tree: {}", e))?;
 
     let plain_items = tree.get_plain_items();
-    let target_path = util::to_workdir_path(file_path);
 
     let blob_hash = plain_items
         .iter()
-        .find(|(path, _)| path == &target_path)
+        .find(|(path, _)| path == target_path)
         .map(|(_, hash)| hash)
-        .ok_or_else(|| format!("File '{}' not found in commit", file_path))?;
+        .ok_or_else(|| format!("File '{}' not found in commit", target_path.display()))?;
 
     let blob = load_object::<Blob>(blob_hash).map_err(|e| format!("Failed to load blob: {}", e))?;
 
     let content = String::from_utf8_lossy(&blob.data);
-    Ok(content.lines().map(|s| s.to_string()).collect())
+    Ok((*blob_hash, content.lines().map(|s| s.to_string()).collect()))
+}
+
+/// The lines two versions of a file have in common, as runs of
+/// `(old_start, new_start, len)` with 1-based line numbers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct LineMapping {
+    runs: Vec<(usize, usize, usize)>,
+}
+
+impl LineMapping {
+    fn compute(old_lines: &[String], new_lines: &[String]) -> Self {
+        let mut runs: Vec<(usize, usize, usize)> = Vec::new();
+        for op in compute_diff(old_lines, new_lines) {
+            if let DiffOperation::Equal { old_line, new_line } = op {
+                match runs.last_mut() {
+                    Some((old, new, len)) if *old + *len == old_line && *new + *len == new_line => {
+                        *len += 1;
+                    }
+                    _ => runs.push((old_line, new_line, 1)),
+                }
+            }
+        }
+        Self { runs }
+    }
+
+    /// `(old_line, new_line)` for every common line, in order.
+    fn pairs(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.runs
+            .iter()
+            .flat_map(|&(old, new, len)| (0..len).map(move |i| (old + i, new + i)))
+    }
+
+    /// Whether every run lies within files of the given lengths.
+    fn fits(&self, old_len: usize, new_len: usize) -> bool {
+        self.runs.iter().all(|&(old, new, len)| {
+            old >= 1 && new >= 1 && old + len - 1 <= old_len && new + len - 1 <= new_len
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    old_blob: String,
+    new_blob: String,
+    mapping: LineMapping,
+}
+
+/// The contents of [`CACHE_FILE`], most recently used entry first.
+#[derive(Serialize, Deserialize, Default)]
+struct BlameCache {
+    entries: Vec<CacheEntry>,
+    #[serde(skip)]
+    changed: bool,
+}
+
+impl BlameCache {
+    fn path() -> PathBuf {
+        util::storage_path().join("info").join(CACHE_FILE)
+    }
+
+    /// The cache of the current repository. A missing or unreadable file is an empty cache.
+    fn load() -> Self {
+        let path = Self::path();
+        let Ok(data) = std::fs::read(&path) else {
+            return Self::default();
+        };
+        serde_json::from_slice(&data).unwrap_or_else(|e| {
+            tracing::debug!("ignoring blame cache '{}': {e}", path.display());
+            Self::default()
+        })
+    }
+
+    /// The mapping from `old_blob` to `new_blob`, if cached and consistent with the line
+    /// counts of both versions. A hit becomes the most recently used entry.
+    fn get(
+        &mut self,
+        old_blob: &ObjectHash,
+        new_blob: &ObjectHash,
+        old_len: usize,
+        new_len: usize,
+    ) -> Option<LineMapping> {
+        let (old_blob, new_blob) = (old_blob.to_string(), new_blob.to_string());
+        let index = self
+            .entries
+            .iter()
+            .position(|entry| entry.old_blob == old_blob && entry.new_blob == new_blob)?;
+        let entry = self.entries.remove(index);
+        if !entry.mapping.fits(old_len, new_len) {
+            self.changed = true;
+            return None;
+        }
+        let mapping = entry.mapping.clone();
+        self.changed |= index != 0;
+        self.entries.insert(0, entry);
+        Some(mapping)
+    }
+
+    fn insert(&mut self, old_blob: &ObjectHash, new_blob: &ObjectHash, mapping: LineMapping) {
+        self.entries.insert(
+            0,
+            CacheEntry {
+                old_blob: old_blob.to_string(),
+                new_blob: new_blob.to_string(),
+                mapping,
+            },
+        );
+        self.changed = true;
+    }
+
+    /// Write the cache back if it changed, keeping the [`CACHE_ENTRIES`] most recently used
+    /// entries. Failures only cost the next run its head start, so they are logged and
+    /// otherwise ignored.
+    fn save(&mut self) {
+        if !self.changed {
+            return;
+        }
+        self.entries.truncate(CACHE_ENTRIES);
+        let path = Self::path();
+        let result = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| serde_json::to_vec(self).map_err(std::io::Error::from))
+            .and_then(|data| std::fs::write(&path, data));
+        if let Err(e) = result {
+            tracing::debug!("failed to write blame cache '{}': {e}", path.display());
+        }
+    }
 }
 
 /// Parse line range from string like "10", "10,20", "10,+5"
@@ -8,7 +8,7 @@ use git_internal::{hash::ObjectHash, internal::object::commit::Commit};
 
 use crate::{
     command::{get_target_commit, load_object},
-    internal::{branch::Branch, config::Config, head::Head},
+    internal::{branch::Branch, config::Config, head::Head, protect},
 };
 
 pub enum BranchListMode {
@@ -80,14 +80,18 @@ pub struct BranchArgs {
     /// Only list branches which don’t contain the specified commit (HEAD if not specified). Implies --list.
     #[clap(long, group = "query", alias = "without", value_name = "commit", num_args = 0..=1, default_missing_value = "HEAD", action = clap::ArgAction::Append)]
     pub no_contains: Vec<String>,
+
+    /// Delete the branch even if it is protected (see `branch.<name>.protected`)
+    #[clap(long)]
+    pub override_protection: bool,
 }
 pub async fn execute(args: BranchArgs) {
     if let Some(new_branch) = args.new_branch {
         create_branch(new_branch, args.commit_hash).await;
     } else if let Some(branch_to_delete) = args.delete {
-        delete_branch(branch_to_delete).await;
+        delete_branch(branch_to_delete, args.override_protection).await;
     } else if let Some(branch_to_delete) = args.delete_safe {
-        delete_branch_safe(branch_to_delete).await;
+        delete_branch_safe(branch_to_delete, args.override_protection).await;
     } else if args.show_current {
         show_current_branch().await;
     } else if args.set_upstream_to.is_some() {
@@ -174,7 +178,7 @@ pub async fn create_branch(new_branch: String, branch_or_commit: Option<String>)
     Branch::update_branch(&new_branch, &commit_id.to_string(), None).await;
 }
 
-async fn delete_branch(branch_name: String) {
+async fn delete_branch(branch_name: String, override_protection: bool) {
     let _ = Branch::find_branch(&branch_name, None)
         .await
         .unwrap_or_else(|| panic!("fatal: branch '{branch_name}' not found"));
@@ -185,6 +189,10 @@ async fn delete_branch(branch_name: String) {
     {
         panic!("fatal: Cannot delete the branch '{branch_name}' which you are currently on");
     }
+    if let Err(e) = protect::check(&branch_name, "delete", override_protection).await {
+        eprintln!("fatal: {e}");
+        return;
+    }
 
     Branch::delete_branch(&branch_name, None).await;
 }
@@ -194,7 +202,7 @@ async fn delete_branch(branch_name: String) {
 /// This performs a merge check to ensure the branch is fully merged into HEAD
 /// before deletion. If the branch is not fully merged, prints an error and
 /// suggests using `branch -D` for force deletion.
-async fn delete_branch_safe(branch_name: String) {
+async fn delete_branch_safe(branch_name: String, override_protection: bool) {
     // 1. Check if branch exists
     let branch = Branch::find_branch(&branch_name, None)
         .await
@@ -207,6 +215,10 @@ async fn delete_branch_safe(branch_name: String) {
     {
         panic!("fatal: Cannot delete the branch '{branch_name}' which you are currently on");
     }
+    if let Err(e) = protect::check(&branch_name, "delete", override_protection).await {
+        eprintln!("fatal: {e}");
+        return;
+    }
 
     // 3. Check if the branch is fully merged into HEAD
     // Get current HEAD commit
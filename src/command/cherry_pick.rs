@@ -28,6 +28,7 @@ use crate::{
         reflog::{ReflogAction, ReflogContext, with_reflog},
     },
     utils::{
+        index_lock::IndexLock,
         object_ext::{BlobExt, TreeExt},
         path, util, worktree,
     },
@@ -149,6 +150,7 @@ async fn cherry_pick_single_commit(
         .map_err(|e| format!("failed to load commit tree: {e}"))?;
 
     let index_file = path::index();
+    let lock = IndexLock::acquire(&index_file).map_err(|e| e.to_string())?;
     let current_index =
         Index::load(&index_file).map_err(|e| format!("failed to load current index: {e}"))?;
     let mut index =
@@ -178,8 +180,7 @@ async fn cherry_pick_single_commit(
     }
 
     // 3. Save updated index and sync working directory
-    index
-        .save(&index_file)
+    lock.commit(&mut index)
         .map_err(|e| format!("failed to save index: {e}"))?;
     reset_workdir_tracked_only(&current_index, &index)?;
 
@@ -0,0 +1,411 @@
+//! Churn command: ranks files by how often they changed, to point refactoring at hot spots.
+//!
+//! The history reachable from `HEAD` is walked newest first and every non-merge commit that
+//! passes the [`CommitFilter`] (`--since`, `--until`, `--author` and pathspecs) is diffed
+//! against its first parent. Merges are skipped, as `git whatchanged` does, so changes
+//! brought in by a merge are only counted in the commits that made them. Each changed path
+//! collects the commits that touched it, the inserted and deleted lines, and the distinct
+//! author emails.
+//!
+//! With `--find-renames` (`-M`), a file added in a commit that deletes a similar one (see
+//! [`crate::utils::rename`]) is diffed against the deleted file, and the churn of the old
+//! name is merged into the newest name. A file later created again under an old name starts
+//! its own count.
+//!
+//! `--by-dir[=<depth>]` rolls the counts up to directories, keeping the first `depth`
+//! components (1 when omitted); files at the top level go to `.`. A commit or author that
+//! touched several files of a directory is counted once for it. The report is sorted by
+//! commits, then by changed lines, then by path; `--top` keeps the first entries and
+//! `--json` prints them as an array of objects instead of a table.
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    io::Write,
+    path::{Component, Path, PathBuf},
+};
+
+use clap::Parser;
+use git_internal::{
+    Diff,
+    hash::ObjectHash,
+    internal::object::{blob::Blob, commit::Commit, tree::Tree},
+};
+use serde::Serialize;
+
+use crate::{
+    command::load_object,
+    internal::{
+        head::Head,
+        log::{date_parser::parse_date, filter::CommitFilter},
+    },
+    utils::{object_ext::TreeExt, rename, util},
+};
+
+#[derive(Parser, Debug)]
+pub struct ChurnArgs {
+    /// Show only the <N> most changed entries
+    #[clap(long, value_name = "N")]
+    pub top: Option<usize>,
+
+    /// Print the report as a JSON array
+    #[clap(long)]
+    pub json: bool,
+
+    /// Roll counts up to directories, keeping the first <depth> path components (default 1)
+    #[clap(
+        long = "by-dir",
+        value_name = "depth",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "1"
+    )]
+    pub by_dir: Option<usize>,
+
+    /// Detect renames and merge the churn of a file's old names into its newest name
+    #[clap(short = 'M', long = "find-renames")]
+    pub find_renames: bool,
+
+    /// Only count commits more recent than a specific date
+    #[clap(long)]
+    pub since: Option<String>,
+
+    /// Only count commits older than a specific date
+    #[clap(long)]
+    pub until: Option<String>,
+
+    /// Only count commits whose author matches this case-insensitive regular expression
+    #[clap(long, value_name = "PATTERN")]
+    pub author: Option<String>,
+
+    /// Only count changes to these paths
+    #[clap(value_name = "PATHSPEC")]
+    pub pathspec: Vec<String>,
+}
+
+/// The churn of one file, or of one directory with `--by-dir`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChurnEntry {
+    pub path: String,
+    /// Commits that changed the path.
+    pub commits: usize,
+    pub insertions: usize,
+    pub deletions: usize,
+    /// Distinct author emails among those commits.
+    pub authors: usize,
+}
+
+/// What one path accumulated during the walk.
+#[derive(Default)]
+struct PathChurn {
+    commits: HashSet<ObjectHash>,
+    authors: HashSet<String>,
+    insertions: usize,
+    deletions: usize,
+}
+
+impl PathChurn {
+    fn merge(&mut self, other: &PathChurn) {
+        self.commits.extend(other.commits.iter().copied());
+        self.authors.extend(other.authors.iter().cloned());
+        self.insertions += other.insertions;
+        self.deletions += other.deletions;
+    }
+
+    fn entry(&self, path: String) -> ChurnEntry {
+        ChurnEntry {
+            path,
+            commits: self.commits.len(),
+            insertions: self.insertions,
+            deletions: self.deletions,
+            authors: self.authors.len(),
+        }
+    }
+}
+
+/// `(old, new)` paths of files renamed by a commit.
+type Renames = Vec<(PathBuf, PathBuf)>;
+
+/// One path changed by a commit.
+struct PathStat {
+    path: PathBuf,
+    insertions: usize,
+    deletions: usize,
+}
+
+pub async fn execute(args: ChurnArgs) {
+    if !util::check_repo_exist() {
+        return;
+    }
+    let _ = execute_to(args, &mut std::io::stdout()).await;
+}
+
+/// Write the report for `args` to `writer`; errors in the arguments or the history are
+/// reported on stderr.
+pub async fn execute_to(args: ChurnArgs, writer: &mut impl Write) -> std::io::Result<()> {
+    let entries = match churn_report(&args).await {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("fatal: {e}");
+            return Ok(());
+        }
+    };
+    if args.json {
+        let json = serde_json::to_string_pretty(&entries).map_err(std::io::Error::other)?;
+        writeln!(writer, "{json}")
+    } else {
+        write_table(writer, &entries)
+    }
+}
+
+/// The sorted, `--top`-limited churn entries for `args`.
+pub async fn churn_report(args: &ChurnArgs) -> Result<Vec<ChurnEntry>, String> {
+    let parse = |date: &str| parse_date(date).map_err(|e| e.to_string());
+    let since = args.since.as_deref().map(parse).transpose()?;
+    let until = args.until.as_deref().map(parse).transpose()?;
+    let filter = CommitFilter::new()
+        .since(since)
+        .until(until)
+        .author(args.author.as_deref())?
+        .paths(args.pathspec.iter().map(util::to_workdir_path).collect());
+
+    let Some(head) = Head::current_commit().await else {
+        return Ok(Vec::new());
+    };
+    let mut commits = reachable_commits(head)?;
+    commits.sort_by_key(|commit| std::cmp::Reverse(commit.committer.timestamp));
+
+    // Newest first, so a rename is seen before the older commits of the old name.
+    let mut churn: HashMap<PathBuf, PathChurn> = HashMap::new();
+    let mut renamed_to: HashMap<PathBuf, PathBuf> = HashMap::new();
+    for commit in &commits {
+        if commit.parent_commit_ids.len() > 1 || !filter.matches_cheap(commit) {
+            continue;
+        }
+        let (stats, renames) = commit_stats(commit, filter.path_list(), args.find_renames)?;
+        let author = commit.author.email.to_lowercase();
+        for stat in stats {
+            let path = renamed_to.get(&stat.path).unwrap_or(&stat.path).clone();
+            let path_churn = churn.entry(path).or_default();
+            path_churn.commits.insert(commit.id);
+            path_churn.authors.insert(author.clone());
+            path_churn.insertions += stat.insertions;
+            path_churn.deletions += stat.deletions;
+        }
+        for (old, new) in renames {
+            let newest = renamed_to.get(&new).unwrap_or(&new).clone();
+            renamed_to.insert(old, newest);
+        }
+    }
+
+    let mut entries: Vec<ChurnEntry> = match args.by_dir {
+        Some(depth) => {
+            let mut dirs: HashMap<String, PathChurn> = HashMap::new();
+            for (path, path_churn) in &churn {
+                dirs.entry(directory(path, depth))
+                    .or_default()
+                    .merge(path_churn);
+            }
+            dirs.into_iter()
+                .map(|(dir, dir_churn)| dir_churn.entry(dir))
+                .collect()
+        }
+        None => churn
+            .iter()
+            .map(|(path, path_churn)| path_churn.entry(path.display().to_string()))
+            .collect(),
+    };
+    entries.sort_by(|a, b| {
+        b.commits
+            .cmp(&a.commits)
+            .then_with(|| (b.insertions + b.deletions).cmp(&(a.insertions + a.deletions)))
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    if let Some(top) = args.top {
+        entries.truncate(top);
+    }
+    Ok(entries)
+}
+
+/// Every commit reachable from `tip`, each once, in no particular order.
+fn reachable_commits(tip: ObjectHash) -> Result<Vec<Commit>, String> {
+    let mut seen = HashSet::new();
+    let mut queue = VecDeque::from([tip]);
+    let mut commits = Vec::new();
+    while let Some(id) = queue.pop_front() {
+        if !seen.insert(id) {
+            continue;
+        }
+        let commit: Commit =
+            load_object(&id).map_err(|e| format!("failed to load commit {id}: {e}"))?;
+        queue.extend(commit.parent_commit_ids.iter().copied());
+        commits.push(commit);
+    }
+    Ok(commits)
+}
+
+/// The paths `commit` changed against its first parent, limited to `paths` when given, and
+/// with `find_renames` the `(old, new)` renames it made.
+fn commit_stats(
+    commit: &Commit,
+    paths: &[PathBuf],
+    find_renames: bool,
+) -> Result<(Vec<PathStat>, Renames), String> {
+    let tree: Tree = load_object(&commit.tree_id).map_err(|e| e.to_string())?;
+    let parent_tree: Option<Tree> = match commit.parent_commit_ids.first() {
+        Some(parent) => {
+            let parent: Commit = load_object(parent).map_err(|e| e.to_string())?;
+            Some(load_object(&parent.tree_id).map_err(|e| e.to_string())?)
+        }
+        None => None,
+    };
+    let new_items = tree.get_plain_items();
+    let mut old_items = parent_tree
+        .as_ref()
+        .map(|tree| tree.get_plain_items())
+        .unwrap_or_default();
+
+    let renames = match &parent_tree {
+        Some(parent_tree) if find_renames => find_renames_between(parent_tree, &tree),
+        _ => Vec::new(),
+    };
+    // Diff each renamed file against its old content, under its new name.
+    for (old, new) in &renames {
+        if let Some(item) = old_items.iter_mut().find(|(path, _)| path == old) {
+            item.0 = new.clone();
+        }
+    }
+
+    let read_content = |_: &PathBuf, hash: &ObjectHash| {
+        load_object::<Blob>(hash)
+            .map(|blob| blob.data)
+            .unwrap_or_default()
+    };
+    let mut stats: Vec<PathStat> = Diff::diff(old_items, new_items, paths.to_vec(), read_content)
+        .into_iter()
+        .map(|item| {
+            let (mut insertions, mut deletions) = (0, 0);
+            for line in item.data.lines() {
+                if line.starts_with('+') && !line.starts_with("+++") {
+                    insertions += 1;
+                } else if line.starts_with('-') && !line.starts_with("---") {
+                    deletions += 1;
+                }
+            }
+            PathStat {
+                path: PathBuf::from(item.path),
+                insertions,
+                deletions,
+            }
+        })
+        .collect();
+    // A rename without edits has no diff but still touched the file.
+    for (_, new) in &renames {
+        let in_paths = paths.is_empty() || paths.iter().any(|p| util::is_sub_path(new, p));
+        if in_paths && !stats.iter().any(|stat| &stat.path == new) {
+            stats.push(PathStat {
+                path: new.clone(),
+                insertions: 0,
+                deletions: 0,
+            });
+        }
+    }
+    Ok((stats, renames))
+}
+
+/// Renames from `old_tree` to `new_tree`, as `(old, new)` paths; each deleted path is the
+/// source of at most one added path.
+fn find_renames_between(old_tree: &Tree, new_tree: &Tree) -> Renames {
+    let old_paths: HashSet<PathBuf> = old_tree
+        .get_plain_items()
+        .into_iter()
+        .map(|(path, _)| path)
+        .collect();
+    let new_paths: Vec<PathBuf> = new_tree
+        .get_plain_items()
+        .into_iter()
+        .map(|(path, _)| path)
+        .collect();
+    let added: Vec<&PathBuf> = new_paths
+        .iter()
+        .filter(|path| !old_paths.contains(*path))
+        .collect();
+    if added.is_empty() || old_paths.iter().all(|path| new_paths.contains(path)) {
+        return Vec::new();
+    }
+
+    let mut renames: Renames = Vec::new();
+    for new in added {
+        if let Some(old) = rename::find_rename_source(old_tree, new_tree, new)
+            && !renames.iter().any(|(source, _)| *source == old)
+        {
+            renames.push((old, new.clone()));
+        }
+    }
+    renames
+}
+
+/// The first `depth` directory components of `path`, or `.` for a top-level file.
+fn directory(path: &Path, depth: usize) -> String {
+    let components: Vec<String> = path
+        .parent()
+        .into_iter()
+        .flat_map(Path::components)
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .take(depth.max(1))
+        .collect();
+    if components.is_empty() {
+        ".".to_string()
+    } else {
+        components.join("/")
+    }
+}
+
+fn write_table(writer: &mut impl Write, entries: &[ChurnEntry]) -> std::io::Result<()> {
+    writeln!(
+        writer,
+        "{:>7} {:>7} {:>7} {:>7}  path",
+        "commits", "added", "deleted", "authors"
+    )?;
+    for entry in entries {
+        writeln!(
+            writer,
+            "{:>7} {:>7} {:>7} {:>7}  {}",
+            entry.commits, entry.insertions, entry.deletions, entry.authors, entry.path
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_directory_rollup_depth() {
+        assert_eq!(directory(Path::new("README.md"), 1), ".");
+        assert_eq!(directory(Path::new("src/main.rs"), 1), "src");
+        assert_eq!(directory(Path::new("src/command/churn.rs"), 1), "src");
+        assert_eq!(
+            directory(Path::new("src/command/churn.rs"), 2),
+            "src/command"
+        );
+        assert_eq!(
+            directory(Path::new("src/command/churn.rs"), 5),
+            "src/command"
+        );
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let args = ChurnArgs::try_parse_from(["churn", "--by-dir", "--top", "3"]).unwrap();
+        assert_eq!(args.by_dir, Some(1));
+        assert_eq!(args.top, Some(3));
+        let args = ChurnArgs::try_parse_from(["churn", "--by-dir=2", "-M", "src"]).unwrap();
+        assert_eq!(args.by_dir, Some(2));
+        assert!(args.find_renames);
+        assert_eq!(args.pathspec, vec!["src".to_string()]);
+    }
+}
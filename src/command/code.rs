@@ -9,6 +9,7 @@ use std::{net::SocketAddr, sync::Arc};
 
 use axum::{Router, response::Html, routing::get};
 use clap::{Parser, ValueEnum};
+use git_internal::internal::object::types::ActorRef;
 use tokio::sync::oneshot;
 
 // use uuid::Uuid;
@@ -17,6 +18,7 @@ use crate::internal::{
         client::CompletionClient,
         history::HistoryManager,
         mcp::{resource::CreateIntentParams, server::LibraMcpServer},
+        memory::MemoryStore,
         providers::{
             anthropic::{CLAUDE_3_5_SONNET, Client as AnthropicClient},
             deepseek::client::Client as DeepSeekClient,
@@ -24,11 +26,13 @@ use crate::internal::{
             openai::{Client as OpenAIClient, GPT_4O_MINI},
             zhipu::{Client as ZhipuClient, GLM_5},
         },
+        repo_context::RepoContextMiddleware,
         tools::{
             ToolRegistry, ToolRegistryBuilder,
             handlers::{
-                ApplyPatchHandler, GrepFilesHandler, ListDirHandler, McpBridgeHandler, PlanHandler,
-                ReadFileHandler, RequestUserInputHandler, ShellHandler,
+                AiHistoryHandler, ApplyPatchHandler, GrepFilesHandler, ListDirHandler,
+                McpBridgeHandler, PlanHandler, ReadFileHandler, RecallHandler, RememberHandler,
+                RequestUserInputHandler, ShellHandler, WriteFileHandler,
             },
         },
     },
@@ -78,6 +82,10 @@ pub struct CodeArgs {
     #[arg(long)]
     pub resume: bool,
 
+    /// Do not start the system prompt with the repository context (branch, HEAD, dirty files)
+    #[arg(long)]
+    pub no_repo_context: bool,
+
     /// Port to listen on (MCP server)
     #[arg(long, default_value_t = 6789)]
     pub mcp_port: u16,
@@ -246,7 +254,13 @@ async fn execute_tui(args: CodeArgs) {
     // Use repository working directory to ensure correct initialization of .libra resources.
     let working_dir = crate::utils::util::working_dir();
 
-    let preamble = system_preamble(&working_dir, args.context.as_deref());
+    let mut preamble = system_preamble(&working_dir, args.context.as_deref());
+    // The session reuses one preamble, so the context reflects the repository at startup.
+    if !args.no_repo_context
+        && let Some(context) = RepoContextMiddleware::default().render().await
+    {
+        preamble = format!("{context}\n\n{preamble}");
+    }
     let temperature = args.temperature;
     let resume = args.resume;
 
@@ -258,14 +272,18 @@ async fn execute_tui(args: CodeArgs) {
         crate::internal::ai::tools::context::UserInputRequest,
     >();
 
-    // Build registry: basic file tools + MCP workflow tools
+    // Build registry: basic file tools + memory tools + MCP workflow tools
+    let memory = Arc::new(MemoryStore::new(&working_dir));
     let mut builder = ToolRegistryBuilder::with_working_dir(working_dir)
         .register("read_file", Arc::new(ReadFileHandler))
         .register("list_dir", Arc::new(ListDirHandler))
         .register("grep_files", Arc::new(GrepFilesHandler))
         .register("apply_patch", Arc::new(ApplyPatchHandler))
+        .register("write_file", Arc::new(WriteFileHandler))
         .register("shell", Arc::new(ShellHandler))
         .register("update_plan", Arc::new(PlanHandler))
+        .register("remember", Arc::new(RememberHandler::new(memory.clone())))
+        .register("recall", Arc::new(RecallHandler::new(memory)))
         .register(
             "request_user_input",
             Arc::new(RequestUserInputHandler::new(user_input_tx)),
@@ -274,6 +292,13 @@ async fn execute_tui(args: CodeArgs) {
     for (name, handler) in McpBridgeHandler::all_handlers(mcp_server.clone()) {
         builder = builder.register(name, handler);
     }
+    // Lets the agent look up the intent and tasks it is executing.
+    if let Some(handler) = ActorRef::agent("libra-code")
+        .ok()
+        .and_then(|actor| AiHistoryHandler::from_mcp_server(&mcp_server, actor))
+    {
+        builder = builder.register("ai_history", Arc::new(handler));
+    }
 
     let registry = Arc::new(builder.build());
 
@@ -458,8 +483,18 @@ where
         preamble: Some(params.preamble),
         temperature: params.temperature,
         max_steps: None, // TUI mode: unlimited tool steps
+        max_tool_calls: None,
         hook_runner,
         allowed_tools: None,
+        request_timeout: None,
+        deadline: None,
+        clock: Arc::new(crate::internal::ai::clock::SystemClock),
+        include_reasoning: false,
+        guardrails: None,
+        budget: None,
+        untrusted_content: Some(Arc::new(
+            crate::internal::ai::completion::UntrustedContent::default(),
+        )),
     };
 
     // Initialize terminal
@@ -510,7 +545,8 @@ where
 
     // Load agent profiles
     let profiles = crate::internal::ai::agent::profile::load_profiles(registry.working_dir());
-    let agent_router = crate::internal::ai::agent::profile::AgentProfileRouter::new(profiles);
+    let agent_router = crate::internal::ai::agent::profile::AgentProfileRouter::new(profiles)
+        .with_default_synonyms();
 
     // Set up session persistence
     let working_dir_str = registry.working_dir().to_string_lossy().to_string();
@@ -29,12 +29,15 @@ use crate::{
     command::{load_object, status},
     common_utils::{check_conventional_commits_message, format_commit_msg},
     internal::{
+        ai::precommit,
         branch::Branch,
         config::Config as UserConfig,
         head::Head,
         reflog::{ReflogAction, ReflogContext, with_reflog},
     },
-    utils::{client_storage::ClientStorage, lfs, object_ext::BlobExt, path, util},
+    utils::{
+        client_storage::ClientStorage, index_lock::IndexLock, lfs, object_ext::BlobExt, path, util,
+    },
 };
 
 #[derive(Parser, Debug, Default)]
@@ -228,6 +231,7 @@ pub async fn execute(args: CommitArgs) {
 
     /* Create & save commit objects */
     let parents_commit_ids = get_parents_ids().await;
+    let previous_head = parents_commit_ids.first().cloned();
 
     // Create author and committer signatures (respecting --author override)
     let (author, committer) = create_commit_signatures(args.author.as_deref()).await;
@@ -288,6 +292,8 @@ pub async fn execute(args: CommitArgs) {
 
         /* update HEAD */
         update_head_and_reflog(&commit.id.to_string(), &commit_message).await;
+        precommit::link_pending_review(&util::storage_path(), previous_head.as_ref(), &commit.id)
+            .await;
         return;
     }
 
@@ -329,6 +335,7 @@ pub async fn execute(args: CommitArgs) {
 
     /* update HEAD */
     update_head_and_reflog(&commit.id.to_string(), &commit_message).await;
+    precommit::link_pending_review(&util::storage_path(), previous_head.as_ref(), &commit.id).await;
 }
 
 /// recursively create tree from index's tracked entries
@@ -412,6 +419,8 @@ fn auto_stage_tracked_changes() -> bool {
     }
 
     let index_path = path::index();
+    // hold the lock across load and save so a concurrent add cannot lose its entries
+    let lock = IndexLock::acquire(&index_path).unwrap_or_else(|e| panic!("fatal: {e}"));
     let mut index = Index::load(&index_path).unwrap();
     let workdir = util::working_dir();
     let mut touched = false;
@@ -437,7 +446,7 @@ fn auto_stage_tracked_changes() -> bool {
     }
 
     if touched {
-        index.save(&index_path).unwrap();
+        lock.commit(&mut index).unwrap();
     }
     touched
 }
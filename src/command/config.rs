@@ -739,7 +739,7 @@ async fn get_all_config(
 /// (`Local → Global → System`), skipping scopes whose backing storage is
 /// missing or invalid. Errors from individual scopes are ignored so that a
 /// later scope can still satisfy the lookup.
-async fn get_config_cascaded(
+pub(crate) async fn get_config_cascaded(
     configuration: &str,
     name: Option<&str>,
     key: &str,
@@ -1,17 +1,38 @@
 //! Provides diff command logic comparing commits, the index, and the working tree with algorithm selection, pathspec filtering, and optional file output.
+//!
+//! Invocation modes, resolved by [`resolve_plan`]:
+//!
+//! - `diff`: working tree against the index.
+//! - `diff --cached [<commit>]`: the index against `<commit>`, default `HEAD` (an empty
+//!   tree before the first commit). `--staged` is a synonym.
+//! - `diff <commit>`: working tree against `<commit>`.
+//! - `diff <a> <b>` and `diff <a>..<b>`: `<a>` against `<b>`; an empty side of `..`
+//!   means `HEAD`.
+//! - `diff --no-index <path1> <path2>`: two files, which need not be in a repository.
+//!
+//! Positional arguments are split into revisions and paths like git does: leading
+//! arguments that name a commit are revisions, the rest are paths. Without `--`, an
+//! argument that names both a commit and an existing file is rejected as ambiguous, and
+//! one that names neither is rejected too. Arguments after `--` are always paths, and
+//! every argument before it must then be a revision. A bare trailing `--` is dropped by
+//! the argument parser, so `diff <rev> --` reads as `diff <rev>`.
+//!
+//! Every mode shares one rendering pipeline: a patch (optionally `--word-diff` and
+//! colored), `--stat` or `--numstat`, limited by the pathspec. With `--exit-code` the
+//! command exits with status 1 when there are differences.
 
 #[cfg(unix)]
 use std::process::{Command, Stdio};
 use std::{
     fmt,
     io::{self, IsTerminal, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use colored::Colorize;
 use git_internal::{
-    Diff,
+    Diff, DiffItem,
     hash::ObjectHash,
     internal::{
         index::Index,
@@ -22,13 +43,17 @@ use git_internal::{
 use similar;
 
 use crate::{
-    command::{get_target_commit, load_object},
+    command::{
+        get_target_commit, load_object,
+        log::{FileStat, format_stat_output},
+    },
     internal::head::Head,
     utils::{
         ignore::{self, IgnorePolicy},
         object_ext::TreeExt,
-        path, util,
-        util::to_workdir_path,
+        path,
+        stat_cache::StatCache,
+        util,
     },
 };
 
@@ -43,14 +68,23 @@ pub struct DiffArgs {
     #[clap(requires = "old", group = "op_new")]
     pub new: Option<String>,
 
-    /// Use stage as new commit. This option is conflict with --new.
-    #[clap(long)]
+    /// Compare the index with a commit (HEAD unless one is given). This option is conflict with --new.
+    #[clap(long, visible_alias = "cached")]
     #[clap(group = "op_new")]
     pub staged: bool,
 
-    #[clap(help = "Files to compare")]
+    /// Compare two paths on the filesystem; they need not be inside a repository
+    #[clap(long = "no-index", conflicts_with_all = ["old", "new", "staged"])]
+    pub no_index: bool,
+
+    /// Revisions followed by files to compare
+    #[clap(value_name = "REVISION_OR_PATH")]
     pathspec: Vec<String>,
 
+    /// Files to compare, never read as revisions
+    #[clap(last = true, value_name = "PATH")]
+    paths: Vec<String>,
+
     // TODO: If algorithm support gets added to git-internal
     /// choose the exact diff algorithm default value is histogram
     /// support myers and myersMinimal
@@ -60,86 +94,145 @@ pub struct DiffArgs {
     // Print the result to file
     #[clap(long, value_name = "FILENAME")]
     pub output: Option<String>,
+
+    /// Show a diffstat instead of the patch
+    #[clap(long, group = "format")]
+    pub stat: bool,
+
+    /// Show the inserted and deleted line counts of each file, tab separated
+    #[clap(long, group = "format")]
+    pub numstat: bool,
+
+    /// Show changed words inline as [-removed-]{+added+} instead of whole lines
+    #[clap(long = "word-diff", group = "format")]
+    pub word_diff: bool,
+
+    /// Color the patch: always, never, or auto (only when writing to a terminal)
+    #[clap(
+        long,
+        value_enum,
+        value_name = "WHEN",
+        num_args = 0..=1,
+        require_equals = true,
+        default_value_t = ColorWhen::Auto,
+        default_missing_value = "always"
+    )]
+    pub color: ColorWhen,
+
+    /// Exit with status 1 if there are differences and 0 otherwise
+    #[clap(long = "exit-code")]
+    pub exit_code: bool,
+}
+
+/// When `--color` colors the patch.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum ColorWhen {
+    Always,
+    Never,
+    #[default]
+    Auto,
+}
+
+/// One side of a comparison.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum DiffSource {
+    /// The tree of a commit.
+    Commit(ObjectHash),
+    /// The stage-0 entries of the index.
+    Index,
+    /// The files in the working directory.
+    Worktree,
+    /// No files, e.g. `HEAD` before the first commit.
+    Empty,
+}
+
+/// A resolved invocation: what to compare and the paths limiting it.
+#[derive(Debug, PartialEq, Eq)]
+struct DiffPlan {
+    old: DiffSource,
+    new: DiffSource,
+    paths: Vec<PathBuf>,
 }
 
 pub async fn execute(args: DiffArgs) {
-    if !util::check_repo_exist() {
-        return;
-    }
-    tracing::debug!("diff args: {:?}", args);
-    let index = Index::load(path::index()).unwrap();
+    let exit_code = args.exit_code;
+    let output = args.output.clone();
+    let mut buf = Vec::new();
+    let differs = match execute_to(args, &mut buf).await {
+        Ok(differs) => differs,
+        Err(e) => {
+            eprintln!("fatal: {e}");
+            return;
+        }
+    };
 
-    let mut w = match args.output {
+    match output {
         Some(ref path) => {
-            let file = std::fs::File::create(path)
+            let mut file = std::fs::File::create(path)
                 .map_err(|e| {
                     eprintln!("fatal: could not open to file '{path}' for writing: {e}");
                 })
                 .unwrap();
-            Some(file)
+            file.write_all(&buf).unwrap();
         }
-        None => None,
-    };
-
-    let old_blobs = match &args.old {
-        // explicit --old <commit>
-        Some(source) => match get_target_commit(source).await {
-            Ok(commit_hash) => get_commit_blobs(&commit_hash).await,
-            Err(e) => {
-                eprintln!("fatal: {e}, can't use as diff old source");
-                return;
-            }
-        },
-
-        // no --old
-        None => {
-            if args.staged {
-                // git diff --staged  => old = HEAD
-                match Head::current_commit().await {
-                    Some(commit_hash) => get_commit_blobs(&commit_hash).await,
-                    None => {
-                        println!("No commits yet - nothing to compare");
-                        return;
-                    }
-                }
-            } else {
-                // default git diff => old = INDEX
-                let files = index.tracked_files();
-                get_files_blobs(&files, &index, IgnorePolicy::Respect)
-            }
+        #[cfg(unix)]
+        None if io::stdout().is_terminal() => {
+            let mut child = Command::new("less")
+                .arg("-R")
+                .arg("-F")
+                .stdin(Stdio::piped())
+                .spawn()
+                .expect("failed to execute process");
+            let stdin = child.stdin.as_mut().unwrap();
+            stdin.write_all(&buf).unwrap();
+            child.wait().unwrap();
         }
-    };
-
-    let new_blobs = match args.new {
-        Some(ref source) => match get_target_commit(source).await {
-            Ok(commit_hash) => get_commit_blobs(&commit_hash).await,
-            Err(e) => {
-                eprintln!("fatal: {e}, can't use as diff new source");
-                return;
-            }
-        },
         None => {
-            let files = if args.staged {
-                // use staged as new commit
-                index.tracked_files()
-            } else {
-                // use working directory as new commit
-                // NOTE: git didn't show diff for untracked files, but we do
-                util::list_workdir_files().unwrap()
-            };
-            get_files_blobs(&files, &index, IgnorePolicy::Respect)
+            io::stdout().write_all(&buf).unwrap();
         }
+    }
+
+    if exit_code && differs {
+        std::process::exit(1);
+    }
+}
+
+/// Render the diff selected by `args` into `writer` and report whether anything differs.
+///
+/// `--output` and the pager are left to [`execute`]; `--color=auto` colors only when
+/// standard output is a terminal and no `--output` file is given.
+pub async fn execute_to(args: DiffArgs, writer: &mut impl Write) -> Result<bool, String> {
+    let color = match args.color {
+        ColorWhen::Always => true,
+        ColorWhen::Never => false,
+        ColorWhen::Auto => io::stdout().is_terminal() && args.output.is_none(),
     };
 
-    // use pathspec to filter files
-    let paths: Vec<PathBuf> = args.pathspec.iter().map(util::to_workdir_path).collect();
+    if args.no_index {
+        let files: Vec<&String> = args.pathspec.iter().chain(&args.paths).collect();
+        let [old, new] = files.as_slice() else {
+            return Err("--no-index requires exactly two paths".to_string());
+        };
+        let items = diff_files(Path::new(old), Path::new(new))?;
+        render(&args, &items, color, writer).map_err(|e| e.to_string())?;
+        return Ok(!items.is_empty());
+    }
+
+    if !util::check_repo_exist() {
+        return Ok(false);
+    }
+    tracing::debug!("diff args: {:?}", args);
+    let plan = resolve_plan(&args).await?;
+    let index = Index::load(path::index()).unwrap();
+    let old_blobs = source_blobs(&plan.old, &index).await;
+    let new_blobs = source_blobs(&plan.new, &index).await;
 
     let read_content = |file: &PathBuf, hash: &ObjectHash| {
         // read content from blob or file
         match load_object::<Blob>(hash) {
             Ok(blob) => blob.data,
             Err(_) => {
-                let file = to_workdir_path(file);
+                let file = util::workdir_to_absolute(file);
                 std::fs::read(&file)
                     .map_err(|e| {
                         eprintln!("fatal: could not read file '{}': {}", file.display(), e);
@@ -150,45 +243,301 @@ pub async fn execute(args: DiffArgs) {
     };
 
     // Get diff output as string using the unified diff function
-    let diff_output = Diff::diff(
+    let mut items: Vec<DiffItem> = Diff::diff(
         old_blobs,
         new_blobs,
         // args.algorithm.unwrap_or_default(),
-        paths,
+        plan.paths,
         read_content,
-    );
+    )
+    .into_iter()
+    .filter(|item| !item.data.is_empty())
+    .collect();
+    items.sort_by(|a, b| a.path.cmp(&b.path));
+
+    render(&args, &items, color, writer).map_err(|e| e.to_string())?;
+    Ok(!items.is_empty())
+}
 
-    let results: Vec<String> = diff_output.iter().map(|i| i.data.clone()).collect();
+/// Work out the two sides and the pathspec from `args`; see the module docs.
+async fn resolve_plan(args: &DiffArgs) -> Result<DiffPlan, String> {
+    let (mut revisions, paths) = split_revisions(&args.pathspec, &args.paths).await?;
+    if let Some(old) = &args.old {
+        if !revisions.is_empty() {
+            return Err("--old and --new cannot be combined with revision arguments".to_string());
+        }
+        for source in std::iter::once(old).chain(&args.new) {
+            let commit = get_target_commit(source)
+                .await
+                .map_err(|e| format!("{e}, can't use '{source}' as diff source"))?;
+            revisions.push(commit);
+        }
+    }
+
+    let (old, new) = match (args.staged, revisions.as_slice()) {
+        (false, []) => (DiffSource::Index, DiffSource::Worktree),
+        (true, []) => match Head::current_commit().await {
+            Some(head) => (DiffSource::Commit(head), DiffSource::Index),
+            None => (DiffSource::Empty, DiffSource::Index),
+        },
+        (false, [commit]) => (DiffSource::Commit(*commit), DiffSource::Worktree),
+        (true, [commit]) => (DiffSource::Commit(*commit), DiffSource::Index),
+        (false, [old, new]) => (DiffSource::Commit(*old), DiffSource::Commit(*new)),
+        (true, _) => return Err("--cached takes at most one commit".to_string()),
+        (false, _) => return Err("too many revisions; diff compares at most two".to_string()),
+    };
+
+    Ok(DiffPlan {
+        old,
+        new,
+        paths: paths.iter().map(util::to_workdir_path).collect(),
+    })
+}
+
+/// Split positional arguments into commits and paths using git's rules; `after_dashdash`
+/// holds the arguments given after `--`.
+async fn split_revisions(
+    args: &[String],
+    after_dashdash: &[String],
+) -> Result<(Vec<ObjectHash>, Vec<String>), String> {
+    let mut revisions = Vec::new();
+    let mut rest = args;
+    while let Some((arg, tail)) = rest.split_first() {
+        let Some(commits) = parse_revision(arg).await else {
+            break;
+        };
+        if after_dashdash.is_empty() && Path::new(arg).exists() {
+            return Err(format!(
+                "ambiguous argument '{arg}': both revision and filename\n\
+                 Use '--' to separate paths from revisions, like this:\n\
+                 'libra diff [<revision>...] -- [<file>...]'"
+            ));
+        }
+        revisions.extend(commits);
+        rest = tail;
+    }
 
-    // Handle output - libra processes the string according to its needs
-    match w {
-        Some(ref mut file) => {
-            file.write_all(results.join("").as_bytes()).unwrap();
+    if !after_dashdash.is_empty() {
+        if let Some(arg) = rest.first() {
+            return Err(format!("bad revision '{arg}'"));
         }
-        None => {
-            let output = if io::stdout().is_terminal() {
-                colorize_diff(&results.join(""))
-            } else {
-                results.join("")
-            };
-            #[cfg(unix)]
-            {
-                let mut child = Command::new("less")
-                    .arg("-R")
-                    .arg("-F")
-                    .stdin(Stdio::piped())
-                    .spawn()
-                    .expect("failed to execute process");
-                let stdin = child.stdin.as_mut().unwrap();
-                stdin.write_all(output.as_bytes()).unwrap();
-                child.wait().unwrap();
+        return Ok((revisions, after_dashdash.to_vec()));
+    }
+    if let Some(arg) = rest.iter().find(|arg| !Path::new(arg).exists()) {
+        return Err(format!(
+            "ambiguous argument '{arg}': unknown revision or path not in the working tree.\n\
+             Use '--' to separate paths from revisions, like this:\n\
+             'libra diff [<revision>...] -- [<file>...]'"
+        ));
+    }
+    Ok((revisions, rest.to_vec()))
+}
+
+/// The commits `arg` names: one for a plain revision, two for `<a>..<b>`.
+pub(crate) async fn parse_revision(arg: &str) -> Option<Vec<ObjectHash>> {
+    async fn resolve(rev: &str) -> Option<ObjectHash> {
+        let rev = if rev.is_empty() { "HEAD" } else { rev };
+        get_target_commit(rev).await.ok()
+    }
+    match arg.split_once("..") {
+        Some((old, new)) if !old.starts_with('.') && !new.starts_with('.') => {
+            Some(vec![resolve(old).await?, resolve(new).await?])
+        }
+        Some(_) => None,
+        None => Some(vec![resolve(arg).await?]),
+    }
+}
+
+/// The `(path, blob)` pairs of one side of the comparison.
+async fn source_blobs(source: &DiffSource, index: &Index) -> Vec<(PathBuf, ObjectHash)> {
+    match source {
+        DiffSource::Commit(commit) => get_commit_blobs(commit).await,
+        DiffSource::Index => index
+            .tracked_entries(0)
+            .into_iter()
+            .map(|entry| (PathBuf::from(&entry.name), entry.hash))
+            .collect(),
+        DiffSource::Worktree => {
+            // NOTE: git didn't show diff for untracked files, but we do
+            let files = util::list_workdir_files().unwrap();
+            get_files_blobs(&files, index, IgnorePolicy::Respect)
+        }
+        DiffSource::Empty => Vec::new(),
+    }
+}
+
+/// The patch between two files outside the object store, for `--no-index`. Empty when
+/// their contents are equal.
+fn diff_files(old: &Path, new: &Path) -> Result<Vec<DiffItem>, String> {
+    let read = |path: &Path| {
+        std::fs::read(path)
+            .map(|data| String::from_utf8_lossy(&data).into_owned())
+            .map_err(|e| format!("could not read '{}': {e}", path.display()))
+    };
+    let (old_text, new_text) = (read(old)?, read(new)?);
+    if old_text == new_text {
+        return Ok(Vec::new());
+    }
+    let (old, new) = (old.display().to_string(), new.display().to_string());
+    let hunks = similar::TextDiff::from_lines(&old_text, &new_text)
+        .unified_diff()
+        .context_radius(3)
+        .header(&format!("a/{old}"), &format!("b/{new}"))
+        .to_string();
+    Ok(vec![DiffItem {
+        path: new.clone(),
+        data: format!("diff --git a/{old} b/{new}\n{hunks}"),
+    }])
+}
+
+/// Write `items` in the format selected by `args`, colored when `color` is set.
+fn render(
+    args: &DiffArgs,
+    items: &[DiffItem],
+    color: bool,
+    writer: &mut impl Write,
+) -> io::Result<()> {
+    // `colored` decides from the environment by default; `--color` overrides that for
+    // the patch and the stat bars alike.
+    colored::control::set_override(color);
+    let result = write_items(args, items, color, writer);
+    colored::control::unset_override();
+    result
+}
+
+fn write_items(
+    args: &DiffArgs,
+    items: &[DiffItem],
+    color: bool,
+    writer: &mut impl Write,
+) -> io::Result<()> {
+    if args.stat || args.numstat {
+        let stats: Vec<FileStat> = items
+            .iter()
+            .map(|item| {
+                let (insertions, deletions) = count_changed_lines(&item.data);
+                FileStat {
+                    path: item.path.clone(),
+                    insertions,
+                    deletions,
+                }
+            })
+            .collect();
+        if args.stat {
+            return writer.write_all(format_stat_output(&stats).as_bytes());
+        }
+        for stat in stats {
+            writeln!(
+                writer,
+                "{}\t{}\t{}",
+                stat.insertions, stat.deletions, stat.path
+            )?;
+        }
+        return Ok(());
+    }
+
+    let patch: String = items.iter().map(|item| item.data.as_str()).collect();
+    let patch = if args.word_diff {
+        word_diff(&patch)
+    } else {
+        patch
+    };
+    let patch = if color { colorize_diff(&patch) } else { patch };
+    writer.write_all(patch.as_bytes())
+}
+
+/// Inserted and deleted lines in a unified patch.
+fn count_changed_lines(patch: &str) -> (usize, usize) {
+    let mut counts = (0, 0);
+    for line in patch_body_lines(patch) {
+        if line.starts_with('+') {
+            counts.0 += 1;
+        } else if line.starts_with('-') {
+            counts.1 += 1;
+        }
+    }
+    counts
+}
+
+/// The lines of `patch` inside hunks, i.e. after an `@@` header and before the next
+/// `diff --git` line, so removed lines that start with `--` are not taken for headers.
+fn patch_body_lines(patch: &str) -> impl Iterator<Item = &str> {
+    let mut in_hunk = false;
+    patch.lines().filter(move |line| {
+        if line.starts_with("diff --git") {
+            in_hunk = false;
+        } else if line.starts_with("@@") {
+            in_hunk = true;
+            return false;
+        }
+        in_hunk
+    })
+}
+
+/// Rewrite a unified patch so each run of removed and added lines becomes one block
+/// of text with the changed words marked as `[-removed-]{+added+}`.
+fn word_diff(patch: &str) -> String {
+    let mut out = String::with_capacity(patch.len());
+    let mut removed = String::new();
+    let mut added = String::new();
+    let mut in_hunk = false;
+    for line in patch.lines() {
+        if line.starts_with("diff --git") {
+            in_hunk = false;
+        } else if line.starts_with("@@") {
+            in_hunk = true;
+        } else if in_hunk {
+            if let Some(text) = line.strip_prefix('-') {
+                removed.push_str(text);
+                removed.push('\n');
+                continue;
             }
-            #[cfg(not(unix))]
-            {
-                io::stdout().write_all(output.as_bytes()).unwrap();
+            if let Some(text) = line.strip_prefix('+') {
+                added.push_str(text);
+                added.push('\n');
+                continue;
             }
         }
+        flush_word_diff(&mut out, &mut removed, &mut added);
+        if in_hunk && line.starts_with('\\') {
+            continue;
+        }
+        out.push_str(match line.strip_prefix(' ') {
+            Some(context) if in_hunk => context,
+            _ => line,
+        });
+        out.push('\n');
     }
+    flush_word_diff(&mut out, &mut removed, &mut added);
+    out
+}
+
+/// Append the word diff of `removed` against `added` to `out` and clear both.
+fn flush_word_diff(out: &mut String, removed: &mut String, added: &mut String) {
+    if removed.is_empty() && added.is_empty() {
+        return;
+    }
+    let diff = similar::TextDiff::from_words(removed.as_str(), added.as_str());
+    for change in diff.iter_all_changes() {
+        let value = change.value();
+        // Whitespace keeps the layout readable; marking it would split lines.
+        if value.trim().is_empty() {
+            if change.tag() != similar::ChangeTag::Delete {
+                out.push_str(value);
+            }
+            continue;
+        }
+        match change.tag() {
+            similar::ChangeTag::Equal => out.push_str(value),
+            similar::ChangeTag::Delete => out.push_str(&format!("[-{value}-]")),
+            similar::ChangeTag::Insert => out.push_str(&format!("{{+{value}+}}")),
+        }
+    }
+    if !out.ends_with('\n') {
+        out.push('\n');
+    }
+    removed.clear();
+    added.clear();
 }
 
 async fn get_commit_blobs(commit_hash: &ObjectHash) -> Vec<(PathBuf, ObjectHash)> {
@@ -199,16 +548,24 @@ async fn get_commit_blobs(commit_hash: &ObjectHash) -> Vec<(PathBuf, ObjectHash)
 
 /// diff needs to print hashes even if the files have not been staged yet.
 /// This helper maps workdir paths to blob ids while applying the shared ignore policy.
+/// Files whose stat data still matches the index reuse the indexed blob id unhashed.
 fn get_files_blobs(
     files: &[PathBuf],
     index: &Index,
     policy: IgnorePolicy,
 ) -> Vec<(PathBuf, ObjectHash)> {
+    let cache = StatCache::new(path::index());
     files
         .iter()
         .filter(|path| !ignore::should_ignore(path, policy, index))
         .map(|p| {
             let path = util::workdir_to_absolute(p);
+            if let Some(entry) = p.to_str().and_then(|name| index.get(name, 0))
+                && let Ok(meta) = path.symlink_metadata()
+                && cache.is_fresh(entry, &meta)
+            {
+                return (p.to_owned(), entry.hash);
+            }
             let data = std::fs::read(&path).unwrap();
             (p.to_owned(), calculate_object_hash(ObjectType::Blob, &data))
         })
@@ -324,6 +681,21 @@ mod test {
             assert!(args.is_err());
             assert!(args.err().unwrap().kind() == clap::error::ErrorKind::MissingRequiredArgument);
         }
+        {
+            // --cached is --staged; paths after -- are kept apart
+            let args = DiffArgs::try_parse_from(["diff", "--cached", "HEAD", "--", "a"]).unwrap();
+            assert!(args.staged);
+            assert_eq!(args.pathspec, vec!["HEAD".to_string()]);
+            assert_eq!(args.paths, vec!["a".to_string()]);
+        }
+        {
+            // --no-index compares files, not revisions
+            let args = DiffArgs::try_parse_from(["diff", "--no-index", "--staged", "a", "b"]);
+            assert!(args.err().unwrap().kind() == clap::error::ErrorKind::ArgumentConflict);
+            // a single output format
+            let args = DiffArgs::try_parse_from(["diff", "--stat", "--numstat"]);
+            assert!(args.err().unwrap().kind() == clap::error::ErrorKind::ArgumentConflict);
+        }
         // TODO: Enable these tests when --algorithm arg is fully implemented
         // {
         //     // --algorithm arg
@@ -359,6 +731,19 @@ mod test {
         println!("{result}");
     }
 
+    #[test]
+    /// Removed lines that look like `---` headers are still counted and word-diffed.
+    fn test_word_diff_and_counts_read_only_hunks() {
+        let patch = "diff --git a/f b/f\n--- a/f\n+++ b/f\n@@ -1,2 +1,2 @@\n\
+                     --- old rule\n+-- new rule\n same\n";
+        assert_eq!(count_changed_lines(patch), (1, 1));
+        assert_eq!(
+            word_diff(patch),
+            "diff --git a/f b/f\n--- a/f\n+++ b/f\n@@ -1,2 +1,2 @@\n\
+             -- [-old-]{+new+} rule\nsame\n"
+        );
+    }
+
     #[tokio::test]
     #[serial]
     /// Tests that the get_files_blobs function properly respects .libraignore patterns.
@@ -0,0 +1,538 @@
+//! Implements `export-git`, which writes branches, tags and their history as a `git fast-import`
+//! stream, to stdout or a file.
+//!
+//! Commits are emitted parents-first with `from`/`merge` clauses, each preceded by the blobs it
+//! introduces; file changes are computed against the first parent, skipping unchanged subtrees.
+//! Annotated tags become `tag` commands and lightweight tags and branch tips `reset` commands.
+//!
+//! `--marks <file>` makes exports incremental: objects already listed in the file are referred to
+//! by their mark instead of being emitted again, and the file is updated with the new marks. The
+//! file uses the `:<mark> <id>` format of git, so it can be handed to `git fast-import` as well.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use clap::Parser;
+use git_internal::{
+    errors::GitError,
+    hash::ObjectHash,
+    internal::object::{
+        blob::Blob,
+        commit::Commit,
+        tag::Tag as GitTag,
+        tree::{Tree, TreeItemMode},
+        types::ObjectType,
+    },
+};
+use indicatif::ProgressBar;
+
+use crate::{
+    command::load_object,
+    internal::{
+        branch::Branch,
+        tag::{self, TagObject},
+    },
+    utils::{
+        fast_import::{
+            BlobCommand, Command, CommitCommand, CommitRef, DataRef, FileChange, StreamWriter,
+            TagCommand,
+        },
+        util,
+    },
+};
+
+const BRANCH_REF_PREFIX: &str = "refs/heads/";
+const TAG_REF_PREFIX: &str = "refs/tags/";
+
+#[derive(Parser, Debug, Default)]
+pub struct ExportGitArgs {
+    /// Export only this branch or tag (a short name or a full refs/... name). Can be repeated
+    #[clap(long = "ref", value_name = "REF")]
+    pub refs: Vec<String>,
+
+    /// Write the stream to a file instead of stdout
+    #[clap(short, long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+
+    /// Marks file to read before and update after the export, to only emit new objects
+    #[clap(long, value_name = "FILE")]
+    pub marks: Option<PathBuf>,
+}
+
+/// What an export emitted.
+#[derive(Debug, Default)]
+pub struct ExportSummary {
+    pub commits: usize,
+    pub blobs: usize,
+    pub tags: usize,
+    pub refs: usize,
+}
+
+pub async fn execute(args: ExportGitArgs) {
+    if !util::check_repo_exist() {
+        return;
+    }
+    if let Err(e) = execute_checked(args).await {
+        eprintln!("fatal: {e}");
+    }
+}
+
+/// Writes the stream and reports on stderr, returning errors instead of printing them.
+pub async fn execute_checked(args: ExportGitArgs) -> Result<ExportSummary, String> {
+    let tips = collect_tips(&args.refs).await?;
+    let mut marks = match &args.marks {
+        Some(path) => read_marks(path)?,
+        None => HashMap::new(),
+    };
+
+    let summary = match &args.output {
+        Some(path) => {
+            let file = fs::File::create(path)
+                .map_err(|e| format!("failed to create {}: {e}", path.display()))?;
+            export(&tips, &mut marks, BufWriter::new(file))?
+        }
+        None => export(&tips, &mut marks, BufWriter::new(io::stdout().lock()))?,
+    };
+    if let Some(path) = &args.marks {
+        write_marks(path, &marks)?;
+    }
+
+    // stdout may be the stream itself, so the summary goes to stderr.
+    eprintln!(
+        "Exported {} commits, {} blobs and {} tags ({} refs)",
+        summary.commits, summary.blobs, summary.tags, summary.refs
+    );
+    Ok(summary)
+}
+
+/// A ref to export.
+enum Tip {
+    Branch(String, ObjectHash),
+    LightweightTag(String, ObjectHash),
+    /// The tag ref and the tag object, which points to a commit.
+    AnnotatedTag(String, Box<GitTag>),
+}
+
+impl Tip {
+    fn ref_name(&self) -> &str {
+        match self {
+            Tip::Branch(name, _) | Tip::LightweightTag(name, _) | Tip::AnnotatedTag(name, _) => {
+                name
+            }
+        }
+    }
+
+    fn commit(&self) -> ObjectHash {
+        match self {
+            Tip::Branch(_, commit) | Tip::LightweightTag(_, commit) => *commit,
+            Tip::AnnotatedTag(_, tag) => tag.object_hash,
+        }
+    }
+}
+
+/// Collects the branches and tags to export, all of them when `selected` is empty.
+async fn collect_tips(selected: &[String]) -> Result<Vec<Tip>, String> {
+    let mut tips = Vec::new();
+    for branch in Branch::list_branches(None).await {
+        tips.push(Tip::Branch(
+            format!("{BRANCH_REF_PREFIX}{}", branch.name),
+            branch.commit,
+        ));
+    }
+    for tag in tag::list().await.map_err(|e| e.to_string())? {
+        let ref_name = format!("{TAG_REF_PREFIX}{}", tag.name);
+        match tag.object {
+            TagObject::Commit(commit) => tips.push(Tip::LightweightTag(ref_name, commit.id)),
+            TagObject::Tag(tag_object) if tag_object.object_type == ObjectType::Commit => {
+                tips.push(Tip::AnnotatedTag(ref_name, Box::new(tag_object)))
+            }
+            _ => eprintln!(
+                "warning: tag '{}' does not point to a commit; skipped",
+                tag.name
+            ),
+        }
+    }
+
+    if selected.is_empty() {
+        return Ok(tips);
+    }
+    let mut chosen = Vec::new();
+    for name in selected {
+        let candidates = [
+            name.clone(),
+            format!("{BRANCH_REF_PREFIX}{name}"),
+            format!("{TAG_REF_PREFIX}{name}"),
+        ];
+        let index = candidates
+            .iter()
+            .find_map(|candidate| tips.iter().position(|tip| tip.ref_name() == candidate))
+            .ok_or_else(|| format!("ref '{name}' not found"))?;
+        chosen.push(tips.remove(index));
+    }
+    Ok(chosen)
+}
+
+/// Writes every object reachable from `tips` that has no mark yet, then the refs.
+fn export(
+    tips: &[Tip],
+    marks: &mut HashMap<ObjectHash, u64>,
+    writer: impl Write,
+) -> Result<ExportSummary, String> {
+    let mut exporter = Exporter {
+        writer: StreamWriter::new(writer),
+        next_mark: marks.values().max().map_or(1, |mark| mark + 1),
+        marks,
+        summary: ExportSummary::default(),
+        progress: ProgressBar::new_spinner(),
+    };
+    let write_error = |e: io::Error| format!("failed to write stream: {e}");
+
+    exporter
+        .emit(Command::Feature("done".to_string()))
+        .map_err(write_error)?;
+    let mut emitted_for_ref: HashMap<String, ObjectHash> = HashMap::new();
+    for tip in tips {
+        // Commits only reachable from a tag are written on the tag ref, like git does.
+        let ref_name = tip.ref_name().to_string();
+        for commit in exporter.unmarked_commits(tip.commit())? {
+            exporter.commit(&ref_name, &commit)?;
+            emitted_for_ref.insert(ref_name.clone(), commit.id);
+        }
+    }
+
+    for tip in tips {
+        match tip {
+            Tip::AnnotatedTag(ref_name, tag_object) => exporter.tag(ref_name, tag_object)?,
+            Tip::Branch(ref_name, commit) | Tip::LightweightTag(ref_name, commit) => {
+                if emitted_for_ref.get(ref_name) != Some(commit) {
+                    let from = exporter.commit_ref(commit);
+                    exporter
+                        .emit(Command::Reset {
+                            ref_name: ref_name.clone(),
+                            from: Some(from),
+                        })
+                        .map_err(write_error)?;
+                }
+            }
+        }
+        exporter.summary.refs += 1;
+    }
+    exporter.emit(Command::Done).map_err(write_error)?;
+    exporter.progress.finish_and_clear();
+
+    let mut writer = exporter.writer.into_inner();
+    writer.flush().map_err(write_error)?;
+    Ok(exporter.summary)
+}
+
+struct Exporter<'a, W> {
+    writer: StreamWriter<W>,
+    marks: &'a mut HashMap<ObjectHash, u64>,
+    next_mark: u64,
+    summary: ExportSummary,
+    progress: ProgressBar,
+}
+
+impl<W: Write> Exporter<'_, W> {
+    fn emit(&mut self, command: Command) -> io::Result<()> {
+        self.writer.write_command(&command)
+    }
+
+    fn new_mark(&mut self, id: ObjectHash) -> u64 {
+        let mark = self.next_mark;
+        self.next_mark += 1;
+        self.marks.insert(id, mark);
+        mark
+    }
+
+    fn commit_ref(&self, id: &ObjectHash) -> CommitRef {
+        match self.marks.get(id) {
+            Some(mark) => CommitRef::Mark(*mark),
+            None => CommitRef::Oid(id.to_string()),
+        }
+    }
+
+    /// Commits reachable from `tip` without a mark, parents before children.
+    fn unmarked_commits(&self, tip: ObjectHash) -> Result<Vec<Commit>, String> {
+        let mut loaded: HashMap<ObjectHash, Commit> = HashMap::new();
+        let mut visited = HashSet::new();
+        let mut order = Vec::new();
+        let mut stack = vec![(tip, false)];
+        while let Some((id, expanded)) = stack.pop() {
+            if expanded {
+                if let Some(commit) = loaded.remove(&id) {
+                    order.push(commit);
+                }
+                continue;
+            }
+            if self.marks.contains_key(&id) || !visited.insert(id) {
+                continue;
+            }
+            let commit: Commit =
+                load_object(&id).map_err(|e| format!("failed to load commit {id}: {e}"))?;
+            stack.push((id, true));
+            for parent in commit.parent_commit_ids.iter().rev() {
+                stack.push((*parent, false));
+            }
+            loaded.insert(id, commit);
+        }
+        Ok(order)
+    }
+
+    fn commit(&mut self, ref_name: &str, commit: &Commit) -> Result<(), String> {
+        let write_error = |e: io::Error| format!("failed to write stream: {e}");
+        let parent_tree = match commit.parent_commit_ids.first() {
+            Some(parent) => {
+                let parent: Commit = load_object(parent)
+                    .map_err(|e| format!("failed to load commit {parent}: {e}"))?;
+                Some(parent.tree_id)
+            }
+            None => None,
+        };
+        let mut deletes = Vec::new();
+        let mut modifies = Vec::new();
+        diff_trees(
+            parent_tree.as_ref(),
+            Some(&commit.tree_id),
+            "",
+            &mut deletes,
+            &mut modifies,
+        )
+        .map_err(|e| format!("failed to diff commit {}: {e}", commit.id))?;
+
+        let mut changes: Vec<FileChange> = deletes
+            .into_iter()
+            .map(|path| FileChange::Delete { path })
+            .collect();
+        for (path, mode, id) in modifies {
+            let data = if mode == TreeItemMode::Commit {
+                // Submodule commits are not in this repository; git takes them by id.
+                DataRef::Oid(id.to_string())
+            } else {
+                DataRef::Mark(self.blob(&id)?)
+            };
+            changes.push(FileChange::Modify { mode, data, path });
+        }
+
+        if commit.parent_commit_ids.is_empty() {
+            // Without `from`, fast-import would build on the ref's current tip.
+            self.emit(Command::Reset {
+                ref_name: ref_name.to_string(),
+                from: None,
+            })
+            .map_err(write_error)?;
+        }
+        let (encoding, message) = split_message(&commit.message);
+        let mut parents = commit.parent_commit_ids.iter().map(|p| self.commit_ref(p));
+        let from = parents.next();
+        let merges = parents.collect();
+        let mark = self.new_mark(commit.id);
+        self.emit(Command::Commit(Box::new(CommitCommand {
+            ref_name: ref_name.to_string(),
+            mark: Some(mark),
+            original_oid: None,
+            author: Some(commit.author.clone()),
+            committer: commit.committer.clone(),
+            encoding,
+            message: message.into_bytes(),
+            from,
+            merges,
+            changes,
+        })))
+        .map_err(write_error)?;
+        self.summary.commits += 1;
+        self.tick();
+        Ok(())
+    }
+
+    /// Emits the blob unless it already has a mark, and returns its mark.
+    fn blob(&mut self, id: &ObjectHash) -> Result<u64, String> {
+        if let Some(mark) = self.marks.get(id) {
+            return Ok(*mark);
+        }
+        let blob: Blob = load_object(id).map_err(|e| format!("failed to load blob {id}: {e}"))?;
+        let mark = self.new_mark(*id);
+        self.emit(Command::Blob(BlobCommand {
+            mark: Some(mark),
+            original_oid: None,
+            data: blob.data,
+        }))
+        .map_err(|e| format!("failed to write stream: {e}"))?;
+        self.summary.blobs += 1;
+        self.tick();
+        Ok(mark)
+    }
+
+    fn tag(&mut self, ref_name: &str, tag_object: &GitTag) -> Result<(), String> {
+        let write_error = |e: io::Error| format!("failed to write stream: {e}");
+        let name = ref_name.strip_prefix(TAG_REF_PREFIX).unwrap_or(ref_name);
+        if let Some(mark) = self.marks.get(&tag_object.id) {
+            return self
+                .emit(Command::Reset {
+                    ref_name: ref_name.to_string(),
+                    from: Some(CommitRef::Mark(*mark)),
+                })
+                .map_err(write_error);
+        }
+        let from = self.commit_ref(&tag_object.object_hash);
+        let mark = self.new_mark(tag_object.id);
+        self.emit(Command::Tag(TagCommand {
+            name: name.to_string(),
+            mark: Some(mark),
+            original_oid: None,
+            from,
+            tagger: Some(tag_object.tagger.clone()),
+            message: tag_object.message.clone().into_bytes(),
+        }))
+        .map_err(write_error)?;
+        self.summary.tags += 1;
+        self.tick();
+        Ok(())
+    }
+
+    fn tick(&self) {
+        let summary = &self.summary;
+        self.progress.set_message(format!(
+            "Exporting objects: {} commits, {} blobs, {} tags",
+            summary.commits, summary.blobs, summary.tags
+        ));
+        self.progress.tick();
+    }
+}
+
+/// Splits a stored commit message into its `encoding` header, if any, and the message proper.
+fn split_message(message: &str) -> (Option<String>, String) {
+    if let Some(body) = message.strip_prefix('\n') {
+        return (None, body.to_string());
+    }
+    // Headers libra keeps in front of the message (encoding, signatures) end at a blank line.
+    let Some((headers, body)) = message.split_once("\n\n") else {
+        return (None, message.to_string());
+    };
+    let encoding = headers
+        .lines()
+        .find_map(|line| line.strip_prefix("encoding "))
+        .map(str::to_string);
+    (encoding, body.to_string())
+}
+
+/// Collects the paths deleted and the files added or changed between two trees.
+fn diff_trees(
+    old: Option<&ObjectHash>,
+    new: Option<&ObjectHash>,
+    prefix: &str,
+    deletes: &mut Vec<String>,
+    modifies: &mut Vec<(String, TreeItemMode, ObjectHash)>,
+) -> Result<(), GitError> {
+    if old == new {
+        return Ok(());
+    }
+    let old_items = tree_entries(old)?;
+    let new_items = tree_entries(new)?;
+    let join = |name: &str| {
+        if prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{prefix}/{name}")
+        }
+    };
+
+    for (name, (mode, id)) in &old_items {
+        let path = join(name);
+        match new_items.get(name) {
+            None => deletes.push(path),
+            Some((new_mode, _))
+                if (*mode == TreeItemMode::Tree) != (*new_mode == TreeItemMode::Tree) =>
+            {
+                deletes.push(path)
+            }
+            Some((TreeItemMode::Tree, new_id)) => {
+                diff_trees(Some(id), Some(new_id), &path, deletes, modifies)?
+            }
+            Some(_) => {}
+        }
+    }
+    for (name, (mode, id)) in &new_items {
+        let path = join(name);
+        let old_entry = old_items.get(name);
+        if *mode == TreeItemMode::Tree {
+            let old_tree = old_entry
+                .filter(|(old_mode, _)| *old_mode == TreeItemMode::Tree)
+                .map(|(_, old_id)| old_id);
+            if old_tree.is_none() {
+                diff_trees(None, Some(id), &path, deletes, modifies)?;
+            }
+        } else if old_entry != Some(&(*mode, *id)) {
+            modifies.push((path, *mode, *id));
+        }
+    }
+    Ok(())
+}
+
+fn tree_entries(
+    id: Option<&ObjectHash>,
+) -> Result<BTreeMap<String, (TreeItemMode, ObjectHash)>, GitError> {
+    let Some(id) = id else {
+        return Ok(BTreeMap::new());
+    };
+    if *id == ObjectHash::from_type_and_data(ObjectType::Tree, &[]) {
+        return Ok(BTreeMap::new());
+    }
+    let tree: Tree = load_object(id)?;
+    Ok(tree
+        .tree_items
+        .into_iter()
+        .map(|item| (item.name, (item.mode, item.id)))
+        .collect())
+}
+
+/// Reads a `:<mark> <id>` marks file; a missing file has no marks.
+fn read_marks(path: &Path) -> Result<HashMap<ObjectHash, u64>, String> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(HashMap::new()),
+        Err(e) => return Err(format!("failed to read {}: {e}", path.display())),
+    };
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let parsed = line.split_once(' ').and_then(|(mark, id)| {
+                let mark = mark.strip_prefix(':')?.parse().ok()?;
+                Some((ObjectHash::from_str(id.trim()).ok()?, mark))
+            });
+            parsed.ok_or_else(|| format!("invalid line '{line}' in {}", path.display()))
+        })
+        .collect()
+}
+
+fn write_marks(path: &Path, marks: &HashMap<ObjectHash, u64>) -> Result<(), String> {
+    let sorted: BTreeMap<u64, &ObjectHash> = marks.iter().map(|(id, mark)| (*mark, id)).collect();
+    let content: String = sorted
+        .into_iter()
+        .map(|(mark, id)| format!(":{mark} {id}\n"))
+        .collect();
+    fs::write(path, content).map_err(|e| format!("failed to write {}: {e}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_message_extracts_encoding_header() {
+        assert_eq!(
+            split_message("\nsubject\n\nbody\n"),
+            (None, "subject\n\nbody\n".to_string())
+        );
+        assert_eq!(
+            split_message("encoding ISO-8859-1\n\nsubject\n"),
+            (Some("ISO-8859-1".to_string()), "subject\n".to_string())
+        );
+    }
+}
@@ -4,6 +4,7 @@ use std::{
     collections::HashSet,
     fs, io,
     io::{Error as IoError, Write},
+    str::FromStr,
     time::Instant,
     vec,
 };
@@ -15,7 +16,6 @@ use git_internal::{
     internal::object::commit::Commit,
 };
 use indicatif::ProgressBar;
-use sea_orm::TransactionTrait;
 use tokio::io::{AsyncRead, AsyncReadExt};
 use tokio_util::io::StreamReader;
 use url::Url;
@@ -29,13 +29,13 @@ use crate::{
     internal::{
         branch::Branch,
         config::{Config, RemoteConfig},
-        db::get_db_conn_instance,
         head::Head,
         protocol::{
             DiscoveryResult, FetchStream, ProtocolClient, git_client::GitClient,
             https_client::HttpsClient, local_client::LocalClient, set_wire_hash_kind,
         },
-        reflog::{HEAD, Reflog, ReflogAction, ReflogContext, ReflogError},
+        ref_transaction::{RefName, RefTransaction},
+        reflog::{HEAD, ReflogAction},
     },
     utils::{self, path_ext::PathExt, util},
 };
@@ -340,86 +340,56 @@ pub async fn fetch_repository(
         });
     }
 
-    let db = get_db_conn_instance().await;
-    let transaction_result = db
-        .transaction(|txn| {
-            Box::pin(async move {
-                // 1. Update remote-tracking branches and record reflogs
-                for r in &refs {
-                    let full_ref_name: String;
-
-                    // Determine the full ref name (e.g., "refs/remotes/origin/main")
-                    if let Some(branch_name) = r._ref.strip_prefix("refs/heads/") {
-                        full_ref_name =
-                            format!("refs/remotes/{}/{}", remote_config.name, branch_name);
-                    } else if let Some(mr_name) = r._ref.strip_prefix("refs/mr/") {
-                        // Handle merge requests if your system supports them
-                        full_ref_name =
-                            format!("refs/remotes/{}/mr/{}", remote_config.name, mr_name);
-                    } else {
-                        tracing::warn!("Unsupported ref type during fetch: {}", r._ref);
-                        continue; // Skip unsupported ref types
-                    }
+    // Remote-tracking branches and the remote's HEAD move together or not at all.
+    let mut transaction = RefTransaction::begin();
+    // 1. Update remote-tracking branches and record reflogs
+    for r in &refs {
+        let full_ref_name: String;
+
+        // Determine the full ref name (e.g., "refs/remotes/origin/main")
+        if let Some(branch_name) = r._ref.strip_prefix("refs/heads/") {
+            full_ref_name = format!("refs/remotes/{}/{}", remote_config.name, branch_name);
+        } else if let Some(mr_name) = r._ref.strip_prefix("refs/mr/") {
+            // Handle merge requests if your system supports them
+            full_ref_name = format!("refs/remotes/{}/mr/{}", remote_config.name, mr_name);
+        } else {
+            tracing::warn!("Unsupported ref type during fetch: {}", r._ref);
+            continue; // Skip unsupported ref types
+        }
+        let Ok(new) = ObjectHash::from_str(&r._hash) else {
+            tracing::warn!("Invalid object id for {}: {}", r._ref, r._hash);
+            continue;
+        };
 
-                    // Get the old OID *before* updating the branch
-                    let old_oid = Branch::find_branch_with_conn(
-                        txn,
-                        &full_ref_name,
-                        Some(&remote_config.name),
-                    )
-                    .await
-                    .map_or(ObjectHash::zero_str(get_hash_kind()), |b| {
-                        b.commit.to_string()
-                    });
-
-                    // Update the branch pointer
-                    Branch::update_branch_with_conn(
-                        txn,
-                        &full_ref_name,
-                        &r._hash,
-                        Some(&remote_config.name),
-                    )
-                    .await;
-
-                    // Prepare and insert the reflog entry for this specific remote-tracking branch
-                    let context = ReflogContext {
-                        old_oid: old_oid.to_string(),
-                        new_oid: r._hash.clone(),
-                        action: ReflogAction::Fetch, // Using a simple Fetch action
-                    };
-                    Reflog::insert_single_entry(txn, &context, &full_ref_name).await?;
-                }
+        // Only move the branch from where it was when the fetch started.
+        let name = RefName::remote_branch(remote_config.name.as_str(), full_ref_name.as_str());
+        match Branch::find_branch(&full_ref_name, Some(&remote_config.name)).await {
+            Some(old) => transaction.update(name, new, Some(old.commit), Some(ReflogAction::Fetch)),
+            None => transaction.create(name, new, Some(ReflogAction::Fetch)),
+        };
+    }
 
-                // 2. Update the remote's HEAD pointer
-                if let Some(remote_head) = remote_head {
-                    if let Some(remote_head_ref) =
-                        ref_heads.iter().find(|r| r._hash == remote_head._hash)
-                    {
-                        if let Some(remote_head_branch) =
-                            remote_head_ref._ref.strip_prefix("refs/heads/")
-                        {
-                            // This updates `refs/remotes/origin/HEAD`
-                            Head::update_with_conn(
-                                txn,
-                                Head::Branch(remote_head_branch.to_owned()),
-                                Some(&remote_config.name),
-                            )
-                            .await;
-                        }
-                    } else if branch.is_none() {
-                        eprintln!("remote HEAD not found");
-                    } else {
-                        tracing::debug!("Specified branch not found in remote HEAD");
-                    }
-                } else {
-                    tracing::warn!("fetch empty, remote HEAD not found");
-                }
-                Ok::<_, ReflogError>(())
-            })
-        })
-        .await;
+    // 2. Update the remote's HEAD pointer
+    if let Some(remote_head) = remote_head {
+        if let Some(remote_head_ref) = ref_heads.iter().find(|r| r._hash == remote_head._hash) {
+            if let Some(remote_head_branch) = remote_head_ref._ref.strip_prefix("refs/heads/") {
+                // This updates `refs/remotes/origin/HEAD`
+                transaction.set_head(
+                    Head::Branch(remote_head_branch.to_owned()),
+                    Some(&remote_config.name),
+                    None,
+                );
+            }
+        } else if branch.is_none() {
+            eprintln!("remote HEAD not found");
+        } else {
+            tracing::debug!("Specified branch not found in remote HEAD");
+        }
+    } else {
+        tracing::warn!("fetch empty, remote HEAD not found");
+    }
 
-    if let Err(e) = transaction_result {
+    if let Err(e) = transaction.commit().await {
         eprintln!("fatal: failed to update references after fetch: {}", e);
     }
 }
@@ -0,0 +1,712 @@
+//! Implements `filter-repo`, a lightweight history rewrite over every commit reachable from a
+//! branch, a tag or a detached HEAD.
+//!
+//! Filters:
+//! - `--path-remove <pathspec>` drops matching entries (a file, or a directory with everything
+//!   below it) from every tree. Commits left with no changes are dropped unless `--keep-empty`.
+//! - `--replace-author "Old <old@x>:New <new@y>"` rewrites author and committer identities.
+//!   Leaving the old name empty (`"<old@x>:New <new@y>"`) matches on the email alone.
+//! - `--message-filter "<regex>==><replacement>"` rewrites commit messages.
+//!
+//! Commits are rewritten parents-first, building an old→new id map that is used for the
+//! parents of later commits and, at the end, to move every branch and tag in a single
+//! [`RefTransaction`], so a failure leaves every ref where it was. The map is written to
+//! `.libra/filter-map` (`<old> <new>` per line, all zeros for a dropped commit). The index and
+//! work tree are then reset to the rewritten HEAD, which is why a dirty work tree is refused
+//! without `--force`.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use clap::Parser;
+use git_internal::{
+    errors::GitError,
+    hash::ObjectHash,
+    internal::object::{
+        ObjectTrait,
+        commit::Commit,
+        signature::Signature,
+        tag::Tag as GitTag,
+        tree::{Tree, TreeItem, TreeItemMode},
+        types::ObjectType,
+    },
+};
+use indicatif::{ProgressBar, ProgressStyle};
+use regex::Regex;
+
+use crate::{
+    command::{load_object, reset, save_object, status},
+    internal::{
+        branch::Branch,
+        head::Head,
+        protect::{self, ProtectionRule},
+        ref_transaction::{RefName, RefTransaction},
+        reflog::ReflogAction,
+        tag::{self, TagObject},
+    },
+    utils::util,
+};
+
+/// File under the storage directory that receives the old→new commit map.
+pub const FILTER_MAP_FILE: &str = "filter-map";
+
+/// Separator between the pattern and the replacement of `--message-filter`.
+const MESSAGE_FILTER_SEPARATOR: &str = "==>";
+
+#[derive(Parser, Debug, Default)]
+pub struct FilterRepoArgs {
+    /// Remove a path (file or directory) from every commit. Can be repeated
+    #[clap(long = "path-remove", value_name = "PATHSPEC")]
+    pub path_remove: Vec<String>,
+
+    /// Replace an identity in authors and committers, as "Old <old@x>:New <new@y>". Can be repeated
+    #[clap(long = "replace-author", value_name = "OLD:NEW")]
+    pub replace_author: Vec<String>,
+
+    /// Rewrite commit messages, as "<regex>==><replacement>". Can be repeated
+    #[clap(long = "message-filter", value_name = "REGEX==>REPLACEMENT")]
+    pub message_filter: Vec<String>,
+
+    /// Keep commits that become empty after --path-remove
+    #[clap(long)]
+    pub keep_empty: bool,
+
+    /// Rewrite even if the work tree has uncommitted changes
+    #[clap(short, long)]
+    pub force: bool,
+
+    /// Only report what would be rewritten
+    #[clap(short = 'n', long)]
+    pub dry_run: bool,
+
+    /// Rewrite protected branches too (see `branch.<name>.protected`)
+    #[clap(long)]
+    pub override_protection: bool,
+}
+
+/// An identity given to `--replace-author`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Identity {
+    name: String,
+    email: String,
+}
+
+impl Identity {
+    fn parse(spec: &str) -> Result<Self, String> {
+        let spec = spec.trim();
+        let start = spec.find('<');
+        let end = spec.rfind('>');
+        match (start, end) {
+            (Some(start), Some(end)) if start < end && end == spec.len() - 1 => Ok(Identity {
+                name: spec[..start].trim().to_string(),
+                email: spec[start + 1..end].trim().to_string(),
+            }),
+            _ => Err(format!(
+                "invalid identity '{spec}', expected 'Name <email>'"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct AuthorReplacement {
+    old: Identity,
+    new: Identity,
+}
+
+impl AuthorReplacement {
+    fn parse(spec: &str) -> Result<Self, String> {
+        let (old, new) = spec
+            .split_once(">:")
+            .ok_or_else(|| format!("invalid --replace-author '{spec}', expected 'OLD:NEW'"))?;
+        let replacement = AuthorReplacement {
+            old: Identity::parse(&format!("{old}>"))?,
+            new: Identity::parse(new)?,
+        };
+        if replacement.new.name.is_empty() {
+            return Err(format!(
+                "invalid --replace-author '{spec}', new name is empty"
+            ));
+        }
+        Ok(replacement)
+    }
+
+    fn matches(&self, signature: &Signature) -> bool {
+        signature.email == self.old.email
+            && (self.old.name.is_empty() || signature.name == self.old.name)
+    }
+}
+
+/// The parsed filters of one run.
+#[derive(Debug)]
+struct Filters {
+    path_remove: Vec<PathBuf>,
+    authors: Vec<AuthorReplacement>,
+    messages: Vec<(Regex, String)>,
+    keep_empty: bool,
+}
+
+impl Filters {
+    fn from_args(args: &FilterRepoArgs) -> Result<Self, String> {
+        let path_remove = args
+            .path_remove
+            .iter()
+            .map(|spec| {
+                let spec = spec.trim_start_matches("./").trim_end_matches('/');
+                if spec.is_empty() {
+                    Err("empty --path-remove pathspec".to_string())
+                } else {
+                    Ok(PathBuf::from(spec))
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let authors = args
+            .replace_author
+            .iter()
+            .map(|spec| AuthorReplacement::parse(spec))
+            .collect::<Result<Vec<_>, _>>()?;
+        let messages = args
+            .message_filter
+            .iter()
+            .map(|spec| {
+                let (pattern, replacement) =
+                    spec.split_once(MESSAGE_FILTER_SEPARATOR).ok_or_else(|| {
+                        format!(
+                            "invalid --message-filter '{spec}', expected '<regex>{MESSAGE_FILTER_SEPARATOR}<replacement>'"
+                        )
+                    })?;
+                let regex = Regex::new(pattern)
+                    .map_err(|e| format!("invalid --message-filter regex '{pattern}': {e}"))?;
+                Ok((regex, replacement.to_string()))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        if path_remove.is_empty() && authors.is_empty() && messages.is_empty() {
+            return Err(
+                "no filters given (use --path-remove, --replace-author or --message-filter)"
+                    .to_string(),
+            );
+        }
+        Ok(Filters {
+            path_remove,
+            authors,
+            messages,
+            keep_empty: args.keep_empty,
+        })
+    }
+
+    fn removes(&self, path: &Path) -> bool {
+        self.path_remove.iter().any(|spec| path.starts_with(spec))
+    }
+
+    /// Whether any removed path lies below `dir`, i.e. whether its tree needs to be visited.
+    fn reaches_into(&self, dir: &Path) -> bool {
+        self.path_remove.iter().any(|spec| spec.starts_with(dir))
+    }
+
+    fn signature(&self, signature: &Signature) -> Signature {
+        let mut signature = signature.clone();
+        if let Some(replacement) = self.authors.iter().find(|r| r.matches(&signature)) {
+            signature.name = replacement.new.name.clone();
+            signature.email = replacement.new.email.clone();
+        }
+        signature
+    }
+
+    fn message(&self, message: &str) -> String {
+        self.messages
+            .iter()
+            .fold(message.to_string(), |message, (regex, replacement)| {
+                regex
+                    .replace_all(&message, replacement.as_str())
+                    .into_owned()
+            })
+    }
+}
+
+/// A ref moved (or removed, when `new` is `None`) by the rewrite.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RefUpdate {
+    pub name: String,
+    pub old: ObjectHash,
+    pub new: Option<ObjectHash>,
+}
+
+/// What a rewrite did, or would do with `--dry-run`.
+#[derive(Debug, Default)]
+pub struct FilterSummary {
+    /// Commits visited, i.e. reachable from any branch, tag or HEAD.
+    pub total: usize,
+    /// Commits that got a new id.
+    pub rewritten: usize,
+    /// Commits dropped because they became empty.
+    pub dropped: usize,
+    /// Old→new commit ids, `None` for dropped commits, in rewrite order.
+    pub map: Vec<(ObjectHash, Option<ObjectHash>)>,
+    pub refs: Vec<RefUpdate>,
+}
+
+/// A ref tip the rewrite has to move afterwards.
+enum RefTip {
+    Branch(String),
+    LightweightTag(String),
+    AnnotatedTag(String, Box<GitTag>),
+    DetachedHead,
+}
+
+impl RefTip {
+    fn display_name(&self) -> String {
+        match self {
+            RefTip::Branch(name) => format!("refs/heads/{name}"),
+            RefTip::LightweightTag(name) | RefTip::AnnotatedTag(name, _) => {
+                format!("refs/tags/{name}")
+            }
+            RefTip::DetachedHead => "HEAD".to_string(),
+        }
+    }
+}
+
+pub async fn execute(args: FilterRepoArgs) {
+    if !util::check_repo_exist() {
+        return;
+    }
+    if let Err(e) = execute_checked(args).await {
+        eprintln!("fatal: {e}");
+    }
+}
+
+/// Runs the rewrite and prints its summary, returning errors instead of printing them.
+pub async fn execute_checked(args: FilterRepoArgs) -> Result<FilterSummary, String> {
+    let filters = Filters::from_args(&args)?;
+
+    let dirty = !status::changes_to_be_committed().await.is_empty() || {
+        let unstaged = status::changes_to_be_staged();
+        !unstaged.modified.is_empty() || !unstaged.deleted.is_empty()
+    };
+    if dirty && !args.force && !args.dry_run {
+        return Err(
+            "refusing to rewrite history with uncommitted changes (use --force to override)"
+                .to_string(),
+        );
+    }
+
+    let old_head = Head::current_commit().await;
+    let summary = rewrite_history(&filters, !args.dry_run, args.override_protection).await?;
+
+    let verb = if args.dry_run {
+        "Would rewrite"
+    } else {
+        "Rewrote"
+    };
+    println!(
+        "{verb} {} of {} commits ({} dropped as empty)",
+        summary.rewritten, summary.total, summary.dropped
+    );
+    for update in &summary.refs {
+        match update.new {
+            Some(new) => println!(
+                "  {}: {} -> {}",
+                update.name,
+                short(&update.old),
+                short(&new)
+            ),
+            None => println!("  {}: {} -> (deleted)", update.name, short(&update.old)),
+        }
+    }
+    if args.dry_run {
+        return Ok(summary);
+    }
+
+    write_filter_map(&summary.map)?;
+    println!(
+        "Updated {} refs; commit map written to {}",
+        summary.refs.len(),
+        Path::new(".libra").join(FILTER_MAP_FILE).display()
+    );
+
+    let new_head = Head::current_commit().await;
+    match (old_head, new_head) {
+        (Some(old), Some(new)) if old != new => {
+            if dirty {
+                println!(
+                    "hint: the work tree still holds your uncommitted changes; run 'libra reset --hard' to check out the rewritten HEAD"
+                );
+            } else {
+                reset::reset_index_to_commit(&new)?;
+                reset::reset_working_directory_to_commit(&new, Some(old)).await?;
+            }
+        }
+        (Some(_), None) => {
+            eprintln!("warning: HEAD no longer points to a commit; the work tree was left as is")
+        }
+        _ => {}
+    }
+    Ok(summary)
+}
+
+/// Rewrites every reachable commit and moves the refs; only hashes when `write` is false.
+/// Rewriting a protected branch fails unless `override_protection` is set, before any ref moves.
+async fn rewrite_history(
+    filters: &Filters,
+    write: bool,
+    override_protection: bool,
+) -> Result<FilterSummary, String> {
+    let tips = collect_tips().await?;
+    let commits = topo_order(tips.iter().map(|(_, id)| *id))
+        .map_err(|e| format!("failed to walk history: {e}"))?;
+
+    let progress = ProgressBar::new(commits.len() as u64);
+    progress.set_style(
+        ProgressStyle::default_bar()
+            .template("{spinner:.magenta} Rewriting commits [{bar:40.green/white}] {pos}/{len}")
+            .unwrap()
+            .progress_chars("=> "),
+    );
+
+    let mut rewriter = TreeRewriter {
+        filters,
+        write,
+        cache: HashMap::new(),
+    };
+    let empty_tree = empty_tree_id();
+    let mut summary = FilterSummary {
+        total: commits.len(),
+        ..Default::default()
+    };
+    let mut map: HashMap<ObjectHash, Option<ObjectHash>> = HashMap::new();
+    let mut old_trees: HashMap<ObjectHash, ObjectHash> = HashMap::new();
+    let mut new_trees: HashMap<ObjectHash, ObjectHash> = HashMap::new();
+
+    for commit in &commits {
+        progress.inc(1);
+        old_trees.insert(commit.id, commit.tree_id);
+
+        let mut parents = Vec::new();
+        for parent in &commit.parent_commit_ids {
+            if let Some(Some(new_parent)) = map.get(parent)
+                && !parents.contains(new_parent)
+            {
+                parents.push(*new_parent);
+            }
+        }
+        let tree_id = rewriter
+            .rewrite(&commit.tree_id, Path::new(""))
+            .map_err(|e| format!("failed to rewrite tree of {}: {e}", commit.id))?;
+
+        if !filters.path_remove.is_empty() && !filters.keep_empty && parents.len() <= 1 {
+            let new_base = parents.first().map_or(empty_tree, |p| new_trees[p]);
+            let old_base = commit
+                .parent_commit_ids
+                .first()
+                .map_or(empty_tree, |p| old_trees[p]);
+            // Commits that were already empty are kept, only newly emptied ones are dropped.
+            if tree_id == new_base && commit.tree_id != old_base {
+                let replacement = parents.first().copied();
+                map.insert(commit.id, replacement);
+                summary.map.push((commit.id, None));
+                summary.dropped += 1;
+                continue;
+            }
+        }
+
+        let author = filters.signature(&commit.author);
+        let committer = filters.signature(&commit.committer);
+        let message = filters.message(&commit.message);
+        let unchanged = tree_id == commit.tree_id
+            && parents == commit.parent_commit_ids
+            && author == commit.author
+            && committer == commit.committer
+            && message == commit.message;
+        let new_id = if unchanged {
+            commit.id
+        } else {
+            let new_commit = Commit::new(author, committer, tree_id, parents, &message);
+            if write {
+                save_object(&new_commit, &new_commit.id)
+                    .map_err(|e| format!("failed to save commit: {e}"))?;
+            }
+            summary.rewritten += 1;
+            new_commit.id
+        };
+        map.insert(commit.id, Some(new_id));
+        new_trees.insert(new_id, tree_id);
+        summary.map.push((commit.id, Some(new_id)));
+    }
+    progress.finish_and_clear();
+
+    // All refs move in one transaction, so a failure leaves none of them rewritten.
+    let mut transaction = RefTransaction::begin();
+    for (tip, old) in &tips {
+        let new = map.get(old).copied().flatten();
+        if new == Some(*old) {
+            continue;
+        }
+        let name = tip.display_name();
+        if write {
+            let protection = match tip {
+                RefTip::Branch(branch) => protect::check(branch, "rewrite", override_protection)
+                    .await
+                    .map_err(|e| e.to_string())?,
+                _ => None,
+            };
+            queue_ref_update(&mut transaction, tip, *old, new, protection.as_ref())
+                .map_err(|e| format!("failed to update {name}: {e}"))?;
+        }
+        summary.refs.push(RefUpdate {
+            name,
+            old: *old,
+            new,
+        });
+    }
+    if write {
+        transaction
+            .commit()
+            .await
+            .map_err(|e| format!("failed to update refs: {e}"))?;
+    }
+    Ok(summary)
+}
+
+/// Collects the refs to rewrite together with the commit each one points to.
+async fn collect_tips() -> Result<Vec<(RefTip, ObjectHash)>, String> {
+    let mut tips = Vec::new();
+    for branch in Branch::list_branches(None).await {
+        tips.push((RefTip::Branch(branch.name), branch.commit));
+    }
+    for tag in tag::list().await.map_err(|e| e.to_string())? {
+        match tag.object {
+            TagObject::Commit(commit) => tips.push((RefTip::LightweightTag(tag.name), commit.id)),
+            TagObject::Tag(tag_object) if tag_object.object_type == ObjectType::Commit => {
+                let target = tag_object.object_hash;
+                tips.push((RefTip::AnnotatedTag(tag.name, Box::new(tag_object)), target));
+            }
+            _ => eprintln!(
+                "warning: tag '{}' does not point to a commit; left as is",
+                tag.name
+            ),
+        }
+    }
+    if let Head::Detached(commit) = Head::current().await {
+        tips.push((RefTip::DetachedHead, commit));
+    }
+    Ok(tips)
+}
+
+/// Queues moving one ref from `old` to its rewritten commit, or deleting it when nothing is
+/// left. A rewritten annotated tag gets a new tag object, saved right away.
+fn queue_ref_update(
+    transaction: &mut RefTransaction,
+    tip: &RefTip,
+    old: ObjectHash,
+    new: Option<ObjectHash>,
+    protection: Option<&ProtectionRule>,
+) -> Result<(), String> {
+    // Only an overridden protected branch gets a reflog entry, to record the override.
+    let reflog = protection.map(|rule| ReflogAction::FilterRepo {
+        details: protect::annotate("rewrite history", Some(rule)),
+    });
+    match (tip, new) {
+        (RefTip::Branch(name), Some(new)) => {
+            transaction.update(RefName::branch(name.as_str()), new, Some(old), reflog);
+        }
+        (RefTip::Branch(name), None) => {
+            transaction.delete(RefName::branch(name.as_str()), Some(old), reflog);
+        }
+        (RefTip::LightweightTag(name), Some(new)) => {
+            transaction.update(RefName::tag(name.as_str()), new, Some(old), None);
+        }
+        (RefTip::AnnotatedTag(name, tag_object), Some(new)) => {
+            let rewritten = GitTag::new(
+                new,
+                ObjectType::Commit,
+                tag_object.tag_name.clone(),
+                tag_object.tagger.clone(),
+                tag_object.message.clone(),
+            );
+            save_object(&rewritten, &rewritten.id).map_err(|e| e.to_string())?;
+            transaction.update(
+                RefName::tag(name.as_str()),
+                rewritten.id,
+                Some(tag_object.id),
+                None,
+            );
+        }
+        (RefTip::LightweightTag(name), None) => {
+            transaction.delete(RefName::tag(name.as_str()), Some(old), None);
+        }
+        (RefTip::AnnotatedTag(name, tag_object), None) => {
+            transaction.delete(RefName::tag(name.as_str()), Some(tag_object.id), None);
+        }
+        (RefTip::DetachedHead, Some(new)) => {
+            transaction.set_head(Head::Detached(new), None, None);
+        }
+        (RefTip::DetachedHead, None) => {}
+    }
+    Ok(())
+}
+
+/// Orders every commit reachable from `tips` so that parents come before their children.
+fn topo_order(tips: impl Iterator<Item = ObjectHash>) -> Result<Vec<Commit>, GitError> {
+    let mut loaded: HashMap<ObjectHash, Commit> = HashMap::new();
+    let mut visited = HashSet::new();
+    let mut order = Vec::new();
+    let mut stack: Vec<(ObjectHash, bool)> = tips.map(|id| (id, false)).collect();
+    stack.reverse();
+
+    while let Some((id, expanded)) = stack.pop() {
+        if expanded {
+            if let Some(commit) = loaded.remove(&id) {
+                order.push(commit);
+            }
+            continue;
+        }
+        if !visited.insert(id) {
+            continue;
+        }
+        let commit: Commit = load_object(&id)?;
+        stack.push((id, true));
+        for parent in commit.parent_commit_ids.iter().rev() {
+            if !visited.contains(parent) {
+                stack.push((*parent, false));
+            }
+        }
+        loaded.insert(id, commit);
+    }
+    Ok(order)
+}
+
+/// Rewrites trees for `--path-remove`, caching results per tree and directory.
+struct TreeRewriter<'a> {
+    filters: &'a Filters,
+    write: bool,
+    cache: HashMap<(ObjectHash, PathBuf), ObjectHash>,
+}
+
+impl TreeRewriter<'_> {
+    fn rewrite(&mut self, tree_id: &ObjectHash, dir: &Path) -> Result<ObjectHash, GitError> {
+        if self.filters.path_remove.is_empty() {
+            return Ok(*tree_id);
+        }
+        let key = (*tree_id, dir.to_path_buf());
+        if let Some(id) = self.cache.get(&key) {
+            return Ok(*id);
+        }
+
+        let tree: Tree = load_object(tree_id)?;
+        let mut items = Vec::with_capacity(tree.tree_items.len());
+        for item in &tree.tree_items {
+            let path = dir.join(&item.name);
+            if self.filters.removes(&path) {
+                continue;
+            }
+            if item.mode == TreeItemMode::Tree && self.filters.reaches_into(&path) {
+                let id = self.rewrite(&item.id, &path)?;
+                // Directories emptied by the filter disappear, as they would in git.
+                if id != empty_tree_id() {
+                    items.push(TreeItem::new(item.mode, id, item.name.clone()));
+                }
+            } else {
+                items.push(item.clone());
+            }
+        }
+
+        let new_id = if items == tree.tree_items {
+            *tree_id
+        } else {
+            let new_tree = if items.is_empty() {
+                Tree::from_bytes(&[], empty_tree_id())?
+            } else {
+                Tree::from_tree_items(items)?
+            };
+            if self.write {
+                save_object(&new_tree, &new_tree.id)?;
+            }
+            new_tree.id
+        };
+        self.cache.insert(key, new_id);
+        Ok(new_id)
+    }
+}
+
+fn empty_tree_id() -> ObjectHash {
+    ObjectHash::from_type_and_data(ObjectType::Tree, &[])
+}
+
+fn short(id: &ObjectHash) -> String {
+    id.to_string()[..7].to_string()
+}
+
+/// Writes `.libra/filter-map`: a header line, then `<old> <new>` per commit.
+fn write_filter_map(map: &[(ObjectHash, Option<ObjectHash>)]) -> Result<(), String> {
+    let mut content = String::from("old new\n");
+    for (old, new) in map {
+        let old = old.to_string();
+        let new = new.map_or_else(|| "0".repeat(old.len()), |new| new.to_string());
+        content.push_str(&format!("{old} {new}\n"));
+    }
+    let path = util::storage_path().join(FILTER_MAP_FILE);
+    fs::write(&path, content).map_err(|e| format!("failed to write {}: {e}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_author_replacement() {
+        let r = AuthorReplacement::parse("Jon Doe <jon@exmaple.com>:Jon Doe <jon@example.com>")
+            .unwrap();
+        assert_eq!(r.old.name, "Jon Doe");
+        assert_eq!(r.old.email, "jon@exmaple.com");
+        assert_eq!(r.new.email, "jon@example.com");
+
+        let email_only = AuthorReplacement::parse("<old@x>:New <new@y>").unwrap();
+        assert!(email_only.old.name.is_empty());
+        assert!(AuthorReplacement::parse("old@x:new@y").is_err());
+        assert!(AuthorReplacement::parse("<old@x>:<new@y>").is_err());
+    }
+
+    #[test]
+    fn test_filters_require_at_least_one_filter() {
+        let err = Filters::from_args(&FilterRepoArgs::default()).unwrap_err();
+        assert!(err.contains("no filters given"));
+
+        let args = FilterRepoArgs {
+            message_filter: vec!["no separator".into()],
+            ..Default::default()
+        };
+        assert!(Filters::from_args(&args).is_err());
+    }
+
+    #[test]
+    fn test_path_remove_matches_whole_components() {
+        let args = FilterRepoArgs {
+            path_remove: vec!["./secrets/".into(), "key.pem".into()],
+            ..Default::default()
+        };
+        let filters = Filters::from_args(&args).unwrap();
+        assert!(filters.removes(Path::new("secrets")));
+        assert!(filters.removes(Path::new("secrets/a.txt")));
+        assert!(filters.removes(Path::new("key.pem")));
+        assert!(!filters.removes(Path::new("secrets2/a.txt")));
+        assert!(!filters.removes(Path::new("src/key.pem")));
+        assert!(filters.reaches_into(Path::new("")));
+        assert!(!filters.reaches_into(Path::new("src")));
+    }
+
+    #[test]
+    fn test_message_filter_replaces_every_match() {
+        let args = FilterRepoArgs {
+            message_filter: vec![r"TICKET-(\d+)==>#$1".into()],
+            ..Default::default()
+        };
+        let filters = Filters::from_args(&args).unwrap();
+        assert_eq!(
+            filters.message("\nfix TICKET-1 and TICKET-22\n"),
+            "\nfix #1 and #22\n"
+        );
+    }
+}
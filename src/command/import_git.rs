@@ -0,0 +1,864 @@
+//! Implements `import-git`, which converts the history of a local Git repository into libra
+//! objects.
+//!
+//! The source repository is read through `git fast-export`, whose stream is parsed and converted
+//! one command at a time by [`fast_import::StreamReader`], so large repositories are never held
+//! in memory. `--stream <file>` imports an existing fast-export stream instead, without needing
+//! git at all.
+//!
+//! Blobs, trees, commits and annotated tags are written in the same format git uses, so their ids
+//! are preserved. Where an id still differs (for example a commit whose signature was stripped by
+//! fast-export), the old→new pair is appended to `.libra/import-git/map`.
+//!
+//! Branches and tags from the stream are recreated and HEAD follows the source repository's HEAD.
+//! The marks of both sides are kept under `.libra/import-git/`, so a later run only exports and
+//! converts what is new; objects that already exist are skipped either way.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fs,
+    io::{self, BufRead, BufReader},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    str::FromStr,
+};
+
+use clap::Parser;
+use git_internal::{
+    errors::GitError,
+    hash::ObjectHash,
+    internal::object::{
+        ObjectTrait,
+        blob::Blob,
+        commit::Commit,
+        signature::{Signature, SignatureType},
+        tag::Tag as GitTag,
+        tree::{Tree, TreeItem, TreeItemMode},
+        types::ObjectType,
+    },
+};
+use indicatif::ProgressBar;
+
+use crate::{
+    command::{load_object, reset, save_object, status},
+    internal::{branch::Branch, head::Head, tag},
+    utils::{
+        client_storage::ClientStorage,
+        fast_import::{
+            BlobCommand, CommitCommand, CommitRef, DataRef, FileChange, StreamReader, TagCommand,
+        },
+        util,
+    },
+};
+
+/// Directory under the storage directory that holds the marks and the id map.
+pub const IMPORT_DIR: &str = "import-git";
+/// Marks of the libra objects created from the stream, as `:<mark> <id>` lines.
+const MARKS_FILE: &str = "marks";
+/// Marks file maintained by `git fast-export` for incremental exports.
+const GIT_MARKS_FILE: &str = "git-marks";
+/// Original ids that were imported under a different id, as `<old> <new>` lines.
+const MAP_FILE: &str = "map";
+
+const BRANCH_REF_PREFIX: &str = "refs/heads/";
+const TAG_REF_PREFIX: &str = "refs/tags/";
+
+#[derive(Parser, Debug)]
+pub struct ImportGitArgs {
+    /// The Git repository to import, either a work tree or a bare repository
+    #[clap(value_name = "GIT_REPO", required_unless_present = "stream")]
+    pub source: Option<PathBuf>,
+
+    /// Import a `git fast-export` stream from a file ("-" for stdin) instead of running git
+    #[clap(long, value_name = "FILE", conflicts_with = "source")]
+    pub stream: Option<PathBuf>,
+}
+
+/// What an import did.
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    pub commits: usize,
+    pub blobs: usize,
+    pub tags: usize,
+    /// Objects that already existed, from an earlier import or another source.
+    pub skipped: usize,
+    /// Commits and tags whose libra id differs from their original id.
+    pub remapped: usize,
+    /// Refs created or moved, with their new target.
+    pub refs: Vec<(String, ObjectHash)>,
+}
+
+pub async fn execute(args: ImportGitArgs) {
+    if !util::check_repo_exist() {
+        return;
+    }
+    if let Err(e) = execute_checked(args).await {
+        eprintln!("fatal: {e}");
+    }
+}
+
+/// Runs the import and prints its summary, returning errors instead of printing them.
+pub async fn execute_checked(args: ImportGitArgs) -> Result<ImportSummary, String> {
+    let import_dir = util::storage_path().join(IMPORT_DIR);
+    fs::create_dir_all(&import_dir)
+        .map_err(|e| format!("failed to create {}: {e}", import_dir.display()))?;
+
+    let old_head = Head::current_commit().await;
+    // Checked up front: once the branches move, the work tree looks dirty against them.
+    let dirty = old_head.is_some() && {
+        let unstaged = status::changes_to_be_staged();
+        !status::changes_to_be_committed().await.is_empty()
+            || !unstaged.modified.is_empty()
+            || !unstaged.deleted.is_empty()
+    };
+    let mut importer = Importer::load(&import_dir).await?;
+
+    let source_head = match (&args.stream, &args.source) {
+        (Some(stream), _) => {
+            if stream == Path::new("-") {
+                importer.import(io::stdin().lock()).await?;
+            } else {
+                let file = fs::File::open(stream)
+                    .map_err(|e| format!("failed to open {}: {e}", stream.display()))?;
+                importer.import(BufReader::new(file)).await?;
+            }
+            None
+        }
+        (None, Some(source)) => {
+            import_from_git(&mut importer, source, &import_dir).await?;
+            source_head_branch(source)
+        }
+        (None, None) => return Err("no Git repository or --stream given".to_string()),
+    };
+
+    importer.save(&import_dir)?;
+    let summary = importer.update_refs().await?;
+    if let Some(branch) = choose_head(source_head, &summary).await
+        && !matches!(Head::current().await, Head::Branch(current) if current == branch)
+    {
+        Head::update(Head::Branch(branch), None).await;
+    }
+
+    println!(
+        "Imported {} commits, {} blobs and {} tags ({} already present)",
+        summary.commits, summary.blobs, summary.tags, summary.skipped
+    );
+    if summary.remapped > 0 {
+        println!(
+            "{} objects got a new id; the mapping is in {}",
+            summary.remapped,
+            Path::new(".libra")
+                .join(IMPORT_DIR)
+                .join(MAP_FILE)
+                .display()
+        );
+    }
+    for (name, id) in &summary.refs {
+        println!("  {name} -> {}", &id.to_string()[..7]);
+    }
+
+    let new_head = Head::current_commit().await;
+    if let Some(new) = new_head
+        && old_head != new_head
+    {
+        if dirty {
+            println!(
+                "hint: the work tree has uncommitted changes; run 'libra reset --hard' to check out the imported HEAD"
+            );
+        } else {
+            reset::reset_working_directory_to_commit(&new, old_head).await?;
+            reset::reset_index_to_commit(&new)?;
+        }
+    }
+    Ok(summary)
+}
+
+/// Streams `git fast-export` of every branch and tag of `source` into the importer.
+async fn import_from_git(
+    importer: &mut Importer,
+    source: &Path,
+    import_dir: &Path,
+) -> Result<(), String> {
+    let git_marks = import_dir.join(GIT_MARKS_FILE);
+    // fast-export writes its marks even when the import fails, so they only replace the
+    // previous marks once everything has been converted.
+    let new_git_marks = import_dir.join(format!("{GIT_MARKS_FILE}.new"));
+
+    let mut command = Command::new("git");
+    command
+        .arg("-C")
+        .arg(source)
+        .args([
+            "fast-export",
+            "--show-original-ids",
+            "--signed-tags=strip",
+            "--tag-of-filtered-object=drop",
+            "--reencode=no",
+            "--mark-tags",
+            "--use-done-feature",
+        ])
+        .arg(format!("--export-marks={}", new_git_marks.display()));
+    if git_marks.exists() {
+        command.arg(format!("--import-marks={}", git_marks.display()));
+    }
+    command
+        .args(["--branches", "--tags"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    let mut child = command
+        .spawn()
+        .map_err(|e| format!("failed to run git fast-export: {e}"))?;
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let result = importer.import(BufReader::new(stdout)).await;
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("failed to run git fast-export: {e}"))?;
+    if !output.status.success() {
+        let _ = fs::remove_file(&new_git_marks);
+        return Err(format!(
+            "git fast-export failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    if let Err(e) = result {
+        let _ = fs::remove_file(&new_git_marks);
+        return Err(e);
+    }
+    fs::rename(&new_git_marks, &git_marks)
+        .map_err(|e| format!("failed to save {}: {e}", git_marks.display()))
+}
+
+/// The branch HEAD points to in the source repository, if it is on a branch.
+fn source_head_branch(source: &Path) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(source)
+        .args(["symbolic-ref", "--quiet", "--short", "HEAD"])
+        .output()
+        .ok()?;
+    let branch = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    (output.status.success() && !branch.is_empty()).then_some(branch)
+}
+
+/// Picks the branch for HEAD: the source's HEAD, else the current branch if it exists, else
+/// `main`, `master` or the first imported branch.
+async fn choose_head(source_head: Option<String>, summary: &ImportSummary) -> Option<String> {
+    let mut candidates = Vec::new();
+    candidates.extend(source_head);
+    if let Head::Branch(current) = Head::current().await {
+        candidates.push(current);
+    }
+    candidates.extend(["main".to_string(), "master".to_string()]);
+    candidates.extend(
+        summary
+            .refs
+            .iter()
+            .filter_map(|(name, _)| name.strip_prefix(BRANCH_REF_PREFIX).map(str::to_string)),
+    );
+    for candidate in candidates {
+        if Branch::find_branch(&candidate, None).await.is_some() {
+            return Some(candidate);
+        }
+    }
+    None
+}
+
+/// Converts fast-export commands into libra objects.
+struct Importer {
+    storage: ClientStorage,
+    marks: HashMap<u64, ObjectHash>,
+    /// Original id → libra id, for ids that differ.
+    map: HashMap<String, ObjectHash>,
+    new_map_entries: Vec<(String, ObjectHash)>,
+    /// Ref tips as the stream sees them; `None` after a `reset` without `from`.
+    refs: HashMap<String, Option<ObjectHash>>,
+    /// Refs named by the stream, in the order they should be updated.
+    touched: BTreeSet<String>,
+    /// The tree of the last commit, kept so consecutive commits only rebuild what changed.
+    current: Option<(ObjectHash, DirNode)>,
+    summary: ImportSummary,
+    progress: ProgressBar,
+}
+
+impl Importer {
+    async fn load(import_dir: &Path) -> Result<Self, String> {
+        let marks = read_pairs(&import_dir.join(MARKS_FILE))?
+            .into_iter()
+            .map(|(mark, id)| {
+                let mark = mark
+                    .strip_prefix(':')
+                    .and_then(|m| m.parse().ok())
+                    .ok_or_else(|| format!("invalid mark '{mark}' in marks file"))?;
+                Ok((mark, id))
+            })
+            .collect::<Result<_, String>>()?;
+        let map = read_pairs(&import_dir.join(MAP_FILE))?
+            .into_iter()
+            .collect();
+        let refs = Branch::list_branches(None)
+            .await
+            .into_iter()
+            .map(|branch| {
+                (
+                    format!("{BRANCH_REF_PREFIX}{}", branch.name),
+                    Some(branch.commit),
+                )
+            })
+            .collect();
+
+        Ok(Importer {
+            storage: util::objects_storage(),
+            marks,
+            map,
+            new_map_entries: Vec::new(),
+            refs,
+            touched: BTreeSet::new(),
+            current: None,
+            summary: ImportSummary::default(),
+            progress: ProgressBar::new_spinner(),
+        })
+    }
+
+    async fn import(&mut self, reader: impl BufRead) -> Result<(), String> {
+        use crate::utils::fast_import::Command as StreamCommand;
+
+        let mut stream = StreamReader::new(reader);
+        while let Some(command) = stream.next_command().map_err(|e| e.to_string())? {
+            match command {
+                StreamCommand::Blob(blob) => self.blob(blob)?,
+                StreamCommand::Commit(commit) => self.commit(*commit)?,
+                StreamCommand::Tag(tag) => self.tag(tag)?,
+                StreamCommand::Reset { ref_name, from } => {
+                    let target = from.map(|from| self.resolve(&from)).transpose()?;
+                    self.refs.insert(ref_name.clone(), target);
+                    self.touched.insert(ref_name);
+                }
+                StreamCommand::Feature(feature) => match feature.as_str() {
+                    "done" | "force" | "date-format=raw" => {}
+                    _ => return Err(format!("unsupported fast-import feature '{feature}'")),
+                },
+                StreamCommand::Progress(message) => self.progress.println(message),
+                StreamCommand::Option(_) | StreamCommand::Checkpoint | StreamCommand::Done => {}
+            }
+        }
+        self.progress.finish_and_clear();
+        Ok(())
+    }
+
+    fn blob(&mut self, blob: BlobCommand) -> Result<(), String> {
+        let id = self.store_blob(blob.data)?;
+        if let Some(mark) = blob.mark {
+            self.marks.insert(mark, id);
+        }
+        Ok(())
+    }
+
+    fn store_blob(&mut self, data: Vec<u8>) -> Result<ObjectHash, String> {
+        let blob = Blob::from_content_bytes(data);
+        if self.storage.exist(&blob.id) {
+            self.summary.skipped += 1;
+        } else {
+            save_object(&blob, &blob.id).map_err(|e| format!("failed to save blob: {e}"))?;
+            self.summary.blobs += 1;
+            self.tick();
+        }
+        Ok(blob.id)
+    }
+
+    fn commit(&mut self, commit: CommitCommand) -> Result<(), String> {
+        // Already imported under this original id: reuse it without rebuilding its tree.
+        if let Some(existing) = commit.original_oid.as_deref().and_then(|o| self.known(o)) {
+            self.summary.skipped += 1;
+            self.finish_commit(&commit, existing, None);
+            return Ok(());
+        }
+
+        let first_parent = match &commit.from {
+            Some(from) => Some(self.resolve(from)?),
+            None => self.refs.get(&commit.ref_name).copied().flatten(),
+        };
+        let mut parents: Vec<ObjectHash> = first_parent.into_iter().collect();
+        for merge in &commit.merges {
+            let merge = self.resolve(merge)?;
+            if !parents.contains(&merge) {
+                parents.push(merge);
+            }
+        }
+
+        let mut root = match (self.current.take(), first_parent) {
+            (Some((id, root)), Some(parent)) if id == parent => root,
+            (_, Some(parent)) => {
+                let parent: Commit = load_object(&parent)
+                    .map_err(|e| format!("failed to load parent commit {parent}: {e}"))?;
+                DirNode::unloaded(parent.tree_id)
+            }
+            (_, None) => DirNode::empty(),
+        };
+        for change in commit.changes.clone() {
+            self.apply(&mut root, change)?;
+        }
+        let tree_id = root
+            .write(&self.storage)
+            .map_err(|e| format!("failed to write tree: {e}"))?;
+
+        let committer = commit.committer.clone();
+        let author = commit.author.clone().unwrap_or_else(|| Signature {
+            signature_type: SignatureType::Author,
+            ..committer.clone()
+        });
+        // Headers libra has no field for are kept in front of the message, as in the raw object.
+        let mut message = String::new();
+        if let Some(encoding) = &commit.encoding {
+            message.push_str(&format!("encoding {encoding}\n"));
+        }
+        message.push('\n');
+        message.push_str(&String::from_utf8_lossy(&commit.message));
+
+        let new_commit = Commit::new(author, committer, tree_id, parents, &message);
+        if self.storage.exist(&new_commit.id) {
+            self.summary.skipped += 1;
+        } else {
+            save_object(&new_commit, &new_commit.id)
+                .map_err(|e| format!("failed to save commit: {e}"))?;
+            self.summary.commits += 1;
+            self.tick();
+        }
+        self.finish_commit(&commit, new_commit.id, Some(root));
+        Ok(())
+    }
+
+    fn finish_commit(&mut self, commit: &CommitCommand, id: ObjectHash, root: Option<DirNode>) {
+        if let Some(mark) = commit.mark {
+            self.marks.insert(mark, id);
+        }
+        if let Some(original) = &commit.original_oid {
+            self.record(original, id);
+        }
+        self.refs.insert(commit.ref_name.clone(), Some(id));
+        self.touched.insert(commit.ref_name.clone());
+        self.current = root.map(|root| (id, root));
+    }
+
+    fn apply(&mut self, root: &mut DirNode, change: FileChange) -> Result<(), String> {
+        let result = match change {
+            FileChange::Modify { mode, data, path } => {
+                let id = match data {
+                    DataRef::Mark(mark) => self.mark(mark)?,
+                    DataRef::Oid(oid) => ObjectHash::from_str(&oid)
+                        .map_err(|e| format!("invalid object id '{oid}': {e}"))?,
+                    DataRef::Inline(data) => self.store_blob(data)?,
+                };
+                let entry = if mode == TreeItemMode::Tree {
+                    Entry::Dir(DirNode::unloaded(id))
+                } else {
+                    Entry::File(mode, id)
+                };
+                root.set(&components(&path), entry)
+            }
+            FileChange::Delete { path } => root.remove(&components(&path)).map(|_| ()),
+            FileChange::Copy { source, dest } => match root.get(&components(&source)) {
+                Ok(Some(entry)) => root.set(&components(&dest), entry),
+                Ok(None) => return Err(format!("cannot copy missing path '{source}'")),
+                Err(e) => Err(e),
+            },
+            FileChange::Rename { source, dest } => match root.remove(&components(&source)) {
+                Ok(Some(entry)) => root.set(&components(&dest), entry),
+                Ok(None) => return Err(format!("cannot rename missing path '{source}'")),
+                Err(e) => Err(e),
+            },
+            FileChange::DeleteAll => {
+                *root = DirNode::empty();
+                Ok(())
+            }
+        };
+        result.map_err(|e| format!("failed to update tree: {e}"))
+    }
+
+    fn tag(&mut self, tag: TagCommand) -> Result<(), String> {
+        let ref_name = format!("{TAG_REF_PREFIX}{}", tag.name);
+        if let Some(existing) = tag.original_oid.as_deref().and_then(|o| self.known(o)) {
+            self.summary.skipped += 1;
+            self.finish_tag(&tag, ref_name, existing);
+            return Ok(());
+        }
+
+        let target = self.resolve(&tag.from)?;
+        let target_type = self
+            .storage
+            .get_object_type(&target)
+            .map_err(|e| format!("tag '{}' points to a missing object: {e}", tag.name))?;
+        let tagger = tag.tagger.clone().unwrap_or(Signature {
+            signature_type: SignatureType::Tagger,
+            name: String::new(),
+            email: String::new(),
+            timestamp: 0,
+            timezone: "+0000".to_string(),
+        });
+        let mut tag_object = GitTag::new(
+            target,
+            target_type,
+            tag.name.clone(),
+            tagger,
+            String::from_utf8_lossy(&tag.message).into_owned(),
+        );
+        // Hash the serialized object so the id matches the one git computes.
+        let data = tag_object
+            .to_data()
+            .map_err(|e| format!("failed to encode tag '{}': {e}", tag.name))?;
+        tag_object.id = ObjectHash::from_type_and_data(ObjectType::Tag, &data);
+
+        if self.storage.exist(&tag_object.id) {
+            self.summary.skipped += 1;
+        } else {
+            save_object(&tag_object, &tag_object.id)
+                .map_err(|e| format!("failed to save tag '{}': {e}", tag.name))?;
+            self.summary.tags += 1;
+            self.tick();
+        }
+        self.finish_tag(&tag, ref_name, tag_object.id);
+        Ok(())
+    }
+
+    fn finish_tag(&mut self, tag: &TagCommand, ref_name: String, id: ObjectHash) {
+        if let Some(mark) = tag.mark {
+            self.marks.insert(mark, id);
+        }
+        if let Some(original) = &tag.original_oid {
+            self.record(original, id);
+        }
+        self.refs.insert(ref_name.clone(), Some(id));
+        self.touched.insert(ref_name);
+    }
+
+    /// The libra id of an already imported original id, if that object is present.
+    fn known(&self, original: &str) -> Option<ObjectHash> {
+        let id = match self.map.get(original) {
+            Some(id) => *id,
+            None => ObjectHash::from_str(original).ok()?,
+        };
+        self.storage.exist(&id).then_some(id)
+    }
+
+    fn record(&mut self, original: &str, id: ObjectHash) {
+        if original != id.to_string() && self.map.get(original) != Some(&id) {
+            self.map.insert(original.to_string(), id);
+            self.new_map_entries.push((original.to_string(), id));
+            self.summary.remapped += 1;
+        }
+    }
+
+    fn resolve(&self, commit: &CommitRef) -> Result<ObjectHash, String> {
+        match commit {
+            CommitRef::Mark(mark) => self.mark(*mark),
+            CommitRef::Oid(oid) => self
+                .known(oid)
+                .ok_or_else(|| format!("unknown object '{oid}'")),
+            CommitRef::Ref(name) => self
+                .refs
+                .get(name)
+                .copied()
+                .flatten()
+                .ok_or_else(|| format!("unknown ref '{name}'")),
+        }
+    }
+
+    fn mark(&self, mark: u64) -> Result<ObjectHash, String> {
+        self.marks
+            .get(&mark)
+            .copied()
+            .ok_or_else(|| format!("unknown mark :{mark}"))
+    }
+
+    fn tick(&self) {
+        let summary = &self.summary;
+        self.progress.set_message(format!(
+            "Importing objects: {} commits, {} blobs, {} tags",
+            summary.commits, summary.blobs, summary.tags
+        ));
+        self.progress.tick();
+    }
+
+    /// Writes the marks and appends the new id pairs for the next run.
+    fn save(&self, import_dir: &Path) -> Result<(), String> {
+        let marks: BTreeMap<_, _> = self.marks.iter().collect();
+        let content: String = marks
+            .into_iter()
+            .map(|(mark, id)| format!(":{mark} {id}\n"))
+            .collect();
+        let path = import_dir.join(MARKS_FILE);
+        fs::write(&path, content)
+            .map_err(|e| format!("failed to write {}: {e}", path.display()))?;
+
+        if !self.new_map_entries.is_empty() {
+            let path = import_dir.join(MAP_FILE);
+            let mut content = fs::read_to_string(&path).unwrap_or_default();
+            for (old, new) in &self.new_map_entries {
+                content.push_str(&format!("{old} {new}\n"));
+            }
+            fs::write(&path, content)
+                .map_err(|e| format!("failed to write {}: {e}", path.display()))?;
+        }
+        Ok(())
+    }
+
+    /// Points every branch and tag named by the stream at its imported target.
+    async fn update_refs(mut self) -> Result<ImportSummary, String> {
+        for ref_name in std::mem::take(&mut self.touched) {
+            let Some(Some(id)) = self.refs.get(&ref_name).copied() else {
+                continue;
+            };
+            if let Some(branch) = ref_name.strip_prefix(BRANCH_REF_PREFIX) {
+                let current = Branch::find_branch(branch, None).await.map(|b| b.commit);
+                if current == Some(id) {
+                    continue;
+                }
+                Branch::update_branch(branch, &id.to_string(), None).await;
+            } else if let Some(name) = ref_name.strip_prefix(TAG_REF_PREFIX) {
+                let current = tag::list()
+                    .await
+                    .map_err(|e| e.to_string())?
+                    .into_iter()
+                    .find(|t| t.name == name);
+                if current.is_some_and(|t| tag_target(&t.object) == Some(id)) {
+                    continue;
+                }
+                tag::set_target(name, &id)
+                    .await
+                    .map_err(|e| format!("failed to update tag '{name}': {e}"))?;
+            } else {
+                eprintln!(
+                    "warning: ignoring ref '{ref_name}', only branches and tags are imported"
+                );
+                continue;
+            }
+            self.summary.refs.push((ref_name, id));
+        }
+        Ok(self.summary)
+    }
+}
+
+/// The object a tag ref points to.
+fn tag_target(object: &tag::TagObject) -> Option<ObjectHash> {
+    match object {
+        tag::TagObject::Commit(commit) => Some(commit.id),
+        tag::TagObject::Tag(tag) => Some(tag.id),
+        _ => None,
+    }
+}
+
+/// Reads `<key> <id>` lines; a missing file is empty and non-id lines (headers) are skipped.
+fn read_pairs(path: &Path) -> Result<Vec<(String, ObjectHash)>, String> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("failed to read {}: {e}", path.display())),
+    };
+    Ok(content
+        .lines()
+        .filter_map(|line| {
+            let (key, id) = line.split_once(' ')?;
+            Some((key.to_string(), ObjectHash::from_str(id.trim()).ok()?))
+        })
+        .collect())
+}
+
+fn components(path: &str) -> Vec<&str> {
+    path.split('/').filter(|c| !c.is_empty()).collect()
+}
+
+/// An entry of a directory being built.
+#[derive(Clone)]
+enum Entry {
+    File(TreeItemMode, ObjectHash),
+    Dir(DirNode),
+}
+
+/// A directory of the tree being built, loaded from storage the first time it is changed.
+#[derive(Clone)]
+struct DirNode {
+    /// The tree id, while the directory is unchanged since it was loaded or written.
+    id: Option<ObjectHash>,
+    entries: Option<BTreeMap<String, Entry>>,
+}
+
+impl DirNode {
+    fn empty() -> Self {
+        DirNode {
+            id: None,
+            entries: Some(BTreeMap::new()),
+        }
+    }
+
+    fn unloaded(id: ObjectHash) -> Self {
+        DirNode {
+            id: Some(id),
+            entries: None,
+        }
+    }
+
+    fn entries(&mut self) -> Result<&mut BTreeMap<String, Entry>, GitError> {
+        if self.entries.is_none() {
+            let id = self.id.expect("an unloaded directory has an id");
+            let mut entries = BTreeMap::new();
+            if id != empty_tree_id() {
+                let tree: Tree = load_object(&id)?;
+                for item in tree.tree_items {
+                    let entry = if item.mode == TreeItemMode::Tree {
+                        Entry::Dir(DirNode::unloaded(item.id))
+                    } else {
+                        Entry::File(item.mode, item.id)
+                    };
+                    entries.insert(item.name, entry);
+                }
+            }
+            self.entries = Some(entries);
+        }
+        Ok(self.entries.as_mut().expect("entries were just loaded"))
+    }
+
+    fn set(&mut self, path: &[&str], entry: Entry) -> Result<(), GitError> {
+        let Some((name, rest)) = path.split_first() else {
+            return Err(GitError::InvalidPathError("empty path".to_string()));
+        };
+        self.entries()?;
+        self.id = None;
+        let entries = self.entries.as_mut().expect("entries were just loaded");
+        if rest.is_empty() {
+            entries.insert(name.to_string(), entry);
+            return Ok(());
+        }
+        let child = entries
+            .entry(name.to_string())
+            .and_modify(|existing| {
+                if matches!(existing, Entry::File(..)) {
+                    *existing = Entry::Dir(DirNode::empty());
+                }
+            })
+            .or_insert_with(|| Entry::Dir(DirNode::empty()));
+        match child {
+            Entry::Dir(dir) => dir.set(rest, entry),
+            Entry::File(..) => unreachable!("files on the path were replaced by directories"),
+        }
+    }
+
+    fn get(&mut self, path: &[&str]) -> Result<Option<Entry>, GitError> {
+        let Some((name, rest)) = path.split_first() else {
+            return Ok(Some(Entry::Dir(self.clone())));
+        };
+        match self.entries()?.get_mut(*name) {
+            Some(entry) if rest.is_empty() => Ok(Some(entry.clone())),
+            Some(Entry::Dir(dir)) => dir.get(rest),
+            _ => Ok(None),
+        }
+    }
+
+    /// Removes the entry at `path`, dropping directories it leaves empty.
+    fn remove(&mut self, path: &[&str]) -> Result<Option<Entry>, GitError> {
+        let Some((name, rest)) = path.split_first() else {
+            return Ok(None);
+        };
+        let entries = self.entries()?;
+        let removed = if rest.is_empty() {
+            entries.remove(*name)
+        } else {
+            let Some(Entry::Dir(dir)) = entries.get_mut(*name) else {
+                return Ok(None);
+            };
+            let removed = dir.remove(rest)?;
+            if dir.entries.as_ref().is_some_and(BTreeMap::is_empty) {
+                entries.remove(*name);
+            }
+            removed
+        };
+        if removed.is_some() {
+            self.id = None;
+        }
+        Ok(removed)
+    }
+
+    /// Saves every changed tree below this directory and returns its id.
+    fn write(&mut self, storage: &ClientStorage) -> Result<ObjectHash, GitError> {
+        if let Some(id) = self.id {
+            return Ok(id);
+        }
+        let entries = self.entries()?;
+        let mut items = Vec::with_capacity(entries.len());
+        for (name, entry) in entries.iter_mut() {
+            match entry {
+                Entry::File(mode, id) => items.push(TreeItem::new(*mode, *id, name.clone())),
+                Entry::Dir(dir) => {
+                    let id = dir.write(storage)?;
+                    // Git does not record empty directories.
+                    if id != empty_tree_id() {
+                        items.push(TreeItem::new(TreeItemMode::Tree, id, name.clone()));
+                    }
+                }
+            }
+        }
+        // Git orders tree entries as if directory names ended with '/'.
+        items.sort_by_key(git_sort_key);
+
+        let tree = if items.is_empty() {
+            Tree::from_bytes(&[], empty_tree_id())?
+        } else {
+            Tree::from_tree_items(items)?
+        };
+        if !storage.exist(&tree.id) {
+            save_object(&tree, &tree.id)?;
+        }
+        self.id = Some(tree.id);
+        Ok(tree.id)
+    }
+}
+
+fn git_sort_key(item: &TreeItem) -> Vec<u8> {
+    let mut key = item.name.as_bytes().to_vec();
+    if item.mode == TreeItemMode::Tree {
+        key.push(b'/');
+    }
+    key
+}
+
+fn empty_tree_id() -> ObjectHash {
+    ObjectHash::from_type_and_data(ObjectType::Tree, &[])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_git_sort_key_orders_directories_with_trailing_slash() {
+        let id = empty_tree_id();
+        let mut items = [
+            TreeItem::new(TreeItemMode::Tree, id, "a".to_string()),
+            TreeItem::new(TreeItemMode::Blob, id, "a.txt".to_string()),
+            TreeItem::new(TreeItemMode::Blob, id, "a-b".to_string()),
+        ];
+        items.sort_by_key(git_sort_key);
+        let names: Vec<_> = items.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(names, ["a-b", "a.txt", "a"]);
+    }
+
+    #[test]
+    fn test_dir_node_set_get_and_remove_prunes_empty_dirs() {
+        let id = empty_tree_id();
+        let mut root = DirNode::empty();
+        root.set(&["src", "lib", "a.rs"], Entry::File(TreeItemMode::Blob, id))
+            .unwrap();
+        root.set(&["README"], Entry::File(TreeItemMode::Blob, id))
+            .unwrap();
+        assert!(matches!(
+            root.get(&["src", "lib", "a.rs"]).unwrap(),
+            Some(Entry::File(TreeItemMode::Blob, _))
+        ));
+        assert!(root.get(&["src", "missing"]).unwrap().is_none());
+
+        assert!(root.remove(&["src", "lib", "a.rs"]).unwrap().is_some());
+        let entries = root.entries().unwrap();
+        assert!(!entries.contains_key("src"));
+        assert!(entries.contains_key("README"));
+    }
+}
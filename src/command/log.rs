@@ -1,13 +1,14 @@
 //! Log command rendering commit history with optional decorations, filtering, and custom formatting utilities.
+//!
+//! `--output=dot` and `--output=mermaid` write the selected commits as a graph instead (see
+//! [`crate::internal::log::graph_export`]), straight to stdout rather than through the pager.
 
-#[cfg(unix)]
-use std::io::Write;
 #[cfg(unix)]
 use std::process::{Command, Stdio};
 use std::{
-    cmp::min,
     collections::{HashMap, HashSet, VecDeque},
-    path::PathBuf,
+    io::Write,
+    path::{Path, PathBuf},
     str::FromStr,
 };
 
@@ -21,22 +22,26 @@ use git_internal::{
 
 use crate::{
     command::load_object,
+    common_utils::parse_commit_msg,
     internal::{
         branch::Branch,
         config::Config,
+        config_defaults::{ApplyConfigDefaults, ConfigDefault, parse_bool},
         head::Head,
         log::{
             date_parser::parse_date,
+            filter::CommitFilter,
             formatter::{CommitFormatter, FormatContext, FormatType},
+            graph_export::{self, Decoration, DecorationKind, GraphFormat, GraphNode},
         },
     },
-    utils::{object_ext::TreeExt, util},
+    utils::{object_ext::TreeExt, rename, util},
 };
 
 #[derive(Parser, Debug)]
 pub struct LogArgs {
     /// Limit the number of output
-    #[clap(short, long)]
+    #[clap(short, long, alias = "max-count")]
     pub number: Option<usize>,
     /// Shorthand for --pretty=oneline --abbrev-commit
     #[clap(long)]
@@ -61,7 +66,7 @@ pub struct LogArgs {
     /// Show names and status of changed files
     #[clap(long)]
     pub name_status: bool,
-    /// Filter commits by author name or email
+    /// Filter commits by author name or email (case-insensitive regular expression)
     #[clap(long)]
     pub author: Option<String>,
     /// Show commits more recent than a specific date
@@ -90,12 +95,51 @@ pub struct LogArgs {
     /// Show diffstat (file change statistics) for each commit
     #[clap(long)]
     pub stat: bool,
+    /// Continue listing the history of a single file beyond renames
+    #[clap(long)]
+    pub follow: bool,
+    /// Write the selected commits as a Graphviz DOT or Mermaid graph
+    #[clap(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        conflicts_with_all = ["patch", "name_only", "name_status", "stat", "graph", "oneline", "pretty"]
+    )]
+    pub output: Option<GraphFormat>,
 
     /// Files to limit diff output (used with -p, --name-only, or --stat)
     #[clap(value_name = "PATHS", num_args = 0..)]
     pathspec: Vec<String>,
 }
 
+impl ApplyConfigDefaults for LogArgs {
+    const CONFIG_DEFAULTS: &'static [ConfigDefault] = &[ConfigDefault {
+        key: "log.decorate",
+        arg: "decorate",
+        negation: Some("no_decorate"),
+    }];
+
+    fn apply_config_default(&mut self, arg: &str, value: &str) -> Result<(), String> {
+        match arg {
+            "decorate" => {
+                // Like git, `log.decorate` also accepts a plain boolean.
+                let value = match parse_bool(value) {
+                    Ok(true) => "short".to_string(),
+                    Ok(false) => "no".to_string(),
+                    Err(_) => {
+                        str_to_decorate_option(value)
+                            .map_err(|v| format!("invalid decorate option '{v}'"))?;
+                        value.to_string()
+                    }
+                };
+                self.decorate = Some(value);
+            }
+            _ => unreachable!("unknown log config default: {arg}"),
+        }
+        Ok(())
+    }
+}
+
 #[derive(PartialEq, Debug)]
 enum DecorateOptions {
     No,
@@ -116,76 +160,6 @@ pub struct FileChange {
     pub status: ChangeType,
 }
 
-struct CommitFilter {
-    author: Option<String>,
-    since: Option<i64>,
-    until: Option<i64>,
-    paths: Vec<PathBuf>,
-}
-
-impl CommitFilter {
-    fn new(
-        author: Option<String>,
-        since: Option<i64>,
-        until: Option<i64>,
-        paths: Vec<PathBuf>,
-    ) -> Self {
-        Self {
-            author: author.map(|s| s.to_lowercase()),
-            since,
-            until,
-            paths,
-        }
-    }
-
-    fn passes_non_path_filters(&self, commit: &Commit) -> bool {
-        if let Some(author_filter) = &self.author {
-            let author = format!(
-                "{} <{}>",
-                commit.author.name.to_lowercase(),
-                commit.author.email.to_lowercase()
-            );
-            if !author.contains(author_filter) {
-                return false;
-            }
-        }
-
-        let ts = commit.committer.timestamp as i64;
-        if let Some(since) = self.since
-            && ts < since
-        {
-            return false;
-        }
-        if let Some(until) = self.until
-            && ts > until
-        {
-            return false;
-        }
-
-        true
-    }
-
-    async fn matches_paths(&self, commit: &Commit, cached_changes: Option<&[FileChange]>) -> bool {
-        if self.paths.is_empty() {
-            return true;
-        }
-
-        if let Some(changes) = cached_changes {
-            !changes.is_empty()
-        } else {
-            commit_touches_paths(commit, &self.paths).await
-        }
-    }
-
-    async fn matches(&self, commit: &Commit, cached_changes: Option<&[FileChange]>) -> bool {
-        if !self.passes_non_path_filters(commit) {
-            return false;
-        }
-
-        self.matches_paths(commit, cached_changes).await
-    }
-}
-
 fn str_to_decorate_option(s: &str) -> Result<DecorateOptions, String> {
     match s {
         "no" => Ok(DecorateOptions::No),
@@ -236,6 +210,9 @@ async fn determine_decorate_option(args: &LogArgs) -> Result<DecorateOptions, St
         .and_then(|s| str_to_decorate_option(&s).ok())
     {
         Ok(config_deco)
+    } else if args.output.is_some() {
+        // A graph is written for a renderer, not a terminal, so "auto" would always say no.
+        Ok(DecorateOptions::Short)
     } else {
         str_to_decorate_option("auto")
     }
@@ -297,6 +274,36 @@ struct Reference {
 }
 
 pub async fn execute(args: LogArgs) {
+    if args.output.is_some() {
+        let _ = execute_to(args, &mut std::io::stdout()).await;
+        return;
+    }
+
+    #[cfg(unix)]
+    {
+        let mut process = Command::new("less")
+            .arg("-R")
+            .arg("-F")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::inherit())
+            .spawn()
+            .expect("failed to execute process");
+        match process.stdin.take() {
+            Some(mut stdin) => {
+                let _ = execute_to(args, &mut stdin).await;
+            }
+            None => eprintln!("Failed to capture stdin"),
+        }
+        let _ = process.wait().expect("failed to wait on child");
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = execute_to(args, &mut std::io::stdout()).await;
+    }
+}
+
+/// Write the log for `args` to `writer`; errors in the arguments are reported on stderr.
+pub async fn execute_to(args: LogArgs, writer: &mut impl Write) -> std::io::Result<()> {
     let name_status = args.name_status;
     // Check parameter mutual exclusion: if both name flags and --patch are specified, prioritize the name display flags
     let name_only = args.name_only && !name_status;
@@ -306,32 +313,37 @@ pub async fn execute(args: LogArgs) {
         Ok(v) => v,
         Err(e) => {
             eprintln!("fatal: {}", e);
-            return;
+            return Ok(());
         }
     };
     let until = match args.until.as_deref().map(parse_date).transpose() {
         Ok(v) => v,
         Err(e) => {
             eprintln!("fatal: {}", e);
-            return;
+            return Ok(());
+        }
+    };
+    if args.follow && args.pathspec.len() != 1 {
+        eprintln!("fatal: --follow requires exactly one pathspec");
+        return Ok(());
+    }
+    let mut path_filters: Vec<PathBuf> = args.pathspec.iter().map(util::to_workdir_path).collect();
+    let mut filter = match CommitFilter::new()
+        .since(since)
+        .until(until)
+        .author(args.author.as_deref())
+    {
+        Ok(filter) => filter.paths(path_filters.clone()).max_count(args.number),
+        Err(e) => {
+            eprintln!("fatal: {}", e);
+            return Ok(());
         }
     };
-    let path_filters: Vec<PathBuf> = args.pathspec.iter().map(util::to_workdir_path).collect();
-    let filter = CommitFilter::new(args.author.clone(), since, until, path_filters.clone());
 
     let decorate_option = determine_decorate_option(&args)
         .await
         .expect("fatal: invalid --decorate option");
 
-    #[cfg(unix)]
-    let mut process = Command::new("less")
-        .arg("-R")
-        .arg("-F")
-        .stdin(Stdio::piped())
-        .stdout(Stdio::inherit())
-        .spawn()
-        .expect("failed to execute process");
-
     let head = Head::current().await;
     // check if the current branch has any commits
     let branch_name = if let Head::Branch(n) = head.to_owned() {
@@ -364,7 +376,6 @@ pub async fn execute(args: LogArgs) {
     };
     let formatter = CommitFormatter::new(format_type);
 
-    let max_output_number = min(args.number.unwrap_or(usize::MAX), reachable_commits.len());
     let mut output_number = 0;
     let mut graph_state = if args.graph {
         Some(GraphState::new())
@@ -377,31 +388,67 @@ pub async fn execute(args: LogArgs) {
         full_hash_len
     } else if let Some(n) = args.abbrev {
         if n == 0 { default_abbrev } else { n }
-    } else if args.abbrev_commit || args.oneline || args.pretty.is_some() {
+    } else if args.abbrev_commit || args.oneline || args.pretty.is_some() || args.output.is_some() {
         default_abbrev
     } else {
         full_hash_len
     };
+    // With --follow, the old name of the followed file applies from the next (older) commit on.
+    let mut followed_rename: Option<PathBuf> = None;
+    let mut graph_nodes = args.output.map(|_| Vec::new());
     for commit in reachable_commits {
-        if output_number >= max_output_number {
+        // Commits come newest first, so nothing older can be shown once the limit is hit.
+        if filter.limit_reached(output_number) {
             break;
         }
-        if !filter.passes_non_path_filters(&commit) {
+        if let Some(old_path) = followed_rename.take() {
+            path_filters = vec![old_path];
+            filter.set_paths(path_filters.clone());
+        }
+        let renamed_from = if args.follow {
+            find_followed_rename(&commit, &path_filters[0])
+        } else {
+            None
+        };
+        followed_rename = renamed_from.clone();
+        if !filter.matches_cheap(&commit) {
             continue;
         }
 
-        let mut cached_changes = if filter.paths.is_empty() && !name_only && !name_status {
+        let mut cached_changes = if !filter.has_path_filter() && !name_only && !name_status {
             None
         } else {
             Some(get_changed_files_for_commit(&commit, &path_filters).await)
         };
 
-        if !filter.matches(&commit, cached_changes.as_deref()).await {
+        if !filter
+            .matches_paths(&commit, cached_changes.as_deref())
+            .await
+        {
             continue;
         }
 
         output_number += 1;
 
+        if let Some(nodes) = graph_nodes.as_mut() {
+            let decorations = if decorate_option == DecorateOptions::No {
+                Vec::new()
+            } else {
+                graph_decorations(
+                    &commit,
+                    &commit_hash,
+                    branch_name.as_deref(),
+                    ref_commits
+                        .get(&commit.id)
+                        .map(Vec::as_slice)
+                        .unwrap_or_default(),
+                    decorate_option == DecorateOptions::Full,
+                )
+            };
+            nodes.push(graph_node(&commit, abbrev_len, decorations));
+            continue;
+        }
+
         let ref_msg = if decorate_option != DecorateOptions::No {
             let mut ref_msgs: Vec<String> = vec![];
             if output_number == 1 {
@@ -490,6 +537,13 @@ pub async fn execute(args: LogArgs) {
         };
         let mut message = formatter.format(&commit, &ctx);
 
+        if let Some(old_path) = &renamed_from {
+            if !message.ends_with('\n') {
+                message.push('\n');
+            }
+            message.push_str(&format!("renamed from {}", old_path.display()));
+        }
+
         if name_only || name_status {
             if let Some(changes) = cached_changes.take()
                 && !changes.is_empty()
@@ -518,32 +572,86 @@ pub async fn execute(args: LogArgs) {
             }
         }
 
-        #[cfg(unix)]
-        {
-            if let Some(ref mut stdin) = process.stdin {
-                writeln!(stdin, "{message}").unwrap();
-            } else {
-                eprintln!("Failed to capture stdin");
-            }
-        }
-        #[cfg(not(unix))]
-        {
-            println!("{message}");
-        }
+        writeln!(writer, "{message}")?;
     }
 
-    #[cfg(unix)]
-    {
-        let _ = process.wait().expect("failed to wait on child");
+    if let (Some(format), Some(nodes)) = (args.output, graph_nodes) {
+        graph_export::write_graph(writer, format, &nodes)?;
     }
+    Ok(())
 }
 
-async fn commit_touches_paths(commit: &Commit, filters: &[PathBuf]) -> bool {
-    if filters.is_empty() {
-        return true;
+/// `commit` as a node of an exported graph, with hashes abbreviated to `abbrev_len`.
+fn graph_node(commit: &Commit, abbrev_len: usize, decorations: Vec<Decoration>) -> GraphNode {
+    let abbrev = |id: &ObjectHash| {
+        let id = id.to_string();
+        id[..abbrev_len.min(id.len())].to_string()
+    };
+    let (subject, _) = parse_commit_msg(&commit.message);
+    GraphNode {
+        id: abbrev(&commit.id),
+        subject: subject.lines().next().unwrap_or_default().to_string(),
+        parents: commit.parent_commit_ids.iter().map(abbrev).collect(),
+        decorations,
     }
-    let changes = get_changed_files_for_commit(commit, filters).await;
-    !changes.is_empty()
+}
+
+/// The decorations of `commit` in an exported graph: HEAD on the commit it resolves to,
+/// then the tags and branches in `refs`, with their `refs/` prefix when `full`.
+fn graph_decorations(
+    commit: &Commit,
+    head_commit: &str,
+    head_branch: Option<&str>,
+    refs: &[Reference],
+    full: bool,
+) -> Vec<Decoration> {
+    let prefix = |kind: &str| {
+        if full {
+            format!("refs/{kind}/")
+        } else {
+            String::new()
+        }
+    };
+    let mut decorations = Vec::new();
+    if commit.id.to_string() == head_commit {
+        decorations.push(Decoration {
+            kind: DecorationKind::Head,
+            label: match head_branch {
+                Some(branch) => format!("HEAD -> {}{branch}", prefix("heads")),
+                None => "HEAD".to_string(),
+            },
+        });
+    }
+    let mut refs = refs.to_vec();
+    refs.sort();
+    for reference in refs {
+        let decoration = match reference.kind {
+            ReferenceKind::Local if Some(reference.name.as_str()) == head_branch => continue,
+            ReferenceKind::Tag => Decoration {
+                kind: DecorationKind::Tag,
+                label: format!("tag: {}{}", prefix("tags"), reference.name),
+            },
+            ReferenceKind::Remote => Decoration {
+                kind: DecorationKind::Remote,
+                label: format!("{}{}", prefix("remotes"), reference.name),
+            },
+            ReferenceKind::Local => Decoration {
+                kind: DecorationKind::Branch,
+                label: format!("{}{}", prefix("heads"), reference.name),
+            },
+        };
+        decorations.push(decoration);
+    }
+    decorations
+}
+
+/// The path `path` had in the first parent of `commit`, if `commit` renamed it.
+fn find_followed_rename(commit: &Commit, path: &Path) -> Option<PathBuf> {
+    let parent = commit.parent_commit_ids.first()?;
+    let tree = load_object::<Tree>(&commit.tree_id).ok()?;
+    let parent_commit = load_object::<Commit>(parent).ok()?;
+    let parent_tree = load_object::<Tree>(&parent_commit.tree_id).ok()?;
+    rename::find_rename_source(&parent_tree, &tree, path)
 }
 
 /// Get list of changed files for a commit
@@ -1063,12 +1171,11 @@ mod tests {
         commit.author.email = "lvy@test.com".into();
         commit.committer.timestamp = 1_766_102_400; // 2025-12-19 00:00:00 UTC
 
-        let filter = CommitFilter::new(
-            Some("lvy".to_string()),
-            Some(1_766_000_000),
-            Some(1_766_200_000),
-            Vec::new(),
-        );
+        let filter = CommitFilter::new()
+            .author(Some("lvy"))
+            .unwrap()
+            .since(Some(1_766_000_000))
+            .until(Some(1_766_200_000));
 
         assert!(filter.matches(&commit, None).await);
     }
@@ -1,17 +1,22 @@
 //! Command module hub exporting all subcommands plus shared helpers for loading/saving objects and prompting for authentication.
 
 pub mod add;
+pub mod ai;
 pub mod blame;
 pub mod branch;
 pub mod checkout;
 pub mod cherry_pick;
+pub mod churn;
 pub mod clean;
 pub mod clone;
 pub mod code;
 pub mod commit;
 pub mod config;
 pub mod diff;
+pub mod export_git;
 pub mod fetch;
+pub mod filter_repo;
+pub mod import_git;
 pub mod index_pack;
 pub mod init;
 pub mod lfs;
@@ -34,6 +39,7 @@ pub mod tag;
 pub mod worktree;
 
 pub mod stash;
+pub mod stats;
 pub mod status;
 pub mod switch;
 
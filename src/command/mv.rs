@@ -9,7 +9,7 @@ use git_internal::internal::index::{Index, IndexEntry};
 
 use crate::{
     command::calc_file_blob_hash,
-    utils::{path, util},
+    utils::{index_lock::IndexLock, path, util},
 };
 
 #[derive(Parser, Debug)]
@@ -82,6 +82,7 @@ pub async fn execute(args: MvArgs) -> Result<(), String> {
     // Check the validity of all sources and collect the valid move operations.
     let mut move_plan = MovePlan::default();
     let index_file = path::index();
+    let lock = IndexLock::acquire(&index_file).map_err(|e| format!("fatal: {e}"))?;
     let mut index = match Index::load(&index_file) {
         Ok(index) => index,
         Err(err) => {
@@ -116,6 +117,7 @@ pub async fn execute(args: MvArgs) -> Result<(), String> {
         args.dry_run,
         args.force,
         &mut index,
+        lock,
     )
 }
 /// Validates a source path and builds the move plan.
@@ -355,6 +357,7 @@ fn perform_moves(
     dry_run: bool,
     force: bool,
     index: &mut Index,
+    lock: IndexLock,
 ) -> Result<(), String> {
     let mut moved_count = 0usize;
 
@@ -466,7 +469,7 @@ fn perform_moves(
 
     // After performing all moves, save the index if there were any moves.
     if moved_count > 0
-        && let Err(e) = index.save(path::index())
+        && let Err(e) = lock.commit(index)
     {
         return Err(format!("fatal: failed to save index after mv: {e}"));
     }
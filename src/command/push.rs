@@ -34,6 +34,7 @@ use crate::{
         config::Config,
         db::get_db_conn_instance,
         head::Head,
+        protect,
         protocol::{
             ProtocolClient, get_wire_hash_kind, https_client::HttpsClient, lfs_client::LFSClient,
             set_wire_hash_kind,
@@ -62,6 +63,10 @@ pub struct PushArgs {
     /// Do everything except actually send the updates
     #[clap(long, short = 'n')]
     pub dry_run: bool,
+
+    /// Force push even if the branch is protected (see `branch.<name>.protected`)
+    #[clap(long, requires = "force")]
+    pub override_protection: bool,
 }
 
 pub async fn execute(args: PushArgs) {
@@ -96,6 +101,19 @@ pub async fn execute(args: PushArgs) {
     let repo_url = Config::get_remote_url(&repository).await;
 
     let branch = args.refspec.unwrap_or(branch);
+    // Checked up front, before contacting the remote: a forced push to a protected branch is
+    // refused whether or not the remote would need the force.
+    let protection = if args.force {
+        match protect::check(&branch, "force push", args.override_protection).await {
+            Ok(rule) => rule,
+            Err(e) => {
+                eprintln!("fatal: {e}");
+                return;
+            }
+        }
+    } else {
+        None
+    };
     let commit_hash = match Branch::find_branch(&branch, None).await {
         Some(branch_info) => branch_info.commit.to_string(),
         None => {
@@ -299,7 +317,8 @@ pub async fn execute(args: PushArgs) {
     println!("{}", "Push success".green());
 
     let remote_tracking_branch = format!("refs/remotes/{}/{}", repository, branch);
-    update_remote_tracking(&remote_tracking_branch, &commit_hash, &repository).await;
+    let message = protect::annotate("push", protection.as_ref());
+    update_remote_tracking(&remote_tracking_branch, &commit_hash, &repository, message).await;
 
     // set after push success
     if args.set_upstream {
@@ -316,12 +335,14 @@ pub async fn execute(args: PushArgs) {
 /// * `remote_tracking_branch` - The full ref name (e.g., "refs/remotes/origin/master")
 /// * `commit_hash` - The commit hash to update the branch to
 /// * `remote_name` - The name of the remote associated with this tracking branch (e.g., "origin")
+/// * `message` - The reflog message, noting an overridden branch protection if there was one
 ///
 /// If the transaction fails, an error is printed to stderr.
 async fn update_remote_tracking(
     remote_tracking_branch: &str,
     commit_hash: &str,
     remote_name: &str,
+    message: String,
 ) {
     let remote_tracking_branch = remote_tracking_branch.to_string();
     let commit_hash = commit_hash.to_string();
@@ -353,7 +374,7 @@ async fn update_remote_tracking(
                 let context = ReflogContext {
                     old_oid,
                     new_oid: commit_hash.clone(),
-                    action: ReflogAction::Push,
+                    action: ReflogAction::Push { message },
                 };
                 Reflog::insert_single_entry(txn, &context, &remote_tracking_branch).await?;
                 Ok::<_, ReflogError>(())
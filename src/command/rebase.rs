@@ -1,4 +1,8 @@
 //! Rebase implementation that parses onto/branch arguments, replays commits onto a new base, handles conflicts, and updates branch refs.
+//!
+//! `-i` makes the rebase interactive: the commits to replay are offered as a todo list (see
+//! [`crate::internal::rebase_todo`]) that can reorder, reword, squash, fix up or drop them
+//! and run commands in between.
 
 use std::{
     borrow::Cow,
@@ -18,15 +22,20 @@ use sea_orm::{ConnectionTrait, DbBackend, Statement, TransactionTrait, Value};
 
 use crate::{
     command::{load_object, save_object, status},
+    common_utils::parse_commit_msg,
     internal::{
         branch::Branch,
         db::get_db_conn_instance,
         head::Head,
+        protect,
+        rebase_todo::{self, ExternalEditor, RebaseEditor, TodoItem},
+        ref_transaction::{RefName, RefTransaction},
         reflog,
         reflog::{ReflogAction, ReflogContext, ReflogError, with_reflog},
     },
     utils::{
         ignore::IgnorePolicy,
+        index_lock::IndexLock,
         object_ext::{BlobExt, TreeExt},
         path, util, worktree,
     },
@@ -41,8 +50,8 @@ pub struct RebaseState {
     pub onto: ObjectHash,
     /// Original HEAD commit before rebase started
     pub orig_head: ObjectHash,
-    /// Remaining commits to replay (in order)
-    pub todo: VecDeque<ObjectHash>,
+    /// Remaining steps (in order); a plain rebase only has picks
+    pub todo: VecDeque<TodoItem>,
     /// Commits already replayed
     pub done: Vec<ObjectHash>,
     /// Current commit being applied (stopped due to conflict)
@@ -211,7 +220,7 @@ impl RebaseState {
             .map_err(|e| format!("Invalid orig_head hash: {e}"))?;
         let current_head = ObjectHash::from_str(current_head_str.trim())
             .map_err(|e| format!("Invalid current_head hash: {e}"))?;
-        let todo = VecDeque::from(Self::parse_hash_list(&todo_str)?);
+        let todo = VecDeque::from(Self::parse_todo_list(&todo_str)?);
         let done = Self::parse_hash_list(&done_str)?;
         let stopped_sha = match stopped_str {
             Some(s) if !s.trim().is_empty() => Some(
@@ -239,7 +248,12 @@ impl RebaseState {
             .await
             .map_err(|e| format!("failed to clear existing rebase_state: {e}"))?;
 
-        let todo = Self::format_hash_list(state.todo.iter().cloned());
+        let todo = state
+            .todo
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("\n");
         let done = Self::format_hash_list(state.done.iter().cloned());
         let stopped_value = match &state.stopped_sha {
             Some(sha) => sha.to_string().into(),
@@ -323,7 +337,7 @@ impl RebaseState {
             .map_err(|e| format!("Invalid current-head hash: {}", e))?;
 
         let todo_content = fs::read_to_string(dir.join("todo")).unwrap_or_default();
-        let todo = VecDeque::from(Self::parse_hash_list(&todo_content)?);
+        let todo = VecDeque::from(Self::parse_todo_list(&todo_content)?);
 
         let done_content = fs::read_to_string(dir.join("done")).unwrap_or_default();
         let done = Self::parse_hash_list(&done_content)?;
@@ -363,6 +377,14 @@ impl RebaseState {
         Ok(commits)
     }
 
+    fn parse_todo_list(content: &str) -> Result<Vec<TodoItem>, String> {
+        content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(str::parse)
+            .collect()
+    }
+
     fn format_hash_list(list: impl IntoIterator<Item = ObjectHash>) -> String {
         let mut out = String::new();
         for (idx, hash) in list.into_iter().enumerate() {
@@ -435,6 +457,14 @@ pub struct RebaseArgs {
     /// Skip the current commit and continue with the next
     #[clap(long, conflicts_with_all = ["continue_rebase", "abort", "upstream"])]
     pub skip: bool,
+
+    /// Edit the list of commits to replay before starting
+    #[clap(short, long, conflicts_with_all = ["continue_rebase", "abort", "skip"])]
+    pub interactive: bool,
+
+    /// Rebase even if the current branch is protected (see `branch.<name>.protected`)
+    #[clap(long, conflicts_with_all = ["continue_rebase", "abort", "skip"])]
+    pub override_protection: bool,
 }
 
 /// Execute the rebase command
@@ -449,13 +479,19 @@ pub struct RebaseArgs {
 /// The process maintains commit order but changes their parent relationships,
 /// effectively "moving" the branch to start from the upstream commit.
 pub async fn execute(args: RebaseArgs) {
+    execute_with_editor(args, &mut ExternalEditor).await;
+}
+
+/// [`execute`], asking `editor` for the todo list of `-i` and for reworded and squashed
+/// commit messages.
+pub async fn execute_with_editor(args: RebaseArgs, editor: &mut dyn RebaseEditor) {
     if !util::check_repo_exist() {
         return;
     }
 
     // Handle --continue, --abort, --skip
     if args.continue_rebase {
-        rebase_continue().await;
+        rebase_continue(editor).await;
         return;
     }
     if args.abort {
@@ -463,7 +499,7 @@ pub async fn execute(args: RebaseArgs) {
         return;
     }
     if args.skip {
-        rebase_skip().await;
+        rebase_skip(editor).await;
         return;
     }
 
@@ -491,11 +527,22 @@ pub async fn execute(args: RebaseArgs) {
         }
     };
 
-    start_rebase(&upstream).await;
+    start_rebase(
+        &upstream,
+        args.interactive,
+        args.override_protection,
+        editor,
+    )
+    .await;
 }
 
 /// Start a new rebase operation
-async fn start_rebase(upstream: &str) {
+async fn start_rebase(
+    upstream: &str,
+    interactive: bool,
+    override_protection: bool,
+    editor: &mut dyn RebaseEditor,
+) {
     let db = get_db_conn_instance().await;
 
     // Get the current branch that will be moved to the new base
@@ -506,6 +553,14 @@ async fn start_rebase(upstream: &str) {
             return;
         }
     };
+    let protection = match protect::check(&current_branch_name, "rebase", override_protection).await
+    {
+        Ok(rule) => rule,
+        Err(e) => {
+            eprintln!("fatal: {e}");
+            return;
+        }
+    };
 
     // Get the current HEAD commit that represents the tip of the branch to rebase
     let head_to_rebase_id = match Head::current_commit().await {
@@ -535,8 +590,9 @@ async fn start_rebase(upstream: &str) {
         }
     };
 
-    // Check if rebase is actually needed
-    if base_id == head_to_rebase_id {
+    // Check if rebase is actually needed; an interactive rebase may still edit the commits
+    // on top of an upstream it already contains.
+    if base_id == head_to_rebase_id && !interactive {
         let upstream_commit: Commit = match load_object(&upstream_id) {
             Ok(c) => c,
             Err(e) => {
@@ -553,6 +609,13 @@ async fn start_rebase(upstream: &str) {
         };
 
         let index_file = path::index();
+        let lock = match IndexLock::acquire(&index_file) {
+            Ok(lock) => lock,
+            Err(e) => {
+                eprintln!("fatal: {e}");
+                return;
+            }
+        };
         let current_index = match git_internal::internal::index::Index::load(&index_file) {
             Ok(index) => index,
             Err(e) => {
@@ -569,42 +632,33 @@ async fn start_rebase(upstream: &str) {
             return;
         }
 
-        let fast_forward_action = ReflogAction::Rebase {
+        let fast_forward_action = || ReflogAction::Rebase {
             state: "fast-forward".to_string(),
-            details: format!("moving {} to {}", current_branch_name, upstream),
-        };
-        let fast_forward_context = ReflogContext {
-            old_oid: head_to_rebase_id.to_string(),
-            new_oid: upstream_id.to_string(),
-            action: fast_forward_action,
+            details: protect::annotate(
+                &format!("moving {} to {}", current_branch_name, upstream),
+                protection.as_ref(),
+            ),
         };
 
-        let branch_name_cloned = current_branch_name.clone();
-        let upstream_id_str = upstream_id.to_string();
-        if let Err(e) = with_reflog(
-            fast_forward_context,
-            move |txn: &sea_orm::DatabaseTransaction| {
-                Box::pin(async move {
-                    Branch::update_branch_with_conn(
-                        txn,
-                        &branch_name_cloned,
-                        &upstream_id_str,
-                        None,
-                    )
-                    .await;
-                    Head::update_with_conn(txn, Head::Branch(branch_name_cloned), None).await;
-                    Ok(())
-                })
-            },
-            true,
-        )
-        .await
-        {
+        let mut transaction = RefTransaction::begin();
+        transaction
+            .update(
+                RefName::branch(current_branch_name.as_str()),
+                upstream_id,
+                Some(head_to_rebase_id),
+                Some(fast_forward_action()),
+            )
+            .set_head(
+                Head::Branch(current_branch_name.clone()),
+                None,
+                Some(fast_forward_action()),
+            );
+        if let Err(e) = transaction.commit().await {
             eprintln!("fatal: failed to fast-forward: {e}");
             return;
         }
 
-        if let Err(e) = index.save(&index_file) {
+        if let Err(e) = lock.commit(&mut index) {
             eprintln!("fatal: failed to save index: {:?}", e);
             return;
         }
@@ -619,7 +673,7 @@ async fn start_rebase(upstream: &str) {
         );
         return;
     }
-    if base_id == upstream_id {
+    if base_id == upstream_id && !interactive {
         println!("Current branch is ahead of upstream. No rebase needed.");
         return;
     }
@@ -633,6 +687,36 @@ async fn start_rebase(upstream: &str) {
         }
     };
 
+    let todo = if interactive {
+        let edited = match editor.edit_todo(&rebase_todo::generate_todo(
+            &commits_to_replay,
+            &upstream_id,
+        )) {
+            Ok(edited) => edited,
+            Err(e) => {
+                eprintln!("fatal: {e}");
+                return;
+            }
+        };
+        match rebase_todo::parse_todo(&edited, &commits_to_replay) {
+            Ok(todo) if todo.is_empty() => {
+                println!("Nothing to do");
+                return;
+            }
+            Ok(todo) => todo,
+            Err(e) => {
+                eprintln!("error: invalid todo list: {e}");
+                return;
+            }
+        }
+    } else {
+        commits_to_replay
+            .iter()
+            .copied()
+            .map(TodoItem::Pick)
+            .collect()
+    };
+
     let upstream_commit: Commit = match load_object(&upstream_id) {
         Ok(commit) => commit,
         Err(e) => {
@@ -666,7 +750,7 @@ async fn start_rebase(upstream: &str) {
 
     let start_action = ReflogAction::Rebase {
         state: "start".to_string(),
-        details: format!("checkout {}", upstream),
+        details: protect::annotate(&format!("checkout {}", upstream), protection.as_ref()),
     };
     let start_context = ReflogContext {
         old_oid: head_to_rebase_id.to_string(),
@@ -693,7 +777,7 @@ async fn start_rebase(upstream: &str) {
         head_name: current_branch_name.clone(),
         onto: upstream_id,
         orig_head: head_to_rebase_id,
-        todo: VecDeque::from(commits_to_replay.clone()),
+        todo: VecDeque::from(todo),
         done: Vec::new(),
         stopped_sha: None,
         current_head: upstream_id,
@@ -708,11 +792,16 @@ async fn start_rebase(upstream: &str) {
     Head::update_with_conn(db, Head::Detached(upstream_id), None).await;
 
     // Continue replaying commits
-    continue_replay(&mut state, &current_branch_name, upstream).await;
+    continue_replay(&mut state, &current_branch_name, upstream, editor).await;
 }
 
-/// Continue replaying commits from the current state
-async fn continue_replay(state: &mut RebaseState, branch_name: &str, upstream_display: &str) {
+/// Continue replaying the todo steps from the current state
+async fn continue_replay(
+    state: &mut RebaseState,
+    branch_name: &str,
+    upstream_display: &str,
+    editor: &mut dyn RebaseEditor,
+) {
     let db = get_db_conn_instance().await;
     let commit_subject = |commit_id: &ObjectHash| -> String {
         match load_object::<Commit>(commit_id) {
@@ -735,9 +824,65 @@ async fn continue_replay(state: &mut RebaseState, branch_name: &str, upstream_di
         upstream_display
     );
 
-    while let Some(commit_id) = state.todo.front().cloned() {
+    while let Some(item) = state.todo.front().cloned() {
+        let commit_id = match &item {
+            TodoItem::Exec(command) => {
+                state.todo.pop_front();
+                if let Err(e) = state.save().await {
+                    eprintln!("warning: failed to save rebase state: {}", e);
+                }
+                println!("Executing: {command}");
+                if let Err(e) = run_exec(command) {
+                    eprintln!("error: {e}");
+                    eprintln!("You can fix the problem, and then run 'libra rebase --continue'");
+                    eprintln!(
+                        "To abort and return to the original branch, run 'libra rebase --abort'"
+                    );
+                    return;
+                }
+                continue;
+            }
+            TodoItem::Drop(commit_id) => {
+                state.todo.pop_front();
+                println!(
+                    "Dropped: {} {}",
+                    &commit_id.to_string()[..7],
+                    commit_subject(commit_id)
+                );
+                if let Err(e) = state.save().await {
+                    eprintln!("warning: failed to save rebase state: {}", e);
+                }
+                continue;
+            }
+            item => *item.commit().expect("only exec steps have no commit"),
+        };
         match replay_commit_with_conflict_detection(&commit_id, &state.current_head).await {
             ReplayResult::Success(replayed_commit_id) => {
+                let replayed_commit_id = match apply_todo_step(
+                    &item,
+                    replayed_commit_id,
+                    state.current_head,
+                    editor,
+                ) {
+                    Ok(id) => id,
+                    Err(e) => {
+                        // The replayed changes are in the index; `--continue` commits
+                        // them and retries the step.
+                        state.stopped_sha = Some(commit_id);
+                        if let Err(e) = state.save().await {
+                            eprintln!("fatal: failed to save rebase state: {}", e);
+                        }
+                        eprintln!(
+                            "error: could not apply {}: {e}",
+                            &commit_id.to_string()[..7]
+                        );
+                        eprintln!("To retry, run 'libra rebase --continue'");
+                        eprintln!(
+                            "To abort and return to the original branch, run 'libra rebase --abort'"
+                        );
+                        return;
+                    }
+                };
                 state.current_head = replayed_commit_id;
                 // Move commit from todo to done
                 state.todo.pop_front();
@@ -803,48 +948,101 @@ async fn continue_replay(state: &mut RebaseState, branch_name: &str, upstream_di
     }
 }
 
+/// Turn the replayed commit of a todo step into the commit the step asks for: `reword`
+/// edits its message, `squash` and `fixup` fold it into `previous_id`, the commit before it.
+fn apply_todo_step(
+    item: &TodoItem,
+    replayed_id: ObjectHash,
+    previous_id: ObjectHash,
+    editor: &mut dyn RebaseEditor,
+) -> Result<ObjectHash, String> {
+    let replayed: Commit = load_object(&replayed_id).map_err(|e| e.to_string())?;
+    let replayed_message = parse_commit_msg(&replayed.message).0.trim().to_string();
+    let (parents, message) = match item {
+        TodoItem::Reword(_) => (
+            replayed.parent_commit_ids.clone(),
+            edit_commit_message(editor, &format!("{replayed_message}\n"))?,
+        ),
+        TodoItem::Squash(_) | TodoItem::Fixup(_) => {
+            let previous: Commit = load_object(&previous_id).map_err(|e| e.to_string())?;
+            let previous_message = parse_commit_msg(&previous.message).0.trim().to_string();
+            let message = if matches!(item, TodoItem::Squash(_)) {
+                edit_commit_message(
+                    editor,
+                    &format!(
+                        "# This is a combination of 2 commits.\n{previous_message}\n\n{replayed_message}\n"
+                    ),
+                )?
+            } else {
+                previous_message
+            };
+            (previous.parent_commit_ids.clone(), message)
+        }
+        _ => return Ok(replayed_id),
+    };
+    let commit = Commit::from_tree_id(replayed.tree_id, parents, &message);
+    save_object(&commit, &commit.id).map_err(|e| format!("commit save: {e}"))?;
+    Ok(commit.id)
+}
+
+fn edit_commit_message(editor: &mut dyn RebaseEditor, message: &str) -> Result<String, String> {
+    let edited = rebase_todo::clean_message(&editor.edit_message(message)?);
+    if edited.is_empty() {
+        return Err("aborting commit due to empty commit message".to_string());
+    }
+    Ok(edited)
+}
+
+/// Run the command of an `exec` step in the working directory.
+fn run_exec(command: &str) -> Result<(), String> {
+    #[cfg(not(target_os = "windows"))]
+    let status = std::process::Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .current_dir(util::working_dir())
+        .status();
+    #[cfg(target_os = "windows")]
+    let status = std::process::Command::new("cmd")
+        .arg("/C")
+        .arg(command)
+        .current_dir(util::working_dir())
+        .status();
+    match status {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(format!("exec '{command}' failed: {status}")),
+        Err(e) => Err(format!("failed to run exec '{command}': {e}")),
+    }
+}
+
 /// Finalize rebase after all commits are replayed
 async fn finalize_rebase(state: &RebaseState) -> anyhow::Result<()> {
     let db = get_db_conn_instance().await;
     let final_commit_id = state.current_head;
 
-    let finish_action = ReflogAction::Rebase {
+    let finish_action = || ReflogAction::Rebase {
         state: "finish".to_string(),
         details: format!("returning to refs/heads/{}", state.head_name),
     };
-    let finish_context = ReflogContext {
-        old_oid: state.orig_head.to_string(),
-        new_oid: final_commit_id.to_string(),
-        action: finish_action,
-    };
 
-    let branch_name_cloned = state.head_name.clone();
-    if let Err(e) = with_reflog(
-        finish_context,
-        move |txn: &sea_orm::DatabaseTransaction| {
-            Box::pin(async move {
-                // This is the crucial step: move the original branch from its old position
-                // to the final replayed commit.
-                Branch::update_branch_with_conn(
-                    txn,
-                    &branch_name_cloned,
-                    &final_commit_id.to_string(),
-                    None,
-                )
-                .await;
-
-                // Also, re-attach HEAD to the newly moved branch.
-                Head::update_with_conn(txn, Head::Branch(branch_name_cloned.clone()), None).await;
-                Ok(())
-            })
-        },
-        true,
-    )
-    .await
-    {
+    // This is the crucial step: move the original branch from its old position to the
+    // final replayed commit and re-attach HEAD to it, both or neither.
+    let mut transaction = RefTransaction::begin();
+    transaction
+        .update(
+            RefName::branch(state.head_name.as_str()),
+            final_commit_id,
+            Some(state.orig_head),
+            Some(finish_action()),
+        )
+        .set_head(
+            Head::Branch(state.head_name.clone()),
+            None,
+            Some(finish_action()),
+        );
+    if let Err(e) = transaction.commit().await {
         // Attempt to restore HEAD to a safe state
         Head::update_with_conn(db, Head::Detached(state.onto), None).await;
-        return Err(e).context("failed to record reflog for rebase finish");
+        return Err(e).context("failed to move the branch to the rebased commits");
     }
 
     // Reset the working directory and index to match the final state
@@ -855,6 +1053,7 @@ async fn finalize_rebase(state: &RebaseState) -> anyhow::Result<()> {
         load_object(&final_commit.tree_id).context("failed to load final tree for rebase")?;
 
     let index_file = path::index();
+    let lock = IndexLock::acquire(&index_file)?;
     let current_index = git_internal::internal::index::Index::load(&index_file)
         .map_err(|e| anyhow::anyhow!(e))
         .context("failed to load current index before rebase finish")?;
@@ -862,8 +1061,7 @@ async fn finalize_rebase(state: &RebaseState) -> anyhow::Result<()> {
     rebuild_index_from_tree(&final_tree, &mut index, "")
         .map_err(|e| anyhow::anyhow!(e))
         .context("failed to rebuild index from final tree")?;
-    index
-        .save(&index_file)
+    lock.commit(&mut index)
         .map_err(|e| anyhow::anyhow!(e))
         .context("failed to save index after rebase")?;
     reset_workdir_tracked_only(&current_index, &index)
@@ -884,7 +1082,7 @@ async fn finalize_rebase(state: &RebaseState) -> anyhow::Result<()> {
 }
 
 /// Continue a rebase after conflict resolution
-async fn rebase_continue() {
+async fn rebase_continue(editor: &mut dyn RebaseEditor) {
     match RebaseState::is_in_progress().await {
         Ok(true) => {}
         Ok(false) => {
@@ -917,7 +1115,7 @@ async fn rebase_continue() {
             } else {
                 let head_name = state.head_name.clone();
                 let onto_display = state.onto.to_string()[..7].to_string();
-                continue_replay(&mut state, &head_name, &onto_display).await;
+                continue_replay(&mut state, &head_name, &onto_display, editor).await;
             }
             return;
         }
@@ -968,15 +1166,30 @@ async fn rebase_continue() {
         eprintln!("fatal: failed to save commit: {:?}", e);
         return;
     }
+    let step = state
+        .todo
+        .front()
+        .cloned()
+        .unwrap_or(TodoItem::Pick(stopped_sha));
+    let new_commit_id = match apply_todo_step(&step, new_commit.id, state.current_head, editor) {
+        Ok(id) => id,
+        Err(e) => {
+            eprintln!(
+                "error: could not apply {}: {e}",
+                &stopped_sha.to_string()[..7]
+            );
+            return;
+        }
+    };
 
     println!(
         "Applied: {} {}",
-        &new_commit.id.to_string()[..7],
+        &new_commit_id.to_string()[..7],
         original_commit.message.lines().next().unwrap_or("")
     );
 
     // Update state
-    state.current_head = new_commit.id;
+    state.current_head = new_commit_id;
     state.todo.pop_front();
     state.done.push(stopped_sha);
     state.stopped_sha = None;
@@ -997,7 +1210,7 @@ async fn rebase_continue() {
     } else {
         let head_name = state.head_name.clone();
         let onto_display = state.onto.to_string()[..7].to_string();
-        continue_replay(&mut state, &head_name, &onto_display).await;
+        continue_replay(&mut state, &head_name, &onto_display, editor).await;
     }
 }
 
@@ -1085,6 +1298,13 @@ async fn rebase_abort() {
     };
 
     let index_file = path::index();
+    let lock = match IndexLock::acquire(&index_file) {
+        Ok(lock) => lock,
+        Err(e) => {
+            eprintln!("fatal: {e}");
+            return;
+        }
+    };
     let current_index = match git_internal::internal::index::Index::load(&index_file) {
         Ok(idx) => idx,
         Err(e) => {
@@ -1097,7 +1317,7 @@ async fn rebase_abort() {
         eprintln!("fatal: failed to rebuild index: {}", e);
         return;
     }
-    if let Err(e) = index.save(&index_file) {
+    if let Err(e) = lock.commit(&mut index) {
         eprintln!("fatal: failed to save index: {:?}", e);
         return;
     }
@@ -1115,7 +1335,7 @@ async fn rebase_abort() {
 }
 
 /// Skip the current commit and continue with the next
-async fn rebase_skip() {
+async fn rebase_skip(editor: &mut dyn RebaseEditor) {
     match RebaseState::is_in_progress().await {
         Ok(true) => {}
         Ok(false) => {
@@ -1138,19 +1358,13 @@ async fn rebase_skip() {
 
     let skipped_sha = match state.stopped_sha {
         Some(sha) => sha,
-        None => {
-            if state.todo.is_empty() {
+        None => match state.todo.front().and_then(TodoItem::commit) {
+            Some(sha) => *sha,
+            None => {
                 eprintln!("fatal: no commit to skip");
                 return;
             }
-            match state.todo.front().cloned() {
-                Some(sha) => sha,
-                None => {
-                    eprintln!("fatal: no commit to skip");
-                    return;
-                }
-            }
-        }
+        },
     };
 
     let skipped_message = match load_object::<Commit>(&skipped_sha) {
@@ -1195,6 +1409,13 @@ async fn rebase_skip() {
     };
 
     let index_file = path::index();
+    let lock = match IndexLock::acquire(&index_file) {
+        Ok(lock) => lock,
+        Err(e) => {
+            eprintln!("fatal: {e}");
+            return;
+        }
+    };
     let current_index = match git_internal::internal::index::Index::load(&index_file) {
         Ok(idx) => idx,
         Err(e) => {
@@ -1207,7 +1428,7 @@ async fn rebase_skip() {
         eprintln!("fatal: failed to rebuild index: {}", e);
         return;
     }
-    if let Err(e) = index.save(&index_file) {
+    if let Err(e) = lock.commit(&mut index) {
         eprintln!("fatal: failed to save index: {:?}", e);
         return;
     }
@@ -1228,7 +1449,7 @@ async fn rebase_skip() {
     } else {
         let head_name = state.head_name.clone();
         let onto_display = state.onto.to_string()[..7].to_string();
-        continue_replay(&mut state, &head_name, &onto_display).await;
+        continue_replay(&mut state, &head_name, &onto_display, editor).await;
     }
 }
 
@@ -1647,6 +1868,10 @@ async fn replay_commit_with_conflict_detection(
     new_parent_id: &ObjectHash,
 ) -> ReplayResult {
     let index_file = path::index();
+    let lock = match IndexLock::acquire(&index_file) {
+        Ok(lock) => lock,
+        Err(e) => return ReplayResult::error(format!("index lock: {}", e)),
+    };
     let current_index = match git_internal::internal::index::Index::load(&index_file) {
         Ok(idx) => idx,
         Err(e) => return ReplayResult::error(format!("index load: {:?}", e)),
@@ -1747,7 +1972,6 @@ async fn replay_commit_with_conflict_detection(
         }
 
         // Update index with conflict entries
-        let index_file = path::index();
         let mut index = git_internal::internal::index::Index::new();
 
         // Add non-conflicting files at stage 0
@@ -1802,7 +2026,7 @@ async fn replay_commit_with_conflict_detection(
             }
         }
 
-        if let Err(e) = index.save(&index_file) {
+        if let Err(e) = lock.commit(&mut index) {
             return ReplayResult::Conflict {
                 paths: conflicts,
                 message: Some(format!("index save: {}", e)),
@@ -1869,7 +2093,7 @@ async fn replay_commit_with_conflict_detection(
     if let Err(e) = rebuild_index_from_tree(&new_tree, &mut index, "") {
         return ReplayResult::error(format!("index rebuild: {}", e));
     }
-    if let Err(e) = index.save(&index_file) {
+    if let Err(e) = lock.commit(&mut index) {
         return ReplayResult::error(format!("index save: {}", e));
     }
     if let Err(e) = reset_workdir_tracked_only(&current_index, &index) {
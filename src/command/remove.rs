@@ -9,7 +9,7 @@ use tokio::fs;
 
 use crate::{
     command::status::{changes_to_be_committed, changes_to_be_staged},
-    utils::{path, path_ext::PathExt, util},
+    utils::{index_lock::IndexLock, path, path_ext::PathExt, util},
 };
 
 #[derive(Parser, Debug, Clone)]
@@ -79,6 +79,13 @@ pub async fn execute(args: RemoveArgs) {
     let idx_file = path::index();
     let mut remove_list = Vec::new();
     let mut remove_dir_list = Vec::new();
+    let lock = match IndexLock::acquire(&idx_file) {
+        Ok(lock) => lock,
+        Err(err) => {
+            eprintln!("fatal: {}", err);
+            return;
+        }
+    };
     let mut index = match Index::load(&idx_file) {
         Ok(index) => index,
         Err(err) => {
@@ -295,7 +302,7 @@ pub async fn execute(args: RemoveArgs) {
         }
     }
 
-    if index.save(&idx_file).is_err() {
+    if lock.commit(&mut index).is_err() {
         eprintln!("Failed to save index.");
     }
 }
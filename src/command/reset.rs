@@ -21,9 +21,11 @@ use crate::{
         branch::Branch,
         db::get_db_conn_instance,
         head::Head,
+        protect::{self, ProtectionRule},
         reflog::{ReflogAction, ReflogContext, with_reflog},
     },
     utils::{
+        index_lock::{self, IndexLock},
         object_ext::{BlobExt, TreeExt},
         path, util,
     },
@@ -50,6 +52,10 @@ pub struct ResetArgs {
     /// Pathspecs to reset specific files
     #[clap(value_name = "PATH")]
     pub pathspecs: Vec<String>,
+
+    /// Hard reset even if the current branch is protected (see `branch.<name>.protected`)
+    #[clap(long)]
+    pub override_protection: bool,
 }
 
 #[derive(Debug)]
@@ -84,6 +90,20 @@ pub async fn execute(args: ResetArgs) {
         return;
     }
 
+    // A hard reset throws away the branch's commits together with the work tree.
+    let mut protection = None;
+    if let ResetMode::Hard = mode
+        && let Head::Branch(branch) = Head::current().await
+    {
+        match protect::check(&branch, "reset --hard", args.override_protection).await {
+            Ok(rule) => protection = rule,
+            Err(e) => {
+                eprintln!("fatal: {e}");
+                return;
+            }
+        }
+    }
+
     // Resolve target commit
     let target_commit_id = match resolve_commit(&args.target).await {
         Ok(id) => id,
@@ -94,7 +114,7 @@ pub async fn execute(args: ResetArgs) {
     };
 
     // Perform reset based on mode
-    match perform_reset(target_commit_id, mode, &args.target).await {
+    match perform_reset(target_commit_id, mode, &args.target, protection.as_ref()).await {
         Ok(_) => {
             println!(
                 "HEAD is now at {} {}",
@@ -137,6 +157,13 @@ async fn reset_pathspecs(pathspecs: &[String], target: &str) {
     };
 
     let index_file = path::index();
+    let lock = match IndexLock::acquire(&index_file) {
+        Ok(lock) => lock,
+        Err(e) => {
+            eprintln!("fatal: {e}");
+            return;
+        }
+    };
     let mut index = match Index::load(&index_file) {
         Ok(idx) => idx,
         Err(e) => {
@@ -177,7 +204,7 @@ async fn reset_pathspecs(pathspecs: &[String], target: &str) {
         }
     }
 
-    if changed && let Err(e) = index.save(&index_file) {
+    if changed && let Err(e) = lock.commit(&mut index) {
         eprintln!("fatal: failed to save index: {e}");
     }
 }
@@ -188,6 +215,7 @@ async fn perform_reset(
     target_commit_id: ObjectHash,
     mode: ResetMode,
     target_ref_str: &str, // e.g, "HEAD~2"
+    protection: Option<&ProtectionRule>,
 ) -> Result<(), String> {
     // avoids holding the transaction open while doing read-only preparations.
     let db = get_db_conn_instance().await;
@@ -208,7 +236,7 @@ async fn perform_reset(
     let current_head_state = Head::current_with_conn(db).await;
 
     let action = ReflogAction::Reset {
-        target: target_ref_str.to_string(),
+        target: protect::annotate(target_ref_str, protection),
     };
     let context = ReflogContext {
         old_oid: old_oid.to_string(),
@@ -277,8 +305,7 @@ pub(crate) fn reset_index_to_commit(commit_id: &ObjectHash) -> Result<(), String
     // Rebuild index from tree
     rebuild_index_from_tree(&tree, &mut index, "")?;
 
-    index
-        .save(&index_file)
+    index_lock::save_index(&mut index, &index_file)
         .map_err(|e| format!("failed to save index: {e}"))?;
 
     Ok(())
@@ -19,6 +19,7 @@ use crate::{
     command::calc_file_blob_hash,
     internal::{branch::Branch, head::Head, protocol::lfs_client::LFSClient},
     utils::{
+        index_lock::IndexLock,
         lfs,
         object_ext::{BlobExt, CommitExt, TreeExt},
         path,
@@ -314,6 +315,7 @@ pub fn restore_index(
     let target_blobs = preprocess_blobs(target_blobs);
 
     let idx_file = path::index();
+    let lock = IndexLock::acquire(&idx_file).map_err(|e| io::Error::other(e.to_string()))?;
     let mut index = Index::load(&idx_file).map_err(|e| io::Error::other(e.to_string()))?;
     let deleted_files_index = get_index_deleted_files_in_filters(&index, filter, &target_blobs)?;
 
@@ -359,8 +361,7 @@ pub fn restore_index(
             }
         }
     }
-    index
-        .save(&idx_file)
+    lock.commit(&mut index)
         .map_err(|e| io::Error::other(e.to_string()))?; // DO NOT forget to save
     Ok(())
 }
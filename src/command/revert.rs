@@ -19,6 +19,7 @@ use crate::{
     common_utils::format_commit_msg,
     internal::{branch::Branch, head::Head},
     utils::{
+        index_lock::IndexLock,
         object_ext::{BlobExt, TreeExt},
         path, util,
     },
@@ -173,9 +174,10 @@ async fn revert_single_commit(
 
     let mut new_index = Index::new();
     rebuild_index_from_tree(&final_tree, &mut new_index, "")?;
+    let lock = IndexLock::acquire(path::index()).map_err(|e| e.to_string())?;
     let current_index = Index::load(path::index()).unwrap_or_else(|_| Index::new());
     reset_workdir_safely(&current_index, &new_index)?;
-    new_index.save(path::index()).map_err(|e| e.to_string())?;
+    lock.commit(&mut new_index).map_err(|e| e.to_string())?;
 
     if args.no_commit {
         Ok(None)
@@ -237,13 +239,13 @@ async fn build_tree_from_map(
 /// Handle reverting the root commit (initial commit)
 /// Root commits have no parents, so reverting them means creating an empty repository state
 async fn revert_root_commit(args: &RevertArgs) -> Result<Option<ObjectHash>, String> {
-    let new_index = Index::new(); // Create an empty index
+    let mut new_index = Index::new(); // Create an empty index
 
+    let lock = IndexLock::acquire(path::index()).map_err(|e| e.to_string())?;
     let current_index = Index::load(path::index()).unwrap_or_else(|_| Index::new());
     reset_workdir_safely(&current_index, &new_index)?;
 
-    new_index
-        .save(path::index())
+    lock.commit(&mut new_index)
         .map_err(|e| format!("failed to save index: {e}"))?;
 
     if args.no_commit {
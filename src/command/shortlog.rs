@@ -6,15 +6,99 @@
 //! commands in this crate:
 //!
 //! - **Argument parsing** is handled by [`ShortlogArgs`], which defines the
-//!   supported flags and options using `clap::Parser`. The key flags are:
+//!   supported flags and options using `clap::Parser`. Flags git shortlog
+//!   shares keep its short forms (`-n`, `-s`, `-e`, `-c`). The date and
+//!   author filters stay long-only, as in git: their letters already mean
+//!   something else in the log family (`-S` is the pickaxe, `-s` is
+//!   `--summary`, `-u` is `--patch`). The remaining flags are long-only too:
+//!   they are rarely typed interactively, and leaving their letters free
+//!   avoids clashing with short options git may add to shortlog. The key
+//!   flags are:
 //!   - `numbered` (`-n` / `--numbered`): sort authors by descending commit
-//!     count rather than by name.
+//!     count rather than by name. Defaults from `shortlog.numbered`, which
+//!     `--no-numbered` overrides.
 //!   - `summary` (`-s` / `--summary`): emit only per-author commit counts,
 //!     suppressing individual commit subjects.
 //!   - `email` (`-e` / `--email`): include the author email address in the
 //!     report header.
-//!   - `since` / `until`: restrict the set of commits by committer timestamp,
-//!     using the repository-wide date parser in [`parse_date`].
+//!   - `committer` (`-c` / `--committer`): group by committer instead of
+//!     author, as git shortlog does.
+//!   - `since` / `until` (`--since`, `--until`): restrict the
+//!     set of commits by committer timestamp, using the repository-wide date
+//!     parser in [`parse_date`].
+//!   - `date_match` (`--date-match any|author|committer`): which timestamp the
+//!     date window applies to. `any` keeps a commit when either its author or
+//!     its committer timestamp falls in the window.
+//!   - `author` (`--author <regex>`): count only commits whose author
+//!     `name <email>` matches a case-insensitive regular expression, as
+//!     `log --author` does.
+//!   - `path` (`--path <glob>`, repeatable): keep only commits that touch a
+//!     matching path. A glob also matches everything below a matching
+//!     directory, so `--path src/ai` covers `src/ai/**`.
+//!   - `abbrev` (`--abbrev[=<n>]`): prefix each subject with the first `n`
+//!     hex digits of its commit hash (7 when `n` is omitted). Only visible in
+//!     detailed mode, since `--summary` prints no subjects.
+//!   - `dates` (`--dates`): append `(first .. last)` to each author header,
+//!     the committer dates of their earliest and latest counted commits,
+//!     rendered with [`format_timestamp`] like `log` does.
+//!   - `body` (`--body[=<lines>]`): print up to `lines` lines of each
+//!     commit's message body under its subject, indented further (3 when
+//!     `lines` is omitted, 0 without the flag). Blank lines between the
+//!     subject and the body are skipped. Only visible in detailed mode.
+//!   - `subjects_reverse` (`--subjects-reverse`): list each author's subjects
+//!     oldest first instead of newest first, e.g. for a narrative changelog.
+//!     The order of the authors themselves does not change.
+//!   - `pairs` (`--pairs`): group by collaboration instead of by author. Every
+//!     two people credited on a commit (its author plus `Co-authored-by`
+//!     trailers, see [`co_authors`]) form a pair, and each pair is listed with
+//!     the number of commits they share.
+//!   - `pager` (`-p` / `--pager` / `--paginate`, `-P` / `--no-pager`): page
+//!     terminal output through `$PAGER`. Defaults from `shortlog.pager`, which
+//!     `--no-pager` overrides. git shortlog has no pager flags of its own; the
+//!     spellings are those of git's global `-p`/`--paginate` and
+//!     `-P`/`--no-pager`, accepted here after the subcommand.
+//!   - `repo` (`--repo <path>`, repeatable) and `workspace` (`--workspace`):
+//!     report on several repositories at once instead of the current one.
+//!     `--workspace` adds every repository listed in the nearest
+//!     [`WORKSPACE_FILE`], one path per line relative to that file.
+//!   - `show_repo` (`--show-repo`): prefix each subject with the name of the
+//!     repository it came from.
+//!   - `cache` (`--cache` / `--no-cache`): reuse the shortlog cache described
+//!     below instead of walking the full history. Off by default; defaults
+//!     from `shortlog.cache`, which `--no-cache` overrides.
+//!   - `yearly` / `monthly` / `weekly` (`--yearly`, `--monthly`, `--weekly`):
+//!     instead of subjects, list the number of commits per calendar year,
+//!     calendar month or ISO week, one `period  count` line each.
+//!     `by_author` (`--by-author`) breaks the counts down per author instead,
+//!     listing each author's periods under their header; `--pairs` needs it,
+//!     since a commit counts once for every pair it credits. The
+//!     date is the committer's, or the author's with `--date-match author` or
+//!     `any`, taken in the timezone recorded in the signature rather than UTC.
+//!     Periods without commits are left out unless `--fill-gaps` lists every
+//!     period from the first to the last one in the report. `--csv` prints
+//!     `period,count` rows instead, or with `--by-author`
+//!     `author,period,count,machine_id` rows, quoting names as CSV
+//!     requires. The `machine_id` (see [`machine_id`]) stays the same for an
+//!     identity across runs and changes of name casing, for deduplication
+//!     downstream.
+//!   - `oneline_total` (`--oneline-total`): print a single `14 commits by 6
+//!     authors` line (`committers` with `-c`) after all filters have applied,
+//!     and nothing per author.
+//!   - `strict` (`--strict`): abort with `fatal:` on a commit object that
+//!     cannot be read. By default such a commit is logged with
+//!     `tracing::warn!` and skipped, along with the history only reachable
+//!     through it, and the report covers the rest.
+//!   - `all` (`--all`): count the commits reachable from every local branch
+//!     and `HEAD` instead of from `HEAD` alone. History shared by several
+//!     tips is walked once, so each commit is counted once. Bypasses the
+//!     shortlog cache, which tracks a single tip.
+//!   - `aliases` (`--aliases <path>`): merge identities listed in a
+//!     contributors file of `name = alias1, alias2` lines into one canonical
+//!     author before grouping; aliases are names or emails (see [`Aliases`]).
+//!   - `jobs` (`-j` / `--jobs <n>`): how many tasks read commit objects and
+//!     diff trees for `--path`, the number of CPUs by default. `--jobs 1`
+//!     does one thing at a time, for debugging; the report is the same
+//!     either way.
 //!
 //! - **Execution entrypoints**:
 //!   - [`execute`] is the user-facing async entrypoint used by the CLI
@@ -23,45 +107,119 @@
 //!     arbitrary `Write` implementor, which makes it easier to test and to
 //!     reuse from other tooling without being tied to a specific output
 //!     stream.
+//!   - [`execute_paged_to`] sits between the two: with `--pager` (or
+//!     `shortlog.pager`) and a terminal destination it renders the report
+//!     into memory and feeds it to the pager named by `$PAGER`. Without a
+//!     terminal, without `$PAGER`, with `--no-pager`, or when the pager cannot
+//!     be started, the report goes straight to the writer.
 //!
 //! - **Commit collection and filtering**:
 //!   - [`get_commits_for_shortlog`] resolves the current [`Head`] and
 //!     obtains the relevant list of [`Commit`] objects to be included in the
 //!     report. The exact traversal strategy is delegated to the internal git
-//!     engine.
-//!   - [`passes_filter`] applies `since`/`until` constraints to each
-//!     commit, converting user-supplied date strings via [`parse_date`] and
-//!     comparing them against the commit committer timestamp (to match `git log`).
+//!     engine. With `--repo`/`--workspace` it opens each repository by path
+//!     as a [`Repository`], with its own database and object store, so every
+//!     traversal uses that repository's own `HEAD` without changing the
+//!     working directory, and merges the results newest first. A repository
+//!     that cannot be opened is reported on stderr and skipped.
+//!   - History is walked breadth first by blocking tasks that share one queue
+//!     of commits to read (see [`walk_commits`]), so the walk never waits for
+//!     a whole generation. Reading a linear history is inherently one commit
+//!     at a time; there the tasks overlap the per-commit tree diffs of
+//!     `--path` with reading the commits below.
+//!   - A [`CommitFilter`], shared with `log`, selects the commits. It applies
+//!     `since`/`until` (user-supplied date strings are converted via
+//!     [`parse_date`]) against the committer timestamp, to match `git log`,
+//!     unless `--date-match` selects the author timestamp or either one, then
+//!     `--author`, then `--path` by diffing each commit's tree against its
+//!     first parent. Diffing loads two trees per commit, so path-scoped
+//!     reports are noticeably slower on long histories; the cheaper filters
+//!     run first to limit the cost.
 //!
 //! - **Aggregation and formatting**:
 //!   - Commits are grouped by author identity in an in-memory
 //!     `HashMap<String, AuthorStats>`, where [`AuthorStats`] tracks the
-//!     author name, optional email address, total commit count, and a list
-//!     of commit subjects.
+//!     author name, optional email address, total commit count, a list
+//!     of commit subjects, and the range of committer timestamps seen.
 //!   - If `-e` is provided, grouping is by `name <email>`. Otherwise, it is
 //!     by `name` only (merging multiple emails for the same author).
+//!   - With `--pairs`, the same map is keyed by `A + B` instead, where `A` and
+//!     `B` are identities formed by the same rule, ordered case-insensitively.
 //!   - After aggregation, the authors are converted to a vector, optionally
 //!     sorted by commit count (`numbered`) or left in deterministic order,
 //!     and finally rendered to the provided writer in either detailed or
 //!     summary form depending on the `summary` flag.
+//!   - With `--cache` (or `shortlog.cache`) and the current repository only
+//!     (no `--repo`/`--workspace`), the aggregates are cached in
+//!     `.libra/info/shortlog-cache`, keyed by a fingerprint of the options
+//!     that affect them (grouping flags, `--abbrev`, resolved date window,
+//!     `--date-match`, `--path`, the content of the `--aliases` file) together with the
+//!     `HEAD` commit they cover. When `HEAD` has moved forward, only the commits the entry
+//!     does not cover yet are read and merged in; when it no longer descends
+//!     from the cached tip, or the fingerprint differs, the entry is rebuilt.
+//!     A few option combinations are kept at once, and a cache that cannot be
+//!     read or written is silently ignored. The cache is only filled by
+//!     `shortlog` itself; prewarming it from `libra maintenance` is deferred
+//!     until that command exists.
+//!   - [`write_report`] computes one count-column width for the whole report
+//!     and indents subjects past it, so authors with very large counts do not
+//!     misalign other rows.
 //!
 //! The implementation is intentionally streaming-friendly at the output
 //! layer (it writes directly to the provided `Write`), while still
 //! aggregating per-author statistics in memory for predictable formatting.
 
-use std::{collections::HashMap, io::Write};
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    env,
+    io::{IsTerminal, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    str::FromStr,
+};
 
+use chrono::{DateTime, Datelike, Days, FixedOffset, Months, NaiveDate, Offset, Utc};
 use clap::Parser;
-use git_internal::internal::object::commit::Commit;
+use git_internal::{
+    hash::ObjectHash,
+    internal::object::{
+        ObjectTrait,
+        commit::Commit,
+        signature::Signature,
+        tree::{Tree, TreeItemMode},
+    },
+};
+use sea_orm::DatabaseConnection;
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use tokio::task::JoinSet;
 
-use crate::internal::{head::Head, log::date_parser::parse_date};
+use crate::{
+    internal::{
+        branch::Branch,
+        config_defaults::{ApplyConfigDefaults, ConfigDefault, parse_bool},
+        db::establish_connection,
+        head::Head,
+        log::{
+            date_parser::parse_date,
+            filter::{CommitFilter, DateMatch},
+            formatter::format_timestamp,
+        },
+    },
+    utils::client_storage::ClientStorage,
+};
 
 #[derive(Parser, Debug)]
 pub struct ShortlogArgs {
     /// Sort output according to the number of commits per author
-    #[clap(short = 'n', long = "numbered")]
+    #[clap(short = 'n', long = "numbered", overrides_with = "no_numbered")]
     pub numbered: bool,
 
+    /// Sort output by author name, overriding `shortlog.numbered`
+    #[clap(long = "no-numbered", overrides_with = "numbered")]
+    pub no_numbered: bool,
+
     /// Suppress commit description and provide a commit count summary only
     #[clap(short = 's', long = "summary")]
     pub summary: bool,
@@ -70,6 +228,10 @@ pub struct ShortlogArgs {
     #[clap(short = 'e', long = "email")]
     pub email: bool,
 
+    /// Group commits by committer rather than author
+    #[clap(short = 'c', long = "committer")]
+    pub committer: bool,
+
     /// Show commits more recent than a specific date
     #[clap(long = "since")]
     pub since: Option<String>,
@@ -77,13 +239,338 @@ pub struct ShortlogArgs {
     /// Show commits older than a specific date
     #[clap(long = "until")]
     pub until: Option<String>,
+
+    /// Which timestamp `--since`/`--until` compare against
+    #[clap(long = "date-match", value_enum, default_value_t = DateMatch::Committer)]
+    pub date_match: DateMatch,
+
+    /// Only count commits whose author matches this case-insensitive regular expression
+    #[clap(long = "author", value_name = "PATTERN")]
+    pub author: Option<String>,
+
+    /// Only count commits touching paths matching this glob (repeatable).
+    /// Each commit is diffed against its first parent, which is slow on long histories.
+    #[clap(long = "path", value_name = "GLOB")]
+    pub path: Vec<String>,
+
+    /// Prefix each subject with the first <n> hex digits of its commit hash (default 7)
+    #[clap(
+        long = "abbrev",
+        value_name = "n",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "7"
+    )]
+    pub abbrev: Option<usize>,
+
+    /// Append each author's first and last commit dates (committer time) to their header
+    #[clap(long = "dates")]
+    pub dates: bool,
+
+    /// Print up to <lines> lines of each commit's message body under its subject (default 3)
+    #[clap(
+        long = "body",
+        value_name = "lines",
+        num_args = 0..=1,
+        require_equals = true,
+        default_value_t = 0,
+        default_missing_value = "3"
+    )]
+    pub body: usize,
+
+    /// List each author's subjects oldest first; authors keep their order
+    #[clap(long = "subjects-reverse")]
+    pub subjects_reverse: bool,
+
+    /// List pairs of people credited together (author and `Co-authored-by` trailers)
+    /// with the number of commits they share, instead of per-author counts
+    #[clap(long = "pairs")]
+    pub pairs: bool,
+
+    /// Page the output through `$PAGER` when stdout is a terminal
+    #[clap(
+        short = 'p',
+        long = "pager",
+        visible_alias = "paginate",
+        overrides_with = "no_pager"
+    )]
+    pub pager: bool,
+
+    /// Write directly to stdout, overriding `--pager` and `shortlog.pager`
+    #[clap(short = 'P', long = "no-pager", overrides_with = "pager")]
+    pub no_pager: bool,
+
+    /// Report on the repository at <path> instead of the current one (repeatable)
+    #[clap(long = "repo", value_name = "PATH")]
+    pub repo: Vec<PathBuf>,
+
+    /// Also report on every repository listed in the nearest `.libra-workspace` file
+    #[clap(long = "workspace")]
+    pub workspace: bool,
+
+    /// Prefix each subject with the name of the repository it came from
+    #[clap(long = "show-repo")]
+    pub show_repo: bool,
+
+    /// Reuse per-author aggregates from earlier runs, reading only commits they do not cover
+    #[clap(long = "cache", overrides_with = "no_cache")]
+    pub cache: bool,
+
+    /// Recompute the report from the full history, overriding `--cache` and `shortlog.cache`
+    #[clap(long = "no-cache", overrides_with = "cache")]
+    pub no_cache: bool,
+
+    /// Count commits per calendar year instead of listing subjects
+    #[clap(long = "yearly", group = "timeline", conflicts_with = "summary")]
+    pub yearly: bool,
+
+    /// Count commits per calendar month instead of listing subjects
+    #[clap(long = "monthly", group = "timeline", conflicts_with = "summary")]
+    pub monthly: bool,
+
+    /// Count commits per ISO week instead of listing subjects
+    #[clap(long = "weekly", group = "timeline", conflicts_with = "summary")]
+    pub weekly: bool,
+
+    /// Break the `--yearly`/`--monthly`/`--weekly` counts down per author
+    #[clap(long = "by-author", requires = "timeline")]
+    pub by_author: bool,
+
+    /// Print the `--yearly`/`--monthly`/`--weekly` counts as CSV rows of period and count,
+    /// led by author and followed by machine id with `--by-author`
+    #[clap(long = "csv", requires = "timeline")]
+    pub csv: bool,
+
+    /// Also list periods without commits between the first and last period of the report
+    #[clap(long = "fill-gaps", requires = "timeline")]
+    pub fill_gaps: bool,
+
+    /// Print only the `<n> commits by <m> authors` totals line
+    #[clap(long = "oneline-total", conflicts_with_all = ["pairs", "timeline"])]
+    pub oneline_total: bool,
+
+    /// Abort when a commit object cannot be read instead of warning and skipping it
+    #[clap(long = "strict")]
+    pub strict: bool,
+
+    /// Count commits reachable from every local branch, not only from HEAD
+    #[clap(long = "all")]
+    pub all: bool,
+
+    /// Merge the identities listed in <path> (`name = alias1, alias2` lines) into one author
+    #[clap(long = "aliases", value_name = "PATH")]
+    pub aliases: Option<PathBuf>,
+
+    /// Read commit objects and diff trees on <n> threads (default: the number of CPUs)
+    #[clap(short = 'j', long = "jobs", value_name = "n")]
+    pub jobs: Option<usize>,
+}
+
+/// File listing the repositories of a workspace, one path per line relative to the file.
+/// Blank lines and lines starting with `#` are ignored.
+pub const WORKSPACE_FILE: &str = ".libra-workspace";
+
+/// File below `.libra/info` holding per-author aggregates from earlier runs; see
+/// `--cache`.
+const CACHE_FILE: &str = "shortlog-cache";
+
+/// How many option combinations [`CACHE_FILE`] keeps aggregates for.
+const CACHE_ENTRIES: usize = 8;
+
+/// Canonical identities read from an `--aliases` file, keyed by lower-cased alias.
+///
+/// Each non-blank line that does not start with `#` reads `canonical = alias1, alias2`,
+/// where the canonical identity is `Name` or `Name <email>` and each alias is a name or an
+/// email. A commit whose name or email matches an alias (case-insensitively) is counted
+/// under the canonical name, and under its email too when one is given, which `-e` needs
+/// to merge identities with different emails.
+#[derive(Debug, Default)]
+pub(crate) struct Aliases {
+    targets: BTreeMap<String, (String, Option<String>)>,
+    /// The file as read, which the cache fingerprint covers.
+    source: String,
+}
+
+impl Aliases {
+    pub(crate) fn load(path: &Path) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("cannot read aliases file '{}': {e}", path.display()))?;
+        Self::parse(&content).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    fn parse(content: &str) -> Result<Self, String> {
+        let mut aliases = BTreeMap::new();
+        for (number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (canonical, listed) = line
+                .split_once('=')
+                .ok_or_else(|| format!("line {}: expected `name = alias, ...`", number + 1))?;
+            let (name, email) = match canonical.split_once('<') {
+                Some((name, rest)) => (
+                    name.trim(),
+                    Some(rest.trim_end().trim_end_matches('>').trim().to_string()),
+                ),
+                None => (canonical.trim(), None),
+            };
+            if name.is_empty() {
+                return Err(format!("line {}: missing canonical name", number + 1));
+            }
+            let target = (name.to_string(), email.filter(|email| !email.is_empty()));
+            // The canonical name is an alias of itself, so `-e` merges its other emails too.
+            let keys = std::iter::once(name).chain(
+                listed
+                    .split(',')
+                    .map(|alias| alias.trim().trim_start_matches('<').trim_end_matches('>')),
+            );
+            for key in keys.filter(|key| !key.is_empty()) {
+                aliases.insert(key.to_lowercase(), target.clone());
+            }
+        }
+        Ok(Self {
+            targets: aliases,
+            source: content.to_string(),
+        })
+    }
+
+    /// The identity `name <email>` is counted under, by name first and then by email.
+    pub(crate) fn resolve(&self, name: &str, email: &str) -> (String, String) {
+        let target = self
+            .targets
+            .get(&name.to_lowercase())
+            .or_else(|| self.targets.get(&email.to_lowercase()));
+        match target {
+            Some((name, canonical_email)) => (
+                name.clone(),
+                canonical_email.clone().unwrap_or_else(|| email.to_string()),
+            ),
+            None => (name.to_string(), email.to_string()),
+        }
+    }
+}
+
+/// Calendar period `--yearly`, `--monthly` and `--weekly` count commits in.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Timeline {
+    Yearly,
+    Monthly,
+    Weekly,
+}
+
+impl Timeline {
+    fn from_args(args: &ShortlogArgs) -> Option<Self> {
+        if args.yearly {
+            Some(Timeline::Yearly)
+        } else if args.monthly {
+            Some(Timeline::Monthly)
+        } else if args.weekly {
+            Some(Timeline::Weekly)
+        } else {
+            None
+        }
+    }
+
+    /// The first day of the period containing `date`.
+    fn period_start(self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Timeline::Yearly => date.with_ordinal(1).expect("every year has a first day"),
+            Timeline::Monthly => date.with_day(1).expect("every month has a first day"),
+            Timeline::Weekly => date - Days::new(u64::from(date.weekday().num_days_from_monday())),
+        }
+    }
+
+    /// The first day of the period after the one starting on `start`.
+    fn next_period(self, start: NaiveDate) -> NaiveDate {
+        match self {
+            Timeline::Yearly => start + Months::new(12),
+            Timeline::Monthly => start + Months::new(1),
+            Timeline::Weekly => start + Days::new(7),
+        }
+    }
+
+    /// `2026` for years, `2026-01` for months, `2026-W02` for ISO weeks.
+    fn label(self, start: NaiveDate) -> String {
+        match self {
+            Timeline::Yearly => start.format("%Y").to_string(),
+            Timeline::Monthly => start.format("%Y-%m").to_string(),
+            Timeline::Weekly => {
+                let week = start.iso_week();
+                format!("{}-W{:02}", week.year(), week.week())
+            }
+        }
+    }
 }
 
+/// The calendar date of `signature` in its own timezone (`+0800` etc.), so a commit made
+/// late on the last day of a month (or year) counts towards that month wherever the report is run.
+/// An unparsable timezone is taken as UTC.
+fn local_date(signature: &Signature) -> NaiveDate {
+    let offset = parse_utc_offset(&signature.timezone).unwrap_or_else(|| Utc.fix());
+    DateTime::from_timestamp(signature.timestamp as i64, 0)
+        .unwrap_or_default()
+        .with_timezone(&offset)
+        .date_naive()
+}
+
+fn parse_utc_offset(timezone: &str) -> Option<FixedOffset> {
+    let (sign, digits) = match timezone.as_bytes().first()? {
+        b'+' => (1, &timezone[1..]),
+        b'-' => (-1, &timezone[1..]),
+        _ => return None,
+    };
+    if digits.len() != 4 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let hours: i32 = digits[..2].parse().ok()?;
+    let minutes: i32 = digits[2..].parse().ok()?;
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+impl ApplyConfigDefaults for ShortlogArgs {
+    const CONFIG_DEFAULTS: &'static [ConfigDefault] = &[
+        ConfigDefault {
+            key: "shortlog.numbered",
+            arg: "numbered",
+            negation: Some("no_numbered"),
+        },
+        ConfigDefault {
+            key: "shortlog.pager",
+            arg: "pager",
+            negation: Some("no_pager"),
+        },
+        ConfigDefault {
+            key: "shortlog.cache",
+            arg: "cache",
+            negation: Some("no_cache"),
+        },
+    ];
+
+    fn apply_config_default(&mut self, arg: &str, value: &str) -> Result<(), String> {
+        match arg {
+            "numbered" => self.numbered = parse_bool(value)?,
+            "pager" => self.pager = parse_bool(value)?,
+            "cache" => self.cache = parse_bool(value)?,
+            _ => unreachable!("unknown shortlog config default: {arg}"),
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
 struct AuthorStats {
     name: String,
     email: String,
     count: usize,
-    subjects: Vec<String>,
+    /// Subjects with the author timestamp of their commit, newest first.
+    subjects: Vec<(usize, String)>,
+    /// Earliest and latest committer timestamps among the counted commits.
+    first: Option<i64>,
+    last: Option<i64>,
+    /// Commits per `--yearly`/`--monthly`/`--weekly` period, keyed by the period's first day.
+    #[serde(default)]
+    periods: BTreeMap<NaiveDate, usize>,
 }
 
 impl AuthorStats {
@@ -93,17 +580,50 @@ impl AuthorStats {
             email,
             count: 0,
             subjects: Vec::new(),
+            first: None,
+            last: None,
+            periods: BTreeMap::new(),
         }
     }
 
-    fn add_commit(&mut self, subject: String) {
+    fn add_commit(&mut self, subject: String, authored: usize, timestamp: i64) {
         self.count += 1;
-        self.subjects.push(subject);
+        self.subjects.push((authored, subject));
+        self.first = Some(self.first.map_or(timestamp, |first| first.min(timestamp)));
+        self.last = Some(self.last.map_or(timestamp, |last| last.max(timestamp)));
+    }
+
+    fn add_period(&mut self, period: Option<NaiveDate>) {
+        if let Some(period) = period {
+            *self.periods.entry(period).or_default() += 1;
+        }
+    }
+
+    /// Fold in the stats of commits that are newer in history than the ones counted so far.
+    /// On equal timestamps their subjects go first, as a full traversal would list them.
+    fn merge_newer(&mut self, newer: AuthorStats) {
+        self.count += newer.count;
+        let older = std::mem::replace(&mut self.subjects, newer.subjects);
+        self.subjects.extend(older);
+        self.subjects
+            .sort_by_key(|(timestamp, _)| Reverse(*timestamp));
+        self.first = self.first.into_iter().chain(newer.first).min();
+        self.last = self.last.max(newer.last);
+        for (period, count) in newer.periods {
+            *self.periods.entry(period).or_default() += count;
+        }
     }
 }
 
 pub async fn execute_to(args: ShortlogArgs, writer: &mut impl Write) -> std::io::Result<()> {
-    if !crate::utils::util::check_repo_exist() {
+    let repos = match report_repositories(&args) {
+        Ok(repos) => repos,
+        Err(e) => {
+            eprintln!("fatal: {}", e);
+            return Ok(());
+        }
+    };
+    if repos.is_empty() && !crate::utils::util::check_repo_exist() {
         return Ok(());
     }
 
@@ -132,35 +652,74 @@ pub async fn execute_to(args: ShortlogArgs, writer: &mut impl Write) -> std::io:
         None
     };
 
-    let commits = get_commits_for_shortlog(&args, since_ts, until_ts).await;
-
-    let mut author_map: HashMap<String, AuthorStats> = HashMap::new();
+    let path_filter = if args.path.is_empty() {
+        None
+    } else {
+        let patterns = args
+            .path
+            .iter()
+            .map(|p| p.trim_end_matches('/'))
+            .collect::<Vec<_>>();
+        match wax::any(patterns) {
+            Ok(glob) => Some(glob),
+            Err(e) => {
+                eprintln!("fatal: invalid --path glob: {}", e);
+                return Ok(());
+            }
+        }
+    };
 
-    for commit in commits {
-        let author_name = commit.author.name.clone();
-        let author_email = commit.author.email.clone();
+    let filter = match CommitFilter::new()
+        .since(since_ts)
+        .until(until_ts)
+        .date_match(args.date_match)
+        .path_glob(path_filter)
+        .author(args.author.as_deref())
+    {
+        Ok(filter) => filter,
+        Err(e) => {
+            eprintln!("fatal: {}", e);
+            return Ok(());
+        }
+    };
 
-        // If email is not requested, group by name only.
-        // If email is requested, group by name + email.
-        let key = if args.email {
-            format!("{} <{}>", author_name, author_email)
-        } else {
-            author_name.clone()
-        };
+    if args.jobs == Some(0) {
+        eprintln!("fatal: --jobs must be greater than zero");
+        return Ok(());
+    }
+    if args.pairs && Timeline::from_args(&args).is_some() && !args.by_author {
+        eprintln!("fatal: --pairs with --yearly, --monthly or --weekly needs --by-author");
+        return Ok(());
+    }
 
-        let subject = commit
-            .message
-            .trim()
-            .lines()
-            .next()
-            .unwrap_or("")
-            .to_string();
+    let aliases = match args.aliases.as_deref().map(Aliases::load).transpose() {
+        Ok(aliases) => aliases.unwrap_or_default(),
+        Err(e) => {
+            eprintln!("fatal: {}", e);
+            return Ok(());
+        }
+    };
 
-        author_map
-            .entry(key)
-            .or_insert_with(|| AuthorStats::new(author_name.clone(), author_email.clone()))
-            .add_commit(subject);
-    }
+    let author_map = if repos.is_empty() && args.cache && !args.no_cache && !args.all {
+        aggregate_with_cache(&args, &aliases, &filter, since_ts, until_ts).await
+    } else {
+        get_commits_for_shortlog(&args, &repos, &filter)
+            .await
+            .map(|commits| {
+                let mut author_map = HashMap::new();
+                for (repo, commit) in &commits {
+                    record_commit(&args, &aliases, &mut author_map, repo, commit);
+                }
+                author_map
+            })
+    };
+    let author_map = match author_map {
+        Ok(author_map) => author_map,
+        Err(e) => {
+            eprintln!("fatal: {}", e);
+            return Ok(());
+        }
+    };
 
     let mut authors: Vec<(&String, &AuthorStats)> = author_map.iter().collect();
 
@@ -175,109 +734,1054 @@ pub async fn execute_to(args: ShortlogArgs, writer: &mut impl Write) -> std::io:
         authors.sort_by(|a, b| a.1.name.to_lowercase().cmp(&b.1.name.to_lowercase()));
     }
 
-    // Determine the width needed for the commit count column.
-    // Use at least 4 characters to preserve the existing layout for small repositories.
-    let max_count = authors
-        .iter()
-        .map(|(_, stats)| stats.count)
-        .max()
-        .unwrap_or(0);
+    let authors: Vec<&AuthorStats> = authors.into_iter().map(|(_, stats)| stats).collect();
+    if args.oneline_total {
+        return write_total(writer, &authors, args.committer);
+    }
+    if let Some(timeline) = Timeline::from_args(&args) {
+        let email = args.email && !args.pairs;
+        return match (args.by_author, args.csv) {
+            (false, csv) => write_timeline_totals(writer, &authors, timeline, args.fill_gaps, csv),
+            (true, false) => write_timeline(writer, &authors, email, timeline, args.fill_gaps),
+            (true, true) => write_timeline_csv(writer, &authors, email, timeline, args.fill_gaps),
+        };
+    }
+    // Pair labels already carry the emails when `-e` is given.
+    write_report(
+        writer,
+        &authors,
+        args.email && !args.pairs,
+        args.summary,
+        args.dates,
+        args.subjects_reverse,
+    )
+}
+
+/// Count `commit` (from the repository named `repo`) towards the groups it belongs to,
+/// with identities merged by `aliases`.
+///
+/// Commits must be recorded newest first so that each group's subjects stay in order.
+fn record_commit(
+    args: &ShortlogArgs,
+    aliases: &Aliases,
+    author_map: &mut HashMap<String, AuthorStats>,
+    repo: &str,
+    commit: &Commit,
+) {
+    let ident = if args.committer {
+        &commit.committer
+    } else {
+        &commit.author
+    };
+    let (author_name, author_email) = aliases.resolve(&ident.name, &ident.email);
+    let authored = commit.author.timestamp;
+    let timestamp = commit.committer.timestamp as i64;
+    // Periods follow the timestamp `--date-match` selects, the author's for `any`.
+    let period = Timeline::from_args(args).map(|timeline| {
+        let signature = match args.date_match {
+            DateMatch::Committer => &commit.committer,
+            DateMatch::Author | DateMatch::Any => &commit.author,
+        };
+        timeline.period_start(local_date(signature))
+    });
+
+    let mut lines = commit.message.trim().lines();
+    let subject = lines.next().unwrap_or("");
+    let subject = match args.abbrev {
+        Some(len) => {
+            let hash = commit.id.to_string();
+            format!("{} {subject}", &hash[..len.min(hash.len())])
+        }
+        None => subject.to_string(),
+    };
+    let mut subject = if args.show_repo {
+        format!("[{repo}] {subject}")
+    } else {
+        subject
+    };
+    // Body lines ride along after a newline; `write_report` indents them.
+    for line in lines
+        .skip_while(|line| line.trim().is_empty())
+        .take(args.body)
+    {
+        subject.push('\n');
+        subject.push_str(line.trim_end());
+    }
+
+    if args.pairs {
+        for pair in collaboration_pairs(commit, aliases, args.email) {
+            let stats = author_map
+                .entry(pair.clone())
+                .or_insert_with(|| AuthorStats::new(pair, String::new()));
+            stats.add_commit(subject.clone(), authored, timestamp);
+            stats.add_period(period);
+        }
+        return;
+    }
+
+    // If email is not requested, group by name only.
+    // If email is requested, group by name + email.
+    let key = if args.email {
+        format!("{} <{}>", author_name, author_email)
+    } else {
+        author_name.clone()
+    };
+
+    let stats = author_map
+        .entry(key)
+        .or_insert_with(|| AuthorStats::new(author_name, author_email));
+    stats.add_commit(subject, authored, timestamp);
+    stats.add_period(period);
+}
+
+/// Like [`execute_to`], but routes the report through the user's pager when paging is
+/// enabled and `writer` is a terminal (`is_terminal`).
+///
+/// Falls back to writing directly when no pager applies or the pager fails to start.
+pub async fn execute_paged_to(
+    args: ShortlogArgs,
+    writer: &mut impl Write,
+    is_terminal: bool,
+) -> std::io::Result<()> {
+    let Some(pager) = pager_command(&args, is_terminal, std::env::var("PAGER").ok()) else {
+        return execute_to(args, writer).await;
+    };
+
+    let mut output = Vec::new();
+    execute_to(args, &mut output).await?;
+
+    let mut words = pager.split_whitespace();
+    let program = words.next().unwrap_or_default();
+    match Command::new(program)
+        .args(words)
+        .stdin(Stdio::piped())
+        .spawn()
+    {
+        Ok(mut child) => {
+            if let Some(mut stdin) = child.stdin.take() {
+                // The user may quit the pager before reading everything.
+                let _ = stdin.write_all(&output);
+            }
+            child.wait()?;
+            Ok(())
+        }
+        Err(e) => {
+            tracing::debug!("failed to start pager '{pager}': {e}");
+            writer.write_all(&output)
+        }
+    }
+}
+
+pub async fn execute(args: ShortlogArgs) {
+    let is_terminal = std::io::stdout().is_terminal();
+    if let Err(e) = execute_paged_to(args, &mut std::io::stdout(), is_terminal).await {
+        // Ignore broken pipe errors which happen when piping to head/less
+        if e.kind() != std::io::ErrorKind::BrokenPipe {
+            eprintln!("error: {}", e);
+        }
+    }
+}
+
+/// The pager command to feed output to, if any.
+///
+/// Paging needs `--pager` (or `shortlog.pager`) without `--no-pager`, a terminal, and a
+/// non-blank `configured` pager (`$PAGER`).
+fn pager_command(
+    args: &ShortlogArgs,
+    is_terminal: bool,
+    configured: Option<String>,
+) -> Option<String> {
+    if !args.pager || args.no_pager || !is_terminal {
+        return None;
+    }
+    configured.filter(|pager| !pager.trim().is_empty())
+}
+
+/// `Co-authored-by: Name <email>` trailers in `message`, as `(name, email)` pairs.
+///
+/// The trailer key is matched case-insensitively; a trailer without `<email>` yields an
+/// empty email.
+fn co_authors(message: &str) -> Vec<(String, String)> {
+    message
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            if !key.trim().eq_ignore_ascii_case("co-authored-by") {
+                return None;
+            }
+            let value = value.trim();
+            let (name, email) = match value.split_once('<') {
+                Some((name, rest)) => (name.trim(), rest.trim_end_matches('>').trim()),
+                None => (value, ""),
+            };
+            (!name.is_empty()).then(|| (name.to_string(), email.to_string()))
+        })
+        .collect()
+}
+
+/// Every pair of distinct people credited on `commit`, labelled `A + B`.
+///
+/// People are identified by name, or by `name <email>` when `email` is set, after merging
+/// `aliases`, and each pair is ordered case-insensitively so the same two people always
+/// produce the same label.
+fn collaboration_pairs(commit: &Commit, aliases: &Aliases, email: bool) -> Vec<String> {
+    let identity = |name: &str, mail: &str| {
+        let (name, mail) = aliases.resolve(name, mail);
+        if email {
+            format!("{name} <{mail}>")
+        } else {
+            name.to_string()
+        }
+    };
+    let mut people = vec![identity(&commit.author.name, &commit.author.email)];
+    for (name, mail) in co_authors(&commit.message) {
+        let person = identity(&name, &mail);
+        if !people.contains(&person) {
+            people.push(person);
+        }
+    }
+    people.sort_by_key(|person| person.to_lowercase());
+
+    let mut pairs = Vec::new();
+    for (i, first) in people.iter().enumerate() {
+        for second in &people[i + 1..] {
+            pairs.push(format!("{first} + {second}"));
+        }
+    }
+    pairs
+}
+
+/// Render the per-author report.
+///
+/// The count column width is computed once across every printed group (at least 4 to
+/// preserve the layout for small repositories), and subjects are indented to start just
+/// past that column, so very large counts never shift later rows. Body lines recorded after
+/// a subject (see `--body`) are indented four more columns. With `dates`, each header ends
+/// with the author's `(first .. last)` commit dates.
+fn write_report(
+    writer: &mut impl Write,
+    authors: &[&AuthorStats],
+    email: bool,
+    summary: bool,
+    dates: bool,
+    subjects_reverse: bool,
+) -> std::io::Result<()> {
+    let max_count = authors.iter().map(|stats| stats.count).max().unwrap_or(0);
     let width = std::cmp::max(4, max_count.to_string().len());
+    let indent = " ".repeat(width + 2);
 
-    for (_key, stats) in authors {
-        if args.email {
-            writeln!(
+    for stats in authors {
+        if email {
+            write!(
                 writer,
                 "{:>width$}  {} <{}>",
-                stats.count,
-                stats.name,
-                stats.email,
-                width = width
+                stats.count, stats.name, stats.email
             )?;
         } else {
-            writeln!(
+            write!(writer, "{:>width$}  {}", stats.count, stats.name)?;
+        }
+        if dates && let (Some(first), Some(last)) = (stats.first, stats.last) {
+            write!(
                 writer,
-                "{:>width$}  {}",
-                stats.count,
-                stats.name,
-                width = width
+                " ({} .. {})",
+                format_timestamp(first),
+                format_timestamp(last)
             )?;
         }
-        if !args.summary {
-            for subject in &stats.subjects {
-                writeln!(writer, "      {}", subject)?;
+        writeln!(writer)?;
+        if !summary {
+            let mut subjects: Vec<_> = stats.subjects.iter().map(|(_, s)| s).collect();
+            if subjects_reverse {
+                subjects.reverse();
+            }
+            for subject in subjects {
+                let mut lines = subject.lines();
+                writeln!(writer, "{indent}{}", lines.next().unwrap_or(""))?;
+                for line in lines {
+                    if line.is_empty() {
+                        writeln!(writer)?;
+                    } else {
+                        writeln!(writer, "{indent}    {line}")?;
+                    }
+                }
             }
         }
     }
+    Ok(())
+}
+
+/// Writes the `--oneline-total` line. Without `--pairs` every counted commit belongs to
+/// exactly one group, so the group counts add up to the number of commits.
+fn write_total(
+    writer: &mut impl Write,
+    authors: &[&AuthorStats],
+    committer: bool,
+) -> std::io::Result<()> {
+    let commits: usize = authors.iter().map(|stats| stats.count).sum();
+    let people = if committer { "committer" } else { "author" };
+    writeln!(
+        writer,
+        "{commits} commit{} by {} {people}{}",
+        if commits == 1 { "" } else { "s" },
+        authors.len(),
+        if authors.len() == 1 { "" } else { "s" },
+    )
+}
 
+/// The periods to list from `periods`: those with commits, or with `fill_gaps` every period
+/// from the first to the last one of the whole report.
+fn timeline_rows(
+    periods: &BTreeMap<NaiveDate, usize>,
+    range: Option<(NaiveDate, NaiveDate)>,
+    timeline: Timeline,
+    fill_gaps: bool,
+) -> Vec<(NaiveDate, usize)> {
+    let Some((first, last)) = range.filter(|_| fill_gaps) else {
+        return periods.iter().map(|(p, c)| (*p, *c)).collect();
+    };
+    let mut rows = Vec::new();
+    let mut period = first;
+    while period <= last {
+        rows.push((period, periods.get(&period).copied().unwrap_or(0)));
+        period = timeline.next_period(period);
+    }
+    rows
+}
+
+/// The first and last period any author has commits in.
+fn timeline_range(authors: &[&AuthorStats]) -> Option<(NaiveDate, NaiveDate)> {
+    let first = authors
+        .iter()
+        .filter_map(|s| s.periods.keys().next())
+        .min()?;
+    let last = authors
+        .iter()
+        .filter_map(|s| s.periods.keys().next_back())
+        .max()?;
+    Some((*first, *last))
+}
+
+/// Writes a `period  count` line (or a `period,count` row with `csv`) per period, counting the
+/// commits of every author together.
+fn write_timeline_totals(
+    writer: &mut impl Write,
+    authors: &[&AuthorStats],
+    timeline: Timeline,
+    fill_gaps: bool,
+    csv: bool,
+) -> std::io::Result<()> {
+    let mut totals: BTreeMap<NaiveDate, usize> = BTreeMap::new();
+    for stats in authors {
+        for (period, count) in &stats.periods {
+            *totals.entry(*period).or_default() += count;
+        }
+    }
+    let range = timeline_range(authors);
+    if csv {
+        writeln!(writer, "period,count")?;
+    }
+    for (period, count) in timeline_rows(&totals, range, timeline, fill_gaps) {
+        if csv {
+            writeln!(writer, "{},{count}", timeline.label(period))?;
+        } else {
+            writeln!(writer, "{}  {count}", timeline.label(period))?;
+        }
+    }
     Ok(())
 }
 
-pub async fn execute(args: ShortlogArgs) {
-    if let Err(e) = execute_to(args, &mut std::io::stdout()).await {
-        // Ignore broken pipe errors which happen when piping to head/less
-        if e.kind() != std::io::ErrorKind::BrokenPipe {
-            eprintln!("error: {}", e);
+/// Like [`write_report`], but lists a `period  count` line per period under each author.
+fn write_timeline(
+    writer: &mut impl Write,
+    authors: &[&AuthorStats],
+    email: bool,
+    timeline: Timeline,
+    fill_gaps: bool,
+) -> std::io::Result<()> {
+    let range = timeline_range(authors);
+    let max_count = authors.iter().map(|stats| stats.count).max().unwrap_or(0);
+    let width = std::cmp::max(4, max_count.to_string().len());
+    let indent = " ".repeat(width + 2);
+
+    for stats in authors {
+        if email {
+            writeln!(
+                writer,
+                "{:>width$}  {} <{}>",
+                stats.count, stats.name, stats.email
+            )?;
+        } else {
+            writeln!(writer, "{:>width$}  {}", stats.count, stats.name)?;
+        }
+        for (period, count) in timeline_rows(&stats.periods, range, timeline, fill_gaps) {
+            writeln!(writer, "{indent}{}  {count}", timeline.label(period))?;
         }
     }
+    Ok(())
 }
 
-async fn get_commits_for_shortlog(
-    _args: &ShortlogArgs,
-    since_ts: Option<i64>,
-    until_ts: Option<i64>,
-) -> Vec<Commit> {
-    use crate::command::log::get_reachable_commits;
+/// Writes `author,period,count,machine_id` rows (with a header) for spreadsheets.
+fn write_timeline_csv(
+    writer: &mut impl Write,
+    authors: &[&AuthorStats],
+    email: bool,
+    timeline: Timeline,
+    fill_gaps: bool,
+) -> std::io::Result<()> {
+    let range = timeline_range(authors);
+    writeln!(writer, "author,period,count,machine_id")?;
+    for stats in authors {
+        let author = if email {
+            format!("{} <{}>", stats.name, stats.email)
+        } else {
+            stats.name.clone()
+        };
+        let author = csv_field(&author);
+        let id = machine_id(&stats.name, &stats.email);
+        for (period, count) in timeline_rows(&stats.periods, range, timeline, fill_gaps) {
+            writeln!(writer, "{author},{},{count},{id}", timeline.label(period))?;
+        }
+    }
+    Ok(())
+}
 
-    let head = Head::current().await;
-    let commit_hash = match head {
-        Head::Branch(name) => {
-            let branch = crate::internal::branch::Branch::find_branch(&name, None)
-                .await
-                .map(|b| b.commit.to_string());
-            match branch {
-                Some(h) => h,
-                None => {
+/// A stable identifier for an author: the first 16 hex digits of the SHA-1 of the lowercased,
+/// trimmed name and email, so it survives runs and changes in display-name casing.
+fn machine_id(name: &str, email: &str) -> String {
+    let identity = format!(
+        "{}\n{}",
+        name.trim().to_lowercase(),
+        email.trim().to_lowercase()
+    );
+    hex::encode(Sha1::digest(identity.as_bytes()))[..16].to_string()
+}
+
+/// Quotes `value` for CSV (RFC 4180) when it contains a comma, quote or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// The repositories named by `--repo` and `--workspace`, in order and without duplicates.
+/// Empty means the report covers the current repository only.
+fn report_repositories(args: &ShortlogArgs) -> Result<Vec<PathBuf>, String> {
+    let mut repos = args.repo.clone();
+    if args.workspace {
+        let cwd = env::current_dir().map_err(|e| e.to_string())?;
+        let file = cwd
+            .ancestors()
+            .map(|dir| dir.join(WORKSPACE_FILE))
+            .find(|file| file.is_file())
+            .ok_or_else(|| format!("no {WORKSPACE_FILE} file found in {}", cwd.display()))?;
+        repos.extend(read_workspace_file(&file).map_err(|e| e.to_string())?);
+    }
+    let mut seen = Vec::new();
+    repos.retain(|repo| {
+        let key = repo.canonicalize().unwrap_or_else(|_| repo.clone());
+        let fresh = !seen.contains(&key);
+        seen.push(key);
+        fresh
+    });
+    Ok(repos)
+}
+
+/// Repository paths listed in the workspace file at `file`, resolved against its directory.
+fn read_workspace_file(file: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let base = file.parent().unwrap_or(Path::new("."));
+    Ok(std::fs::read_to_string(file)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| base.join(line))
+        .collect())
+}
+
+/// Display name of the repository at `path`: its directory name.
+fn repo_name(path: &Path) -> String {
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+/// A repository read through explicit paths, so several can be traversed in one process
+/// without changing its working directory.
+struct Repository {
+    name: String,
+    objects: ClientStorage,
+    db: DatabaseConnection,
+}
+
+impl Repository {
+    /// Opens the repository containing `path`.
+    async fn open(path: &Path) -> std::io::Result<Self> {
+        let storage = crate::utils::util::try_get_storage_path(Some(path.to_path_buf()))?;
+        let database = storage.join(crate::utils::util::DATABASE);
+        let db = establish_connection(&database.to_string_lossy()).await?;
+        Ok(Self {
+            name: repo_name(path),
+            objects: ClientStorage::init(storage.join("objects")),
+            db,
+        })
+    }
+
+    /// The commit `HEAD` points to.
+    async fn head_commit(&self) -> Option<String> {
+        match Head::current_with_conn(&self.db).await {
+            Head::Branch(name) => {
+                let branch = Branch::find_branch_with_conn(&self.db, &name, None)
+                    .await
+                    .map(|b| b.commit.to_string());
+                if branch.is_none() {
                     eprintln!("fatal: current branch has no commits");
-                    return Vec::new();
                 }
+                branch
             }
+            Head::Detached(hash) => Some(hash.to_string()),
         }
-        Head::Detached(hash) => hash.to_string(),
-    };
+    }
 
-    let mut commits: Vec<Commit> = get_reachable_commits(commit_hash, None)
-        .await
+    /// Walks this repository from `tips` like [`walk_commits`] and returns every commit
+    /// reached with whether it passes `filter`. The tree diffs the path filters need run on
+    /// the walk's tasks, for the commits the cheaper filters keep; a tree that cannot be read
+    /// is handled like an unreadable commit in [`load_commit`].
+    async fn filtered_walk(
+        &self,
+        tips: Vec<String>,
+        filter: &CommitFilter<'_>,
+        strict: bool,
+        jobs: usize,
+        stop: impl FnMut(&str) -> bool,
+    ) -> Result<Vec<(Commit, bool)>, String> {
+        let diff = filter.has_path_filter();
+        let visited = walk_commits(&self.objects, tips, strict, jobs, stop, |commit| {
+            (diff && filter.matches_cheap(commit)).then(|| {
+                let objects = self.objects.clone();
+                let commit = commit.clone();
+                move || changed_paths(&objects, &commit)
+            })
+        })
+        .await?;
+
+        let mut commits = Vec::with_capacity(visited.len());
+        for (commit, changed) in visited {
+            let keep = match changed {
+                _ if !filter.matches_cheap(&commit) => false,
+                None => true,
+                Some(Ok(changed)) => filter.matches_changed_paths(&changed),
+                Some(Err(e)) if strict => {
+                    return Err(format!("unreadable tree of commit {}: {e}", commit.id));
+                }
+                Some(Err(e)) => {
+                    tracing::warn!("skipping commit {} with unreadable tree: {e}", commit.id);
+                    false
+                }
+            };
+            commits.push((commit, keep));
+        }
+        Ok(commits)
+    }
+}
+
+/// The paths in `objects` added, modified or deleted by `commit` relative to its first
+/// parent.
+fn changed_paths(objects: &ClientStorage, commit: &Commit) -> Result<Vec<PathBuf>, String> {
+    let mut new = HashMap::new();
+    tree_files(objects, commit.tree_id, Path::new(""), &mut new)?;
+    let mut old = HashMap::new();
+    if let Some(parent) = commit.parent_commit_ids.first() {
+        let data = objects.get(parent).map_err(|e| e.to_string())?;
+        let parent = parse_commit(&data, *parent)?;
+        tree_files(objects, parent.tree_id, Path::new(""), &mut old)?;
+    }
+
+    let mut changed: Vec<PathBuf> = new
+        .iter()
+        .filter(|(path, id)| old.get(*path) != Some(*id))
+        .map(|(path, _)| path.clone())
+        .chain(old.keys().filter(|path| !new.contains_key(*path)).cloned())
+        .collect();
+    changed.sort();
+    Ok(changed)
+}
+
+/// Collects every non-tree entry below the tree `id` into `files`, keyed by its path under
+/// `dir`.
+fn tree_files(
+    objects: &ClientStorage,
+    id: ObjectHash,
+    dir: &Path,
+    files: &mut HashMap<PathBuf, ObjectHash>,
+) -> Result<(), String> {
+    let data = objects.get(&id).map_err(|e| e.to_string())?;
+    let tree = Tree::from_bytes(&data, id).map_err(|e| e.to_string())?;
+    for item in tree.tree_items {
+        let path = dir.join(&item.name);
+        if item.mode == TreeItemMode::Tree {
+            tree_files(objects, item.id, &path, files)?;
+        } else {
+            files.insert(path, item.id);
+        }
+    }
+    Ok(())
+}
+
+/// The commits to report, each with the name of its repository, newest first.
+///
+/// Without `repos` this is the current repository. Otherwise every repository is traversed
+/// from its own `HEAD` and the streams are merged; repositories that cannot be opened are
+/// reported and skipped. Fails only for an unreadable object under `--strict`.
+async fn get_commits_for_shortlog(
+    args: &ShortlogArgs,
+    repos: &[PathBuf],
+    filter: &CommitFilter<'_>,
+) -> Result<Vec<(String, Commit)>, String> {
+    if repos.is_empty() {
+        let repo = Repository::open(&crate::utils::util::working_dir())
+            .await
+            .map_err(|e| e.to_string())?;
+        return Ok(repo_commits(args, &repo, filter)
+            .await?
+            .into_iter()
+            .map(|commit| (repo.name.clone(), commit))
+            .collect());
+    }
+
+    let mut commits = Vec::new();
+    for path in repos {
+        let repo = match Repository::open(path).await {
+            Ok(repo) => repo,
+            Err(e) => {
+                eprintln!("warning: skipping repository '{}': {}", path.display(), e);
+                continue;
+            }
+        };
+        commits.extend(
+            repo_commits(args, &repo, filter)
+                .await?
+                .into_iter()
+                .map(|commit| (repo.name.clone(), commit)),
+        );
+    }
+    commits.sort_by_key(|(_, commit)| Reverse(commit.author.timestamp));
+    Ok(commits)
+}
+
+/// The commits of `repo` that pass the filters.
+async fn repo_commits(
+    args: &ShortlogArgs,
+    repo: &Repository,
+    filter: &CommitFilter<'_>,
+) -> Result<Vec<Commit>, String> {
+    let mut tips: Vec<String> = repo.head_commit().await.into_iter().collect();
+    if args.all {
+        tips.extend(
+            Branch::list_branches_with_conn(&repo.db, None)
+                .await
+                .into_iter()
+                .map(|branch| branch.commit.to_string()),
+        );
+    }
+
+    // Tips share history, so the walk must yield each commit once before any filtering.
+    let jobs = args.jobs.unwrap_or_else(default_jobs);
+    let mut commits: Vec<Commit> = repo
+        .filtered_walk(tips, filter, args.strict, jobs, |_| false)
+        .await?
         .into_iter()
-        .filter(|c| passes_filter(c, since_ts, until_ts))
+        .filter_map(|(commit, keep)| keep.then_some(commit))
         .collect();
 
-    commits.sort_by(|a, b| b.author.timestamp.cmp(&a.author.timestamp));
+    commits.sort_by_key(|commit| Reverse(commit.author.timestamp));
 
-    commits
+    Ok(commits)
 }
 
-fn passes_filter(commit: &Commit, since_ts: Option<i64>, until_ts: Option<i64>) -> bool {
-    let commit_ts = commit.committer.timestamp as i64;
+/// Every commit in `objects` reachable from any of `tips`, each exactly once, in no
+/// particular order. Commits are read by up to `jobs` tasks.
+pub(crate) async fn reachable_commits(
+    objects: &ClientStorage,
+    tips: Vec<String>,
+    strict: bool,
+    jobs: usize,
+) -> Result<Vec<Commit>, String> {
+    let commits = walk_commits(objects, tips, strict, jobs, |_| false, |_| None::<fn()>).await?;
+    Ok(commits.into_iter().map(|(commit, _)| commit).collect())
+}
 
-    if let Some(since) = since_ts
-        && commit_ts < since
-    {
-        return false;
+/// The number of tasks `--jobs` defaults to: the number of CPUs, or 1 if unknown.
+pub(crate) fn default_jobs() -> usize {
+    std::thread::available_parallelism().map_or(1, usize::from)
+}
+
+/// The work [`walk_commits`] hands to a blocking task.
+enum WalkTask<T> {
+    /// Reading a commit, `None` if it was skipped as unreadable.
+    Load(Option<Box<Commit>>),
+    /// An `inspect` job for the commit with this id.
+    Inspect(String, T),
+}
+
+/// Walks the history in `objects` from `tips` and returns every commit reached, each once,
+/// in the order a breadth-first walk visits them. Ids for which `stop` returns true are
+/// neither loaded nor walked past.
+///
+/// Up to `jobs` blocking tasks work through a queue shared by the whole walk, so a task
+/// picks up the next id as soon as any commit's parents are known. A linear history still
+/// reads one commit at a time; what overlaps there is the per-commit work: `inspect` may
+/// return a job for each loaded commit, which runs on the same tasks while the walk goes
+/// on, and whose result is returned with the commit.
+async fn walk_commits<T, J>(
+    objects: &ClientStorage,
+    tips: Vec<String>,
+    strict: bool,
+    jobs: usize,
+    mut stop: impl FnMut(&str) -> bool,
+    mut inspect: impl FnMut(&Commit) -> Option<J>,
+) -> Result<Vec<(Commit, Option<T>)>, String>
+where
+    T: Send + 'static,
+    J: FnOnce() -> T + Send + 'static,
+{
+    let mut seen = HashSet::new();
+    let mut pending: VecDeque<String> = tips.iter().cloned().collect();
+    let mut inspections: VecDeque<(String, J)> = VecDeque::new();
+    let mut reached: HashMap<String, (Commit, Option<T>)> = HashMap::new();
+    let mut tasks = JoinSet::new();
+
+    loop {
+        // Loads come first: they are what discovers more work.
+        while tasks.len() < jobs {
+            if let Some(id) = pending.pop_front() {
+                if stop(&id) || !seen.insert(id.clone()) {
+                    continue;
+                }
+                let objects = objects.clone();
+                tasks.spawn_blocking(move || {
+                    load_commit(&objects, &id, strict)
+                        .map(|commit| WalkTask::Load(commit.map(Box::new)))
+                });
+            } else if let Some((id, job)) = inspections.pop_front() {
+                tasks.spawn_blocking(move || Ok(WalkTask::Inspect(id, job())));
+            } else {
+                break;
+            }
+        }
+        let Some(done) = tasks.join_next().await else {
+            break;
+        };
+        match done.map_err(|e| format!("commit reader failed: {e}"))?? {
+            WalkTask::Load(None) => {}
+            WalkTask::Load(Some(commit)) => {
+                let commit = *commit;
+                pending.extend(commit.parent_commit_ids.iter().map(ToString::to_string));
+                let id = commit.id.to_string();
+                if let Some(job) = inspect(&commit) {
+                    inspections.push_back((id.clone(), job));
+                }
+                reached.insert(id, (commit, None));
+            }
+            WalkTask::Inspect(id, result) => {
+                if let Some((_, slot)) = reached.get_mut(&id) {
+                    *slot = Some(result);
+                }
+            }
+        }
     }
 
-    if let Some(until) = until_ts
-        && commit_ts > until
-    {
+    // Tasks finish in any order; replay the walk over what was read to restore it.
+    let mut commits = Vec::with_capacity(reached.len());
+    let mut order: VecDeque<String> = tips.into();
+    while let Some(id) = order.pop_front() {
+        if let Some((commit, result)) = reached.remove(&id) {
+            order.extend(commit.parent_commit_ids.iter().map(ToString::to_string));
+            commits.push((commit, result));
+        }
+    }
+    Ok(commits)
+}
+
+/// Loads the commit `id` from `objects`. An object that cannot be read or parsed is an error
+/// under `--strict`; otherwise it is logged and skipped (`Ok(None)`), together with any
+/// history only reachable through it.
+fn load_commit(objects: &ClientStorage, id: &str, strict: bool) -> Result<Option<Commit>, String> {
+    let loaded = ObjectHash::from_str(id).and_then(|hash| {
+        let data = objects.get(&hash).map_err(|e| e.to_string())?;
+        parse_commit(&data, hash)
+    });
+    match loaded {
+        Ok(commit) => Ok(Some(commit)),
+        Err(e) if strict => Err(format!("unreadable commit object {id}: {e}")),
+        Err(e) => {
+            tracing::warn!("skipping unreadable commit object {id}: {e}");
+            Ok(None)
+        }
+    }
+}
+
+/// Parses the commit object `data`. `Commit::from_bytes` assumes a well-formed object and
+/// panics on anything else, so the header is checked first: a `tree` line, any `parent`
+/// lines, then `author` and `committer` lines, each ending in a line break.
+fn parse_commit(data: &[u8], hash: ObjectHash) -> Result<Commit, String> {
+    let text =
+        std::str::from_utf8(data).map_err(|_| "malformed commit object: not UTF-8".to_string())?;
+    let malformed = |what: &str| format!("malformed commit object: {what}");
+    let mut lines = text.split_inclusive('\n');
+    let mut next_line = |what: &str| {
+        lines
+            .next()
+            .and_then(|line| line.strip_suffix('\n'))
+            .ok_or_else(|| malformed(&format!("missing {what} line")))
+    };
+
+    let tree = next_line("tree")?;
+    tree.strip_prefix("tree ")
+        .and_then(|id| ObjectHash::from_str(id).ok())
+        .ok_or_else(|| malformed("bad tree line"))?;
+    let mut line = next_line("author")?;
+    while let Some(parent) = line.strip_prefix("parent ") {
+        ObjectHash::from_str(parent).map_err(|_| malformed("bad parent line"))?;
+        line = next_line("author")?;
+    }
+    if !line.starts_with("author ") || !is_signature_line(line) {
+        return Err(malformed("bad author line"));
+    }
+    let committer = next_line("committer")?;
+    if !committer.starts_with("committer ") || !is_signature_line(committer) {
+        return Err(malformed("bad committer line"));
+    }
+
+    Commit::from_bytes(data, hash).map_err(|e| e.to_string())
+}
+
+/// Whether `line` has the `<type> <name> <<email>> <timestamp> <timezone>` shape
+/// `Signature::from_data` relies on.
+fn is_signature_line(line: &str) -> bool {
+    let (Some(name_start), Some(email_start), Some(email_end)) =
+        (line.find(' '), line.find('<'), line.find('>'))
+    else {
+        return false;
+    };
+    if email_start < name_start + 2 || email_end <= email_start {
         return false;
     }
+    line.get(email_end + 2..)
+        .and_then(|rest| rest.split_once(' '))
+        .is_some_and(|(timestamp, _)| timestamp.parse::<usize>().is_ok())
+}
+
+/// Aggregates for one set of grouping and filter options, covering every commit reachable
+/// from `tip`.
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    fingerprint: String,
+    tip: String,
+    /// Every commit reachable from `tip`, including those the filters dropped.
+    covered: HashSet<String>,
+    authors: HashMap<String, AuthorStats>,
+}
+
+impl CacheEntry {
+    fn empty(fingerprint: String) -> Self {
+        Self {
+            fingerprint,
+            tip: String::new(),
+            covered: HashSet::new(),
+            authors: HashMap::new(),
+        }
+    }
+}
+
+/// The contents of [`CACHE_FILE`], most recently written entry first.
+#[derive(Serialize, Deserialize, Default)]
+struct ShortlogCache {
+    entries: Vec<CacheEntry>,
+}
+
+impl ShortlogCache {
+    fn path() -> PathBuf {
+        crate::utils::util::storage_path()
+            .join("info")
+            .join(CACHE_FILE)
+    }
+
+    /// The cache of the current repository. A missing or unreadable file is an empty cache.
+    fn load() -> Self {
+        let path = Self::path();
+        let Ok(data) = std::fs::read(&path) else {
+            return Self::default();
+        };
+        serde_json::from_slice(&data).unwrap_or_else(|e| {
+            tracing::debug!("ignoring shortlog cache '{}': {e}", path.display());
+            Self::default()
+        })
+    }
+
+    fn take(&mut self, fingerprint: &str) -> Option<CacheEntry> {
+        let index = self
+            .entries
+            .iter()
+            .position(|entry| entry.fingerprint == fingerprint)?;
+        Some(self.entries.remove(index))
+    }
+
+    /// Write the cache back, keeping the newest [`CACHE_ENTRIES`] entries. Failures only cost
+    /// the next run its head start, so they are logged and otherwise ignored.
+    fn save(&mut self) {
+        self.entries.truncate(CACHE_ENTRIES);
+        let path = Self::path();
+        let result = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| serde_json::to_vec(self).map_err(std::io::Error::from))
+            .and_then(|data| std::fs::write(&path, data));
+        if let Err(e) = result {
+            tracing::debug!("failed to write shortlog cache '{}': {e}", path.display());
+        }
+    }
+}
+
+/// Identifies the options that decide which commits count and how they are grouped and
+/// rendered. Relative dates are resolved first, so `--since "2 weeks ago"` changes the
+/// fingerprint as time passes.
+fn filter_fingerprint(
+    args: &ShortlogArgs,
+    aliases: &Aliases,
+    since_ts: Option<i64>,
+    until_ts: Option<i64>,
+) -> String {
+    let mut options = format!(
+        "{:?}",
+        (
+            args.email,
+            args.committer,
+            args.pairs,
+            args.abbrev,
+            args.body,
+            args.show_repo
+                .then(|| repo_name(&crate::utils::util::working_dir())),
+            since_ts,
+            until_ts,
+            args.date_match,
+            &args.author,
+            &args.path,
+            Timeline::from_args(args),
+        )
+    );
+    // The file as read, so any edit to it rebuilds the entries built from it. Without
+    // aliases the fingerprint stays what it was before `--aliases` existed.
+    if !aliases.source.is_empty() {
+        options.push_str(&aliases.source);
+    }
+    hex::encode(Sha1::digest(options.as_bytes()))
+}
+
+/// The aggregates for the current repository, reusing [`CACHE_FILE`]. Only commits the cached
+/// entry does not cover are read and merged in; if its tip is no longer an ancestor of `HEAD`
+/// (the branch was reset or rewritten) the entry is rebuilt from scratch.
+async fn aggregate_with_cache(
+    args: &ShortlogArgs,
+    aliases: &Aliases,
+    filter: &CommitFilter<'_>,
+    since_ts: Option<i64>,
+    until_ts: Option<i64>,
+) -> Result<HashMap<String, AuthorStats>, String> {
+    let repo = Repository::open(&crate::utils::util::working_dir())
+        .await
+        .map_err(|e| e.to_string())?;
+    let Some(tip) = repo.head_commit().await else {
+        return Ok(HashMap::new());
+    };
+    let fingerprint = filter_fingerprint(args, aliases, since_ts, until_ts);
+    let mut cache = ShortlogCache::load();
+    let mut entry = cache
+        .take(&fingerprint)
+        .unwrap_or_else(|| CacheEntry::empty(fingerprint.clone()));
+    if entry.tip == tip {
+        return Ok(entry.authors);
+    }
+
+    let jobs = args.jobs.unwrap_or_else(default_jobs);
+    let uncovered = match uncovered_commits(&repo, &tip, &entry, filter, args.strict, jobs).await? {
+        Some(commits) => commits,
+        None => {
+            entry = CacheEntry::empty(fingerprint);
+            uncovered_commits(&repo, &tip, &entry, filter, args.strict, jobs)
+                .await?
+                .unwrap_or_default()
+        }
+    };
+    entry
+        .covered
+        .extend(uncovered.iter().map(|(commit, _)| commit.id.to_string()));
+
+    let mut commits: Vec<Commit> = uncovered
+        .into_iter()
+        .filter_map(|(commit, keep)| keep.then_some(commit))
+        .collect();
+    commits.sort_by(|a, b| b.author.timestamp.cmp(&a.author.timestamp));
+
+    let mut fresh = HashMap::new();
+    for commit in &commits {
+        record_commit(args, aliases, &mut fresh, &repo.name, commit);
+    }
+    for (key, stats) in fresh {
+        match entry.authors.get_mut(&key) {
+            Some(cached) => cached.merge_newer(stats),
+            None => {
+                entry.authors.insert(key, stats);
+            }
+        }
+    }
+
+    entry.tip = tip;
+    cache.entries.insert(0, entry);
+    cache.save();
+    Ok(cache.entries.swap_remove(0).authors)
+}
+
+/// The commits of `repo` reachable from `tip` that `entry` does not cover, each with whether
+/// it passes `filter`, or `None` if the walk never reaches the entry's own tip. Covered
+/// commits are recognized by id and never loaded. Unreadable commits are handled as in
+/// [`load_commit`].
+async fn uncovered_commits(
+    repo: &Repository,
+    tip: &str,
+    entry: &CacheEntry,
+    filter: &CommitFilter<'_>,
+    strict: bool,
+    jobs: usize,
+) -> Result<Option<Vec<(Commit, bool)>>, String> {
+    let mut reached_tip = entry.covered.is_empty();
+    let commits = repo
+        .filtered_walk(vec![tip.to_string()], filter, strict, jobs, |id| {
+            let covered = entry.covered.contains(id);
+            reached_tip |= covered && id == entry.tip;
+            covered
+        })
+        .await?;
 
-    true
+    Ok(reached_tip.then_some(commits))
 }
 
 #[cfg(test)]
 mod tests {
+    use std::{
+        sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        },
+        time::Duration,
+    };
+
+    use serial_test::serial;
+    use tempfile::tempdir;
+
     use super::*;
+    use crate::{command::save_object, utils::test};
 
     #[test]
     fn test_parse_args() {
@@ -293,5 +1797,264 @@ mod tests {
 
         let args = ShortlogArgs::parse_from(["shortlog", "--since", "2024-01-01"]);
         assert!(args.since.is_some());
+        assert_eq!(args.date_match, DateMatch::Committer);
+
+        let args = ShortlogArgs::parse_from(["shortlog", "--date-match", "any"]);
+        assert_eq!(args.date_match, DateMatch::Any);
+
+        let args = ShortlogArgs::parse_from(["shortlog", "--path", "src/*", "--path", "docs"]);
+        assert_eq!(args.path, vec!["src/*", "docs"]);
+        assert_eq!(args.abbrev, None);
+
+        let args = ShortlogArgs::parse_from(["shortlog", "--abbrev"]);
+        assert_eq!(args.abbrev, Some(7));
+
+        let args = ShortlogArgs::parse_from(["shortlog", "--abbrev=12"]);
+        assert_eq!(args.abbrev, Some(12));
+
+        assert_eq!(args.body, 0);
+        let args = ShortlogArgs::parse_from(["shortlog", "--body"]);
+        assert_eq!(args.body, 3);
+        let args = ShortlogArgs::parse_from(["shortlog", "--body=1"]);
+        assert_eq!(args.body, 1);
+        assert!(!args.pairs);
+        assert!(!args.dates);
+
+        let args = ShortlogArgs::parse_from(["shortlog", "--pairs", "-s"]);
+        assert!(args.pairs);
+        assert!(!args.pager);
+
+        let args = ShortlogArgs::parse_from(["shortlog", "--pager", "--no-pager"]);
+        assert!(!args.pager);
+        assert!(args.no_pager);
+        assert_eq!(args.jobs, None);
+
+        let args = ShortlogArgs::parse_from(["shortlog", "-j", "4"]);
+        assert_eq!(args.jobs, Some(4));
+    }
+
+    #[test]
+    fn test_parse_alias_forms() {
+        use clap::{CommandFactory, parser::ValueSource};
+
+        // Every spelling of every argument that has more than one, as clap sees them, so a
+        // new short form or alias is covered without touching this test.
+        let mut spelled = Vec::new();
+        for arg in ShortlogArgs::command().get_arguments() {
+            let mut forms: Vec<String> = arg
+                .get_long_and_visible_aliases()
+                .into_iter()
+                .flatten()
+                .map(|long| format!("--{long}"))
+                .collect();
+            forms.extend(
+                arg.get_short_and_visible_aliases()
+                    .into_iter()
+                    .flatten()
+                    .map(|c| format!("-{c}")),
+            );
+            if forms.len() < 2 {
+                continue;
+            }
+            let id = arg.get_id().to_string();
+            for form in &forms {
+                let mut argv = vec!["shortlog".to_string(), form.clone()];
+                if arg.get_action().takes_values() {
+                    argv.push("2".to_string());
+                }
+                let matches = ShortlogArgs::command().try_get_matches_from(&argv).unwrap();
+                assert_eq!(
+                    matches.value_source(&id),
+                    Some(ValueSource::CommandLine),
+                    "{form} did not set {id}"
+                );
+            }
+            spelled.push(id);
+        }
+        spelled.sort();
+        assert_eq!(
+            spelled,
+            [
+                "committer",
+                "email",
+                "jobs",
+                "no_pager",
+                "numbered",
+                "pager",
+                "summary",
+            ]
+        );
+
+        let args = ShortlogArgs::parse_from(["shortlog", "-nsec"]);
+        assert!(args.numbered && args.summary && args.email && args.committer);
+        assert!(!ShortlogArgs::parse_from(["shortlog"]).committer);
+        // The filters have no short forms, as in git, where `-S` is the pickaxe.
+        for short in ["-S", "-U", "-A"] {
+            assert!(ShortlogArgs::try_parse_from(["shortlog", short, "x"]).is_err());
+        }
+    }
+
+    #[test]
+    fn test_pager_command() {
+        let less = || Some("less -R".to_string());
+        let paged = ShortlogArgs::parse_from(["shortlog", "--pager"]);
+        assert_eq!(
+            pager_command(&paged, true, less()).as_deref(),
+            Some("less -R")
+        );
+        assert_eq!(pager_command(&paged, false, less()), None);
+        assert_eq!(pager_command(&paged, true, None), None);
+        assert_eq!(pager_command(&paged, true, Some("  ".to_string())), None);
+
+        let direct = ShortlogArgs::parse_from(["shortlog", "--no-pager"]);
+        assert_eq!(pager_command(&direct, true, less()), None);
+        let default = ShortlogArgs::parse_from(["shortlog"]);
+        assert_eq!(pager_command(&default, true, less()), None);
+    }
+
+    #[test]
+    fn test_co_authors_parses_trailers() {
+        let message = "Fix parser\n\nBody mentions co-authors: nobody\n\n\
+            Co-authored-by: Ada Lovelace <ada@oa.org>\n\
+            co-authored-by:Grace Hopper<grace@oa.org>\n\
+            Co-Authored-By: Linus\n\
+            Signed-off-by: Someone <s@oa.org>\n";
+        assert_eq!(
+            co_authors(message),
+            vec![
+                ("Ada Lovelace".to_string(), "ada@oa.org".to_string()),
+                ("Grace Hopper".to_string(), "grace@oa.org".to_string()),
+                ("Linus".to_string(), String::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_commit_rejects_malformed_objects() {
+        let tree = "tree 4b825dc642cb6eb9a060e54bf8d69288fbee4904\n";
+        let author = "author A U Thor <a@oa.org> 1700000000 +0000\n";
+        let committer = "committer A U Thor <a@oa.org> 1700000000 +0000\n";
+        let hash = ObjectHash::default();
+
+        let valid = format!("{tree}{author}{committer}\nsubject\n");
+        let commit = parse_commit(valid.as_bytes(), hash).unwrap();
+        assert_eq!(commit.author.email, "a@oa.org");
+
+        for corrupt in [
+            String::new(),
+            "garbage".to_string(),
+            format!("{tree}{author}"),
+            format!("{tree}parent 123\n{author}{committer}\nsubject\n"),
+            format!("{tree}author <a@oa.org> 1700000000 +0000\n{committer}\nsubject\n"),
+            format!("{tree}author A U Thor a@oa.org 1700000000\n{committer}\nsubject\n"),
+            format!("{tree}{author}committer A U Thor <a@oa.org> soon +0000\n\nsubject\n"),
+            format!("{tree}{committer}{author}\nsubject\n"),
+        ] {
+            let err = parse_commit(corrupt.as_bytes(), hash).unwrap_err();
+            assert!(
+                err.starts_with("malformed commit object"),
+                "{corrupt:?}: {err}"
+            );
+        }
+    }
+
+    /// Walks from `tip` with an `inspect` job per commit that sleeps briefly, and returns the
+    /// most jobs seen running at once along with the ids in walk order.
+    async fn walk_with_slow_inspections(
+        objects: &ClientStorage,
+        tip: &str,
+        jobs: usize,
+    ) -> (usize, Vec<String>) {
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let commits = walk_commits(
+            objects,
+            vec![tip.to_string()],
+            true,
+            jobs,
+            |_| false,
+            |_| {
+                let (running, peak) = (running.clone(), peak.clone());
+                Some(move || {
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    std::thread::sleep(Duration::from_millis(20));
+                    running.fetch_sub(1, Ordering::SeqCst);
+                })
+            },
+        )
+        .await
+        .unwrap();
+        assert!(commits.iter().all(|(_, inspected)| inspected.is_some()));
+        let ids = commits.iter().map(|(commit, _)| commit.id.to_string());
+        (peak.load(Ordering::SeqCst), ids.collect())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_jobs_overlap_on_linear_history() {
+        let temp = tempdir().unwrap();
+        test::setup_with_new_libra_in(temp.path()).await;
+        let _guard = test::ChangeDirGuard::new(temp.path());
+
+        let mut parents = vec![];
+        for i in 0..8 {
+            let signature = |kind: &str| {
+                let line = format!("{kind} A U Thor <a@oa.org> {} +0000", 1_700_000_000 + i);
+                Signature::from_data(line.into_bytes()).unwrap()
+            };
+            let commit = Commit::new(
+                signature("author"),
+                signature("committer"),
+                ObjectHash::default(),
+                parents,
+                &format!("commit {i}"),
+            );
+            save_object(&commit, &commit.id).unwrap();
+            parents = vec![commit.id];
+        }
+        let objects = crate::utils::util::objects_storage();
+        let tip = parents[0].to_string();
+
+        // One commit is read at a time either way, but with several jobs each commit's work
+        // overlaps with reading the ones below it.
+        let (sequential, order) = walk_with_slow_inspections(&objects, &tip, 1).await;
+        assert_eq!(sequential, 1);
+        assert_eq!(order.len(), 8);
+        assert_eq!(order[0], tip);
+        let (parallel, parallel_order) = walk_with_slow_inspections(&objects, &tip, 4).await;
+        assert!(parallel > 1, "jobs never overlapped");
+        assert_eq!(parallel_order, order);
+    }
+
+    #[test]
+    fn test_large_counts_stay_aligned() {
+        let mut prolific = AuthorStats::new("Prolific".to_string(), "p@oa.org".to_string());
+        for i in 0..12_345 {
+            prolific.add_commit(format!("subject {i}"), 0, i);
+        }
+        let mut occasional = AuthorStats::new("Occasional".to_string(), "o@oa.org".to_string());
+        occasional.add_commit("only subject".to_string(), 0, 0);
+
+        let mut out = Vec::new();
+        write_report(
+            &mut out,
+            &[&prolific, &occasional],
+            false,
+            false,
+            false,
+            false,
+        )
+        .unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        let headers: Vec<&str> = out.lines().filter(|l| !l.starts_with(' ')).collect();
+        assert_eq!(headers, vec!["12345  Prolific"]);
+        assert!(out.contains("\n    1  Occasional\n"));
+        // Every subject starts in the same column, right after the count column.
+        for line in out.lines().filter(|l| l.contains("subject")) {
+            assert_eq!(line.len() - line.trim_start().len(), 7, "{line:?}");
+        }
+        assert_eq!(out.lines().count(), 12_345 + 1 + 2);
     }
 }
@@ -31,14 +31,85 @@ use crate::{
         rebuild_index_from_tree, remove_empty_directories, reset_index_to_commit,
         restore_working_directory_from_tree,
     },
-    internal::head::Head,
-    utils::{object, object_ext::TreeExt, tree, util},
+    internal::{
+        ai::{
+            intent::{read_active_intent, write_active_intent},
+            session::SessionStore,
+        },
+        head::Head,
+    },
+    utils::{index_lock, object, object_ext::TreeExt, tree, util},
 };
 
+/// Stash commit trailer recording the active intent pointer (`stash push --include-ai`).
+const AI_INTENT_TRAILER: &str = "Libra-AI-Intent";
+/// Stash commit trailer recording the AI session to resume (`stash push --include-ai`).
+const AI_SESSION_TRAILER: &str = "Libra-AI-Session";
+
+/// AI state recorded alongside a stash by `stash push --include-ai`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StashAiState {
+    /// Intent the active intent pointer referred to when the stash was made.
+    pub intent_id: Option<String>,
+    /// Most recent `libra code` session at the time of the stash.
+    pub session_id: Option<String>,
+}
+
+impl StashAiState {
+    /// Capture the current intent pointer and latest session of the repository.
+    fn capture(git_dir: &Path, workdir: &Path) -> Result<Self, String> {
+        let intent_id = read_active_intent(git_dir).map_err(|e| e.to_string())?;
+        let session_id = SessionStore::new(workdir)
+            .load_latest()
+            .map_err(|e| e.to_string())?
+            .map(|session| session.id);
+        Ok(Self {
+            intent_id,
+            session_id,
+        })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.intent_id.is_none() && self.session_id.is_none()
+    }
+
+    /// Render as commit message trailers.
+    fn to_trailers(&self) -> String {
+        let mut trailers = String::new();
+        if let Some(intent_id) = &self.intent_id {
+            trailers.push_str(&format!("{AI_INTENT_TRAILER}: {intent_id}\n"));
+        }
+        if let Some(session_id) = &self.session_id {
+            trailers.push_str(&format!("{AI_SESSION_TRAILER}: {session_id}\n"));
+        }
+        trailers
+    }
+
+    /// Parse the trailers written by [`StashAiState::to_trailers`]; `None` for plain stashes.
+    fn from_message(message: &str) -> Option<Self> {
+        let mut state = Self::default();
+        for line in message.lines() {
+            if let Some(value) = line.strip_prefix(&format!("{AI_INTENT_TRAILER}: ")) {
+                state.intent_id = Some(value.trim().to_string());
+            } else if let Some(value) = line.strip_prefix(&format!("{AI_SESSION_TRAILER}: ")) {
+                state.session_id = Some(value.trim().to_string());
+            }
+        }
+        (!state.is_empty()).then_some(state)
+    }
+}
+
 pub async fn execute(stash_cmd: Stash) {
     let result = match stash_cmd {
-        Stash::Push { message } => push(message).await,
-        Stash::Pop { stash } => pop(stash).await,
+        Stash::Push {
+            message,
+            include_ai,
+        } => push(message, include_ai).await,
+        Stash::Pop { stash } => pop(stash).await.map(|ai_state| {
+            if let Some(ai_state) = ai_state {
+                report_restored_ai_state(&ai_state);
+            }
+        }),
         Stash::List => list().await,
         Stash::Apply { stash } => apply(stash).await,
         Stash::Drop { stash } => drop_stash(stash).await,
@@ -49,7 +120,7 @@ pub async fn execute(stash_cmd: Stash) {
     }
 }
 
-async fn push(message: Option<String>) -> Result<(), String> {
+async fn push(message: Option<String>, include_ai: bool) -> Result<(), String> {
     if !has_changes().await {
         eprintln!("No local changes to save");
         return Ok(());
@@ -126,13 +197,21 @@ async fn push(message: Option<String>) -> Result<(), String> {
     let worktree_tree_hash = object::write_git_object(&git_dir, "tree", &worktree_tree_data)
         .map_err(|e| e.to_string())?;
 
-    // 5. Create the final stash commit
+    // 5. Create the final stash commit, recording AI state as trailers if requested
+    let mut stash_commit_message = final_message.clone();
+    if include_ai {
+        let ai_state = StashAiState::capture(&git_dir, workdir)?;
+        if !ai_state.is_empty() {
+            stash_commit_message.push_str("\n\n");
+            stash_commit_message.push_str(&ai_state.to_trailers());
+        }
+    }
     let stash_commit = Commit::new(
         author,            // The original author signature
         committer.clone(), // CLONE the committer to retain ownership
         worktree_tree_hash,
         vec![head_commit_hash, index_commit_hash],
-        &final_message,
+        &stash_commit_message,
     );
     let stash_commit_data = stash_commit.to_data().map_err(|e| e.to_string())?;
     let stash_commit_hash = object::write_git_object(&git_dir, "commit", &stash_commit_data)
@@ -149,13 +228,48 @@ async fn push(message: Option<String>) -> Result<(), String> {
     Ok(())
 }
 
-async fn pop(stash: Option<String>) -> Result<(), String> {
+/// Apply a stash and drop it from the stash list.
+///
+/// If the stash was made with `--include-ai`, the recorded intent pointer is
+/// reactivated and the recorded AI state is returned so the caller can report
+/// the session to resume. Plain stashes return `None`.
+pub async fn pop(stash: Option<String>) -> Result<Option<StashAiState>, String> {
+    let (_, hash_str) = resolve_stash_to_commit_hash(stash.clone())?;
+    let ai_state = read_stash_ai_state(&hash_str)?;
+
     // First, apply the stash.
     do_apply(stash.clone()).await?;
 
     // If apply was successful, drop the stash.
     // We use the original `stash` Option<String> for the drop command.
-    drop_stash(stash).await
+    drop_stash(stash).await?;
+
+    if let Some(intent_id) = ai_state
+        .as_ref()
+        .and_then(|state| state.intent_id.as_deref())
+    {
+        let git_dir = util::try_get_storage_path(None).map_err(|e| e.to_string())?;
+        write_active_intent(&git_dir, intent_id).map_err(|e| e.to_string())?;
+    }
+    Ok(ai_state)
+}
+
+/// Read the AI state recorded in a stash commit, if any.
+fn read_stash_ai_state(stash_hash: &str) -> Result<Option<StashAiState>, String> {
+    let git_dir = util::try_get_storage_path(None).map_err(|e| e.to_string())?;
+    let hash = ObjectHash::from_str(stash_hash).map_err(|e| e.to_string())?;
+    let data = object::read_git_object(&git_dir, &hash).map_err(|e| e.to_string())?;
+    let commit = Commit::from_bytes(&data, hash).map_err(|e| e.to_string())?;
+    Ok(StashAiState::from_message(&commit.message))
+}
+
+fn report_restored_ai_state(ai_state: &StashAiState) {
+    if let Some(intent_id) = &ai_state.intent_id {
+        println!("Restored active intent {intent_id}");
+    }
+    if let Some(session_id) = &ai_state.session_id {
+        println!("Resume AI session {session_id} with `libra code --resume`");
+    }
 }
 
 async fn list() -> Result<(), String> {
@@ -267,8 +381,7 @@ async fn do_apply(stash: Option<String>) -> Result<(), String> {
     restore_working_directory_from_tree(&merged_tree, workdir, "")?;
     rebuild_index_from_tree(&merged_tree, &mut index, "")?;
 
-    index
-        .save(&index_path)
+    index_lock::save_index(&mut index, &index_path)
         .map_err(|e| format!("Failed to save index: {}", e))?;
 
     let current_branch_name = match Head::current().await {
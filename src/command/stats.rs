@@ -0,0 +1,340 @@
+//! Stats command: a one-screen health overview of the repository.
+//!
+//! The commits reachable from `HEAD` and every local branch are walked once, with the same
+//! walker `shortlog --all` uses, and reported as totals: commits, contributors, the dates of
+//! the first and last commit, and the average number of commits per week over the 52 weeks
+//! ending at the last commit (rather than today, so the figure describes the history and not
+//! how long ago it stopped). Contributors are counted by author name after merging the
+//! identities of an `--aliases` file, as `shortlog --aliases` does.
+//!
+//! The object section counts the commits, trees and blobs reachable from those commits,
+//! plus annotated tag objects, with their uncompressed sizes. Each tree and blob is read
+//! once however many commits share it, and a blob is reported under the first path it was
+//! seen at, walking newest commits first. Reading every blob is the expensive part, so
+//! `--fast` counts blobs from the trees that list them without loading them: the blob size
+//! and the largest-blobs list are then left out, of the JSON too.
+//!
+//! `--json` prints the report as one JSON object for dashboards.
+
+use std::{
+    collections::{HashMap, HashSet},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use chrono::DateTime;
+use clap::Parser;
+use git_internal::{
+    hash::ObjectHash,
+    internal::object::{
+        commit::Commit,
+        tree::{Tree, TreeItemMode},
+    },
+};
+use serde::Serialize;
+
+use crate::{
+    command::{
+        load_object,
+        shortlog::{Aliases, default_jobs, reachable_commits},
+    },
+    internal::{
+        branch::Branch,
+        head::Head,
+        log::formatter::format_timestamp,
+        tag::{self, TagObject},
+    },
+    utils::{client_storage::ClientStorage, util},
+};
+
+/// Length of the window `commits_per_week` averages over, in weeks.
+const ACTIVITY_WEEKS: i64 = 52;
+
+#[derive(Parser, Debug)]
+pub struct StatsArgs {
+    /// Print the report as a JSON object
+    #[clap(long)]
+    pub json: bool,
+
+    /// Do not read blobs: leave out blob sizes and the largest blobs
+    #[clap(long)]
+    pub fast: bool,
+
+    /// Number of largest blobs to list
+    #[clap(long, value_name = "N", default_value_t = 10)]
+    pub top: usize,
+
+    /// Merge contributor identities listed in a contributors file, as `shortlog --aliases`
+    #[clap(long, value_name = "PATH")]
+    pub aliases: Option<PathBuf>,
+}
+
+/// Count and total uncompressed size of the objects of one type.
+#[derive(Debug, Default, Serialize, PartialEq, Eq)]
+pub struct ObjectCount {
+    pub count: usize,
+    /// `None` for blobs under `--fast`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+}
+
+#[derive(Debug, Default, Serialize, PartialEq, Eq)]
+pub struct ObjectCounts {
+    pub commits: ObjectCount,
+    pub trees: ObjectCount,
+    pub blobs: ObjectCount,
+    pub tags: ObjectCount,
+}
+
+/// A blob and the first path it was seen at.
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct BlobEntry {
+    pub path: String,
+    pub id: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RepoStats {
+    pub commits: usize,
+    pub contributors: usize,
+    /// Committer date of the oldest commit, RFC 3339 in UTC.
+    pub first_commit: Option<String>,
+    /// Committer date of the newest commit, RFC 3339 in UTC.
+    pub last_commit: Option<String>,
+    pub branches: usize,
+    pub tags: usize,
+    /// Average over the [`ACTIVITY_WEEKS`] weeks ending at the last commit, to two decimals.
+    pub commits_per_week: f64,
+    pub objects: ObjectCounts,
+    /// `None` under `--fast`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub largest_blobs: Option<Vec<BlobEntry>>,
+    /// Committer timestamps behind `first_commit` and `last_commit`, for the text report.
+    #[serde(skip)]
+    dates: Option<(i64, i64)>,
+}
+
+pub async fn execute(args: StatsArgs) {
+    if !util::check_repo_exist() {
+        return;
+    }
+    let _ = execute_to(args, &mut std::io::stdout()).await;
+}
+
+/// Write the report for `args` to `writer`; errors are reported on stderr.
+pub async fn execute_to(args: StatsArgs, writer: &mut impl Write) -> std::io::Result<()> {
+    let stats = match repo_stats(&args).await {
+        Ok(stats) => stats,
+        Err(e) => {
+            eprintln!("fatal: {e}");
+            return Ok(());
+        }
+    };
+    if args.json {
+        let json = serde_json::to_string_pretty(&stats).map_err(std::io::Error::other)?;
+        writeln!(writer, "{json}")
+    } else {
+        write_report(writer, &stats)
+    }
+}
+
+/// The statistics of the repository in the current directory.
+pub async fn repo_stats(args: &StatsArgs) -> Result<RepoStats, String> {
+    let aliases = match &args.aliases {
+        Some(path) => Aliases::load(path)?,
+        None => Aliases::default(),
+    };
+    let branches = Branch::list_branches(None).await;
+    let tags = tag::list().await.map_err(|e| e.to_string())?;
+
+    // An attached HEAD is one of the branches; a detached one may not be reachable from any.
+    let mut tips = Vec::new();
+    if let Head::Detached(hash) = Head::current().await {
+        tips.push(hash.to_string());
+    }
+    tips.extend(branches.iter().map(|branch| branch.commit.to_string()));
+    let storage = util::objects_storage();
+    let mut commits = reachable_commits(&storage, tips, true, default_jobs()).await?;
+    commits.sort_by_key(|commit| std::cmp::Reverse(commit.committer.timestamp));
+
+    let contributors: HashSet<String> = commits
+        .iter()
+        .map(|commit| aliases.resolve(&commit.author.name, &commit.author.email).0)
+        .collect();
+    let dates = commits.last().zip(commits.first()).map(|(first, last)| {
+        (
+            first.committer.timestamp as i64,
+            last.committer.timestamp as i64,
+        )
+    });
+    let rfc3339 =
+        |timestamp: i64| DateTime::from_timestamp(timestamp, 0).map(|date| date.to_rfc3339());
+
+    let ObjectScan {
+        counts: mut objects,
+        blobs,
+    } = count_objects(&storage, &commits, !args.fast)?;
+    let mut tag_size = 0;
+    for tag in &tags {
+        if let TagObject::Tag(object) = &tag.object {
+            objects.tags.count += 1;
+            tag_size += object_size(&storage, &object.id)?;
+        }
+    }
+    objects.tags.size = Some(tag_size);
+    let largest_blobs = blobs.map(|mut blobs| {
+        blobs.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+        blobs.truncate(args.top);
+        blobs
+    });
+
+    Ok(RepoStats {
+        commits: commits.len(),
+        contributors: contributors.len(),
+        first_commit: dates.and_then(|(first, _)| rfc3339(first)),
+        last_commit: dates.and_then(|(_, last)| rfc3339(last)),
+        branches: branches.len(),
+        tags: tags.len(),
+        commits_per_week: commits_per_week(&commits),
+        objects,
+        largest_blobs,
+        dates,
+    })
+}
+
+/// Average commits per week over the [`ACTIVITY_WEEKS`] weeks ending at the newest of
+/// `commits`, which come newest first.
+fn commits_per_week(commits: &[Commit]) -> f64 {
+    let Some(last) = commits.first() else {
+        return 0.0;
+    };
+    let start = last.committer.timestamp as i64 - ACTIVITY_WEEKS * 7 * 24 * 60 * 60;
+    let recent = commits
+        .iter()
+        .take_while(|commit| commit.committer.timestamp as i64 > start)
+        .count();
+    (recent as f64 / ACTIVITY_WEEKS as f64 * 100.0).round() / 100.0
+}
+
+/// Object counts and, when blobs are read, every blob with its size.
+struct ObjectScan {
+    counts: ObjectCounts,
+    blobs: Option<Vec<BlobEntry>>,
+}
+
+/// Count the objects reachable from `commits`, reading blobs only when `read_blobs`.
+fn count_objects(
+    storage: &ClientStorage,
+    commits: &[Commit],
+    read_blobs: bool,
+) -> Result<ObjectScan, String> {
+    let mut counts = ObjectCounts::default();
+    let (mut commit_size, mut tree_size) = (0, 0);
+    let mut seen_trees = HashSet::new();
+    // Blob id to the first path it was seen at.
+    let mut blobs: HashMap<ObjectHash, PathBuf> = HashMap::new();
+
+    for commit in commits {
+        counts.commits.count += 1;
+        commit_size += object_size(storage, &commit.id)?;
+        let mut pending = vec![(commit.tree_id, PathBuf::new())];
+        while let Some((tree_id, dir)) = pending.pop() {
+            if !seen_trees.insert(tree_id) {
+                continue;
+            }
+            let tree: Tree = load_object(&tree_id)
+                .map_err(|e| format!("unreadable tree object {tree_id}: {e}"))?;
+            counts.trees.count += 1;
+            tree_size += object_size(storage, &tree_id)?;
+            for item in &tree.tree_items {
+                let path = dir.join(&item.name);
+                match item.mode {
+                    TreeItemMode::Tree => pending.push((item.id, path)),
+                    // A submodule commit lives in another repository.
+                    TreeItemMode::Commit => {}
+                    _ => {
+                        blobs.entry(item.id).or_insert(path);
+                    }
+                }
+            }
+        }
+    }
+
+    counts.commits.size = Some(commit_size);
+    counts.trees.size = Some(tree_size);
+    counts.blobs.count = blobs.len();
+    if !read_blobs {
+        return Ok(ObjectScan {
+            counts,
+            blobs: None,
+        });
+    }
+    let mut entries = Vec::with_capacity(blobs.len());
+    for (id, path) in blobs {
+        entries.push(BlobEntry {
+            path: path.to_string_lossy().into_owned(),
+            id: id.to_string(),
+            size: object_size(storage, &id)?,
+        });
+    }
+    counts.blobs.size = Some(entries.iter().map(|blob| blob.size).sum());
+    Ok(ObjectScan {
+        counts,
+        blobs: Some(entries),
+    })
+}
+
+/// Uncompressed size of the object `id`.
+fn object_size(storage: &ClientStorage, id: &ObjectHash) -> Result<u64, String> {
+    storage
+        .get(id)
+        .map(|data| data.len() as u64)
+        .map_err(|e| format!("unreadable object {id}: {e}"))
+}
+
+fn write_report(writer: &mut impl Write, stats: &RepoStats) -> std::io::Result<()> {
+    let date = |timestamp: i64| format_timestamp(timestamp);
+    writeln!(writer, "Commits:          {}", stats.commits)?;
+    writeln!(writer, "Contributors:     {}", stats.contributors)?;
+    if let Some((first, last)) = stats.dates {
+        writeln!(writer, "First commit:     {}", date(first))?;
+        writeln!(writer, "Last commit:      {}", date(last))?;
+    }
+    writeln!(writer, "Branches:         {}", stats.branches)?;
+    writeln!(writer, "Tags:             {}", stats.tags)?;
+    writeln!(
+        writer,
+        "Commits per week: {:.2} (last {ACTIVITY_WEEKS} weeks)",
+        stats.commits_per_week
+    )?;
+
+    writeln!(writer, "\nObjects          count          size")?;
+    let objects = &stats.objects;
+    for (kind, count) in [
+        ("commits", &objects.commits),
+        ("trees", &objects.trees),
+        ("blobs", &objects.blobs),
+        ("tags", &objects.tags),
+    ] {
+        let size = count
+            .size
+            .map_or_else(|| "-".to_string(), |size| size.to_string());
+        writeln!(writer, "  {kind:<10} {:>9} {size:>13}", count.count)?;
+    }
+
+    if let Some(blobs) = &stats.largest_blobs
+        && !blobs.is_empty()
+    {
+        writeln!(writer, "\nLargest blobs")?;
+        for blob in blobs {
+            writeln!(
+                writer,
+                "  {:>10}  {}",
+                blob.size,
+                Path::new(&blob.path).display()
+            )?;
+        }
+    }
+    Ok(())
+}
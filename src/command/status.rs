@@ -11,7 +11,7 @@ use colored::Colorize;
 use git_internal::{
     hash::{ObjectHash, get_hash_kind},
     internal::{
-        index::Index,
+        index::{Index, IndexEntry},
         object::{
             commit::Commit,
             tree::{Tree, TreeItemMode},
@@ -21,12 +21,18 @@ use git_internal::{
 
 use super::stash;
 use crate::{
-    command::calc_file_blob_hash,
-    internal::{config::Config, head::Head},
+    internal::{
+        config::Config,
+        config_defaults::{ApplyConfigDefaults, ConfigDefault, parse_bool},
+        head::Head,
+    },
     utils::{
         ignore::{self, IgnorePolicy},
+        index_lock::{self, IndexLock},
         object_ext::{CommitExt, TreeExt},
-        path, util,
+        path,
+        stat_cache::{self, StatCache},
+        util,
     },
 };
 
@@ -43,9 +49,18 @@ pub struct StatusArgs {
     pub porcelain: Option<PorcelainVersion>,
 
     /// Give the output in the short-format
-    #[clap(short = 's', long = "short", conflicts_with = "porcelain")]
+    #[clap(
+        short = 's',
+        long = "short",
+        conflicts_with = "porcelain",
+        overrides_with = "no_short"
+    )]
     pub short: bool,
 
+    /// Give the output in the long-format, overriding `status.short`
+    #[clap(long = "no-short", overrides_with = "short")]
+    pub no_short: bool,
+
     /// Output with branch info (short or porcelain mode)
     #[clap(long = "branch")]
     pub branch: bool,
@@ -65,6 +80,27 @@ pub struct StatusArgs {
         default_value = "normal"
     )]
     pub untracked_files: UntrackedFiles,
+
+    /// Remove a stale index lock left behind by a crashed libra process
+    #[clap(long = "force-unlock")]
+    pub force_unlock: bool,
+}
+
+impl ApplyConfigDefaults for StatusArgs {
+    const CONFIG_DEFAULTS: &'static [ConfigDefault] = &[ConfigDefault {
+        key: "status.short",
+        arg: "short",
+        negation: Some("no_short"),
+    }];
+
+    fn apply_config_default(&mut self, arg: &str, value: &str) -> Result<(), String> {
+        match arg {
+            // `--porcelain` takes precedence over a configured short format.
+            "short" => self.short = parse_bool(value)? && self.porcelain.is_none(),
+            _ => unreachable!("unknown status config default: {arg}"),
+        }
+        Ok(())
+    }
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
@@ -207,6 +243,17 @@ pub async fn execute_to(args: StatusArgs, writer: &mut impl Write) {
         return;
     }
 
+    if args.force_unlock {
+        match index_lock::force_unlock(path::index()) {
+            Ok(true) => writeln!(writer, "Removed stale index lock").unwrap(),
+            Ok(false) => {}
+            Err(e) => {
+                writeln!(writer, "fatal: cannot remove index lock: {e}").unwrap();
+                return;
+            }
+        }
+    }
+
     let is_porcelain = args.porcelain.is_some();
     let is_standard_mode = !is_porcelain && !args.short;
 
@@ -236,7 +283,9 @@ pub async fn execute_to(args: StatusArgs, writer: &mut impl Write) {
 
     // to cur_dir relative path
     let staged = changes_to_be_committed().await.to_relative();
-    let mut unstaged = changes_to_be_staged().to_relative();
+    let (unstaged, refreshed) = worktree_changes(IgnorePolicy::Respect);
+    refresh_stat_cache(refreshed);
+    let mut unstaged = unstaged.to_relative();
     let mut ignored_files = if args.ignored && !matches!(args.untracked_files, UntrackedFiles::No) {
         list_ignored_files().to_relative().new
     } else {
@@ -782,24 +831,42 @@ pub fn changes_to_be_staged() -> Changes {
 /// Variant of [`changes_to_be_staged`] that lets callers pick the ignore strategy explicitly.
 /// Commands such as `add --force` or `status --ignored` can switch policies as needed.
 pub fn changes_to_be_staged_with_policy(policy: IgnorePolicy) -> Changes {
+    worktree_changes(policy).0
+}
+
+/// Compare `index` and `workdir`, hashing only tracked files whose stat data is not fresh.
+/// Also returns refreshed entries for hashed files whose content turned out unchanged, built
+/// from the stat data read before hashing.
+fn worktree_changes(policy: IgnorePolicy) -> (Changes, Vec<IndexEntry>) {
     let mut changes = Changes::default();
-    let workdir = util::working_dir();
-    let index = Index::load(path::index()).unwrap();
-    let tracked_files = index.tracked_files();
-    for file in tracked_files.iter() {
-        if ignore::should_ignore(file, policy, &index) {
+    let mut refreshed = Vec::new();
+    let index_file = path::index();
+    let index = Index::load(&index_file).unwrap();
+    let cache = StatCache::new(&index_file);
+    for entry in index.tracked_entries(0) {
+        let file = PathBuf::from(&entry.name);
+        if ignore::should_ignore(&file, policy, &index) {
             continue;
         }
-        let file_str = file.to_str().unwrap();
-        let file_abs = util::workdir_to_absolute(file);
-        if !file_abs.exists() {
-            changes.deleted.push(file.clone());
-        } else if index.is_modified(file_str, 0, &workdir) {
-            // only calc the hash if the file is modified (metadata), for optimization
-            let file_hash = calc_file_blob_hash(&file_abs).unwrap();
-            if !index.verify_hash(file_str, 0, &file_hash) {
-                changes.modified.push(file.clone());
+        let file_abs = util::workdir_to_absolute(&file);
+        let meta = match file_abs.symlink_metadata() {
+            Ok(meta) if file_abs.exists() => meta,
+            _ => {
+                changes.deleted.push(file);
+                continue;
             }
+        };
+        if cache.is_fresh(entry, &meta) {
+            continue;
+        }
+        let file_hash = stat_cache::hash_file(&file_abs).unwrap();
+        if entry.hash != file_hash {
+            changes.modified.push(file);
+        } else {
+            let mut fresh = IndexEntry::new(&meta, file_hash, entry.name.clone());
+            // only the stat data is refreshed; a mode change stays unstaged
+            fresh.mode = entry.mode;
+            refreshed.push(fresh);
         }
     }
     let files = util::list_workdir_files().unwrap(); // to workdir
@@ -810,7 +877,29 @@ pub fn changes_to_be_staged_with_policy(policy: IgnorePolicy) -> Changes {
             changes.new.push(file);
         }
     }
-    changes
+    (changes, refreshed)
+}
+
+/// Record refreshed stat data so the next status can skip hashing those files. Best effort:
+/// if another process holds the index lock, the refresh is left for a later run.
+fn refresh_stat_cache(refreshed: Vec<IndexEntry>) {
+    if refreshed.is_empty() {
+        return;
+    }
+    let index_file = path::index();
+    let Ok(lock) = IndexLock::acquire(&index_file) else {
+        return;
+    };
+    let Ok(mut index) = Index::load(&index_file) else {
+        return;
+    };
+    for entry in refreshed {
+        // skip entries restaged while we were scanning
+        if index.verify_hash(&entry.name, 0, &entry.hash) {
+            index.update(entry);
+        }
+    }
+    let _ = lock.commit(&mut index);
 }
 
 /// List ignored files (not tracked by index, but ignored by .libraignore) under workdir
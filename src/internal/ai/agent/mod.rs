@@ -6,6 +6,7 @@ pub mod profile;
 pub mod runtime;
 
 pub use runtime::{
-    Agent, AgentBuilder, ChatAgent, ToolLoopConfig, ToolLoopObserver, run_tool_loop,
-    run_tool_loop_with_history_and_observer,
+    Agent, AgentBuilder, ChatAgent, ConfigIssue, ContextMiddleware, PromptOutcome,
+    SelectionStrategy, ThoughtOnlyAnswer, ToolLoopConfig, ToolLoopObserver, run_tool_loop,
+    run_tool_loop_with_history_and_observer, run_tool_loop_with_observer,
 };
@@ -8,12 +8,34 @@
 //! 1. `{working_dir}/.libra/agents/*.md` (project-local)
 //! 2. `~/.config/libra/agents/*.md` (user-global)
 //! 3. Embedded defaults compiled into the binary
+//!
+//! A profile's `language` and `tone` become an instruction appended to its system prompt;
+//! see [`style`] and [`build_agent_from_profile`].
 
 pub mod parser;
 pub mod router;
+pub mod style;
+
+use crate::internal::ai::{AgentBuilder, CompletionModel};
 
 pub use parser::{AgentProfile, parse_agent_profile};
-pub use router::{AgentProfileRouter, load_embedded_profiles, load_profiles};
+pub use router::{
+    AgentProfileRouter, Embedder, RouteAmbiguity, RouterConfig, default_synonyms,
+    load_embedded_profiles, load_profiles,
+};
+
+/// An [`AgentBuilder`] for `model` with the profile's system prompt and its language and
+/// tone instruction, which also reaches the models the agent calls internally.
+pub fn build_agent_from_profile<M: CompletionModel>(
+    model: M,
+    profile: &AgentProfile,
+) -> AgentBuilder<M> {
+    let builder = AgentBuilder::new(model).preamble(&profile.system_prompt);
+    match profile.style_instruction() {
+        Some(instruction) => builder.response_style(instruction),
+        None => builder,
+    }
+}
 
 #[deprecated(note = "Use AgentProfileRouter instead.")]
 pub type AgentRouter = AgentProfileRouter;
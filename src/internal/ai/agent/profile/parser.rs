@@ -1,13 +1,17 @@
 //! Agent profile parser: markdown + YAML frontmatter → AgentProfile.
 
-use std::path::Path;
+use std::{collections::HashSet, path::Path};
+
+use super::style::{normalize_language, normalize_tone};
+use crate::internal::ai::completion::{CompletionError, ModelCapabilities};
 
 /// A parsed agent profile from a markdown file with YAML frontmatter.
 #[derive(Debug, Clone)]
 pub struct AgentProfile {
     /// Unique name for this agent.
     pub name: String,
-    /// Human-readable description (used for auto-selection matching).
+    /// Human-readable description (used for auto-selection matching). When the frontmatter
+    /// leaves it empty, the first paragraph of the body is used instead.
     pub description: String,
     /// List of tool names this agent is allowed to use.
     pub tools: Vec<String>,
@@ -15,13 +19,48 @@ pub struct AgentProfile {
     pub model_preference: String,
     /// The system prompt body (everything after the frontmatter).
     pub system_prompt: String,
+    /// Language of the description (e.g. "en", "zh"); selects how the router tokenizes it.
+    /// When absent, the router detects CJK text itself.
+    pub lang: Option<String>,
+    /// Terms that rule this profile out: inputs containing any of them never route to it.
+    pub exclude_keywords: Vec<String>,
+    /// Language the agent answers in (e.g. "Japanese"); see [`super::style`].
+    pub language: Option<String>,
+    /// Tone the agent answers in (e.g. "formal"); see [`super::style`].
+    pub tone: Option<String>,
+}
+
+impl AgentProfile {
+    /// Check that a model can run this profile.
+    ///
+    /// Profiles that list tools need a model with tool calling; binding one to a text-only
+    /// model would otherwise only fail on the first request.
+    pub fn validate_capabilities(
+        &self,
+        capabilities: &ModelCapabilities,
+    ) -> Result<(), CompletionError> {
+        if !self.tools.is_empty() && !capabilities.supports_tools {
+            return Err(CompletionError::Unsupported(format!(
+                "agent '{}' needs tool calling ({} tools), which the model does not support",
+                self.name,
+                self.tools.len()
+            )));
+        }
+        Ok(())
+    }
 }
 
 /// Parse a markdown string with YAML frontmatter into an AgentProfile.
 ///
-/// The parser is intentionally simple and supports only single-line `key: value` fields and
-/// array-style tool lists like `tools: ["read_file", "list_dir"]`. It does not currently
-/// support multiline values or quoted values containing `:`.
+/// The parser is intentionally simple: it supports `key: value` fields, array-style tool
+/// lists like `tools: ["read_file", "list_dir"]`, and the scalar forms a description needs
+/// to span lines:
+///
+/// - quoted values (`"..."` or `'...'`), which may continue over several lines; line breaks
+///   fold into spaces, a blank line into a newline, and `\n` in double quotes is a newline;
+/// - block scalars `|` (literal, line breaks kept) and `>` (folded, line breaks become
+///   spaces), optionally with a `-` or `+` chomping indicator, taking the more indented
+///   lines below the key. Trailing line breaks are dropped either way.
 ///
 /// Expected format:
 /// ```text
@@ -30,11 +69,26 @@ pub struct AgentProfile {
 /// description: Implementation planning specialist...
 /// tools: ["read_file", "list_dir", "grep_files"]
 /// model: default
+/// lang: en
+/// exclude_keywords: ["deploy", "release"]
+/// language: ja
+/// tone: formal
 /// ---
 ///
 /// You are an implementation planner...
 /// ```
+///
+/// A missing or empty `description` falls back to the first paragraph of the body (skipping
+/// Markdown headings), with its lines joined by spaces. `language` and `tone` are normalized
+/// by [`super::style`].
+///
+/// A key given twice keeps its last value, with a warning in the log naming the repeated
+/// keys, since that is usually a copy-paste mistake.
 pub fn parse_agent_profile(content: &str) -> Option<AgentProfile> {
+    // Files saved on Windows may carry a UTF-8 BOM and CRLF line endings; neither is
+    // whitespace to `trim`, so normalize them before looking for the opening fence.
+    let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+    let content = content.replace("\r\n", "\n");
     let content = content.trim();
     if !content.starts_with("---") {
         return None;
@@ -49,29 +103,210 @@ pub fn parse_agent_profile(content: &str) -> Option<AgentProfile> {
     let mut description = None;
     let mut tools = Vec::new();
     let mut model_preference = "default".to_string();
+    let mut lang = None;
+    let mut exclude_keywords = Vec::new();
+    let mut language = None;
+    let mut tone = None;
 
-    for line in frontmatter.lines() {
-        let line = line.trim();
-        if let Some(val) = line.strip_prefix("name:") {
-            name = Some(val.trim().to_string());
-        } else if let Some(val) = line.strip_prefix("description:") {
-            description = Some(val.trim().to_string());
-        } else if let Some(val) = line.strip_prefix("model:") {
-            model_preference = val.trim().to_string();
-        } else if let Some(val) = line.strip_prefix("tools:") {
-            tools = parse_string_list(val.trim());
+    let lines = frontmatter.lines().collect::<Vec<_>>();
+    let mut seen_keys = HashSet::new();
+    let mut duplicate_keys = Vec::new();
+    let mut next = 0;
+    while let Some(line) = lines.get(next) {
+        next += 1;
+        let Some((key, val)) = line.trim().split_once(':') else {
+            continue;
+        };
+        if !seen_keys.insert(key) && !duplicate_keys.contains(&key) {
+            duplicate_keys.push(key);
+        }
+        let val = val.trim();
+        match key {
+            "tools" => tools = parse_string_list(val),
+            "exclude_keywords" => exclude_keywords = parse_string_list(val),
+            _ => {
+                let val = parse_scalar(val, &lines, &mut next);
+                match key {
+                    "name" => name = Some(val),
+                    "description" => description = Some(val),
+                    "model" => model_preference = val,
+                    "lang" => lang = Some(val).filter(|lang| !lang.is_empty()),
+                    "language" => language = normalize_language(&val),
+                    "tone" => tone = normalize_tone(&val),
+                    _ => {}
+                }
+            }
         }
     }
 
+    if !duplicate_keys.is_empty() {
+        tracing::warn!(
+            profile = name.as_deref().unwrap_or_default(),
+            keys = %duplicate_keys.join(", "),
+            "agent profile frontmatter repeats keys; the last value of each is used"
+        );
+    }
+
+    let name = name?;
+    if !is_valid_profile_name(&name) {
+        tracing::warn!(name = %name.escape_debug(), "rejecting agent profile with invalid name");
+        return None;
+    }
+
+    let description = description
+        .filter(|description| !description.is_empty())
+        .unwrap_or_else(|| first_paragraph(body));
+
     Some(AgentProfile {
-        name: name?,
-        description: description.unwrap_or_default(),
+        name,
+        description,
         tools,
         model_preference,
         system_prompt: body.to_string(),
+        lang,
+        exclude_keywords,
+        language,
+        tone,
     })
 }
 
+/// The value of a scalar field whose text after the colon is `first`. Block scalars and
+/// quoted values left open consume the following `lines`, advancing `next` past them.
+fn parse_scalar(first: &str, lines: &[&str], next: &mut usize) -> String {
+    if let Some(indicator) = first.strip_prefix('|').or_else(|| first.strip_prefix('>'))
+        && matches!(indicator, "" | "-" | "+")
+    {
+        return parse_block_scalar(first.starts_with('>'), lines, next);
+    }
+    let Some(quote) = first.chars().next().filter(|c| *c == '"' || *c == '\'') else {
+        return first.to_string();
+    };
+
+    let mut text = first[1..].to_string();
+    while closing_quote(&text, quote).is_none() {
+        let Some(line) = lines.get(*next) else {
+            break;
+        };
+        *next += 1;
+        // A line break folds into a space; a blank line stands for a line break.
+        let line = line.trim();
+        if line.is_empty() {
+            text.push('\n');
+        } else {
+            if !text.ends_with('\n') {
+                text.push(' ');
+            }
+            text.push_str(line);
+        }
+    }
+    let end = closing_quote(&text, quote).unwrap_or(text.len());
+    unescape(&text[..end], quote)
+}
+
+/// The indented lines of a `|` or `>` block scalar starting at `next`.
+fn parse_block_scalar(folded: bool, lines: &[&str], next: &mut usize) -> String {
+    let start = *next;
+    let mut indent = None;
+    while let Some(line) = lines.get(*next) {
+        if line.trim().is_empty() {
+            *next += 1;
+            continue;
+        }
+        let width = line.len() - line.trim_start().len();
+        if width == 0 || indent.is_some_and(|indent| width < indent) {
+            break;
+        }
+        indent.get_or_insert(width);
+        *next += 1;
+    }
+    let indent = indent.unwrap_or(0);
+    let block = lines[start..*next]
+        .iter()
+        .map(|line| line.get(indent..).unwrap_or("").trim_end());
+
+    let mut value = String::new();
+    for line in block {
+        if !folded {
+            value.push_str(line);
+            value.push('\n');
+        } else if line.is_empty() {
+            value.push('\n');
+        } else if value.is_empty() || value.ends_with('\n') {
+            value.push_str(line);
+        } else {
+            value.push(' ');
+            value.push_str(line);
+        }
+    }
+    value.trim_end_matches('\n').to_string()
+}
+
+/// Byte offset of the quote closing `text`, which follows an opening `quote`.
+fn closing_quote(text: &str, quote: char) -> Option<usize> {
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' if quote == '"' => {
+                chars.next();
+            }
+            '\'' if quote == '\'' && chars.peek().is_some_and(|(_, c)| *c == '\'') => {
+                chars.next();
+            }
+            c if c == quote => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Resolve escapes: `\n`, `\t`, `\"` and `\\` in double quotes, `''` in single quotes.
+fn unescape(text: &str, quote: char) -> String {
+    if quote == '\'' {
+        return text.replace("''", "'");
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// The first paragraph of `body` that is not a Markdown heading, on one line.
+fn first_paragraph(body: &str) -> String {
+    body.split("\n\n")
+        .map(|paragraph| {
+            paragraph
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .find(|paragraph| !paragraph.is_empty())
+        .unwrap_or_default()
+}
+
+/// Whether `name` can be used as a profile name.
+///
+/// Names double as dedup keys and file-like identifiers, so they must be non-empty and free
+/// of path separators, whitespace and control characters.
+fn is_valid_profile_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name
+            .chars()
+            .any(|c| c == '/' || c == '\\' || c.is_whitespace() || c.is_control())
+}
+
 /// Load an agent profile from a file path.
 pub fn load_agent_profile_from_file(path: &Path) -> Option<AgentProfile> {
     let content = match std::fs::read_to_string(path) {
@@ -142,6 +377,145 @@ You are an implementation planner.
         assert_eq!(def.tools, vec!["read_file", "list_dir", "grep_files"]);
         assert_eq!(def.model_preference, "default");
         assert!(def.system_prompt.contains("implementation planner"));
+        assert_eq!(def.lang, None);
+        assert!(def.exclude_keywords.is_empty());
+
+        let zh = "---\nname: zh_reviewer\ndescription: 代码审查\nlang: zh\n---\nbody";
+        assert_eq!(parse_agent_profile(zh).unwrap().lang.as_deref(), Some("zh"));
+    }
+
+    fn assert_same_profile(actual: &AgentProfile, expected: &AgentProfile) {
+        assert_eq!(actual.name, expected.name);
+        assert_eq!(actual.description, expected.description);
+        assert_eq!(actual.tools, expected.tools);
+        assert_eq!(actual.model_preference, expected.model_preference);
+        assert_eq!(actual.system_prompt, expected.system_prompt);
+        assert_eq!(actual.lang, expected.lang);
+        assert_eq!(actual.exclude_keywords, expected.exclude_keywords);
+        assert_eq!(actual.language, expected.language);
+        assert_eq!(actual.tone, expected.tone);
+    }
+
+    #[test]
+    fn test_parse_bom_prefixed_profile() {
+        let clean = parse_agent_profile(SAMPLE_AGENT).unwrap();
+        let with_bom = format!("\u{feff}{SAMPLE_AGENT}");
+        let def = parse_agent_profile(&with_bom).unwrap();
+        assert_same_profile(&def, &clean);
+    }
+
+    #[test]
+    fn test_parse_crlf_profile() {
+        let clean = parse_agent_profile(SAMPLE_AGENT).unwrap();
+        let crlf = SAMPLE_AGENT.replace('\n', "\r\n");
+        let def = parse_agent_profile(&crlf).unwrap();
+        assert_same_profile(&def, &clean);
+
+        let both = format!("\u{feff}{crlf}");
+        let def = parse_agent_profile(&both).unwrap();
+        assert_same_profile(&def, &clean);
+    }
+
+    #[test]
+    fn test_validate_capabilities() {
+        let def = parse_agent_profile(SAMPLE_AGENT).unwrap();
+        assert!(
+            def.validate_capabilities(&ModelCapabilities::default())
+                .is_ok()
+        );
+
+        let text_only = ModelCapabilities {
+            supports_tools: false,
+            ..ModelCapabilities::default()
+        };
+        let err = def.validate_capabilities(&text_only).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("agent 'planner' needs tool calling (3 tools)")
+        );
+    }
+
+    #[test]
+    fn test_parse_exclude_keywords() {
+        let content = "---\nname: reviewer\ndescription: Code review\nexclude_keywords: [\"Deploy\", 'release notes']\n---\nbody";
+        let def = parse_agent_profile(content).unwrap();
+        assert_eq!(def.exclude_keywords, vec!["Deploy", "release notes"]);
+    }
+
+    #[test]
+    fn test_empty_description_uses_first_body_paragraph() {
+        let content = "---\nname: migrator\ndescription:\n---\n\n# Migrator\n\nPlans database schema\nmigrations and rollbacks.\n\nYou write SQL.";
+        let def = parse_agent_profile(content).unwrap();
+        assert_eq!(
+            def.description,
+            "Plans database schema migrations and rollbacks."
+        );
+
+        let router = crate::internal::ai::agent::profile::AgentProfileRouter::new(vec![def]);
+        assert_eq!(
+            router
+                .select("plan the database schema migrations")
+                .unwrap()
+                .name,
+            "migrator"
+        );
+
+        // An explicit description stays authoritative.
+        let def = parse_agent_profile(SAMPLE_AGENT).unwrap();
+        assert_eq!(def.description, "Implementation planning specialist");
+    }
+
+    #[test]
+    fn test_literal_block_description() {
+        let content = "---\nname: migrator\ndescription: |\n  Plans database schema migrations.\n\n  Also writes rollback scripts.\ntools: [\"read_file\"]\n---\nbody";
+        let def = parse_agent_profile(content).unwrap();
+        assert_eq!(
+            def.description,
+            "Plans database schema migrations.\n\nAlso writes rollback scripts."
+        );
+        assert_eq!(def.tools, vec!["read_file"]);
+
+        // Keywords come from every line, not just the first one.
+        let router = crate::internal::ai::agent::profile::AgentProfileRouter::new(vec![def]);
+        assert_eq!(
+            router.select("need rollback scripts").unwrap().name,
+            "migrator"
+        );
+    }
+
+    #[test]
+    fn test_folded_block_description() {
+        let content = "---\nname: migrator\ndescription: >-\n    Plans database\n    schema migrations.\n\n    Writes rollbacks.\nmodel: fast\n---\nbody";
+        let def = parse_agent_profile(content).unwrap();
+        assert_eq!(
+            def.description,
+            "Plans database schema migrations.\nWrites rollbacks."
+        );
+        assert_eq!(def.model_preference, "fast");
+
+        let router = crate::internal::ai::agent::profile::AgentProfileRouter::new(vec![def]);
+        assert_eq!(
+            router.select("plan schema migrations").unwrap().name,
+            "migrator"
+        );
+    }
+
+    #[test]
+    fn test_quoted_multiline_description() {
+        let content = "---\nname: \"reviewer\"\ndescription: \"Reviews code: logic,\n  security and\n\n  \\\"style\\\".\\nThen reports.\"\nlang: 'en'\n---\nbody";
+        let def = parse_agent_profile(content).unwrap();
+        assert_eq!(def.name, "reviewer");
+        assert_eq!(
+            def.description,
+            "Reviews code: logic, security and\n\"style\".\nThen reports."
+        );
+        assert_eq!(def.lang.as_deref(), Some("en"));
+
+        let single = "---\nname: r\ndescription: 'It''s a\n  reviewer'\n---\nbody";
+        assert_eq!(
+            parse_agent_profile(single).unwrap().description,
+            "It's a reviewer"
+        );
     }
 
     #[test]
@@ -155,6 +529,29 @@ You are an implementation planner.
         assert!(parse_agent_profile(content).is_none());
     }
 
+    #[test]
+    fn test_parse_profile_name_validation() {
+        let with_name = |name: &str| format!("---\nname: {name}\ndescription: test\n---\nbody");
+
+        let def = parse_agent_profile(&with_name("code_reviewer-2.v1")).unwrap();
+        assert_eq!(def.name, "code_reviewer-2.v1");
+
+        for invalid in [
+            "my/agent",
+            "..\\agent",
+            "my agent",
+            "my\tagent",
+            "bell\u{7}",
+            "esc\u{1b}[0m",
+            "",
+        ] {
+            assert!(
+                parse_agent_profile(&with_name(invalid)).is_none(),
+                "{invalid:?} should be rejected"
+            );
+        }
+    }
+
     #[test]
     fn test_parse_string_list() {
         assert_eq!(parse_string_list(r#"["a", "b", "c"]"#), vec!["a", "b", "c"]);
@@ -180,4 +577,46 @@ You are an implementation planner.
         let def = parse_agent_profile(resolver).unwrap();
         assert_eq!(def.name, "build_error_resolver");
     }
+
+    #[test]
+    fn test_duplicate_keys_warn_and_last_wins() {
+        use std::sync::{Arc, Mutex};
+
+        /// Collects everything the subscriber writes.
+        #[derive(Clone, Default)]
+        struct Captured(Arc<Mutex<Vec<u8>>>);
+
+        impl std::io::Write for Captured {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let captured = Captured::default();
+        let writer = captured.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || writer.clone())
+            .with_ansi(false)
+            .finish();
+
+        let def = tracing::subscriber::with_default(subscriber, || {
+            parse_agent_profile(
+                "---\nname: planner\ndescription: Plans\nname: reviewer\nmodel: fast\nname: architect\n---\nBody\n",
+            )
+        })
+        .unwrap();
+        assert_eq!(def.name, "architect");
+        assert_eq!(def.model_preference, "fast");
+
+        let logs = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+        assert!(logs.contains("WARN"), "{logs}");
+        assert!(logs.contains("repeats keys"), "{logs}");
+        assert!(logs.contains("keys=name"), "{logs}");
+        assert!(logs.contains("profile=\"architect\""), "{logs}");
+    }
 }
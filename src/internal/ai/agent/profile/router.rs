@@ -1,19 +1,241 @@
 //! Agent profile router: auto-selects the appropriate profile based on user input.
+//!
+//! Profile descriptions are split into keywords and a profile scores one point per keyword
+//! found in the input. Chinese, Japanese and Korean text has no spaces and its meaningful
+//! terms are often one or two characters long, so CJK descriptions are split into
+//! overlapping character bigrams instead of words (see [`Tokenizer`]).
+//!
+//! With [`AgentProfileRouter::with_tool_keywords`], the words in a profile's tool names
+//! (`run_build` → `run`, `build`) also count, at half the weight of a description keyword.
+//!
+//! Each extra strategy is a toggle in [`RouterConfig`]; the default is plain substring
+//! counting, and they can be combined through [`AgentProfileRouter::with_config`].
+//!
+//! [`AgentProfileRouter::select`] breaks ties between equally scoring profiles in favour of
+//! the first one; [`AgentProfileRouter::select_strict`] reports them as a [`RouteAmbiguity`].
+//!
+//! Semantic routing is separate: after [`AgentProfileRouter::with_embeddings`] has embedded
+//! every description, [`AgentProfileRouter::select_async`] picks the profile whose
+//! description is most similar to the input by cosine similarity.
+
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use thiserror::Error;
 
 use super::parser::AgentProfile;
+use crate::internal::ai::completion::CompletionError;
 
+/// Minimum matching keywords for [`Tokenizer::Words`].
 const MIN_MATCH_SCORE: usize = 2;
+/// Minimum matching bigrams for [`Tokenizer::CjkBigrams`]. Most CJK terms are two
+/// characters, i.e. a single bigram, so two bigrams carry about as much signal as two
+/// English keywords even though a longer term contributes several.
+const MIN_BIGRAM_MATCH_SCORE: usize = 2;
 const MAX_PROFILE_FILE_BYTES: u64 = 1024 * 1024;
+/// Lowest cosine similarity at which [`AgentProfileRouter::select_async`] picks a profile.
+const MIN_EMBEDDING_SIMILARITY: f32 = 0.3;
+/// Score units for a description keyword match; a tool-name keyword is worth one unit.
+const DESCRIPTION_KEYWORD_WEIGHT: usize = 2;
+
+/// Common development terms and the canonical form used in profile descriptions.
+const DEFAULT_SYNONYMS: &[(&str, &str)] = &[
+    ("bug", "error"),
+    ("defect", "error"),
+    ("fault", "error"),
+    ("crash", "failure"),
+    ("broken", "failure"),
+    ("compile", "compilation"),
+    ("compiler", "compilation"),
+    ("blueprint", "design"),
+    ("layout", "architecture"),
+    ("structure", "architecture"),
+    ("audit", "review"),
+    ("inspect", "review"),
+    ("vulnerability", "security"),
+    ("refactor", "refactoring"),
+    ("roadmap", "planning"),
+];
+
+/// Suffixes removed by [`stem`], longest first within each family.
+const STEM_SUFFIXES: &[&str] = &[
+    "ations", "ation", "ities", "ity", "ings", "ing", "ions", "ion", "ies", "ers", "er", "es",
+    "ed", "ly", "y", "s",
+];
+/// Shortest stem [`stem`] leaves behind, so short words are not reduced to fragments.
+const MIN_STEM_LEN: usize = 4;
+
+/// Returns the default synonym map (lowercase token -> canonical form).
+pub fn default_synonyms() -> HashMap<String, String> {
+    DEFAULT_SYNONYMS
+        .iter()
+        .map(|(from, to)| (from.to_string(), to.to_string()))
+        .collect()
+}
+
+/// Strip one common English suffix from an ASCII word (`compilation` → `compil`).
+///
+/// Keywords are matched as substrings, so a stem matches every inflection that starts
+/// with it (`compil` is found in "compiler" and "compiling"). Non-ASCII words are kept.
+fn stem(word: &str) -> &str {
+    if !word.is_ascii() {
+        return word;
+    }
+    STEM_SUFFIXES
+        .iter()
+        .filter_map(|suffix| word.strip_suffix(suffix))
+        .find(|stem| stem.len() >= MIN_STEM_LEN)
+        .unwrap_or(word)
+}
+
+/// Which matching strategies an [`AgentProfileRouter`] combines.
+///
+/// The default enables none of them, which is plain keyword substring counting.
+#[derive(Debug, Clone, Default)]
+pub struct RouterConfig {
+    /// Map of lowercase input tokens to the canonical terms used in profile descriptions.
+    pub synonyms: Option<HashMap<String, String>>,
+    /// Score the words in profile tool names as weak keywords.
+    pub tool_keywords: bool,
+    /// Reduce keywords to their stems, and look up synonyms by stem.
+    pub stemming: bool,
+}
+
+/// How a profile description is split into keywords.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tokenizer {
+    /// Words separated by non-alphanumeric characters, minus stop words and short words.
+    Words,
+    /// Character bigrams for runs of CJK characters; other text is split into words.
+    CjkBigrams,
+}
+
+impl Tokenizer {
+    /// Pick the tokenizer for `profile`: its `lang` hint if set, otherwise CJK bigrams
+    /// when the description contains CJK characters.
+    pub fn for_profile(profile: &AgentProfile) -> Self {
+        match profile.lang.as_deref() {
+            Some(lang) => {
+                let lang = lang.to_ascii_lowercase();
+                if ["zh", "ja", "ko"].iter().any(|cjk| lang.starts_with(cjk)) {
+                    Tokenizer::CjkBigrams
+                } else {
+                    Tokenizer::Words
+                }
+            }
+            None if profile.description.chars().any(is_cjk) => Tokenizer::CjkBigrams,
+            None => Tokenizer::Words,
+        }
+    }
+
+    /// Matches a profile needs before it can be selected.
+    fn min_match_score(self) -> usize {
+        match self {
+            Tokenizer::Words => MIN_MATCH_SCORE,
+            Tokenizer::CjkBigrams => MIN_BIGRAM_MATCH_SCORE,
+        }
+    }
+}
+
+/// Han ideographs, kana and Hangul syllables.
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30FF}'
+        | '\u{3400}'..='\u{4DBF}'
+        | '\u{4E00}'..='\u{9FFF}'
+        | '\u{AC00}'..='\u{D7AF}'
+        | '\u{F900}'..='\u{FAFF}'
+    )
+}
+
+/// Turns text into a vector for [`AgentProfileRouter::select_async`]. Every call must
+/// return vectors of the same length.
+#[async_trait]
+pub trait Embedder: Send + Sync {
+    async fn embed(&self, text: &str) -> Result<Vec<f32>, CompletionError>;
+}
+
+/// Cosine of the angle between `a` and `b`; 0.0 when either is a zero vector or their
+/// lengths differ.
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norms = norm(a) * norm(b);
+    if norms == 0.0 { 0.0 } else { dot / norms }
+}
+
+/// Returned by [`AgentProfileRouter::select_strict`] when several profiles match equally well.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("input matches several profiles equally well: {}", .candidates.join(", "))]
+pub struct RouteAmbiguity {
+    /// Names of the tied profiles, in profile order.
+    pub candidates: Vec<String>,
+}
 
 /// Routes user input to the most appropriate agent profile.
 pub struct AgentProfileRouter {
     profiles: Vec<AgentProfile>,
+    config: RouterConfig,
+    /// One embedding per profile description, in profile order; empty until
+    /// [`with_embeddings`](Self::with_embeddings).
+    description_embeddings: Vec<Vec<f32>>,
 }
 
 impl AgentProfileRouter {
-    /// Create a new router with the given agent profiles.
+    /// Create a new router with the given agent profiles and plain keyword matching.
     pub fn new(profiles: Vec<AgentProfile>) -> Self {
-        Self { profiles }
+        Self::with_config(profiles, RouterConfig::default())
+    }
+
+    /// Create a router that combines the strategies enabled in `config`.
+    pub fn with_config(profiles: Vec<AgentProfile>, config: RouterConfig) -> Self {
+        let router = Self {
+            profiles,
+            config: RouterConfig {
+                synonyms: None,
+                ..config
+            },
+            description_embeddings: Vec::new(),
+        };
+        match config.synonyms {
+            Some(synonyms) => router.with_synonyms(synonyms),
+            None => router,
+        }
+    }
+
+    /// Expand input tokens through `synonyms` before scoring.
+    ///
+    /// Keys are matched against lowercase input tokens; values should be terms that
+    /// appear in profile descriptions.
+    pub fn with_synonyms(mut self, synonyms: HashMap<String, String>) -> Self {
+        self.config.synonyms = Some(
+            synonyms
+                .into_iter()
+                .map(|(from, to)| (from.to_lowercase(), to.to_lowercase()))
+                .collect(),
+        );
+        self
+    }
+
+    /// Expand input tokens through [`default_synonyms`] before scoring.
+    pub fn with_default_synonyms(self) -> Self {
+        self.with_synonyms(default_synonyms())
+    }
+
+    /// Also score the words in each profile's tool names (split on `_`) as keywords worth
+    /// half a description keyword, which helps tell apart profiles with terse descriptions.
+    pub fn with_tool_keywords(mut self) -> Self {
+        self.config.tool_keywords = true;
+        self
+    }
+
+    /// Match keywords by their stem, so "compiling" finds a profile about "compilation".
+    pub fn with_stemming(mut self) -> Self {
+        self.config.stemming = true;
+        self
     }
 
     /// Select the best matching profile for the given user input.
@@ -22,22 +244,115 @@ impl AgentProfileRouter {
     /// appear in the user input. Returns the profile with the highest match score,
     /// or None if no profile matches above a minimum threshold.
     pub fn select(&self, input: &str) -> Option<&AgentProfile> {
-        let input_lower = input.to_lowercase();
-        let mut best: Option<(&AgentProfile, usize)> = None;
+        self.select_with_confidence(input)
+            .map(|(profile, _)| profile)
+    }
 
-        for profile in &self.profiles {
-            let score = Self::match_score(&input_lower, profile);
-            // Require at least 2 keyword matches to avoid false positives
-            // on short or generic inputs like "test", "build", etc.
-            if score >= MIN_MATCH_SCORE
-                && best
-                    .as_ref()
-                    .is_none_or(|(_, best_score)| score > *best_score)
+    /// Like [`select`](Self::select), but also return how well the profile matched.
+    ///
+    /// Confidence is the fraction of the profile's description keywords found in the
+    /// input, in `0.0..=1.0`. Unlike raw match counts it is comparable across profiles
+    /// with descriptions of different lengths, so callers can apply their own cutoff.
+    pub fn select_with_confidence(&self, input: &str) -> Option<(&AgentProfile, f32)> {
+        let mut best: Option<(&AgentProfile, usize, usize)> = None;
+        for (profile, score, total) in self.candidates(input) {
+            if best
+                .as_ref()
+                .is_none_or(|(_, best_score, _)| score > *best_score)
             {
-                best = Some((profile, score));
+                best = Some((profile, score, total));
             }
         }
 
+        best.map(|(profile, score, total)| (profile, score as f32 / total as f32))
+    }
+
+    /// Like [`select`](Self::select), but refuse to choose between profiles that tie for
+    /// the best score: the error names every tied profile, in profile order.
+    pub fn select_strict(&self, input: &str) -> Result<Option<&AgentProfile>, RouteAmbiguity> {
+        let candidates = self.candidates(input);
+        let Some(best_score) = candidates.iter().map(|(_, score, _)| *score).max() else {
+            return Ok(None);
+        };
+        let mut tied = candidates
+            .into_iter()
+            .filter(|(_, score, _)| *score == best_score)
+            .map(|(profile, _, _)| profile);
+        let first = tied.next();
+        let rest: Vec<&AgentProfile> = tied.collect();
+        if rest.is_empty() {
+            return Ok(first);
+        }
+        Err(RouteAmbiguity {
+            candidates: first
+                .into_iter()
+                .chain(rest)
+                .map(|profile| profile.name.clone())
+                .collect(),
+        })
+    }
+
+    /// Every profile matching `input` above the minimum score, with its matched and total
+    /// keyword weight, in profile order.
+    fn candidates(&self, input: &str) -> Vec<(&AgentProfile, usize, usize)> {
+        let input_lower = self.normalize(&input.to_lowercase());
+        self.profiles
+            .iter()
+            .filter_map(|profile| {
+                let tokenizer = Tokenizer::for_profile(profile);
+                let (score, total) = self.match_score(&input_lower, profile, tokenizer);
+                // Require at least 2 keyword matches to avoid false positives
+                // on short or generic inputs like "test", "build", etc.
+                (score >= tokenizer.min_match_score() * DESCRIPTION_KEYWORD_WEIGHT)
+                    .then_some((profile, score, total))
+            })
+            .collect()
+    }
+
+    /// Embed every profile description with `embedder`, enabling
+    /// [`select_async`](Self::select_async).
+    pub async fn with_embeddings<E: Embedder + ?Sized>(
+        mut self,
+        embedder: &E,
+    ) -> Result<Self, CompletionError> {
+        let mut embeddings = Vec::with_capacity(self.profiles.len());
+        for profile in &self.profiles {
+            embeddings.push(embedder.embed(&profile.description).await?);
+        }
+        self.description_embeddings = embeddings;
+        Ok(self)
+    }
+
+    /// Select the profile whose description embedding is most similar to `input`'s, as long
+    /// as the similarity reaches [`MIN_EMBEDDING_SIMILARITY`]; ties go to the first profile.
+    ///
+    /// Falls back to keyword scoring ([`select`](Self::select)) when `embedder` is `None`,
+    /// the descriptions were not embedded with [`with_embeddings`](Self::with_embeddings),
+    /// or embedding the input fails.
+    pub async fn select_async<E: Embedder + ?Sized>(
+        &self,
+        input: &str,
+        embedder: Option<&E>,
+    ) -> Option<&AgentProfile> {
+        let input_embedding = match embedder {
+            Some(embedder) if self.description_embeddings.len() == self.profiles.len() => {
+                embedder.embed(input).await.ok()
+            }
+            _ => None,
+        };
+        let Some(input_embedding) = input_embedding else {
+            return self.select(input);
+        };
+
+        let mut best: Option<(&AgentProfile, f32)> = None;
+        for (profile, embedding) in self.profiles.iter().zip(&self.description_embeddings) {
+            let similarity = cosine_similarity(&input_embedding, embedding);
+            if similarity >= MIN_EMBEDDING_SIMILARITY
+                && best.is_none_or(|(_, best)| similarity > best)
+            {
+                best = Some((profile, similarity));
+            }
+        }
         best.map(|(profile, _)| profile)
     }
 
@@ -51,17 +366,125 @@ impl AgentProfileRouter {
         self.profiles.iter().find(|a| a.name == name)
     }
 
+    /// Append the canonical form of every input token that has a synonym.
+    ///
+    /// The original text is kept so that terms already in canonical form still match.
+    fn normalize(&self, input_lower: &str) -> String {
+        let Some(synonyms) = &self.config.synonyms else {
+            return input_lower.to_string();
+        };
+        let lookup = |token: &str| {
+            synonyms.get(token).or_else(|| {
+                if !self.config.stemming {
+                    return None;
+                }
+                let token = stem(token);
+                synonyms
+                    .iter()
+                    .find(|(from, _)| stem(from) == token)
+                    .map(|(_, to)| to)
+            })
+        };
+        let mut normalized = input_lower.to_string();
+        for canonical in input_lower
+            .split(|c: char| !c.is_alphanumeric())
+            .filter_map(lookup)
+        {
+            normalized.push(' ');
+            normalized.push_str(canonical);
+        }
+        normalized
+    }
+
     /// Calculate a match score for a profile against user input.
-    fn match_score(input_lower: &str, profile: &AgentProfile) -> usize {
-        let keywords = Self::extract_keywords(&profile.description);
-        keywords
+    ///
+    /// Returns the matched and total keyword weight, counting description keywords as
+    /// [`DESCRIPTION_KEYWORD_WEIGHT`] and, with tool keywords enabled, tool-name words that
+    /// are not already description keywords as one. The match is zero when the input
+    /// contains one of the profile's `exclude_keywords`. With stemming enabled, keywords
+    /// that share a stem count once.
+    fn match_score(
+        &self,
+        input_lower: &str,
+        profile: &AgentProfile,
+        tokenizer: Tokenizer,
+    ) -> (usize, usize) {
+        let mut keywords = Self::extract_keywords(&profile.description, tokenizer);
+        if self.config.stemming {
+            let mut seen = std::collections::HashSet::new();
+            keywords = keywords
+                .iter()
+                .map(|kw| stem(kw).to_string())
+                .filter(|kw| seen.insert(kw.clone()))
+                .collect();
+        }
+        let mut tool_keywords = Vec::new();
+        if self.config.tool_keywords {
+            for word in profile.tools.iter().flat_map(|tool| {
+                Self::extract_words(&tool.to_lowercase().replace('_', " ")).collect::<Vec<_>>()
+            }) {
+                let word = if self.config.stemming {
+                    stem(&word).to_string()
+                } else {
+                    word
+                };
+                if !keywords.contains(&word) && !tool_keywords.contains(&word) {
+                    tool_keywords.push(word);
+                }
+            }
+        }
+
+        let matches = |words: &[String]| {
+            words
+                .iter()
+                .filter(|kw| input_lower.contains(kw.as_str()))
+                .count()
+        };
+        let total = keywords.len() * DESCRIPTION_KEYWORD_WEIGHT + tool_keywords.len();
+        if profile
+            .exclude_keywords
             .iter()
-            .filter(|kw| input_lower.contains(kw.as_str()))
-            .count()
+            .any(|kw| input_lower.contains(kw.to_lowercase().as_str()))
+        {
+            return (0, total);
+        }
+        let matched = matches(&keywords) * DESCRIPTION_KEYWORD_WEIGHT + matches(&tool_keywords);
+        (matched, total)
     }
 
     /// Extract meaningful keywords from a description string.
-    fn extract_keywords(description: &str) -> Vec<String> {
+    fn extract_keywords(description: &str, tokenizer: Tokenizer) -> Vec<String> {
+        let description = description.to_lowercase();
+        let mut keywords = Vec::new();
+        match tokenizer {
+            Tokenizer::Words => keywords.extend(Self::extract_words(&description)),
+            Tokenizer::CjkBigrams => {
+                let mut rest = description.as_str();
+                while let Some(start) = rest.find(is_cjk) {
+                    keywords.extend(Self::extract_words(&rest[..start]));
+                    let run = &rest[start..];
+                    let end = run.find(|c: char| !is_cjk(c)).unwrap_or(run.len());
+                    let chars: Vec<char> = run[..end].chars().collect();
+                    if chars.len() == 1 {
+                        keywords.push(chars[0].to_string());
+                    }
+                    keywords.extend(chars.windows(2).map(|pair| pair.iter().collect()));
+                    rest = &run[end..];
+                }
+                keywords.extend(Self::extract_words(rest));
+                // Bigrams repeat far more often than words; count each one once.
+                let mut seen = std::collections::HashSet::new();
+                keywords.retain(|kw| seen.insert(kw.clone()));
+            }
+        }
+        keywords
+    }
+
+    /// Split on non-alphanumeric characters, dropping stop words and short words.
+    ///
+    /// ASCII words need at least three letters; other scripts pack more meaning per
+    /// character, so two are enough.
+    fn extract_words(text: &str) -> impl Iterator<Item = String> + '_ {
         let stop_words = [
             "a", "an", "the", "is", "are", "was", "were", "be", "been", "being", "have", "has",
             "had", "do", "does", "did", "will", "would", "could", "should", "may", "might",
@@ -70,12 +493,16 @@ impl AgentProfileRouter {
             "after", "above", "below", "between", "use", "that", "this", "it", "its",
         ];
 
-        description
-            .to_lowercase()
-            .split(|c: char| !c.is_alphanumeric())
-            .filter(|w| w.len() > 2 && !stop_words.contains(w))
+        text.split(|c: char| !c.is_alphanumeric())
+            .filter(move |w| {
+                let long_enough = if w.is_ascii() {
+                    w.len() > 2
+                } else {
+                    w.chars().count() >= 2
+                };
+                long_enough && !stop_words.contains(w)
+            })
             .map(String::from)
-            .collect()
     }
 }
 
@@ -244,6 +671,58 @@ mod tests {
         assert_eq!(selected.unwrap().name, "build_error_resolver");
     }
 
+    #[test]
+    fn test_router_synonyms_route_to_architect() {
+        let input = "draft a blueprint for the plugin layout";
+
+        let router = AgentProfileRouter::new(load_embedded_profiles());
+        assert!(router.select(input).is_none());
+
+        let router = router.with_default_synonyms();
+        let selected = router.select(input);
+        assert_eq!(selected.unwrap().name, "architect");
+    }
+
+    #[test]
+    fn test_router_config_combines_stemming_and_synonyms() {
+        let input = "audited for defects and vulnerabilities";
+        let router = |config: RouterConfig| {
+            AgentProfileRouter::with_config(load_embedded_profiles(), config)
+        };
+
+        // Neither strategy alone finds two keywords: the synonyms are keyed by the bare
+        // words, and stemming only turns "vulnerabilities" into a match.
+        assert!(router(RouterConfig::default()).select(input).is_none());
+        let synonyms_only = RouterConfig {
+            synonyms: Some(default_synonyms()),
+            ..RouterConfig::default()
+        };
+        assert!(router(synonyms_only).select(input).is_none());
+        let stemming_only = RouterConfig {
+            stemming: true,
+            ..RouterConfig::default()
+        };
+        assert!(router(stemming_only).select(input).is_none());
+
+        let combined = router(RouterConfig {
+            synonyms: Some(default_synonyms()),
+            stemming: true,
+            ..RouterConfig::default()
+        });
+        assert_eq!(combined.select(input).unwrap().name, "code_reviewer");
+        // Stemming keeps the plain routes working.
+        assert_eq!(
+            combined
+                .select("fix the build error compilation failure")
+                .unwrap()
+                .name,
+            "build_error_resolver"
+        );
+        assert_eq!(stem("compilation"), "compil");
+        assert_eq!(stem("fixes"), "fixe");
+        assert_eq!(stem("代码"), "代码");
+    }
+
     #[test]
     fn test_router_no_match() {
         let profiles = load_embedded_profiles();
@@ -253,6 +732,26 @@ mod tests {
         assert!(selected.is_none());
     }
 
+    #[test]
+    fn test_router_confidence_higher_for_precise_query() {
+        let router = AgentProfileRouter::new(load_embedded_profiles());
+
+        let (vague, vague_confidence) = router
+            .select_with_confidence("fix the build error")
+            .unwrap();
+        let (precise, precise_confidence) = router
+            .select_with_confidence(
+                "fix the build error: compilation failure with type errors, resolve it incrementally",
+            )
+            .unwrap();
+
+        assert_eq!(vague.name, "build_error_resolver");
+        assert_eq!(precise.name, "build_error_resolver");
+        assert!(precise_confidence > vague_confidence);
+        assert!(precise_confidence <= 1.0);
+        assert!(vague_confidence > 0.0);
+    }
+
     #[test]
     fn test_router_get_by_name() {
         let profiles = load_embedded_profiles();
@@ -272,6 +771,10 @@ mod tests {
                 tools: vec![],
                 model_preference: "default".to_string(),
                 system_prompt: "A".to_string(),
+                lang: None,
+                exclude_keywords: vec![],
+                language: None,
+                tone: None,
             },
             AgentProfile {
                 name: "agent_b".to_string(),
@@ -279,6 +782,10 @@ mod tests {
                 tools: vec![],
                 model_preference: "default".to_string(),
                 system_prompt: "B".to_string(),
+                lang: None,
+                exclude_keywords: vec![],
+                language: None,
+                tone: None,
             },
         ];
         let router = AgentProfileRouter::new(profiles);
@@ -289,6 +796,119 @@ mod tests {
         assert_eq!(selected.unwrap().name, "agent_a");
     }
 
+    #[test]
+    fn test_select_strict_reports_tied_profiles() {
+        let profile = |name: &str, description: &str| AgentProfile {
+            name: name.to_string(),
+            description: description.to_string(),
+            tools: vec![],
+            model_preference: "default".to_string(),
+            system_prompt: String::new(),
+            lang: None,
+            exclude_keywords: vec![],
+            language: None,
+            tone: None,
+        };
+        let router = AgentProfileRouter::new(vec![
+            profile("linter", "review code style"),
+            profile("auditor", "audit security issues"),
+            profile("reviewer", "review code quality"),
+        ]);
+
+        let err = router.select_strict("review this code").unwrap_err();
+        assert_eq!(err.candidates, vec!["linter", "reviewer"]);
+        assert_eq!(
+            err.to_string(),
+            "input matches several profiles equally well: linter, reviewer"
+        );
+        // The lenient selection still takes the first.
+        assert_eq!(router.select("review this code").unwrap().name, "linter");
+
+        assert_eq!(
+            router
+                .select_strict("review code quality")
+                .unwrap()
+                .unwrap()
+                .name,
+            "reviewer"
+        );
+        assert!(router.select_strict("deploy the app").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_tool_keywords_break_tie() {
+        let profile = |name: &str, description: &str, tool: &str| AgentProfile {
+            name: name.to_string(),
+            description: description.to_string(),
+            tools: vec![tool.to_string()],
+            model_preference: "default".to_string(),
+            system_prompt: String::new(),
+            lang: None,
+            exclude_keywords: vec![],
+            language: None,
+            tone: None,
+        };
+        let profiles = vec![
+            profile("linter", "Fixes errors in style", "read_file"),
+            profile("fixer", "Fixes errors quickly", "run_build"),
+        ];
+        let input = "fixes errors after the build";
+
+        // Both descriptions match twice; by default the first profile wins the tie.
+        let router = AgentProfileRouter::new(profiles.clone());
+        assert_eq!(router.select(input).unwrap().name, "linter");
+
+        let router = AgentProfileRouter::new(profiles).with_tool_keywords();
+        let (selected, confidence) = router.select_with_confidence(input).unwrap();
+        assert_eq!(selected.name, "fixer");
+        // 2 description keywords (weight 2) and "build" (weight 1) of 3 + 2 keywords.
+        assert_eq!(confidence, 5.0 / 8.0);
+
+        // Tool words alone never reach the threshold.
+        assert!(router.select("run the build").is_none());
+    }
+
+    #[test]
+    fn test_exclude_keywords_suppress_match() {
+        let profile = |name: &str, description: &str, exclude: &[&str]| AgentProfile {
+            name: name.to_string(),
+            description: description.to_string(),
+            tools: vec![],
+            model_preference: "default".to_string(),
+            system_prompt: String::new(),
+            lang: None,
+            exclude_keywords: exclude.iter().map(|kw| kw.to_string()).collect(),
+            language: None,
+            tone: None,
+        };
+        let profiles = vec![
+            profile(
+                "reviewer",
+                "Review code changes for quality",
+                &["Deploy", "release"],
+            ),
+            profile("shipper", "Deploy code changes", &[]),
+        ];
+        let router = AgentProfileRouter::new(profiles);
+
+        // The reviewer matches more keywords, so it wins without the exclusion.
+        assert_eq!(
+            router
+                .select("review code changes for quality")
+                .unwrap()
+                .name,
+            "reviewer"
+        );
+        assert_eq!(
+            router
+                .select("review code changes for quality, then deploy")
+                .unwrap()
+                .name,
+            "shipper"
+        );
+        assert!(router.select("review code for the release").is_none());
+    }
+
     #[test]
     fn test_load_profiles_with_project_override() {
         let tmp = tempfile::TempDir::new().unwrap();
@@ -305,4 +925,129 @@ mod tests {
         assert_eq!(planner.description, "Custom planner");
         assert_eq!(planner.model_preference, "fast");
     }
+
+    fn chinese_reviewer() -> AgentProfile {
+        super::super::parser::parse_agent_profile(
+            "---\nname: zh_reviewer\ndescription: 代码审查专家，检查代码质量和安全问题\n---\n你是代码审查专家。",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_cjk_description_uses_bigrams() {
+        let profile = chinese_reviewer();
+        assert_eq!(Tokenizer::for_profile(&profile), Tokenizer::CjkBigrams);
+        let keywords =
+            AgentProfileRouter::extract_keywords(&profile.description, Tokenizer::CjkBigrams);
+        assert!(keywords.contains(&"代码".to_string()));
+        assert!(keywords.contains(&"安全".to_string()));
+        assert_eq!(keywords.iter().filter(|kw| *kw == "代码").count(), 1);
+
+        // Mixed text keeps words for the non-CJK parts, including short non-ASCII ones.
+        let keywords =
+            AgentProfileRouter::extract_keywords("review Rust 代码 für né", Tokenizer::CjkBigrams);
+        assert_eq!(keywords, vec!["review", "rust", "代码", "für", "né"]);
+
+        // An explicit hint wins over detection.
+        let hinted = AgentProfile {
+            lang: Some("en".to_string()),
+            ..profile
+        };
+        assert_eq!(Tokenizer::for_profile(&hinted), Tokenizer::Words);
+        let hinted = AgentProfile {
+            lang: Some("zh-CN".to_string()),
+            description: "code review".to_string(),
+            ..hinted
+        };
+        assert_eq!(Tokenizer::for_profile(&hinted), Tokenizer::CjkBigrams);
+    }
+
+    #[test]
+    fn test_router_selects_chinese_profile() {
+        let mut profiles = load_embedded_profiles();
+        profiles.push(chinese_reviewer());
+        let router = AgentProfileRouter::new(profiles);
+
+        let selected = router.select("请帮我审查这段代码的安全性");
+        assert_eq!(selected.unwrap().name, "zh_reviewer");
+        assert!(router.select("你好").is_none());
+
+        // English routing is unaffected by the extra profile.
+        assert_eq!(
+            router
+                .select("review this code for quality and security")
+                .unwrap()
+                .name,
+            "code_reviewer"
+        );
+        assert_eq!(
+            router
+                .select("design the system architecture")
+                .unwrap()
+                .name,
+            "architect"
+        );
+    }
+
+    /// Counts words per concept: axis 0 is storage, axis 1 is user interface.
+    struct ConceptEmbedder;
+
+    #[async_trait]
+    impl Embedder for ConceptEmbedder {
+        async fn embed(&self, text: &str) -> Result<Vec<f32>, CompletionError> {
+            let mut vector = vec![0.0; 2];
+            for word in text.to_lowercase().split(|c: char| !c.is_alphanumeric()) {
+                match word {
+                    "database" | "schema" | "migrations" | "sql" | "table" => vector[0] += 1.0,
+                    "frontend" | "button" | "styling" | "css" | "layout" => vector[1] += 1.0,
+                    _ => {}
+                }
+            }
+            Ok(vector)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_select_async_picks_most_similar_description() {
+        let profile = |name: &str, description: &str| {
+            super::super::parser::parse_agent_profile(&format!(
+                "---\nname: {name}\ndescription: {description}\n---\nYou help."
+            ))
+            .unwrap()
+        };
+        let router = AgentProfileRouter::new(vec![
+            profile("ui_expert", "Frontend button styling"),
+            profile("db_expert", "Database schema migrations"),
+        ]);
+        let input = "the sql table is slow";
+        assert!(router.select(input).is_none());
+
+        // Without embedded descriptions the embedder is ignored.
+        assert!(
+            router
+                .select_async(input, Some(&ConceptEmbedder))
+                .await
+                .is_none()
+        );
+
+        let router = router.with_embeddings(&ConceptEmbedder).await.unwrap();
+        let selected = router.select_async(input, Some(&ConceptEmbedder)).await;
+        assert_eq!(selected.unwrap().name, "db_expert");
+        let selected = router
+            .select_async("css for the layout", Some(&ConceptEmbedder))
+            .await;
+        assert_eq!(selected.unwrap().name, "ui_expert");
+        assert!(
+            router
+                .select_async("hello", Some(&ConceptEmbedder))
+                .await
+                .is_none()
+        );
+
+        // No embedder falls back to keyword scoring.
+        let selected = router
+            .select_async("frontend button styling", None::<&dyn Embedder>)
+            .await;
+        assert_eq!(selected.unwrap().name, "ui_expert");
+    }
 }
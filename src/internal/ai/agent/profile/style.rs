@@ -0,0 +1,226 @@
+//! Response language and tone of agent profiles.
+//!
+//! A profile may set `language:` and `tone:` in its frontmatter. Known values are normalized
+//! (`ja`, `Japanese` and `JAPANESE` all become "Japanese"); anything else is kept as written,
+//! so a team can ask for "Swiss German" or a "playful" tone, with a warning in the log in
+//! case it was a typo. The style becomes one instruction, such as "Always respond in
+//! Japanese; use a formal tone.", appended to the system prompt of the profile's agent and
+//! of the models it calls internally, like the best-of judge.
+//!
+//! The language comes from the first of: the `--language` flag of the `ai` commands, the
+//! profile, and the [`LANGUAGE_KEY`] config. The tone only comes from the profile.
+
+use super::AgentProfile;
+
+/// Config key of the language used when neither the flag nor the profile sets one.
+pub const LANGUAGE_KEY: &str = "ai.language";
+
+/// Language codes and the names used in the instruction.
+const KNOWN_LANGUAGES: &[(&str, &str)] = &[
+    ("en", "English"),
+    ("zh", "Chinese"),
+    ("ja", "Japanese"),
+    ("ko", "Korean"),
+    ("de", "German"),
+    ("fr", "French"),
+    ("es", "Spanish"),
+    ("pt", "Portuguese"),
+    ("ru", "Russian"),
+    ("it", "Italian"),
+];
+
+const KNOWN_TONES: &[&str] = &[
+    "neutral",
+    "formal",
+    "casual",
+    "friendly",
+    "concise",
+    "technical",
+];
+
+/// The name of the language `value` stands for, by code or name; an unknown language is
+/// kept as written. `None` for an empty value.
+pub fn normalize_language(value: &str) -> Option<String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return None;
+    }
+    let known = KNOWN_LANGUAGES
+        .iter()
+        .find(|(code, name)| value.eq_ignore_ascii_case(code) || value.eq_ignore_ascii_case(name));
+    match known {
+        Some((_, name)) => Some(name.to_string()),
+        None => {
+            tracing::warn!(language = %value, "unknown response language, using it as written");
+            Some(value.to_string())
+        }
+    }
+}
+
+/// `value` in lower case when it is a known tone, else as written. `None` for an empty value.
+pub fn normalize_tone(value: &str) -> Option<String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return None;
+    }
+    match KNOWN_TONES
+        .iter()
+        .find(|tone| value.eq_ignore_ascii_case(tone))
+    {
+        Some(tone) => Some(tone.to_string()),
+        None => {
+            tracing::warn!(tone = %value, "unknown response tone, using it as written");
+            Some(value.to_string())
+        }
+    }
+}
+
+/// The instruction asking for answers in `language` with a `tone` tone, if either is set.
+pub fn style_instruction(language: Option<&str>, tone: Option<&str>) -> Option<String> {
+    match (language, tone) {
+        (Some(language), Some(tone)) => {
+            Some(format!("Always respond in {language}; use a {tone} tone."))
+        }
+        (Some(language), None) => Some(format!("Always respond in {language}.")),
+        (None, Some(tone)) => Some(format!("Use a {tone} tone.")),
+        (None, None) => None,
+    }
+}
+
+impl AgentProfile {
+    /// The profile with its language resolved: `flag` wins over the profile's own
+    /// `language:`, which wins over `config_default`.
+    pub fn with_language(mut self, flag: Option<&str>, config_default: Option<&str>) -> Self {
+        if let Some(language) = flag.and_then(normalize_language) {
+            self.language = Some(language);
+        } else if self.language.is_none() {
+            self.language = config_default.and_then(normalize_language);
+        }
+        self
+    }
+
+    /// The instruction for the profile's language and tone, if it sets either.
+    pub fn style_instruction(&self) -> Option<String> {
+        style_instruction(self.language.as_deref(), self.tone.as_deref())
+    }
+
+    /// The system prompt followed by the style instruction.
+    pub fn preamble(&self) -> String {
+        match self.style_instruction() {
+            Some(instruction) => format!("{}\n\n{instruction}", self.system_prompt.trim_end()),
+            None => self.system_prompt.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::internal::ai::{
+        CompletionModel, Prompt,
+        agent::profile::{build_agent_from_profile, parse_agent_profile},
+        completion::{
+            AssistantContent, CompletionError, CompletionRequest, CompletionResponse, Text,
+        },
+    };
+
+    fn profile(frontmatter: &str) -> AgentProfile {
+        parse_agent_profile(&format!(
+            "---\nname: reviewer\ndescription: Reviews code\n{frontmatter}---\nYou review code.\n"
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_normalize_known_and_free_form_values() {
+        assert_eq!(normalize_language("ja").as_deref(), Some("Japanese"));
+        assert_eq!(normalize_language(" GERMAN ").as_deref(), Some("German"));
+        assert_eq!(
+            normalize_language("Swiss German").as_deref(),
+            Some("Swiss German")
+        );
+        assert_eq!(normalize_language(""), None);
+        assert_eq!(normalize_tone("Formal").as_deref(), Some("formal"));
+        assert_eq!(normalize_tone("playful").as_deref(), Some("playful"));
+    }
+
+    #[test]
+    fn test_profile_level_style() {
+        let profile = profile("language: ja\ntone: Formal\n");
+        assert_eq!(profile.language.as_deref(), Some("Japanese"));
+        assert_eq!(profile.tone.as_deref(), Some("formal"));
+        assert_eq!(
+            profile.preamble(),
+            "You review code.\n\nAlways respond in Japanese; use a formal tone."
+        );
+
+        let plain = self::profile("");
+        assert_eq!(plain.style_instruction(), None);
+        assert_eq!(plain.preamble(), "You review code.");
+    }
+
+    #[test]
+    fn test_language_precedence() {
+        // The config applies only when the profile sets no language.
+        let config_level = profile("tone: casual\n").with_language(None, Some("fr"));
+        assert_eq!(
+            config_level.preamble(),
+            "You review code.\n\nAlways respond in French; use a casual tone."
+        );
+
+        let profile_level = profile("language: de\n").with_language(None, Some("fr"));
+        assert_eq!(
+            profile_level.preamble(),
+            "You review code.\n\nAlways respond in German."
+        );
+
+        let flag_level = profile("language: de\n").with_language(Some("es"), Some("fr"));
+        assert_eq!(
+            flag_level.preamble(),
+            "You review code.\n\nAlways respond in Spanish."
+        );
+
+        // An empty flag does not clear the profile's language.
+        let empty_flag = profile("language: de\n").with_language(Some(" "), None);
+        assert_eq!(empty_flag.language.as_deref(), Some("German"));
+    }
+
+    /// Records the preamble of each request and answers "ok".
+    #[derive(Clone, Default)]
+    struct RecordingModel(Arc<Mutex<Vec<Option<String>>>>);
+
+    impl CompletionModel for RecordingModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            request: CompletionRequest,
+        ) -> Result<CompletionResponse<Self::Response>, CompletionError> {
+            self.0.lock().unwrap().push(request.preamble);
+            Ok(CompletionResponse {
+                content: vec![AssistantContent::Text(Text {
+                    text: "ok".to_string(),
+                })],
+                raw_response: (),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_agent_from_profile_sends_style_last() {
+        let model = RecordingModel::default();
+        let preambles = Arc::clone(&model.0);
+        let profile = profile("tone: formal\n").with_language(Some("ko"), None);
+        let agent = build_agent_from_profile(model, &profile).build();
+        let _: String = agent.prompt("review this").await.unwrap();
+
+        assert_eq!(
+            preambles.lock().unwrap().as_slice(),
+            [Some(
+                "You review code.\n\nAlways respond in Korean; use a formal tone.".to_string()
+            )]
+        );
+    }
+}
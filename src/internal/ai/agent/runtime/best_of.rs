@@ -0,0 +1,534 @@
+//! Best-of-n sampling: generate several candidate answers and keep one.
+
+use std::{fmt, sync::Arc};
+
+use async_trait::async_trait;
+
+use super::{Agent, PromptOutcome, tool_loop};
+use crate::internal::ai::completion::{
+    AssistantContent, CompletionError, CompletionModel, CompletionRequest, Message, UserContent,
+    check_refusal, enforce_capabilities,
+};
+
+/// Instructions given to the judge model by [`ModelJudge`].
+const JUDGE_PREAMBLE: &str = "You grade answers written by an assistant. Rate how well the \
+answer fulfils the request on a scale from 0 (useless) to 10 (perfect). Reply with the number \
+only.";
+
+/// Scores one candidate answer; higher is better.
+#[async_trait]
+pub trait CandidateJudge: Send + Sync {
+    async fn score(&self, prompt: &str, candidate: &str) -> Result<f64, CompletionError>;
+
+    /// [`CandidateJudge::score`] for an agent whose answers follow `response_style` (see
+    /// [`AgentBuilder::response_style`]). Judges that write text should follow it too; the
+    /// default ignores it.
+    ///
+    /// [`AgentBuilder::response_style`]: super::AgentBuilder::response_style
+    async fn score_styled(
+        &self,
+        prompt: &str,
+        candidate: &str,
+        response_style: Option<&str>,
+    ) -> Result<f64, CompletionError> {
+        let _ = response_style;
+        self.score(prompt, candidate).await
+    }
+}
+
+/// A [`CandidateJudge`] that asks a (usually cheaper) model to rate each candidate.
+pub struct ModelJudge<J: CompletionModel> {
+    model: J,
+}
+
+impl<J: CompletionModel> ModelJudge<J> {
+    pub fn new(model: J) -> Self {
+        Self { model }
+    }
+}
+
+#[async_trait]
+impl<J: CompletionModel + 'static> CandidateJudge for ModelJudge<J> {
+    async fn score(&self, prompt: &str, candidate: &str) -> Result<f64, CompletionError> {
+        self.score_styled(prompt, candidate, None).await
+    }
+
+    async fn score_styled(
+        &self,
+        prompt: &str,
+        candidate: &str,
+        response_style: Option<&str>,
+    ) -> Result<f64, CompletionError> {
+        let preamble = match response_style {
+            Some(style) => format!("{JUDGE_PREAMBLE}\n\n{style}"),
+            None => JUDGE_PREAMBLE.to_string(),
+        };
+        let request = CompletionRequest {
+            preamble: Some(preamble),
+            chat_history: vec![Message::user(format!(
+                "Request:\n{prompt}\n\nAnswer:\n{candidate}"
+            ))],
+            temperature: Some(0.0),
+            ..Default::default()
+        };
+        let response = self.model.completion(request).await?;
+        check_refusal(&response.content)?;
+        let reply = response_text(&response.content);
+        reply
+            .split(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .find_map(|token| token.parse::<f64>().ok())
+            .ok_or_else(|| {
+                CompletionError::ResponseError(format!("Judge returned no score: {reply}"))
+            })
+    }
+}
+
+/// Caller-supplied selector: receives the candidate texts, returns the chosen index.
+pub type CandidateSelector = dyn Fn(&[String]) -> usize + Send + Sync;
+
+/// How [`Agent::prompt_best_of`] picks one answer among its candidates.
+#[derive(Clone, Default)]
+pub enum SelectionStrategy {
+    /// The first candidate the provider returned.
+    #[default]
+    First,
+    /// The candidate with the most characters.
+    Longest,
+    /// The candidate the judge scores highest; ties go to the earlier candidate.
+    Judge(Arc<dyn CandidateJudge>),
+    /// A caller-supplied function returning the index of the chosen candidate.
+    Custom(Arc<CandidateSelector>),
+}
+
+impl SelectionStrategy {
+    /// Score candidates with `model` through a [`ModelJudge`].
+    pub fn judge<J: CompletionModel + 'static>(model: J) -> Self {
+        Self::Judge(Arc::new(ModelJudge::new(model)))
+    }
+
+    /// Pick candidates with `select`, which receives the candidate texts.
+    pub fn custom(select: impl Fn(&[String]) -> usize + Send + Sync + 'static) -> Self {
+        Self::Custom(Arc::new(select))
+    }
+
+    async fn select(
+        &self,
+        prompt: &str,
+        candidates: &[String],
+        response_style: Option<&str>,
+    ) -> Result<usize, CompletionError> {
+        match self {
+            SelectionStrategy::First => Ok(0),
+            SelectionStrategy::Longest => Ok(candidates
+                .iter()
+                .enumerate()
+                .rev()
+                .max_by_key(|(_, text)| text.chars().count())
+                .map_or(0, |(idx, _)| idx)),
+            SelectionStrategy::Judge(judge) => {
+                let scores = futures::future::try_join_all(
+                    candidates
+                        .iter()
+                        .map(|text| judge.score_styled(prompt, text, response_style)),
+                )
+                .await?;
+                let mut best = 0;
+                for (idx, score) in scores.iter().enumerate() {
+                    if *score > scores[best] {
+                        best = idx;
+                    }
+                }
+                Ok(best)
+            }
+            SelectionStrategy::Custom(select) => {
+                let idx = select(candidates);
+                if idx >= candidates.len() {
+                    return Err(CompletionError::ResponseError(format!(
+                        "Selection strategy chose candidate {idx} of {}",
+                        candidates.len()
+                    )));
+                }
+                Ok(idx)
+            }
+        }
+    }
+}
+
+impl fmt::Debug for SelectionStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SelectionStrategy::First => write!(f, "First"),
+            SelectionStrategy::Longest => write!(f, "Longest"),
+            SelectionStrategy::Judge(_) => write!(f, "Judge"),
+            SelectionStrategy::Custom(_) => write!(f, "Custom"),
+        }
+    }
+}
+
+impl<M: CompletionModel> Agent<M> {
+    /// Sample `n` candidate answers to `prompt` and return the one picked by the agent's
+    /// [`SelectionStrategy`].
+    ///
+    /// Providers that accept `n` produce all candidates in one call; others get `n`
+    /// parallel calls. The outcome's `usage` covers every candidate (judge calls are not
+    /// counted). Best-of cannot be combined with tool calling, so an agent with tools
+    /// answers with a single regular run instead.
+    pub async fn prompt_best_of(
+        &self,
+        prompt: impl Into<Message> + Send,
+        n: usize,
+    ) -> Result<PromptOutcome, CompletionError> {
+        let prompt = prompt.into();
+        if !self.tools.is_empty() {
+            tracing::debug!("agent has tools configured; best-of sampling falls back to n=1");
+            return self.run_with_history_detailed(vec![prompt]).await;
+        }
+        let prompt_text = user_text(&prompt);
+
+        let mut request = CompletionRequest {
+            preamble: self.run_preamble().await,
+            chat_history: vec![prompt],
+            temperature: self.temperature,
+            top_p: self.top_p,
+            max_tokens: self.max_tokens,
+            seed: self.seed,
+            n: Some(n.max(1)),
+            ..Default::default()
+        };
+        enforce_capabilities(
+            &self.model.capabilities(),
+            self.capability_policy,
+            &mut request,
+        )?;
+        if let Some(guardrails) = &self.guardrails {
+            guardrails.filter_request(&mut request)?;
+        }
+
+        if let Some(budget) = &self.budget {
+            budget.reserve_calls(n.max(1) as u64)?;
+        }
+        let sampled = match self.request_timeout {
+            Some(limit) => tokio::time::timeout(limit, self.model.completion_candidates(request))
+                .await
+                .map_err(|_| CompletionError::Timeout(limit))?,
+            None => self.model.completion_candidates(request).await,
+        }?;
+        if let Some(budget) = &self.budget {
+            budget.record(sampled.usage);
+        }
+
+        let mut texts = Vec::new();
+        let mut reasoning = Vec::new();
+        let mut refusal = None;
+        for content in &sampled.candidates {
+            // A refused candidate is skipped; the run fails only if every one was refused.
+            if let Err(refused) = check_refusal(content) {
+                refusal.get_or_insert(refused);
+                continue;
+            }
+            if content
+                .iter()
+                .any(|c| matches!(c, AssistantContent::ToolCall(_)))
+            {
+                return Err(CompletionError::ResponseError(
+                    "Best-of sampling does not support tool calls".into(),
+                ));
+            }
+            let text = response_text(content);
+            if text.is_empty() {
+                continue;
+            }
+            texts.push(text);
+            reasoning.push(
+                content
+                    .iter()
+                    .filter_map(|c| match c {
+                        AssistantContent::Reasoning(block) => {
+                            Some(block.display_text().to_string())
+                        }
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>(),
+            );
+        }
+        if texts.is_empty() {
+            if let Some(refused) = refusal {
+                return Err(refused);
+            }
+            return Err(CompletionError::ResponseError(
+                "Model returned no text candidates".into(),
+            ));
+        }
+
+        let chosen = self
+            .selection
+            .select(&prompt_text, &texts, self.response_style.as_deref())
+            .await?;
+        let text = texts.swap_remove(chosen);
+        let reasoning = reasoning.swap_remove(chosen);
+        let text = match &self.guardrails {
+            Some(guardrails) => guardrails.filter_response(text)?,
+            None => text,
+        };
+        let text = if self.include_reasoning {
+            tool_loop::prepend_reasoning(&reasoning, &text)
+        } else {
+            text
+        };
+        Ok(PromptOutcome {
+            text,
+            used_tools: false,
+            steps: 0,
+            reasoning,
+            usage: sampled.usage,
+            suppressed_tool_calls: 0,
+            deadline_reached: false,
+        })
+    }
+}
+
+fn response_text(content: &[AssistantContent]) -> String {
+    content
+        .iter()
+        .filter_map(|c| match c {
+            AssistantContent::Text(t) => Some(t.text.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn user_text(message: &Message) -> String {
+    match message {
+        Message::User { content } | Message::System { content } => content
+            .iter()
+            .filter_map(|c| match c {
+                UserContent::Text(t) => Some(t.text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Message::Assistant { .. } => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::internal::ai::{
+        agent::AgentBuilder,
+        completion::{CompletionResponse, Text, Usage},
+        tools::{Tool, ToolCallError, ToolDefinition, ToolSet},
+    };
+
+    const CANDIDATES: [&str; 3] = ["short", "the longest candidate", "medium one"];
+
+    /// Returns the candidates in turn and reports 10 input and 5 output tokens per call.
+    #[derive(Clone, Default)]
+    struct RotatingModel {
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl CompletionModel for RotatingModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            request: CompletionRequest,
+        ) -> Result<CompletionResponse<Self::Response>, CompletionError> {
+            assert_eq!(request.n, None, "fallback must send single-candidate calls");
+            let idx = self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(CompletionResponse {
+                content: vec![AssistantContent::Text(Text {
+                    text: CANDIDATES[idx % CANDIDATES.len()].to_string(),
+                })],
+                raw_response: (),
+            })
+        }
+
+        fn usage(&self, _response: &CompletionResponse<Self::Response>) -> Option<Usage> {
+            Some(Usage {
+                input_tokens: 10,
+                output_tokens: 5,
+            })
+        }
+    }
+
+    /// Judge that prefers the answer mentioning "medium".
+    #[derive(Clone)]
+    struct MediumJudge;
+
+    impl CompletionModel for MediumJudge {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            request: CompletionRequest,
+        ) -> Result<CompletionResponse<Self::Response>, CompletionError> {
+            let Message::User { content } = &request.chat_history[0] else {
+                panic!("judge prompt must be a user message");
+            };
+            let UserContent::Text(prompt) = content.iter().next().unwrap() else {
+                panic!("judge prompt must be text");
+            };
+            let score = if prompt.text.contains("Answer:\nmedium") {
+                "Score: 9"
+            } else {
+                "2"
+            };
+            Ok(CompletionResponse {
+                content: vec![AssistantContent::Text(Text {
+                    text: score.to_string(),
+                })],
+                raw_response: (),
+            })
+        }
+    }
+
+    async fn best_of(strategy: SelectionStrategy) -> PromptOutcome {
+        AgentBuilder::new(RotatingModel::default())
+            .selection_strategy(strategy)
+            .build()
+            .prompt_best_of("write a commit message", 3)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_selection_strategies() {
+        assert_eq!(best_of(SelectionStrategy::First).await.text, "short");
+        assert_eq!(
+            best_of(SelectionStrategy::Longest).await.text,
+            "the longest candidate"
+        );
+        assert_eq!(
+            best_of(SelectionStrategy::judge(MediumJudge)).await.text,
+            "medium one"
+        );
+        assert_eq!(
+            best_of(SelectionStrategy::custom(|candidates| candidates.len() - 1))
+                .await
+                .text,
+            "medium one"
+        );
+
+        let err = AgentBuilder::new(RotatingModel::default())
+            .selection_strategy(SelectionStrategy::custom(|_| 7))
+            .build()
+            .prompt_best_of("write a commit message", 3)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("candidate 7 of 3"));
+    }
+
+    /// Judge that records the preamble of each request and rates every candidate alike.
+    #[derive(Clone, Default)]
+    struct RecordingJudge(Arc<std::sync::Mutex<Vec<Option<String>>>>);
+
+    impl CompletionModel for RecordingJudge {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            request: CompletionRequest,
+        ) -> Result<CompletionResponse<Self::Response>, CompletionError> {
+            self.0.lock().unwrap().push(request.preamble);
+            Ok(CompletionResponse {
+                content: vec![AssistantContent::Text(Text {
+                    text: "5".to_string(),
+                })],
+                raw_response: (),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_judge_follows_response_style() {
+        let judge = RecordingJudge::default();
+        let preambles = Arc::clone(&judge.0);
+        let outcome = AgentBuilder::new(RotatingModel::default())
+            .response_style("Always respond in Japanese.")
+            .selection_strategy(SelectionStrategy::judge(judge))
+            .build()
+            .prompt_best_of("write a commit message", 3)
+            .await
+            .unwrap();
+
+        assert_eq!(outcome.text, "short");
+        let preambles = preambles.lock().unwrap();
+        assert_eq!(preambles.len(), 3);
+        let expected = format!("{JUDGE_PREAMBLE}\n\nAlways respond in Japanese.");
+        assert!(
+            preambles
+                .iter()
+                .all(|preamble| preamble.as_deref() == Some(expected.as_str())),
+            "{preambles:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_usage_accumulates_over_candidates() {
+        let model = RotatingModel::default();
+        let calls = Arc::clone(&model.calls);
+        let outcome = AgentBuilder::new(model)
+            .build()
+            .prompt_best_of("write a commit message", 3)
+            .await
+            .unwrap();
+
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert_eq!(
+            outcome.usage,
+            Usage {
+                input_tokens: 30,
+                output_tokens: 15,
+            }
+        );
+        assert_eq!(outcome.usage.total_tokens(), 45);
+    }
+
+    #[tokio::test]
+    async fn test_tools_fall_back_to_single_run() {
+        struct NoopTool;
+
+        impl Tool for NoopTool {
+            fn name(&self) -> String {
+                "noop".to_string()
+            }
+
+            fn description(&self) -> String {
+                "Does nothing".to_string()
+            }
+
+            fn definition(&self) -> ToolDefinition {
+                ToolDefinition {
+                    name: self.name(),
+                    description: self.description(),
+                    parameters: serde_json::json!({"type": "object", "properties": {}}),
+                }
+            }
+
+            fn call(&self, _args: serde_json::Value) -> Result<serde_json::Value, ToolCallError> {
+                Ok(serde_json::Value::Null)
+            }
+        }
+
+        let model = RotatingModel::default();
+        let calls = Arc::clone(&model.calls);
+        let tools = ToolSet::default();
+        tools.add(Arc::new(NoopTool));
+        let outcome = AgentBuilder::new(model)
+            .tools(tools)
+            .selection_strategy(SelectionStrategy::Longest)
+            .build()
+            .prompt_best_of("write a commit message", 3)
+            .await
+            .unwrap();
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(outcome.text, "short");
+        assert_eq!(outcome.usage.total_tokens(), 15);
+    }
+}
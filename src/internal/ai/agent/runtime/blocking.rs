@@ -0,0 +1,92 @@
+//! Synchronous entry points for callers outside an async context.
+//!
+//! Enabled with the `blocking` feature. Calls are driven on a process-wide runtime that is
+//! created on first use and reused afterwards. Calling them from inside a Tokio runtime
+//! would block one of its workers, so that is rejected with an error instead.
+
+use std::sync::OnceLock;
+
+use tokio::runtime::{Builder, Handle, Runtime};
+
+use super::Agent;
+use crate::internal::ai::completion::{CompletionError, CompletionModel, Message, Prompt};
+
+static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+
+/// The shared runtime, built on first use.
+fn runtime() -> Result<&'static Runtime, CompletionError> {
+    if let Some(runtime) = RUNTIME.get() {
+        return Ok(runtime);
+    }
+    let runtime = Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| CompletionError::RequestError(Box::new(e)))?;
+    // Another thread may have won the race; its runtime is used and ours dropped.
+    Ok(RUNTIME.get_or_init(|| runtime))
+}
+
+impl<M: CompletionModel> Agent<M> {
+    /// Blocking version of [`Prompt::prompt`].
+    ///
+    /// Fails with [`CompletionError::RequestError`] when called from within an async
+    /// runtime; use `agent.prompt(..).await` there instead.
+    pub fn prompt_blocking(
+        &self,
+        prompt: impl Into<Message> + Send,
+    ) -> Result<String, CompletionError> {
+        if Handle::try_current().is_ok() {
+            return Err(CompletionError::RequestError(
+                "prompt_blocking cannot be called from within an async runtime; \
+                 await Agent::prompt instead"
+                    .into(),
+            ));
+        }
+        runtime()?.block_on(self.prompt(prompt))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::internal::ai::{
+        agent::AgentBuilder,
+        completion::{
+            CompletionError, CompletionModel, CompletionRequest, CompletionResponse,
+            message::{AssistantContent, Text},
+        },
+    };
+
+    #[derive(Clone)]
+    struct EchoModel;
+
+    impl CompletionModel for EchoModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            _request: CompletionRequest,
+        ) -> Result<CompletionResponse<Self::Response>, CompletionError> {
+            Ok(CompletionResponse {
+                content: vec![AssistantContent::Text(Text {
+                    text: "pong".to_string(),
+                })],
+                raw_response: (),
+            })
+        }
+    }
+
+    #[test]
+    fn test_prompt_blocking_outside_runtime() {
+        let agent = AgentBuilder::new(EchoModel).build();
+        assert_eq!(agent.prompt_blocking("ping").unwrap(), "pong");
+        // The runtime is reused for later calls.
+        assert_eq!(agent.prompt_blocking("ping again").unwrap(), "pong");
+    }
+
+    #[tokio::test]
+    async fn test_prompt_blocking_rejects_async_context() {
+        let agent = AgentBuilder::new(EchoModel).build();
+        let err = agent.prompt_blocking("ping").unwrap_err();
+        assert!(err.to_string().contains("within an async runtime"), "{err}");
+    }
+}
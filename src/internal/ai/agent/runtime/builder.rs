@@ -1,17 +1,46 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
-use super::Agent;
+use tokio::sync::Semaphore;
+
+use super::{
+    Agent, ContextMiddleware, InterimTextHandler, MissingToolHandler, SelectionStrategy,
+    ThoughtOnlyAnswer,
+};
 use crate::internal::ai::{
-    completion::CompletionModel,
-    tools::{Tool, ToolRegistry, ToolSet},
+    budget::SharedBudget,
+    clock::{Clock, SharedClock, SystemClock},
+    completion::{CapabilityPolicy, CompletionModel, Guardrails, SamplingParams},
+    history::HistoryManager,
+    tools::{Tool, ToolDefinition, ToolRegistry, ToolSet, ToolWithDefinition},
 };
 
 /// A builder for configuring and creating AI Agent instances.
 pub struct AgentBuilder<M: CompletionModel> {
-    model: M,
+    model: Arc<M>,
     preamble: Option<String>,
+    response_style: Option<String>,
     temperature: Option<f64>,
+    top_p: Option<f64>,
+    max_tokens: Option<u64>,
+    seed: Option<u64>,
     max_steps: Option<usize>,
+    max_tool_calls: Option<usize>,
+    request_timeout: Option<Duration>,
+    best_effort_deadline: Option<Duration>,
+    deadline: Option<Duration>,
+    clock: SharedClock,
+    include_reasoning: bool,
+    reflect: bool,
+    capability_policy: CapabilityPolicy,
+    guardrails: Option<Arc<Guardrails>>,
+    interim_text: Option<InterimTextHandler>,
+    on_missing_tool: Option<MissingToolHandler>,
+    thought_only_answer: ThoughtOnlyAnswer,
+    selection: SelectionStrategy,
+    context_middleware: Vec<Arc<dyn ContextMiddleware>>,
+    budget: Option<SharedBudget>,
+    step_history: Option<Arc<HistoryManager>>,
+    tool_concurrency: HashMap<String, Arc<Semaphore>>,
     tools: ToolSet,
 }
 
@@ -19,20 +48,84 @@ impl<M: CompletionModel> AgentBuilder<M> {
     /// Creates a new AgentBuilder with the specified CompletionModel.
     pub fn new(model: M) -> Self {
         Self {
-            model,
+            model: Arc::new(model),
             preamble: None,
+            response_style: None,
             temperature: None,
+            top_p: None,
+            max_tokens: None,
+            seed: None,
             max_steps: None,
+            max_tool_calls: None,
+            request_timeout: None,
+            best_effort_deadline: None,
+            deadline: None,
+            clock: Arc::new(SystemClock),
+            include_reasoning: false,
+            reflect: false,
+            capability_policy: CapabilityPolicy::default(),
+            guardrails: None,
+            interim_text: None,
+            on_missing_tool: None,
+            thought_only_answer: ThoughtOnlyAnswer::default(),
+            selection: SelectionStrategy::default(),
+            context_middleware: Vec::new(),
+            budget: None,
+            step_history: None,
+            tool_concurrency: HashMap::new(),
             tools: ToolSet::default(),
         }
     }
 
+    /// Creates a builder seeded with the configuration of an existing agent.
+    ///
+    /// The model `Arc` is shared with `agent`, and the tools are the same instances.
+    /// Tool usage statistics start empty for the derived agent.
+    pub fn from_agent(agent: &Agent<M>) -> Self {
+        Self {
+            model: Arc::clone(&agent.model),
+            preamble: agent.preamble.clone(),
+            response_style: agent.response_style.clone(),
+            temperature: agent.temperature,
+            top_p: agent.top_p,
+            max_tokens: agent.max_tokens,
+            seed: agent.seed,
+            max_steps: agent.max_steps,
+            max_tool_calls: agent.max_tool_calls,
+            request_timeout: agent.request_timeout,
+            best_effort_deadline: agent.best_effort_deadline,
+            deadline: agent.deadline,
+            clock: Arc::clone(&agent.clock),
+            include_reasoning: agent.include_reasoning,
+            reflect: agent.reflect,
+            capability_policy: agent.capability_policy,
+            guardrails: agent.guardrails.clone(),
+            interim_text: agent.interim_text.clone(),
+            on_missing_tool: agent.on_missing_tool.clone(),
+            thought_only_answer: agent.thought_only_answer,
+            selection: agent.selection.clone(),
+            context_middleware: agent.context_middleware.clone(),
+            budget: agent.budget.clone(),
+            step_history: agent.step_history.clone(),
+            tool_concurrency: agent.tool_concurrency.clone(),
+            tools: ToolSet::new(agent.tools.tools().iter().cloned()),
+        }
+    }
+
     /// Sets the preamble (system prompt) for the agent.
     pub fn preamble(mut self, preamble: impl Into<String>) -> Self {
         self.preamble = Some(preamble.into());
         self
     }
 
+    /// Sets an instruction on the language and tone of answers, such as "Always respond in
+    /// Japanese.", sent after the preamble. Unlike the preamble, it is also given to the
+    /// best-of judge, so auxiliary output matches the answers.
+    pub fn response_style(mut self, instruction: impl Into<String>) -> Self {
+        self.response_style = Some(instruction.into());
+        self
+    }
+
     /// Sets the maximum number of tool-call steps for tool execution loops.
     /// Defaults to 4 if not set.
     ///
@@ -43,6 +136,189 @@ impl<M: CompletionModel> AgentBuilder<M> {
         self
     }
 
+    /// Caps the total number of tool invocations in one run, across all steps.
+    /// Unlimited by default.
+    ///
+    /// `max_steps` bounds model round-trips, but a single response can request many
+    /// tools. The run fails with an error as soon as the next call would exceed `limit`.
+    pub fn max_tool_calls(mut self, limit: usize) -> Self {
+        self.max_tool_calls = Some(limit);
+        self
+    }
+
+    /// Lets at most `limit` calls of the tool named `name` run at once. Unlimited by default.
+    ///
+    /// The tool calls of one step run concurrently; cap tools that must not overlap with
+    /// themselves, e.g. ones hitting a rate-limited API. A `limit` of 0 counts as 1. The cap
+    /// is shared with clones of the built agent and agents derived from it through
+    /// [`from_agent`](Self::from_agent).
+    pub fn tool_concurrency_limit(mut self, name: impl Into<String>, limit: usize) -> Self {
+        self.tool_concurrency
+            .insert(name.into(), Arc::new(Semaphore::new(limit.max(1))));
+        self
+    }
+
+    /// Sets a deadline for each model completion call.
+    ///
+    /// A call that does not finish in time fails with [`CompletionError::Timeout`], which
+    /// is retryable. Unset by default, so a hung provider connection only ends when the
+    /// provider's HTTP client gives up. When the agent runs inside a DAG node with its own
+    /// timeout, keep this shorter so a single hung request fails before the node does.
+    ///
+    /// [`CompletionError::Timeout`]: crate::internal::ai::completion::CompletionError::Timeout
+    pub fn request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = Some(timeout);
+        self
+    }
+
+    /// Limits the wall-clock time of a whole run, completions and tool calls included, and
+    /// answers with what the run has gathered instead of failing when it runs out.
+    ///
+    /// Once `deadline` has passed, or a completion is cut off by it, the agent stops
+    /// calling tools and sends one last request without tools that tells the model to
+    /// answer now (see [`BEST_EFFORT_PROMPT`]). That request is bounded by
+    /// [`BEST_EFFORT_TIMEOUT`]; its answer is returned with
+    /// [`PromptOutcome::deadline_reached`] set. Only a failure of that last request is an
+    /// error. Unset by default.
+    ///
+    /// [`BEST_EFFORT_PROMPT`]: super::BEST_EFFORT_PROMPT
+    /// [`BEST_EFFORT_TIMEOUT`]: super::BEST_EFFORT_TIMEOUT
+    /// [`PromptOutcome::deadline_reached`]: super::PromptOutcome::deadline_reached
+    pub fn best_effort_deadline(mut self, deadline: Duration) -> Self {
+        self.best_effort_deadline = Some(deadline);
+        self
+    }
+
+    /// Sets an absolute wall-clock ceiling for a whole run, for callers such as CI jobs that
+    /// must not wait past it.
+    ///
+    /// The remaining time is checked before every completion and every round of tool calls,
+    /// and each completion's timeout is shrunk to fit it. When it runs out the run fails
+    /// with [`CompletionError::DeadlineExceeded`], carrying the model's last text or a
+    /// summary of the tool calls that completed. A tool already running is not interrupted,
+    /// so a slow tool can overshoot the deadline by its own duration. With a
+    /// [`best_effort_deadline`](Self::best_effort_deadline) as well, the best-effort
+    /// request is also bounded by this one. Unset by default.
+    ///
+    /// [`CompletionError::DeadlineExceeded`]: crate::internal::ai::completion::CompletionError::DeadlineExceeded
+    pub fn deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Sets the clock [`deadline`](Self::deadline), [`best_effort_deadline`](Self::best_effort_deadline)
+    /// and the completion timeouts derived from them are measured against. Defaults to
+    /// [`SystemClock`]; tests pass a [`ManualClock`] to make a deadline pass at a chosen step.
+    ///
+    /// [`ManualClock`]: crate::internal::ai::clock::ManualClock
+    pub fn clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Prepends the model's reasoning to the returned text. Off by default.
+    ///
+    /// Reasoning is always available through [`PromptOutcome::reasoning`]; this is for
+    /// debugging prompts where seeing it inline is easier.
+    ///
+    /// [`PromptOutcome::reasoning`]: super::PromptOutcome::reasoning
+    pub fn include_reasoning(mut self, include: bool) -> Self {
+        self.include_reasoning = include;
+        self
+    }
+
+    /// Asks the model once, after it has answered, to critique and improve its answer, and
+    /// returns the improved one. Off by default.
+    ///
+    /// The reflection is a single extra completion without tools, sending the conversation,
+    /// the answer and [`REFLECTION_PROMPT`]. A best-effort answer after the deadline is not
+    /// reflected on, and an empty reflection keeps the original answer.
+    ///
+    /// [`REFLECTION_PROMPT`]: super::REFLECTION_PROMPT
+    pub fn reflect(mut self, reflect: bool) -> Self {
+        self.reflect = reflect;
+        self
+    }
+
+    /// Chooses the answer when the model's final response has no text, as reasoning models
+    /// sometimes return only their thoughts. Defaults to [`ThoughtOnlyAnswer::Retry`],
+    /// which asks again; [`ThoughtOnlyAnswer::Empty`] and [`ThoughtOnlyAnswer::Thought`]
+    /// end the run with an empty answer or with the thoughts instead.
+    pub fn thought_only_answer(mut self, answer: ThoughtOnlyAnswer) -> Self {
+        self.thought_only_answer = answer;
+        self
+    }
+
+    /// Chooses what happens when a request needs features the model lacks, as reported by
+    /// [`CompletionModel::capabilities`]. Defaults to [`CapabilityPolicy::Strict`], which
+    /// fails before anything is sent; [`CapabilityPolicy::Degrade`] drops or rewrites the
+    /// unsupported parts with a warning.
+    pub fn capability_policy(mut self, policy: CapabilityPolicy) -> Self {
+        self.capability_policy = policy;
+        self
+    }
+
+    /// Filters every outbound request and the final answer through `guardrails`.
+    ///
+    /// Violations fail the run with [`CompletionError::PolicyViolation`].
+    ///
+    /// [`CompletionError::PolicyViolation`]: crate::internal::ai::completion::CompletionError::PolicyViolation
+    pub fn guardrails(mut self, guardrails: Guardrails) -> Self {
+        self.guardrails = Some(Arc::new(guardrails));
+        self
+    }
+
+    /// Forwards text the model sends alongside tool calls (e.g. "Let me check the
+    /// tests...") to `handler` instead of discarding it.
+    ///
+    /// The text passes the inbound guardrails first. It is not part of the final answer.
+    pub fn on_interim_text(mut self, handler: impl Fn(&str) + Send + Sync + 'static) -> Self {
+        self.interim_text = Some(Arc::new(handler));
+        self
+    }
+
+    /// Builds the error message for a model call to a tool the agent does not have (for
+    /// localization or to add guidance) from the tool name. Defaults to
+    /// `Tool not found: <name>`. An agent without any tools answers the first such call of a
+    /// run with [`NO_TOOLS_NOTICE`](super::NO_TOOLS_NOTICE) instead, and only fails on the next.
+    pub fn on_missing_tool(
+        mut self,
+        handler: impl Fn(&str) -> String + Send + Sync + 'static,
+    ) -> Self {
+        self.on_missing_tool = Some(Arc::new(handler));
+        self
+    }
+
+    /// Prepends the context `middleware` produces to the preamble of every run. Context
+    /// from several middlewares appears in the order they were added.
+    pub fn context_middleware(mut self, middleware: impl ContextMiddleware + 'static) -> Self {
+        self.context_middleware.push(Arc::new(middleware));
+        self
+    }
+
+    /// Draws every completion call from `budget`, which may be shared with other agents
+    /// and tool loops. Calls beyond its limits fail with
+    /// [`CompletionError::BudgetExceeded`](crate::internal::ai::completion::CompletionError::BudgetExceeded).
+    pub fn budget(mut self, budget: SharedBudget) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// Records every completed step of each run (request summary, response and tool
+    /// results) on the branch of `history`; see [`step_history`](super::step_history).
+    /// Off by default.
+    pub fn record_steps(mut self, history: Arc<HistoryManager>) -> Self {
+        self.step_history = Some(history);
+        self
+    }
+
+    /// Chooses how [`Agent::prompt_best_of`] picks the answer among its candidates.
+    /// Defaults to [`SelectionStrategy::First`].
+    pub fn selection_strategy(mut self, strategy: SelectionStrategy) -> Self {
+        self.selection = strategy;
+        self
+    }
+
     /// Sets the tools for the agent.
     pub fn tools(mut self, tools: ToolSet) -> Self {
         self.tools = tools;
@@ -50,8 +326,24 @@ impl<M: CompletionModel> AgentBuilder<M> {
     }
 
     /// Add a single tool to the agent.
-    pub fn tool(mut self, tool: impl Tool + 'static) -> Self {
-        self.tools.tools.push(std::sync::Arc::new(tool));
+    pub fn tool(self, tool: impl Tool + 'static) -> Self {
+        self.tools.add(Arc::new(tool));
+        self
+    }
+
+    /// Add a tool but offer `definition` to the model instead of the tool's own.
+    ///
+    /// Useful to hide internal parameters; calls still reach `tool` by its real name.
+    /// See [`ToolWithDefinition`].
+    pub fn tool_with_definition(
+        self,
+        tool: impl Tool + 'static,
+        definition: ToolDefinition,
+    ) -> Self {
+        self.tools.add(Arc::new(ToolWithDefinition::new(
+            Arc::new(tool),
+            definition,
+        )));
         self
     }
 
@@ -75,13 +367,75 @@ impl<M: CompletionModel> AgentBuilder<M> {
         Ok(self)
     }
 
+    /// Sets the nucleus sampling probability mass (0.0 to 1.0).
+    pub fn top_p(mut self, top_p: f64) -> Result<Self, String> {
+        if !(0.0..=1.0).contains(&top_p) {
+            return Err(format!("top_p must be between 0.0 and 1.0, got {}", top_p));
+        }
+        self.top_p = Some(top_p);
+        Ok(self)
+    }
+
+    /// Sets the maximum number of tokens generated per completion.
+    pub fn max_tokens(mut self, max_tokens: u64) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    /// Sets the sampling seed for providers that support reproducible output.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Sets all sampling parameters at once.
+    ///
+    /// Fields left as `None` keep their current value, and the per-field
+    /// setters called afterwards override anything set here.
+    pub fn sampling(mut self, params: SamplingParams) -> Result<Self, String> {
+        if let Some(temperature) = params.temperature {
+            self = self.temperature(temperature)?;
+        }
+        if let Some(top_p) = params.top_p {
+            self = self.top_p(top_p)?;
+        }
+        if let Some(max_tokens) = params.max_tokens {
+            self.max_tokens = Some(max_tokens);
+        }
+        if let Some(seed) = params.seed {
+            self.seed = Some(seed);
+        }
+        Ok(self)
+    }
+
     /// Builds and returns the configured Agent instance.
     pub fn build(self) -> Agent<M> {
         Agent {
-            model: Arc::new(self.model),
+            model: self.model,
             preamble: self.preamble,
+            response_style: self.response_style,
             temperature: self.temperature,
+            top_p: self.top_p,
+            max_tokens: self.max_tokens,
+            seed: self.seed,
             max_steps: self.max_steps.or(Some(4)),
+            max_tool_calls: self.max_tool_calls,
+            request_timeout: self.request_timeout,
+            best_effort_deadline: self.best_effort_deadline,
+            deadline: self.deadline,
+            clock: self.clock,
+            include_reasoning: self.include_reasoning,
+            reflect: self.reflect,
+            capability_policy: self.capability_policy,
+            guardrails: self.guardrails,
+            interim_text: self.interim_text,
+            on_missing_tool: self.on_missing_tool,
+            thought_only_answer: self.thought_only_answer,
+            selection: self.selection,
+            context_middleware: self.context_middleware,
+            budget: self.budget,
+            step_history: self.step_history,
+            tool_concurrency: self.tool_concurrency,
             tools: self.tools,
         }
     }
@@ -145,6 +499,23 @@ mod tests {
         assert!(builder_high.temperature(2.1).is_err());
     }
 
+    #[test]
+    fn test_agent_builder_sampling_validation() {
+        use crate::internal::ai::completion::SamplingParams;
+
+        let params = SamplingParams {
+            top_p: Some(1.5),
+            ..Default::default()
+        };
+        assert!(AgentBuilder::new(MockModel).sampling(params).is_err());
+
+        let params = SamplingParams {
+            temperature: Some(3.0),
+            ..Default::default()
+        };
+        assert!(AgentBuilder::new(MockModel).sampling(params).is_err());
+    }
+
     // Helper to reuse builder for test simplicity, though real builder consumes self
     impl AgentBuilder<MockModel> {
         fn new_with_temp(self, temp: f64) -> Result<Self, String> {
@@ -158,4 +529,33 @@ mod tests {
         let tools = ToolSet::default();
         let _agent = AgentBuilder::new(model).tools(tools).build();
     }
+
+    #[test]
+    fn test_agent_builder_from_agent() {
+        let base = AgentBuilder::new(MockModel)
+            .preamble("system prompt")
+            .temperature(0.2)
+            .unwrap()
+            .top_p(0.9)
+            .unwrap()
+            .max_tokens(256)
+            .seed(7)
+            .max_steps(2)
+            .build();
+
+        let variant = AgentBuilder::from_agent(&base)
+            .temperature(1.1)
+            .unwrap()
+            .build();
+
+        assert_eq!(variant.temperature, Some(1.1));
+        assert_eq!(base.temperature, Some(0.2));
+        assert!(std::sync::Arc::ptr_eq(&variant.model, &base.model));
+        assert_eq!(variant.preamble.as_deref(), Some("system prompt"));
+        assert_eq!(variant.top_p, Some(0.9));
+        assert_eq!(variant.max_tokens, Some(256));
+        assert_eq!(variant.seed, Some(7));
+        assert_eq!(variant.max_steps, Some(2));
+        assert_eq!(variant.tools.len(), base.tools.len());
+    }
 }
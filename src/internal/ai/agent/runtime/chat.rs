@@ -1,5 +1,11 @@
+use std::sync::Arc;
+
 use super::Agent;
-use crate::internal::ai::completion::{CompletionError, CompletionModel, Message};
+use crate::internal::ai::{
+    completion::{CompletionError, CompletionModel, Message},
+    memory::{MemoryStore, format_memories},
+    tools::{Tool, ToolStats},
+};
 
 /// A stateful agent that maintains conversation history.
 ///
@@ -25,6 +31,8 @@ pub struct ChatAgent<M: CompletionModel> {
     agent: Agent<M>,
     /// The history of the conversation.
     history: Vec<Message>,
+    /// Store to pull relevant memories from, and how many to inject per request.
+    memory: Option<(Arc<MemoryStore>, usize)>,
 }
 
 impl<M: CompletionModel> ChatAgent<M> {
@@ -36,9 +44,19 @@ impl<M: CompletionModel> ChatAgent<M> {
         Self {
             agent,
             history: Vec::new(),
+            memory: None,
         }
     }
 
+    /// Inject up to `top_n` memories relevant to each prompt into the preamble.
+    ///
+    /// Memories are looked up with [`MemoryStore::recall`] using the user's message as the
+    /// query and appended after the agent's own preamble for that request only.
+    pub fn with_memory(mut self, store: Arc<MemoryStore>, top_n: usize) -> Self {
+        self.memory = Some((store, top_n));
+        self
+    }
+
     /// Sends a message to the agent and gets a response, updating the history.
     ///
     /// This method:
@@ -52,14 +70,16 @@ impl<M: CompletionModel> ChatAgent<M> {
         &mut self,
         prompt: impl Into<String> + Send,
     ) -> Result<String, CompletionError> {
-        let user_msg = Message::user(prompt.into());
+        let prompt = prompt.into();
+        let user_msg = Message::user(prompt.clone());
 
         // Update history with user message first
         self.history.push(user_msg);
+        let agent = self.agent_for_prompt(&prompt);
 
         // Run the agent with the current history.
         // We must clone the history because the agent takes ownership of the context for the request.
-        let response = self.agent.run_with_history(self.history.clone()).await?;
+        let response = agent.run_with_history(self.history.clone()).await?;
 
         // Update history with assistant response
         self.history.push(Message::assistant(response.clone()));
@@ -67,6 +87,31 @@ impl<M: CompletionModel> ChatAgent<M> {
         Ok(response)
     }
 
+    /// The agent to use for `prompt`: the base agent, with relevant memories appended to
+    /// its preamble when memory injection is enabled.
+    fn agent_for_prompt(&self, prompt: &str) -> std::borrow::Cow<'_, Agent<M>> {
+        let Some((store, top_n)) = &self.memory else {
+            return std::borrow::Cow::Borrowed(&self.agent);
+        };
+        let memories = match store.recall(prompt, *top_n) {
+            Ok(memories) => memories,
+            Err(e) => {
+                tracing::warn!(path = %store.path().display(), error = %e, "failed to read memories");
+                return std::borrow::Cow::Borrowed(&self.agent);
+            }
+        };
+        let Some(section) = format_memories(&memories) else {
+            return std::borrow::Cow::Borrowed(&self.agent);
+        };
+
+        let mut agent = self.agent.clone();
+        agent.preamble = Some(match agent.preamble.take() {
+            Some(preamble) => format!("{preamble}\n\n{section}"),
+            None => section,
+        });
+        std::borrow::Cow::Owned(agent)
+    }
+
     /// Returns a reference to the current conversation history.
     ///
     /// Note: The history grows with each turn. For long-running conversations,
@@ -83,10 +128,38 @@ impl<M: CompletionModel> ChatAgent<M> {
         self.history.clear();
     }
 
+    /// Offer `tool` to the model from the next request on.
+    ///
+    /// The tool set is shared with every clone of the agent (see [`ChatAgent::clone_agent`]),
+    /// so they gain the tool too.
+    pub fn add_tool(&self, tool: impl Tool + 'static) {
+        self.agent.tools.add(Arc::new(tool));
+    }
+
+    /// Stop offering the tool called `name`; returns whether it was present. A step already
+    /// in progress can still finish calling it.
+    pub fn remove_tool(&self, name: &str) -> bool {
+        self.agent.tools.remove(name)
+    }
+
+    /// Per-tool usage accumulated over this chat session.
+    ///
+    /// Agents obtained through [`ChatAgent::clone_agent`] record into the same counters.
+    pub fn tool_stats(&self) -> &ToolStats {
+        self.agent.tool_stats()
+    }
+
+    /// Formatted per-tool usage report for this chat session.
+    pub fn tool_stats_report(&self) -> String {
+        self.agent.tool_stats().report()
+    }
+
     /// Clone the inner agent for background execution.
     ///
     /// This is useful when you need to execute the agent in a separate task
-    /// while still being able to update the history afterwards.
+    /// while still being able to update the history afterwards. The clone shares this
+    /// session's tool set; give it a [`ToolSet::snapshot`](crate::internal::ai::tools::ToolSet::snapshot)
+    /// through [`AgentBuilder::tools`](super::AgentBuilder::tools) to isolate it.
     pub fn clone_agent(&self) -> Agent<M> {
         self.agent.clone()
     }
@@ -161,4 +234,265 @@ mod tests {
         chat_agent.clear_history();
         assert!(chat_agent.history().is_empty());
     }
+
+    #[tokio::test]
+    async fn test_tool_stats_accumulate_across_session() {
+        use serde_json::json;
+
+        use crate::internal::ai::{
+            completion::message::{Function, ToolCall},
+            tools::{Tool, ToolCallError, ToolDefinition, ToolSet},
+        };
+
+        /// Calls `echo_tool` whenever the last message is a user prompt.
+        #[derive(Clone)]
+        struct ToolCallingModel;
+
+        impl CompletionModel for ToolCallingModel {
+            type Response = ();
+
+            async fn completion(
+                &self,
+                request: CompletionRequest,
+            ) -> Result<CompletionResponse<()>, CompletionError> {
+                let content = match request.chat_history.last() {
+                    Some(Message::User {
+                        content: OneOrMany::One(UserContent::Text(_)),
+                    }) => AssistantContent::ToolCall(ToolCall {
+                        id: "call_1".to_string(),
+                        name: "echo_tool".to_string(),
+                        function: Function {
+                            name: "echo_tool".to_string(),
+                            arguments: json!({}),
+                        },
+                    }),
+                    _ => AssistantContent::Text(Text {
+                        text: "done".to_string(),
+                    }),
+                };
+                Ok(CompletionResponse {
+                    content: vec![content],
+                    raw_response: (),
+                })
+            }
+        }
+
+        struct EchoTool;
+
+        impl Tool for EchoTool {
+            fn definition(&self) -> ToolDefinition {
+                ToolDefinition {
+                    name: "echo_tool".to_string(),
+                    description: "Echo".to_string(),
+                    parameters: json!({"type": "object", "properties": {}}),
+                }
+            }
+
+            fn call(&self, _args: serde_json::Value) -> Result<serde_json::Value, ToolCallError> {
+                Ok(json!("echo"))
+            }
+        }
+
+        let tools = ToolSet::default();
+        tools.add(std::sync::Arc::new(EchoTool));
+        let agent = crate::internal::ai::AgentBuilder::new(ToolCallingModel)
+            .tools(tools)
+            .build();
+        let mut chat_agent = ChatAgent::new(agent);
+
+        chat_agent.chat("one").await.unwrap();
+        chat_agent.chat("two").await.unwrap();
+
+        let usage = chat_agent.tool_stats().get("echo_tool").unwrap();
+        assert_eq!(usage.calls, 2);
+        assert_eq!(usage.errors, 0);
+        assert_eq!(usage.result_bytes, 2 * "\"echo\"".len() as u64);
+        assert!(chat_agent.tool_stats_report().contains("echo_tool"));
+    }
+
+    #[tokio::test]
+    async fn test_memories_from_earlier_run_are_injected() {
+        use serde_json::json;
+        use tempfile::TempDir;
+
+        use crate::internal::ai::{
+            agent::runtime::tool_loop::{ToolLoopConfig, run_tool_loop},
+            completion::message::{Function, ToolCall},
+            tools::{ToolRegistry, handlers::RememberHandler},
+        };
+
+        /// Saves a memory through the `remember` tool, then finishes.
+        #[derive(Clone)]
+        struct RememberingModel;
+
+        impl CompletionModel for RememberingModel {
+            type Response = ();
+
+            async fn completion(
+                &self,
+                request: CompletionRequest,
+            ) -> Result<CompletionResponse<()>, CompletionError> {
+                let content = match request.chat_history.last() {
+                    Some(Message::User {
+                        content: OneOrMany::One(UserContent::Text(_)),
+                    }) => AssistantContent::ToolCall(ToolCall {
+                        id: "call_1".to_string(),
+                        name: "remember".to_string(),
+                        function: Function {
+                            name: "remember".to_string(),
+                            arguments: json!({
+                                "key": "build command",
+                                "value": "cargo nextest run",
+                            }),
+                        },
+                    }),
+                    _ => AssistantContent::Text(Text {
+                        text: "noted".to_string(),
+                    }),
+                };
+                Ok(CompletionResponse {
+                    content: vec![content],
+                    raw_response: (),
+                })
+            }
+        }
+
+        /// Replies with the preamble it was given.
+        #[derive(Clone)]
+        struct PreambleEchoModel;
+
+        impl CompletionModel for PreambleEchoModel {
+            type Response = ();
+
+            async fn completion(
+                &self,
+                request: CompletionRequest,
+            ) -> Result<CompletionResponse<()>, CompletionError> {
+                Ok(CompletionResponse {
+                    content: vec![AssistantContent::Text(Text {
+                        text: request.preamble.unwrap_or_default(),
+                    })],
+                    raw_response: (),
+                })
+            }
+        }
+
+        let temp_dir = TempDir::new().unwrap();
+
+        // First run: the model stores a memory through the tool.
+        let mut registry = ToolRegistry::with_working_dir(temp_dir.path().to_path_buf());
+        registry.register(
+            "remember",
+            Arc::new(RememberHandler::new(Arc::new(MemoryStore::new(
+                temp_dir.path(),
+            )))),
+        );
+        let text = run_tool_loop(
+            &RememberingModel,
+            "our build command is cargo nextest run",
+            &registry,
+            ToolLoopConfig::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(text, "noted");
+
+        // Later run: a fresh agent and store see the memory in the preamble.
+        let store = Arc::new(MemoryStore::new(temp_dir.path()));
+        let agent = super::super::AgentBuilder::new(PreambleEchoModel)
+            .preamble("You are helpful.")
+            .build();
+        let mut chat_agent = ChatAgent::new(agent).with_memory(store, 3);
+
+        let preamble = chat_agent.chat("how do I run the build?").await.unwrap();
+        assert!(preamble.starts_with("You are helpful.\n\n"));
+        assert!(
+            preamble.contains("- build command: cargo nextest run"),
+            "{preamble}"
+        );
+
+        // Unrelated prompts get the plain preamble.
+        let preamble = chat_agent.chat("hello").await.unwrap();
+        assert_eq!(preamble, "You are helpful.");
+    }
+
+    /// A tool named `name` that answers `"ok"`.
+    struct NamedTool(&'static str);
+
+    impl Tool for NamedTool {
+        fn definition(&self) -> crate::internal::ai::tools::ToolDefinition {
+            crate::internal::ai::tools::ToolDefinition {
+                name: self.0.to_string(),
+                description: "test tool".to_string(),
+                parameters: serde_json::json!({"type": "object", "properties": {}}),
+            }
+        }
+
+        fn call(
+            &self,
+            _args: serde_json::Value,
+        ) -> Result<serde_json::Value, crate::internal::ai::tools::ToolCallError> {
+            Ok(serde_json::json!("ok"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tool_added_between_turns_is_advertised() {
+        use std::sync::Mutex;
+
+        /// Records the tool names offered in each request.
+        #[derive(Clone, Default)]
+        struct RecordingModel {
+            offered: Arc<Mutex<Vec<Vec<String>>>>,
+        }
+
+        impl CompletionModel for RecordingModel {
+            type Response = ();
+
+            async fn completion(
+                &self,
+                request: CompletionRequest,
+            ) -> Result<CompletionResponse<()>, CompletionError> {
+                let names = request.tools.iter().map(|t| t.name.clone()).collect();
+                self.offered.lock().unwrap().push(names);
+                Ok(CompletionResponse {
+                    content: vec![AssistantContent::Text(Text {
+                        text: "done".to_string(),
+                    })],
+                    raw_response: (),
+                })
+            }
+        }
+
+        let model = RecordingModel::default();
+        let offered = model.offered.clone();
+        let mut chat_agent = ChatAgent::new(Agent::new(model));
+
+        chat_agent.chat("one").await.unwrap();
+        chat_agent.add_tool(NamedTool("lookup"));
+        chat_agent.chat("two").await.unwrap();
+        assert!(chat_agent.remove_tool("lookup"));
+        assert!(!chat_agent.remove_tool("lookup"));
+        chat_agent.chat("three").await.unwrap();
+
+        let offered = offered.lock().unwrap();
+        assert_eq!(
+            *offered,
+            vec![vec![], vec!["lookup".to_string()], Vec::<String>::new()]
+        );
+    }
+
+    #[test]
+    fn test_cloned_agents_share_tool_set() {
+        let chat_agent = ChatAgent::new(Agent::new(MockModel));
+        let clone = chat_agent.clone_agent();
+
+        chat_agent.add_tool(NamedTool("lookup"));
+        assert_eq!(clone.tool_set().len(), 1);
+
+        let snapshot = clone.tool_set().snapshot();
+        chat_agent.remove_tool("lookup");
+        assert!(clone.tool_set().is_empty());
+        assert_eq!(snapshot.len(), 1);
+    }
 }
@@ -0,0 +1,15 @@
+//! Per-run context injected ahead of the agent's preamble.
+
+use async_trait::async_trait;
+
+/// Supplies context for the requests of an [`Agent`](super::Agent) run; see
+/// [`AgentBuilder::context_middleware`](super::AgentBuilder::context_middleware).
+///
+/// [`ContextMiddleware::context`] is called once at the start of every run and the result
+/// is reused for each step of that run, so the context is fresh per run without being
+/// regathered between tool calls.
+#[async_trait]
+pub trait ContextMiddleware: Send + Sync {
+    /// The block to prepend to the preamble, or `None` to add nothing to this run.
+    async fn context(&self) -> Option<String>;
+}
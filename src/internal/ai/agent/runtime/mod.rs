@@ -1,23 +1,136 @@
-use std::sync::Arc;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use futures::StreamExt;
+use thiserror::Error;
+use tokio::sync::Semaphore;
 
 use crate::internal::ai::{
+    budget::SharedBudget,
+    clock::{SharedClock, SystemClock},
     completion::{
-        Chat, CompletionError, CompletionModel, CompletionRequest, Message, Prompt,
+        CapabilityPolicy, Chat, CompletionError, CompletionModel, CompletionRequest, Guardrails,
+        Message, Prompt, Usage, check_refusal, completion_with_timeout, enforce_capabilities,
         message::{AssistantContent, OneOrMany, ToolResult, UserContent},
+        partial_result,
     },
-    tools::{ToolDefinition, ToolSet},
+    history::HistoryManager,
+    tools::{Tool, ToolCallError, ToolDefinition, ToolSet, ToolStats},
 };
 
+pub mod best_of;
+#[cfg(feature = "blocking")]
+pub mod blocking;
 pub mod builder;
+pub mod middleware;
+pub mod step_history;
 pub mod tool_loop;
+pub use best_of::{CandidateJudge, ModelJudge, SelectionStrategy};
 pub use builder::AgentBuilder;
+pub use middleware::ContextMiddleware;
+use step_history::{AgentStepRecord, RecordedToolCall, RequestSummary};
 pub use tool_loop::{
     ToolLoopConfig, ToolLoopObserver, run_tool_loop, run_tool_loop_with_history_and_observer,
+    run_tool_loop_with_observer,
 };
 
 pub mod chat;
 pub use chat::ChatAgent;
 
+/// Result of [`Agent::prompt_detailed`]: the final text plus how it was produced.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PromptOutcome {
+    /// Final text response of the model.
+    pub text: String,
+    /// Whether the model called at least one tool before answering.
+    pub used_tools: bool,
+    /// Number of tool-calling rounds (model responses that requested tools).
+    pub steps: usize,
+    /// Reasoning the model emitted along the way, in order.
+    pub reasoning: Vec<String>,
+    /// Tokens used by every completion call of the run, as far as the provider reports them.
+    pub usage: Usage,
+    /// Tool calls that repeated the id of a call already executed in this run (e.g. a
+    /// replayed response after a retry) and were answered from the earlier result instead.
+    pub suppressed_tool_calls: usize,
+    /// Whether the run hit [`AgentBuilder::best_effort_deadline`] and `text` is the model's
+    /// best-effort answer.
+    pub deadline_reached: bool,
+}
+
+/// Sent as a final user message when a run reaches its
+/// [`AgentBuilder::best_effort_deadline`].
+pub const BEST_EFFORT_PROMPT: &str = "Time is up. Do not call any more tools. Answer now with \
+     the information gathered so far, and say briefly what is still unverified or missing.";
+
+/// Sent after the answer of an agent built with [`AgentBuilder::reflect`], asking for the
+/// improved answer.
+pub const REFLECTION_PROMPT: &str = "Critique your answer above: check it for mistakes, \
+     omissions and unclear wording. Then reply with the improved answer only, without the \
+     critique.";
+
+/// Answered, as a refused tool call, to the first tool call of a run whose agent has no tools
+/// at all; a second such call fails the run like any call to an unknown tool.
+pub const NO_TOOLS_NOTICE: &str = "no tools are available; answer directly without calling tools";
+
+/// Bound on the best-effort completion sent after the deadline, so a run overshoots its
+/// deadline by at most this much.
+pub const BEST_EFFORT_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Consecutive reasoning-only rounds [`ThoughtOnlyAnswer::Retry`] allows when the agent has
+/// no `max_steps`, so a model that never answers cannot keep the run going.
+pub const MAX_REASONING_ONLY_ROUNDS: usize = 8;
+
+/// What an [`Agent`] answers when the model's final response has no text, typically
+/// because a reasoning model returned only its thoughts; see
+/// [`AgentBuilder::thought_only_answer`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ThoughtOnlyAnswer {
+    /// Ask again while the model only reasons, up to `max_steps` rounds in a row (or
+    /// [`MAX_REASONING_ONLY_ROUNDS`] without `max_steps`); fail on other content without text.
+    #[default]
+    Retry,
+    /// Answer with an empty string.
+    Empty,
+    /// Answer with the reasoning of that response.
+    Thought,
+}
+
+/// A misconfiguration reported by [`Agent::validate`].
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum ConfigIssue {
+    /// The preamble mentions tools but the agent has none to call.
+    #[error("preamble refers to tools but the agent has no tools")]
+    PreambleNeedsTools,
+    #[error("temperature {0} is outside 0.0..=2.0")]
+    TemperatureOutOfRange(f64),
+    #[error("top_p {0} is outside 0.0..=1.0")]
+    TopPOutOfRange(f64),
+    /// Several tools share a name; calls only ever reach the first of them.
+    #[error("tool '{0}' is registered more than once")]
+    DuplicateTool(String),
+}
+
+/// How one tool call of a step is answered.
+enum PlannedCall {
+    /// Run the tool.
+    Run(Arc<dyn Tool>),
+    /// Answer with the result already recorded for this id: an earlier call with the same id,
+    /// or a refusal decided while planning.
+    Replay,
+}
+
+/// Receives text a model returned together with tool calls; see
+/// [`AgentBuilder::on_interim_text`].
+pub type InterimTextHandler = Arc<dyn Fn(&str) + Send + Sync>;
+
+/// Builds the error message for a call to a tool the agent does not have, from the tool
+/// name; see [`AgentBuilder::on_missing_tool`].
+pub type MissingToolHandler = Arc<dyn Fn(&str) -> String + Send + Sync>;
+
 /// An AI Agent that manages interactions with a CompletionModel.
 ///
 /// This is a **stateless** agent (also known as a Simple Agent). It handles configuration
@@ -38,10 +151,54 @@ pub struct Agent<M: CompletionModel> {
     model: Arc<M>,
     /// System prompt or preamble to set the agent's behavior context.
     preamble: Option<String>,
+    /// Instruction on the language and tone of answers, sent after the preamble and to the
+    /// best-of judge.
+    response_style: Option<String>,
     /// Sampling temperature (0.0 to 2.0). Higher values mean more creativity.
     temperature: Option<f64>,
+    /// Nucleus sampling probability mass (0.0 to 1.0).
+    top_p: Option<f64>,
+    /// Upper bound on the number of tokens generated per completion.
+    max_tokens: Option<u64>,
+    /// Seed for providers that support reproducible sampling.
+    seed: Option<u64>,
     /// Maximum number of steps for tool execution loops. `None` means unlimited (though currently enforced to 4 by default).
     max_steps: Option<usize>,
+    /// Cap on tool invocations across all steps of one run. `None` means unlimited.
+    max_tool_calls: Option<usize>,
+    /// Deadline for each model completion call. `None` waits indefinitely.
+    request_timeout: Option<Duration>,
+    /// Wall-clock limit for a whole run, after which the agent asks for a best-effort answer.
+    best_effort_deadline: Option<Duration>,
+    /// Wall-clock limit for a whole run, after which it fails with its partial result.
+    deadline: Option<Duration>,
+    /// Time source for the deadlines and the completion timeouts derived from them.
+    clock: SharedClock,
+    /// Prepend the model's reasoning to the returned text (debugging aid).
+    include_reasoning: bool,
+    /// Ask the model once to critique and improve its final answer.
+    reflect: bool,
+    /// How to handle requests that use features the model does not support.
+    capability_policy: CapabilityPolicy,
+    /// Content filters applied to outbound requests and the final answer.
+    guardrails: Option<Arc<Guardrails>>,
+    /// Receives text sent alongside tool calls. `None` discards it.
+    interim_text: Option<InterimTextHandler>,
+    /// Message for calls to unknown tools. `None` uses `Tool not found: <name>`.
+    on_missing_tool: Option<MissingToolHandler>,
+    /// How a final response without text is answered.
+    thought_only_answer: ThoughtOnlyAnswer,
+    /// How [`Agent::prompt_best_of`] picks among candidates.
+    selection: SelectionStrategy,
+    /// Context sources whose output precedes the preamble, in order.
+    context_middleware: Vec<Arc<dyn ContextMiddleware>>,
+    /// Limits shared with other agents and tool loops. `None` is unlimited.
+    budget: Option<SharedBudget>,
+    /// Branch every completed step is recorded to. `None` records nothing.
+    step_history: Option<Arc<HistoryManager>>,
+    /// Caps on concurrent calls per tool name, shared with clones. Tools without an entry
+    /// are unlimited.
+    tool_concurrency: HashMap<String, Arc<Semaphore>>,
     /// Set of tools available to the agent.
     tools: ToolSet,
 }
@@ -55,59 +212,416 @@ impl<M: CompletionModel> Agent<M> {
         Self {
             model: Arc::new(model),
             preamble: None,
+            response_style: None,
             temperature: None,
+            top_p: None,
+            max_tokens: None,
+            seed: None,
             max_steps: Some(4),
+            max_tool_calls: None,
+            request_timeout: None,
+            best_effort_deadline: None,
+            deadline: None,
+            clock: Arc::new(SystemClock),
+            include_reasoning: false,
+            reflect: false,
+            capability_policy: CapabilityPolicy::default(),
+            guardrails: None,
+            interim_text: None,
+            on_missing_tool: None,
+            thought_only_answer: ThoughtOnlyAnswer::default(),
+            selection: SelectionStrategy::default(),
+            context_middleware: Vec::new(),
+            budget: None,
+            step_history: None,
+            tool_concurrency: HashMap::new(),
             tools: ToolSet::default(),
         }
     }
 
+    /// Per-tool usage recorded by this agent's tool loop.
+    ///
+    /// Clones of the agent share the same counters, so usage accumulates across
+    /// every run of the agent and its clones.
+    pub fn tool_stats(&self) -> &ToolStats {
+        &self.tools.stats
+    }
+
+    /// The budget this agent draws from, if any; see [`AgentBuilder::budget`].
+    pub fn budget(&self) -> Option<&SharedBudget> {
+        self.budget.as_ref()
+    }
+
+    /// The overall deadline of a run, if any; see [`AgentBuilder::deadline`].
+    pub fn deadline(&self) -> Option<Duration> {
+        self.deadline
+    }
+
+    /// Replaces the budget while keeping the tools (and their statistics) shared.
+    pub(crate) fn with_budget(mut self, budget: SharedBudget) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// The agent's tools. Clones of the agent share this set; see [`ToolSet`].
+    pub fn tool_set(&self) -> &ToolSet {
+        &self.tools
+    }
+
+    /// How many tools the agent currently offers the model.
+    pub fn tool_count(&self) -> usize {
+        self.tools.len()
+    }
+
+    /// Names of the tools the agent currently offers the model, in the order they were added.
+    pub fn tool_names(&self) -> Vec<String> {
+        self.tools.tools().iter().map(|tool| tool.name()).collect()
+    }
+
+    /// Check the configuration without calling the model, reporting every issue found.
+    ///
+    /// Purely diagnostic: an agent that fails validation still runs. The preamble check is
+    /// a heuristic that looks for the word "tool" in the agent's own preamble, not in
+    /// context added by [`ContextMiddleware`].
+    pub fn validate(&self) -> Result<(), Vec<ConfigIssue>> {
+        let mut issues = Vec::new();
+        let tools = self.tools.tools();
+        if tools.is_empty()
+            && self.preamble.as_deref().is_some_and(|preamble| {
+                preamble.split(|c: char| !c.is_alphanumeric()).any(|word| {
+                    word.eq_ignore_ascii_case("tool") || word.eq_ignore_ascii_case("tools")
+                })
+            })
+        {
+            issues.push(ConfigIssue::PreambleNeedsTools);
+        }
+        if let Some(temperature) = self.temperature
+            && !(0.0..=2.0).contains(&temperature)
+        {
+            issues.push(ConfigIssue::TemperatureOutOfRange(temperature));
+        }
+        if let Some(top_p) = self.top_p
+            && !(0.0..=1.0).contains(&top_p)
+        {
+            issues.push(ConfigIssue::TopPOutOfRange(top_p));
+        }
+        let mut seen = Vec::new();
+        for tool in tools.iter() {
+            let name = tool.name();
+            if seen.contains(&name) {
+                if !issues.contains(&ConfigIssue::DuplicateTool(name.clone())) {
+                    issues.push(ConfigIssue::DuplicateTool(name));
+                }
+            } else {
+                seen.push(name);
+            }
+        }
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(issues)
+        }
+    }
+
+    /// Like [`Prompt::prompt`], but also reports whether tools were used and how many
+    /// tool-calling rounds ran.
+    pub async fn prompt_detailed(
+        &self,
+        prompt: impl Into<Message> + Send,
+    ) -> Result<PromptOutcome, CompletionError> {
+        self.run_with_history_detailed(vec![prompt.into()]).await
+    }
+
+    /// Run each prompt as an independent conversation, at most `concurrency` at a time
+    /// (at least one), and return the answers in input order.
+    ///
+    /// Failures are reported per prompt; one failed prompt does not stop the others.
+    pub async fn prompt_many<P>(
+        &self,
+        prompts: impl IntoIterator<Item = P>,
+        concurrency: usize,
+    ) -> Vec<Result<String, CompletionError>>
+    where
+        P: Into<Message> + Send,
+    {
+        futures::stream::iter(prompts)
+            .map(|prompt| self.run_with_history(vec![prompt.into()]))
+            .buffered(concurrency.max(1))
+            .collect()
+            .await
+    }
+
     pub(crate) async fn run_with_history(
         &self,
-        mut chat_history: Vec<Message>,
+        chat_history: Vec<Message>,
     ) -> Result<String, CompletionError> {
-        let tools: Vec<ToolDefinition> = self.tools.tools.iter().map(|t| t.definition()).collect();
+        self.run_with_history_detailed(chat_history)
+            .await
+            .map(|outcome| outcome.text)
+    }
+
+    /// The preamble for one run: the context of every [`ContextMiddleware`], in order,
+    /// followed by the configured preamble and the response style.
+    pub(crate) async fn run_preamble(&self) -> Option<String> {
+        let mut sections = Vec::new();
+        for middleware in &self.context_middleware {
+            sections.extend(middleware.context().await);
+        }
+        sections.extend(self.preamble.clone());
+        sections.extend(self.response_style.clone());
+        (!sections.is_empty()).then(|| sections.join("\n\n"))
+    }
+
+    pub(crate) async fn run_with_history_detailed(
+        &self,
+        chat_history: Vec<Message>,
+    ) -> Result<PromptOutcome, CompletionError> {
+        // Fail (or degrade) before the first request rather than on a provider error.
+        let capabilities = self.model.capabilities();
+        let mut preflight = CompletionRequest {
+            preamble: self.run_preamble().await,
+            chat_history,
+            tools: self.tools.definitions(),
+            ..Default::default()
+        };
+        enforce_capabilities(&capabilities, self.capability_policy, &mut preflight)?;
+        let CompletionRequest {
+            preamble,
+            mut chat_history,
+            ..
+        } = preflight;
 
+        // Keys are `<run>-<step>`, or `<run>-<step>-r<n>` for the n-th re-ask after a
+        // reasoning-only reply: fresh per run and per request, so a deduplicating provider
+        // does not replay the reply being re-asked.
+        let run_id = uuid::Uuid::new_v4().simple().to_string();
+        let mut completed_calls: HashMap<String, serde_json::Value> = HashMap::new();
+        let mut suppressed_tool_calls = 0usize;
+        let mut no_tools_noticed = false;
         let mut steps = 0usize;
+        let mut tool_calls_made = 0usize;
+        let mut reasoning = Vec::new();
+        let mut reasoning_only_rounds = 0usize;
+        let mut usage = Usage::default();
+        let best_effort_at = self
+            .best_effort_deadline
+            .map(|limit| self.clock.now() + limit);
+        let best_effort_passed = || best_effort_at.is_some_and(|at| self.clock.now() >= at);
+        let deadline_at = self.deadline.map(|limit| self.clock.now() + limit);
+        let deadline_passed = || deadline_at.is_some_and(|at| self.clock.now() >= at);
+        // What the run has to show if the deadline passes.
+        let mut last_text = String::new();
+        let mut completed_tools = Vec::new();
+        let deadline_exceeded =
+            |last_text: &str, completed_tools: &[String]| CompletionError::DeadlineExceeded {
+                limit: self.deadline.unwrap_or_default(),
+                partial: partial_result(last_text, completed_tools),
+            };
 
         loop {
-            let request = CompletionRequest {
-                preamble: self.preamble.clone(),
+            if deadline_passed() {
+                return Err(deadline_exceeded(&last_text, &completed_tools));
+            }
+            if best_effort_passed() {
+                let answer = self
+                    .best_effort_answer(&preamble, chat_history, deadline_at)
+                    .await;
+                let (text, answer_usage) = match answer {
+                    Err(CompletionError::Timeout(_)) if deadline_passed() => {
+                        return Err(deadline_exceeded(&last_text, &completed_tools));
+                    }
+                    answer => answer?,
+                };
+                usage += answer_usage;
+                let text = if self.include_reasoning {
+                    tool_loop::prepend_reasoning(&reasoning, &text)
+                } else {
+                    text
+                };
+                return Ok(PromptOutcome {
+                    text,
+                    used_tools: steps > 0,
+                    steps,
+                    reasoning,
+                    usage,
+                    suppressed_tool_calls,
+                    deadline_reached: true,
+                });
+            }
+
+            // One snapshot per step: the request advertises exactly the tools this step can
+            // call, even if the set changes while it runs.
+            let step_tools = self.tools.tools();
+            let tools: Vec<ToolDefinition> = if capabilities.supports_tools {
+                step_tools.iter().map(|t| t.definition()).collect()
+            } else {
+                Vec::new()
+            };
+            let mut request = CompletionRequest {
+                preamble: preamble.clone(),
                 chat_history: chat_history.clone(),
                 temperature: self.temperature,
-                tools: tools.clone(),
+                top_p: self.top_p,
+                max_tokens: self.max_tokens,
+                seed: self.seed,
+                tools,
+                idempotency_key: Some(match reasoning_only_rounds {
+                    0 => format!("{run_id}-{steps}"),
+                    round => format!("{run_id}-{steps}-r{round}"),
+                }),
                 ..Default::default()
             };
+            if let Some(guardrails) = &self.guardrails {
+                guardrails.filter_request(&mut request)?;
+            }
+            let request_summary = self.step_history.as_ref().map(|_| {
+                RequestSummary::new(
+                    &request.chat_history,
+                    request.tools.iter().map(|t| t.name.clone()).collect(),
+                )
+            });
+
+            if let Some(budget) = &self.budget {
+                budget.reserve_call()?;
+            }
+            // The deadlines also bound the completion itself.
+            let timeout = [best_effort_at, deadline_at]
+                .into_iter()
+                .flatten()
+                .map(|at| at.saturating_duration_since(self.clock.now()))
+                .chain(self.request_timeout)
+                .min();
+            let response = match completion_with_timeout(self.model.as_ref(), request, timeout)
+                .await
+            {
+                Err(CompletionError::Timeout(_)) if best_effort_passed() || deadline_passed() => {
+                    continue;
+                }
+                response => response?,
+            };
+            if let Some(response_usage) = self.model.usage(&response) {
+                usage += response_usage;
+                if let Some(budget) = &self.budget {
+                    budget.record(response_usage);
+                }
+            }
 
-            let response = self.model.completion(request).await?;
+            // A refusal ends the run: it is not an answer, and tool calls sent with it are
+            // not run.
+            if let Err(refused) = check_refusal(&response.content) {
+                if let (Some(history), Some(request), CompletionError::Refused { message }) =
+                    (&self.step_history, request_summary, &refused)
+                {
+                    let record = AgentStepRecord {
+                        run_id: run_id.clone(),
+                        step: steps + 1,
+                        request,
+                        response: format!("[refused] {message}"),
+                        tool_calls: Vec::new(),
+                    };
+                    step_history::persist_step(history, &record).await;
+                }
+                return Err(refused);
+            }
 
             let mut tool_calls = Vec::new();
+            let mut text_parts = Vec::new();
+            let mut response_reasoning = Vec::new();
             for item in &response.content {
-                if let AssistantContent::ToolCall(tc) = item {
-                    tool_calls.push(tc.clone());
+                match item {
+                    AssistantContent::ToolCall(tc) => tool_calls.push(tc.clone()),
+                    AssistantContent::Reasoning(block) => {
+                        reasoning.push(block.display_text().to_string());
+                        response_reasoning.push(block.display_text());
+                    }
+                    AssistantContent::Text(t) => text_parts.push(t.text.as_str()),
+                    // Handled above.
+                    AssistantContent::Refusal { .. } => {}
                 }
             }
 
             if tool_calls.is_empty() {
-                let text_response = response
-                    .content
-                    .iter()
-                    .filter_map(|c| match c {
-                        AssistantContent::Text(t) => Some(t.text.clone()),
-                        _ => None,
-                    })
-                    .collect::<Vec<_>>()
-                    .join("\n");
+                let mut text_response = text_parts.join("\n");
+                if text_response.is_empty()
+                    && self.thought_only_answer == ThoughtOnlyAnswer::Thought
+                {
+                    text_response = response_reasoning.join("\n");
+                }
+                // With `Empty` or `Thought`, a response without text is the answer as is.
+                let retry = self.thought_only_answer == ThoughtOnlyAnswer::Retry;
+
+                if text_response.is_empty() && !response_reasoning.is_empty() && retry {
+                    // The model is still thinking; ask again for the answer.
+                    reasoning_only_rounds += 1;
+                    let limit = self.max_steps.unwrap_or(MAX_REASONING_ONLY_ROUNDS);
+                    if reasoning_only_rounds > limit {
+                        return Err(CompletionError::ResponseError(format!(
+                            "Model returned only reasoning for {limit} consecutive rounds",
+                        )));
+                    }
+                    continue;
+                }
 
-                if text_response.is_empty() && !response.content.is_empty() {
+                if text_response.is_empty() && !response.content.is_empty() && retry {
                     // Return a more user-friendly error instead of debug format
                     return Err(CompletionError::ResponseError(
                         "Model returned non-text response (likely only thought or unsupported content)".into()
                     ));
                 }
 
-                return Ok(text_response);
+                let text_response = match &self.guardrails {
+                    Some(guardrails) => guardrails.filter_response(text_response)?,
+                    None => text_response,
+                };
+                let text_response = if self.reflect {
+                    let (refined, reflection_usage) = self
+                        .reflected_answer(&preamble, chat_history, text_response)
+                        .await?;
+                    usage += reflection_usage;
+                    refined
+                } else {
+                    text_response
+                };
+                if let (Some(history), Some(request)) = (&self.step_history, request_summary) {
+                    let record = AgentStepRecord {
+                        run_id: run_id.clone(),
+                        step: steps + 1,
+                        request,
+                        response: text_response.clone(),
+                        tool_calls: Vec::new(),
+                    };
+                    step_history::persist_step(history, &record).await;
+                }
+                let text = if self.include_reasoning {
+                    tool_loop::prepend_reasoning(&reasoning, &text_response)
+                } else {
+                    text_response
+                };
+                return Ok(PromptOutcome {
+                    text,
+                    used_tools: steps > 0,
+                    steps,
+                    reasoning,
+                    usage,
+                    suppressed_tool_calls,
+                    deadline_reached: false,
+                });
+            }
+
+            if !text_parts.is_empty() {
+                last_text = text_parts.join("\n");
+            }
+            if let Some(handler) = &self.interim_text
+                && !text_parts.is_empty()
+            {
+                let text = text_parts.join("\n");
+                match &self.guardrails {
+                    Some(guardrails) => handler(&guardrails.filter_response(text)?),
+                    None => handler(&text),
+                }
             }
 
+            reasoning_only_rounds = 0;
             steps += 1;
             if let Some(limit) = self.max_steps
                 && steps > limit
@@ -131,33 +645,148 @@ impl<M: CompletionModel> Agent<M> {
                 content: assistant_content,
             });
 
-            let mut results = Vec::new();
-            for tc in tool_calls {
-                let tool = self
-                    .tools
-                    .tools
-                    .iter()
-                    .find(|t| t.name() == tc.function.name)
-                    .ok_or_else(|| {
-                        CompletionError::RequestError(
-                            std::io::Error::new(
-                                std::io::ErrorKind::NotFound,
-                                format!("Tool not found: {}", tc.function.name),
-                            )
-                            .into(),
-                        )
-                    })?;
-
-                let result = tool
-                    .call(tc.function.arguments.clone())
-                    .map_err(CompletionError::RequestError)?;
+            if deadline_passed() {
+                return Err(deadline_exceeded(&last_text, &completed_tools));
+            }
+
+            // Resolve every call first, then run the new ones concurrently; results keep
+            // the order of the calls. A step that fails to resolve runs none of its calls.
+            // Replays do not count towards the limit; a step that would cross it is
+            // rejected as a whole.
+            let mut seen = HashSet::new();
+            let new_calls = tool_calls
+                .iter()
+                .filter(|tc| !completed_calls.contains_key(&tc.id) && seen.insert(&tc.id))
+                .count();
+            tool_calls_made += new_calls;
+            if let Some(limit) = self.max_tool_calls
+                && tool_calls_made > limit
+            {
+                return Err(CompletionError::ResponseError(format!(
+                    "Tool calling exceeded max tool calls ({limit})",
+                )));
+            }
+
+            let mut planned = Vec::with_capacity(tool_calls.len());
+            for tc in &tool_calls {
+                // A replayed response must not run a tool with side effects twice.
+                if completed_calls.contains_key(&tc.id)
+                    || planned.iter().any(|(id, _)| *id == &tc.id)
+                {
+                    suppressed_tool_calls += 1;
+                    tracing::warn!(
+                        call_id = %tc.id,
+                        tool = %tc.function.name,
+                        "suppressing duplicate tool call"
+                    );
+                    planned.push((&tc.id, PlannedCall::Replay));
+                    continue;
+                }
+
+                match step_tools.iter().find(|t| t.name() == tc.function.name) {
+                    Some(tool) => planned.push((&tc.id, PlannedCall::Run(Arc::clone(tool)))),
+                    // A model that hallucinates a call with no tools on offer gets one refusal
+                    // it can act on, like any other reportable tool error.
+                    None if step_tools.is_empty() && !no_tools_noticed => {
+                        let refusal = ToolCallError::NotPermitted {
+                            reason: NO_TOOLS_NOTICE.to_string(),
+                        };
+                        tracing::debug!(tool = %tc.function.name, "tool call without tools");
+                        completed_calls.insert(tc.id.clone(), refusal.model_report().unwrap());
+                        planned.push((&tc.id, PlannedCall::Replay));
+                    }
+                    None => {
+                        let message = match &self.on_missing_tool {
+                            Some(handler) => handler(&tc.function.name),
+                            None => format!("Tool not found: {}", tc.function.name),
+                        };
+                        return Err(CompletionError::RequestError(
+                            std::io::Error::new(std::io::ErrorKind::NotFound, message).into(),
+                        ));
+                    }
+                }
+            }
+
+            no_tools_noticed |= step_tools.is_empty();
+
+            let outcomes = futures::future::join_all(planned.iter().zip(&tool_calls).map(
+                |((_, call), tc)| async move {
+                    match call {
+                        PlannedCall::Run(tool) => Some(
+                            self.call_tool(Arc::clone(tool), tc.function.arguments.clone())
+                                .await,
+                        ),
+                        PlannedCall::Replay => None,
+                    }
+                },
+            ))
+            .await;
 
+            let mut results: Vec<UserContent> = Vec::with_capacity(planned.len());
+            let mut internal_error = None;
+            for (tc, outcome) in tool_calls.iter().zip(outcomes) {
+                let result = match outcome {
+                    Some((outcome, elapsed)) => {
+                        // Failures the model can act on become the tool result; internal
+                        // ones fail the run.
+                        let (result, success) = match outcome {
+                            Ok(value) => (value, true),
+                            Err(ToolCallError::Internal(source)) => {
+                                self.tools
+                                    .stats
+                                    .record(&tc.function.name, elapsed, false, 0);
+                                internal_error.get_or_insert(source);
+                                continue;
+                            }
+                            Err(err) => {
+                                tracing::debug!(tool = %tc.function.name, "tool failed: {err}");
+                                (err.model_report().unwrap_or_default(), false)
+                            }
+                        };
+                        self.tools.stats.record(
+                            &tc.function.name,
+                            elapsed,
+                            success,
+                            result.to_string().len(),
+                        );
+                        completed_tools.push(tc.function.name.clone());
+                        completed_calls.insert(tc.id.clone(), result.clone());
+                        result
+                    }
+                    // Duplicates come after the call they repeat, which is recorded by now.
+                    None => completed_calls.get(&tc.id).cloned().unwrap_or_default(),
+                };
                 results.push(UserContent::ToolResult(ToolResult {
                     id: tc.id.clone(),
                     name: tc.function.name.clone(),
                     result,
                 }));
             }
+            if let Some(source) = internal_error {
+                return Err(CompletionError::RequestError(source));
+            }
+
+            if let (Some(history), Some(request)) = (&self.step_history, request_summary) {
+                let calls = tool_calls.iter().zip(&results).map(|(tc, result)| {
+                    let UserContent::ToolResult(result) = result else {
+                        unreachable!("tool rounds only produce tool results")
+                    };
+                    RecordedToolCall {
+                        id: tc.id.clone(),
+                        name: tc.function.name.clone(),
+                        arguments: tc.function.arguments.clone(),
+                        result: result.result.clone(),
+                    }
+                });
+                let record = AgentStepRecord {
+                    run_id: run_id.clone(),
+                    step: steps,
+                    request,
+                    response: text_parts.join("\n"),
+                    tool_calls: calls.collect(),
+                };
+                step_history::persist_step(history, &record).await;
+            }
 
             let tool_result_content = match OneOrMany::many(results) {
                 Some(content) => content,
@@ -173,6 +802,137 @@ impl<M: CompletionModel> Agent<M> {
     }
 }
 
+impl<M: CompletionModel> Agent<M> {
+    /// Run `tool` on the blocking pool, within its concurrency cap if it has one, and
+    /// report how long the call itself took.
+    async fn call_tool(
+        &self,
+        tool: Arc<dyn Tool>,
+        args: serde_json::Value,
+    ) -> (Result<serde_json::Value, ToolCallError>, Duration) {
+        let _permit = match self.tool_concurrency.get(&tool.name()) {
+            Some(semaphore) => Some(
+                Arc::clone(semaphore)
+                    .acquire_owned()
+                    .await
+                    .expect("tool semaphores are never closed"),
+            ),
+            None => None,
+        };
+        let started = self.clock.now();
+        let result = tokio::task::spawn_blocking(move || tool.call(args))
+            .await
+            .unwrap_or_else(|e| Err(ToolCallError::Internal(Box::new(e))));
+        (result, self.clock.now().saturating_duration_since(started))
+    }
+
+    /// `answer` improved by one more completion asking for [`REFLECTION_PROMPT`], requested
+    /// without tools, together with the tokens it used. An empty reflection keeps `answer`.
+    async fn reflected_answer(
+        &self,
+        preamble: &Option<String>,
+        mut chat_history: Vec<Message>,
+        answer: String,
+    ) -> Result<(String, Usage), CompletionError> {
+        chat_history.push(Message::assistant(answer.clone()));
+        chat_history.push(Message::user(REFLECTION_PROMPT));
+        let mut request = CompletionRequest {
+            preamble: preamble.clone(),
+            chat_history,
+            temperature: self.temperature,
+            top_p: self.top_p,
+            max_tokens: self.max_tokens,
+            seed: self.seed,
+            ..Default::default()
+        };
+        if let Some(guardrails) = &self.guardrails {
+            guardrails.filter_request(&mut request)?;
+        }
+        if let Some(budget) = &self.budget {
+            budget.reserve_call()?;
+        }
+        let response =
+            completion_with_timeout(self.model.as_ref(), request, self.request_timeout).await?;
+        let usage = self.model.usage(&response);
+        if let (Some(usage), Some(budget)) = (usage, &self.budget) {
+            budget.record(usage);
+        }
+
+        let text = response
+            .content
+            .iter()
+            .filter_map(|item| match item {
+                AssistantContent::Text(t) => Some(t.text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        if text.trim().is_empty() {
+            return Ok((answer, usage.unwrap_or_default()));
+        }
+        let text = match &self.guardrails {
+            Some(guardrails) => guardrails.filter_response(text)?,
+            None => text,
+        };
+        Ok((text, usage.unwrap_or_default()))
+    }
+
+    /// The answer to [`BEST_EFFORT_PROMPT`], requested without tools and bounded by
+    /// [`BEST_EFFORT_TIMEOUT`], together with the tokens it used.
+    async fn best_effort_answer(
+        &self,
+        preamble: &Option<String>,
+        mut chat_history: Vec<Message>,
+        deadline_at: Option<Instant>,
+    ) -> Result<(String, Usage), CompletionError> {
+        chat_history.push(Message::user(BEST_EFFORT_PROMPT));
+        let mut request = CompletionRequest {
+            preamble: preamble.clone(),
+            chat_history,
+            temperature: self.temperature,
+            top_p: self.top_p,
+            max_tokens: self.max_tokens,
+            seed: self.seed,
+            ..Default::default()
+        };
+        if let Some(guardrails) = &self.guardrails {
+            guardrails.filter_request(&mut request)?;
+        }
+        if let Some(budget) = &self.budget {
+            budget.reserve_call()?;
+        }
+        let timeout = deadline_at.map_or(BEST_EFFORT_TIMEOUT, |at| {
+            BEST_EFFORT_TIMEOUT.min(at.saturating_duration_since(self.clock.now()))
+        });
+        let response = completion_with_timeout(self.model.as_ref(), request, Some(timeout)).await?;
+        let usage = self.model.usage(&response);
+        if let (Some(usage), Some(budget)) = (usage, &self.budget) {
+            budget.record(usage);
+        }
+        check_refusal(&response.content)?;
+
+        let text = response
+            .content
+            .iter()
+            .filter_map(|item| match item {
+                AssistantContent::Text(t) => Some(t.text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        if text.trim().is_empty() {
+            return Err(CompletionError::ResponseError(
+                "Model returned no best-effort answer after the deadline".into(),
+            ));
+        }
+        let text = match &self.guardrails {
+            Some(guardrails) => guardrails.filter_response(text)?,
+            None => text,
+        };
+        Ok((text, usage.unwrap_or_default()))
+    }
+}
+
 impl<M: CompletionModel> Prompt for Agent<M> {
     async fn prompt(&self, prompt: impl Into<Message> + Send) -> Result<String, CompletionError> {
         let msg = prompt.into();
@@ -194,16 +954,18 @@ impl<M: CompletionModel> Chat for Agent<M> {
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
     use serde_json::json;
 
-    use super::AgentBuilder;
+    use super::{AgentBuilder, ConfigIssue, ThoughtOnlyAnswer};
     use crate::internal::ai::{
         completion::{
-            CompletionError, CompletionModel, CompletionRequest, CompletionResponse, Message,
-            Prompt,
-            message::{AssistantContent, Function, Text, ToolCall, UserContent},
+            CapabilityPolicy, CompletionError, CompletionModel, CompletionRequest,
+            CompletionResponse, Message, ModelCapabilities, Prompt,
+            message::{AssistantContent, Function, Reasoning, Text, ToolCall, UserContent},
         },
-        tools::{Tool, ToolDefinition, ToolSet},
+        tools::{Tool, ToolCallError, ToolDefinition, ToolSet},
     };
 
     #[derive(Clone)]
@@ -270,18 +1032,15 @@ mod tests {
             }
         }
 
-        fn call(
-            &self,
-            _args: serde_json::Value,
-        ) -> Result<serde_json::Value, Box<dyn std::error::Error + Send + Sync>> {
+        fn call(&self, _args: serde_json::Value) -> Result<serde_json::Value, ToolCallError> {
             Ok(json!({"ok": true}))
         }
     }
 
     #[tokio::test]
     async fn test_tool_call_loop_executes_tool() {
-        let mut tool_set = ToolSet::default();
-        tool_set.tools.push(std::sync::Arc::new(MockTool));
+        let tool_set = ToolSet::default();
+        tool_set.add(std::sync::Arc::new(MockTool));
 
         let agent = AgentBuilder::new(MockModel).tools(tool_set).build();
         let response = Prompt::prompt(&agent, "hi").await.unwrap();
@@ -289,92 +1048,1611 @@ mod tests {
         assert_eq!(response, "done");
     }
 
+    #[test]
+    fn test_tool_count_and_names() {
+        struct OtherTool;
+
+        impl Tool for OtherTool {
+            fn name(&self) -> String {
+                "other_tool".to_string()
+            }
+
+            fn description(&self) -> String {
+                "Other tool".to_string()
+            }
+
+            fn definition(&self) -> ToolDefinition {
+                ToolDefinition {
+                    name: self.name(),
+                    description: self.description(),
+                    parameters: json!({ "type": "object", "properties": {} }),
+                }
+            }
+
+            fn call(&self, _args: serde_json::Value) -> Result<serde_json::Value, ToolCallError> {
+                Ok(json!({}))
+            }
+        }
+
+        let agent = AgentBuilder::new(MockModel).build();
+        assert_eq!(agent.tool_count(), 0);
+        assert!(agent.tool_names().is_empty());
+
+        let agent = AgentBuilder::new(MockModel)
+            .tool(MockTool)
+            .tool(OtherTool)
+            .build();
+        assert_eq!(agent.tool_count(), 2);
+        assert_eq!(agent.tool_names(), ["mock_tool", "other_tool"]);
+    }
+
     #[tokio::test]
-    async fn test_max_steps_allows_exact_tool_call_count() {
-        use std::sync::{
-            Arc,
-            atomic::{AtomicUsize, Ordering},
+    async fn test_prompt_detailed_reports_tool_use() {
+        let tool_set = ToolSet::default();
+        tool_set.add(std::sync::Arc::new(MockTool));
+
+        let agent = AgentBuilder::new(MockModel).tools(tool_set).build();
+        let outcome = agent.prompt_detailed("hi").await.unwrap();
+        assert_eq!(outcome.text, "done");
+        assert!(outcome.used_tools);
+        assert_eq!(outcome.steps, 1);
+    }
+
+    #[tokio::test]
+    async fn test_steps_are_recorded_to_history() {
+        use super::step_history::{AGENT_STEP_TYPE, AgentStepRecord};
+        use crate::{
+            internal::ai::history::HistoryManager,
+            utils::{storage::local::LocalStorage, storage_ext::StorageExt},
         };
 
-        #[derive(Clone)]
-        struct AlwaysToolCallModel {
-            completions: Arc<AtomicUsize>,
+        let dir = tempfile::tempdir().unwrap();
+        let repo_path = dir.path().join(".libra");
+        std::fs::create_dir(&repo_path).unwrap();
+        let storage = Arc::new(LocalStorage::new(repo_path.join("objects")));
+        let history = Arc::new(HistoryManager::new(storage.clone(), repo_path));
+
+        let tool_set = ToolSet::default();
+        tool_set.add(Arc::new(MockTool));
+        let agent = AgentBuilder::new(MockModel)
+            .tools(tool_set.clone())
+            .record_steps(history.clone())
+            .build();
+        assert_eq!(agent.prompt_detailed("hi").await.unwrap().text, "done");
+
+        let mut steps = Vec::new();
+        for (_, hash) in history.list_objects(AGENT_STEP_TYPE).await.unwrap() {
+            steps.push(storage.get_json::<AgentStepRecord>(&hash).await.unwrap());
         }
+        steps.sort_by_key(|record| record.step);
+        assert_eq!(steps.len(), 2);
+        assert_eq!(steps[0].run_id, steps[1].run_id);
+        assert_eq!(steps[0].request.prompt.as_deref(), Some("hi"));
+        assert_eq!(steps[0].tool_calls.len(), 1);
+        assert_eq!(steps[0].tool_calls[0].result, json!({"ok": true}));
+        assert_eq!((steps[1].step, steps[1].response.as_str()), (2, "done"));
+        assert!(steps[1].tool_calls.is_empty());
 
-        impl CompletionModel for AlwaysToolCallModel {
+        // Without a history manager nothing is written.
+        let agent = AgentBuilder::new(MockModel).tools(tool_set).build();
+        agent.prompt_detailed("hi").await.unwrap();
+        assert_eq!(
+            history.list_objects(AGENT_STEP_TYPE).await.unwrap().len(),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn test_interim_text_is_forwarded() {
+        use std::sync::Mutex;
+
+        /// Says what it is about to do in the same response as the tool call.
+        #[derive(Clone)]
+        struct NarratingModel;
+
+        impl CompletionModel for NarratingModel {
             type Response = ();
 
             async fn completion(
                 &self,
-                _request: CompletionRequest,
+                request: CompletionRequest,
             ) -> Result<CompletionResponse<Self::Response>, CompletionError> {
-                self.completions.fetch_add(1, Ordering::SeqCst);
-
-                Ok(CompletionResponse {
-                    content: vec![AssistantContent::ToolCall(ToolCall {
-                        id: "call".to_string(),
-                        name: "always_tool".to_string(),
-                        function: Function {
-                            name: "always_tool".to_string(),
-                            arguments: json!({}),
-                        },
-                    })],
-                    raw_response: (),
-                })
+                let mut response = MockModel.completion(request).await?;
+                if matches!(response.content[0], AssistantContent::ToolCall(_)) {
+                    response.content.insert(
+                        0,
+                        AssistantContent::Text(Text {
+                            text: "Let me check...".to_string(),
+                        }),
+                    );
+                }
+                Ok(response)
             }
         }
 
-        #[derive(Clone)]
-        struct CountingTool {
-            calls: Arc<AtomicUsize>,
-        }
+        let tools = || {
+            let tool_set = ToolSet::default();
+            tool_set.add(Arc::new(MockTool));
+            tool_set
+        };
 
-        impl Tool for CountingTool {
-            fn name(&self) -> String {
-                "always_tool".to_string()
-            }
+        let interim = Arc::new(Mutex::new(Vec::new()));
+        let sink = interim.clone();
+        let agent = AgentBuilder::new(NarratingModel)
+            .tools(tools())
+            .on_interim_text(move |text| sink.lock().unwrap().push(text.to_string()))
+            .build();
+        let outcome = agent.prompt_detailed("hi").await.unwrap();
+        assert_eq!(outcome.text, "done");
+        assert_eq!(*interim.lock().unwrap(), vec!["Let me check..."]);
 
-            fn description(&self) -> String {
-                "Always returns the same mock value".to_string()
-            }
+        // Without a handler the interim text is dropped, as before.
+        let agent = AgentBuilder::new(NarratingModel).tools(tools()).build();
+        assert_eq!(Prompt::prompt(&agent, "hi").await.unwrap(), "done");
+    }
 
-            fn definition(&self) -> ToolDefinition {
-                ToolDefinition {
-                    name: self.name(),
-                    description: self.description(),
-                    parameters: json!({ "type": "object" }),
-                }
-            }
+    #[tokio::test]
+    async fn test_context_middleware_runs_once_per_run() {
+        use std::sync::{
+            Mutex,
+            atomic::{AtomicUsize, Ordering},
+        };
 
-            fn call(
-                &self,
-                _args: serde_json::Value,
-            ) -> Result<serde_json::Value, Box<dyn std::error::Error + Send + Sync>> {
-                self.calls.fetch_add(1, Ordering::SeqCst);
-                Ok(json!({"ok": true}))
+        use super::ContextMiddleware;
+
+        /// Numbers the runs it was consulted for.
+        struct CountingContext(AtomicUsize);
+
+        #[async_trait::async_trait]
+        impl ContextMiddleware for CountingContext {
+            async fn context(&self) -> Option<String> {
+                let run = self.0.fetch_add(1, Ordering::SeqCst) + 1;
+                Some(format!("run {run}"))
             }
         }
 
-        let completion_calls = Arc::new(AtomicUsize::new(0));
-        let tool_calls = Arc::new(AtomicUsize::new(0));
+        /// [`MockModel`] that records the preamble of each request.
+        #[derive(Clone, Default)]
+        struct PreambleModel(Arc<Mutex<Vec<Option<String>>>>);
 
-        let mut tool_set = ToolSet::default();
-        tool_set.tools.push(std::sync::Arc::new(CountingTool {
-            calls: tool_calls.clone(),
-        }));
+        impl CompletionModel for PreambleModel {
+            type Response = ();
 
-        let model = AlwaysToolCallModel {
-            completions: completion_calls.clone(),
-        };
-        let agent = AgentBuilder::new(model)
-            .tools(tool_set)
-            .max_steps(2)
+            async fn completion(
+                &self,
+                request: CompletionRequest,
+            ) -> Result<CompletionResponse<Self::Response>, CompletionError> {
+                self.0.lock().unwrap().push(request.preamble.clone());
+                MockModel.completion(request).await
+            }
+        }
+
+        let model = PreambleModel::default();
+        let agent = AgentBuilder::new(model.clone())
+            .preamble("Be brief.")
+            .context_middleware(CountingContext(AtomicUsize::new(0)))
+            .tool(MockTool)
             .build();
+        agent.prompt_detailed("hi").await.unwrap();
+        agent.prompt_detailed("again").await.unwrap();
 
-        let err = Prompt::prompt(&agent, "hi").await.unwrap_err().to_string();
+        // Two steps per run, but the context is gathered once per run.
+        let first = Some("run 1\n\nBe brief.".to_string());
+        let second = Some("run 2\n\nBe brief.".to_string());
+        assert_eq!(
+            *model.0.lock().unwrap(),
+            vec![first.clone(), first, second.clone(), second]
+        );
+    }
+
+    /// A tool the mock models never call.
+    struct OtherTool;
+
+    impl Tool for OtherTool {
+        fn name(&self) -> String {
+            "other_tool".to_string()
+        }
+
+        fn description(&self) -> String {
+            "Unused tool".to_string()
+        }
+
+        fn definition(&self) -> ToolDefinition {
+            ToolDefinition {
+                name: self.name(),
+                description: self.description(),
+                parameters: json!({"type": "object"}),
+            }
+        }
+
+        fn call(&self, _args: serde_json::Value) -> Result<serde_json::Value, ToolCallError> {
+            Ok(json!({}))
+        }
+    }
+
+    fn other_tools() -> ToolSet {
+        let tool_set = ToolSet::default();
+        tool_set.add(std::sync::Arc::new(OtherTool));
+        tool_set
+    }
+
+    #[tokio::test]
+    async fn test_missing_tool_message_is_customizable() {
+        let err = AgentBuilder::new(MockModel)
+            .tools(other_tools())
+            .build()
+            .prompt_detailed("hi")
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("Tool not found: mock_tool"),
+            "{err}"
+        );
+
+        let agent = AgentBuilder::new(MockModel)
+            .tools(other_tools())
+            .on_missing_tool(|name| format!("`{name}` is unavailable; answer without it"))
+            .build();
+        let err = agent.prompt_detailed("hi").await.unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("`mock_tool` is unavailable; answer without it"),
+            "{err}"
+        );
+        assert!(!err.to_string().contains("Tool not found"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_tool_call_without_tools_recovers_once() {
+        use std::sync::Mutex;
+
+        /// Calls a tool until it sees a tool result, then answers; records the results.
+        #[derive(Clone)]
+        struct HallucinatingModel {
+            results: Arc<Mutex<Vec<serde_json::Value>>>,
+            always: bool,
+        }
+
+        impl CompletionModel for HallucinatingModel {
+            type Response = ();
+
+            async fn completion(
+                &self,
+                request: CompletionRequest,
+            ) -> Result<CompletionResponse<Self::Response>, CompletionError> {
+                let results: Vec<_> = request
+                    .chat_history
+                    .iter()
+                    .filter_map(|msg| match msg {
+                        Message::User { content } => Some(content.iter()),
+                        _ => None,
+                    })
+                    .flatten()
+                    .filter_map(|c| match c {
+                        UserContent::ToolResult(result) => Some(result.result.clone()),
+                        _ => None,
+                    })
+                    .collect();
+                let answer = !results.is_empty() && !self.always;
+                *self.results.lock().unwrap() = results;
+                let content = if answer {
+                    AssistantContent::Text(Text {
+                        text: "answered directly".to_string(),
+                    })
+                } else {
+                    AssistantContent::ToolCall(ToolCall {
+                        id: format!("call_{}", request.chat_history.len()),
+                        name: "search".to_string(),
+                        function: Function {
+                            name: "search".to_string(),
+                            arguments: json!({}),
+                        },
+                    })
+                };
+                Ok(CompletionResponse {
+                    content: vec![content],
+                    raw_response: (),
+                })
+            }
+        }
+
+        let results = Arc::new(Mutex::new(Vec::new()));
+        let agent = AgentBuilder::new(HallucinatingModel {
+            results: Arc::clone(&results),
+            always: false,
+        })
+        .build();
+        let outcome = agent.prompt_detailed("hi").await.unwrap();
+        assert_eq!(outcome.text, "answered directly");
+        assert_eq!(outcome.steps, 1);
+        let report = results.lock().unwrap()[0].clone();
+        assert_eq!(report["retryable"], json!(false));
+        assert!(
+            report["error"]
+                .as_str()
+                .unwrap()
+                .contains(super::NO_TOOLS_NOTICE),
+            "{report}"
+        );
+
+        // The notice is given once; insisting fails the run as before.
+        let agent = AgentBuilder::new(HallucinatingModel {
+            results: Arc::clone(&results),
+            always: true,
+        })
+        .build();
+        let err = agent.prompt_detailed("hi").await.unwrap_err();
+        assert!(err.to_string().contains("Tool not found: search"), "{err}");
+        assert_eq!(results.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_tool_call_errors_by_variant() {
+        use std::sync::Mutex;
+
+        /// Fails every call with the error built by its function.
+        struct FailingTool(fn() -> ToolCallError);
+
+        impl Tool for FailingTool {
+            fn definition(&self) -> ToolDefinition {
+                MockTool.definition()
+            }
+
+            fn call(&self, _args: serde_json::Value) -> Result<serde_json::Value, ToolCallError> {
+                Err((self.0)())
+            }
+        }
+
+        /// [`MockModel`] that records the tool results it is sent.
+        #[derive(Clone, Default)]
+        struct RecordingModel {
+            results: Arc<Mutex<Vec<serde_json::Value>>>,
+        }
+
+        impl CompletionModel for RecordingModel {
+            type Response = ();
+
+            async fn completion(
+                &self,
+                request: CompletionRequest,
+            ) -> Result<CompletionResponse<Self::Response>, CompletionError> {
+                for message in &request.chat_history {
+                    if let Message::User { content } = message {
+                        for item in content.iter() {
+                            if let UserContent::ToolResult(result) = item {
+                                self.results.lock().unwrap().push(result.result.clone());
+                            }
+                        }
+                    }
+                }
+                MockModel.completion(request).await
+            }
+        }
+
+        let reported: [(fn() -> ToolCallError, serde_json::Value); 4] = [
+            (
+                || ToolCallError::InvalidArguments {
+                    details: "value must be positive".to_string(),
+                },
+                json!({"error": "Invalid arguments: value must be positive", "retryable": false}),
+            ),
+            (
+                || ToolCallError::NotPermitted {
+                    reason: "read-only session".to_string(),
+                },
+                json!({"error": "Not permitted: read-only session", "retryable": false}),
+            ),
+            (
+                || ToolCallError::Execution {
+                    message: "rate limited".to_string(),
+                    retryable: true,
+                },
+                json!({"error": "Tool execution failed: rate limited", "retryable": true}),
+            ),
+            (
+                || ToolCallError::Timeout,
+                json!({"error": "Tool timed out", "retryable": true}),
+            ),
+        ];
+        for (error, expected) in reported {
+            let model = RecordingModel::default();
+            let agent = AgentBuilder::new(model.clone())
+                .tool(FailingTool(error))
+                .build();
+            let outcome = agent.prompt_detailed("hi").await.unwrap();
+            assert_eq!(outcome.text, "done");
+            assert_eq!(*model.results.lock().unwrap(), vec![expected]);
+            assert_eq!(agent.tool_stats().get("mock_tool").unwrap().errors, 1);
+        }
+
+        // Internal errors, including the old boxed form, fail the run.
+        let agent = AgentBuilder::new(RecordingModel::default())
+            .tool(FailingTool(|| {
+                let source: Box<dyn std::error::Error + Send + Sync> = "index corrupted".into();
+                source.into()
+            }))
+            .build();
+        let err = agent.prompt_detailed("hi").await.unwrap_err();
+        assert!(matches!(err, CompletionError::RequestError(_)));
+        assert!(err.to_string().contains("index corrupted"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_replayed_tool_call_runs_once() {
+        use std::sync::{
+            Mutex,
+            atomic::{AtomicUsize, Ordering},
+        };
+
+        /// Thinks first, then requests `call_1`, then replays the same response as a
+        /// retried request would, then answers.
+        #[derive(Clone, Default)]
+        struct ReplayingModel {
+            keys: Arc<Mutex<Vec<Option<String>>>>,
+        }
+
+        impl CompletionModel for ReplayingModel {
+            type Response = ();
+
+            async fn completion(
+                &self,
+                request: CompletionRequest,
+            ) -> Result<CompletionResponse<Self::Response>, CompletionError> {
+                let round = {
+                    let mut keys = self.keys.lock().unwrap();
+                    keys.push(request.idempotency_key.clone());
+                    keys.len()
+                };
+                let content = match round {
+                    1 => AssistantContent::Reasoning(Reasoning::new("planning")),
+                    2 | 3 => AssistantContent::ToolCall(ToolCall {
+                        id: "call_1".to_string(),
+                        name: "counting_tool".to_string(),
+                        function: Function {
+                            name: "counting_tool".to_string(),
+                            arguments: json!({}),
+                        },
+                    }),
+                    _ => AssistantContent::Text(Text {
+                        text: "done".to_string(),
+                    }),
+                };
+                Ok(CompletionResponse {
+                    content: vec![content],
+                    raw_response: (),
+                })
+            }
+        }
+
+        struct CountingTool(Arc<AtomicUsize>);
+
+        impl Tool for CountingTool {
+            fn name(&self) -> String {
+                "counting_tool".to_string()
+            }
+
+            fn description(&self) -> String {
+                "Counts its calls".to_string()
+            }
+
+            fn definition(&self) -> ToolDefinition {
+                ToolDefinition {
+                    name: self.name(),
+                    description: self.description(),
+                    parameters: json!({ "type": "object", "properties": {} }),
+                }
+            }
+
+            fn call(&self, _args: serde_json::Value) -> Result<serde_json::Value, ToolCallError> {
+                let n = self.0.fetch_add(1, Ordering::SeqCst) + 1;
+                Ok(json!({ "executions": n }))
+            }
+        }
+
+        let executions = Arc::new(AtomicUsize::new(0));
+        let model = ReplayingModel::default();
+        let agent = AgentBuilder::new(model.clone())
+            .tool(CountingTool(executions.clone()))
+            .build();
+        let outcome = agent.prompt_detailed("hi").await.unwrap();
+
+        assert_eq!(outcome.text, "done");
+        assert_eq!(executions.load(Ordering::SeqCst), 1);
+        assert_eq!(outcome.suppressed_tool_calls, 1);
+        assert_eq!(outcome.steps, 2);
+
+        let keys = model
+            .keys
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|key| key.clone().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(keys.len(), 4);
+        // Re-asking after a reasoning-only reply gets a key of its own within the step.
+        assert_eq!(keys[1], format!("{}-r1", keys[0]));
+        assert_ne!(keys[1], keys[2]);
+        assert_ne!(keys[2], keys[3]);
+        let run = keys[0].rsplit_once('-').unwrap().0;
+        assert!(keys.iter().all(|key| key.starts_with(run)));
+    }
+
+    #[tokio::test]
+    async fn test_sampling_params_reach_request() {
+        use std::sync::{Arc, Mutex};
+
+        use crate::internal::ai::completion::SamplingParams;
+
+        #[derive(Clone, Default)]
+        struct CapturingModel {
+            last_request: Arc<Mutex<Option<CompletionRequest>>>,
+        }
+
+        impl CompletionModel for CapturingModel {
+            type Response = ();
+
+            async fn completion(
+                &self,
+                request: CompletionRequest,
+            ) -> Result<CompletionResponse<Self::Response>, CompletionError> {
+                *self.last_request.lock().unwrap() = Some(request);
+                Ok(CompletionResponse {
+                    content: vec![AssistantContent::Text(Text {
+                        text: "ok".to_string(),
+                    })],
+                    raw_response: (),
+                })
+            }
+        }
+
+        let model = CapturingModel::default();
+        let agent = AgentBuilder::new(model.clone())
+            .sampling(SamplingParams {
+                temperature: Some(0.3),
+                top_p: Some(0.9),
+                max_tokens: Some(256),
+                seed: Some(7),
+            })
+            .unwrap()
+            .seed(42)
+            .build();
+
+        Prompt::prompt(&agent, "hi").await.unwrap();
+
+        let request = model.last_request.lock().unwrap().take().unwrap();
+        assert_eq!(request.temperature, Some(0.3));
+        assert_eq!(request.top_p, Some(0.9));
+        assert_eq!(request.max_tokens, Some(256));
+        // The per-field setter called after `sampling` wins.
+        assert_eq!(request.seed, Some(42));
+    }
+
+    #[tokio::test]
+    async fn test_max_steps_allows_exact_tool_call_count() {
+        use std::sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        };
+
+        #[derive(Clone)]
+        struct AlwaysToolCallModel {
+            completions: Arc<AtomicUsize>,
+        }
+
+        impl CompletionModel for AlwaysToolCallModel {
+            type Response = ();
+
+            async fn completion(
+                &self,
+                _request: CompletionRequest,
+            ) -> Result<CompletionResponse<Self::Response>, CompletionError> {
+                let n = self.completions.fetch_add(1, Ordering::SeqCst);
+
+                Ok(CompletionResponse {
+                    content: vec![AssistantContent::ToolCall(ToolCall {
+                        id: format!("call_{n}"),
+                        name: "always_tool".to_string(),
+                        function: Function {
+                            name: "always_tool".to_string(),
+                            arguments: json!({}),
+                        },
+                    })],
+                    raw_response: (),
+                })
+            }
+        }
+
+        #[derive(Clone)]
+        struct CountingTool {
+            calls: Arc<AtomicUsize>,
+        }
+
+        impl Tool for CountingTool {
+            fn name(&self) -> String {
+                "always_tool".to_string()
+            }
+
+            fn description(&self) -> String {
+                "Always returns the same mock value".to_string()
+            }
+
+            fn definition(&self) -> ToolDefinition {
+                ToolDefinition {
+                    name: self.name(),
+                    description: self.description(),
+                    parameters: json!({ "type": "object" }),
+                }
+            }
+
+            fn call(&self, _args: serde_json::Value) -> Result<serde_json::Value, ToolCallError> {
+                self.calls.fetch_add(1, Ordering::SeqCst);
+                Ok(json!({"ok": true}))
+            }
+        }
+
+        let completion_calls = Arc::new(AtomicUsize::new(0));
+        let tool_calls = Arc::new(AtomicUsize::new(0));
+
+        let tool_set = ToolSet::default();
+        tool_set.add(std::sync::Arc::new(CountingTool {
+            calls: tool_calls.clone(),
+        }));
+
+        let model = AlwaysToolCallModel {
+            completions: completion_calls.clone(),
+        };
+        let agent = AgentBuilder::new(model)
+            .tools(tool_set)
+            .max_steps(2)
+            .build();
+
+        let err = Prompt::prompt(&agent, "hi").await.unwrap_err().to_string();
 
         assert_eq!(tool_calls.load(Ordering::SeqCst), 2);
         assert_eq!(completion_calls.load(Ordering::SeqCst), 3);
         assert!(err.contains("max steps"));
     }
+
+    #[derive(Clone)]
+    struct SlowModel {
+        hang: bool,
+    }
+
+    impl CompletionModel for SlowModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            _request: CompletionRequest,
+        ) -> Result<CompletionResponse<Self::Response>, CompletionError> {
+            if self.hang {
+                std::future::pending::<()>().await;
+            }
+            Ok(CompletionResponse {
+                content: vec![AssistantContent::Text(Text {
+                    text: "fast".to_string(),
+                })],
+                raw_response: (),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_request_timeout_then_fallback() {
+        use std::time::{Duration, Instant};
+
+        let timeout = Duration::from_millis(50);
+        let hung = AgentBuilder::new(SlowModel { hang: true })
+            .request_timeout(timeout)
+            .build();
+
+        let started = Instant::now();
+        let err = Prompt::prompt(&hung, "hi").await.unwrap_err();
+        let elapsed = started.elapsed();
+        assert!(matches!(err, CompletionError::Timeout(d) if d == timeout));
+        assert!(
+            elapsed >= timeout && elapsed < Duration::from_secs(2),
+            "{elapsed:?}"
+        );
+        assert!(err.is_retryable());
+
+        let fallback = AgentBuilder::new(SlowModel { hang: false })
+            .request_timeout(timeout)
+            .build();
+        assert_eq!(Prompt::prompt(&fallback, "hi").await.unwrap(), "fast");
+    }
+
+    #[tokio::test]
+    async fn test_best_effort_answer_after_deadline() {
+        use std::time::{Duration, Instant};
+
+        use super::BEST_EFFORT_PROMPT;
+
+        /// Calls `mock_tool` once, then takes far longer than the deadline for every request
+        /// except the best-effort one, which must come without tools.
+        #[derive(Clone)]
+        struct SlowAfterToolModel;
+
+        impl CompletionModel for SlowAfterToolModel {
+            type Response = ();
+
+            async fn completion(
+                &self,
+                request: CompletionRequest,
+            ) -> Result<CompletionResponse<Self::Response>, CompletionError> {
+                let last_text = match request.chat_history.last() {
+                    Some(Message::User { content }) => match content.iter().next() {
+                        Some(UserContent::Text(text)) => text.text.clone(),
+                        _ => String::new(),
+                    },
+                    _ => String::new(),
+                };
+                let text = if last_text == BEST_EFFORT_PROMPT {
+                    assert!(request.tools.is_empty(), "best-effort request offers tools");
+                    "partial answer".to_string()
+                } else if request.chat_history.len() == 1 {
+                    return Ok(CompletionResponse {
+                        content: vec![AssistantContent::ToolCall(ToolCall {
+                            id: "call_1".to_string(),
+                            name: "mock_tool".to_string(),
+                            function: Function {
+                                name: "mock_tool".to_string(),
+                                arguments: json!({"value": 1}),
+                            },
+                        })],
+                        raw_response: (),
+                    });
+                } else {
+                    tokio::time::sleep(Duration::from_secs(30)).await;
+                    "too late".to_string()
+                };
+                Ok(CompletionResponse {
+                    content: vec![AssistantContent::Text(Text { text })],
+                    raw_response: (),
+                })
+            }
+        }
+
+        let agent = AgentBuilder::new(SlowAfterToolModel)
+            .tool(MockTool)
+            .best_effort_deadline(Duration::from_millis(100))
+            .build();
+
+        let started = Instant::now();
+        let outcome = agent.prompt_detailed("investigate").await.unwrap();
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(outcome.text, "partial answer");
+        assert!(outcome.deadline_reached);
+        assert!(outcome.used_tools);
+
+        // Runs that finish in time are unaffected.
+        let quick = AgentBuilder::new(SlowModel { hang: false })
+            .best_effort_deadline(Duration::from_secs(5))
+            .build();
+        let outcome = quick.prompt_detailed("hi").await.unwrap();
+        assert_eq!(outcome.text, "fast");
+        assert!(!outcome.deadline_reached);
+    }
+
+    #[tokio::test]
+    async fn test_deadline_fails_with_partial_result() {
+        use std::time::{Duration, Instant};
+
+        /// Calls `mock_tool` once, optionally narrating, then never answers again.
+        #[derive(Clone)]
+        struct StallingModel {
+            narration: Option<&'static str>,
+        }
+
+        impl CompletionModel for StallingModel {
+            type Response = ();
+
+            async fn completion(
+                &self,
+                request: CompletionRequest,
+            ) -> Result<CompletionResponse<Self::Response>, CompletionError> {
+                if request.chat_history.len() > 1 {
+                    std::future::pending::<()>().await;
+                }
+                let mut content: Vec<AssistantContent> = self
+                    .narration
+                    .map(|text| {
+                        AssistantContent::Text(Text {
+                            text: text.to_string(),
+                        })
+                    })
+                    .into_iter()
+                    .collect();
+                content.push(AssistantContent::ToolCall(ToolCall {
+                    id: "call_1".to_string(),
+                    name: "mock_tool".to_string(),
+                    function: Function {
+                        name: "mock_tool".to_string(),
+                        arguments: json!({"value": 1}),
+                    },
+                }));
+                Ok(CompletionResponse {
+                    content,
+                    raw_response: (),
+                })
+            }
+        }
+
+        let limit = Duration::from_millis(100);
+        let run = |narration| async move {
+            let agent = AgentBuilder::new(StallingModel { narration })
+                .tool(MockTool)
+                .deadline(limit)
+                .build();
+            let started = Instant::now();
+            let err = Prompt::prompt(&agent, "investigate").await.unwrap_err();
+            let elapsed = started.elapsed();
+            assert!(
+                elapsed >= limit && elapsed < Duration::from_secs(2),
+                "{elapsed:?}"
+            );
+            assert!(!err.is_retryable());
+            err
+        };
+
+        let err = run(Some("Checking the value first.")).await;
+        assert!(
+            matches!(&err, CompletionError::DeadlineExceeded { limit: l, .. } if *l == limit),
+            "{err:?}"
+        );
+        assert_eq!(err.partial_result(), Some("Checking the value first."));
+
+        let err = run(None).await;
+        assert_eq!(
+            err.partial_result(),
+            Some("Completed 1 tool call(s): mock_tool")
+        );
+
+        // Runs that finish in time are unaffected.
+        let quick = AgentBuilder::new(SlowModel { hang: false })
+            .deadline(Duration::from_secs(5))
+            .build();
+        assert_eq!(Prompt::prompt(&quick, "hi").await.unwrap(), "fast");
+    }
+
+    #[tokio::test]
+    async fn test_deadline_follows_injected_clock() {
+        use std::time::Duration;
+
+        use crate::internal::ai::clock::ManualClock;
+
+        /// Takes ten seconds of the manual clock per completion, and always calls `mock_tool`
+        /// under a new call id.
+        #[derive(Clone)]
+        struct SlowModel(ManualClock);
+
+        impl CompletionModel for SlowModel {
+            type Response = ();
+
+            async fn completion(
+                &self,
+                request: CompletionRequest,
+            ) -> Result<CompletionResponse<Self::Response>, CompletionError> {
+                self.0.advance(Duration::from_secs(10));
+                Ok(CompletionResponse {
+                    content: vec![AssistantContent::ToolCall(ToolCall {
+                        id: format!("call_{}", request.chat_history.len()),
+                        name: "mock_tool".to_string(),
+                        function: Function {
+                            name: "mock_tool".to_string(),
+                            arguments: json!({"value": 1}),
+                        },
+                    })],
+                    raw_response: (),
+                })
+            }
+        }
+
+        let clock = ManualClock::new();
+        let agent = AgentBuilder::new(SlowModel(clock.clone()))
+            .tool(MockTool)
+            .max_steps(10)
+            .deadline(Duration::from_secs(25))
+            .clock(clock)
+            .build();
+
+        // The third completion ends at 30s, so its tool calls are never run.
+        let err = Prompt::prompt(&agent, "investigate").await.unwrap_err();
+        assert_eq!(
+            err.partial_result(),
+            Some("Completed 2 tool call(s): mock_tool, mock_tool"),
+            "{err:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reflection_returns_improved_answer() {
+        use std::sync::Mutex;
+
+        use super::REFLECTION_PROMPT;
+
+        /// Drafts an answer, then improves it when asked to reflect on the draft.
+        #[derive(Clone, Default)]
+        struct ReflectingModel {
+            requests: Arc<Mutex<Vec<CompletionRequest>>>,
+        }
+
+        impl CompletionModel for ReflectingModel {
+            type Response = ();
+
+            async fn completion(
+                &self,
+                request: CompletionRequest,
+            ) -> Result<CompletionResponse<Self::Response>, CompletionError> {
+                let reflecting = matches!(
+                    request.chat_history.last(),
+                    Some(Message::User { content }) if matches!(
+                        content.iter().next(),
+                        Some(UserContent::Text(text)) if text.text == REFLECTION_PROMPT
+                    )
+                );
+                self.requests.lock().unwrap().push(request);
+                let text = if reflecting { "improved" } else { "draft" };
+                Ok(CompletionResponse {
+                    content: vec![AssistantContent::Text(Text {
+                        text: text.to_string(),
+                    })],
+                    raw_response: (),
+                })
+            }
+        }
+
+        let model = ReflectingModel::default();
+        let agent = AgentBuilder::new(model.clone())
+            .tool(MockTool)
+            .reflect(true)
+            .build();
+        let outcome = agent.prompt_detailed("explain").await.unwrap();
+        assert_eq!(outcome.text, "improved");
+
+        let requests = model.requests.lock().unwrap().clone();
+        assert_eq!(requests.len(), 2);
+        let reflection = &requests[1];
+        assert!(
+            reflection.tools.is_empty(),
+            "reflection request offers tools"
+        );
+        assert_eq!(reflection.chat_history.len(), 3);
+        assert!(matches!(
+            &reflection.chat_history[1],
+            Message::Assistant { content, .. } if matches!(
+                content.iter().next(),
+                Some(AssistantContent::Text(text)) if text.text == "draft"
+            )
+        ));
+
+        // Without the flag the draft is the answer.
+        let plain = AgentBuilder::new(ReflectingModel::default()).build();
+        assert_eq!(
+            plain.prompt_detailed("explain").await.unwrap().text,
+            "draft"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_prompt_many_bounds_concurrency() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        /// Echoes the prompt after a short delay, failing prompts that say "fail", and
+        /// records the most requests it ever had in flight.
+        #[derive(Clone, Default)]
+        struct EchoModel {
+            in_flight: Arc<AtomicUsize>,
+            peak: Arc<AtomicUsize>,
+        }
+
+        impl CompletionModel for EchoModel {
+            type Response = ();
+
+            async fn completion(
+                &self,
+                request: CompletionRequest,
+            ) -> Result<CompletionResponse<Self::Response>, CompletionError> {
+                let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                self.peak.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+                self.in_flight.fetch_sub(1, Ordering::SeqCst);
+
+                let prompt = match request.chat_history.last() {
+                    Some(Message::User { content }) => match content.iter().next() {
+                        Some(UserContent::Text(text)) => text.text.clone(),
+                        _ => String::new(),
+                    },
+                    _ => String::new(),
+                };
+                if prompt == "fail" {
+                    return Err(CompletionError::ResponseError("refused".to_string()));
+                }
+                Ok(CompletionResponse {
+                    content: vec![AssistantContent::Text(Text {
+                        text: format!("echo {prompt}"),
+                    })],
+                    raw_response: (),
+                })
+            }
+        }
+
+        let model = EchoModel::default();
+        let agent = AgentBuilder::new(model.clone()).build();
+        let results = agent.prompt_many(["a", "b", "fail", "c", "d"], 2).await;
+
+        assert_eq!(results.len(), 5);
+        assert_eq!(results[0].as_ref().unwrap(), "echo a");
+        assert_eq!(results[1].as_ref().unwrap(), "echo b");
+        assert!(results[2].is_err());
+        assert_eq!(results[3].as_ref().unwrap(), "echo c");
+        assert_eq!(results[4].as_ref().unwrap(), "echo d");
+        assert_eq!(model.peak.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_reasoning_only_turn_then_text() {
+        /// Thinks without answering first, then answers on the follow-up request.
+        #[derive(Clone)]
+        struct ThinkingModel {
+            calls: Arc<std::sync::atomic::AtomicUsize>,
+        }
+
+        impl CompletionModel for ThinkingModel {
+            type Response = ();
+
+            async fn completion(
+                &self,
+                _request: CompletionRequest,
+            ) -> Result<CompletionResponse<Self::Response>, CompletionError> {
+                let call = self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                let content = if call == 0 {
+                    vec![AssistantContent::Reasoning(Reasoning::new("step one"))]
+                } else {
+                    vec![AssistantContent::Text(Text {
+                        text: "answer".to_string(),
+                    })]
+                };
+                Ok(CompletionResponse {
+                    content,
+                    raw_response: (),
+                })
+            }
+        }
+
+        let model = || ThinkingModel {
+            calls: Arc::new(std::sync::atomic::AtomicUsize::new(0)),
+        };
+
+        let outcome = AgentBuilder::new(model())
+            .build()
+            .prompt_detailed("hi")
+            .await
+            .unwrap();
+        assert_eq!(outcome.text, "answer");
+        assert_eq!(outcome.reasoning, vec!["step one"]);
+        assert!(!outcome.used_tools);
+
+        let debug = AgentBuilder::new(model()).include_reasoning(true).build();
+        assert_eq!(
+            Prompt::prompt(&debug, "hi").await.unwrap(),
+            "<reasoning>\nstep one\n</reasoning>\n\nanswer"
+        );
+    }
+
+    #[test]
+    fn test_validate_reports_every_issue() {
+        let agent = AgentBuilder::new(MockModel)
+            .preamble("Always use the tools to look things up.")
+            .build();
+        assert_eq!(agent.validate(), Err(vec![ConfigIssue::PreambleNeedsTools]));
+
+        let mut agent = AgentBuilder::new(MockModel)
+            .preamble("Always use the tools to look things up.")
+            .tool(MockTool)
+            .tool(MockTool)
+            .tool(MockTool)
+            .build();
+        agent.temperature = Some(3.5);
+        assert_eq!(
+            agent.validate(),
+            Err(vec![
+                ConfigIssue::TemperatureOutOfRange(3.5),
+                ConfigIssue::DuplicateTool("mock_tool".to_string()),
+            ])
+        );
+
+        let agent = AgentBuilder::new(MockModel)
+            .preamble("Answer briefly.")
+            .tool(MockTool)
+            .build();
+        assert_eq!(agent.validate(), Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_thought_only_answer() {
+        /// Never gets past thinking.
+        #[derive(Clone)]
+        struct ThoughtOnlyModel;
+
+        impl CompletionModel for ThoughtOnlyModel {
+            type Response = ();
+
+            async fn completion(
+                &self,
+                _request: CompletionRequest,
+            ) -> Result<CompletionResponse<Self::Response>, CompletionError> {
+                Ok(CompletionResponse {
+                    content: vec![AssistantContent::Reasoning(Reasoning::new(
+                        "still thinking",
+                    ))],
+                    raw_response: (),
+                })
+            }
+        }
+
+        let err = AgentBuilder::new(ThoughtOnlyModel)
+            .max_steps(2)
+            .build()
+            .prompt_detailed("hi")
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("only reasoning"), "{err}");
+
+        // Without `max_steps` the retries still stop.
+        let mut unbounded = AgentBuilder::new(ThoughtOnlyModel).build();
+        unbounded.max_steps = None;
+        let err = unbounded.prompt_detailed("hi").await.unwrap_err();
+        assert!(
+            err.to_string().contains(&format!(
+                "{} consecutive rounds",
+                super::MAX_REASONING_ONLY_ROUNDS
+            )),
+            "{err}"
+        );
+
+        let empty = AgentBuilder::new(ThoughtOnlyModel)
+            .thought_only_answer(ThoughtOnlyAnswer::Empty)
+            .build()
+            .prompt_detailed("hi")
+            .await
+            .unwrap();
+        assert_eq!(empty.text, "");
+        assert_eq!(empty.reasoning, vec!["still thinking"]);
+
+        let thought = AgentBuilder::new(ThoughtOnlyModel)
+            .thought_only_answer(ThoughtOnlyAnswer::Thought)
+            .build();
+        assert_eq!(
+            Prompt::prompt(&thought, "hi").await.unwrap(),
+            "still thinking"
+        );
+    }
+
+    /// Requests three new `mock_tool` calls in every response.
+    #[derive(Clone)]
+    struct BurstModel;
+
+    impl CompletionModel for BurstModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            request: CompletionRequest,
+        ) -> Result<CompletionResponse<Self::Response>, CompletionError> {
+            let round = request.chat_history.len();
+            let content = (1..=3)
+                .map(|i| {
+                    AssistantContent::ToolCall(ToolCall {
+                        id: format!("call_{round}_{i}"),
+                        name: "mock_tool".to_string(),
+                        function: Function {
+                            name: "mock_tool".to_string(),
+                            arguments: json!({"value": i}),
+                        },
+                    })
+                })
+                .collect();
+            Ok(CompletionResponse {
+                content,
+                raw_response: (),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_max_tool_calls_rejects_the_crossing_step() {
+        let tool_set = ToolSet::default();
+        tool_set.add(Arc::new(MockTool));
+
+        let agent = AgentBuilder::new(BurstModel)
+            .tools(tool_set)
+            .max_steps(10)
+            .max_tool_calls(4)
+            .build();
+        let err = Prompt::prompt(&agent, "hi").await.unwrap_err();
+
+        assert!(
+            err.to_string().contains("exceeded max tool calls (4)"),
+            "{err}"
+        );
+        // The first step's three calls fit; the second step would bring the total to six,
+        // so none of its calls run, not even the one still under the limit.
+        assert_eq!(agent.tool_stats().get("mock_tool").unwrap().calls, 3);
+    }
+
+    #[tokio::test]
+    async fn test_missing_tool_runs_no_call_of_its_step() {
+        /// Calls `mock_tool` and an unknown tool in one response.
+        #[derive(Clone)]
+        struct MixedModel;
+
+        impl CompletionModel for MixedModel {
+            type Response = ();
+
+            async fn completion(
+                &self,
+                _request: CompletionRequest,
+            ) -> Result<CompletionResponse<Self::Response>, CompletionError> {
+                let content = ["mock_tool", "missing_tool"]
+                    .into_iter()
+                    .map(|name| {
+                        AssistantContent::ToolCall(ToolCall {
+                            id: format!("call_{name}"),
+                            name: name.to_string(),
+                            function: Function {
+                                name: name.to_string(),
+                                arguments: json!({"value": 1}),
+                            },
+                        })
+                    })
+                    .collect();
+                Ok(CompletionResponse {
+                    content,
+                    raw_response: (),
+                })
+            }
+        }
+
+        let tool_set = ToolSet::default();
+        tool_set.add(Arc::new(MockTool));
+        let agent = AgentBuilder::new(MixedModel).tools(tool_set).build();
+        let err = Prompt::prompt(&agent, "hi").await.unwrap_err();
+
+        assert!(
+            err.to_string().contains("Tool not found: missing_tool"),
+            "{err}"
+        );
+        assert!(agent.tool_stats().get("mock_tool").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_tool_concurrency_limit_serializes_one_tool() {
+        use std::{
+            sync::atomic::{AtomicUsize, Ordering},
+            time::Duration,
+        };
+
+        /// Sleeps briefly and records the most calls it saw running at once.
+        struct SlowTool {
+            name: &'static str,
+            running: AtomicUsize,
+            peak: Arc<AtomicUsize>,
+        }
+
+        impl Tool for SlowTool {
+            fn name(&self) -> String {
+                self.name.to_string()
+            }
+
+            fn description(&self) -> String {
+                "Slow tool".to_string()
+            }
+
+            fn definition(&self) -> ToolDefinition {
+                ToolDefinition {
+                    name: self.name(),
+                    description: self.description(),
+                    parameters: json!({"type": "object", "properties": {}}),
+                }
+            }
+
+            fn call(&self, _args: serde_json::Value) -> Result<serde_json::Value, ToolCallError> {
+                let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+                self.peak.fetch_max(running, Ordering::SeqCst);
+                std::thread::sleep(Duration::from_millis(50));
+                self.running.fetch_sub(1, Ordering::SeqCst);
+                Ok(json!({"ok": true}))
+            }
+        }
+
+        /// Calls `capped` and `free` three times each in its first response.
+        #[derive(Clone)]
+        struct FanOutModel;
+
+        impl CompletionModel for FanOutModel {
+            type Response = ();
+
+            async fn completion(
+                &self,
+                request: CompletionRequest,
+            ) -> Result<CompletionResponse<Self::Response>, CompletionError> {
+                if request.chat_history.len() > 1 {
+                    return Ok(CompletionResponse {
+                        content: vec![AssistantContent::Text(Text {
+                            text: "done".to_string(),
+                        })],
+                        raw_response: (),
+                    });
+                }
+                let content = ["capped", "free"]
+                    .iter()
+                    .flat_map(|name| (1..=3).map(move |i| (name, i)))
+                    .map(|(name, i)| {
+                        AssistantContent::ToolCall(ToolCall {
+                            id: format!("{name}_{i}"),
+                            name: name.to_string(),
+                            function: Function {
+                                name: name.to_string(),
+                                arguments: json!({}),
+                            },
+                        })
+                    })
+                    .collect();
+                Ok(CompletionResponse {
+                    content,
+                    raw_response: (),
+                })
+            }
+        }
+
+        let capped_peak = Arc::new(AtomicUsize::new(0));
+        let free_peak = Arc::new(AtomicUsize::new(0));
+        let agent = AgentBuilder::new(FanOutModel)
+            .tool(SlowTool {
+                name: "capped",
+                running: AtomicUsize::new(0),
+                peak: capped_peak.clone(),
+            })
+            .tool(SlowTool {
+                name: "free",
+                running: AtomicUsize::new(0),
+                peak: free_peak.clone(),
+            })
+            .tool_concurrency_limit("capped", 1)
+            .build();
+        let answer = Prompt::prompt(&agent, "hi").await.unwrap();
+
+        assert_eq!(answer, "done");
+        assert_eq!(capped_peak.load(Ordering::SeqCst), 1);
+        assert!(free_peak.load(Ordering::SeqCst) > 1);
+        assert_eq!(agent.tool_stats().get("capped").unwrap().calls, 3);
+        assert_eq!(agent.tool_stats().get("free").unwrap().calls, 3);
+    }
+
+    #[tokio::test]
+    async fn test_capability_preflight_strict_and_degrade() {
+        /// A model without tool calling that reports how many tools it was sent.
+        #[derive(Clone)]
+        struct TextOnlyModel;
+
+        impl CompletionModel for TextOnlyModel {
+            type Response = ();
+
+            fn capabilities(&self) -> ModelCapabilities {
+                ModelCapabilities {
+                    supports_tools: false,
+                    ..ModelCapabilities::default()
+                }
+            }
+
+            async fn completion(
+                &self,
+                request: CompletionRequest,
+            ) -> Result<CompletionResponse<Self::Response>, CompletionError> {
+                Ok(CompletionResponse {
+                    content: vec![AssistantContent::Text(Text {
+                        text: format!("saw {} tools", request.tools.len()),
+                    })],
+                    raw_response: (),
+                })
+            }
+        }
+
+        let tools = || {
+            let tool_set = ToolSet::default();
+            for _ in 0..3 {
+                tool_set.add(Arc::new(MockTool));
+            }
+            tool_set
+        };
+
+        let strict = AgentBuilder::new(TextOnlyModel).tools(tools()).build();
+        let err = Prompt::prompt(&strict, "hi").await.unwrap_err();
+        assert!(matches!(err, CompletionError::Unsupported(_)));
+        assert!(
+            err.to_string()
+                .contains("model does not support tool calling; 3 tools configured"),
+            "{err}"
+        );
+
+        let degraded = AgentBuilder::new(TextOnlyModel)
+            .tools(tools())
+            .capability_policy(CapabilityPolicy::Degrade)
+            .build();
+        assert_eq!(
+            Prompt::prompt(&degraded, "hi").await.unwrap(),
+            "saw 0 tools"
+        );
+
+        // Models with the default capabilities are unaffected.
+        let agent = AgentBuilder::new(MockModel).tools(tools()).build();
+        assert_eq!(Prompt::prompt(&agent, "hi").await.unwrap(), "done");
+    }
+
+    #[tokio::test]
+    async fn test_tool_definition_override_hides_field() {
+        use std::sync::Mutex;
+
+        /// Calls `lookup` with the hidden `verbose` flag, then answers with the tool result.
+        #[derive(Clone, Default)]
+        struct LookupModel {
+            offered: Arc<Mutex<Vec<ToolDefinition>>>,
+        }
+
+        impl CompletionModel for LookupModel {
+            type Response = ();
+
+            async fn completion(
+                &self,
+                request: CompletionRequest,
+            ) -> Result<CompletionResponse<Self::Response>, CompletionError> {
+                *self.offered.lock().unwrap() = request.tools.clone();
+                let result = request.chat_history.iter().find_map(|msg| match msg {
+                    Message::User { content } => content.iter().find_map(|c| match c {
+                        UserContent::ToolResult(result) => Some(result.result.to_string()),
+                        _ => None,
+                    }),
+                    _ => None,
+                });
+                let content = match result {
+                    Some(text) => AssistantContent::Text(Text { text }),
+                    None => AssistantContent::ToolCall(ToolCall {
+                        id: "call_1".to_string(),
+                        name: "lookup".to_string(),
+                        function: Function {
+                            name: "lookup".to_string(),
+                            arguments: json!({"key": "a", "verbose": true}),
+                        },
+                    }),
+                };
+                Ok(CompletionResponse {
+                    content: vec![content],
+                    raw_response: (),
+                })
+            }
+        }
+
+        struct LookupTool;
+
+        impl Tool for LookupTool {
+            fn definition(&self) -> ToolDefinition {
+                ToolDefinition {
+                    name: "lookup".to_string(),
+                    description: "Look up a key".to_string(),
+                    parameters: json!({
+                        "type": "object",
+                        "properties": {
+                            "key": { "type": "string" },
+                            "verbose": { "type": "boolean" }
+                        }
+                    }),
+                }
+            }
+
+            fn call(&self, args: serde_json::Value) -> Result<serde_json::Value, ToolCallError> {
+                Ok(json!({ "key": args["key"], "verbose": args["verbose"] }))
+            }
+        }
+
+        let model = LookupModel::default();
+        let offered = Arc::clone(&model.offered);
+        let agent = AgentBuilder::new(model)
+            .tool_with_definition(
+                LookupTool,
+                ToolDefinition {
+                    name: "ignored".to_string(),
+                    description: "Look up a key".to_string(),
+                    parameters: json!({
+                        "type": "object",
+                        "properties": { "key": { "type": "string" } }
+                    }),
+                },
+            )
+            .build();
+        let response = Prompt::prompt(&agent, "look up a").await.unwrap();
+
+        let offered = offered.lock().unwrap().clone();
+        assert_eq!(offered.len(), 1);
+        assert_eq!(offered[0].name, "lookup");
+        assert!(offered[0].parameters["properties"].get("verbose").is_none());
+        // The real tool still received and handled the hidden field.
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&response).unwrap(),
+            json!({"key": "a", "verbose": true})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_refusal_ends_run_without_running_tools() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        use super::step_history::{AGENT_STEP_TYPE, AgentStepRecord};
+        use crate::{
+            internal::ai::history::HistoryManager,
+            utils::{storage::local::LocalStorage, storage_ext::StorageExt},
+        };
+
+        /// Refuses, with a tool call alongside that must not run.
+        #[derive(Clone, Default)]
+        struct RefusingModel(Arc<AtomicUsize>);
+
+        impl CompletionModel for RefusingModel {
+            type Response = ();
+
+            async fn completion(
+                &self,
+                _request: CompletionRequest,
+            ) -> Result<CompletionResponse<Self::Response>, CompletionError> {
+                self.0.fetch_add(1, Ordering::SeqCst);
+                Ok(CompletionResponse {
+                    content: vec![
+                        AssistantContent::Refusal {
+                            message: "I can't help with that.".to_string(),
+                        },
+                        AssistantContent::ToolCall(ToolCall {
+                            id: "call_1".to_string(),
+                            name: "mock_tool".to_string(),
+                            function: Function {
+                                name: "mock_tool".to_string(),
+                                arguments: json!({"value": 1}),
+                            },
+                        }),
+                    ],
+                    raw_response: (),
+                })
+            }
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let repo_path = dir.path().join(".libra");
+        std::fs::create_dir(&repo_path).unwrap();
+        let storage = Arc::new(LocalStorage::new(repo_path.join("objects")));
+        let history = Arc::new(HistoryManager::new(storage.clone(), repo_path));
+
+        let model = RefusingModel::default();
+        let tool_set = ToolSet::default();
+        tool_set.add(Arc::new(MockTool));
+        let agent = AgentBuilder::new(model.clone())
+            .tools(tool_set)
+            .record_steps(history.clone())
+            .build();
+        let error = agent.prompt_detailed("hi").await.unwrap_err();
+
+        assert!(
+            matches!(&error, CompletionError::Refused { message } if message == "I can't help with that."),
+            "{error:?}"
+        );
+        assert!(!error.is_retryable());
+        assert_eq!(model.0.load(Ordering::SeqCst), 1);
+
+        let records = history.list_objects(AGENT_STEP_TYPE).await.unwrap();
+        assert_eq!(records.len(), 1);
+        let record = storage
+            .get_json::<AgentStepRecord>(&records[0].1)
+            .await
+            .unwrap();
+        assert_eq!(record.response, "[refused] I can't help with that.");
+        assert!(record.tool_calls.is_empty());
+    }
 }
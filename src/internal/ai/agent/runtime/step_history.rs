@@ -0,0 +1,130 @@
+//! Persisting the steps of an agent run to the AI history branch.
+//!
+//! With [`AgentBuilder::record_steps`](super::AgentBuilder::record_steps), every model
+//! response an agent acts on (a tool-calling round or the final answer) is stored through
+//! [`StorageExt::put_tracked`] as one [`AgentStepRecord`] under
+//! `agent_step/<run>-<step>` of the manager's branch. Runs are not recorded unless a
+//! [`HistoryManager`] is configured.
+//!
+//! Commands that care about a run's outcome rather than its steps store one
+//! [`AgentRunRecord`] under `agent_run/<run>` instead, optionally linked to the commit the
+//! run was about.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    internal::ai::{
+        completion::message::{Message, UserContent},
+        history::HistoryManager,
+    },
+    utils::storage_ext::{Identifiable, StorageExt},
+};
+
+/// Object type of [`AgentStepRecord`]s on the history branch.
+pub const AGENT_STEP_TYPE: &str = "agent_step";
+
+/// Object type of [`AgentRunRecord`]s on the history branch.
+pub const AGENT_RUN_TYPE: &str = "agent_run";
+
+/// Longest prompt excerpt kept in a [`RequestSummary`], in characters.
+const PROMPT_EXCERPT_CHARS: usize = 500;
+
+/// What the model was asked in one step, without repeating the whole conversation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RequestSummary {
+    /// Messages in the request's chat history.
+    pub messages: usize,
+    /// Names of the tools the request offered.
+    pub tools: Vec<String>,
+    /// Start of the latest user text in the history, if any.
+    pub prompt: Option<String>,
+}
+
+impl RequestSummary {
+    pub(crate) fn new(chat_history: &[Message], tools: Vec<String>) -> Self {
+        let prompt = chat_history.iter().rev().find_map(|message| match message {
+            Message::User { content } => content.iter().find_map(|item| match item {
+                UserContent::Text(text) => {
+                    Some(text.text.chars().take(PROMPT_EXCERPT_CHARS).collect())
+                }
+                _ => None,
+            }),
+            _ => None,
+        });
+        Self {
+            messages: chat_history.len(),
+            tools,
+            prompt,
+        }
+    }
+}
+
+/// One tool call of a step and what it returned.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedToolCall {
+    pub id: String,
+    pub name: String,
+    pub arguments: serde_json::Value,
+    pub result: serde_json::Value,
+}
+
+/// A completed step of an agent run, as stored on the history branch.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AgentStepRecord {
+    /// Identifies the run; shared by all of its steps.
+    pub run_id: String,
+    /// Position of the step within the run, starting at 1.
+    pub step: usize,
+    pub request: RequestSummary,
+    /// Text the model returned in this step (empty for a bare tool call).
+    pub response: String,
+    /// Tool calls of the step with their results; empty for the final answer.
+    pub tool_calls: Vec<RecordedToolCall>,
+}
+
+impl Identifiable for AgentStepRecord {
+    fn object_id(&self) -> String {
+        format!("{}-{}", self.run_id, self.step)
+    }
+    fn object_type(&self) -> String {
+        AGENT_STEP_TYPE.to_string()
+    }
+}
+
+/// The outcome of a whole agent run, as stored on the history branch.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AgentRunRecord {
+    pub run_id: String,
+    /// Name of the profile the run used, if any.
+    pub profile: Option<String>,
+    /// The run's final answer.
+    pub answer: String,
+    /// Commit the run is about, once it exists.
+    pub commit: Option<String>,
+    /// Task the run worked on, if any.
+    #[serde(default)]
+    pub task: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Identifiable for AgentRunRecord {
+    fn object_id(&self) -> String {
+        self.run_id.clone()
+    }
+    fn object_type(&self) -> String {
+        AGENT_RUN_TYPE.to_string()
+    }
+}
+
+/// Store `record` on the branch of `history`. A failed write is logged and does not fail
+/// the run.
+pub(crate) async fn persist_step(history: &HistoryManager, record: &AgentStepRecord) {
+    if let Err(e) = history.get_storage().put_tracked(record, history).await {
+        tracing::warn!(
+            run_id = %record.run_id,
+            step = record.step,
+            "failed to record agent step: {e}"
+        );
+    }
+}
@@ -0,0 +1,194 @@
+//! Token and model-call budgets shared between concurrently running agents.
+//!
+//! A [`SharedBudget`] is cheap to clone; every clone draws from the same counters. Attach one
+//! budget to several [`AgentAction`](crate::internal::ai::AgentAction) or
+//! [`ToolLoopAction`](crate::internal::ai::ToolLoopAction) nodes (or plain agents) to cap the
+//! combined spend of independent DAG branches running in parallel.
+//!
+//! Every completion call reserves against the budget before it is sent. Once a limit is
+//! reached, further reservations fail with [`CompletionError::BudgetExceeded`]; calls that were
+//! already reserved still run to completion and record their tokens. Token usage is only known
+//! after a call returns, so the token limit may be overshot by the calls in flight when it is
+//! reached.
+
+use std::{
+    fmt,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use crate::internal::ai::completion::{CompletionError, Usage};
+
+/// The quantity a [`SharedBudget`] ran out of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BudgetResource {
+    /// Model completion calls.
+    Calls,
+    /// Input plus output tokens, as reported by the provider.
+    Tokens,
+}
+
+impl fmt::Display for BudgetResource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BudgetResource::Calls => write!(f, "model calls"),
+            BudgetResource::Tokens => write!(f, "tokens"),
+        }
+    }
+}
+
+/// Point-in-time view of a [`SharedBudget`]. `None` limits are unlimited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BudgetSnapshot {
+    pub calls_used: u64,
+    pub tokens_used: u64,
+    pub max_calls: Option<u64>,
+    pub max_tokens: Option<u64>,
+}
+
+impl BudgetSnapshot {
+    /// Calls that may still be reserved, or `None` if calls are unlimited.
+    pub fn calls_remaining(&self) -> Option<u64> {
+        self.max_calls
+            .map(|limit| limit.saturating_sub(self.calls_used))
+    }
+
+    /// Tokens left before the limit, or `None` if tokens are unlimited.
+    pub fn tokens_remaining(&self) -> Option<u64> {
+        self.max_tokens
+            .map(|limit| limit.saturating_sub(self.tokens_used))
+    }
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    calls: AtomicU64,
+    tokens: AtomicU64,
+}
+
+/// Token and call limits shared by every clone; see the [module docs](self).
+#[derive(Debug, Clone, Default)]
+pub struct SharedBudget {
+    max_calls: Option<u64>,
+    max_tokens: Option<u64>,
+    counters: Arc<Counters>,
+}
+
+impl SharedBudget {
+    /// An unlimited budget; add limits with [`with_max_calls`](Self::with_max_calls) and
+    /// [`with_max_tokens`](Self::with_max_tokens).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Caps the number of completion calls across all holders of the budget.
+    pub fn with_max_calls(mut self, max_calls: u64) -> Self {
+        self.max_calls = Some(max_calls);
+        self
+    }
+
+    /// Caps the tokens used across all holders of the budget.
+    pub fn with_max_tokens(mut self, max_tokens: u64) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    /// Reserves one completion call; see [`reserve_calls`](Self::reserve_calls).
+    pub fn reserve_call(&self) -> Result<(), CompletionError> {
+        self.reserve_calls(1)
+    }
+
+    /// Reserves `count` completion calls at once, or none of them.
+    ///
+    /// Fails with [`CompletionError::BudgetExceeded`] if the token limit has been reached or
+    /// the calls would exceed the call limit.
+    pub fn reserve_calls(&self, count: u64) -> Result<(), CompletionError> {
+        if let Some(limit) = self.max_tokens {
+            let used = self.counters.tokens.load(Ordering::Acquire);
+            if used >= limit {
+                return Err(CompletionError::BudgetExceeded {
+                    resource: BudgetResource::Tokens,
+                    used,
+                    limit,
+                });
+            }
+        }
+        let Some(limit) = self.max_calls else {
+            self.counters.calls.fetch_add(count, Ordering::AcqRel);
+            return Ok(());
+        };
+        self.counters
+            .calls
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |used| {
+                used.checked_add(count).filter(|total| *total <= limit)
+            })
+            .map(|_| ())
+            .map_err(|used| CompletionError::BudgetExceeded {
+                resource: BudgetResource::Calls,
+                used,
+                limit,
+            })
+    }
+
+    /// Records the tokens a finished call used.
+    pub fn record(&self, usage: Usage) {
+        self.counters
+            .tokens
+            .fetch_add(usage.total_tokens(), Ordering::AcqRel);
+    }
+
+    /// Current usage and limits.
+    pub fn snapshot(&self) -> BudgetSnapshot {
+        BudgetSnapshot {
+            calls_used: self.counters.calls.load(Ordering::Acquire),
+            tokens_used: self.counters.tokens.load(Ordering::Acquire),
+            max_calls: self.max_calls,
+            max_tokens: self.max_tokens,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget_limits_calls_and_tokens() {
+        let budget = SharedBudget::new().with_max_calls(3).with_max_tokens(100);
+        let clone = budget.clone();
+
+        budget.reserve_call().unwrap();
+        clone.reserve_calls(2).unwrap();
+        assert!(matches!(
+            budget.reserve_call(),
+            Err(CompletionError::BudgetExceeded {
+                resource: BudgetResource::Calls,
+                used: 3,
+                limit: 3,
+            })
+        ));
+        assert_eq!(budget.snapshot().calls_remaining(), Some(0));
+
+        let tokens = SharedBudget::new().with_max_tokens(100);
+        tokens.reserve_call().unwrap();
+        tokens.record(Usage {
+            input_tokens: 80,
+            output_tokens: 30,
+        });
+        let snapshot = tokens.snapshot();
+        assert_eq!(
+            (snapshot.tokens_used, snapshot.tokens_remaining()),
+            (110, Some(0))
+        );
+        assert_eq!(snapshot.calls_remaining(), None);
+        assert!(matches!(
+            tokens.reserve_call(),
+            Err(CompletionError::BudgetExceeded {
+                resource: BudgetResource::Tokens,
+                ..
+            })
+        ));
+    }
+}
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use reqwest::Client as HttpClient;
 
 use crate::internal::ai::completion::CompletionModel;
@@ -27,24 +29,38 @@ impl<P> Client<P> {
     /// - 30 seconds timeout
     /// - System proxy support (from environment variables)
     pub fn new(base_url: &str, provider: P) -> Self {
-        // Build client with timeout and system proxy support
-        let http_client = HttpClient::builder()
-            .timeout(std::time::Duration::from_secs(30))
-            .build()
-            .unwrap_or_else(|e| {
-                tracing::warn!(
-                    "Failed to build HTTP client with timeout: {}. Using default client.",
-                    e
-                );
-                HttpClient::new()
-            });
-
         Self {
             base_url: base_url.to_string(),
-            http_client,
+            http_client: build_http_client(DEFAULT_REQUEST_TIMEOUT),
             provider,
         }
     }
+
+    /// Replaces the default 30 second HTTP timeout.
+    ///
+    /// The timeout covers the whole request, from connecting until the response body
+    /// has been read.
+    pub fn with_request_timeout(mut self, timeout: Duration) -> Self {
+        self.http_client = build_http_client(timeout);
+        self
+    }
+}
+
+/// Default HTTP timeout for provider requests.
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Build an HTTP client with the given timeout and system proxy support.
+fn build_http_client(timeout: Duration) -> HttpClient {
+    HttpClient::builder()
+        .timeout(timeout)
+        .build()
+        .unwrap_or_else(|e| {
+            tracing::warn!(
+                "Failed to build HTTP client with timeout: {}. Using default client.",
+                e
+            );
+            HttpClient::new()
+        })
 }
 
 /// Trait defining provider-specific behavior.
@@ -0,0 +1,87 @@
+//! The time source agents and tool loops measure their deadlines and timeouts against.
+//!
+//! [`AgentBuilder::clock`](crate::internal::ai::AgentBuilder::clock) and
+//! [`ToolLoopConfig::clock`](crate::internal::ai::agent::ToolLoopConfig::clock) default to
+//! [`SystemClock`]. Tests inject a [`ManualClock`] instead and move it forward by hand, so a
+//! deadline passes at an exact step of the run rather than after some real time that a slow
+//! machine may or may not have spent.
+//!
+//! The clock only decides when a deadline has passed and how long a completion may still
+//! take. Waiting itself, such as a completion cut off by its timeout, still happens in real
+//! time.
+
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// A source of monotonic time.
+pub trait Clock: Send + Sync + fmt::Debug {
+    /// The current instant.
+    fn now(&self) -> Instant;
+}
+
+/// [`Clock`] shared between an agent, its clones and the tool loops they run.
+pub type SharedClock = Arc<dyn Clock>;
+
+/// The real time, [`Instant::now`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A clock that stands still until [`advance`](Self::advance)d. Clones share the same time.
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+    start: Instant,
+    elapsed: Arc<Mutex<Duration>>,
+}
+
+impl ManualClock {
+    /// A clock reading the real time it was created at.
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            elapsed: Arc::default(),
+        }
+    }
+
+    /// Moves the clock, and every clone of it, forward by `by`.
+    pub fn advance(&self, by: Duration) {
+        *self.elapsed.lock().unwrap() += by;
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.start + *self.elapsed.lock().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manual_clock_moves_only_when_advanced() {
+        let clock = ManualClock::new();
+        let shared: SharedClock = Arc::new(clock.clone());
+        let before = shared.now();
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(shared.now(), before);
+
+        clock.advance(Duration::from_secs(90));
+        assert_eq!(shared.now() - before, Duration::from_secs(90));
+    }
+}
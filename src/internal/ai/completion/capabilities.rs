@@ -0,0 +1,231 @@
+//! Model capability descriptors and request pre-flight checks.
+//!
+//! Providers accept different subsets of [`CompletionRequest`]. Rather than letting each
+//! provider silently drop or reject unsupported fields, agents compare the request with
+//! [`CompletionModel::capabilities`](super::CompletionModel::capabilities) before sending
+//! it, and either fail early or degrade the request according to a [`CapabilityPolicy`].
+
+use super::{
+    CompletionError, CompletionRequest,
+    message::{Message, Text, UserContent},
+};
+
+/// What a model accepts in a completion request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModelCapabilities {
+    /// Accepts tool definitions and returns tool calls.
+    pub supports_tools: bool,
+    /// Accepts image content in user messages.
+    pub supports_vision: bool,
+    /// Can be constrained to emit JSON objects.
+    pub supports_json_mode: bool,
+    /// Accepts a system prompt (preamble or system messages).
+    pub supports_system_role: bool,
+    /// Context window in tokens, when known.
+    pub max_context: Option<u64>,
+}
+
+impl Default for ModelCapabilities {
+    /// Plain text chat with tool calling and a system prompt, which every built-in
+    /// provider supports. Everything else is assumed unsupported.
+    fn default() -> Self {
+        Self {
+            supports_tools: true,
+            supports_vision: false,
+            supports_json_mode: false,
+            supports_system_role: true,
+            max_context: None,
+        }
+    }
+}
+
+/// What to do when a request uses a feature the model does not support.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CapabilityPolicy {
+    /// Fail before sending the request.
+    #[default]
+    Strict,
+    /// Remove or rewrite the unsupported parts and log a warning.
+    Degrade,
+}
+
+/// Check `request` against `capabilities`, applying `policy` to any mismatch.
+///
+/// Under [`CapabilityPolicy::Degrade`]:
+/// - tool definitions are dropped,
+/// - images are replaced with a text placeholder,
+/// - the preamble and system messages are turned into user messages.
+pub fn enforce_capabilities(
+    capabilities: &ModelCapabilities,
+    policy: CapabilityPolicy,
+    request: &mut CompletionRequest,
+) -> Result<(), CompletionError> {
+    let mut gaps = Vec::new();
+
+    if !capabilities.supports_tools && !request.tools.is_empty() {
+        gaps.push(format!(
+            "model does not support tool calling; {} tools configured",
+            request.tools.len()
+        ));
+        if policy == CapabilityPolicy::Degrade {
+            request.tools.clear();
+        }
+    }
+
+    let images = count_images(&request.chat_history);
+    if !capabilities.supports_vision && images > 0 {
+        gaps.push(format!(
+            "model does not support image input; {images} images in the conversation"
+        ));
+        if policy == CapabilityPolicy::Degrade {
+            replace_images(&mut request.chat_history);
+        }
+    }
+
+    let system_messages = request
+        .chat_history
+        .iter()
+        .filter(|m| matches!(m, Message::System { .. }))
+        .count();
+    if !capabilities.supports_system_role && (request.preamble.is_some() || system_messages > 0) {
+        gaps.push("model does not support a system prompt".to_string());
+        if policy == CapabilityPolicy::Degrade {
+            demote_system_prompt(request);
+        }
+    }
+
+    if gaps.is_empty() {
+        return Ok(());
+    }
+    match policy {
+        CapabilityPolicy::Strict => Err(CompletionError::Unsupported(gaps.join("; "))),
+        CapabilityPolicy::Degrade => {
+            for gap in gaps {
+                tracing::warn!("{gap}; degrading request");
+            }
+            Ok(())
+        }
+    }
+}
+
+fn count_images(history: &[Message]) -> usize {
+    history
+        .iter()
+        .map(|message| match message {
+            Message::User { content } | Message::System { content } => content
+                .iter()
+                .filter(|c| matches!(c, UserContent::Image(_)))
+                .count(),
+            Message::Assistant { .. } => 0,
+        })
+        .sum()
+}
+
+fn replace_images(history: &mut [Message]) {
+    for message in history {
+        if let Message::User { content } | Message::System { content } = message {
+            let replaced = content.iter().cloned().map(|c| match c {
+                UserContent::Image(_) => UserContent::Text(Text {
+                    text: "[image omitted: the model does not accept images]".to_string(),
+                }),
+                other => other,
+            });
+            if let Some(new_content) = super::OneOrMany::many(replaced.collect()) {
+                *content = new_content;
+            }
+        }
+    }
+}
+
+fn demote_system_prompt(request: &mut CompletionRequest) {
+    for message in &mut request.chat_history {
+        if let Message::System { content } = message {
+            *message = Message::User {
+                content: content.clone(),
+            };
+        }
+    }
+    if let Some(preamble) = request.preamble.take() {
+        request.chat_history.insert(0, Message::user(preamble));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::ai::{
+        completion::{OneOrMany, message::Image},
+        tools::ToolDefinition,
+    };
+
+    fn request_with_everything() -> CompletionRequest {
+        CompletionRequest {
+            preamble: Some("be brief".to_string()),
+            chat_history: vec![Message::User {
+                content: OneOrMany::many(vec![
+                    UserContent::Text(Text {
+                        text: "what is this?".to_string(),
+                    }),
+                    UserContent::Image(Image {
+                        data: "aGVsbG8=".to_string(),
+                        mime_type: Some("image/png".to_string()),
+                    }),
+                ])
+                .unwrap(),
+            }],
+            tools: vec![ToolDefinition {
+                name: "read_file".to_string(),
+                description: "Read a file".to_string(),
+                parameters: serde_json::json!({"type": "object", "properties": {}}),
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn text_only() -> ModelCapabilities {
+        ModelCapabilities {
+            supports_tools: false,
+            supports_system_role: false,
+            ..ModelCapabilities::default()
+        }
+    }
+
+    #[test]
+    fn test_strict_reports_every_gap() {
+        let mut request = request_with_everything();
+        let err = enforce_capabilities(&text_only(), CapabilityPolicy::Strict, &mut request)
+            .unwrap_err()
+            .to_string();
+
+        assert!(err.contains("does not support tool calling; 1 tools configured"));
+        assert!(err.contains("does not support image input; 1 images"));
+        assert!(err.contains("does not support a system prompt"));
+        // Strict mode leaves the request untouched.
+        assert_eq!(request.tools.len(), 1);
+        assert!(request.preamble.is_some());
+    }
+
+    #[test]
+    fn test_degrade_rewrites_request() {
+        let mut request = request_with_everything();
+        enforce_capabilities(&text_only(), CapabilityPolicy::Degrade, &mut request).unwrap();
+
+        assert!(request.tools.is_empty());
+        assert!(request.preamble.is_none());
+        assert_eq!(request.chat_history.len(), 2);
+        assert_eq!(request.chat_history[0], Message::user("be brief"));
+        assert_eq!(count_images(&request.chat_history), 0);
+    }
+
+    #[test]
+    fn test_supported_request_passes_unchanged() {
+        let mut request = request_with_everything();
+        let capabilities = ModelCapabilities {
+            supports_vision: true,
+            ..ModelCapabilities::default()
+        };
+        enforce_capabilities(&capabilities, CapabilityPolicy::Strict, &mut request).unwrap();
+        assert_eq!(request.tools.len(), 1);
+        assert_eq!(count_images(&request.chat_history), 1);
+    }
+}
@@ -40,6 +40,23 @@ pub enum AssistantContent {
     Text(Text),
     // Future-proof: Tool Call support
     ToolCall(ToolCall),
+    /// Model reasoning ("thinking") that precedes the answer. Not part of the reply text.
+    Reasoning(Reasoning),
+    /// The provider declined to answer on content-policy grounds, either with an explicit
+    /// refusal or a refusal finish reason. `message` is the provider's explanation.
+    Refusal {
+        message: String,
+    },
+}
+
+impl AssistantContent {
+    /// The provider's explanation if this is a [`AssistantContent::Refusal`].
+    pub fn refusal(&self) -> Option<&str> {
+        match self {
+            AssistantContent::Refusal { message } => Some(message),
+            _ => None,
+        }
+    }
 }
 
 /// Text content.
@@ -48,6 +65,15 @@ pub struct Text {
     pub text: String,
 }
 
+/// Reasoning content produced by thinking-capable models.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Reasoning {
+    pub reasoning: String,
+    /// The provider withheld the reasoning; `reasoning` holds no readable text.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub redacted: bool,
+}
+
 /// Image content.
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 pub struct Image {
@@ -86,6 +112,31 @@ impl Text {
     }
 }
 
+impl Reasoning {
+    pub fn new(reasoning: impl Into<String>) -> Self {
+        Self {
+            reasoning: reasoning.into(),
+            redacted: false,
+        }
+    }
+
+    pub fn redacted() -> Self {
+        Self {
+            reasoning: String::new(),
+            redacted: true,
+        }
+    }
+
+    /// Readable form of the reasoning, with a placeholder for redacted blocks.
+    pub fn display_text(&self) -> &str {
+        if self.redacted {
+            "[redacted reasoning]"
+        } else {
+            &self.reasoning
+        }
+    }
+}
+
 impl std::fmt::Display for Text {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.text)
@@ -126,6 +177,14 @@ impl<T> OneOrMany<T> {
             OneOrMany::Many(items) => OneOrManyIter::Many(items.iter()),
         }
     }
+
+    // Returns a mutable iterator over the items.
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, T> {
+        match self {
+            OneOrMany::One(item) => std::slice::from_mut(item).iter_mut(),
+            OneOrMany::Many(items) => items.iter_mut(),
+        }
+    }
 }
 
 impl<T> IntoIterator for OneOrMany<T> {
@@ -1,14 +1,24 @@
+pub mod capabilities;
+pub mod guardrails;
 pub mod message;
 pub mod request;
+pub mod untrusted;
 
-use std::future::Future;
+use std::{future::Future, time::Duration};
 
+pub use capabilities::{CapabilityPolicy, ModelCapabilities, enforce_capabilities};
+pub use guardrails::{GuardrailAction, GuardrailDirection, GuardrailRule, Guardrails};
 pub use message::{
-    AssistantContent, Function, Message, MessageError, OneOrMany, Text, ToolCall, ToolResult,
-    UserContent,
+    AssistantContent, Function, Message, MessageError, OneOrMany, Reasoning, Text, ToolCall,
+    ToolResult, UserContent,
+};
+pub use request::{
+    CompletionCandidates, CompletionRequest, CompletionResponse, SamplingParams, Usage,
 };
-pub use request::{CompletionRequest, CompletionResponse};
 use thiserror::Error;
+pub use untrusted::UntrustedContent;
+
+use crate::internal::ai::budget::BudgetResource;
 
 #[derive(Debug, Error)]
 pub enum CompletionError {
@@ -29,6 +39,90 @@ pub enum CompletionError {
 
     #[error("Feature not implemented: {0}")]
     NotImplemented(String),
+
+    #[error("Timeout: completion request did not finish within {} ms", .0.as_millis())]
+    Timeout(Duration),
+
+    #[error("Unsupported by model: {0}")]
+    Unsupported(String),
+
+    /// Content matched a [`Guardrails`] rule. Only the rule name is kept, never the match.
+    #[error("Policy violation: {direction} content matched guardrail rule '{rule}'")]
+    PolicyViolation {
+        rule: String,
+        direction: GuardrailDirection,
+    },
+
+    /// The provider declined the request on content-policy grounds. Never retried.
+    #[error("Refused by provider: {message}")]
+    Refused { message: String },
+
+    /// The overall deadline of a run passed (see
+    /// [`AgentBuilder::deadline`](crate::internal::ai::AgentBuilder::deadline) and
+    /// [`ToolLoopConfig::deadline`](crate::internal::ai::agent::ToolLoopConfig::deadline)).
+    /// `partial` is the best result so far: the model's last text, else a summary of the
+    /// tool calls that completed, else empty.
+    #[error("Deadline exceeded: the run did not finish within {} ms", .limit.as_millis())]
+    DeadlineExceeded { limit: Duration, partial: String },
+
+    /// A [`SharedBudget`](crate::internal::ai::budget::SharedBudget) refused to reserve the call.
+    #[error("Budget exceeded: {used} of {limit} {resource} used")]
+    BudgetExceeded {
+        resource: BudgetResource,
+        used: u64,
+        limit: u64,
+    },
+}
+
+impl CompletionError {
+    /// Whether the failed request may succeed if sent again (possibly to a fallback model).
+    ///
+    /// Timeouts and transport-level connection failures are retryable; malformed requests,
+    /// provider rejections, content-policy refusals and response parsing errors are not.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            CompletionError::Timeout(_) => true,
+            CompletionError::HttpError(e) => e.is_timeout() || e.is_connect(),
+            _ => false,
+        }
+    }
+
+    /// The partial result of a run stopped by its deadline, if it produced any.
+    pub fn partial_result(&self) -> Option<&str> {
+        match self {
+            CompletionError::DeadlineExceeded { partial, .. } if !partial.is_empty() => {
+                Some(partial)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The partial result reported in [`CompletionError::DeadlineExceeded`]: `last_text` if
+/// the model said anything, else the names of the tool calls that completed.
+pub(crate) fn partial_result(last_text: &str, completed_tools: &[String]) -> String {
+    if !last_text.trim().is_empty() {
+        last_text.trim().to_string()
+    } else if completed_tools.is_empty() {
+        String::new()
+    } else {
+        format!(
+            "Completed {} tool call(s): {}",
+            completed_tools.len(),
+            completed_tools.join(", ")
+        )
+    }
+}
+
+/// Fail with [`CompletionError::Refused`] if `content` holds a refusal, so an agent stops
+/// instead of treating it as an answer or running the tool calls next to it.
+pub fn check_refusal(content: &[AssistantContent]) -> Result<(), CompletionError> {
+    match content.iter().find_map(AssistantContent::refusal) {
+        Some(message) => Err(CompletionError::Refused {
+            message: message.to_string(),
+        }),
+        None => Ok(()),
+    }
 }
 
 pub trait CompletionModel: Clone + Send + Sync {
@@ -38,6 +132,60 @@ pub trait CompletionModel: Clone + Send + Sync {
         &self,
         request: CompletionRequest,
     ) -> impl Future<Output = Result<CompletionResponse<Self::Response>, CompletionError>> + Send;
+
+    /// Request features this model accepts. Agents check requests against it before
+    /// sending them; see [`enforce_capabilities`].
+    fn capabilities(&self) -> ModelCapabilities {
+        ModelCapabilities::default()
+    }
+
+    /// Token usage reported in `response`, if the provider returns it.
+    fn usage(&self, _response: &CompletionResponse<Self::Response>) -> Option<Usage> {
+        None
+    }
+
+    /// Generate `request.n` alternative responses (at least one).
+    ///
+    /// The default issues that many single-candidate calls in parallel. Providers that can
+    /// return several choices from one call override this and forward `n` instead.
+    fn completion_candidates(
+        &self,
+        request: CompletionRequest,
+    ) -> impl Future<Output = Result<CompletionCandidates, CompletionError>> + Send {
+        async move {
+            let n = request.n.unwrap_or(1).max(1);
+            let single = CompletionRequest { n: None, ..request };
+            let responses =
+                futures::future::join_all((0..n).map(|_| self.completion(single.clone()))).await;
+
+            let mut candidates = CompletionCandidates::default();
+            for response in responses {
+                let response = response?;
+                if let Some(usage) = self.usage(&response) {
+                    candidates.usage += usage;
+                }
+                candidates.candidates.push(response.content);
+            }
+            Ok(candidates)
+        }
+    }
+}
+
+/// Send one completion request, giving up with [`CompletionError::Timeout`] after `timeout`.
+///
+/// The deadline covers the whole call, including reading and assembling the response.
+/// `None` waits indefinitely.
+pub async fn completion_with_timeout<M: CompletionModel>(
+    model: &M,
+    request: CompletionRequest,
+    timeout: Option<Duration>,
+) -> Result<CompletionResponse<M::Response>, CompletionError> {
+    match timeout {
+        Some(limit) => tokio::time::timeout(limit, model.completion(request))
+            .await
+            .map_err(|_| CompletionError::Timeout(limit))?,
+        None => model.completion(request).await,
+    }
 }
 
 pub trait Prompt: Send + Sync {
@@ -9,12 +9,60 @@ pub struct CompletionRequest {
     pub preamble: Option<String>,   // Future-proof: Preamble support
     pub chat_history: Vec<Message>, // Conversation messages
     pub temperature: Option<f64>,   // Sampling temperature
+    pub top_p: Option<f64>,         // Nucleus sampling probability mass
+    pub max_tokens: Option<u64>,    // Upper bound on generated tokens
+    pub seed: Option<u64>,          // Seed for reproducible sampling
+    pub n: Option<usize>,           // Number of candidates to generate
+    /// Identifies one logical step of an agent run; resending the same step reuses it so
+    /// providers that support idempotency can deduplicate the generation.
+    pub idempotency_key: Option<String>,
     // Future-proof: Tools support
     pub tools: Vec<ToolDefinition>, // Tools available to the model
     // Future-proof: RAG support
     pub documents: Vec<Value>, // Placeholder for Document
 }
 
+/// Sampling parameters that can be applied to a request in one go.
+///
+/// Every field is optional; `None` leaves the provider default in place.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SamplingParams {
+    pub temperature: Option<f64>,
+    pub top_p: Option<f64>,
+    pub max_tokens: Option<u64>,
+    pub seed: Option<u64>,
+}
+
+/// Token counts reported by a provider for one or more completion calls.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Usage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+impl Usage {
+    pub fn total_tokens(&self) -> u64 {
+        self.input_tokens + self.output_tokens
+    }
+}
+
+impl std::ops::AddAssign for Usage {
+    fn add_assign(&mut self, other: Self) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+    }
+}
+
+/// Alternative responses generated for one request, see
+/// [`CompletionModel::completion_candidates`](super::CompletionModel::completion_candidates).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CompletionCandidates {
+    /// Content of each candidate, in the order the provider returned them.
+    pub candidates: Vec<Vec<AssistantContent>>,
+    /// Usage summed over every call made to produce the candidates.
+    pub usage: Usage,
+}
+
 /// Represents a response from the AI completion service.
 #[derive(Debug)]
 pub struct CompletionResponse<T> {
@@ -0,0 +1,568 @@
+//! Packing unified diffs into a bounded prompt context.
+//!
+//! Truncating a large diff keeps whatever happens to come first, which is often a lock
+//! file. [`DiffPacker`] instead splits the diff into hunks, scores each one and packs the
+//! most valuable hunks that fit a token budget:
+//!
+//! - **size**: more changed lines score higher, with diminishing returns;
+//! - **file type**: source files outweigh docs and config, which outweigh lock files and
+//!   generated assets;
+//! - **signatures**: hunks that change or sit inside a function, type or class definition
+//!   (detected with simple per-language keyword heuristics) get a bonus.
+//!
+//! The packed document groups the chosen hunks under per-file headers in diff order and
+//! ends with an explicit omission summary. Packing is deterministic: ties are broken by
+//! position in the diff, so the same diff and budget always produce the same document.
+//!
+//! Token counts use [`estimate_tokens`], a character-based estimate; budgets derived from
+//! [`ModelCapabilities::max_context`] leave room for the rest of the prompt and the answer.
+
+use std::{collections::BTreeSet, fmt};
+
+use crate::internal::ai::completion::ModelCapabilities;
+
+/// Context window assumed when the model does not report one.
+const DEFAULT_CONTEXT_TOKENS: u64 = 8_192;
+/// Tokens kept free for the omission summary line.
+const SUMMARY_RESERVE_TOKENS: usize = 24;
+
+/// Rough token count for `text`: about four characters per token.
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// One `@@` hunk of a unified diff.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffHunk {
+    /// Path of the file after the change (before it, for deletions).
+    pub path: String,
+    /// Position of the file in the diff.
+    pub file_index: usize,
+    /// Position of the hunk within its file.
+    pub hunk_index: usize,
+    /// The hunk text, including its `@@` header line.
+    pub text: String,
+    /// Number of added plus removed lines.
+    pub changed_lines: usize,
+    /// Priority computed by [`score_hunk`].
+    pub score: f64,
+}
+
+impl DiffHunk {
+    fn line_count(&self) -> usize {
+        self.text.lines().count()
+    }
+}
+
+/// Hunks left out of a [`PackedDiff`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OmissionSummary {
+    pub hunks: usize,
+    pub files: usize,
+    pub lines: usize,
+}
+
+impl fmt::Display for OmissionSummary {
+    /// E.g. `12 hunks in 4 files omitted, 3k lines`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let plural = |n: usize, word: &str| {
+            if n == 1 {
+                format!("{n} {word}")
+            } else {
+                format!("{n} {word}s")
+            }
+        };
+        let lines = if self.lines >= 1_000 {
+            format!("{}k lines", (self.lines + 500) / 1_000)
+        } else {
+            plural(self.lines, "line")
+        };
+        write!(
+            f,
+            "{} in {} omitted, {lines}",
+            plural(self.hunks, "hunk"),
+            plural(self.files, "file")
+        )
+    }
+}
+
+/// The result of [`DiffPacker::pack`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackedDiff {
+    /// Context document to place in the prompt.
+    pub document: String,
+    /// Selected hunks, highest priority first.
+    pub included: Vec<DiffHunk>,
+    /// What was left out; `None` when the whole diff fit.
+    pub omitted: Option<OmissionSummary>,
+    /// Estimated tokens of [`PackedDiff::document`].
+    pub tokens: usize,
+}
+
+/// Packs diff hunks into a token budget.
+#[derive(Debug, Clone, Copy)]
+pub struct DiffPacker {
+    budget: usize,
+}
+
+impl DiffPacker {
+    /// Pack into at most `budget` estimated tokens.
+    pub fn new(budget: usize) -> Self {
+        Self { budget }
+    }
+
+    /// Budget the diff to half of the model's context window.
+    pub fn for_model(capabilities: &ModelCapabilities) -> Self {
+        let context = capabilities.max_context.unwrap_or(DEFAULT_CONTEXT_TOKENS);
+        Self::new(usize::try_from(context / 2).unwrap_or(usize::MAX))
+    }
+
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    /// Select the highest-scoring hunks of `diff` that fit the budget.
+    ///
+    /// Hunks are considered in priority order; one that does not fit is skipped and
+    /// smaller hunks after it may still be included.
+    pub fn pack(&self, diff: &str) -> PackedDiff {
+        let hunks = parse_hunks(diff);
+
+        let mut order: Vec<usize> = (0..hunks.len()).collect();
+        order.sort_by(|&a, &b| {
+            hunks[b]
+                .score
+                .total_cmp(&hunks[a].score)
+                .then(hunks[a].file_index.cmp(&hunks[b].file_index))
+                .then(hunks[a].hunk_index.cmp(&hunks[b].hunk_index))
+        });
+
+        let mut used = SUMMARY_RESERVE_TOKENS;
+        let mut files = BTreeSet::new();
+        let mut selected = Vec::new();
+        for idx in order {
+            let hunk = &hunks[idx];
+            let header = if files.contains(&hunk.file_index) {
+                0
+            } else {
+                estimate_tokens(&file_header(&hunk.path))
+            };
+            let cost = header + estimate_tokens(&hunk.text);
+            if used + cost > self.budget {
+                continue;
+            }
+            used += cost;
+            files.insert(hunk.file_index);
+            selected.push(idx);
+        }
+
+        let chosen: BTreeSet<usize> = selected.iter().copied().collect();
+        let omitted_hunks: Vec<&DiffHunk> = hunks
+            .iter()
+            .enumerate()
+            .filter(|(idx, _)| !chosen.contains(idx))
+            .map(|(_, hunk)| hunk)
+            .collect();
+        let omitted = (!omitted_hunks.is_empty()).then(|| OmissionSummary {
+            hunks: omitted_hunks.len(),
+            files: omitted_hunks
+                .iter()
+                .map(|hunk| hunk.file_index)
+                .collect::<BTreeSet<_>>()
+                .len(),
+            lines: omitted_hunks.iter().map(|hunk| hunk.line_count()).sum(),
+        });
+
+        let mut document = String::new();
+        let mut current_file = None;
+        for &idx in &chosen {
+            let hunk = &hunks[idx];
+            if current_file != Some(hunk.file_index) {
+                current_file = Some(hunk.file_index);
+                document.push_str(&file_header(&hunk.path));
+            }
+            document.push_str(&hunk.text);
+            document.push('\n');
+        }
+        if let Some(summary) = &omitted {
+            document.push_str(&format!("[{summary}]\n"));
+        }
+
+        let tokens = estimate_tokens(&document);
+        PackedDiff {
+            document,
+            included: selected.into_iter().map(|idx| hunks[idx].clone()).collect(),
+            omitted,
+            tokens,
+        }
+    }
+}
+
+fn file_header(path: &str) -> String {
+    format!("### {path}\n")
+}
+
+/// Split a unified diff into scored hunks.
+///
+/// Hunk extents follow the line counts in their `@@` headers, so removed lines that look
+/// like file headers are not mistaken for them. Malformed hunks end at the next header.
+pub fn parse_hunks(diff: &str) -> Vec<DiffHunk> {
+    let lines: Vec<&str> = diff.lines().collect();
+    let mut hunks = Vec::new();
+    let mut path: Option<String> = None;
+    let mut file_index = 0;
+    let mut hunk_index = 0;
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+        if let Some(old) = line.strip_prefix("--- ")
+            && let Some(new) = lines.get(i + 1).and_then(|l| l.strip_prefix("+++ "))
+        {
+            if path.is_some() {
+                file_index += 1;
+            }
+            path = header_path(new, "b/").or_else(|| header_path(old, "a/"));
+            hunk_index = 0;
+            i += 2;
+            continue;
+        }
+        let (Some(file), Some((old_count, new_count))) =
+            (&path, line.strip_prefix("@@ ").and_then(hunk_counts))
+        else {
+            i += 1;
+            continue;
+        };
+
+        let start = i;
+        let (mut old_seen, mut new_seen, mut changed) = (0, 0, 0);
+        i += 1;
+        while i < lines.len() && (old_seen < old_count || new_seen < new_count) {
+            match lines[i].chars().next() {
+                Some('+') => {
+                    new_seen += 1;
+                    changed += 1;
+                }
+                Some('-') => {
+                    old_seen += 1;
+                    changed += 1;
+                }
+                Some(' ') | None => {
+                    old_seen += 1;
+                    new_seen += 1;
+                }
+                Some('\\') => {}
+                Some(_) => break,
+            }
+            i += 1;
+        }
+        if lines.get(i).is_some_and(|l| l.starts_with('\\')) {
+            i += 1;
+        }
+
+        let text = lines[start..i].join("\n");
+        hunks.push(DiffHunk {
+            path: file.clone(),
+            file_index,
+            hunk_index,
+            score: score_hunk(file, &text, changed),
+            text,
+            changed_lines: changed,
+        });
+        hunk_index += 1;
+    }
+    hunks
+}
+
+/// Strip the `a/`/`b/` prefix and any trailing timestamp; `/dev/null` yields `None`.
+fn header_path(raw: &str, prefix: &str) -> Option<String> {
+    let raw = raw.split('\t').next().unwrap_or(raw).trim();
+    (raw != "/dev/null").then(|| raw.strip_prefix(prefix).unwrap_or(raw).to_string())
+}
+
+/// Parse the old and new line counts from the text after `@@ `.
+fn hunk_counts(header: &str) -> Option<(usize, usize)> {
+    let ranges = header.split(" @@").next()?;
+    let (old, new) = ranges.split_once(' ')?;
+    let count = |range: &str| match range.split_once(',') {
+        Some((_, count)) => count.parse().ok(),
+        None => Some(1),
+    };
+    Some((
+        count(old.strip_prefix('-')?)?,
+        count(new.strip_prefix('+')?)?,
+    ))
+}
+
+/// Priority of a hunk: size with diminishing returns, weighted by file type and by
+/// proximity to definitions.
+pub fn score_hunk(path: &str, text: &str, changed_lines: usize) -> f64 {
+    let size = (1.0 + changed_lines as f64).ln();
+    size * file_weight(path) * signature_weight(path, text)
+}
+
+fn file_weight(path: &str) -> f64 {
+    let lower = path.to_ascii_lowercase();
+    let file_name = lower.rsplit('/').next().unwrap_or(&lower);
+    let extension = file_name.rsplit_once('.').map_or("", |(_, ext)| ext);
+
+    if file_name.ends_with(".lock")
+        || matches!(
+            file_name,
+            "package-lock.json" | "pnpm-lock.yaml" | "go.sum" | "cargo.lock"
+        )
+    {
+        return 0.1;
+    }
+    if file_name.contains(".min.") || matches!(extension, "snap" | "svg" | "map") {
+        return 0.2;
+    }
+    let source = matches!(
+        extension,
+        "rs" | "py"
+            | "go"
+            | "js"
+            | "jsx"
+            | "ts"
+            | "tsx"
+            | "java"
+            | "kt"
+            | "c"
+            | "h"
+            | "cc"
+            | "cpp"
+            | "hpp"
+            | "cs"
+            | "rb"
+            | "swift"
+            | "sh"
+    );
+    if source {
+        let test = lower.contains("/tests/")
+            || lower.starts_with("tests/")
+            || lower.contains("_test.")
+            || lower.contains(".test.")
+            || lower.contains("/test_");
+        return if test { 0.8 } else { 1.0 };
+    }
+    if matches!(extension, "toml" | "yaml" | "yml" | "json" | "md" | "txt") {
+        return 0.6;
+    }
+    0.5
+}
+
+/// 1.5 when a changed line is a definition, 1.2 when the hunk sits inside one (its
+/// header or context names a definition), 1.0 otherwise.
+fn signature_weight(path: &str, text: &str) -> f64 {
+    let extension = path.rsplit_once('.').map_or("", |(_, ext)| ext);
+    let mut lines = text.lines();
+    let header = lines.next().unwrap_or_default();
+    let header_context = header.splitn(3, "@@").nth(2).unwrap_or_default();
+
+    let mut in_context = is_signature(extension, header_context);
+    for line in lines {
+        let (marker, code) = line.split_at(line.len().min(1));
+        if !is_signature(extension, code) {
+            continue;
+        }
+        if marker == "+" || marker == "-" {
+            return 1.5;
+        }
+        in_context = true;
+    }
+    if in_context { 1.2 } else { 1.0 }
+}
+
+fn is_signature(extension: &str, line: &str) -> bool {
+    let line = line.trim_start();
+    let keywords: &[&str] = match extension {
+        "rs" => &[
+            "fn ",
+            "pub fn ",
+            "pub(crate) fn ",
+            "async fn ",
+            "pub async fn ",
+            "struct ",
+            "pub struct ",
+            "enum ",
+            "pub enum ",
+            "trait ",
+            "pub trait ",
+            "impl ",
+            "impl<",
+        ],
+        "py" => &["def ", "async def ", "class "],
+        "go" => &["func ", "type "],
+        "js" | "jsx" | "ts" | "tsx" => &[
+            "function ",
+            "async function ",
+            "export function ",
+            "export default function ",
+            "class ",
+            "export class ",
+            "interface ",
+            "export interface ",
+        ],
+        "java" | "kt" | "cs" => &[
+            "public ",
+            "private ",
+            "protected ",
+            "class ",
+            "interface ",
+            "fun ",
+        ],
+        "rb" => &["def ", "class ", "module "],
+        _ => &[],
+    };
+    keywords.iter().any(|keyword| line.starts_with(keyword))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Three files: a Rust source file with a signature change and a body change, a
+    /// README edit and a large lock file update.
+    fn sample_diff() -> String {
+        let mut lock = String::from(
+            "diff --git a/Cargo.lock b/Cargo.lock\n--- a/Cargo.lock\n+++ b/Cargo.lock\n@@ -1,40 +1,40 @@\n",
+        );
+        for n in 0..40 {
+            lock.push_str(&format!(
+                "-checksum = \"old{n:02}\"\n+checksum = \"new{n:02}\"\n"
+            ));
+        }
+        format!(
+            "{lock}{}",
+            "\
+diff --git a/src/lib.rs b/src/lib.rs
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -10,3 +10,3 @@ use std::io;
+
+-pub fn parse(input: &str) -> Result<Ast> {
++pub fn parse(input: &str, strict: bool) -> Result<Ast> {
+     let tokens = lex(input)?;
+@@ -40,4 +40,5 @@ fn lower(ast: Ast) -> Ir {
+     let mut ir = Ir::default();
+-    ir.push(ast.root);
++    ir.reserve(ast.len());
++    ir.push(ast.root);
+     ir
+diff --git a/README.md b/README.md
+--- a/README.md
++++ b/README.md
+@@ -1,2 +1,2 @@
+ # Parser
+-Parses things.
++Parses things, strictly if asked.
+"
+        )
+    }
+
+    fn selection(packed: &PackedDiff) -> Vec<(String, usize)> {
+        packed
+            .included
+            .iter()
+            .map(|hunk| (hunk.path.clone(), hunk.hunk_index))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_hunks_follows_header_counts() {
+        let diff = "--- a/a.txt\n+++ b/a.txt\n@@ -1,2 +1,1 @@\n--- not a header\n-+++ either\n";
+        let hunks = parse_hunks(diff);
+        assert_eq!(hunks.len(), 1);
+        assert_eq!(hunks[0].path, "a.txt");
+        assert_eq!(hunks[0].changed_lines, 2);
+    }
+
+    #[test]
+    fn test_pack_under_budgets() {
+        let diff = sample_diff();
+        let hunks = parse_hunks(&diff);
+        assert_eq!(hunks.len(), 4);
+
+        // Everything fits: nothing is omitted.
+        let packed = DiffPacker::new(100_000).pack(&diff);
+        assert_eq!(
+            selection(&packed),
+            vec![
+                ("src/lib.rs".to_string(), 1),
+                ("src/lib.rs".to_string(), 0),
+                ("README.md".to_string(), 0),
+                ("Cargo.lock".to_string(), 0),
+            ]
+        );
+        assert_eq!(packed.omitted, None);
+
+        // The lock file no longer fits.
+        let packed = DiffPacker::new(300).pack(&diff);
+        assert_eq!(
+            selection(&packed),
+            vec![
+                ("src/lib.rs".to_string(), 1),
+                ("src/lib.rs".to_string(), 0),
+                ("README.md".to_string(), 0),
+            ]
+        );
+        let summary = packed.omitted.unwrap();
+        assert_eq!(summary.to_string(), "1 hunk in 1 file omitted, 81 lines");
+        assert!(packed.tokens <= 300);
+        // Files keep diff order in the document, regardless of priority.
+        let lib = packed.document.find("### src/lib.rs").unwrap();
+        let readme = packed.document.find("### README.md").unwrap();
+        assert!(lib < readme);
+        assert!(!packed.document.contains("Cargo.lock"));
+        assert!(
+            packed
+                .document
+                .ends_with("[1 hunk in 1 file omitted, 81 lines]\n")
+        );
+
+        // Only the highest-value hunk fits.
+        let packed = DiffPacker::new(90).pack(&diff);
+        assert_eq!(selection(&packed), vec![("src/lib.rs".to_string(), 1)]);
+        assert_eq!(
+            packed.omitted.unwrap().to_string(),
+            "3 hunks in 3 files omitted, 90 lines"
+        );
+
+        // Packing is deterministic.
+        assert_eq!(
+            DiffPacker::new(300).pack(&diff),
+            DiffPacker::new(300).pack(&diff)
+        );
+    }
+
+    #[test]
+    fn test_signature_hunks_outrank_plain_edits() {
+        let body = parse_hunks(
+            "--- a/x.py\n+++ b/x.py\n@@ -1,2 +1,2 @@\n     total = 0\n-    total += 1\n+    total += 2\n",
+        );
+        let signature = parse_hunks(
+            "--- a/x.py\n+++ b/x.py\n@@ -1,2 +1,2 @@\n-def add(a):\n+def add(a, b):\n     total = 0\n",
+        );
+        assert!(signature[0].score > body[0].score);
+    }
+
+    #[test]
+    fn test_summary_formatting_and_model_budget() {
+        let summary = OmissionSummary {
+            hunks: 12,
+            files: 4,
+            lines: 3_200,
+        };
+        assert_eq!(summary.to_string(), "12 hunks in 4 files omitted, 3k lines");
+
+        let capabilities = ModelCapabilities {
+            max_context: Some(200_000),
+            ..ModelCapabilities::default()
+        };
+        assert_eq!(DiffPacker::for_model(&capabilities).budget(), 100_000);
+        assert_eq!(
+            DiffPacker::for_model(&ModelCapabilities::default()).budget(),
+            4_096
+        );
+    }
+}
@@ -0,0 +1,15 @@
+//! End-to-end agent evaluation.
+//!
+//! A scenario file (see [`scenario`]) gives a prompt, the profile or agent settings to
+//! answer it with, a scripted model transcript (or `live: true`), and assertions on the
+//! result. [`EvalRunner`] routes the prompt, runs the tool loop, and reports which
+//! assertions failed. The scenarios in `tests/ai_scenarios` replay under `cargo test`;
+//! `libra ai eval --live` also runs live ones against a real provider.
+
+pub mod replay;
+pub mod runner;
+pub mod scenario;
+
+pub use replay::{ReplayModel, ReplayToolCall, ReplayTurn};
+pub use runner::{EvalReport, EvalRunner, ScenarioOutcome, ScenarioStatus, run_replay_scenarios};
+pub use scenario::{AgentSettings, Assertion, RunRecord, Scenario, ScenarioError, load_scenarios};
@@ -0,0 +1,123 @@
+//! A completion model that plays back a scripted transcript.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::internal::ai::completion::{
+    CompletionError, CompletionModel, CompletionRequest, CompletionResponse,
+    message::{AssistantContent, Function, Text, ToolCall},
+};
+
+/// One scripted model response.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ReplayTurn {
+    #[serde(default)]
+    pub text: Option<String>,
+    #[serde(default)]
+    pub tool_calls: Vec<ReplayToolCall>,
+}
+
+/// A tool call the model makes, and optionally what the tool answers.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ReplayToolCall {
+    pub name: String,
+    #[serde(default = "empty_arguments")]
+    pub arguments: Value,
+    /// Tool output fed back to the model; `"ok"` when absent.
+    #[serde(default)]
+    pub result: Option<String>,
+}
+
+fn empty_arguments() -> Value {
+    Value::Object(Default::default())
+}
+
+/// Answers the `n`th completion with the `n`th [`ReplayTurn`], whatever the request says,
+/// and fails once the transcript runs out.
+///
+/// Tool calls get deterministic ids (`replay_<turn>_<index>`) so the scripted results can be
+/// served by [`ReplayModel::tool_results`].
+#[derive(Clone)]
+pub struct ReplayModel {
+    turns: Arc<Vec<ReplayTurn>>,
+    next: Arc<AtomicUsize>,
+}
+
+impl ReplayModel {
+    pub fn new(turns: Vec<ReplayTurn>) -> Self {
+        Self {
+            turns: Arc::new(turns),
+            next: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Scripted tool output by call id.
+    pub fn tool_results(&self) -> HashMap<String, String> {
+        let mut results = HashMap::new();
+        for (turn, replay) in self.turns.iter().enumerate() {
+            for (index, call) in replay.tool_calls.iter().enumerate() {
+                if let Some(result) = &call.result {
+                    results.insert(call_id(turn, index), result.clone());
+                }
+            }
+        }
+        results
+    }
+
+    /// Names of every tool the transcript calls.
+    pub fn tool_names(&self) -> impl Iterator<Item = &str> {
+        self.turns
+            .iter()
+            .flat_map(|turn| turn.tool_calls.iter().map(|call| call.name.as_str()))
+    }
+}
+
+fn call_id(turn: usize, index: usize) -> String {
+    format!("replay_{turn}_{index}")
+}
+
+impl CompletionModel for ReplayModel {
+    type Response = ();
+
+    async fn completion(
+        &self,
+        _request: CompletionRequest,
+    ) -> Result<CompletionResponse<Self::Response>, CompletionError> {
+        let turn = self.next.fetch_add(1, Ordering::SeqCst);
+        let Some(replay) = self.turns.get(turn) else {
+            return Err(CompletionError::ResponseError(format!(
+                "replay transcript exhausted after {} turns",
+                self.turns.len()
+            )));
+        };
+
+        let mut content = Vec::new();
+        if let Some(text) = &replay.text {
+            content.push(AssistantContent::Text(Text { text: text.clone() }));
+        }
+        for (index, call) in replay.tool_calls.iter().enumerate() {
+            content.push(AssistantContent::ToolCall(ToolCall {
+                id: call_id(turn, index),
+                name: call.name.clone(),
+                function: Function {
+                    name: call.name.clone(),
+                    arguments: call.arguments.clone(),
+                },
+            }));
+        }
+        Ok(CompletionResponse {
+            content,
+            raw_response: (),
+        })
+    }
+}
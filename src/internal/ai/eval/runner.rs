@@ -0,0 +1,302 @@
+//! Runs scenarios through the tool loop and collects a report.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use serde_json::Value;
+
+use super::{
+    replay::ReplayModel,
+    scenario::{RunRecord, Scenario},
+};
+use crate::internal::ai::{
+    agent::{
+        profile::{AgentProfile, AgentProfileRouter},
+        runtime::tool_loop::{
+            ToolLoopConfig, ToolLoopObserver, run_tool_loop_with_history_and_observer,
+        },
+    },
+    completion::{CompletionError, CompletionModel, Usage},
+    tools::{
+        ToolHandler, ToolInvocation, ToolKind, ToolOutput, ToolRegistry, ToolResult, ToolSpec,
+        handlers::{GrepFilesHandler, ListDirHandler, ReadFileHandler},
+    },
+};
+
+/// Result of one scenario.
+#[derive(Debug)]
+pub enum ScenarioStatus {
+    Passed,
+    /// One message per failed assertion (or the agent error).
+    Failed(Vec<String>),
+    /// Not run, with the reason.
+    Skipped(String),
+}
+
+#[derive(Debug)]
+pub struct ScenarioOutcome {
+    pub name: String,
+    pub path: PathBuf,
+    pub status: ScenarioStatus,
+}
+
+/// Outcomes of a batch of scenarios; `Display` renders the pass/fail report.
+#[derive(Debug, Default)]
+pub struct EvalReport {
+    pub outcomes: Vec<ScenarioOutcome>,
+}
+
+impl EvalReport {
+    pub fn passed(&self) -> usize {
+        self.count(|status| matches!(status, ScenarioStatus::Passed))
+    }
+
+    pub fn failed(&self) -> usize {
+        self.count(|status| matches!(status, ScenarioStatus::Failed(_)))
+    }
+
+    pub fn skipped(&self) -> usize {
+        self.count(|status| matches!(status, ScenarioStatus::Skipped(_)))
+    }
+
+    fn count(&self, predicate: impl Fn(&ScenarioStatus) -> bool) -> usize {
+        self.outcomes
+            .iter()
+            .filter(|outcome| predicate(&outcome.status))
+            .count()
+    }
+}
+
+impl fmt::Display for EvalReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for outcome in &self.outcomes {
+            match &outcome.status {
+                ScenarioStatus::Passed => writeln!(f, "PASS {}", outcome.name)?,
+                ScenarioStatus::Skipped(reason) => writeln!(f, "SKIP {} ({reason})", outcome.name)?,
+                ScenarioStatus::Failed(failures) => {
+                    writeln!(f, "FAIL {} ({})", outcome.name, outcome.path.display())?;
+                    for failure in failures {
+                        for (i, line) in failure.lines().enumerate() {
+                            let bullet = if i == 0 { "  - " } else { "    " };
+                            writeln!(f, "{bullet}{line}")?;
+                        }
+                    }
+                }
+            }
+        }
+        write!(
+            f,
+            "{} passed, {} failed, {} skipped",
+            self.passed(),
+            self.failed(),
+            self.skipped()
+        )
+    }
+}
+
+/// Runs scenarios against a fixed set of agent profiles.
+pub struct EvalRunner {
+    profiles: Vec<AgentProfile>,
+    working_dir: PathBuf,
+}
+
+impl EvalRunner {
+    /// `working_dir` is where live runs' file tools operate.
+    pub fn new(profiles: Vec<AgentProfile>, working_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            profiles,
+            working_dir: working_dir.into(),
+        }
+    }
+
+    /// Run replay scenarios and skip live ones.
+    pub async fn run_replay(&self, scenarios: &[(PathBuf, Scenario)]) -> EvalReport {
+        self.run_all(scenarios, None::<&ReplayModel>).await
+    }
+
+    /// Run replay scenarios from their transcripts and live ones against `live_model`;
+    /// live scenarios are skipped without one.
+    ///
+    /// Live runs only get the read-only file tools (`read_file`, `list_dir`,
+    /// `grep_files`), so an evaluation never edits the working tree.
+    pub async fn run_all<M: CompletionModel>(
+        &self,
+        scenarios: &[(PathBuf, Scenario)],
+        live_model: Option<&M>,
+    ) -> EvalReport {
+        let mut report = EvalReport::default();
+        for (path, scenario) in scenarios {
+            let status = match (scenario.live, live_model) {
+                (false, _) => {
+                    let model = ReplayModel::new(scenario.transcript.clone());
+                    let tools = self.replay_tools(scenario, &model);
+                    self.run_scenario(scenario, &model, &tools).await
+                }
+                (true, Some(model)) => self.run_scenario(scenario, model, &self.live_tools()).await,
+                (true, None) => ScenarioStatus::Skipped("live scenario".to_string()),
+            };
+            report.outcomes.push(ScenarioOutcome {
+                name: scenario.name.clone(),
+                path: path.clone(),
+                status,
+            });
+        }
+        report
+    }
+
+    /// Run one scenario with `model` and `tools` and check its assertions.
+    pub async fn run_scenario<M: CompletionModel>(
+        &self,
+        scenario: &Scenario,
+        model: &M,
+        tools: &ToolRegistry,
+    ) -> ScenarioStatus {
+        let router = AgentProfileRouter::new(self.profiles.clone()).with_default_synonyms();
+        let profile = match &scenario.profile {
+            Some(name) => match router.get(name) {
+                Some(profile) => Some(profile),
+                None => {
+                    return ScenarioStatus::Failed(vec![format!("unknown profile '{name}'")]);
+                }
+            },
+            None => router.select(&scenario.prompt),
+        };
+
+        let defaults = ToolLoopConfig::default();
+        let config = ToolLoopConfig {
+            preamble: scenario
+                .agent
+                .preamble
+                .clone()
+                .or_else(|| profile.map(|p| p.preamble())),
+            temperature: scenario.agent.temperature.or(defaults.temperature),
+            max_steps: scenario.agent.max_steps.or(defaults.max_steps),
+            allowed_tools: profile.map(|p| p.tools.clone()),
+            ..defaults
+        };
+
+        let mut observer = RecordingObserver::default();
+        let turn = run_tool_loop_with_history_and_observer(
+            model,
+            Vec::new(),
+            scenario.prompt.clone(),
+            tools,
+            config,
+            &mut observer,
+        )
+        .await;
+        let answer = match turn {
+            Ok(turn) => turn.final_text,
+            Err(e) => return ScenarioStatus::Failed(vec![format!("agent failed: {e}")]),
+        };
+
+        let record = RunRecord {
+            answer,
+            selected_profile: profile.map(|p| p.name.clone()),
+            tool_calls: observer.tool_calls,
+            steps: observer.steps,
+        };
+        let failures: Vec<String> = scenario
+            .assertions
+            .iter()
+            .filter_map(|assertion| assertion.check(&record).err())
+            .collect();
+        if failures.is_empty() {
+            ScenarioStatus::Passed
+        } else {
+            ScenarioStatus::Failed(failures)
+        }
+    }
+
+    /// Scripted handlers for every tool the profile or transcript names.
+    fn replay_tools(&self, scenario: &Scenario, model: &ReplayModel) -> ToolRegistry {
+        let results = Arc::new(model.tool_results());
+        let mut names: Vec<String> = model.tool_names().map(str::to_string).collect();
+        if let Some(profile) = scenario
+            .profile
+            .as_deref()
+            .and_then(|name| self.profiles.iter().find(|p| p.name == name))
+        {
+            names.extend(profile.tools.iter().cloned());
+        }
+
+        let mut registry = ToolRegistry::with_working_dir(self.working_dir.clone());
+        for name in names {
+            let handler = ScriptedTool {
+                name: name.clone(),
+                results: results.clone(),
+            };
+            registry.register(name, Arc::new(handler));
+        }
+        registry
+    }
+
+    fn live_tools(&self) -> ToolRegistry {
+        let mut registry = ToolRegistry::with_working_dir(self.working_dir.clone());
+        registry.register("read_file", Arc::new(ReadFileHandler));
+        registry.register("list_dir", Arc::new(ListDirHandler));
+        registry.register("grep_files", Arc::new(GrepFilesHandler));
+        registry
+    }
+}
+
+/// Load the scenarios in `dir` and run the replay ones against `profiles`.
+pub async fn run_replay_scenarios(
+    dir: &Path,
+    profiles: Vec<AgentProfile>,
+) -> Result<EvalReport, super::ScenarioError> {
+    let scenarios = super::load_scenarios(dir)?;
+    Ok(EvalRunner::new(profiles, dir).run_replay(&scenarios).await)
+}
+
+#[derive(Default)]
+struct RecordingObserver {
+    tool_calls: Vec<(String, Value)>,
+    steps: usize,
+}
+
+impl ToolLoopObserver for RecordingObserver {
+    fn on_completion(
+        &mut self,
+        _latency: Duration,
+        _result: Result<Option<Usage>, &CompletionError>,
+    ) {
+        self.steps += 1;
+    }
+
+    fn on_tool_call_begin(&mut self, _call_id: &str, tool_name: &str, arguments: &Value) {
+        self.tool_calls
+            .push((tool_name.to_string(), arguments.clone()));
+    }
+}
+
+/// Answers with the transcript's scripted result for the call, or `"ok"`.
+struct ScriptedTool {
+    name: String,
+    results: Arc<HashMap<String, String>>,
+}
+
+#[async_trait]
+impl ToolHandler for ScriptedTool {
+    fn kind(&self) -> ToolKind {
+        ToolKind::Function
+    }
+
+    async fn handle(&self, invocation: ToolInvocation) -> ToolResult<ToolOutput> {
+        let result = self
+            .results
+            .get(&invocation.call_id)
+            .map_or("ok", String::as_str);
+        Ok(ToolOutput::success(result))
+    }
+
+    fn schema(&self) -> ToolSpec {
+        ToolSpec::new(&self.name, "scripted tool")
+    }
+}
@@ -0,0 +1,439 @@
+//! Scenario files and the assertions they make about a run.
+//!
+//! ```yaml
+//! name: build_error_routes_to_resolver
+//! prompt: cargo build fails with a compilation error
+//! profile: build_error_resolver   # optional; the router picks one when absent
+//! agent:
+//!   max_steps: 4
+//! transcript:                      # scripted model responses, one per completion
+//!   - tool_calls:
+//!       - name: read_file
+//!         arguments: { file_path: src/main.rs }
+//!         result: "fn main() {}"
+//!   - text: Fixed the mismatched types.
+//! assert:
+//!   - selected_profile: build_error_resolver
+//!   - tool_called: { name: read_file, args: { file_path: 'src/main\.rs' } }
+//!   - answer_contains: mismatched types
+//!   - max_steps: 2
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::Value;
+use thiserror::Error;
+
+use super::replay::ReplayTurn;
+
+/// Errors loading scenario files.
+#[derive(Debug, Error)]
+pub enum ScenarioError {
+    #[error("failed to read {}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("invalid scenario {}: {source}", path.display())]
+    Yaml {
+        path: PathBuf,
+        source: serde_yaml::Error,
+    },
+
+    #[error("invalid scenario '{name}': {reason}")]
+    Invalid { name: String, reason: String },
+}
+
+/// One evaluation case: a prompt, the agent that answers it, and what must hold afterwards.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    pub name: String,
+    pub prompt: String,
+    /// Profile to run. When absent the profile router selects one from `prompt`, and no
+    /// profile at all is a valid outcome.
+    #[serde(default)]
+    pub profile: Option<String>,
+    #[serde(default)]
+    pub agent: AgentSettings,
+    /// Scripted model responses, consumed one per completion. Ignored when `live` is set.
+    #[serde(default)]
+    pub transcript: Vec<ReplayTurn>,
+    /// Run against a real provider (`libra ai eval --live`) instead of `transcript`.
+    #[serde(default)]
+    pub live: bool,
+    /// Written as single-key maps (`- answer_contains: text`) rather than YAML tags.
+    #[serde(
+        default,
+        rename = "assert",
+        deserialize_with = "serde_yaml::with::singleton_map_recursive::deserialize"
+    )]
+    pub assertions: Vec<Assertion>,
+}
+
+/// Agent overrides applied on top of the selected profile.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AgentSettings {
+    /// System prompt; defaults to the profile's.
+    pub preamble: Option<String>,
+    pub max_steps: Option<usize>,
+    pub temperature: Option<f64>,
+}
+
+/// A check made against a finished run.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum Assertion {
+    /// The final answer contains this text.
+    AnswerContains(String),
+    /// The final answer matches this regex.
+    AnswerMatches(String),
+    /// The final answer equals this text, ignoring surrounding whitespace.
+    AnswerEquals(String),
+    /// The router (or `profile`) chose this profile.
+    SelectedProfile(String),
+    /// A call to `name` was made whose arguments match every regex in `args`. String
+    /// arguments are matched as-is, others against their JSON text.
+    ToolCalled {
+        name: String,
+        #[serde(default)]
+        args: BTreeMap<String, String>,
+    },
+    /// The run took at most this many model completions.
+    MaxSteps(usize),
+}
+
+/// What a scenario run produced, as seen by [`Assertion::check`].
+#[derive(Debug, Clone, Default)]
+pub struct RunRecord {
+    pub answer: String,
+    pub selected_profile: Option<String>,
+    /// Tool calls in order, as `(name, arguments)`.
+    pub tool_calls: Vec<(String, Value)>,
+    /// Model completions made.
+    pub steps: usize,
+}
+
+impl Scenario {
+    /// Parse one scenario from YAML and check its regexes and transcript up front.
+    pub fn from_yaml(source: &str, path: &Path) -> Result<Self, ScenarioError> {
+        let scenario: Scenario =
+            serde_yaml::from_str(source).map_err(|source| ScenarioError::Yaml {
+                path: path.to_path_buf(),
+                source,
+            })?;
+        scenario.validate()?;
+        Ok(scenario)
+    }
+
+    fn validate(&self) -> Result<(), ScenarioError> {
+        let invalid = |reason: String| ScenarioError::Invalid {
+            name: self.name.clone(),
+            reason,
+        };
+        if !self.live && self.transcript.is_empty() {
+            return Err(invalid(
+                "replay scenarios need a transcript (or set `live: true`)".to_string(),
+            ));
+        }
+        for assertion in &self.assertions {
+            let patterns: Vec<&String> = match assertion {
+                Assertion::AnswerMatches(pattern) => vec![pattern],
+                Assertion::ToolCalled { args, .. } => args.values().collect(),
+                _ => Vec::new(),
+            };
+            for pattern in patterns {
+                Regex::new(pattern).map_err(|e| invalid(format!("bad regex {pattern:?}: {e}")))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Load every `*.yaml`/`*.yml` scenario in `dir`, ordered by file name.
+pub fn load_scenarios(dir: &Path) -> Result<Vec<(PathBuf, Scenario)>, ScenarioError> {
+    let io_error = |path: &Path| {
+        let path = path.to_path_buf();
+        move |source| ScenarioError::Io { path, source }
+    };
+    let mut paths = Vec::new();
+    for entry in std::fs::read_dir(dir).map_err(io_error(dir))? {
+        let path = entry.map_err(io_error(dir))?.path();
+        if path
+            .extension()
+            .is_some_and(|ext| ext == "yaml" || ext == "yml")
+        {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    paths
+        .into_iter()
+        .map(|path| {
+            let source = std::fs::read_to_string(&path).map_err(io_error(&path))?;
+            let scenario = Scenario::from_yaml(&source, &path)?;
+            Ok((path, scenario))
+        })
+        .collect()
+}
+
+impl Assertion {
+    /// `Err` describes the mismatch, with a diff for [`Assertion::AnswerEquals`].
+    pub fn check(&self, run: &RunRecord) -> Result<(), String> {
+        match self {
+            Assertion::AnswerContains(needle) => {
+                if run.answer.contains(needle.as_str()) {
+                    Ok(())
+                } else {
+                    Err(format!(
+                        "answer does not contain {needle:?}\n{}",
+                        quote(&run.answer)
+                    ))
+                }
+            }
+            Assertion::AnswerMatches(pattern) => {
+                let regex = Regex::new(pattern).map_err(|e| e.to_string())?;
+                if regex.is_match(&run.answer) {
+                    Ok(())
+                } else {
+                    Err(format!(
+                        "answer does not match /{pattern}/\n{}",
+                        quote(&run.answer)
+                    ))
+                }
+            }
+            Assertion::AnswerEquals(expected) => {
+                if run.answer.trim() == expected.trim() {
+                    return Ok(());
+                }
+                let expected = format!("{}\n", expected.trim());
+                let actual = format!("{}\n", run.answer.trim());
+                let diff = similar::TextDiff::from_lines(&expected, &actual)
+                    .unified_diff()
+                    .header("expected", "answer")
+                    .to_string();
+                Err(format!("answer differs from expected\n{diff}"))
+            }
+            Assertion::SelectedProfile(expected) => match &run.selected_profile {
+                Some(actual) if actual == expected => Ok(()),
+                actual => Err(format!(
+                    "expected profile '{expected}', selected {}",
+                    actual
+                        .as_ref()
+                        .map_or("none".to_string(), |name| format!("'{name}'"))
+                )),
+            },
+            Assertion::ToolCalled { name, args } => {
+                let calls: Vec<&Value> = run
+                    .tool_calls
+                    .iter()
+                    .filter(|(called, _)| called == name)
+                    .map(|(_, arguments)| arguments)
+                    .collect();
+                if calls.iter().any(|arguments| args_match(args, arguments)) {
+                    return Ok(());
+                }
+                if calls.is_empty() {
+                    let called: Vec<&str> = run
+                        .tool_calls
+                        .iter()
+                        .map(|(name, _)| name.as_str())
+                        .collect();
+                    Err(format!(
+                        "tool '{name}' was not called (called: [{}])",
+                        called.join(", ")
+                    ))
+                } else {
+                    let seen: Vec<String> = calls.iter().map(|a| a.to_string()).collect();
+                    Err(format!(
+                        "no call to '{name}' had arguments matching {args:?}; got:\n{}",
+                        quote(&seen.join("\n"))
+                    ))
+                }
+            }
+            Assertion::MaxSteps(limit) => {
+                if run.steps <= *limit {
+                    Ok(())
+                } else {
+                    Err(format!(
+                        "took {} steps, expected at most {limit}",
+                        run.steps
+                    ))
+                }
+            }
+        }
+    }
+}
+
+fn args_match(patterns: &BTreeMap<String, String>, arguments: &Value) -> bool {
+    patterns.iter().all(|(key, pattern)| {
+        let Some(value) = arguments.get(key) else {
+            return false;
+        };
+        let text = match value {
+            Value::String(text) => text.clone(),
+            other => other.to_string(),
+        };
+        Regex::new(pattern).is_ok_and(|regex| regex.is_match(&text))
+    })
+}
+
+/// Indent `text` under a failure message.
+fn quote(text: &str) -> String {
+    text.lines()
+        .map(|line| format!("    | {line}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    const SCENARIO: &str = r#"
+name: sample
+prompt: fix the build
+profile: build_error_resolver
+agent:
+  max_steps: 3
+transcript:
+  - tool_calls:
+      - name: read_file
+        arguments: { file_path: src/main.rs }
+        result: "fn main() {}"
+  - text: done
+assert:
+  - answer_contains: done
+  - answer_matches: "^d.ne$"
+  - answer_equals: done
+  - selected_profile: build_error_resolver
+  - tool_called: { name: read_file, args: { file_path: 'main\.rs$' } }
+  - max_steps: 2
+"#;
+
+    fn record() -> RunRecord {
+        RunRecord {
+            answer: "done".to_string(),
+            selected_profile: Some("build_error_resolver".to_string()),
+            tool_calls: vec![
+                (
+                    "read_file".to_string(),
+                    json!({ "file_path": "src/main.rs" }),
+                ),
+                ("list_dir".to_string(), json!({ "depth": 2 })),
+            ],
+            steps: 2,
+        }
+    }
+
+    #[test]
+    fn test_parse_scenario() {
+        let scenario = Scenario::from_yaml(SCENARIO, Path::new("sample.yaml")).unwrap();
+        assert_eq!(scenario.name, "sample");
+        assert_eq!(scenario.profile.as_deref(), Some("build_error_resolver"));
+        assert_eq!(scenario.agent.max_steps, Some(3));
+        assert!(!scenario.live);
+        assert_eq!(scenario.transcript.len(), 2);
+        assert_eq!(scenario.transcript[0].tool_calls[0].name, "read_file");
+        assert_eq!(scenario.transcript[1].text.as_deref(), Some("done"));
+        assert_eq!(scenario.assertions.len(), 6);
+        assert!(matches!(
+            &scenario.assertions[4],
+            Assertion::ToolCalled { name, args } if name == "read_file" && args.len() == 1
+        ));
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_scenarios() {
+        let path = Path::new("bad.yaml");
+        let unknown_assertion = "name: x\nprompt: p\nlive: true\nassert:\n  - answer_is: y\n";
+        assert!(matches!(
+            Scenario::from_yaml(unknown_assertion, path),
+            Err(ScenarioError::Yaml { .. })
+        ));
+
+        let no_transcript = "name: x\nprompt: p\n";
+        assert!(matches!(
+            Scenario::from_yaml(no_transcript, path),
+            Err(ScenarioError::Invalid { .. })
+        ));
+
+        let bad_regex = "name: x\nprompt: p\nlive: true\nassert:\n  - answer_matches: '('\n";
+        let err = Scenario::from_yaml(bad_regex, path).unwrap_err();
+        assert!(err.to_string().contains("bad regex"), "{err}");
+    }
+
+    #[test]
+    fn test_assertions_pass() {
+        let scenario = Scenario::from_yaml(SCENARIO, Path::new("sample.yaml")).unwrap();
+        let run = record();
+        for assertion in &scenario.assertions {
+            assert_eq!(assertion.check(&run), Ok(()), "{assertion:?}");
+        }
+        let any_args = Assertion::ToolCalled {
+            name: "list_dir".to_string(),
+            args: BTreeMap::new(),
+        };
+        assert!(any_args.check(&run).is_ok());
+        let number_arg = Assertion::ToolCalled {
+            name: "list_dir".to_string(),
+            args: BTreeMap::from([("depth".to_string(), "^2$".to_string())]),
+        };
+        assert!(number_arg.check(&run).is_ok());
+    }
+
+    #[test]
+    fn test_assertion_failures_explain_mismatch() {
+        let run = RunRecord {
+            answer: "line one\nline two".to_string(),
+            ..record()
+        };
+
+        let err = Assertion::AnswerEquals("line one\nline 2".to_string())
+            .check(&run)
+            .unwrap_err();
+        assert!(err.contains("-line 2\n+line two"), "{err}");
+
+        let err = Assertion::AnswerContains("three".to_string())
+            .check(&run)
+            .unwrap_err();
+        assert!(err.contains("    | line two"), "{err}");
+
+        let err = Assertion::SelectedProfile("planner".to_string())
+            .check(&RunRecord::default())
+            .unwrap_err();
+        assert_eq!(err, "expected profile 'planner', selected none");
+
+        let err = Assertion::ToolCalled {
+            name: "apply_patch".to_string(),
+            args: BTreeMap::new(),
+        }
+        .check(&run)
+        .unwrap_err();
+        assert_eq!(
+            err,
+            "tool 'apply_patch' was not called (called: [read_file, list_dir])"
+        );
+
+        let err = Assertion::ToolCalled {
+            name: "read_file".to_string(),
+            args: BTreeMap::from([("file_path".to_string(), "lib\\.rs".to_string())]),
+        }
+        .check(&run)
+        .unwrap_err();
+        assert!(err.contains("src/main.rs"), "{err}");
+
+        let err = Assertion::MaxSteps(1).check(&run).unwrap_err();
+        assert_eq!(err, "took 2 steps, expected at most 1");
+    }
+}
@@ -0,0 +1,565 @@
+//! Machine-readable progress of agent runs, one JSON object per line (`--events`).
+//!
+//! [`EventStreamObserver`] writes every [`ToolLoopObserver`] callback as a [`StreamEvent`] and
+//! flushes after each line, so a consumer such as an IDE plugin sees a run as it happens.
+//! Every event carries the fields
+//!
+//! | field            | meaning                                                    |
+//! |------------------|------------------------------------------------------------|
+//! | `schema_version` | [`EVENT_SCHEMA_VERSION`]; bumped on incompatible changes   |
+//! | `run_id`         | identifies the run; the same in every event of one stream  |
+//! | `seq`            | position in the stream, from 0                             |
+//! | `ts`             | when the event was written, RFC 3339 UTC                   |
+//! | `event`          | the event type, one of the [`EventKind`] variants          |
+//!
+//! followed by the fields of its type:
+//!
+//! | `event`                  | fields                                                  |
+//! |--------------------------|---------------------------------------------------------|
+//! | `run_started`            |                                                         |
+//! | `step_started`           | `step` (from 1)                                         |
+//! | `step_text`              | `text`: text the model wrote alongside tool calls       |
+//! | `reasoning`              | `text`                                                  |
+//! | `completion`             | `latency_ms`, `input_tokens`, `output_tokens`, `error`  |
+//! | `budget`                 | `calls_used`, `tokens_used`, `max_calls`, `max_tokens`  |
+//! | `tool_call_started`      | `call_id`, `tool`, `arguments`                          |
+//! | `tool_call_finished`     | `call_id`, `tool`, `success`, `result`                  |
+//! | `suspicious_tool_result` | `call_id`, `tool`, `rules`                              |
+//! | `retry`                  | `step`, `reason`                                        |
+//! | `run_finished`           | `answer`                                                |
+//! | `run_failed`             | `error`                                                 |
+//!
+//! Optional fields are `null` when unknown. New event types and fields may be added without
+//! a version bump, so consumers should ignore what they do not know.
+//!
+//! Tool arguments and results pass through the run's [`Guardrails`] (outbound direction)
+//! before they are written: redact rules mask what they match, and text that would be
+//! rejected is replaced as a whole with `[REDACTED:<rule>]`.
+//! [`EventStreamObserver::redact_tool_io`] leaves arguments and results out altogether.
+
+use std::{
+    io::{self, Write},
+    sync::Arc,
+    time::Duration,
+};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::Value;
+
+use crate::internal::ai::{
+    agent::ToolLoopObserver,
+    budget::BudgetSnapshot,
+    completion::{CompletionError, GuardrailDirection, Guardrails, Usage},
+    tools::ToolOutput,
+};
+
+/// Version of the [`StreamEvent`] schema written in every event.
+pub const EVENT_SCHEMA_VERSION: u32 = 1;
+
+/// Placeholder for tool arguments and results under [`EventStreamObserver::redact_tool_io`].
+pub const REDACTED: &str = "[REDACTED]";
+
+/// One line of the event stream.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StreamEvent {
+    pub schema_version: u32,
+    pub run_id: String,
+    pub seq: u64,
+    #[serde(serialize_with = "serialize_ts")]
+    pub ts: DateTime<Utc>,
+    #[serde(flatten)]
+    pub kind: EventKind,
+}
+
+/// The type-specific part of a [`StreamEvent`], tagged by `event`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum EventKind {
+    RunStarted,
+    StepStarted {
+        step: usize,
+    },
+    StepText {
+        text: String,
+    },
+    Reasoning {
+        text: String,
+    },
+    Completion {
+        latency_ms: u64,
+        input_tokens: Option<u64>,
+        output_tokens: Option<u64>,
+        error: Option<String>,
+    },
+    Budget {
+        calls_used: u64,
+        tokens_used: u64,
+        max_calls: Option<u64>,
+        max_tokens: Option<u64>,
+    },
+    ToolCallStarted {
+        call_id: String,
+        tool: String,
+        arguments: Value,
+    },
+    ToolCallFinished {
+        call_id: String,
+        tool: String,
+        success: bool,
+        result: Value,
+    },
+    SuspiciousToolResult {
+        call_id: String,
+        tool: String,
+        rules: Vec<String>,
+    },
+    Retry {
+        step: usize,
+        reason: String,
+    },
+    RunFinished {
+        answer: String,
+    },
+    RunFailed {
+        error: String,
+    },
+}
+
+/// Tool-loop observer that writes a [`StreamEvent`] per callback to `W`.
+///
+/// Writing is best-effort: a failed write never interrupts the run; the first error is kept
+/// and available from [`EventStreamObserver::error`].
+pub struct EventStreamObserver<W: Write + Send> {
+    writer: W,
+    run_id: String,
+    seq: u64,
+    guardrails: Option<Arc<Guardrails>>,
+    redact_tool_io: bool,
+    error: Option<io::Error>,
+}
+
+impl<W: Write + Send> EventStreamObserver<W> {
+    /// Stream to `writer` under a new random run id.
+    pub fn new(writer: W) -> Self {
+        Self::with_run_id(writer, uuid::Uuid::new_v4().simple().to_string())
+    }
+
+    pub fn with_run_id(writer: W, run_id: impl Into<String>) -> Self {
+        Self {
+            writer,
+            run_id: run_id.into(),
+            seq: 0,
+            guardrails: None,
+            redact_tool_io: false,
+            error: None,
+        }
+    }
+
+    /// Filter tool arguments and results through `guardrails`, normally the run's own.
+    pub fn guardrails(mut self, guardrails: Arc<Guardrails>) -> Self {
+        self.guardrails = Some(guardrails);
+        self
+    }
+
+    /// Replace every tool argument and result with [`REDACTED`].
+    pub fn redact_tool_io(mut self, redact: bool) -> Self {
+        self.redact_tool_io = redact;
+        self
+    }
+
+    pub fn run_id(&self) -> &str {
+        &self.run_id
+    }
+
+    /// The first write error, if any event could not be written.
+    pub fn error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn emit(&mut self, kind: EventKind) {
+        let event = StreamEvent {
+            schema_version: EVENT_SCHEMA_VERSION,
+            run_id: self.run_id.clone(),
+            seq: self.seq,
+            ts: Utc::now(),
+            kind,
+        };
+        self.seq += 1;
+        let mut line = serde_json::to_string(&event).expect("stream events serialize");
+        line.push('\n');
+        let written = self
+            .writer
+            .write_all(line.as_bytes())
+            .and_then(|()| self.writer.flush());
+        if let Err(e) = written
+            && self.error.is_none()
+        {
+            self.error = Some(e);
+        }
+    }
+
+    fn filter_tool_io(&self, mut value: Value) -> Value {
+        if self.redact_tool_io {
+            return Value::String(REDACTED.to_string());
+        }
+        if let Some(guardrails) = &self.guardrails {
+            filter_value(guardrails, &mut value);
+        }
+        value
+    }
+}
+
+impl<W: Write + Send> ToolLoopObserver for EventStreamObserver<W> {
+    fn on_run_start(&mut self) {
+        self.emit(EventKind::RunStarted);
+    }
+
+    fn on_step_start(&mut self, step: usize) {
+        self.emit(EventKind::StepStarted { step });
+    }
+
+    fn on_retry(&mut self, step: usize, reason: &str) {
+        self.emit(EventKind::Retry {
+            step,
+            reason: reason.to_string(),
+        });
+    }
+
+    fn on_run_end(&mut self, result: Result<&str, &CompletionError>) {
+        self.emit(match result {
+            Ok(answer) => EventKind::RunFinished {
+                answer: answer.to_string(),
+            },
+            Err(e) => EventKind::RunFailed {
+                error: e.to_string(),
+            },
+        });
+    }
+
+    fn on_assistant_step_text(&mut self, text: &str) {
+        self.emit(EventKind::StepText {
+            text: text.to_string(),
+        });
+    }
+
+    fn on_assistant_reasoning(&mut self, text: &str) {
+        self.emit(EventKind::Reasoning {
+            text: text.to_string(),
+        });
+    }
+
+    fn on_completion(
+        &mut self,
+        latency: Duration,
+        result: Result<Option<Usage>, &CompletionError>,
+    ) {
+        let (usage, error) = match result {
+            Ok(usage) => (usage, None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        self.emit(EventKind::Completion {
+            latency_ms: latency.as_millis() as u64,
+            input_tokens: usage.map(|usage| usage.input_tokens),
+            output_tokens: usage.map(|usage| usage.output_tokens),
+            error,
+        });
+    }
+
+    fn on_budget(&mut self, snapshot: &BudgetSnapshot) {
+        self.emit(EventKind::Budget {
+            calls_used: snapshot.calls_used,
+            tokens_used: snapshot.tokens_used,
+            max_calls: snapshot.max_calls,
+            max_tokens: snapshot.max_tokens,
+        });
+    }
+
+    fn on_tool_call_begin(&mut self, call_id: &str, tool_name: &str, arguments: &Value) {
+        let arguments = self.filter_tool_io(arguments.clone());
+        self.emit(EventKind::ToolCallStarted {
+            call_id: call_id.to_string(),
+            tool: tool_name.to_string(),
+            arguments,
+        });
+    }
+
+    fn on_tool_call_end(
+        &mut self,
+        call_id: &str,
+        tool_name: &str,
+        result: &Result<ToolOutput, String>,
+    ) {
+        let (success, result) = match result {
+            Ok(output) => (output.is_success(), output.clone().into_response()),
+            Err(message) => (false, serde_json::json!({ "error": message })),
+        };
+        let result = self.filter_tool_io(result);
+        self.emit(EventKind::ToolCallFinished {
+            call_id: call_id.to_string(),
+            tool: tool_name.to_string(),
+            success,
+            result,
+        });
+    }
+
+    fn on_suspicious_tool_result(&mut self, call_id: &str, tool_name: &str, rules: &[String]) {
+        self.emit(EventKind::SuspiciousToolResult {
+            call_id: call_id.to_string(),
+            tool: tool_name.to_string(),
+            rules: rules.to_vec(),
+        });
+    }
+}
+
+/// Apply the outbound guardrails to every string in `value`. Unlike a request, an event is
+/// never rejected: text that would be is replaced whole.
+fn filter_value(guardrails: &Guardrails, value: &mut Value) {
+    match value {
+        Value::String(text) => match guardrails.filter(GuardrailDirection::Outbound, text) {
+            Ok(filtered) => *text = filtered.into_owned(),
+            Err(CompletionError::PolicyViolation { rule, .. }) => {
+                *text = format!("[REDACTED:{rule}]")
+            }
+            Err(_) => *text = REDACTED.to_string(),
+        },
+        Value::Array(items) => items
+            .iter_mut()
+            .for_each(|item| filter_value(guardrails, item)),
+        Value::Object(map) => map
+            .values_mut()
+            .for_each(|item| filter_value(guardrails, item)),
+        Value::Null | Value::Bool(_) | Value::Number(_) => {}
+    }
+}
+
+fn serialize_ts<S: Serializer>(ts: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&ts.to_rfc3339_opts(SecondsFormat::Millis, true))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::internal::ai::{
+        agent::{ToolLoopConfig, run_tool_loop_with_observer},
+        budget::SharedBudget,
+        completion::GuardrailRule,
+        eval::replay::{ReplayModel, ReplayToolCall, ReplayTurn},
+        tools::{ToolRegistryBuilder, handlers::ReadFileHandler},
+    };
+
+    /// Records what the observer wrote, split at every flush.
+    #[derive(Clone, Default)]
+    struct CapturingWriter {
+        pending: Arc<Mutex<Vec<u8>>>,
+        flushed: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Write for CapturingWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.pending.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            let pending = std::mem::take(&mut *self.pending.lock().unwrap());
+            self.flushed
+                .lock()
+                .unwrap()
+                .push(String::from_utf8(pending).unwrap());
+            Ok(())
+        }
+    }
+
+    /// Step 1 reads a file holding a secret, step 2 answers.
+    async fn run_two_steps(observer: &mut EventStreamObserver<CapturingWriter>, temp: &TempDir) {
+        let file = temp.path().join("notes.txt");
+        std::fs::write(&file, "api key: secret-123\n").unwrap();
+        let model = ReplayModel::new(vec![
+            ReplayTurn {
+                text: Some("Reading the notes.".to_string()),
+                tool_calls: vec![
+                    serde_json::from_value::<ReplayToolCall>(json!({
+                        "name": "read_file",
+                        "arguments": {"file_path": file.display().to_string()},
+                    }))
+                    .unwrap(),
+                ],
+            },
+            ReplayTurn {
+                text: Some("The notes hold a key.".to_string()),
+                ..Default::default()
+            },
+        ]);
+        let registry = ToolRegistryBuilder::with_working_dir(temp.path().to_path_buf())
+            .register("read_file", Arc::new(ReadFileHandler))
+            .build();
+        let config = ToolLoopConfig {
+            budget: Some(SharedBudget::new().with_max_calls(5)),
+            untrusted_content: None,
+            ..Default::default()
+        };
+        let answer = run_tool_loop_with_observer(
+            &model,
+            "What is in the notes?",
+            &registry,
+            config,
+            observer,
+        )
+        .await
+        .unwrap();
+        assert_eq!(answer, "The notes hold a key.");
+    }
+
+    fn parse(writer: &CapturingWriter) -> Vec<StreamEvent> {
+        writer
+            .flushed
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|line| {
+                // every flush carries exactly one complete event
+                assert!(line.ends_with('\n'), "{line:?}");
+                assert_eq!(line.matches('\n').count(), 1, "{line:?}");
+                serde_json::from_str(line).unwrap()
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_two_step_run_streams_ordered_events() {
+        let temp = TempDir::new().unwrap();
+        let writer = CapturingWriter::default();
+        let guardrails =
+            Guardrails::new().redact(GuardrailRule::new("api_key", r"secret-\d+").unwrap());
+        let mut observer = EventStreamObserver::with_run_id(writer.clone(), "run-1")
+            .guardrails(Arc::new(guardrails));
+        run_two_steps(&mut observer, &temp).await;
+        assert!(observer.error().is_none());
+        assert!(writer.pending.lock().unwrap().is_empty());
+
+        let events = parse(&writer);
+        for (seq, event) in events.iter().enumerate() {
+            assert_eq!(event.schema_version, EVENT_SCHEMA_VERSION);
+            assert_eq!(event.run_id, "run-1");
+            assert_eq!(event.seq, seq as u64);
+        }
+        let kinds = events
+            .iter()
+            .map(|event| {
+                serde_json::to_value(&event.kind).unwrap()["event"]
+                    .as_str()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            [
+                "run_started",
+                "step_started",
+                "budget",
+                "completion",
+                "step_text",
+                "tool_call_started",
+                "tool_call_finished",
+                "step_started",
+                "budget",
+                "completion",
+                "run_finished",
+            ]
+        );
+
+        assert_eq!(events[1].kind, EventKind::StepStarted { step: 1 });
+        assert!(matches!(
+            events[2].kind,
+            EventKind::Budget {
+                calls_used: 1,
+                max_calls: Some(5),
+                ..
+            }
+        ));
+        let EventKind::ToolCallFinished {
+            call_id,
+            tool,
+            success,
+            result,
+        } = &events[6].kind
+        else {
+            panic!("{:?}", events[6]);
+        };
+        assert_eq!(
+            (call_id.as_str(), tool.as_str(), *success),
+            ("replay_0_0", "read_file", true)
+        );
+        let content = result["content"].as_str().unwrap();
+        assert!(content.contains("api key: [REDACTED:api_key]"), "{content}");
+        assert_eq!(
+            events[10].kind,
+            EventKind::RunFinished {
+                answer: "The notes hold a key.".to_string()
+            }
+        );
+
+        // the wire format keeps the documented field names
+        let line = &writer.flushed.lock().unwrap()[5];
+        let raw: Value = serde_json::from_str(line).unwrap();
+        assert_eq!(raw["event"], "tool_call_started");
+        assert_eq!(raw["tool"], "read_file");
+        assert!(raw["arguments"]["file_path"].is_string());
+        assert!(raw["ts"].as_str().unwrap().ends_with('Z'));
+    }
+
+    #[tokio::test]
+    async fn test_redacted_tool_io_and_failed_run() {
+        let temp = TempDir::new().unwrap();
+        let writer = CapturingWriter::default();
+        let mut observer = EventStreamObserver::new(writer.clone()).redact_tool_io(true);
+        run_two_steps(&mut observer, &temp).await;
+        let events = parse(&writer);
+        let run_id = observer.run_id().to_string();
+        assert!(events.iter().all(|event| event.run_id == run_id));
+        let EventKind::ToolCallStarted { arguments, .. } = &events[5].kind else {
+            panic!("{:?}", events[5]);
+        };
+        assert_eq!(arguments, REDACTED);
+        let EventKind::ToolCallFinished { result, .. } = &events[6].kind else {
+            panic!("{:?}", events[6]);
+        };
+        assert_eq!(result, REDACTED);
+
+        // a model that has nothing to say ends the run with an error event
+        let writer = CapturingWriter::default();
+        let mut observer = EventStreamObserver::new(writer.clone());
+        let registry = ToolRegistryBuilder::with_working_dir(temp.path().to_path_buf()).build();
+        let err = run_tool_loop_with_observer(
+            &ReplayModel::new(Vec::new()),
+            "hello",
+            &registry,
+            ToolLoopConfig::default(),
+            &mut observer,
+        )
+        .await
+        .unwrap_err();
+        let events = parse(&writer);
+        assert!(matches!(
+            &events[2].kind,
+            EventKind::Completion { error: Some(_), .. }
+        ));
+        assert_eq!(
+            events.last().unwrap().kind,
+            EventKind::RunFailed {
+                error: err.to_string()
+            }
+        );
+    }
+}
@@ -0,0 +1,614 @@
+//! `libra ai explain`: explain a commit, a file or a range of commits.
+//!
+//! The argument decides what is explained ([`ExplainTarget::resolve`]):
+//!
+//! - a **revision** (`HEAD~2`, a branch, a hash) gets the commit message and its diff
+//!   against the first parent, packed with [`DiffPacker`];
+//! - a **path** gets the file at `HEAD` with line numbers, preceded by blame-derived
+//!   authorship highlights: the largest runs of lines written by one commit, and who wrote
+//!   how many lines;
+//! - a **range** (`a..b`, with an empty side meaning `HEAD`) gets the commits reachable from
+//!   `b` but not `a`, oldest first.
+//!
+//! The context is kept under half of the model's context window and sent to the
+//! `explainer` profile (embedded; a project or user profile of the same name replaces it),
+//! which must answer with an [`ExplainReport`] in strict JSON. The report is rendered as
+//! plain text, Markdown with links to the files the context covered, or JSON
+//! ([`ExplainFormat`]).
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use clap::ValueEnum;
+use git_internal::{
+    Diff,
+    hash::ObjectHash,
+    internal::object::{blob::Blob, commit::Commit, tree::Tree},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    command::{
+        blame::{BlameArgs, blame_file},
+        diff::parse_revision,
+        load_object,
+        log::get_reachable_commits,
+    },
+    internal::ai::{
+        CompletionModel, Prompt,
+        agent::profile::{AgentProfile, build_agent_from_profile, parse_agent_profile},
+        completion::CompletionError,
+        diff_context::{DiffPacker, estimate_tokens},
+    },
+    utils::{object_ext::TreeExt, util},
+};
+
+/// Name of the profile explanations run with.
+pub const EXPLAIN_PROFILE: &str = "explainer";
+
+const EMBEDDED_PROFILE: &str = include_str!("agent/profile/embedded/explainer.md");
+
+/// How many runs of lines written by one commit a file explanation highlights.
+const MAX_HIGHLIGHTS: usize = 5;
+
+/// The embedded [`EXPLAIN_PROFILE`], used when no loaded profile has that name.
+pub fn embedded_profile() -> AgentProfile {
+    parse_agent_profile(EMBEDDED_PROFILE).expect("embedded explainer profile is valid")
+}
+
+/// The profile named [`EXPLAIN_PROFILE`] among `profiles`, or the embedded one.
+pub fn explain_profile(profiles: Vec<AgentProfile>) -> AgentProfile {
+    profiles
+        .into_iter()
+        .find(|profile| profile.name == EXPLAIN_PROFILE)
+        .unwrap_or_else(embedded_profile)
+}
+
+/// What `libra ai explain <arg>` explains.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExplainTarget {
+    Revision(ObjectHash),
+    /// A file at `HEAD`, relative to the working directory root.
+    Path(PathBuf),
+    /// Commits reachable from `to` but not from `from`.
+    Range {
+        from: ObjectHash,
+        to: ObjectHash,
+    },
+}
+
+impl ExplainTarget {
+    /// Decide what `arg` names, the way `libra diff` tells revisions from paths: an argument
+    /// that is both a revision and an existing file is ambiguous.
+    pub async fn resolve(arg: &str) -> Result<Self, String> {
+        let exists = Path::new(arg).exists();
+        match parse_revision(arg).await.as_deref() {
+            Some(_) if exists => Err(format!(
+                "ambiguous argument '{arg}': both revision and filename"
+            )),
+            Some([commit]) => Ok(ExplainTarget::Revision(*commit)),
+            Some([from, to]) => Ok(ExplainTarget::Range {
+                from: *from,
+                to: *to,
+            }),
+            _ if exists => Ok(ExplainTarget::Path(util::to_workdir_path(arg))),
+            _ => Err(format!(
+                "ambiguous argument '{arg}': unknown revision or path not in the working tree"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ExplainTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExplainTarget::Revision(commit) => write!(f, "commit {}", short_hash(commit)),
+            ExplainTarget::Path(path) => write!(f, "file {}", path.display()),
+            ExplainTarget::Range { from, to } => {
+                write!(f, "range {}..{}", short_hash(from), short_hash(to))
+            }
+        }
+    }
+}
+
+/// The document sent to the explainer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExplainContext {
+    pub document: String,
+    /// Files the document covers, in order; linked from Markdown output.
+    pub files: Vec<String>,
+}
+
+impl ExplainContext {
+    /// Assemble the context for `target` within `budget` estimated tokens.
+    pub async fn assemble(target: &ExplainTarget, budget: usize) -> Result<Self, String> {
+        match target {
+            ExplainTarget::Revision(commit) => revision_context(commit, budget),
+            ExplainTarget::Path(path) => path_context(path, budget).await,
+            ExplainTarget::Range { from, to } => Ok(range_context(from, to, budget).await),
+        }
+    }
+}
+
+fn revision_context(commit_id: &ObjectHash, budget: usize) -> Result<ExplainContext, String> {
+    let commit = load_object::<Commit>(commit_id).map_err(|e| e.to_string())?;
+    let mut document = format!(
+        "## Commit {commit_id}\nAuthor: {} <{}>\n\n{}\n",
+        commit.author.name,
+        commit.author.email,
+        commit.message.trim()
+    );
+
+    let old_blobs = match commit.parent_commit_ids.first() {
+        Some(parent) => tree_blobs(&load_object::<Commit>(parent).map_err(|e| e.to_string())?)?,
+        None => Vec::new(),
+    };
+    let items = Diff::diff(old_blobs, tree_blobs(&commit)?, Vec::new(), read_blob);
+    let files = items.iter().map(|item| item.path.clone()).collect();
+    let diff: String = items.into_iter().map(|item| item.data).collect();
+
+    let header = "\n## Diff against the first parent\n";
+    let room = budget.saturating_sub(estimate_tokens(&document) + estimate_tokens(header));
+    document.push_str(header);
+    document.push_str(&DiffPacker::new(room).pack(&diff).document);
+    Ok(ExplainContext { document, files })
+}
+
+async fn path_context(path: &Path, budget: usize) -> Result<ExplainContext, String> {
+    let blame = blame_file(&BlameArgs {
+        file: util::workdir_to_absolute(path).display().to_string(),
+        commit: "HEAD".to_string(),
+        line_range: None,
+        no_cache: false,
+        follow: false,
+    })
+    .await?;
+    let lines = blame.lines;
+
+    // Runs of consecutive lines last changed by the same commit, largest first.
+    let mut runs: Vec<(usize, usize)> = Vec::new();
+    for (index, line) in lines.iter().enumerate() {
+        match runs.last_mut() {
+            Some((start, end)) if lines[*start].commit_id == line.commit_id => *end = index,
+            _ => runs.push((index, index)),
+        }
+    }
+    runs.sort_by_key(|&(start, end)| (std::cmp::Reverse(end - start), start));
+
+    let mut document = format!("## File {}\n\n### Authorship highlights\n", path.display());
+    for &(start, end) in runs.iter().take(MAX_HIGHLIGHTS) {
+        let line = &lines[start];
+        let subject = load_object::<Commit>(&line.commit_id)
+            .map(|commit| subject(&commit).to_string())
+            .unwrap_or_default();
+        let span = if start == end {
+            format!("line {}", start + 1)
+        } else {
+            format!("lines {}-{}", start + 1, end + 1)
+        };
+        document.push_str(&format!(
+            "- {span}: {} {} ({})\n",
+            short_hash(&line.commit_id),
+            subject,
+            line.author
+        ));
+    }
+    let mut authors: BTreeMap<&str, usize> = BTreeMap::new();
+    for line in &lines {
+        *authors.entry(&line.author).or_default() += 1;
+    }
+    let mut authors = authors.into_iter().collect::<Vec<_>>();
+    authors.sort_by_key(|&(name, count)| (std::cmp::Reverse(count), name));
+    let authors = authors
+        .iter()
+        .map(|(name, count)| format!("{name} ({count})"))
+        .collect::<Vec<_>>();
+    document.push_str(&format!("- lines by author: {}\n", authors.join(", ")));
+
+    document.push_str("\n### Content\n");
+    let mut shown = 0;
+    for line in &lines {
+        let numbered = format!("{:>5}  {}\n", line.line_number, line.content);
+        if estimate_tokens(&document) + estimate_tokens(&numbered) > budget {
+            break;
+        }
+        document.push_str(&numbered);
+        shown += 1;
+    }
+    if shown < lines.len() {
+        document.push_str(&format!("[{} more lines omitted]\n", lines.len() - shown));
+    }
+    Ok(ExplainContext {
+        document,
+        files: vec![path.display().to_string()],
+    })
+}
+
+async fn range_context(from: &ObjectHash, to: &ObjectHash, budget: usize) -> ExplainContext {
+    let excluded: HashSet<ObjectHash> = get_reachable_commits(from.to_string(), None)
+        .await
+        .into_iter()
+        .map(|commit| commit.id)
+        .collect();
+    // Reachable commits come newest first; reversing keeps commits made within the same
+    // second in order under the stable sort.
+    let mut commits = get_reachable_commits(to.to_string(), None)
+        .await
+        .into_iter()
+        .rev()
+        .filter(|commit| !excluded.contains(&commit.id))
+        .collect::<Vec<_>>();
+    commits.sort_by_key(|commit| commit.committer.timestamp);
+
+    let mut document = format!(
+        "## Commits {}..{} ({}, oldest first)\n",
+        short_hash(from),
+        short_hash(to),
+        commits.len()
+    );
+    let mut shown = 0;
+    for commit in &commits {
+        let line = format!(
+            "- {} {} ({})\n",
+            short_hash(&commit.id),
+            subject(commit),
+            commit.author.name
+        );
+        if estimate_tokens(&document) + estimate_tokens(&line) > budget {
+            break;
+        }
+        document.push_str(&line);
+        shown += 1;
+    }
+    if shown < commits.len() {
+        document.push_str(&format!(
+            "[{} later commits omitted]\n",
+            commits.len() - shown
+        ));
+    }
+    ExplainContext {
+        document,
+        files: Vec::new(),
+    }
+}
+
+fn tree_blobs(commit: &Commit) -> Result<Vec<(PathBuf, ObjectHash)>, String> {
+    Ok(load_object::<Tree>(&commit.tree_id)
+        .map_err(|e| e.to_string())?
+        .get_plain_items())
+}
+
+fn read_blob(_: &PathBuf, hash: &ObjectHash) -> Vec<u8> {
+    load_object::<Blob>(hash)
+        .map(|blob| blob.data)
+        .unwrap_or_default()
+}
+
+fn short_hash(hash: &ObjectHash) -> String {
+    hash.to_string().chars().take(7).collect()
+}
+
+fn subject(commit: &Commit) -> &str {
+    commit.message.trim().lines().next().unwrap_or_default()
+}
+
+/// How `libra ai explain` prints the report.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ExplainFormat {
+    #[default]
+    Text,
+    Markdown,
+    Json,
+}
+
+/// The explainer's answer. Anything but exactly this shape is rejected.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExplainReport {
+    pub summary: String,
+    #[serde(default)]
+    pub notable_changes: Vec<String>,
+    #[serde(default)]
+    pub potential_risks: Vec<String>,
+}
+
+impl ExplainReport {
+    /// Parse a model answer. A surrounding Markdown code fence is tolerated; any other text
+    /// or an unknown field is an error.
+    pub fn parse(answer: &str) -> Result<Self, CompletionError> {
+        let answer = answer.trim();
+        let json = answer
+            .strip_prefix("```json")
+            .or_else(|| answer.strip_prefix("```"))
+            .and_then(|rest| rest.strip_suffix("```"))
+            .unwrap_or(answer);
+        serde_json::from_str(json.trim()).map_err(|e| {
+            CompletionError::ResponseError(format!(
+                "explanation does not match the report schema: {e}"
+            ))
+        })
+    }
+}
+
+/// The result of [`explain`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Explanation {
+    pub target: ExplainTarget,
+    pub context: ExplainContext,
+    pub report: ExplainReport,
+}
+
+/// Explain `target` with `model` under `profile`, keeping the context within half of the
+/// model's context window. With a `deadline`, the agent gives up after it; see
+/// [`AgentBuilder::deadline`](crate::internal::ai::AgentBuilder::deadline).
+pub async fn explain<M: CompletionModel>(
+    model: M,
+    profile: &AgentProfile,
+    target: ExplainTarget,
+    deadline: Option<Duration>,
+) -> Result<Explanation, CompletionError> {
+    let budget = DiffPacker::for_model(&model.capabilities()).budget();
+    let context = ExplainContext::assemble(&target, budget)
+        .await
+        .map_err(|e| CompletionError::RequestError(e.into()))?;
+    let mut builder = build_agent_from_profile(model, profile);
+    if let Some(deadline) = deadline {
+        builder = builder.deadline(deadline);
+    }
+    let answer: String = builder
+        .build()
+        .prompt(format!("Explain this {target}:\n\n{}", context.document))
+        .await?;
+    Ok(Explanation {
+        report: ExplainReport::parse(&answer)?,
+        target,
+        context,
+    })
+}
+
+impl Explanation {
+    /// The report in `format`, ending with a newline.
+    pub fn render(&self, format: ExplainFormat) -> String {
+        let report = &self.report;
+        match format {
+            ExplainFormat::Text => {
+                let mut out = format!("{}\n\n{}\n", self.target, report.summary);
+                for (title, items) in [
+                    ("Notable changes", &report.notable_changes),
+                    ("Potential risks", &report.potential_risks),
+                ] {
+                    out.push_str(&format!("\n{title}:\n"));
+                    if items.is_empty() {
+                        out.push_str("  (none)\n");
+                    }
+                    for item in items {
+                        out.push_str(&format!("  - {item}\n"));
+                    }
+                }
+                out
+            }
+            ExplainFormat::Markdown => {
+                let mut out = format!("# {}\n\n{}\n", self.target, report.summary);
+                let links = self
+                    .context
+                    .files
+                    .iter()
+                    .map(|file| format!("[{file}]({file})"))
+                    .collect::<Vec<_>>();
+                for (title, items) in [
+                    ("Notable changes", &report.notable_changes),
+                    ("Potential risks", &report.potential_risks),
+                    ("Files", &links),
+                ] {
+                    if items.is_empty() {
+                        continue;
+                    }
+                    out.push_str(&format!("\n## {title}\n\n"));
+                    for item in items {
+                        out.push_str(&format!("- {item}\n"));
+                    }
+                }
+                out
+            }
+            ExplainFormat::Json => {
+                let value = serde_json::json!({
+                    "target": self.target.to_string(),
+                    "files": self.context.files,
+                    "summary": report.summary,
+                    "notable_changes": report.notable_changes,
+                    "potential_risks": report.potential_risks,
+                });
+                format!(
+                    "{}\n",
+                    serde_json::to_string_pretty(&value).expect("report serializes")
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use serial_test::serial;
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::{
+        command::{
+            add::{self, AddArgs},
+            commit::{self, CommitArgs},
+        },
+        internal::{
+            ai::eval::replay::{ReplayModel, ReplayTurn},
+            head::Head,
+        },
+        utils::test,
+    };
+
+    const ANSWER: &str = r#"{"summary": "Parser work.", "notable_changes": ["src/parser.rs: handles empty input"], "potential_risks": []}"#;
+
+    fn replay_model() -> ReplayModel {
+        ReplayModel::new(vec![ReplayTurn {
+            text: Some(ANSWER.to_string()),
+            ..Default::default()
+        }])
+    }
+
+    async fn commit_file(path: &str, content: &str, message: &str) -> ObjectHash {
+        test::ensure_file(path, Some(content));
+        add::execute(AddArgs::try_parse_from(["add", path]).unwrap()).await;
+        commit::execute(CommitArgs {
+            message: Some(message.to_string()),
+            ..Default::default()
+        })
+        .await;
+        Head::current_commit().await.unwrap()
+    }
+
+    /// Three commits: `Add parser`, `Handle empty input`, `Document parser`. Returns the
+    /// first one.
+    async fn setup_history() -> ObjectHash {
+        let first = commit_file("src/parser.rs", "fn parse() {}\n", "Add parser").await;
+        commit_file(
+            "src/parser.rs",
+            "fn parse() {}\nfn parse_empty() {}\n",
+            "Handle empty input",
+        )
+        .await;
+        commit_file("README.md", "The parser.\n", "Document parser").await;
+        first
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_explain_revision() {
+        let temp_path = tempdir().unwrap();
+        test::setup_with_new_libra_in(temp_path.path()).await;
+        let _guard = test::ChangeDirGuard::new(temp_path.path());
+        setup_history().await;
+
+        let target = ExplainTarget::resolve("HEAD").await.unwrap();
+        let head = Head::current_commit().await.unwrap();
+        assert_eq!(target, ExplainTarget::Revision(head));
+
+        let explanation = explain(replay_model(), &embedded_profile(), target, None)
+            .await
+            .unwrap();
+        let document = &explanation.context.document;
+        assert!(
+            document.starts_with(&format!("## Commit {head}\n")),
+            "{document}"
+        );
+        assert!(document.contains("Document parser"), "{document}");
+        assert!(document.contains("+The parser."), "{document}");
+        assert!(!document.contains("parse_empty"), "{document}");
+        assert_eq!(explanation.context.files, ["README.md"]);
+        assert_eq!(explanation.report.summary, "Parser work.");
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_explain_path() {
+        let temp_path = tempdir().unwrap();
+        test::setup_with_new_libra_in(temp_path.path()).await;
+        let _guard = test::ChangeDirGuard::new(temp_path.path());
+        setup_history().await;
+
+        let target = ExplainTarget::resolve("src/parser.rs").await.unwrap();
+        assert_eq!(target, ExplainTarget::Path(PathBuf::from("src/parser.rs")));
+
+        let explanation = explain(replay_model(), &embedded_profile(), target, None)
+            .await
+            .unwrap();
+        let document = &explanation.context.document;
+        let sections = document
+            .lines()
+            .filter(|line| line.starts_with('#'))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sections,
+            [
+                "## File src/parser.rs",
+                "### Authorship highlights",
+                "### Content"
+            ]
+        );
+        assert!(document.contains("- line 1: "), "{document}");
+        assert!(document.contains(" Add parser ("), "{document}");
+        assert!(document.contains(" Handle empty input ("), "{document}");
+        assert!(
+            document.contains("    2  fn parse_empty() {}"),
+            "{document}"
+        );
+        assert!(!document.contains("Document parser"), "{document}");
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_explain_range() {
+        let temp_path = tempdir().unwrap();
+        test::setup_with_new_libra_in(temp_path.path()).await;
+        let _guard = test::ChangeDirGuard::new(temp_path.path());
+        let first = setup_history().await;
+
+        let target = ExplainTarget::resolve(&format!("{first}..")).await.unwrap();
+        let head = Head::current_commit().await.unwrap();
+        assert_eq!(
+            target,
+            ExplainTarget::Range {
+                from: first,
+                to: head
+            }
+        );
+
+        let explanation = explain(replay_model(), &embedded_profile(), target, None)
+            .await
+            .unwrap();
+        let subjects = explanation
+            .context
+            .document
+            .lines()
+            .filter_map(|line| line.strip_prefix("- "))
+            .map(|line| line.split_once(' ').unwrap().1)
+            .collect::<Vec<_>>();
+        assert_eq!(subjects.len(), 2, "{}", explanation.context.document);
+        assert!(subjects[0].starts_with("Handle empty input ("));
+        assert!(subjects[1].starts_with("Document parser ("));
+
+        let missing = ExplainTarget::resolve("no-such-thing").await.unwrap_err();
+        assert!(missing.contains("unknown revision or path"), "{missing}");
+    }
+
+    #[test]
+    fn test_render_formats() {
+        let explanation = Explanation {
+            target: ExplainTarget::Path(PathBuf::from("src/parser.rs")),
+            context: ExplainContext {
+                document: String::new(),
+                files: vec!["src/parser.rs".to_string()],
+            },
+            report: ExplainReport::parse(&format!("```json\n{ANSWER}\n```")).unwrap(),
+        };
+
+        assert_eq!(
+            explanation.render(ExplainFormat::Text),
+            "file src/parser.rs\n\nParser work.\n\nNotable changes:\n  - src/parser.rs: handles empty input\n\nPotential risks:\n  (none)\n"
+        );
+        let markdown = explanation.render(ExplainFormat::Markdown);
+        assert!(markdown.starts_with("# file src/parser.rs\n"));
+        assert!(markdown.contains("## Files\n\n- [src/parser.rs](src/parser.rs)\n"));
+        assert!(!markdown.contains("Potential risks"));
+        let json: serde_json::Value =
+            serde_json::from_str(&explanation.render(ExplainFormat::Json)).unwrap();
+        assert_eq!(json["summary"], "Parser work.");
+        assert_eq!(json["files"][0], "src/parser.rs");
+
+        assert!(ExplainReport::parse("It adds a parser.").is_err());
+        assert!(ExplainReport::parse(r#"{"summary": "s", "rating": 5}"#).is_err());
+    }
+}
@@ -0,0 +1,509 @@
+//! `libra ai fix-build`: have the `build_error_resolver` profile fix what `cargo check`
+//! reports.
+//!
+//! The check runs through the [`CargoCheckHandler`] tool, and only its first errors are kept.
+//! For each one, the lines around the error are read with the `read_file` tool. Together with
+//! the diagnostics they stay within half of the model's context window, and are sent to the
+//! profile (embedded; a project or user profile of the same name replaces it). The agent may
+//! look around with the read-only tools of its profile. It answers with one patch, which is
+//! extracted from the answer ([`extract_patch`]).
+//!
+//! The patch always goes through `apply_patch`. By default it is a dry run that only shows the
+//! diff. With [`FixBuild::apply`] the repository's `PreToolUse` hooks are asked first, as they
+//! would be for an agent calling `apply_patch`, and the patch is written only if none blocks it.
+
+use std::{collections::HashSet, path::PathBuf, sync::Arc, time::Duration};
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::internal::ai::{
+    agent::{
+        profile::{AgentProfile, parse_agent_profile},
+        runtime::tool_loop::{
+            NoopObserver, ToolLoopConfig, ToolLoopObserver, run_tool_loop_with_observer,
+        },
+    },
+    completion::{CompletionError, CompletionModel},
+    diff_context::{DiffPacker, estimate_tokens},
+    hooks::{HookAction, HookRunner},
+    tools::{
+        ToolInvocation, ToolOutput, ToolPayload, ToolRegistry, ToolRegistryBuilder,
+        error::ToolError,
+        handlers::{
+            ApplyPatchHandler, CargoCheckHandler, GrepFilesHandler, ListDirHandler,
+            ReadFileHandler, cargo_check::CargoDiagnostic,
+        },
+    },
+};
+
+/// Name of the profile build fixes run with.
+pub const FIX_BUILD_PROFILE: &str = "build_error_resolver";
+
+const EMBEDDED_PROFILE: &str = include_str!("agent/profile/embedded/build_error_resolver.md");
+
+/// Lines shown before and after the span of each error.
+const SNIPPET_CONTEXT_LINES: usize = 10;
+
+/// Tools of the profile the agent may call while it works out the fix.
+const READ_ONLY_TOOLS: [&str; 3] = ["read_file", "list_dir", "grep_files"];
+
+/// The embedded [`FIX_BUILD_PROFILE`], used when no loaded profile has that name.
+pub fn embedded_profile() -> AgentProfile {
+    parse_agent_profile(EMBEDDED_PROFILE).expect("embedded build_error_resolver profile is valid")
+}
+
+/// The profile named [`FIX_BUILD_PROFILE`] among `profiles`, or the embedded one.
+pub fn fix_build_profile(profiles: Vec<AgentProfile>) -> AgentProfile {
+    profiles
+        .into_iter()
+        .find(|profile| profile.name == FIX_BUILD_PROFILE)
+        .unwrap_or_else(embedded_profile)
+}
+
+/// Why a build could not be fixed.
+#[derive(Debug, Error)]
+pub enum FixBuildError {
+    #[error("{0}")]
+    Tool(#[from] ToolError),
+    #[error(transparent)]
+    Agent(#[from] CompletionError),
+    #[error("the patch was blocked by a hook: {0}")]
+    Blocked(String),
+    #[error("the patch does not apply: {0}")]
+    Patch(String),
+}
+
+/// Settings of one fix-build run.
+#[derive(Debug, Clone, Copy)]
+pub struct FixBuild {
+    /// How many errors are sent to the agent, first reported first.
+    pub limit: usize,
+    /// Write the patch instead of only showing it.
+    pub apply: bool,
+    /// Wall-clock ceiling for the agent; see [`ToolLoopConfig::deadline`].
+    pub deadline: Option<Duration>,
+}
+
+/// The result of [`FixBuild::run`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct FixBuildOutcome {
+    /// The errors sent to the agent; empty when the build is clean.
+    pub errors: Vec<CargoDiagnostic>,
+    /// Errors the check reported, including those past the limit.
+    pub total_errors: usize,
+    /// The diagnostics and source snippets sent to the agent.
+    pub context: String,
+    pub answer: String,
+    /// The patch found in the answer.
+    pub patch: Option<String>,
+    /// What `apply_patch` reported: the diff of a dry run, or the files it changed.
+    pub patch_report: Option<String>,
+    /// Whether the patch was written.
+    pub applied: bool,
+}
+
+/// What the `cargo_check` tool returns.
+#[derive(Deserialize)]
+struct CheckReport {
+    errors: Vec<CargoDiagnostic>,
+    total_errors: usize,
+}
+
+impl FixBuild {
+    /// Check the crate in `working_dir` with `check` and let `model` fix the first errors
+    /// under `profile`.
+    pub async fn run<M: CompletionModel>(
+        &self,
+        model: &M,
+        profile: &AgentProfile,
+        working_dir: PathBuf,
+        check: CargoCheckHandler,
+    ) -> Result<FixBuildOutcome, FixBuildError> {
+        self.run_with_observer(model, profile, working_dir, check, &mut NoopObserver)
+            .await
+    }
+
+    /// [`FixBuild::run`], reporting the agent's progress to `observer`.
+    pub async fn run_with_observer<M: CompletionModel>(
+        &self,
+        model: &M,
+        profile: &AgentProfile,
+        working_dir: PathBuf,
+        check: CargoCheckHandler,
+        observer: &mut dyn ToolLoopObserver,
+    ) -> Result<FixBuildOutcome, FixBuildError> {
+        let registry = ToolRegistryBuilder::with_working_dir(working_dir.clone())
+            .register("cargo_check", Arc::new(check))
+            .register("read_file", Arc::new(ReadFileHandler))
+            .register("list_dir", Arc::new(ListDirHandler))
+            .register("grep_files", Arc::new(GrepFilesHandler))
+            .register("apply_patch", Arc::new(ApplyPatchHandler))
+            .build();
+
+        let output = call(
+            &registry,
+            "cargo_check",
+            serde_json::json!({ "limit": self.limit }),
+        )
+        .await?;
+        let report: CheckReport = serde_json::from_str(output.as_text().unwrap_or_default())
+            .map_err(|e| ToolError::ParseError(e.to_string()))?;
+        if report.errors.is_empty() {
+            return Ok(FixBuildOutcome::default());
+        }
+
+        let budget = DiffPacker::for_model(&model.capabilities()).budget();
+        let context = build_context(&registry, &report.errors, budget).await;
+        let hooks = Arc::new(HookRunner::load(&working_dir));
+        let defaults = ToolLoopConfig::default();
+        let config = ToolLoopConfig {
+            preamble: Some(profile.preamble()),
+            hook_runner: Some(hooks.clone()),
+            allowed_tools: Some(
+                profile
+                    .tools
+                    .iter()
+                    .filter(|tool| READ_ONLY_TOOLS.contains(&tool.as_str()))
+                    .cloned()
+                    .collect(),
+            ),
+            deadline: self.deadline,
+            ..defaults
+        };
+        let answer =
+            run_tool_loop_with_observer(model, fix_prompt(&context), &registry, config, observer)
+                .await?;
+
+        let mut outcome = FixBuildOutcome {
+            errors: report.errors,
+            total_errors: report.total_errors,
+            context,
+            patch: extract_patch(&answer),
+            answer,
+            ..Default::default()
+        };
+        let Some(patch) = &outcome.patch else {
+            return Ok(outcome);
+        };
+        let arguments = serde_json::json!({ "patch": patch, "dry_run": !self.apply });
+        if self.apply
+            && let HookAction::Block(reason) = hooks
+                .run_pre_tool_use("apply_patch", arguments.clone())
+                .await
+        {
+            return Err(FixBuildError::Blocked(reason));
+        }
+        let output = call(&registry, "apply_patch", arguments)
+            .await
+            .map_err(|e| FixBuildError::Patch(e.to_string()))?;
+        let text = output.as_text().unwrap_or_default().to_string();
+        if !output.is_success() {
+            return Err(FixBuildError::Patch(text));
+        }
+        outcome.patch_report = Some(text);
+        outcome.applied = self.apply;
+        Ok(outcome)
+    }
+}
+
+async fn call(
+    registry: &ToolRegistry,
+    tool: &str,
+    arguments: serde_json::Value,
+) -> Result<ToolOutput, ToolError> {
+    registry
+        .dispatch(ToolInvocation::new(
+            format!("fix-build-{tool}"),
+            tool,
+            ToolPayload::Function {
+                arguments: arguments.to_string(),
+            },
+            registry.working_dir().to_path_buf(),
+        ))
+        .await
+}
+
+/// The diagnostics, then the source around each error as long as it fits in `budget`
+/// estimated tokens. Files `read_file` refuses, such as those of dependencies outside the
+/// working directory, are left out.
+async fn build_context(
+    registry: &ToolRegistry,
+    errors: &[CargoDiagnostic],
+    budget: usize,
+) -> String {
+    let mut document = String::from("## Errors\n");
+    for error in errors {
+        document.push_str(&format!(
+            "- {}:{}:{}: {}",
+            error.file, error.line_start, error.column_start, error.level
+        ));
+        if let Some(code) = &error.code {
+            document.push_str(&format!("[{code}]"));
+        }
+        document.push_str(&format!(": {}\n", error.message));
+        if let Some(suggestion) = &error.suggestion {
+            document.push_str(&format!("  compiler suggestion: `{suggestion}`\n"));
+        }
+    }
+
+    document.push_str("\n## Source\n");
+    let mut shown = HashSet::new();
+    let mut omitted = 0;
+    for error in errors {
+        let offset = error
+            .line_start
+            .saturating_sub(SNIPPET_CONTEXT_LINES)
+            .max(1);
+        let limit = error.line_end + SNIPPET_CONTEXT_LINES + 1 - offset;
+        if !shown.insert((&error.file, offset)) {
+            continue;
+        }
+        let file_path = registry.working_dir().join(&error.file);
+        let arguments = serde_json::json!({
+            "file_path": file_path.display().to_string(),
+            "offset": offset,
+            "limit": limit,
+        });
+        let Ok(output) = call(registry, "read_file", arguments).await else {
+            continue;
+        };
+        let snippet = format!(
+            "\n### {} (from line {offset})\n{}\n",
+            error.file,
+            output.as_text().unwrap_or_default().trim_end()
+        );
+        if estimate_tokens(&document) + estimate_tokens(&snippet) > budget {
+            omitted += 1;
+            continue;
+        }
+        document.push_str(&snippet);
+    }
+    if omitted > 0 {
+        document.push_str(&format!("\n[source of {omitted} errors omitted]\n"));
+    }
+    document
+}
+
+fn fix_prompt(context: &str) -> String {
+    format!(
+        "`cargo check` fails with the errors below. Fix them with the smallest change.\n\
+         Do not call apply_patch. Answer with a single patch in a ```diff fenced block, as a \
+         unified diff or in the `*** Begin Patch` format, with paths relative to the \
+         repository root.\n\n{context}"
+    )
+}
+
+/// The patch in a model answer: the first fenced block holding a unified diff or a
+/// `*** Begin Patch` patch, or an unfenced `*** Begin Patch` ... `*** End Patch` section.
+pub fn extract_patch(answer: &str) -> Option<String> {
+    let mut rest = answer;
+    while let Some(start) = rest.find("```") {
+        let after = &rest[start + 3..];
+        let body_start = after.find('\n').map_or(after.len(), |i| i + 1);
+        let body = &after[body_start..];
+        let Some(end) = body.find("```") else {
+            break;
+        };
+        let block = &body[..end];
+        let trimmed = block.trim_start();
+        if trimmed.starts_with("*** Begin Patch")
+            || trimmed.starts_with("--- ")
+            || trimmed.starts_with("diff --git ")
+        {
+            return Some(block.to_string());
+        }
+        rest = &body[end + 3..];
+    }
+    let start = answer.find("*** Begin Patch")?;
+    let end = answer[start..].find("*** End Patch")? + start + "*** End Patch".len();
+    Some(format!("{}\n", &answer[start..end]))
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::internal::ai::eval::replay::{ReplayModel, ReplayTurn};
+
+    const FIXTURE: &str = include_str!("../../../tests/data/ai/cargo_check.jsonl");
+
+    const MAIN_RS: &str = "fn main() {\n    let n: u64 = count();\n    println!(\"{}\", undefined_value);\n    let unused = 1;\n}\nfn count() -> usize { 3 }\n";
+
+    const ANSWER: &str = "The count is a `usize`, and the printed value does not exist.\n\n\
+```diff\n\
+--- a/src/main.rs\n\
++++ b/src/main.rs\n\
+@@ -1,4 +1,4 @@\n \
+fn main() {\n\
+-    let n: u64 = count();\n\
+-    println!(\"{}\", undefined_value);\n\
++    let n: u64 = count() as u64;\n\
++    println!(\"{}\", n);\n     \
+let unused = 1;\n\
+```\n";
+
+    /// A crate whose `src/main.rs` matches the captured check output; the check replays it.
+    fn setup_crate() -> (TempDir, CargoCheckHandler) {
+        let temp = TempDir::new().unwrap();
+        std::fs::create_dir(temp.path().join("src")).unwrap();
+        std::fs::write(temp.path().join("src/main.rs"), MAIN_RS).unwrap();
+        std::fs::write(temp.path().join("check.jsonl"), FIXTURE).unwrap();
+        (
+            temp,
+            CargoCheckHandler::with_command("cat check.jsonl; exit 101"),
+        )
+    }
+
+    fn replay_model() -> ReplayModel {
+        ReplayModel::new(vec![ReplayTurn {
+            text: Some(ANSWER.to_string()),
+            ..Default::default()
+        }])
+    }
+
+    #[tokio::test]
+    async fn test_fix_build_applies_patch() {
+        let (temp, check) = setup_crate();
+        let fix = FixBuild {
+            limit: 5,
+            apply: true,
+            deadline: None,
+        };
+
+        let outcome = fix
+            .run(
+                &replay_model(),
+                &embedded_profile(),
+                temp.path().to_path_buf(),
+                check,
+            )
+            .await
+            .unwrap();
+        assert_eq!(outcome.total_errors, 2);
+        assert_eq!(outcome.errors.len(), 2);
+        assert!(
+            outcome
+                .context
+                .contains("- src/main.rs:2:18: error[E0308]: mismatched types\n"),
+            "{}",
+            outcome.context
+        );
+        assert!(
+            outcome
+                .context
+                .contains("compiler suggestion: `count().try_into().unwrap()`"),
+            "{}",
+            outcome.context
+        );
+        assert!(
+            outcome.context.contains("L6: fn count() -> usize { 3 }"),
+            "{}",
+            outcome.context
+        );
+        assert!(outcome.applied);
+        assert_eq!(
+            std::fs::read_to_string(temp.path().join("src/main.rs")).unwrap(),
+            "fn main() {\n    let n: u64 = count() as u64;\n    println!(\"{}\", n);\n    let unused = 1;\n}\nfn count() -> usize { 3 }\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fix_build_dry_run_and_blocking_hook() {
+        let (temp, check) = setup_crate();
+        let fix = FixBuild {
+            limit: 1,
+            apply: false,
+            deadline: None,
+        };
+        let outcome = fix
+            .run(
+                &replay_model(),
+                &embedded_profile(),
+                temp.path().to_path_buf(),
+                check,
+            )
+            .await
+            .unwrap();
+        assert_eq!(outcome.errors.len(), 1);
+        assert_eq!(outcome.total_errors, 2);
+        assert!(!outcome.applied);
+        assert!(
+            outcome
+                .patch_report
+                .as_deref()
+                .unwrap()
+                .contains("+    let n: u64 = count() as u64;")
+        );
+        assert_eq!(
+            std::fs::read_to_string(temp.path().join("src/main.rs")).unwrap(),
+            MAIN_RS
+        );
+
+        let hooks_dir = temp.path().join(".libra");
+        std::fs::create_dir_all(&hooks_dir).unwrap();
+        std::fs::write(
+            hooks_dir.join("hooks.json"),
+            r#"{"hooks": [{"event": "pre_tool_use", "matcher": "apply_patch", "command": "cat > /dev/null; echo '{\"message\":\"no patches today\"}'; exit 2", "description": "blocker"}]}"#,
+        )
+        .unwrap();
+        let check = CargoCheckHandler::with_command("cat check.jsonl; exit 101");
+        let err = FixBuild {
+            limit: 1,
+            apply: true,
+            deadline: None,
+        }
+        .run(
+            &replay_model(),
+            &embedded_profile(),
+            temp.path().to_path_buf(),
+            check,
+        )
+        .await
+        .unwrap_err();
+        assert!(
+            matches!(err, FixBuildError::Blocked(ref reason) if reason.contains("no patches today")),
+            "{err}"
+        );
+        assert_eq!(
+            std::fs::read_to_string(temp.path().join("src/main.rs")).unwrap(),
+            MAIN_RS
+        );
+    }
+
+    #[tokio::test]
+    async fn test_clean_build_needs_no_agent() {
+        let temp = TempDir::new().unwrap();
+        let check = CargoCheckHandler::with_command("echo '    Finished dev profile'");
+        let outcome = FixBuild {
+            limit: 5,
+            apply: true,
+            deadline: None,
+        }
+        .run(
+            &ReplayModel::new(Vec::new()),
+            &embedded_profile(),
+            temp.path().to_path_buf(),
+            check,
+        )
+        .await
+        .unwrap();
+        assert_eq!(outcome, FixBuildOutcome::default());
+    }
+
+    #[test]
+    fn test_extract_patch() {
+        let patch = extract_patch(ANSWER).unwrap();
+        assert!(patch.starts_with("--- a/src/main.rs\n"), "{patch}");
+        assert!(patch.ends_with("     let unused = 1;\n"), "{patch}");
+
+        let codex =
+            "Here:\n*** Begin Patch\n*** Update File: a.rs\n@@\n-a\n+b\n*** End Patch\nDone.";
+        assert_eq!(
+            extract_patch(codex).unwrap(),
+            "*** Begin Patch\n*** Update File: a.rs\n@@\n-a\n+b\n*** End Patch\n"
+        );
+        assert_eq!(extract_patch("```rust\nfn main() {}\n```\nNo patch."), None);
+    }
+}
@@ -1,5 +1,11 @@
-use std::{path::PathBuf, str::FromStr, sync::Arc};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+    str::FromStr,
+    sync::Arc,
+};
 
+use chrono::DateTime;
 use git_internal::{
     errors::GitError,
     hash::ObjectHash,
@@ -9,10 +15,13 @@ use git_internal::{
         tree::{Tree, TreeItem, TreeItemMode},
     },
 };
+use thiserror::Error;
+use tokio::sync::broadcast;
 
 use crate::utils::{
     object::{read_git_object, write_git_object},
     storage::Storage,
+    storage_ext::StorageExt,
 };
 
 /// Default Git reference for the AI history orphan branch.
@@ -25,6 +34,56 @@ use crate::utils::{
 /// from `git gc` — the branch acts as a GC root.
 pub const AI_REF: &str = "refs/libra/intent";
 
+/// Number of unread events a [`HistoryManager::subscribe`] receiver may fall behind
+/// before it starts missing events.
+const EVENT_CHANNEL_CAPACITY: usize = 64;
+
+/// Published by [`HistoryManager`] whenever an object is appended to the history.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectCreated {
+    pub object_id: String,
+    pub object_type: String,
+}
+
+/// Objects one actor created, as counted by [`HistoryManager::actor_stats`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ActorStats {
+    /// Objects of every type.
+    pub total: usize,
+    /// Object count per object type (`intent`, `task`, ...).
+    pub by_type: BTreeMap<String, usize>,
+}
+
+/// A commit of the history branch, as listed by [`HistoryManager::history_log`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryCommit {
+    pub id: ObjectHash,
+    pub message: String,
+}
+
+/// The parts of a history commit the manager reads.
+struct RawCommit {
+    tree: ObjectHash,
+    parents: Vec<ObjectHash>,
+    message: String,
+}
+
+/// Why [`HistoryManager::resolve_prefix`] could not pick a single object.
+#[derive(Debug, Error)]
+pub enum ResolveError {
+    #[error("no AI object id starts with '{0}'")]
+    NotFound(String),
+
+    #[error("short object id '{prefix}' is ambiguous; candidates: {}", .candidates.join(", "))]
+    Ambiguous {
+        prefix: String,
+        candidates: Vec<String>,
+    },
+
+    #[error(transparent)]
+    Git(#[from] GitError),
+}
+
 /// Manages object history using an orphan branch and Git Tree structure.
 ///
 /// The default branch (`refs/libra/intent`) stores **all** AI workflow objects,
@@ -42,11 +101,12 @@ pub const AI_REF: &str = "refs/libra/intent";
 ///   │   └── <plan_id>
 ///   └── …
 pub struct HistoryManager {
-    #[allow(dead_code)]
     storage: Arc<dyn Storage + Send + Sync>,
     repo_path: PathBuf,
     /// The Git reference name this manager writes to (e.g. "refs/libra/history").
     ref_name: String,
+    /// Sender for [`ObjectCreated`] events; see [`HistoryManager::subscribe`].
+    events: broadcast::Sender<ObjectCreated>,
 }
 
 impl HistoryManager {
@@ -63,6 +123,7 @@ impl HistoryManager {
             storage,
             repo_path,
             ref_name: ref_name.into(),
+            events: broadcast::channel(EVENT_CHANNEL_CAPACITY).0,
         }
     }
 
@@ -104,6 +165,14 @@ impl HistoryManager {
         Ok(())
     }
 
+    /// Receive an [`ObjectCreated`] event for every object appended after this call.
+    ///
+    /// Past writes are not replayed. A receiver that falls more than
+    /// `EVENT_CHANNEL_CAPACITY` events behind gets `RecvError::Lagged` and skips ahead.
+    pub fn subscribe(&self) -> broadcast::Receiver<ObjectCreated> {
+        self.events.subscribe()
+    }
+
     /// Return the ref name this manager writes to.
     pub fn ref_name(&self) -> &str {
         &self.ref_name
@@ -203,6 +272,12 @@ impl HistoryManager {
         // 4. Update Ref
         self.update_ref(&self.ref_name, commit_hash)?;
 
+        // 5. Notify subscribers; having none is not an error.
+        let _ = self.events.send(ObjectCreated {
+            object_id: object_id.to_string(),
+            object_type: object_type.to_string(),
+        });
+
         Ok(())
     }
 
@@ -244,6 +319,36 @@ impl HistoryManager {
         Ok(None)
     }
 
+    /// Expand a short object id to the unique full id among all stored objects.
+    ///
+    /// An exact id always wins, even when it is also a prefix of longer ids.
+    pub async fn resolve_prefix(&self, prefix: &str) -> Result<String, ResolveError> {
+        let mut candidates = Vec::new();
+        if let Some(head) = self.resolve_history_head().await? {
+            for type_entry in self.load_commit_tree(&head)? {
+                for item in self.load_tree(&type_entry.id)? {
+                    if item.name == prefix {
+                        return Ok(item.name);
+                    }
+                    if item.name.starts_with(prefix) {
+                        candidates.push(item.name);
+                    }
+                }
+            }
+        }
+        candidates.sort();
+        candidates.dedup();
+
+        match candidates.len() {
+            0 => Err(ResolveError::NotFound(prefix.to_string())),
+            1 => Ok(candidates.remove(0)),
+            _ => Err(ResolveError::Ambiguous {
+                prefix: prefix.to_string(),
+                candidates,
+            }),
+        }
+    }
+
     /// List all objects of a specific type from the current history.
     /// Returns a list of (object_id, object_hash).
     pub async fn list_objects(
@@ -264,6 +369,60 @@ impl HistoryManager {
         Ok(Vec::new())
     }
 
+    /// List the objects of a type created within `since..=until`, as Unix timestamps in
+    /// seconds like those [`parse_date`](crate::internal::log::date_parser::parse_date)
+    /// returns. `None` leaves that side of the window open.
+    ///
+    /// The creation time is the `created_at` of each object's header, so every object blob of
+    /// the type is loaded. Objects without a readable `created_at` are left out.
+    pub async fn list_objects_between(
+        &self,
+        object_type: &str,
+        since: Option<i64>,
+        until: Option<i64>,
+    ) -> Result<Vec<(String, ObjectHash)>, GitError> {
+        let mut objects = Vec::new();
+        for (object_id, hash) in self.list_objects(object_type).await? {
+            let object: serde_json::Value = self.storage.get_json(&hash).await?;
+            let Some(created_at) = object["created_at"]
+                .as_str()
+                .and_then(|value| DateTime::parse_from_rfc3339(value).ok())
+                .map(|value| value.timestamp())
+            else {
+                continue;
+            };
+            if since.is_none_or(|since| created_at >= since)
+                && until.is_none_or(|until| created_at <= until)
+            {
+                objects.push((object_id, hash));
+            }
+        }
+        Ok(objects)
+    }
+
+    /// Count the objects on the branch per `created_by` actor id and object type.
+    ///
+    /// Every object blob is loaded, so this scales with the size of the history. Objects
+    /// without a `created_by` actor are not counted.
+    pub async fn actor_stats(&self) -> Result<HashMap<String, ActorStats>, GitError> {
+        let mut stats: HashMap<String, ActorStats> = HashMap::new();
+        let Some(head) = self.resolve_history_head().await? else {
+            return Ok(stats);
+        };
+        for type_entry in self.load_commit_tree(&head)? {
+            for item in self.load_tree(&type_entry.id)? {
+                let object: serde_json::Value = self.storage.get_json(&item.id).await?;
+                let Some(actor) = object["created_by"]["id"].as_str() else {
+                    continue;
+                };
+                let entry = stats.entry(actor.to_string()).or_default();
+                entry.total += 1;
+                *entry.by_type.entry(type_entry.name.clone()).or_default() += 1;
+            }
+        }
+        Ok(stats)
+    }
+
     pub async fn resolve_history_head(&self) -> Result<Option<ObjectHash>, GitError> {
         let ref_path = self.repo_path.join(&self.ref_name);
         if !ref_path.exists() {
@@ -281,18 +440,92 @@ impl HistoryManager {
             .map_err(|e| GitError::InvalidObjectInfo(e.to_string()))
     }
 
+    /// The commits of the branch, newest first, following first parents.
+    pub async fn history_log(&self) -> Result<Vec<HistoryCommit>, GitError> {
+        let mut log = Vec::new();
+        let mut next = self.resolve_history_head().await?;
+        while let Some(id) = next {
+            let commit = self.read_commit(&id)?;
+            next = commit.parents.first().copied();
+            log.push(HistoryCommit {
+                id,
+                message: commit.message,
+            });
+        }
+        Ok(log)
+    }
+
+    /// Rewrite the branch as a single commit holding the current tree, so every object
+    /// stays reachable while the intermediate commits are dropped from the chain. Returns
+    /// the new head, or `None` when the branch does not exist. A branch that is already a
+    /// single commit is left as is.
+    pub async fn squash(&self) -> Result<Option<ObjectHash>, GitError> {
+        let Some(head) = self.resolve_history_head().await? else {
+            return Ok(None);
+        };
+        let commit = self.read_commit(&head)?;
+        if commit.parents.is_empty() {
+            return Ok(Some(head));
+        }
+        let squashed = self.history_log().await?.len();
+
+        let author = Signature::new(
+            SignatureType::Author,
+            "Libra".to_string(),
+            "history@libra".to_string(),
+        );
+        let committer = Signature::new(
+            SignatureType::Committer,
+            "Libra".to_string(),
+            "history@libra".to_string(),
+        );
+
+        let mut commit_content = String::new();
+        commit_content.push_str(&format!("tree {}\n", commit.tree));
+        commit_content.push_str(&format!("author {}\n", author));
+        commit_content.push_str(&format!("committer {}\n", committer));
+        commit_content.push('\n');
+        commit_content.push_str(&format!("Squash {squashed} history commits"));
+
+        let commit_hash = write_git_object(&self.repo_path, "commit", commit_content.as_bytes())?;
+        self.update_ref(&self.ref_name, commit_hash)?;
+        Ok(Some(commit_hash))
+    }
+
     fn load_commit_tree(&self, commit_id: &ObjectHash) -> Result<Vec<TreeItem>, GitError> {
+        let tree = self.read_commit(commit_id)?.tree;
+        self.load_tree(&tree)
+    }
+
+    fn read_commit(&self, commit_id: &ObjectHash) -> Result<RawCommit, GitError> {
         let data = read_git_object(&self.repo_path, commit_id)?;
-        // Commit format: tree <hash>\nparent...
+        // Commit format: tree <hash>\nparent <hash>...\n\n<message>. The signatures written
+        // here span two lines each and end with an empty one, so the message starts after the
+        // first empty line following the committer.
         let content = String::from_utf8_lossy(&data);
-        for line in content.lines() {
+        let committer = content.find("\ncommitter ").unwrap_or(0);
+        let (headers, message) = match content[committer..].find("\n\n") {
+            Some(end) => content.split_at(committer + end),
+            None => (content.as_ref(), ""),
+        };
+        let message = message.trim_start_matches('\n');
+        let parse = |hash_str: &str| {
+            ObjectHash::from_str(hash_str).map_err(|e| GitError::InvalidObjectInfo(e.to_string()))
+        };
+        let mut tree = None;
+        let mut parents = Vec::new();
+        for line in headers.lines() {
             if let Some(hash_str) = line.strip_prefix("tree ") {
-                let tree_hash = ObjectHash::from_str(hash_str)
-                    .map_err(|e| GitError::InvalidObjectInfo(e.to_string()))?;
-                return self.load_tree(&tree_hash);
+                tree = Some(parse(hash_str)?);
+            } else if let Some(hash_str) = line.strip_prefix("parent ") {
+                parents.push(parse(hash_str)?);
             }
         }
-        Err(GitError::InvalidObjectInfo("Commit has no tree".into()))
+        Ok(RawCommit {
+            tree: tree.ok_or_else(|| GitError::InvalidObjectInfo("Commit has no tree".into()))?,
+            parents,
+            message: message.to_string(),
+        })
     }
 
     fn load_tree(&self, tree_id: &ObjectHash) -> Result<Vec<TreeItem>, GitError> {
@@ -340,7 +573,6 @@ impl HistoryManager {
         Ok(())
     }
 
-    #[cfg(test)]
     pub fn get_storage(&self) -> Arc<dyn Storage + Send + Sync> {
         self.storage.clone()
     }
@@ -351,7 +583,7 @@ mod tests {
     use tempfile::tempdir;
 
     use super::*;
-    use crate::utils::storage::local::LocalStorage;
+    use crate::{internal::log::date_parser::parse_date, utils::storage::local::LocalStorage};
 
     #[tokio::test]
     async fn test_history_append_simple() {
@@ -388,4 +620,264 @@ mod tests {
         assert!(content.contains("tree "));
         assert!(content.contains("Update run/run-1"));
     }
+
+    #[tokio::test]
+    async fn test_subscribe_receives_tracked_writes() {
+        use serde::Serialize;
+
+        use crate::utils::storage_ext::{Identifiable, StorageExt};
+
+        #[derive(Serialize)]
+        struct Note {
+            id: &'static str,
+            kind: &'static str,
+        }
+
+        impl Identifiable for Note {
+            fn object_id(&self) -> String {
+                self.id.to_string()
+            }
+            fn object_type(&self) -> String {
+                self.kind.to_string()
+            }
+        }
+
+        let dir = tempdir().unwrap();
+        let repo_path = dir.path().join(".libra");
+        std::fs::create_dir(&repo_path).unwrap();
+        let storage = Arc::new(LocalStorage::new(repo_path.join("objects")));
+        let manager = HistoryManager::new(storage.clone(), repo_path);
+
+        // Writes before subscribing are not replayed.
+        let early = Note {
+            id: "intent-0",
+            kind: "intent",
+        };
+        storage.put_tracked(&early, &manager).await.unwrap();
+
+        let mut events = manager.subscribe();
+        for note in [
+            Note {
+                id: "intent-1",
+                kind: "intent",
+            },
+            Note {
+                id: "task-1",
+                kind: "task",
+            },
+        ] {
+            storage.put_tracked(&note, &manager).await.unwrap();
+        }
+
+        let first = events.recv().await.unwrap();
+        assert_eq!(first.object_id, "intent-1");
+        assert_eq!(first.object_type, "intent");
+        let second = events.recv().await.unwrap();
+        assert_eq!(second.object_id, "task-1");
+        assert_eq!(second.object_type, "task");
+        assert!(events.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_actor_stats() {
+        use git_internal::internal::object::{intent::Intent, task::Task, types::ActorRef};
+
+        let dir = tempdir().unwrap();
+        let repo_path = dir.path().join(".libra");
+        std::fs::create_dir(&repo_path).unwrap();
+        let storage = Arc::new(LocalStorage::new(repo_path.join("objects")));
+        let manager = HistoryManager::new(storage.clone(), repo_path);
+
+        assert!(manager.actor_stats().await.unwrap().is_empty());
+
+        let alice = ActorRef::human("alice").unwrap();
+        let planner = ActorRef::agent("planner").unwrap();
+        for prompt in ["add caching", "fix flaky test"] {
+            let intent = Intent::new(alice.clone(), prompt).unwrap();
+            storage.put_tracked(&intent, &manager).await.unwrap();
+        }
+        for title in ["cache layer", "cache tests", "retry test"] {
+            let task = Task::new(planner.clone(), title, None).unwrap();
+            storage.put_tracked(&task, &manager).await.unwrap();
+        }
+        let task = Task::new(alice.clone(), "review", None).unwrap();
+        storage.put_tracked(&task, &manager).await.unwrap();
+
+        let stats = manager.actor_stats().await.unwrap();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(
+            stats["alice"],
+            ActorStats {
+                total: 3,
+                by_type: BTreeMap::from([("intent".to_string(), 2), ("task".to_string(), 1)]),
+            }
+        );
+        assert_eq!(
+            stats["planner"],
+            ActorStats {
+                total: 3,
+                by_type: BTreeMap::from([("task".to_string(), 3)]),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_objects_between() {
+        use git_internal::internal::object::{intent::Intent, types::ActorRef};
+
+        let dir = tempdir().unwrap();
+        let repo_path = dir.path().join(".libra");
+        std::fs::create_dir(&repo_path).unwrap();
+        let storage = Arc::new(LocalStorage::new(repo_path.join("objects")));
+        let manager = HistoryManager::new(storage.clone(), repo_path);
+
+        let alice = ActorRef::human("alice").unwrap();
+        for (id, created_at) in [
+            ("yesterday", "2026-10-15T23:59:59Z"),
+            ("morning", "2026-10-16T08:00:00Z"),
+            ("evening", "2026-10-16T20:30:00+02:00"),
+            ("tomorrow", "2026-10-17T00:00:00Z"),
+        ] {
+            let mut intent = serde_json::to_value(Intent::new(alice.clone(), id).unwrap()).unwrap();
+            intent["created_at"] = created_at.into();
+            let hash = storage.put_json(&intent).await.unwrap();
+            manager.append("intent", id, hash).await.unwrap();
+        }
+        // not an object with a header; never in range
+        let hash = storage
+            .put_json(&serde_json::json!({"id": 1}))
+            .await
+            .unwrap();
+        manager.append("intent", "headerless", hash).await.unwrap();
+
+        let ids = |objects: Vec<(String, ObjectHash)>| {
+            let mut ids = objects.into_iter().map(|(id, _)| id).collect::<Vec<_>>();
+            ids.sort();
+            ids
+        };
+        let since = parse_date("2026-10-16").unwrap();
+        let until = parse_date("2026-10-16 23:59:59 +0000").unwrap();
+        assert_eq!(
+            ids(manager
+                .list_objects_between("intent", Some(since), Some(until))
+                .await
+                .unwrap()),
+            ["evening", "morning"]
+        );
+        assert_eq!(
+            ids(manager
+                .list_objects_between("intent", Some(until + 1), None)
+                .await
+                .unwrap()),
+            ["tomorrow"]
+        );
+        assert_eq!(
+            manager
+                .list_objects_between("intent", None, None)
+                .await
+                .unwrap()
+                .len(),
+            4
+        );
+        assert!(
+            manager
+                .list_objects_between("task", None, None)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_squash_keeps_objects_and_drops_history() {
+        let dir = tempdir().unwrap();
+        let repo_path = dir.path().join(".libra");
+        std::fs::create_dir(&repo_path).unwrap();
+        let storage = Arc::new(LocalStorage::new(repo_path.join("objects")));
+        let manager = HistoryManager::new(storage, repo_path);
+        assert_eq!(manager.squash().await.unwrap(), None);
+
+        manager.init_branch().await.unwrap();
+        let blob_hash = ObjectHash::from_str("e69de29bb2d1d6434b8b29ae775ad8c2e48c5391").unwrap();
+        for (object_type, id) in [
+            ("intent", "intent-1"),
+            ("task", "task-1"),
+            ("task", "task-2"),
+            ("task", "task-1"),
+            ("run", "run-1"),
+        ] {
+            manager.append(object_type, id, blob_hash).await.unwrap();
+        }
+        let log = manager.history_log().await.unwrap();
+        assert_eq!(log.len(), 6);
+        assert_eq!(log[0].message, "Update run/run-1");
+        let mut before = Vec::new();
+        for object_type in ["intent", "task", "run"] {
+            before.push(manager.list_objects(object_type).await.unwrap());
+        }
+
+        let head = manager.squash().await.unwrap().unwrap();
+        let log = manager.history_log().await.unwrap();
+        assert_eq!(log.len(), 1);
+        assert_eq!(log[0].id, head);
+        assert_eq!(log[0].message, "Squash 6 history commits");
+        let mut after = Vec::new();
+        for object_type in ["intent", "task", "run"] {
+            after.push(manager.list_objects(object_type).await.unwrap());
+        }
+        assert_eq!(after, before);
+
+        // Squashing again changes nothing; new writes build on the squashed head.
+        assert_eq!(manager.squash().await.unwrap(), Some(head));
+        manager.append("task", "task-3", blob_hash).await.unwrap();
+        let log = manager.history_log().await.unwrap();
+        assert_eq!(log.len(), 2);
+        assert_eq!(log[1].id, head);
+    }
+
+    #[tokio::test]
+    async fn test_resolve_prefix() {
+        let dir = tempdir().unwrap();
+        let repo_path = dir.path().join(".libra");
+        std::fs::create_dir(&repo_path).unwrap();
+        let storage = Arc::new(LocalStorage::new(repo_path.join("objects")));
+        let manager = HistoryManager::new(storage, repo_path);
+
+        assert!(matches!(
+            manager.resolve_prefix("3f2").await,
+            Err(ResolveError::NotFound(_))
+        ));
+
+        let blob_hash = ObjectHash::from_str("e69de29bb2d1d6434b8b29ae775ad8c2e48c5391").unwrap();
+        manager
+            .append("intent", "3f2a91c0", blob_hash)
+            .await
+            .unwrap();
+        manager.append("task", "3f2b07d4", blob_hash).await.unwrap();
+        manager
+            .append("intent", "8c41e2aa", blob_hash)
+            .await
+            .unwrap();
+
+        let err = manager.resolve_prefix("3f2").await.unwrap_err();
+        match &err {
+            ResolveError::Ambiguous { prefix, candidates } => {
+                assert_eq!(prefix, "3f2");
+                assert_eq!(candidates, &["3f2a91c0", "3f2b07d4"]);
+            }
+            other => panic!("expected ambiguity, got {other:?}"),
+        }
+        assert!(err.to_string().contains("3f2a91c0, 3f2b07d4"));
+
+        assert_eq!(manager.resolve_prefix("3f2b").await.unwrap(), "3f2b07d4");
+        assert_eq!(manager.resolve_prefix("8c").await.unwrap(), "8c41e2aa");
+        assert_eq!(
+            manager.resolve_prefix("3f2a91c0").await.unwrap(),
+            "3f2a91c0"
+        );
+        assert!(matches!(
+            manager.resolve_prefix("ffff").await,
+            Err(ResolveError::NotFound(p)) if p == "ffff"
+        ));
+    }
 }
@@ -1,3 +1,5 @@
+use std::{fs, io, path::Path};
+
 use git_internal::internal::object::intent::Intent;
 
 use crate::utils::storage_ext::Identifiable;
@@ -11,3 +13,31 @@ impl Identifiable for Intent {
         self.header().object_type().to_string()
     }
 }
+
+/// File under the storage directory (`.libra/`) naming the intent currently being worked on.
+pub const ACTIVE_INTENT_FILE: &str = "AI_INTENT";
+
+/// Read the active intent pointer, if one is set.
+pub fn read_active_intent(storage: &Path) -> io::Result<Option<String>> {
+    match fs::read_to_string(storage.join(ACTIVE_INTENT_FILE)) {
+        Ok(content) => {
+            let id = content.trim();
+            Ok((!id.is_empty()).then(|| id.to_string()))
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Point the active intent at `intent_id`.
+pub fn write_active_intent(storage: &Path, intent_id: &str) -> io::Result<()> {
+    fs::write(storage.join(ACTIVE_INTENT_FILE), format!("{intent_id}\n"))
+}
+
+/// Remove the active intent pointer. Clearing an unset pointer is not an error.
+pub fn clear_active_intent(storage: &Path) -> io::Result<()> {
+    match fs::remove_file(storage.join(ACTIVE_INTENT_FILE)) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
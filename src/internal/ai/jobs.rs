@@ -0,0 +1,865 @@
+//! Background agent jobs persisted under `.libra/ai/jobs`.
+//!
+//! Long-running agent operations are queued instead of blocking the terminal:
+//! [`JobStore::submit`] records the operation as `<id>.json` with status
+//! [`JobStatus::Pending`], and [`JobStore::run_pending`] later executes the pending jobs
+//! one at a time through a [`JobExecutor`], capturing their output in `<id>.log`.
+//!
+//! ## Status transitions
+//!
+//! ```text
+//! Pending ──run──▶ Running ──▶ Succeeded | Failed | Cancelled
+//!    └────cancel────────────────────────────────────▲
+//! ```
+//!
+//! Every transition rewrites the job file through a temporary file and a rename, so a
+//! crash leaves either the old or the new state on disk, never a partial file. Cancelling
+//! a running job only sets [`Job::cancel_requested`]; the executor notices it at its next
+//! [`JobContext::checkpoint`].
+//!
+//! Runners and cancels in other processes may touch the same job, so each read-modify-write
+//! of a job file happens under `<id>.lock`, created exclusively. A runner claims a pending
+//! job by re-reading it under the lock and skips jobs whose lock is held, so two runners
+//! never start the same job and a cancel is never overwritten by the claim. The lock
+//! records its owner's pid and creation time; since it is only held for a single update, one
+//! older than [`STALE_LOCK_AGE`] was left by a crashed process and is broken.
+//!
+//! While a job runs, its runner holds an OS file lock on `<id>.runner`, which the OS
+//! releases if the runner dies. A job still marked running whose runner lock is free lost
+//! its runner mid-way: the next [`JobStore::run_pending`] marks it failed, and
+//! [`JobStore::cancel`] cancels it outright. It is not retried, since it may have acted
+//! partway (edited files, applied a patch).
+
+use std::{
+    fs::{File, OpenOptions, TryLockError},
+    io::Write,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio_util::sync::CancellationToken;
+
+/// Lifecycle state of a [`Job`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Pending,
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    /// Whether the job can no longer change state.
+    pub fn is_finished(self) -> bool {
+        matches!(
+            self,
+            JobStatus::Succeeded | JobStatus::Failed | JobStatus::Cancelled
+        )
+    }
+}
+
+impl std::fmt::Display for JobStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            JobStatus::Pending => "pending",
+            JobStatus::Running => "running",
+            JobStatus::Succeeded => "succeeded",
+            JobStatus::Failed => "failed",
+            JobStatus::Cancelled => "cancelled",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// A queued agent operation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Job {
+    pub id: String,
+    /// The operation as command-line arguments, interpreted by the [`JobExecutor`].
+    pub args: Vec<String>,
+    pub status: JobStatus,
+    /// Set when a running job was asked to stop.
+    #[serde(default)]
+    pub cancel_requested: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Short result reported by the executor on success.
+    #[serde(default)]
+    pub result: Option<String>,
+    /// Failure message when the job failed.
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Error)]
+pub enum JobError {
+    #[error("job io error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("invalid job file: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("job '{0}' not found")]
+    NotFound(String),
+
+    #[error("job '{id}' is already {status}")]
+    Finished { id: String, status: JobStatus },
+
+    #[error("job was cancelled")]
+    Cancelled,
+
+    #[error("job '{0}' is locked by another process")]
+    Locked(String),
+}
+
+/// How long [`JobStore::lock`] waits for another process to release a job.
+const LOCK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Age past which a `<id>.lock` is taken to be left behind by a crashed process.
+pub const STALE_LOCK_AGE: Duration = Duration::from_secs(10);
+
+/// Error recorded on a job whose runner died before finishing it.
+const RUNNER_GONE: &str = "the runner exited before the job finished";
+
+/// Exclusive hold on one job file, released when dropped.
+struct JobLock {
+    path: PathBuf,
+}
+
+impl Drop for JobLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// OS lock on `<id>.runner`, held for as long as a runner executes the job.
+struct RunnerLock {
+    path: PathBuf,
+    _file: File,
+}
+
+impl Drop for RunnerLock {
+    fn drop(&mut self) {
+        // Removed while still locked; the lock goes with the file handle right after.
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Handed to a [`JobExecutor`] while a job runs.
+pub struct JobContext<'a> {
+    store: &'a JobStore,
+    job_id: String,
+    log: File,
+    cancel: CancellationToken,
+}
+
+impl JobContext<'_> {
+    /// The job's log file; everything written here is kept as captured output.
+    pub fn log(&mut self) -> &mut File {
+        &mut self.log
+    }
+
+    /// Token cancelled when the runner or the user stops the job.
+    pub fn cancellation_token(&self) -> &CancellationToken {
+        &self.cancel
+    }
+
+    /// Return [`JobError::Cancelled`] if the job should stop.
+    ///
+    /// Executors call this between steps. It also picks up `libra ai job cancel` issued
+    /// from another process while the job runs.
+    pub fn checkpoint(&self) -> Result<(), JobError> {
+        if !self.cancel.is_cancelled() && self.store.get(&self.job_id)?.cancel_requested {
+            self.cancel.cancel();
+        }
+        if self.cancel.is_cancelled() {
+            return Err(JobError::Cancelled);
+        }
+        Ok(())
+    }
+}
+
+/// Runs the operation recorded in a job.
+#[async_trait]
+pub trait JobExecutor: Send + Sync {
+    /// Execute `args`, writing progress and output to [`JobContext::log`].
+    ///
+    /// The returned string is stored as [`Job::result`].
+    async fn execute(
+        &self,
+        args: &[String],
+        ctx: &mut JobContext<'_>,
+    ) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>>;
+}
+
+/// Directory of job files.
+pub struct JobStore {
+    dir: PathBuf,
+}
+
+impl JobStore {
+    /// Store jobs in `dir` (usually `.libra/ai/jobs`), created on first submit.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Path of the job's status file.
+    pub fn job_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{id}.json"))
+    }
+
+    /// Path of the job's captured output.
+    pub fn log_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{id}.log"))
+    }
+
+    /// Path of the lock held while the job file is updated.
+    pub fn lock_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{id}.lock"))
+    }
+
+    /// Path of the file a runner holds an OS lock on while the job runs.
+    pub fn runner_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{id}.runner"))
+    }
+
+    /// Take the lock of job `id`, or `None` if another process holds it. A stale lock is
+    /// broken first.
+    fn try_lock(&self, id: &str) -> Result<Option<JobLock>, JobError> {
+        std::fs::create_dir_all(&self.dir)?;
+        let path = self.lock_path(id);
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    let lock = JobLock { path };
+                    writeln!(file, "{} {}", std::process::id(), Utc::now().to_rfc3339())?;
+                    return Ok(Some(lock));
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    if !break_stale_lock(&path)? {
+                        return Ok(None);
+                    }
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    /// Take the lock of job `id`, waiting up to [`LOCK_TIMEOUT`] for its holder, which only
+    /// keeps it for a single update.
+    async fn lock(&self, id: &str) -> Result<JobLock, JobError> {
+        let deadline = Instant::now() + LOCK_TIMEOUT;
+        loop {
+            if let Some(lock) = self.try_lock(id)? {
+                return Ok(lock);
+            }
+            if Instant::now() >= deadline {
+                return Err(JobError::Locked(id.to_string()));
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    /// Take the runner lock of job `id`, or `None` if a live runner holds it.
+    fn try_lock_runner(&self, id: &str) -> Result<Option<RunnerLock>, JobError> {
+        let path = self.runner_path(id);
+        let mut file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)?;
+        match file.try_lock() {
+            Ok(()) => {
+                file.set_len(0)?;
+                writeln!(file, "{}", std::process::id())?;
+                Ok(Some(RunnerLock { path, _file: file }))
+            }
+            Err(TryLockError::WouldBlock) => Ok(None),
+            Err(TryLockError::Error(e)) => Err(e.into()),
+        }
+    }
+
+    /// Whether the runner of a job marked running is gone, e.g. because it crashed.
+    fn runner_gone(&self, id: &str) -> Result<bool, JobError> {
+        let file = match File::open(self.runner_path(id)) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(true),
+            Err(e) => return Err(e.into()),
+        };
+        match file.try_lock_shared() {
+            Ok(()) => Ok(true),
+            Err(TryLockError::WouldBlock) => Ok(false),
+            Err(TryLockError::Error(e)) => Err(e.into()),
+        }
+    }
+
+    /// Queue `args` as a new pending job.
+    pub fn submit(&self, args: Vec<String>) -> Result<Job, JobError> {
+        let now = Utc::now();
+        let job = Job {
+            // v7 ids sort by creation time.
+            id: uuid::Uuid::now_v7().simple().to_string(),
+            args,
+            status: JobStatus::Pending,
+            cancel_requested: false,
+            created_at: now,
+            updated_at: now,
+            result: None,
+            error: None,
+        };
+        self.save(&job)?;
+        Ok(job)
+    }
+
+    /// Load one job. `id` may be any unique prefix of the job id.
+    pub fn get(&self, id: &str) -> Result<Job, JobError> {
+        match std::fs::read_to_string(self.job_path(id)) {
+            Ok(content) => return Ok(serde_json::from_str(&content)?),
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            Err(_) => {}
+        }
+        let mut matches = self
+            .list()?
+            .into_iter()
+            .filter(|job| job.id.starts_with(id));
+        match (matches.next(), matches.next()) {
+            (Some(job), None) if !id.is_empty() => Ok(job),
+            _ => Err(JobError::NotFound(id.to_string())),
+        }
+    }
+
+    /// All jobs, oldest first.
+    pub fn list(&self) -> Result<Vec<Job>, JobError> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut jobs = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                jobs.push(serde_json::from_str::<Job>(&std::fs::read_to_string(
+                    &path,
+                )?)?);
+            }
+        }
+        jobs.sort_by(|a, b| a.created_at.cmp(&b.created_at).then(a.id.cmp(&b.id)));
+        Ok(jobs)
+    }
+
+    /// Cancel a job: pending jobs are cancelled immediately, running jobs are asked to stop,
+    /// and running jobs whose runner is gone are cancelled outright.
+    pub async fn cancel(&self, id: &str) -> Result<Job, JobError> {
+        let id = self.get(id)?.id;
+        let _lock = self.lock(&id).await?;
+        let mut job = self.get(&id)?;
+        match job.status {
+            JobStatus::Pending => job.status = JobStatus::Cancelled,
+            JobStatus::Running if self.runner_gone(&id)? => {
+                job.status = JobStatus::Cancelled;
+                job.error = Some(RUNNER_GONE.to_string());
+            }
+            JobStatus::Running => job.cancel_requested = true,
+            status => {
+                return Err(JobError::Finished { id: job.id, status });
+            }
+        }
+        job.updated_at = Utc::now();
+        self.save(&job)?;
+        Ok(job)
+    }
+
+    /// Execute every pending job in submission order, one at a time.
+    ///
+    /// Stops before the next job once `cancel` is cancelled. Returns the jobs that ran,
+    /// in their final state, along with running jobs whose runner was found gone and that
+    /// were marked failed.
+    pub async fn run_pending(
+        &self,
+        executor: &dyn JobExecutor,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<Job>, JobError> {
+        let mut finished = Vec::new();
+        for queued in self.list()? {
+            if cancel.is_cancelled() {
+                break;
+            }
+            if queued.status.is_finished() {
+                continue;
+            }
+            // A held lock means another runner is claiming the job or it is being
+            // cancelled; either way it is not this runner's to start.
+            let Some(claim) = self.try_lock(&queued.id)? else {
+                continue;
+            };
+            // Re-read under the lock so a cancel issued since the listing is honoured.
+            let mut job = self.get(&queued.id)?;
+            match job.status {
+                JobStatus::Pending => {}
+                JobStatus::Running if self.runner_gone(&job.id)? => {
+                    job.error = Some(RUNNER_GONE.to_string());
+                    self.transition(&mut job, JobStatus::Failed)?;
+                    finished.push(job);
+                    continue;
+                }
+                _ => continue,
+            }
+            let Some(runner) = self.try_lock_runner(&job.id)? else {
+                continue;
+            };
+            self.transition(&mut job, JobStatus::Running)?;
+            drop(claim);
+
+            let mut ctx = JobContext {
+                store: self,
+                job_id: job.id.clone(),
+                log: File::create(self.log_path(&job.id))?,
+                cancel: cancel.child_token(),
+            };
+            let outcome = match ctx.checkpoint() {
+                Ok(()) => executor.execute(&job.args, &mut ctx).await,
+                Err(e) => Err(e.into()),
+            };
+            let cancelled = ctx.cancel.is_cancelled();
+            let _ = ctx.log.flush();
+
+            let _lock = self.lock(&job.id).await?;
+            job = self.get(&job.id)?;
+            match outcome {
+                Ok(result) if !cancelled => {
+                    job.result = result;
+                    self.transition(&mut job, JobStatus::Succeeded)?;
+                }
+                Err(e) if !cancelled => {
+                    job.error = Some(e.to_string());
+                    self.transition(&mut job, JobStatus::Failed)?;
+                }
+                _ => self.transition(&mut job, JobStatus::Cancelled)?,
+            }
+            // Only released once the final state is on disk, so the job never looks
+            // abandoned while it is still running.
+            drop(runner);
+            finished.push(job);
+        }
+        Ok(finished)
+    }
+
+    fn transition(&self, job: &mut Job, status: JobStatus) -> Result<(), JobError> {
+        job.status = status;
+        job.updated_at = Utc::now();
+        self.save(job)
+    }
+
+    /// Write the job file atomically (temporary file + rename).
+    fn save(&self, job: &Job) -> Result<(), JobError> {
+        std::fs::create_dir_all(&self.dir)?;
+        let tmp = self
+            .dir
+            .join(format!(".{}.{}.tmp", job.id, uuid::Uuid::new_v4().simple()));
+        std::fs::write(&tmp, serde_json::to_vec_pretty(job)?)?;
+        std::fs::rename(&tmp, self.job_path(&job.id)).inspect_err(|_| {
+            let _ = std::fs::remove_file(&tmp);
+        })?;
+        Ok(())
+    }
+}
+
+/// Remove the lock at `path` if it was left behind by a crashed process, i.e. it is older
+/// than [`STALE_LOCK_AGE`]. Returns whether the lock is gone.
+fn break_stale_lock(path: &Path) -> std::io::Result<bool> {
+    let content = match std::fs::read(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(true),
+        Err(e) => return Err(e),
+    };
+    // The owner writes "<pid> <created>" right after creating the file; until then, and for
+    // locks without it, the file time stands in.
+    let created = String::from_utf8_lossy(&content)
+        .split_whitespace()
+        .nth(1)
+        .and_then(|created| DateTime::parse_from_rfc3339(created).ok())
+        .map(SystemTime::from);
+    let created = match created {
+        Some(created) => created,
+        None => match std::fs::metadata(path).and_then(|meta| meta.modified()) {
+            Ok(modified) => modified,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(true),
+            Err(e) => return Err(e),
+        },
+    };
+    let stale = SystemTime::now()
+        .duration_since(created)
+        .is_ok_and(|age| age >= STALE_LOCK_AGE);
+    if !stale {
+        return Ok(false);
+    }
+
+    // Move the lock aside before deleting it: if another process broke it and took a fresh
+    // one in the meantime, that one is put back rather than lost.
+    let aside = path.with_extension(format!("lock.{}", uuid::Uuid::new_v4().simple()));
+    match std::fs::rename(path, &aside) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(true),
+        Err(e) => return Err(e),
+    }
+    let moved = std::fs::read(&aside)?;
+    if moved != content {
+        let _ = std::fs::hard_link(&aside, path);
+        std::fs::remove_file(&aside)?;
+        return Ok(false);
+    }
+    std::fs::remove_file(&aside)?;
+    Ok(true)
+}
+
+/// Read a job's captured output; empty if the job has not started.
+pub fn read_log(store: &JobStore, id: &str) -> Result<String, JobError> {
+    match std::fs::read_to_string(store.log_path(id)) {
+        Ok(log) => Ok(log),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(String::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Default job directory for the repository whose storage lives in `libra_dir`.
+pub fn jobs_dir(libra_dir: &Path) -> PathBuf {
+    libra_dir.join("ai").join("jobs")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::internal::ai::{
+        agent::{Agent, AgentBuilder},
+        completion::{
+            AssistantContent, CompletionError, CompletionModel, CompletionRequest,
+            CompletionResponse, Message, Prompt, Text, UserContent,
+        },
+    };
+
+    /// Replies with the prompt in upper case.
+    #[derive(Clone)]
+    struct ShoutingModel;
+
+    impl CompletionModel for ShoutingModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            request: CompletionRequest,
+        ) -> Result<CompletionResponse<Self::Response>, CompletionError> {
+            let Some(Message::User { content }) = request.chat_history.last() else {
+                panic!("expected a user prompt");
+            };
+            let UserContent::Text(prompt) = content.iter().next().unwrap() else {
+                panic!("expected a text prompt");
+            };
+            Ok(CompletionResponse {
+                content: vec![AssistantContent::Text(Text {
+                    text: prompt.text.to_uppercase(),
+                })],
+                raw_response: (),
+            })
+        }
+    }
+
+    /// Runs `prompt <text>` jobs; `--cancel-self` cancels the job from "another process"
+    /// between the prompt and the answer being recorded.
+    struct PromptExecutor {
+        agent: Agent<ShoutingModel>,
+        store_dir: PathBuf,
+    }
+
+    #[async_trait]
+    impl JobExecutor for PromptExecutor {
+        async fn execute(
+            &self,
+            args: &[String],
+            ctx: &mut JobContext<'_>,
+        ) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+            let [op, rest @ ..] = args else {
+                return Err("missing operation".into());
+            };
+            if op != "prompt" {
+                return Err(format!("unknown operation '{op}'").into());
+            }
+            let cancel_self = rest.iter().any(|arg| arg == "--cancel-self");
+            let text = rest
+                .iter()
+                .filter(|arg| *arg != "--cancel-self")
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            writeln!(ctx.log(), "> {text}")?;
+            let answer = self.agent.prompt(text).await?;
+            if cancel_self {
+                JobStore::new(&self.store_dir).cancel(&ctx.job_id).await?;
+            }
+            ctx.checkpoint()?;
+            writeln!(ctx.log(), "{answer}")?;
+            Ok(Some(answer))
+        }
+    }
+
+    fn executor(dir: &Path) -> PromptExecutor {
+        PromptExecutor {
+            agent: AgentBuilder::new(ShoutingModel).build(),
+            store_dir: dir.to_path_buf(),
+        }
+    }
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_run_queue_transitions_and_captures_output() {
+        let temp = tempdir().unwrap();
+        let store = JobStore::new(temp.path().join("jobs"));
+
+        let first = store.submit(args(&["prompt", "hello", "queue"])).unwrap();
+        let second = store.submit(args(&["prompt", "never runs"])).unwrap();
+        let broken = store.submit(args(&["summarize"])).unwrap();
+        assert_eq!(first.status, JobStatus::Pending);
+        assert_eq!(
+            store
+                .list()
+                .unwrap()
+                .iter()
+                .map(|job| &job.id)
+                .collect::<Vec<_>>(),
+            vec![&first.id, &second.id, &broken.id]
+        );
+
+        let cancelled = store.cancel(&second.id[..24]).await.unwrap();
+        assert_eq!(cancelled.status, JobStatus::Cancelled);
+        assert!(matches!(
+            store.cancel(&second.id).await,
+            Err(JobError::Finished {
+                status: JobStatus::Cancelled,
+                ..
+            })
+        ));
+
+        let ran = store
+            .run_pending(&executor(&store.dir), &CancellationToken::new())
+            .await
+            .unwrap();
+        assert_eq!(ran.len(), 2);
+
+        let first = store.get(&first.id).unwrap();
+        assert_eq!(first.status, JobStatus::Succeeded);
+        assert_eq!(first.result.as_deref(), Some("HELLO QUEUE"));
+        assert_eq!(
+            read_log(&store, &first.id).unwrap(),
+            "> hello queue\nHELLO QUEUE\n"
+        );
+
+        let second = store.get(&second.id).unwrap();
+        assert_eq!(second.status, JobStatus::Cancelled);
+        assert!(!store.log_path(&second.id).exists());
+
+        let broken = store.get(&broken.id).unwrap();
+        assert_eq!(broken.status, JobStatus::Failed);
+        assert_eq!(
+            broken.error.as_deref(),
+            Some("unknown operation 'summarize'")
+        );
+
+        // Nothing is left pending, and no temporary files remain.
+        assert!(
+            store
+                .run_pending(&executor(&store.dir), &CancellationToken::new())
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert!(std::fs::read_dir(&store.dir).unwrap().all(|entry| {
+            let name = entry.unwrap().file_name();
+            let name = name.to_string_lossy();
+            !name.ends_with(".tmp") && !name.ends_with(".lock") && !name.ends_with(".runner")
+        }));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_running_job_between_steps() {
+        let temp = tempdir().unwrap();
+        let store = JobStore::new(temp.path());
+        let job = store
+            .submit(args(&["prompt", "--cancel-self", "stop"]))
+            .unwrap();
+
+        let ran = store
+            .run_pending(&executor(temp.path()), &CancellationToken::new())
+            .await
+            .unwrap();
+        assert_eq!(ran[0].status, JobStatus::Cancelled);
+        assert!(ran[0].cancel_requested);
+        assert_eq!(ran[0].result, None);
+        // Output captured before the checkpoint is kept.
+        assert_eq!(read_log(&store, &job.id).unwrap(), "> stop\n");
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_runner_leaves_jobs_pending() {
+        let temp = tempdir().unwrap();
+        let store = JobStore::new(temp.path());
+        let job = store.submit(args(&["prompt", "later"])).unwrap();
+
+        let token = CancellationToken::new();
+        token.cancel();
+        assert!(
+            store
+                .run_pending(&executor(temp.path()), &token)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(store.get(&job.id).unwrap().status, JobStatus::Pending);
+    }
+
+    #[tokio::test]
+    async fn test_locked_job_is_neither_claimed_nor_cancelled() {
+        let temp = tempdir().unwrap();
+        let store = JobStore::new(temp.path());
+        let job = store.submit(args(&["prompt", "held"])).unwrap();
+        File::create(store.lock_path(&job.id)).unwrap();
+
+        // Another process is updating the job, so this runner leaves it alone.
+        assert!(
+            store
+                .run_pending(&executor(temp.path()), &CancellationToken::new())
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(store.get(&job.id).unwrap().status, JobStatus::Pending);
+        assert!(matches!(store.cancel(&job.id).await, Err(JobError::Locked(id)) if id == job.id));
+
+        std::fs::remove_file(store.lock_path(&job.id)).unwrap();
+        assert_eq!(
+            store.cancel(&job.id).await.unwrap().status,
+            JobStatus::Cancelled
+        );
+        assert!(!store.lock_path(&job.id).exists());
+    }
+
+    #[tokio::test]
+    async fn test_stale_lock_is_broken() {
+        let temp = tempdir().unwrap();
+        let store = JobStore::new(temp.path());
+        let stuck = store.submit(args(&["prompt", "stuck"])).unwrap();
+        let other = store.submit(args(&["prompt", "other"])).unwrap();
+        // Left behind by a process that crashed while updating the jobs.
+        let created = Utc::now() - chrono::Duration::from_std(STALE_LOCK_AGE * 2).unwrap();
+        for id in [&stuck.id, &other.id] {
+            std::fs::write(
+                store.lock_path(id),
+                format!("4242 {}\n", created.to_rfc3339()),
+            )
+            .unwrap();
+        }
+
+        assert_eq!(
+            store.cancel(&other.id).await.unwrap().status,
+            JobStatus::Cancelled
+        );
+        let ran = store
+            .run_pending(&executor(temp.path()), &CancellationToken::new())
+            .await
+            .unwrap();
+        assert_eq!(ran.len(), 1);
+        assert_eq!(ran[0].id, stuck.id);
+        assert_eq!(ran[0].status, JobStatus::Succeeded);
+        assert!(!store.lock_path(&stuck.id).exists());
+        assert!(!store.lock_path(&other.id).exists());
+    }
+
+    #[tokio::test]
+    async fn test_job_left_running_by_a_dead_runner_is_not_stuck() {
+        let temp = tempdir().unwrap();
+        let store = JobStore::new(temp.path());
+        let ids = ["orphaned", "abandoned", "live"].map(|text| {
+            let mut job = store.submit(args(&["prompt", text])).unwrap();
+            store.transition(&mut job, JobStatus::Running).unwrap();
+            job.id
+        });
+        let [orphaned, abandoned, live] = &ids;
+        // A crashed runner leaves its runner file, but not the OS lock on it.
+        std::fs::write(store.runner_path(orphaned), "4242\n").unwrap();
+        let runner = store.try_lock_runner(live).unwrap().unwrap();
+
+        let cancelled = store.cancel(abandoned).await.unwrap();
+        assert_eq!(cancelled.status, JobStatus::Cancelled);
+        assert_eq!(cancelled.error.as_deref(), Some(RUNNER_GONE));
+
+        let ran = store
+            .run_pending(&executor(temp.path()), &CancellationToken::new())
+            .await
+            .unwrap();
+        assert_eq!(ran.len(), 1);
+        assert_eq!(&ran[0].id, orphaned);
+        assert_eq!(ran[0].status, JobStatus::Failed);
+        assert_eq!(ran[0].error.as_deref(), Some(RUNNER_GONE));
+
+        // A job whose runner is alive is left to it.
+        assert_eq!(store.get(live).unwrap().status, JobStatus::Running);
+        let live = store.cancel(live).await.unwrap();
+        assert_eq!(live.status, JobStatus::Running);
+        assert!(live.cancel_requested);
+        drop(runner);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_runners_start_each_job_once() {
+        let temp = tempdir().unwrap();
+        let dir = temp.path().to_path_buf();
+        let store = JobStore::new(&dir);
+        for i in 0..200 {
+            store
+                .submit(args(&["prompt", &format!("job {i}")]))
+                .unwrap();
+        }
+
+        let runners: Vec<_> = (0..4)
+            .map(|_| {
+                let dir = dir.clone();
+                tokio::spawn(async move {
+                    JobStore::new(&dir)
+                        .run_pending(&executor(&dir), &CancellationToken::new())
+                        .await
+                        .unwrap()
+                })
+            })
+            .collect();
+        let mut ran = Vec::new();
+        for runner in runners {
+            ran.extend(runner.await.unwrap().into_iter().map(|job| job.id));
+        }
+
+        ran.sort();
+        let before = ran.len();
+        ran.dedup();
+        assert_eq!(ran.len(), before, "a job was started twice");
+        assert_eq!(ran.len(), 200);
+        assert!(
+            store
+                .list()
+                .unwrap()
+                .iter()
+                .all(|job| job.status == JobStatus::Succeeded)
+        );
+    }
+}
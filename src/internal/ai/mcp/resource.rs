@@ -32,7 +32,7 @@ use git_internal::internal::object::{
     plan::{Plan, PlanStep, StepStatus},
     provenance::Provenance,
     run::{Run, RunStatus},
-    task::{GoalType, Task, TaskStatus},
+    task::{GoalType, Task},
     tool::{IoFootprint, ToolInvocation, ToolStatus},
     types::{ActorKind, ActorRef, ArtifactRef},
 };
@@ -47,7 +47,7 @@ use rmcp::{
 use uuid::Uuid;
 
 use crate::{
-    internal::ai::mcp::server::LibraMcpServer,
+    internal::ai::{mcp::server::LibraMcpServer, task::parse_task_status},
     utils::storage_ext::{Identifiable, StorageExt},
 };
 
@@ -740,14 +740,7 @@ impl LibraMcpServer {
 
         // Set task status if explicitly provided
         if let Some(s) = params.status {
-            task.set_status(match s.as_str() {
-                "draft" => TaskStatus::Draft,
-                "running" => TaskStatus::Running,
-                "done" => TaskStatus::Done,
-                "failed" => TaskStatus::Failed,
-                "cancelled" => TaskStatus::Cancelled,
-                _ => return Err(ErrorData::invalid_params("invalid task status", None)),
-            });
+            task.set_status(parse_task_status(&s).map_err(|e| ErrorData::invalid_params(e, None))?);
         }
 
         // Set tags and external_ids
@@ -0,0 +1,34 @@
+//! Persistent agent memory shared across runs.
+//!
+//! Memories are short key-value facts ("the user prefers tabs", "the build command is
+//! cargo nextest") stored in `.libra/ai/memory.json`. Agents write and query them through
+//! the `remember` and `recall` tools, and [`ChatAgent`](crate::internal::ai::ChatAgent)
+//! can inject the most relevant ones into its preamble on every request.
+//!
+//! ## Usage
+//!
+//! ```no_run
+//! use libra::internal::ai::memory::MemoryStore;
+//!
+//! let store = MemoryStore::new(std::path::Path::new("/path/to/project"));
+//! store.remember("build command", "cargo nextest run").unwrap();
+//!
+//! let hits = store.recall("how do I build?", 3).unwrap();
+//! ```
+
+pub mod store;
+
+pub use store::{DEFAULT_MAX_ENTRIES, MAX_VALUE_BYTES, MemoryEntry, MemoryStore};
+
+/// Render entries as a preamble section, or `None` if there are none.
+pub fn format_memories(entries: &[MemoryEntry]) -> Option<String> {
+    if entries.is_empty() {
+        return None;
+    }
+    let lines = entries
+        .iter()
+        .map(|entry| format!("- {}: {}", entry.key, entry.value))
+        .collect::<Vec<_>>()
+        .join("\n");
+    Some(format!("Relevant memories from earlier sessions:\n{lines}"))
+}
@@ -0,0 +1,341 @@
+//! Memory storage: a size-bounded key-value file shared by agent runs.
+
+use std::{
+    cmp::Reverse,
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Default number of entries kept per namespace.
+pub const DEFAULT_MAX_ENTRIES: usize = 200;
+
+/// Longest value accepted by [`MemoryStore::remember`], in bytes.
+pub const MAX_VALUE_BYTES: usize = 2048;
+
+/// A single remembered fact.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemoryEntry {
+    pub key: String,
+    pub value: String,
+    pub created_at: DateTime<Utc>,
+    /// Last time the entry was written or returned by a lookup.
+    pub last_used: DateTime<Utc>,
+}
+
+/// On-disk layout of `memory.json`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct MemoryFile {
+    #[serde(default)]
+    namespaces: BTreeMap<String, Vec<MemoryEntry>>,
+}
+
+/// Persistent key-value memory for agents.
+///
+/// Entries live in `{working_dir}/.libra/ai/memory.json`, grouped by namespace so several
+/// projects (or worktrees) can share one file. Each namespace holds at most `max_entries`
+/// entries; the least recently used ones are evicted first.
+///
+/// Every change rewrites the file through a temporary file and a rename, so a concurrent
+/// reader or writer never observes a half-written file. Concurrent writers in different
+/// processes can still overwrite each other's changes (last writer wins).
+pub struct MemoryStore {
+    path: PathBuf,
+    namespace: String,
+    max_entries: usize,
+    /// Serializes read-modify-write cycles of this instance.
+    lock: Mutex<()>,
+}
+
+impl MemoryStore {
+    /// Create a store for `working_dir`, using its path as the namespace.
+    pub fn new(working_dir: &Path) -> Self {
+        Self {
+            path: working_dir.join(".libra").join("ai").join("memory.json"),
+            namespace: working_dir.display().to_string(),
+            max_entries: DEFAULT_MAX_ENTRIES,
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Use a different namespace within the same file.
+    pub fn with_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = namespace.into();
+        self
+    }
+
+    /// Change how many entries the namespace may hold.
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries.max(1);
+        self
+    }
+
+    /// Path of the backing JSON file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Store `value` under `key`, replacing any previous value.
+    pub fn remember(&self, key: &str, value: &str) -> std::io::Result<()> {
+        let key = key.trim();
+        if key.is_empty() {
+            return Err(invalid_input("memory key must not be empty"));
+        }
+        if value.len() > MAX_VALUE_BYTES {
+            return Err(invalid_input(format!(
+                "memory value is {} bytes, the limit is {MAX_VALUE_BYTES} bytes",
+                value.len()
+            )));
+        }
+
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut file = self.read_file()?;
+        let entries = file.namespaces.entry(self.namespace.clone()).or_default();
+        let now = Utc::now();
+        match entries.iter_mut().find(|e| e.key == key) {
+            Some(entry) => {
+                entry.value = value.to_string();
+                entry.last_used = now;
+            }
+            None => entries.push(MemoryEntry {
+                key: key.to_string(),
+                value: value.to_string(),
+                created_at: now,
+                last_used: now,
+            }),
+        }
+        if entries.len() > self.max_entries {
+            entries.sort_by_key(|entry| Reverse(entry.last_used));
+            entries.truncate(self.max_entries);
+        }
+        self.write_file(&file)
+    }
+
+    /// Remove `key`. Returns whether it existed.
+    pub fn forget(&self, key: &str) -> std::io::Result<bool> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut file = self.read_file()?;
+        let Some(entries) = file.namespaces.get_mut(&self.namespace) else {
+            return Ok(false);
+        };
+        let before = entries.len();
+        entries.retain(|e| e.key != key);
+        if entries.len() == before {
+            return Ok(false);
+        }
+        self.write_file(&file)?;
+        Ok(true)
+    }
+
+    /// All entries of the namespace, most recently used first.
+    pub fn entries(&self) -> std::io::Result<Vec<MemoryEntry>> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut entries = self
+            .read_file()?
+            .namespaces
+            .remove(&self.namespace)
+            .unwrap_or_default();
+        entries.sort_by_key(|entry| Reverse(entry.last_used));
+        Ok(entries)
+    }
+
+    /// Up to `limit` entries matching `query`, best match first.
+    ///
+    /// The query is split into lowercase keywords; an entry scores one point per keyword
+    /// found in its key or value, plus one if the whole query appears verbatim. Entries
+    /// with no match are skipped, and ties go to the most recently used entry. Returned
+    /// entries have their `last_used` timestamp refreshed.
+    pub fn recall(&self, query: &str, limit: usize) -> std::io::Result<Vec<MemoryEntry>> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut file = self.read_file()?;
+        let Some(entries) = file.namespaces.get_mut(&self.namespace) else {
+            return Ok(Vec::new());
+        };
+
+        let mut scored: Vec<(usize, usize)> = entries
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, entry)| {
+                let score = match_score(query, entry);
+                (score > 0).then_some((score, idx))
+            })
+            .collect();
+        scored.sort_by(|a, b| {
+            b.0.cmp(&a.0)
+                .then_with(|| entries[b.1].last_used.cmp(&entries[a.1].last_used))
+        });
+        scored.truncate(limit);
+        if scored.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let now = Utc::now();
+        for &(_, idx) in &scored {
+            entries[idx].last_used = now;
+        }
+        let found = scored
+            .iter()
+            .map(|&(_, idx)| entries[idx].clone())
+            .collect();
+        self.write_file(&file)?;
+        Ok(found)
+    }
+
+    fn read_file(&self) -> std::io::Result<MemoryFile> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(content) => serde_json::from_str(&content)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(MemoryFile::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn write_file(&self, file: &MemoryFile) -> std::io::Result<()> {
+        let dir = self
+            .path
+            .parent()
+            .expect("memory file path always has a parent");
+        std::fs::create_dir_all(dir)?;
+        let json = serde_json::to_string_pretty(file)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+
+        // Write next to the target so the rename stays on one filesystem.
+        let tmp = dir.join(format!(
+            ".memory.json.{}.{}.tmp",
+            std::process::id(),
+            uuid::Uuid::new_v4().simple()
+        ));
+        std::fs::write(&tmp, json)?;
+        std::fs::rename(&tmp, &self.path).inspect_err(|_| {
+            let _ = std::fs::remove_file(&tmp);
+        })
+    }
+}
+
+fn match_score(query: &str, entry: &MemoryEntry) -> usize {
+    let haystack = format!("{} {}", entry.key, entry.value).to_lowercase();
+    let query = query.trim().to_lowercase();
+    let keywords = query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.len() >= 2);
+    let mut score = keywords.filter(|word| haystack.contains(word)).count();
+    if !query.is_empty() && haystack.contains(&query) {
+        score += 1;
+    }
+    score
+}
+
+fn invalid_input(message: impl Into<String>) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidInput, message.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn test_remember_and_recall_across_instances() {
+        let tmp = TempDir::new().unwrap();
+        let store = MemoryStore::new(tmp.path());
+        store.remember("indent", "the user prefers tabs").unwrap();
+        store
+            .remember("build command", "cargo nextest run")
+            .unwrap();
+
+        let fresh = MemoryStore::new(tmp.path());
+        let found = fresh.recall("which build command?", 5).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].value, "cargo nextest run");
+        assert!(fresh.recall("unrelated", 5).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_remember_overwrites_key() {
+        let tmp = TempDir::new().unwrap();
+        let store = MemoryStore::new(tmp.path());
+        store.remember("indent", "spaces").unwrap();
+        store.remember("indent", "tabs").unwrap();
+
+        let entries = store.entries().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].value, "tabs");
+    }
+
+    #[test]
+    fn test_namespaces_are_isolated() {
+        let tmp = TempDir::new().unwrap();
+        let a = MemoryStore::new(tmp.path()).with_namespace("a");
+        let b = MemoryStore::new(tmp.path()).with_namespace("b");
+        a.remember("lang", "rust").unwrap();
+
+        assert!(b.recall("lang", 5).unwrap().is_empty());
+        assert_eq!(a.recall("lang", 5).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let tmp = TempDir::new().unwrap();
+        let store = MemoryStore::new(tmp.path()).with_max_entries(2);
+        store.remember("first", "one").unwrap();
+        store.remember("second", "two").unwrap();
+        // Touch "first" so "second" becomes the eviction candidate.
+        store.recall("first", 1).unwrap();
+        store.remember("third", "three").unwrap();
+
+        let keys: Vec<String> = store
+            .entries()
+            .unwrap()
+            .into_iter()
+            .map(|e| e.key)
+            .collect();
+        assert_eq!(keys.len(), 2);
+        assert!(keys.contains(&"first".to_string()));
+        assert!(keys.contains(&"third".to_string()));
+    }
+
+    #[test]
+    fn test_concurrent_writers_keep_file_valid() {
+        let tmp = TempDir::new().unwrap();
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                let dir = tmp.path().to_path_buf();
+                std::thread::spawn(move || {
+                    let store = MemoryStore::new(&dir);
+                    for j in 0..10 {
+                        store.remember(&format!("k{i}-{j}"), "v").unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let store = MemoryStore::new(tmp.path());
+        let content = std::fs::read_to_string(store.path()).unwrap();
+        assert!(serde_json::from_str::<serde_json::Value>(&content).is_ok());
+        assert!(!store.entries().unwrap().is_empty());
+        let leftovers = std::fs::read_dir(store.path().parent().unwrap())
+            .unwrap()
+            .filter(|e| e.as_ref().unwrap().file_name() != "memory.json")
+            .count();
+        assert_eq!(leftovers, 0);
+    }
+
+    #[test]
+    fn test_rejects_empty_key_and_oversized_value() {
+        let tmp = TempDir::new().unwrap();
+        let store = MemoryStore::new(tmp.path());
+        assert!(store.remember("  ", "v").is_err());
+        assert!(
+            store
+                .remember("k", &"x".repeat(MAX_VALUE_BYTES + 1))
+                .is_err()
+        );
+    }
+}
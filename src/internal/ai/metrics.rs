@@ -0,0 +1,513 @@
+//! Process-wide metrics for agent runs, exported in the Prometheus text format.
+//!
+//! Attach a [`MetricsObserver`] to a tool loop to record completions, latency, token usage
+//! and tool calls into the global [`MetricsRegistry`], then read them back with
+//! [`export_prometheus`]. With the `metrics-http` feature, [`serve`] exposes the same text on
+//! `GET /metrics`.
+//!
+//! Labels only ever carry model and tool names (plus fixed `outcome`/`direction` values), and
+//! each of those is capped at [`MAX_LABEL_VALUES`] distinct values; later names are folded
+//! into `"other"` so a misbehaving caller cannot grow the registry without bound.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write,
+    sync::{Mutex, OnceLock},
+    time::Duration,
+};
+
+use crate::internal::ai::{
+    agent::runtime::tool_loop::ToolLoopObserver,
+    completion::{CompletionError, Usage},
+    tools::ToolOutput,
+};
+
+/// Most distinct model (and, separately, tool) names tracked before folding into `"other"`.
+pub const MAX_LABEL_VALUES: usize = 64;
+
+/// Upper bounds, in seconds, of the `completion_latency_seconds` histogram buckets.
+const LATENCY_BUCKETS: [f64; 10] = [0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0];
+
+const OVERFLOW_LABEL: &str = "other";
+
+/// Counters and histograms for agent runs.
+///
+/// Most callers use the process-wide [`MetricsRegistry::global`]; separate instances are
+/// useful for isolation in tests.
+#[derive(Default)]
+pub struct MetricsRegistry {
+    inner: Mutex<Families>,
+}
+
+#[derive(Default)]
+struct Families {
+    models: BTreeSet<String>,
+    tools: BTreeSet<String>,
+    /// `(model, outcome)` → count.
+    completions: BTreeMap<(String, &'static str), u64>,
+    /// `model` → latency histogram.
+    latency: BTreeMap<String, Histogram>,
+    /// `(tool, outcome)` → count.
+    tool_calls: BTreeMap<(String, &'static str), u64>,
+    /// `(model, direction)` → count.
+    tokens: BTreeMap<(String, &'static str), u64>,
+}
+
+#[derive(Default)]
+struct Histogram {
+    /// Observations per bucket (not cumulative); the last slot holds those above every bound.
+    buckets: [u64; LATENCY_BUCKETS.len() + 1],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, value: f64) {
+        let slot = LATENCY_BUCKETS
+            .iter()
+            .position(|bound| value <= *bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        self.buckets[slot] += 1;
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+impl MetricsRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The registry shared by the whole process.
+    pub fn global() -> &'static MetricsRegistry {
+        static GLOBAL: OnceLock<MetricsRegistry> = OnceLock::new();
+        GLOBAL.get_or_init(MetricsRegistry::new)
+    }
+
+    /// Record one completion call against `model`.
+    pub fn record_completion(
+        &self,
+        model: &str,
+        latency: Duration,
+        result: Result<Option<Usage>, &CompletionError>,
+    ) {
+        let mut families = self.lock();
+        let model = bounded_label(&mut families.models, model);
+        let outcome = if result.is_ok() { "ok" } else { "error" };
+        *families
+            .completions
+            .entry((model.clone(), outcome))
+            .or_default() += 1;
+        families
+            .latency
+            .entry(model.clone())
+            .or_default()
+            .observe(latency.as_secs_f64());
+        if let Ok(Some(usage)) = result {
+            *families.tokens.entry((model.clone(), "input")).or_default() += usage.input_tokens;
+            *families.tokens.entry((model, "output")).or_default() += usage.output_tokens;
+        }
+    }
+
+    /// Record one finished tool call.
+    pub fn record_tool_call(&self, tool: &str, succeeded: bool) {
+        let mut families = self.lock();
+        let tool = bounded_label(&mut families.tools, tool);
+        let outcome = if succeeded { "ok" } else { "error" };
+        *families.tool_calls.entry((tool, outcome)).or_default() += 1;
+    }
+
+    /// Render every metric family in the Prometheus text exposition format.
+    pub fn export_prometheus(&self) -> String {
+        let families = self.lock();
+        let mut out = String::new();
+
+        write_header(
+            &mut out,
+            "completions_total",
+            "counter",
+            "Model completion calls.",
+        );
+        for ((model, outcome), count) in &families.completions {
+            write_sample(
+                &mut out,
+                "completions_total",
+                &[("model", model), ("outcome", outcome)],
+                *count as f64,
+            );
+        }
+
+        write_header(
+            &mut out,
+            "completion_latency_seconds",
+            "histogram",
+            "Latency of model completion calls.",
+        );
+        for (model, histogram) in &families.latency {
+            let mut cumulative = 0;
+            for (bound, count) in LATENCY_BUCKETS.iter().zip(histogram.buckets) {
+                cumulative += count;
+                write_sample(
+                    &mut out,
+                    "completion_latency_seconds_bucket",
+                    &[("model", model), ("le", &bound.to_string())],
+                    cumulative as f64,
+                );
+            }
+            write_sample(
+                &mut out,
+                "completion_latency_seconds_bucket",
+                &[("model", model), ("le", "+Inf")],
+                histogram.count as f64,
+            );
+            write_sample(
+                &mut out,
+                "completion_latency_seconds_sum",
+                &[("model", model)],
+                histogram.sum,
+            );
+            write_sample(
+                &mut out,
+                "completion_latency_seconds_count",
+                &[("model", model)],
+                histogram.count as f64,
+            );
+        }
+
+        write_header(
+            &mut out,
+            "tool_calls_total",
+            "counter",
+            "Tool calls by outcome.",
+        );
+        for ((tool, outcome), count) in &families.tool_calls {
+            write_sample(
+                &mut out,
+                "tool_calls_total",
+                &[("tool", tool), ("outcome", outcome)],
+                *count as f64,
+            );
+        }
+
+        write_header(
+            &mut out,
+            "tokens_total",
+            "counter",
+            "Tokens reported by providers.",
+        );
+        for ((model, direction), count) in &families.tokens {
+            write_sample(
+                &mut out,
+                "tokens_total",
+                &[("model", model), ("direction", direction)],
+                *count as f64,
+            );
+        }
+
+        out
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Families> {
+        // Metrics are best-effort; a panic elsewhere must not disable them.
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Render the global registry; see [`MetricsRegistry::export_prometheus`].
+pub fn export_prometheus() -> String {
+    MetricsRegistry::global().export_prometheus()
+}
+
+/// Tool-loop observer that records into a [`MetricsRegistry`].
+///
+/// The model name is supplied by the caller because completion models do not expose it.
+pub struct MetricsObserver<'a> {
+    registry: &'a MetricsRegistry,
+    model: String,
+}
+
+impl MetricsObserver<'static> {
+    /// Record runs of `model` into the global registry.
+    pub fn new(model: impl Into<String>) -> Self {
+        Self::with_registry(MetricsRegistry::global(), model)
+    }
+}
+
+impl<'a> MetricsObserver<'a> {
+    pub fn with_registry(registry: &'a MetricsRegistry, model: impl Into<String>) -> Self {
+        Self {
+            registry,
+            model: model.into(),
+        }
+    }
+}
+
+impl ToolLoopObserver for MetricsObserver<'_> {
+    fn on_completion(
+        &mut self,
+        latency: Duration,
+        result: Result<Option<Usage>, &CompletionError>,
+    ) {
+        self.registry
+            .record_completion(&self.model, latency, result);
+    }
+
+    fn on_tool_call_end(
+        &mut self,
+        _call_id: &str,
+        tool_name: &str,
+        result: &Result<ToolOutput, String>,
+    ) {
+        self.registry
+            .record_tool_call(tool_name, result.as_ref().is_ok_and(|o| o.is_success()));
+    }
+}
+
+/// Serve the global registry as `GET /metrics` on `addr` until the server fails.
+#[cfg(feature = "metrics-http")]
+pub async fn serve(addr: std::net::SocketAddr) -> std::io::Result<()> {
+    use axum::{Router, http::header, routing::get};
+
+    let app = Router::new().route(
+        "/metrics",
+        get(|| async {
+            (
+                [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+                export_prometheus(),
+            )
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    axum::serve(listener, app).await
+}
+
+/// Map `value` to itself while fewer than [`MAX_LABEL_VALUES`] names are tracked, and to
+/// `"other"` after that.
+fn bounded_label(seen: &mut BTreeSet<String>, value: &str) -> String {
+    if seen.contains(value) {
+        return value.to_string();
+    }
+    if seen.len() >= MAX_LABEL_VALUES {
+        return OVERFLOW_LABEL.to_string();
+    }
+    seen.insert(value.to_string());
+    value.to_string()
+}
+
+fn write_header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+}
+
+fn write_sample(out: &mut String, name: &str, labels: &[(&str, &str)], value: f64) {
+    let labels = labels
+        .iter()
+        .map(|(key, value)| format!("{key}=\"{}\"", escape_label_value(value)))
+        .collect::<Vec<_>>()
+        .join(",");
+    let _ = writeln!(out, "{name}{{{labels}}} {value}");
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, sync::Arc};
+
+    use async_trait::async_trait;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::internal::ai::{
+        agent::runtime::tool_loop::{ToolLoopConfig, run_tool_loop_with_history_and_observer},
+        completion::{
+            AssistantContent, CompletionModel, CompletionRequest, CompletionResponse, Message,
+            UserContent,
+            message::{Function, Text, ToolCall},
+        },
+        tools::{ToolHandler, ToolInvocation, ToolKind, ToolRegistry, ToolSpec},
+    };
+
+    /// Calls `echo` once, then answers; every response reports 10 input and 5 output tokens.
+    #[derive(Clone)]
+    struct EchoingModel;
+
+    impl CompletionModel for EchoingModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            request: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            let has_tool_result = request.chat_history.iter().any(|msg| match msg {
+                Message::User { content } => content
+                    .iter()
+                    .any(|c| matches!(c, UserContent::ToolResult(_))),
+                _ => false,
+            });
+            let content = if has_tool_result {
+                AssistantContent::Text(Text {
+                    text: "done".to_string(),
+                })
+            } else {
+                AssistantContent::ToolCall(ToolCall {
+                    id: "call_1".to_string(),
+                    name: "echo".to_string(),
+                    function: Function {
+                        name: "echo".to_string(),
+                        arguments: json!({}),
+                    },
+                })
+            };
+            Ok(CompletionResponse {
+                content: vec![content],
+                raw_response: (),
+            })
+        }
+
+        fn usage(&self, _response: &CompletionResponse<()>) -> Option<Usage> {
+            Some(Usage {
+                input_tokens: 10,
+                output_tokens: 5,
+            })
+        }
+    }
+
+    struct EchoHandler;
+
+    #[async_trait]
+    impl ToolHandler for EchoHandler {
+        fn kind(&self) -> ToolKind {
+            ToolKind::Function
+        }
+
+        async fn handle(
+            &self,
+            _invocation: ToolInvocation,
+        ) -> crate::internal::ai::tools::ToolResult<ToolOutput> {
+            Ok(ToolOutput::success("echo"))
+        }
+
+        fn schema(&self) -> ToolSpec {
+            ToolSpec::new("echo", "echo tool")
+        }
+    }
+
+    /// Parse exposition text into `series → value`, checking every series belongs to a
+    /// family declared with `# TYPE`.
+    fn parse_exposition(text: &str) -> HashMap<String, f64> {
+        let mut families = Vec::new();
+        let mut samples = HashMap::new();
+        for line in text.lines() {
+            if let Some(declaration) = line.strip_prefix("# TYPE ") {
+                families.push(declaration.split(' ').next().unwrap().to_string());
+                continue;
+            }
+            if line.starts_with('#') {
+                continue;
+            }
+            let (series, value) = line.rsplit_once(' ').unwrap();
+            let name = series.split('{').next().unwrap();
+            assert!(
+                families
+                    .iter()
+                    .any(|family| name.starts_with(family.as_str())),
+                "{name} has no TYPE declaration"
+            );
+            samples.insert(series.to_string(), value.parse::<f64>().unwrap());
+        }
+        samples
+    }
+
+    #[tokio::test]
+    async fn test_metrics_observer_exports_tool_loop_runs() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut tools = ToolRegistry::with_working_dir(temp_dir.path().to_path_buf());
+        tools.register("echo", Arc::new(EchoHandler));
+        let registry = MetricsRegistry::new();
+
+        for prompt in ["first", "second"] {
+            let mut observer = MetricsObserver::with_registry(&registry, "mock-model");
+            let turn = run_tool_loop_with_history_and_observer(
+                &EchoingModel,
+                Vec::new(),
+                prompt,
+                &tools,
+                ToolLoopConfig::default(),
+                &mut observer,
+            )
+            .await
+            .unwrap();
+            assert_eq!(turn.final_text, "done");
+        }
+
+        let text = registry.export_prometheus();
+        for family in [
+            "completions_total",
+            "completion_latency_seconds",
+            "tool_calls_total",
+            "tokens_total",
+        ] {
+            assert!(text.contains(&format!("# TYPE {family} ")), "{text}");
+        }
+
+        let samples = parse_exposition(&text);
+        let value = |series: &str| {
+            samples
+                .get(series)
+                .copied()
+                .unwrap_or_else(|| panic!("{series} missing from\n{text}"))
+        };
+        assert_eq!(
+            value(r#"completions_total{model="mock-model",outcome="ok"}"#),
+            4.0
+        );
+        assert_eq!(
+            value(r#"completion_latency_seconds_count{model="mock-model"}"#),
+            4.0
+        );
+        assert_eq!(
+            value(r#"completion_latency_seconds_bucket{model="mock-model",le="+Inf"}"#),
+            4.0
+        );
+        assert_eq!(value(r#"tool_calls_total{tool="echo",outcome="ok"}"#), 2.0);
+        assert_eq!(
+            value(r#"tokens_total{model="mock-model",direction="input"}"#),
+            40.0
+        );
+        assert_eq!(
+            value(r#"tokens_total{model="mock-model",direction="output"}"#),
+            20.0
+        );
+    }
+
+    #[test]
+    fn test_label_cardinality_is_bounded() {
+        let registry = MetricsRegistry::new();
+        for i in 0..MAX_LABEL_VALUES + 3 {
+            registry.record_tool_call(&format!("tool_{i}"), true);
+        }
+        registry.record_tool_call("late_tool", false);
+
+        let samples = parse_exposition(&registry.export_prometheus());
+        assert_eq!(samples.len(), MAX_LABEL_VALUES + 2);
+        assert_eq!(
+            samples[r#"tool_calls_total{tool="other",outcome="ok"}"#],
+            3.0
+        );
+        assert_eq!(
+            samples[r#"tool_calls_total{tool="other",outcome="error"}"#],
+            1.0
+        );
+        assert_eq!(
+            samples[r#"tool_calls_total{tool="tool_0",outcome="ok"}"#],
+            1.0
+        );
+    }
+}
@@ -23,19 +23,33 @@
 //! ```
 
 pub mod agent;
+pub mod budget;
 pub mod client;
+pub mod clock;
 pub mod commands;
 pub mod completion;
+pub mod diff_context;
+pub mod eval;
+pub mod event_stream;
+pub mod explain;
+pub mod fix_build;
 pub mod history;
 pub mod hooks;
 pub mod intent;
+pub mod jobs;
 pub mod mcp;
+pub mod memory;
+pub mod metrics;
 pub mod node_adapter;
+pub mod precommit;
 pub mod prompt;
 pub mod providers;
+pub mod repo_context;
 pub mod session;
+pub mod task;
 pub mod tools;
 pub mod util;
+pub mod work;
 
 pub use agent::{Agent, AgentBuilder, ChatAgent};
 pub use completion::{Chat, CompletionModel, Message, Prompt};
@@ -19,18 +19,71 @@
 //!    with `"\n\n"` as a separator to form a single prompt.
 //! 2. **Execution**: Run the agent (or tool loop) with the assembled prompt.
 //! 3. **Output**: Broadcast the agent's response to all downstream nodes.
+//!
+//! # Shared Budgets
+//!
+//! Independent branches of a graph run concurrently. To cap their combined spend, attach
+//! clones of one [`SharedBudget`] to each node with `with_budget`; a branch that finds the
+//! budget exhausted fails with
+//! [`CompletionError::BudgetExceeded`](crate::internal::ai::completion::CompletionError::BudgetExceeded)
+//! while the others continue.
+//!
+//! # Deadlines
+//!
+//! A node can be given a timeout with `with_node_timeout`, after which it fails without a
+//! result. When the agent or loop also has an overall deadline
+//! ([`AgentBuilder::deadline`](crate::internal::ai::AgentBuilder::deadline),
+//! [`ToolLoopAction::with_deadline`]), the node timeout is derived from it instead: the
+//! deadline plus [`NODE_DEADLINE_GRACE`], so the run stops on its own first and the node's
+//! error carries the partial result of
+//! [`CompletionError::DeadlineExceeded`].
 
-use std::sync::Arc;
+use std::{future::Future, sync::Arc, time::Duration};
 
 use async_trait::async_trait;
 use dagrs::{Action, Content, EnvVar, InChannels, OutChannels, Output};
 
 use crate::internal::ai::{
     agent::{Agent, ToolLoopConfig, run_tool_loop},
-    completion::{CompletionModel, Prompt},
+    budget::SharedBudget,
+    clock::SystemClock,
+    completion::{CompletionError, CompletionModel, Prompt, UntrustedContent},
     tools::ToolRegistry,
 };
 
+/// How long a node with an overall deadline may run past it: time for the run to notice
+/// the deadline once a tool call in progress returns.
+pub const NODE_DEADLINE_GRACE: Duration = Duration::from_secs(5);
+
+/// The timeout of a node: `node_timeout`, or, when there is also a `deadline`, the deadline
+/// plus [`NODE_DEADLINE_GRACE`].
+fn effective_node_timeout(
+    node_timeout: Option<Duration>,
+    deadline: Option<Duration>,
+) -> Option<Duration> {
+    match (node_timeout, deadline) {
+        (Some(_), Some(deadline)) => Some(deadline + NODE_DEADLINE_GRACE),
+        (node_timeout, _) => node_timeout,
+    }
+}
+
+/// Runs `run` within `timeout`, if any.
+async fn run_with_node_timeout<T>(
+    timeout: Option<Duration>,
+    run: impl Future<Output = Result<T, CompletionError>>,
+) -> Result<T, String> {
+    let result = match timeout {
+        Some(limit) => tokio::time::timeout(limit, run)
+            .await
+            .map_err(|_| format!("Node timed out after {} ms", limit.as_millis()))?,
+        None => run.await,
+    };
+    result.map_err(|e| match e.partial_result() {
+        Some(partial) => format!("{e}\n\nPartial result:\n{partial}"),
+        None => e.to_string(),
+    })
+}
+
 /// An [`Action`] adapter that wraps an AI [`Agent`] for use in a DAG node.
 ///
 /// This adapter bridges the gap between `dagrs::Action` and the AI `Agent`.
@@ -47,6 +100,8 @@ use crate::internal::ai::{
 pub struct AgentAction<M: CompletionModel + 'static> {
     /// The wrapped AI Agent instance.
     agent: Agent<M>,
+    /// Limit on the whole node; see [`AgentAction::with_node_timeout`].
+    node_timeout: Option<Duration>,
 }
 
 impl<M: CompletionModel> AgentAction<M> {
@@ -56,7 +111,27 @@ impl<M: CompletionModel> AgentAction<M> {
     ///
     /// * `agent` - The configured [`Agent`] instance to wrap.
     pub fn new(agent: Agent<M>) -> Self {
-        Self { agent }
+        Self {
+            agent,
+            node_timeout: None,
+        }
+    }
+
+    /// Draws the wrapped agent's completion calls from `budget`; see
+    /// [`AgentBuilder::budget`](crate::internal::ai::AgentBuilder::budget).
+    pub fn with_budget(self, budget: SharedBudget) -> Self {
+        Self {
+            agent: self.agent.with_budget(budget),
+            ..self
+        }
+    }
+
+    /// Fails the node if the agent has not answered within `timeout`. If the agent has an
+    /// [overall deadline](crate::internal::ai::AgentBuilder::deadline), the timeout is
+    /// derived from it instead; see the [module docs](self#deadlines).
+    pub fn with_node_timeout(mut self, timeout: Duration) -> Self {
+        self.node_timeout = Some(timeout);
+        self
     }
 }
 
@@ -112,7 +187,8 @@ impl<M: CompletionModel> Action for AgentAction<M> {
         let input = inputs.join("\n\n");
 
         // Step 2: Run the agent with the assembled prompt
-        match self.agent.prompt(input).await {
+        let timeout = effective_node_timeout(self.node_timeout, self.agent.deadline());
+        match run_with_node_timeout(timeout, self.agent.prompt(input)).await {
             Ok(resp) => {
                 // Broadcast the successful response to all downstream nodes
                 let content = Content::new(resp);
@@ -121,7 +197,7 @@ impl<M: CompletionModel> Action for AgentAction<M> {
             }
             Err(e) => {
                 tracing::error!("Agent Execution Error: {}", e);
-                Output::Err(e.to_string())
+                Output::Err(e)
             }
         }
     }
@@ -149,6 +225,8 @@ pub struct ToolLoopAction<M: CompletionModel + 'static> {
     registry: ToolRegistry,
     /// Configuration controlling the behavior of the tool loop.
     config: ToolLoopConfig,
+    /// Limit on the whole node; see [`ToolLoopAction::with_node_timeout`].
+    node_timeout: Option<Duration>,
 }
 
 impl<M: CompletionModel> ToolLoopAction<M> {
@@ -175,11 +253,51 @@ impl<M: CompletionModel> ToolLoopAction<M> {
                 preamble,
                 temperature,
                 max_steps,
+                max_tool_calls: None,
                 hook_runner: None,
                 allowed_tools: None,
+                request_timeout: None,
+                deadline: None,
+                clock: Arc::new(SystemClock),
+                include_reasoning: false,
+                guardrails: None,
+                budget: None,
+                untrusted_content: Some(Arc::new(UntrustedContent::default())),
             },
+            node_timeout: None,
         }
     }
+
+    /// Sets a deadline for each model completion call in the loop.
+    ///
+    /// A node-level timeout must be longer than this so that a hung request surfaces as a
+    /// retryable [`CompletionError::Timeout`](crate::internal::ai::completion::CompletionError::Timeout)
+    /// instead of the whole node timing out.
+    pub fn with_request_timeout(mut self, timeout: Duration) -> Self {
+        self.config.request_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets an overall deadline for the loop; see [`ToolLoopConfig::deadline`].
+    pub fn with_deadline(mut self, deadline: Duration) -> Self {
+        self.config.deadline = Some(deadline);
+        self
+    }
+
+    /// Fails the node if the loop has not finished within `timeout`. With
+    /// [`with_deadline`](Self::with_deadline) as well, the timeout is derived from the
+    /// deadline instead; see the [module docs](self#deadlines).
+    pub fn with_node_timeout(mut self, timeout: Duration) -> Self {
+        self.node_timeout = Some(timeout);
+        self
+    }
+
+    /// Draws every completion call of the loop from `budget`, which may be shared with
+    /// other nodes.
+    pub fn with_budget(mut self, budget: SharedBudget) -> Self {
+        self.config.budget = Some(budget);
+        self
+    }
 }
 
 #[async_trait]
@@ -231,7 +349,9 @@ impl<M: CompletionModel> Action for ToolLoopAction<M> {
         let prompt = inputs.join("\n\n");
 
         // Run the iterative tool-calling loop with the assembled prompt
-        match run_tool_loop(&self.model, prompt, &self.registry, self.config.clone()).await {
+        let timeout = effective_node_timeout(self.node_timeout, self.config.deadline);
+        let run = run_tool_loop(&self.model, prompt, &self.registry, self.config.clone());
+        match run_with_node_timeout(timeout, run).await {
             Ok(resp) => {
                 // Broadcast the successful response to all downstream nodes
                 let content = Content::new(resp);
@@ -240,7 +360,7 @@ impl<M: CompletionModel> Action for ToolLoopAction<M> {
             }
             Err(e) => {
                 tracing::error!("Agent Tool Loop Error: {}", e);
-                Output::Err(e.to_string())
+                Output::Err(e)
             }
         }
     }
@@ -0,0 +1,457 @@
+//! AI review of staged changes, run from the pre-commit hook.
+//!
+//! `libra ai pre-commit-review` sends the staged diff to the `precommit_reviewer` profile
+//! (embedded; a project or user profile of the same name replaces it), which must answer
+//! with a [`ReviewReport`] in strict JSON. Findings at or above the blocking threshold
+//! ([`PRECOMMIT_SEVERITY_KEY`], `high` by default) fail the hook unless the review is
+//! advisory.
+//!
+//! The hook must never make committing impossible: setting [`SKIP_REVIEW_ENV`] skips the
+//! review, and a repository without a configured provider skips it with a message.
+//!
+//! With `--record` (or `ai.precommit.record`), the review is kept as a pending
+//! [`AgentRunRecord`] in `.libra/ai/precommit-review.json`. The next commit made on top of
+//! the same `HEAD` stores it on the AI history branch with its commit id filled in; see
+//! [`link_pending_review`].
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+    time::Duration,
+};
+
+use chrono::Utc;
+use git_internal::{
+    Diff,
+    hash::ObjectHash,
+    internal::{
+        index::Index,
+        object::{blob::Blob, commit::Commit, tree::Tree},
+    },
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    command::load_object,
+    internal::{
+        ai::{
+            CompletionModel, Prompt,
+            agent::{
+                profile::{AgentProfile, build_agent_from_profile, parse_agent_profile},
+                runtime::step_history::AgentRunRecord,
+            },
+            completion::CompletionError,
+            diff_context::DiffPacker,
+            history::HistoryManager,
+        },
+        head::Head,
+    },
+    utils::{object_ext::TreeExt, path, storage::local::LocalStorage, storage_ext::StorageExt},
+};
+
+/// Name of the profile the review runs with.
+pub const PRECOMMIT_PROFILE: &str = "precommit_reviewer";
+
+/// Config key (`libra config ai.precommit.severity <level>`) for the lowest severity that
+/// blocks the commit.
+pub const PRECOMMIT_SEVERITY_KEY: &str = "ai.precommit.severity";
+
+/// Config key naming the provider the review runs against; unset skips the review.
+pub const PRECOMMIT_PROVIDER_KEY: &str = "ai.precommit.provider";
+
+/// Config key for the model id; unset uses the provider's default model.
+pub const PRECOMMIT_MODEL_KEY: &str = "ai.precommit.model";
+
+/// Config key that turns on recording reviews as [`AgentRunRecord`]s.
+pub const PRECOMMIT_RECORD_KEY: &str = "ai.precommit.record";
+
+/// File below `.libra/ai` holding a recorded review until its commit exists.
+const PENDING_REVIEW_FILE: &str = "precommit-review.json";
+
+/// Environment variable that skips the review when set to anything but `0` or empty.
+pub const SKIP_REVIEW_ENV: &str = "LIBRA_SKIP_AI_REVIEW";
+
+const EMBEDDED_PROFILE: &str = include_str!("agent/profile/embedded/precommit_reviewer.md");
+
+/// The embedded [`PRECOMMIT_PROFILE`], used when no loaded profile has that name.
+pub fn embedded_profile() -> AgentProfile {
+    parse_agent_profile(EMBEDDED_PROFILE).expect("embedded precommit profile is valid")
+}
+
+/// The profile named [`PRECOMMIT_PROFILE`] among `profiles`, or the embedded one.
+pub fn review_profile(profiles: Vec<AgentProfile>) -> AgentProfile {
+    profiles
+        .into_iter()
+        .find(|profile| profile.name == PRECOMMIT_PROFILE)
+        .unwrap_or_else(embedded_profile)
+}
+
+/// Whether [`SKIP_REVIEW_ENV`] asks to skip the review.
+pub fn review_skipped() -> bool {
+    std::env::var(SKIP_REVIEW_ENV).is_ok_and(|value| !value.is_empty() && value != "0")
+}
+
+/// How serious a [`ReviewFinding`] is, lowest first. The default threshold is `High`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FindingSeverity {
+    Low,
+    Medium,
+    #[default]
+    High,
+    Critical,
+}
+
+impl fmt::Display for FindingSeverity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            FindingSeverity::Low => "low",
+            FindingSeverity::Medium => "medium",
+            FindingSeverity::High => "high",
+            FindingSeverity::Critical => "critical",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for FindingSeverity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "low" => Ok(FindingSeverity::Low),
+            "medium" => Ok(FindingSeverity::Medium),
+            "high" => Ok(FindingSeverity::High),
+            "critical" => Ok(FindingSeverity::Critical),
+            other => Err(format!(
+                "invalid severity '{other}' (expected low, medium, high or critical)"
+            )),
+        }
+    }
+}
+
+/// One problem the reviewer reported.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ReviewFinding {
+    pub severity: FindingSeverity,
+    pub file: String,
+    #[serde(default)]
+    pub line: Option<u32>,
+    pub message: String,
+}
+
+impl fmt::Display for ReviewFinding {
+    /// E.g. `HIGH src/lib.rs:42: unwrap() on user input`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = self.severity.to_string().to_ascii_uppercase();
+        match self.line {
+            Some(line) => write!(f, "{severity} {}:{line}: {}", self.file, self.message),
+            None => write!(f, "{severity} {}: {}", self.file, self.message),
+        }
+    }
+}
+
+/// The reviewer's answer. Anything but exactly this shape is rejected.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ReviewReport {
+    pub findings: Vec<ReviewFinding>,
+}
+
+impl ReviewReport {
+    /// Parse a model answer. A surrounding Markdown code fence is tolerated; any other text
+    /// or an unknown field is an error.
+    pub fn parse(answer: &str) -> Result<Self, CompletionError> {
+        let answer = answer.trim();
+        let json = answer
+            .strip_prefix("```json")
+            .or_else(|| answer.strip_prefix("```"))
+            .and_then(|rest| rest.strip_suffix("```"))
+            .unwrap_or(answer);
+        serde_json::from_str(json.trim()).map_err(|e| {
+            CompletionError::ResponseError(format!(
+                "review does not match the findings schema: {e}"
+            ))
+        })
+    }
+
+    /// Findings at or above `threshold`, in report order.
+    pub fn blocking(&self, threshold: FindingSeverity) -> impl Iterator<Item = &ReviewFinding> {
+        self.findings
+            .iter()
+            .filter(move |finding| finding.severity >= threshold)
+    }
+}
+
+/// Settings of one review run.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PrecommitReview {
+    /// Lowest severity that blocks the commit.
+    pub threshold: FindingSeverity,
+    /// Report findings but never block.
+    pub advisory: bool,
+    /// Give up on the review after this long; see
+    /// [`AgentBuilder::deadline`](crate::internal::ai::AgentBuilder::deadline).
+    pub deadline: Option<Duration>,
+}
+
+/// The result of [`PrecommitReview::run`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReviewOutcome {
+    pub report: ReviewReport,
+    /// Number of findings at or above the threshold.
+    pub blocking: usize,
+    /// Exit status for the hook: 1 if blocking findings fail the commit, else 0.
+    pub exit_code: i32,
+}
+
+impl PrecommitReview {
+    /// Review `diff` with `model` under `profile`. Large diffs are packed into half the
+    /// model's context window; see [`DiffPacker`].
+    pub async fn run<M: CompletionModel>(
+        &self,
+        model: M,
+        profile: &AgentProfile,
+        diff: &str,
+    ) -> Result<ReviewOutcome, CompletionError> {
+        let packed = DiffPacker::for_model(&model.capabilities()).pack(diff);
+        let mut builder = build_agent_from_profile(model, profile);
+        if let Some(deadline) = self.deadline {
+            builder = builder.deadline(deadline);
+        }
+        let answer: String = builder
+            .build()
+            .prompt(format!("Review this staged diff:\n\n{}", packed.document))
+            .await?;
+        Ok(self.judge(ReviewReport::parse(&answer)?))
+    }
+
+    /// Apply the threshold and advisory mode to `report`.
+    pub fn judge(&self, report: ReviewReport) -> ReviewOutcome {
+        let blocking = report.blocking(self.threshold).count();
+        let exit_code = i32::from(blocking > 0 && !self.advisory);
+        ReviewOutcome {
+            report,
+            blocking,
+            exit_code,
+        }
+    }
+}
+
+/// Unified diff of the index against `HEAD`, or against nothing on an unborn branch.
+/// Empty when nothing is staged.
+pub async fn staged_diff() -> Result<String, String> {
+    let index = Index::load(path::index()).map_err(|e| e.to_string())?;
+    let old_blobs = match Head::current_commit().await {
+        Some(head) => {
+            let commit = load_object::<Commit>(&head).map_err(|e| e.to_string())?;
+            load_object::<Tree>(&commit.tree_id)
+                .map_err(|e| e.to_string())?
+                .get_plain_items()
+        }
+        None => Vec::new(),
+    };
+    let new_blobs = index
+        .tracked_entries(0)
+        .into_iter()
+        .map(|entry| (PathBuf::from(&entry.name), entry.hash))
+        .collect();
+    let read_content = |_: &PathBuf, hash: &ObjectHash| {
+        load_object::<Blob>(hash)
+            .map(|blob| blob.data)
+            .unwrap_or_default()
+    };
+    Ok(Diff::diff(old_blobs, new_blobs, Vec::new(), read_content)
+        .into_iter()
+        .map(|item| item.data)
+        .collect())
+}
+
+/// A recorded review waiting for its commit.
+#[derive(Debug, Serialize, Deserialize)]
+struct PendingReview {
+    /// `HEAD` when the review ran; the commit must be made on top of it.
+    head: Option<String>,
+    record: AgentRunRecord,
+}
+
+fn pending_review_path(libra_dir: &Path) -> PathBuf {
+    libra_dir.join("ai").join(PENDING_REVIEW_FILE)
+}
+
+/// Keep `outcome` as the pending review of the commit about to be made on top of `head`,
+/// replacing any earlier one.
+pub fn save_pending_review(
+    libra_dir: &Path,
+    head: Option<&ObjectHash>,
+    outcome: &ReviewOutcome,
+) -> std::io::Result<()> {
+    let pending = PendingReview {
+        head: head.map(ToString::to_string),
+        record: AgentRunRecord {
+            run_id: uuid::Uuid::new_v4().simple().to_string(),
+            profile: Some(PRECOMMIT_PROFILE.to_string()),
+            answer: serde_json::to_string(&outcome.report)?,
+            commit: None,
+            task: None,
+            created_at: Utc::now(),
+        },
+    };
+    let file = pending_review_path(libra_dir);
+    std::fs::create_dir_all(file.parent().expect("pending review file has a parent"))?;
+    std::fs::write(file, serde_json::to_vec_pretty(&pending)?)
+}
+
+/// Store the pending review on the AI history branch of `libra_dir`, linked to `commit`,
+/// if it was made for a commit on top of `previous_head`. The pending file is removed
+/// either way, so a review never attaches to an unrelated later commit. Failures are
+/// logged and never fail the commit.
+pub async fn link_pending_review(
+    libra_dir: &Path,
+    previous_head: Option<&ObjectHash>,
+    commit: &ObjectHash,
+) {
+    let file = pending_review_path(libra_dir);
+    let Ok(data) = std::fs::read(&file) else {
+        return;
+    };
+    let _ = std::fs::remove_file(&file);
+    let pending = match serde_json::from_slice::<PendingReview>(&data) {
+        Ok(pending) => pending,
+        Err(e) => {
+            tracing::warn!("ignoring unreadable pending review: {e}");
+            return;
+        }
+    };
+    if pending.head != previous_head.map(ToString::to_string) {
+        return;
+    }
+
+    let mut record = pending.record;
+    record.commit = Some(commit.to_string());
+    let storage = Arc::new(LocalStorage::new(libra_dir.join("objects")));
+    let history = HistoryManager::new(storage.clone(), libra_dir.to_path_buf());
+    if let Err(e) = storage.put_tracked(&record, &history).await {
+        tracing::warn!(run_id = %record.run_id, "failed to record pre-commit review: {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::ai::completion::{
+        CompletionRequest, CompletionResponse,
+        message::{AssistantContent, Text},
+    };
+
+    /// Reports one blocking and one advisory finding for any diff.
+    #[derive(Clone)]
+    struct ReviewerModel;
+
+    impl CompletionModel for ReviewerModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            request: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            assert!(request.preamble.unwrap().contains("single JSON object"));
+            let text = r#"```json
+{"findings": [
+  {"severity": "critical", "file": "src/auth.rs", "line": 12, "message": "API key committed in source"},
+  {"severity": "low", "file": "src/auth.rs", "line": null, "message": "consider a doc comment"}
+]}
+```"#;
+            Ok(CompletionResponse {
+                content: vec![AssistantContent::Text(Text {
+                    text: text.to_string(),
+                })],
+                raw_response: (),
+            })
+        }
+    }
+
+    const DIFF: &str = "diff --git a/src/auth.rs b/src/auth.rs\n--- a/src/auth.rs\n+++ b/src/auth.rs\n@@ -1 +1,2 @@\n fn key() {}\n+const KEY: &str = \"sk-live\";\n";
+
+    #[tokio::test]
+    async fn test_blocking_and_advisory_modes() {
+        let profile = embedded_profile();
+        assert_eq!(profile.name, PRECOMMIT_PROFILE);
+
+        let outcome = PrecommitReview::default()
+            .run(ReviewerModel, &profile, DIFF)
+            .await
+            .unwrap();
+        assert_eq!(outcome.report.findings.len(), 2);
+        assert_eq!((outcome.blocking, outcome.exit_code), (1, 1));
+        assert_eq!(
+            outcome.report.findings[0].to_string(),
+            "CRITICAL src/auth.rs:12: API key committed in source"
+        );
+
+        let advisory = PrecommitReview {
+            advisory: true,
+            ..Default::default()
+        };
+        let outcome = advisory.run(ReviewerModel, &profile, DIFF).await.unwrap();
+        assert_eq!((outcome.blocking, outcome.exit_code), (1, 0));
+
+        // With the threshold at `low` both findings block.
+        let strict = PrecommitReview {
+            threshold: "LOW".parse().unwrap(),
+            advisory: false,
+            deadline: None,
+        };
+        assert_eq!(strict.judge(outcome.report).blocking, 2);
+    }
+
+    #[tokio::test]
+    async fn test_pending_review_links_to_its_commit() {
+        use crate::internal::ai::agent::runtime::step_history::AGENT_RUN_TYPE;
+
+        let dir = tempfile::tempdir().unwrap();
+        let libra_dir = dir.path().join(".libra");
+        std::fs::create_dir_all(libra_dir.join("objects")).unwrap();
+        let head = ObjectHash::new(&[1; 20]);
+        let commit = ObjectHash::new(&[2; 20]);
+        let outcome = PrecommitReview::default().judge(ReviewReport::default());
+
+        // A commit on top of another HEAD drops the pending review.
+        save_pending_review(&libra_dir, Some(&head), &outcome).unwrap();
+        link_pending_review(&libra_dir, None, &commit).await;
+        assert!(!pending_review_path(&libra_dir).exists());
+
+        save_pending_review(&libra_dir, Some(&head), &outcome).unwrap();
+        link_pending_review(&libra_dir, Some(&head), &commit).await;
+        assert!(!pending_review_path(&libra_dir).exists());
+
+        let storage = Arc::new(LocalStorage::new(libra_dir.join("objects")));
+        let history = HistoryManager::new(storage.clone(), libra_dir);
+        let runs = history.list_objects(AGENT_RUN_TYPE).await.unwrap();
+        assert_eq!(runs.len(), 1);
+        let record: AgentRunRecord = storage.get_json(&runs[0].1).await.unwrap();
+        assert_eq!(record.commit, Some(commit.to_string()));
+        assert_eq!(record.answer, r#"{"findings":[]}"#);
+    }
+
+    #[test]
+    fn test_report_schema_is_strict() {
+        assert!(ReviewReport::parse(r#"{"findings": []}"#).is_ok());
+        assert!(ReviewReport::parse("Looks good to me!").is_err());
+        assert!(
+            ReviewReport::parse(
+                r#"{"findings": [{"severity": "blocker", "file": "a", "message": "m"}]}"#
+            )
+            .is_err()
+        );
+        assert!(
+            ReviewReport::parse(
+                r#"{"findings": [{"severity": "low", "file": "a", "message": "m", "fix": "x"}]}"#
+            )
+            .is_err()
+        );
+    }
+}
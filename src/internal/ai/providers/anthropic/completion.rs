@@ -6,8 +6,8 @@ use crate::internal::ai::{
     client::{CompletionClient, Provider},
     completion::{
         AssistantContent, CompletionError, CompletionModel as CompletionModelTrait, Function,
-        Message, Text, ToolCall, UserContent,
-        request::{CompletionRequest, CompletionResponse},
+        Message, ModelCapabilities, Reasoning, Text, ToolCall, UserContent,
+        request::{CompletionRequest, CompletionResponse, Usage},
     },
     providers::anthropic::client::Client,
     tools::ToolDefinition,
@@ -49,6 +49,8 @@ struct AnthropicRequest {
     system: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f64>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     tools: Vec<AnthropicToolDefinition>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -91,6 +93,16 @@ enum AnthropicContentBlock {
         #[serde(skip_serializing_if = "Option::is_none")]
         is_error: Option<bool>,
     },
+    /// Extended thinking output.
+    Thinking {
+        thinking: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        signature: Option<String>,
+    },
+    /// Thinking the API encrypted instead of returning in plain text.
+    RedactedThinking {
+        data: String,
+    },
 }
 
 /// Anthropic image source.
@@ -157,6 +169,21 @@ struct AnthropicErrorResponse {
 impl CompletionModelTrait for Model {
     type Response = AnthropicResponse;
 
+    fn capabilities(&self) -> ModelCapabilities {
+        // Every current Claude model has a 200k-token context window.
+        ModelCapabilities {
+            max_context: Some(200_000),
+            ..ModelCapabilities::default()
+        }
+    }
+
+    fn usage(&self, response: &CompletionResponse<Self::Response>) -> Option<Usage> {
+        Some(Usage {
+            input_tokens: response.raw_response.usage.input_tokens,
+            output_tokens: response.raw_response.usage.output_tokens,
+        })
+    }
+
     async fn completion(
         &self,
         request: CompletionRequest,
@@ -166,7 +193,9 @@ impl CompletionModelTrait for Model {
 
         // Calculate default max_tokens based on model if not provided
         // Anthropic requires max_tokens to be set
-        let max_tokens = calculate_max_tokens(&self.model);
+        let max_tokens = request
+            .max_tokens
+            .unwrap_or_else(|| calculate_max_tokens(&self.model));
 
         // Build request
         let anthropic_request = AnthropicRequest {
@@ -175,6 +204,7 @@ impl CompletionModelTrait for Model {
             max_tokens,
             system,
             temperature: request.temperature,
+            top_p: request.top_p,
             tool_choice: if tools.is_empty() {
                 None
             } else {
@@ -292,6 +322,9 @@ fn build_messages(
                                 });
                             }
                         }
+                        // Thinking blocks cannot be replayed without their signature, and
+                        // refusals are output-only.
+                        AssistantContent::Reasoning(_) | AssistantContent::Refusal { .. } => {}
                         AssistantContent::ToolCall(call) => {
                             content_blocks.push(AnthropicContentBlock::ToolUse {
                                 id: call.id.clone(),
@@ -345,7 +378,30 @@ fn build_messages(
     Ok((system, messages))
 }
 
+/// Stop reason of a response the model declined for policy reasons.
+const REFUSAL_STOP_REASON: &str = "refusal";
+
 fn parse_response(response: &AnthropicResponse) -> Vec<AssistantContent> {
+    if response.stop_reason.as_deref() == Some(REFUSAL_STOP_REASON) {
+        // Any text streamed before the refusal is its only explanation.
+        let text: Vec<&str> = response
+            .content
+            .iter()
+            .filter_map(|block| match block {
+                AnthropicContentBlock::Text { text } if !text.trim().is_empty() => {
+                    Some(text.trim())
+                }
+                _ => None,
+            })
+            .collect();
+        let message = if text.is_empty() {
+            "the model declined to answer under Anthropic's usage policy".to_string()
+        } else {
+            text.join("\n")
+        };
+        return vec![AssistantContent::Refusal { message }];
+    }
+
     let mut parts = Vec::new();
 
     for block in &response.content {
@@ -365,6 +421,14 @@ fn parse_response(response: &AnthropicResponse) -> Vec<AssistantContent> {
                     },
                 }));
             }
+            AnthropicContentBlock::Thinking { thinking, .. } if !thinking.trim().is_empty() => {
+                parts.push(AssistantContent::Reasoning(Reasoning::new(
+                    thinking.clone(),
+                )));
+            }
+            AnthropicContentBlock::RedactedThinking { .. } => {
+                parts.push(AssistantContent::Reasoning(Reasoning::redacted()));
+            }
             _ => {}
         }
     }
@@ -427,6 +491,7 @@ mod tests {
             max_tokens: 4096,
             system: Some("You are a helpful assistant.".to_string()),
             temperature: Some(0.7),
+            top_p: None,
             tools: Vec::new(),
             tool_choice: None,
         };
@@ -501,6 +566,58 @@ mod tests {
         assert_eq!(response.stop_reason, Some("tool_use".to_string()));
     }
 
+    #[test]
+    fn test_anthropic_thinking_response_maps_to_reasoning() {
+        let json = r#"
+        {
+            "id": "msg_789",
+            "type": "message",
+            "role": "assistant",
+            "content": [
+                {"type": "thinking", "thinking": "Check the units first.", "signature": "sig"},
+                {"type": "redacted_thinking", "data": "opaque"},
+                {"type": "text", "text": "It is 20 degrees."}
+            ],
+            "model": "claude-sonnet-4-0",
+            "stop_reason": "end_turn",
+            "usage": {"input_tokens": 10, "output_tokens": 30}
+        }
+        "#;
+
+        let response: AnthropicResponse = serde_json::from_str(json).unwrap();
+        let parts = parse_response(&response);
+        assert_eq!(
+            parts,
+            vec![
+                AssistantContent::Reasoning(Reasoning::new("Check the units first.")),
+                AssistantContent::Reasoning(Reasoning::redacted()),
+                AssistantContent::Text(Text {
+                    text: "It is 20 degrees.".to_string()
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_anthropic_refusal_maps_to_refusal() {
+        let json = r#"
+        {
+            "id": "msg_790",
+            "type": "message",
+            "role": "assistant",
+            "content": [],
+            "model": "claude-sonnet-4-0",
+            "stop_reason": "refusal",
+            "usage": {"input_tokens": 10, "output_tokens": 0}
+        }
+        "#;
+
+        let response: AnthropicResponse = serde_json::from_str(json).unwrap();
+        let parts = parse_response(&response);
+        assert_eq!(parts.len(), 1);
+        assert!(parts[0].refusal().unwrap().contains("usage policy"));
+    }
+
     #[test]
     fn test_build_messages_consolidates_system_content() {
         let request = CompletionRequest {
@@ -5,9 +5,9 @@ use serde::{Deserialize, Serialize};
 use crate::internal::ai::{
     client::{CompletionClient, Provider},
     completion::{
-        AssistantContent, CompletionError, CompletionModel as CompletionModelTrait, Message, Text,
-        ToolCall, UserContent,
-        request::{CompletionRequest, CompletionResponse},
+        AssistantContent, CompletionError, CompletionModel as CompletionModelTrait, Message,
+        Reasoning, Text, ToolCall, UserContent,
+        request::{CompletionRequest, CompletionResponse, Usage},
     },
     providers::deepseek::client::Client,
     tools::ToolDefinition,
@@ -46,6 +46,10 @@ struct DeepSeekRequest {
     messages: Vec<DeepSeekMessage>,
     #[serde(skip_serializing_if = "Option::is_none")]
     temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u64>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     tools: Vec<DeepSeekToolDefinition>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -67,6 +71,9 @@ enum DeepSeekMessage {
         content: Option<String>,
         #[serde(default, skip_serializing_if = "Vec::is_empty")]
         tool_calls: Vec<DeepSeekToolCall>,
+        /// Chain-of-thought returned by reasoning models. Never sent back to the API.
+        #[serde(default, skip_serializing)]
+        reasoning_content: Option<String>,
     },
     Tool {
         tool_call_id: String,
@@ -202,6 +209,7 @@ impl From<&Message> for DeepSeekMessage {
                 DeepSeekMessage::Assistant {
                     content: if text.is_empty() { None } else { Some(text) },
                     tool_calls: Vec::new(),
+                    reasoning_content: None,
                 }
             }
             Message::System { content } => {
@@ -228,6 +236,13 @@ impl From<&Message> for DeepSeekMessage {
 impl CompletionModelTrait for Model {
     type Response = DeepSeekResponse;
 
+    fn usage(&self, response: &CompletionResponse<Self::Response>) -> Option<Usage> {
+        response.raw_response.usage.as_ref().map(|usage| Usage {
+            input_tokens: usage.prompt_tokens as u64,
+            output_tokens: usage.completion_tokens as u64,
+        })
+    }
+
     async fn completion(
         &self,
         request: CompletionRequest,
@@ -240,6 +255,8 @@ impl CompletionModelTrait for Model {
             model: self.model.clone(),
             messages,
             temperature: request.temperature,
+            top_p: request.top_p,
+            max_tokens: request.max_tokens,
             tool_choice: if tools.is_empty() {
                 None
             } else {
@@ -352,6 +369,8 @@ fn build_messages(request: &CompletionRequest) -> Result<Vec<DeepSeekMessage>, C
                                 text_parts.push(t.text.clone());
                             }
                         }
+                        // Reasoning and refusals are output-only for this API.
+                        AssistantContent::Reasoning(_) | AssistantContent::Refusal { .. } => {}
                         AssistantContent::ToolCall(call) => {
                             tool_calls.push(DeepSeekToolCall {
                                 id: call.id.clone(),
@@ -374,6 +393,7 @@ fn build_messages(request: &CompletionRequest) -> Result<Vec<DeepSeekMessage>, C
                 messages.push(DeepSeekMessage::Assistant {
                     content: text,
                     tool_calls,
+                    reasoning_content: None,
                 });
             }
             Message::System { content } => {
@@ -393,14 +413,32 @@ fn build_messages(request: &CompletionRequest) -> Result<Vec<DeepSeekMessage>, C
     Ok(messages)
 }
 
+/// Finish reason of a response stopped by DeepSeek's content moderation.
+const CONTENT_FILTER_FINISH_REASON: &str = "content_filter";
+
 fn parse_choice_content(choice: &DeepSeekChoice) -> Result<Vec<AssistantContent>, CompletionError> {
     match &choice.message {
         DeepSeekMessage::Assistant {
             content,
             tool_calls,
+            reasoning_content,
         } => {
+            if choice.finish_reason.as_deref() == Some(CONTENT_FILTER_FINISH_REASON) {
+                return Ok(vec![AssistantContent::Refusal {
+                    message: "the response was blocked by DeepSeek's content filter".to_string(),
+                }]);
+            }
+
             let mut parts = Vec::new();
 
+            if let Some(reasoning) = reasoning_content
+                && !reasoning.trim().is_empty()
+            {
+                parts.push(AssistantContent::Reasoning(Reasoning::new(
+                    reasoning.clone(),
+                )));
+            }
+
             if let Some(text) = content
                 && !text.trim().is_empty()
             {
@@ -474,6 +512,8 @@ mod tests {
                 },
             ],
             temperature: Some(0.7),
+            top_p: None,
+            max_tokens: None,
             tools: Vec::new(),
             tool_choice: None,
             stream: false,
@@ -493,6 +533,8 @@ mod tests {
                 content: "hi".to_string(),
             }],
             temperature: None,
+            top_p: None,
+            max_tokens: None,
             tools: vec![DeepSeekToolDefinition {
                 r#type: "function".to_string(),
                 function: DeepSeekFunctionDefinition {
@@ -546,6 +588,37 @@ mod tests {
         assert_eq!(response.usage.unwrap().total_tokens, 21);
     }
 
+    #[test]
+    fn test_deepseek_reasoning_content_maps_to_reasoning() {
+        let json = r#"
+        {
+            "index": 0,
+            "message": {
+                "role": "assistant",
+                "content": "42",
+                "reasoning_content": "Six times seven."
+            },
+            "finish_reason": "stop"
+        }
+        "#;
+
+        let choice: DeepSeekChoice = serde_json::from_str(json).unwrap();
+        let parts = parse_choice_content(&choice).unwrap();
+        assert_eq!(
+            parts,
+            vec![
+                AssistantContent::Reasoning(Reasoning::new("Six times seven.")),
+                AssistantContent::Text(Text {
+                    text: "42".to_string()
+                }),
+            ]
+        );
+
+        // Reasoning must not be echoed back in later requests.
+        let serialized = serde_json::to_value(&choice.message).unwrap();
+        assert!(serialized.get("reasoning_content").is_none());
+    }
+
     #[test]
     fn test_model_new() {
         let client = Client::with_api_key("test-key".to_string());
@@ -30,15 +30,19 @@ fn test_part_deserialization() {
     } else {
         panic!("Expected FunctionResponse part, got {:?}", part);
     }
+
+    // Test thought summary part
+    let json = r#"{"text": "Considering the question", "thought": true}"#;
+    let part: Part = serde_json::from_str(json).unwrap();
+    assert_eq!(part.thought, Some(true));
+    assert_eq!(part.text.as_deref(), Some("Considering the question"));
 }
 
 #[test]
 fn test_tool_definition_generation() {
-    use std::error::Error;
-
     use serde_json::Value;
 
-    use crate::internal::ai::tools::{Tool, ToolDefinition};
+    use crate::internal::ai::tools::{Tool, ToolCallError, ToolDefinition};
 
     struct MyTool;
     impl Tool for MyTool {
@@ -60,7 +64,7 @@ fn test_tool_definition_generation() {
                 }),
             }
         }
-        fn call(&self, _args: Value) -> Result<Value, Box<dyn Error + Send + Sync>> {
+        fn call(&self, _args: Value) -> Result<Value, ToolCallError> {
             Ok(Value::Null)
         }
     }
@@ -71,3 +75,26 @@ fn test_tool_definition_generation() {
     assert_eq!(def.description, "My Tool Description");
     assert_eq!(def.parameters["type"], "object");
 }
+
+#[test]
+fn test_safety_block_parses_to_refusal() {
+    use crate::internal::ai::providers::gemini::{
+        completion::parse_assistant_output, gemini_api_types::GenerateContentResponse,
+    };
+
+    let json = r#"{"candidates": [{"finishReason": "SAFETY"}]}"#;
+    let response: GenerateContentResponse = serde_json::from_str(json).unwrap();
+    let content = parse_assistant_output(&response).unwrap();
+    assert_eq!(
+        content[0].refusal(),
+        Some("the response was blocked by Gemini (SAFETY)")
+    );
+
+    let json = r#"{"promptFeedback": {"blockReason": "PROHIBITED_CONTENT"}}"#;
+    let response: GenerateContentResponse = serde_json::from_str(json).unwrap();
+    let content = parse_assistant_output(&response).unwrap();
+    assert_eq!(
+        content[0].refusal(),
+        Some("the prompt was blocked by Gemini (PROHIBITED_CONTENT)")
+    );
+}
@@ -11,7 +11,8 @@ use crate::internal::ai::{
     client::Provider,
     completion::{
         AssistantContent, CompletionError, CompletionModel as CompletionModelTrait,
-        CompletionRequest, CompletionResponse, Function, Message, Text, ToolCall, UserContent,
+        CompletionRequest, CompletionResponse, Function, Message, Reasoning, Text, ToolCall,
+        UserContent,
     },
     tools::ToolDefinition,
 };
@@ -98,6 +99,8 @@ impl CompletionModelTrait for CompletionModel {
                     for item in content.into_iter() {
                         match item {
                             AssistantContent::Text(t) => parts.push(Part::text(t.text)),
+                            // Thought summaries and refusals are output-only.
+                            AssistantContent::Reasoning(_) | AssistantContent::Refusal { .. } => {}
                             AssistantContent::ToolCall(tool_call) => {
                                 // Convert tool call to function call
                                 parts.push(Part::function_call(
@@ -145,6 +148,9 @@ impl CompletionModelTrait for CompletionModel {
             system_instruction,
             generation_config: Some(GenerationConfig {
                 temperature: request.temperature,
+                top_p: request.top_p,
+                max_output_tokens: request.max_tokens,
+                seed: request.seed,
             }),
             tools,
         };
@@ -196,9 +202,41 @@ impl CompletionModelTrait for CompletionModel {
     }
 }
 
-fn parse_assistant_output(
+/// Finish reasons of a candidate stopped by Gemini's safety filters.
+const SAFETY_FINISH_REASONS: &[&str] = &[
+    "SAFETY",
+    "PROHIBITED_CONTENT",
+    "BLOCKLIST",
+    "SPII",
+    "IMAGE_SAFETY",
+];
+
+/// Why Gemini refused the request, if it did: a blocked prompt or a candidate stopped by a
+/// safety filter.
+fn refusal_reason(api_resp: &GenerateContentResponse) -> Option<String> {
+    if let Some(reason) = api_resp
+        .prompt_feedback
+        .as_ref()
+        .and_then(|feedback| feedback.block_reason.as_deref())
+    {
+        return Some(format!("the prompt was blocked by Gemini ({reason})"));
+    }
+    let reason = api_resp
+        .candidates
+        .as_ref()
+        .and_then(|c| c.first())
+        .and_then(|c| c.finish_reason.as_deref())
+        .filter(|reason| SAFETY_FINISH_REASONS.contains(reason))?;
+    Some(format!("the response was blocked by Gemini ({reason})"))
+}
+
+pub(super) fn parse_assistant_output(
     api_resp: &GenerateContentResponse,
 ) -> Result<Vec<AssistantContent>, CompletionError> {
+    if let Some(message) = refusal_reason(api_resp) {
+        return Ok(vec![AssistantContent::Refusal { message }]);
+    }
+
     let parts = api_resp
         .candidates
         .as_ref()
@@ -213,7 +251,11 @@ fn parse_assistant_output(
         if let Some(text) = &part.text
             && !text.trim().is_empty()
         {
-            assistant_parts.push(AssistantContent::Text(Text { text: text.clone() }));
+            if part.thought == Some(true) {
+                assistant_parts.push(AssistantContent::Reasoning(Reasoning::new(text.clone())));
+            } else {
+                assistant_parts.push(AssistantContent::Text(Text { text: text.clone() }));
+            }
         }
 
         if let Some(function_call) = &part.function_call {
@@ -42,6 +42,9 @@ pub struct Part {
     pub function_call: Option<FunctionCall>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub function_response: Option<FunctionResponse>,
+    /// Set by the API when `text` is a thought summary rather than answer text.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thought: Option<bool>,
 }
 
 impl Part {
@@ -51,6 +54,7 @@ impl Part {
             text: Some(text.into()),
             function_call: None,
             function_response: None,
+            thought: None,
         }
     }
 
@@ -63,6 +67,7 @@ impl Part {
                 args,
             }),
             function_response: None,
+            thought: None,
         }
     }
 
@@ -75,6 +80,7 @@ impl Part {
                 name: name.into(),
                 response,
             }),
+            thought: None,
         }
     }
 
@@ -135,6 +141,12 @@ pub struct GenerateContentRequest {
 pub struct GenerationConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_output_tokens: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
 }
 
 /// Response structure for content generated by Gemini API.
@@ -142,6 +154,14 @@ pub struct GenerationConfig {
 #[serde(rename_all = "camelCase")]
 pub struct GenerateContentResponse {
     pub candidates: Option<Vec<Candidate>>,
+    pub prompt_feedback: Option<PromptFeedback>,
+}
+
+/// Feedback on the prompt, set when the prompt itself was blocked.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptFeedback {
+    pub block_reason: Option<String>,
 }
 
 /// A candidate content generated by the model.
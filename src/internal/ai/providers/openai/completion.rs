@@ -6,8 +6,8 @@ use crate::internal::ai::{
     client::{CompletionClient, Provider},
     completion::{
         AssistantContent, CompletionError, CompletionModel as CompletionModelTrait, Function,
-        Message, Text, ToolCall, UserContent,
-        request::{CompletionRequest, CompletionResponse},
+        Message, Reasoning, Text, ToolCall, UserContent,
+        request::{CompletionCandidates, CompletionRequest, CompletionResponse, Usage},
     },
     providers::openai::client::Client,
     tools::ToolDefinition,
@@ -46,6 +46,14 @@ struct OpenAIRequest {
     messages: Vec<OpenAIMessage>,
     #[serde(skip_serializing_if = "Option::is_none")]
     temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    n: Option<usize>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     tools: Vec<OpenAIToolDefinition>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -66,6 +74,12 @@ enum OpenAIMessage {
         content: Option<String>,
         #[serde(default, skip_serializing_if = "Vec::is_empty")]
         tool_calls: Vec<OpenAIToolCall>,
+        /// Chain-of-thought returned by reasoning models. Never sent back to the API.
+        #[serde(default, skip_serializing)]
+        reasoning_content: Option<String>,
+        /// Explanation sent instead of `content` when the model refuses. Output-only.
+        #[serde(default, skip_serializing)]
+        refusal: Option<String>,
     },
     Tool {
         tool_call_id: String,
@@ -148,6 +162,15 @@ struct OpenAIUsage {
     total_tokens: usize,
 }
 
+impl OpenAIUsage {
+    fn to_usage(&self) -> Usage {
+        Usage {
+            input_tokens: self.prompt_tokens as u64,
+            output_tokens: self.completion_tokens as u64,
+        }
+    }
+}
+
 /// OpenAI chat completion response.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct OpenAIResponse {
@@ -206,6 +229,8 @@ impl From<&Message> for OpenAIMessage {
                 OpenAIMessage::Assistant {
                     content: if text.is_empty() { None } else { Some(text) },
                     tool_calls: Vec::new(),
+                    reasoning_content: None,
+                    refusal: None,
                 }
             }
             Message::System { content } => {
@@ -236,14 +261,76 @@ impl CompletionModelTrait for Model {
         &self,
         request: CompletionRequest,
     ) -> Result<CompletionResponse<Self::Response>, CompletionError> {
+        let openai_response = self.send(&request).await?;
+
+        // Extract choice
+        let choice = openai_response
+            .choices
+            .first()
+            .ok_or_else(|| CompletionError::ResponseError("No choices in response".to_string()))?;
+
+        let content = parse_choice_content(choice)?;
+
+        Ok(CompletionResponse {
+            content,
+            raw_response: openai_response,
+        })
+    }
+
+    fn usage(&self, response: &CompletionResponse<Self::Response>) -> Option<Usage> {
+        response
+            .raw_response
+            .usage
+            .as_ref()
+            .map(OpenAIUsage::to_usage)
+    }
+
+    /// Asks for all candidates in one call through the `n` request parameter.
+    async fn completion_candidates(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionCandidates, CompletionError> {
+        let request = CompletionRequest {
+            n: Some(request.n.unwrap_or(1).max(1)),
+            ..request
+        };
+        let openai_response = self.send(&request).await?;
+        if openai_response.choices.is_empty() {
+            return Err(CompletionError::ResponseError(
+                "No choices in response".to_string(),
+            ));
+        }
+
+        Ok(CompletionCandidates {
+            candidates: openai_response
+                .choices
+                .iter()
+                .map(parse_choice_content)
+                .collect::<Result<_, _>>()?,
+            usage: openai_response
+                .usage
+                .as_ref()
+                .map(OpenAIUsage::to_usage)
+                .unwrap_or_default(),
+        })
+    }
+}
+
+impl Model {
+    /// Send `request` to the chat completions endpoint and decode the response.
+    async fn send(&self, request: &CompletionRequest) -> Result<OpenAIResponse, CompletionError> {
         let tools = parse_tools(&request.tools);
-        let messages = build_messages(&request)?;
+        let messages = build_messages(request)?;
 
         // Build request
         let openai_request = OpenAIRequest {
             model: self.model.clone(),
             messages,
             temperature: request.temperature,
+            top_p: request.top_p,
+            max_tokens: request.max_tokens,
+            seed: request.seed,
+            n: request.n,
             tool_choice: if tools.is_empty() {
                 None
             } else {
@@ -258,6 +345,9 @@ impl CompletionModelTrait for Model {
             .http_client
             .post(format!("{}/chat/completions", self.client.base_url))
             .json(&openai_request);
+        if let Some(key) = &request.idempotency_key {
+            req_builder = req_builder.header("Idempotency-Key", key);
+        }
         req_builder = self.client.provider.on_request(req_builder);
 
         let response = req_builder
@@ -277,21 +367,7 @@ impl CompletionModelTrait for Model {
             return Err(CompletionError::ProviderError(response_text));
         }
 
-        let openai_response: OpenAIResponse =
-            serde_json::from_str(&response_text).map_err(CompletionError::JsonError)?;
-
-        // Extract choice
-        let choice = openai_response
-            .choices
-            .first()
-            .ok_or_else(|| CompletionError::ResponseError("No choices in response".to_string()))?;
-
-        let content = parse_choice_content(choice)?;
-
-        Ok(CompletionResponse {
-            content,
-            raw_response: openai_response,
-        })
+        serde_json::from_str(&response_text).map_err(CompletionError::JsonError)
     }
 }
 
@@ -354,6 +430,8 @@ fn build_messages(request: &CompletionRequest) -> Result<Vec<OpenAIMessage>, Com
                                 text_parts.push(t.text.clone());
                             }
                         }
+                        // Reasoning and refusals are output-only for this API.
+                        AssistantContent::Reasoning(_) | AssistantContent::Refusal { .. } => {}
                         AssistantContent::ToolCall(call) => {
                             tool_calls.push(OpenAIToolCall {
                                 id: call.id.clone(),
@@ -376,6 +454,8 @@ fn build_messages(request: &CompletionRequest) -> Result<Vec<OpenAIMessage>, Com
                 messages.push(OpenAIMessage::Assistant {
                     content: text,
                     tool_calls,
+                    reasoning_content: None,
+                    refusal: None,
                 });
             }
             Message::System { content } => {
@@ -395,14 +475,38 @@ fn build_messages(request: &CompletionRequest) -> Result<Vec<OpenAIMessage>, Com
     Ok(messages)
 }
 
+/// Finish reason of a choice the content filter cut off.
+const CONTENT_FILTER_FINISH_REASON: &str = "content_filter";
+
 fn parse_choice_content(choice: &OpenAIChoice) -> Result<Vec<AssistantContent>, CompletionError> {
     match &choice.message {
         OpenAIMessage::Assistant {
             content,
             tool_calls,
+            reasoning_content,
+            refusal,
         } => {
+            if let Some(message) = refusal.as_ref().filter(|m| !m.trim().is_empty()) {
+                return Ok(vec![AssistantContent::Refusal {
+                    message: message.clone(),
+                }]);
+            }
+            if choice.finish_reason.as_deref() == Some(CONTENT_FILTER_FINISH_REASON) {
+                return Ok(vec![AssistantContent::Refusal {
+                    message: "the response was blocked by OpenAI's content filter".to_string(),
+                }]);
+            }
+
             let mut parts = Vec::new();
 
+            if let Some(reasoning) = reasoning_content
+                && !reasoning.trim().is_empty()
+            {
+                parts.push(AssistantContent::Reasoning(Reasoning::new(
+                    reasoning.clone(),
+                )));
+            }
+
             if let Some(text) = content
                 && !text.trim().is_empty()
             {
@@ -476,6 +580,10 @@ mod tests {
                 },
             ],
             temperature: Some(0.7),
+            top_p: None,
+            max_tokens: None,
+            seed: None,
+            n: Some(3),
             tools: Vec::new(),
             tool_choice: None,
         };
@@ -483,6 +591,7 @@ mod tests {
         let json = serde_json::to_string(&request).unwrap();
         assert!(json.contains("\"model\":\"gpt-4o\""));
         assert!(json.contains("\"temperature\":0.7"));
+        assert!(json.contains("\"n\":3"));
     }
 
     #[test]
@@ -493,6 +602,10 @@ mod tests {
                 content: "hi".to_string(),
             }],
             temperature: None,
+            top_p: None,
+            max_tokens: None,
+            seed: None,
+            n: None,
             tools: vec![OpenAIToolDefinition {
                 r#type: "function".to_string(),
                 function: OpenAIFunctionDefinition {
@@ -512,6 +625,54 @@ mod tests {
 
         let json = serde_json::to_value(request).unwrap();
         assert_eq!(json["tool_choice"], "auto");
+        assert!(json.get("n").is_none());
+    }
+
+    #[test]
+    fn test_refusals_parse_to_refusal_content() {
+        let choice = |message: serde_json::Value, finish_reason: &str| -> OpenAIChoice {
+            serde_json::from_value(serde_json::json!({
+                "index": 0,
+                "message": message,
+                "finish_reason": finish_reason,
+            }))
+            .unwrap()
+        };
+
+        let refused = choice(
+            serde_json::json!({
+                "role": "assistant",
+                "content": null,
+                "refusal": "I can't help with that."
+            }),
+            "stop",
+        );
+        assert_eq!(
+            parse_choice_content(&refused).unwrap(),
+            vec![AssistantContent::Refusal {
+                message: "I can't help with that.".to_string()
+            }]
+        );
+
+        let filtered = choice(
+            serde_json::json!({"role": "assistant", "content": "partial"}),
+            "content_filter",
+        );
+        assert!(matches!(
+            parse_choice_content(&filtered).unwrap().as_slice(),
+            [AssistantContent::Refusal { message }] if message.contains("content filter")
+        ));
+
+        let answered = choice(
+            serde_json::json!({"role": "assistant", "content": "Hello", "refusal": null}),
+            "stop",
+        );
+        assert_eq!(
+            parse_choice_content(&answered).unwrap(),
+            vec![AssistantContent::Text(Text {
+                text: "Hello".to_string()
+            })]
+        );
     }
 
     #[test]
@@ -5,8 +5,9 @@ use serde::{Deserialize, Serialize};
 use crate::internal::ai::{
     client::{CompletionClient, Provider},
     completion::{
-        AssistantContent, CompletionError, Function, Message, Text, ToolCall, UserContent,
-        request::{CompletionRequest, CompletionResponse},
+        AssistantContent, CompletionError, Function, Message, Reasoning, Text, ToolCall,
+        UserContent,
+        request::{CompletionRequest, CompletionResponse, Usage},
     },
     providers::zhipu::client::Client,
     tools::ToolDefinition,
@@ -45,6 +46,10 @@ struct ZhipuRequest {
     messages: Vec<ZhipuMessage>,
     #[serde(skip_serializing_if = "Option::is_none")]
     temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u64>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     tools: Vec<ZhipuToolDefinition>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -65,6 +70,9 @@ enum ZhipuMessage {
         content: Option<String>,
         #[serde(default, skip_serializing_if = "Vec::is_empty")]
         tool_calls: Vec<ZhipuToolCall>,
+        /// Chain-of-thought returned by reasoning models. Never sent back to the API.
+        #[serde(default, skip_serializing)]
+        reasoning_content: Option<String>,
     },
     Tool {
         tool_call_id: String,
@@ -204,6 +212,7 @@ impl From<&Message> for ZhipuMessage {
                 ZhipuMessage::Assistant {
                     content: if text.is_empty() { None } else { Some(text) },
                     tool_calls: Vec::new(),
+                    reasoning_content: None,
                 }
             }
             Message::System { content } => {
@@ -230,6 +239,13 @@ impl From<&Message> for ZhipuMessage {
 impl crate::internal::ai::completion::CompletionModel for Model {
     type Response = ZhipuResponse;
 
+    fn usage(&self, response: &CompletionResponse<Self::Response>) -> Option<Usage> {
+        response.raw_response.usage.as_ref().map(|usage| Usage {
+            input_tokens: usage.prompt_tokens as u64,
+            output_tokens: usage.completion_tokens as u64,
+        })
+    }
+
     async fn completion(
         &self,
         request: CompletionRequest,
@@ -242,6 +258,8 @@ impl crate::internal::ai::completion::CompletionModel for Model {
             model: self.model.clone(),
             messages,
             temperature: request.temperature,
+            top_p: request.top_p,
+            max_tokens: request.max_tokens,
             tool_choice: if tools.is_empty() {
                 None
             } else {
@@ -351,6 +369,8 @@ fn build_messages(request: &CompletionRequest) -> Result<Vec<ZhipuMessage>, Comp
                                 text_parts.push(t.text.clone());
                             }
                         }
+                        // Reasoning and refusals are output-only for this API.
+                        AssistantContent::Reasoning(_) | AssistantContent::Refusal { .. } => {}
                         AssistantContent::ToolCall(call) => {
                             tool_calls.push(ZhipuToolCall {
                                 id: call.id.clone(),
@@ -373,6 +393,7 @@ fn build_messages(request: &CompletionRequest) -> Result<Vec<ZhipuMessage>, Comp
                 messages.push(ZhipuMessage::Assistant {
                     content: text,
                     tool_calls,
+                    reasoning_content: None,
                 });
             }
             Message::System { content } => {
@@ -392,14 +413,32 @@ fn build_messages(request: &CompletionRequest) -> Result<Vec<ZhipuMessage>, Comp
     Ok(messages)
 }
 
+/// Finish reason of a response stopped by Zhipu's content moderation.
+const SENSITIVE_FINISH_REASON: &str = "sensitive";
+
 fn parse_choice_content(choice: &ZhipuChoice) -> Result<Vec<AssistantContent>, CompletionError> {
     match &choice.message {
         ZhipuMessage::Assistant {
             content,
             tool_calls,
+            reasoning_content,
         } => {
+            if choice.finish_reason.as_deref() == Some(SENSITIVE_FINISH_REASON) {
+                return Ok(vec![AssistantContent::Refusal {
+                    message: "the response was blocked by Zhipu's content filter".to_string(),
+                }]);
+            }
+
             let mut parts = Vec::new();
 
+            if let Some(reasoning) = reasoning_content
+                && !reasoning.trim().is_empty()
+            {
+                parts.push(AssistantContent::Reasoning(Reasoning::new(
+                    reasoning.clone(),
+                )));
+            }
+
             if let Some(text) = content
                 && !text.trim().is_empty()
             {
@@ -473,6 +512,8 @@ mod tests {
                 },
             ],
             temperature: Some(0.7),
+            top_p: None,
+            max_tokens: None,
             tools: Vec::new(),
             tool_choice: None,
         };
@@ -490,6 +531,8 @@ mod tests {
                 content: "hi".to_string(),
             }],
             temperature: None,
+            top_p: None,
+            max_tokens: None,
             tools: vec![ZhipuToolDefinition {
                 r#type: "function".to_string(),
                 function: ZhipuFunctionDefinition {
@@ -542,6 +585,24 @@ mod tests {
         assert_eq!(response.usage.unwrap().total_tokens, 21);
     }
 
+    #[test]
+    fn test_sensitive_finish_reason_parses_to_refusal() {
+        let json = r#"
+        {
+            "index": 0,
+            "message": {"role": "assistant", "content": ""},
+            "finish_reason": "sensitive"
+        }
+        "#;
+
+        let choice: ZhipuChoice = serde_json::from_str(json).unwrap();
+        let content = parse_choice_content(&choice).unwrap();
+        assert_eq!(
+            content[0].refusal(),
+            Some("the response was blocked by Zhipu's content filter")
+        );
+    }
+
     #[test]
     fn test_model_new() {
         let client = Client::with_api_key("test-key".to_string());
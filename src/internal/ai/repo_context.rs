@@ -0,0 +1,397 @@
+//! Baseline repository facts for AI requests.
+//!
+//! Most AI commands want the same orientation before they start: which branch they are on,
+//! what HEAD is, what is uncommitted and who has been working here. [`RepoContextMiddleware`]
+//! gathers a configurable set of [`RepoFact`]s into a [`RepoSnapshot`] and renders it as a
+//! compact block that fits a token budget:
+//!
+//! ```text
+//! ## Repository context
+//! Branch: main
+//! HEAD: 1a2b3c4 Fix parser
+//! Dirty files: (2) M src/lib.rs, A notes.md
+//! Recent authors: (last 50 commits) Alice (31), Bob (19)
+//! ```
+//!
+//! As a [`ContextMiddleware`] it is gathered once per agent run, so every step of a run sees
+//! the same snapshot while the next run starts from the current repository state.
+
+use std::collections::{BTreeMap, HashMap};
+
+use async_trait::async_trait;
+use git_internal::internal::object::commit::Commit;
+
+use crate::{
+    command::{
+        load_object,
+        log::get_reachable_commits,
+        status::{changes_to_be_committed, changes_to_be_staged},
+    },
+    internal::{
+        ai::{agent::ContextMiddleware, diff_context::estimate_tokens},
+        head::Head,
+    },
+    utils::util,
+};
+
+/// Heading of the rendered block.
+const HEADER: &str = "## Repository context";
+/// Appended to a fact cut short by the token budget.
+const TRUNCATION_MARKER: &str = " …";
+
+/// One piece of repository state [`RepoContextMiddleware`] can report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RepoFact {
+    /// The current branch, or the commit a detached HEAD points at.
+    Branch,
+    /// Abbreviated hash and subject of the HEAD commit.
+    HeadSubject,
+    /// Staged and unstaged changes to tracked files.
+    DirtyFiles,
+    /// Commit counts per author over the most recent commits.
+    RecentShortlog,
+}
+
+impl RepoFact {
+    fn label(self) -> &'static str {
+        match self {
+            RepoFact::Branch => "Branch",
+            RepoFact::HeadSubject => "HEAD",
+            RepoFact::DirtyFiles => "Dirty files",
+            RepoFact::RecentShortlog => "Recent authors",
+        }
+    }
+}
+
+/// What [`RepoContextMiddleware`] gathers and how much room the block may take.
+#[derive(Debug, Clone)]
+pub struct RepoContextConfig {
+    /// Facts to report, in rendering order.
+    pub facts: Vec<RepoFact>,
+    /// Upper bound for the rendered block, in [`estimate_tokens`] tokens.
+    pub token_budget: usize,
+    /// Dirty files listed by name; the rest are only counted.
+    pub max_dirty_files: usize,
+    /// Number of recent commits the author summary covers.
+    pub shortlog_commits: usize,
+}
+
+impl Default for RepoContextConfig {
+    fn default() -> Self {
+        Self {
+            facts: vec![
+                RepoFact::Branch,
+                RepoFact::HeadSubject,
+                RepoFact::DirtyFiles,
+                RepoFact::RecentShortlog,
+            ],
+            token_budget: 256,
+            max_dirty_files: 20,
+            shortlog_commits: 50,
+        }
+    }
+}
+
+/// Repository facts gathered at one point in time, rendered with [`RepoSnapshot::render`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RepoSnapshot {
+    /// Each fact with its rendered value, in the configured order. Facts that do not apply
+    /// (e.g. recent authors in a repository without commits) are left out.
+    pub facts: Vec<(RepoFact, String)>,
+}
+
+impl RepoSnapshot {
+    /// Gather `config.facts` from the repository in the current directory.
+    pub async fn gather(config: &RepoContextConfig) -> Self {
+        let head_commit = Head::current_commit().await;
+        let mut facts = Vec::new();
+        for &fact in &config.facts {
+            let value = match fact {
+                RepoFact::Branch => Some(match Head::current().await {
+                    Head::Branch(name) => name,
+                    Head::Detached(hash) => format!("(detached at {})", short_hash(&hash)),
+                }),
+                RepoFact::HeadSubject => Some(match head_commit {
+                    Some(hash) => match load_object::<Commit>(&hash) {
+                        Ok(commit) => format!("{} {}", short_hash(&hash), subject(&commit)),
+                        Err(e) => {
+                            tracing::debug!("repo context: cannot load HEAD {hash}: {e}");
+                            continue;
+                        }
+                    },
+                    None => "(no commits yet)".to_string(),
+                }),
+                RepoFact::DirtyFiles => Some(dirty_files(config.max_dirty_files).await),
+                RepoFact::RecentShortlog => match head_commit {
+                    Some(hash) => recent_authors(hash.to_string(), config.shortlog_commits).await,
+                    None => None,
+                },
+            };
+            if let Some(value) = value {
+                facts.push((fact, value));
+            }
+        }
+        Self { facts }
+    }
+
+    /// Render the block within `token_budget`.
+    ///
+    /// Facts are added in order while they fit; the first one that does not is cut short
+    /// and marked with `…`, and later facts are dropped. Returns an empty string when not
+    /// even the heading fits or there are no facts.
+    pub fn render(&self, token_budget: usize) -> String {
+        if self.facts.is_empty() || estimate_tokens(HEADER) > token_budget {
+            return String::new();
+        }
+        let mut block = HEADER.to_string();
+        for (fact, value) in &self.facts {
+            let line = format!("\n{}: {value}", fact.label());
+            if estimate_tokens(&block) + estimate_tokens(&line) <= token_budget {
+                block.push_str(&line);
+                continue;
+            }
+            // Each estimated token is four characters.
+            let room = token_budget
+                .saturating_sub(estimate_tokens(&block) + estimate_tokens(TRUNCATION_MARKER))
+                * 4;
+            if room > fact.label().len() + 3 {
+                block.extend(line.chars().take(room));
+                block.push_str(TRUNCATION_MARKER);
+            }
+            break;
+        }
+        block
+    }
+}
+
+/// Prepends a [`RepoSnapshot`] of the current repository to the preamble of each agent run.
+///
+/// Outside a repository it adds nothing.
+#[derive(Debug, Clone, Default)]
+pub struct RepoContextMiddleware {
+    config: RepoContextConfig,
+}
+
+impl RepoContextMiddleware {
+    pub fn new(config: RepoContextConfig) -> Self {
+        Self { config }
+    }
+
+    /// Gather and render the block now; `None` outside a repository or when nothing fits.
+    pub async fn render(&self) -> Option<String> {
+        if util::try_get_storage_path(None).is_err() {
+            return None;
+        }
+        let block = RepoSnapshot::gather(&self.config)
+            .await
+            .render(self.config.token_budget);
+        (!block.is_empty()).then_some(block)
+    }
+}
+
+#[async_trait]
+impl ContextMiddleware for RepoContextMiddleware {
+    async fn context(&self) -> Option<String> {
+        self.render().await
+    }
+}
+
+fn short_hash(hash: &impl ToString) -> String {
+    hash.to_string().chars().take(7).collect()
+}
+
+fn subject(commit: &Commit) -> &str {
+    commit.message.trim().lines().next().unwrap_or_default()
+}
+
+/// `(<n>) M a.rs, A b.rs, …` over staged and unstaged changes, or `none`.
+async fn dirty_files(limit: usize) -> String {
+    let mut files = BTreeMap::new();
+    for changes in [changes_to_be_committed().await, changes_to_be_staged()] {
+        for (status, paths) in [
+            ('A', changes.new),
+            ('M', changes.modified),
+            ('D', changes.deleted),
+        ] {
+            for path in paths {
+                files.entry(path.display().to_string()).or_insert(status);
+            }
+        }
+    }
+    if files.is_empty() {
+        return "none".to_string();
+    }
+
+    let mut listed = files
+        .iter()
+        .take(limit)
+        .map(|(path, status)| format!("{status} {path}"))
+        .collect::<Vec<_>>();
+    if files.len() > limit {
+        listed.push(format!("… and {} more", files.len() - limit));
+    }
+    format!("({}) {}", files.len(), listed.join(", "))
+}
+
+/// `(last <n> commits) Alice (3), Bob (1)` over the `limit` most recent commits reachable from `head`.
+async fn recent_authors(head: String, limit: usize) -> Option<String> {
+    if limit == 0 {
+        return None;
+    }
+    let mut commits = get_reachable_commits(head, Some(limit)).await;
+    commits.sort_by_key(|commit| std::cmp::Reverse(commit.committer.timestamp));
+    commits.truncate(limit);
+
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for commit in &commits {
+        *counts.entry(commit.author.name.as_str()).or_default() += 1;
+    }
+    let mut authors = counts.into_iter().collect::<Vec<_>>();
+    authors.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    let authors = authors
+        .iter()
+        .map(|(name, count)| format!("{name} ({count})"))
+        .collect::<Vec<_>>();
+    Some(format!(
+        "(last {} commits) {}",
+        commits.len(),
+        authors.join(", ")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use clap::Parser;
+    use serial_test::serial;
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::{
+        command::{
+            add::{self, AddArgs},
+            commit::{self, CommitArgs},
+        },
+        internal::ai::{
+            AgentBuilder, CompletionModel, Prompt,
+            completion::{
+                CompletionError, CompletionRequest, CompletionResponse,
+                message::{AssistantContent, Text},
+            },
+        },
+        utils::test,
+    };
+
+    /// Records the preamble of each request and answers `"ok"`.
+    #[derive(Clone, Default)]
+    struct PreambleModel(Arc<Mutex<Vec<Option<String>>>>);
+
+    impl CompletionModel for PreambleModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            request: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            self.0.lock().unwrap().push(request.preamble);
+            Ok(CompletionResponse {
+                content: vec![AssistantContent::Text(Text {
+                    text: "ok".to_string(),
+                })],
+                raw_response: (),
+            })
+        }
+    }
+
+    #[test]
+    fn test_render_respects_budget() {
+        let snapshot = RepoSnapshot {
+            facts: vec![
+                (RepoFact::Branch, "main".to_string()),
+                (RepoFact::HeadSubject, "1a2b3c4 Fix parser".to_string()),
+                (
+                    RepoFact::DirtyFiles,
+                    format!("(40) {}", "M src/file.rs, ".repeat(40)),
+                ),
+                (
+                    RepoFact::RecentShortlog,
+                    "(last 2 commits) Alice (2)".to_string(),
+                ),
+            ],
+        };
+
+        let full = snapshot.render(usize::MAX);
+        assert_eq!(full.lines().count(), 5);
+        assert!(full.ends_with("Recent authors: (last 2 commits) Alice (2)"));
+
+        let block = snapshot.render(40);
+        assert!(estimate_tokens(&block) <= 40, "{block}");
+        let lines = block.lines().collect::<Vec<_>>();
+        assert_eq!(
+            lines[..3],
+            [HEADER, "Branch: main", "HEAD: 1a2b3c4 Fix parser"]
+        );
+        assert!(lines[3].starts_with("Dirty files: (40) M src/file.rs"));
+        assert!(lines[3].ends_with(" …"));
+        assert_eq!(lines.len(), 4, "{block}");
+
+        assert_eq!(snapshot.render(2), "");
+        assert_eq!(RepoSnapshot::default().render(100), "");
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_repo_context_reaches_the_request() {
+        let temp_path = tempdir().unwrap();
+        test::setup_with_new_libra_in(temp_path.path()).await;
+        let _guard = test::ChangeDirGuard::new(temp_path.path());
+
+        test::ensure_file("a.txt", Some("one\n"));
+        add::execute(AddArgs::try_parse_from(["add", "a.txt"]).unwrap()).await;
+        commit::execute(CommitArgs {
+            message: Some("Add a.txt".to_string()),
+            ..Default::default()
+        })
+        .await;
+        test::ensure_file("a.txt", Some("two\n"));
+
+        let Head::Branch(branch) = Head::current().await else {
+            panic!("expected a branch");
+        };
+        let head = Head::current_commit().await.unwrap();
+
+        let model = PreambleModel::default();
+        let agent = AgentBuilder::new(model.clone())
+            .preamble("You review code.")
+            .context_middleware(RepoContextMiddleware::default())
+            .build();
+        agent.prompt("hi").await.unwrap();
+
+        let preamble = model.0.lock().unwrap()[0].clone().unwrap();
+        let (context, rest) = preamble.split_once("\n\n").unwrap();
+        assert_eq!(rest, "You review code.");
+        let lines = context.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], HEADER);
+        assert_eq!(lines[1], format!("Branch: {branch}"));
+        assert_eq!(lines[2], format!("HEAD: {} Add a.txt", short_hash(&head)));
+        assert_eq!(lines[3], "Dirty files: (1) M a.txt");
+        assert!(
+            lines[4].starts_with("Recent authors: (last 1 commits) "),
+            "{context}"
+        );
+
+        // A tight budget cuts the block short instead of overflowing it.
+        let agent = AgentBuilder::new(model.clone())
+            .context_middleware(RepoContextMiddleware::new(RepoContextConfig {
+                token_budget: 12,
+                ..Default::default()
+            }))
+            .build();
+        agent.prompt("hi").await.unwrap();
+        let preamble = model.0.lock().unwrap()[1].clone().unwrap();
+        assert!(estimate_tokens(&preamble) <= 12, "{preamble}");
+        assert!(preamble.starts_with(HEADER));
+        assert!(!preamble.contains("Recent authors"), "{preamble}");
+    }
+}
@@ -0,0 +1,94 @@
+//! Task status parsing and the transitions a [`Task`] may go through.
+//!
+//! ```text
+//! Draft ──▶ Running ──▶ Done
+//!   │          │
+//!   ├──────────┴──▶ Failed | Cancelled
+//! ```
+//!
+//! `Done`, `Failed` and `Cancelled` are final. Every status update goes through
+//! [`check_status_transition`], so tools and commands reject the same changes.
+
+use git_internal::internal::object::{
+    task::{Task, TaskStatus},
+    types::ActorRef,
+};
+use serde_json::json;
+
+/// Header tag naming the actor that last changed a task's status.
+pub const STATUS_UPDATED_BY_TAG: &str = "status_updated_by";
+
+/// Parse a status name as written by [`TaskStatus::as_str`].
+pub fn parse_task_status(status: &str) -> Result<TaskStatus, String> {
+    match status {
+        "draft" => Ok(TaskStatus::Draft),
+        "running" => Ok(TaskStatus::Running),
+        "done" => Ok(TaskStatus::Done),
+        "failed" => Ok(TaskStatus::Failed),
+        "cancelled" => Ok(TaskStatus::Cancelled),
+        _ => Err(format!(
+            "invalid task status '{status}'; expected draft, running, done, failed or cancelled"
+        )),
+    }
+}
+
+/// Check that `task` may move from its current status to `to`.
+pub fn check_status_transition(task: &Task, to: &TaskStatus) -> Result<(), String> {
+    let from = task.status();
+    let allowed = matches!(
+        (from, to),
+        (TaskStatus::Draft, TaskStatus::Running)
+            | (
+                TaskStatus::Draft | TaskStatus::Running,
+                TaskStatus::Failed | TaskStatus::Cancelled
+            )
+            | (TaskStatus::Running, TaskStatus::Done)
+    );
+    if allowed {
+        Ok(())
+    } else {
+        Err(format!(
+            "task {} cannot move from {from} to {to}",
+            task.header().object_id()
+        ))
+    }
+}
+
+/// `kind:id` of `actor`, e.g. `agent:libra-code`.
+pub fn actor_label(actor: &ActorRef) -> String {
+    format!("{}:{}", actor.kind(), actor.id())
+}
+
+/// Record `actor` as the last writer of `task`'s status.
+///
+/// `Task` exposes no mutable header, so the tag and timestamp are set on its JSON form.
+pub fn attribute_status_change(task: Task, actor: &ActorRef) -> Result<Task, serde_json::Error> {
+    let mut value = serde_json::to_value(task)?;
+    value["updated_at"] = serde_json::to_value(chrono::Utc::now())?;
+    value["tags"][STATUS_UPDATED_BY_TAG] = json!(actor_label(actor));
+    serde_json::from_value(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_transitions() {
+        let mut task = Task::new(ActorRef::agent("planner").unwrap(), "cache layer", None).unwrap();
+        assert!(check_status_transition(&task, &TaskStatus::Done).is_err());
+        assert!(check_status_transition(&task, &TaskStatus::Cancelled).is_ok());
+        assert!(check_status_transition(&task, &TaskStatus::Running).is_ok());
+
+        task.set_status(TaskStatus::Running);
+        assert!(check_status_transition(&task, &TaskStatus::Running).is_err());
+        assert!(check_status_transition(&task, &TaskStatus::Done).is_ok());
+
+        task.set_status(TaskStatus::Done);
+        let err = check_status_transition(&task, &TaskStatus::Draft).unwrap_err();
+        assert!(err.ends_with("cannot move from done to draft"), "{err}");
+
+        assert_eq!(parse_task_status("running").unwrap(), TaskStatus::Running);
+        assert!(parse_task_status("in_progress").is_err());
+    }
+}
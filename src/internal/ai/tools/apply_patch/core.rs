@@ -233,8 +233,61 @@ pub fn apply_hunks(hunks: &[Hunk], cwd: &Path) -> Result<ApplyResult, ApplyPatch
     })
 }
 
+/// Computes the per-file result of applying `hunks` without touching the filesystem.
+///
+/// Files are read but never written, so this is safe to use for dry runs. Moved files are
+/// reported under their destination path, like [`apply_hunks`] does.
+pub fn preview_hunks(hunks: &[Hunk], cwd: &Path) -> Result<Vec<FileDiff>, ApplyPatchError> {
+    if hunks.is_empty() {
+        return Err(ApplyPatchError::ComputeReplacements(
+            "No files were modified.".to_string(),
+        ));
+    }
+
+    let mut file_diffs = Vec::with_capacity(hunks.len());
+    for hunk in hunks {
+        match hunk {
+            Hunk::AddFile { path, contents } => file_diffs.push(FileDiff {
+                path: cwd.join(path),
+                old_content: String::new(),
+                new_content: contents.clone(),
+            }),
+            Hunk::DeleteFile { path } => {
+                let abs_path = cwd.join(path);
+                let old_content = std::fs::read_to_string(&abs_path).map_err(|e| {
+                    ApplyPatchError::IoError(IoError {
+                        context: format!("Failed to read file to delete {}", abs_path.display()),
+                        source: e,
+                    })
+                })?;
+                file_diffs.push(FileDiff {
+                    path: abs_path,
+                    old_content,
+                    new_content: String::new(),
+                });
+            }
+            Hunk::UpdateFile {
+                path,
+                move_path,
+                chunks,
+            } => {
+                let abs_path = cwd.join(path);
+                let AppliedPatch {
+                    original_contents,
+                    new_contents,
+                } = derive_new_contents_from_chunks(&abs_path, chunks)?;
+                file_diffs.push(FileDiff {
+                    path: move_path.as_ref().map_or(abs_path, |dest| cwd.join(dest)),
+                    old_content: original_contents,
+                    new_content: new_contents,
+                });
+            }
+        }
+    }
+    Ok(file_diffs)
+}
+
 struct AppliedPatch {
-    #[allow(dead_code)]
     original_contents: String,
     new_contents: String,
 }
@@ -373,7 +426,7 @@ fn compute_replacements(
 
 /// Apply the `(start_index, old_len, new_lines)` replacements to `original_lines`,
 /// returning the modified file contents as a vector of lines.
-fn apply_replacements(
+pub(super) fn apply_replacements(
     mut lines: Vec<String>,
     replacements: &[(usize, usize, Vec<String>)],
 ) -> Vec<String> {
@@ -6,13 +6,21 @@
 //! - Supports Add/Delete/Update/Move operations
 //! - Supports multiple files in a single patch
 //! - Uses fuzzy matching (seek_sequence) for tolerance
+//!
+//! Standard unified diffs (`--- a/file` / `+++ b/file`) are accepted as well; see [`unified`].
 
 mod core;
 mod parser;
 mod seek_sequence;
+pub mod unified;
 
 pub use core::{
-    AffectedPaths, ApplyPatchError, ApplyResult, FileDiff, apply_hunks, apply_patch, format_summary,
+    AffectedPaths, ApplyPatchError, ApplyResult, FileDiff, apply_hunks, apply_patch,
+    format_summary, preview_hunks,
 };
 
 pub use parser::{ApplyPatchArgs, Hunk, UpdateFileChunk, parse_patch};
+pub use unified::{
+    HunkOutcome, UnifiedFilePatch, UnifiedPlan, is_unified_diff, parse_unified_diff,
+    plan_unified_diff, write_planned_files,
+};
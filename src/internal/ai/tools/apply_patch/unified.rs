@@ -0,0 +1,422 @@
+//! Standard unified diff support (`--- a/file` / `+++ b/file` / `@@ -l,s +l,s @@`).
+//!
+//! Models frequently emit `git diff`-style patches instead of the Codex format, so the
+//! `apply_patch` tool accepts both. Unified diffs are applied in two steps: [`plan_unified_diff`]
+//! locates every hunk and computes the resulting file contents without writing anything, and
+//! [`write_planned_files`] commits a plan to disk. Each hunk is located with
+//! [`seek_sequence`](super::seek_sequence::seek_sequence), preferring the position given by its
+//! header, so a hunk still applies when earlier edits shifted the file.
+
+use std::path::{Path, PathBuf};
+
+use super::{
+    core::{AffectedPaths, ApplyPatchError, FileDiff, apply_replacements},
+    parser::ParseError,
+    seek_sequence::seek_sequence,
+};
+
+/// All hunks for one file of a unified diff.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnifiedFilePatch {
+    /// Path on the `---` line; `None` for `/dev/null` (file creation).
+    pub old_path: Option<PathBuf>,
+    /// Path on the `+++` line; `None` for `/dev/null` (file deletion).
+    pub new_path: Option<PathBuf>,
+    pub hunks: Vec<UnifiedHunk>,
+}
+
+impl UnifiedFilePatch {
+    /// Every path this patch reads or writes, resolved against `cwd`.
+    pub fn resolved_paths(&self, cwd: &Path) -> Vec<PathBuf> {
+        self.old_path
+            .iter()
+            .chain(self.new_path.iter())
+            .map(|p| cwd.join(p))
+            .collect()
+    }
+
+    fn display_path(&self) -> &Path {
+        self.new_path
+            .as_deref()
+            .or(self.old_path.as_deref())
+            .unwrap_or(Path::new(""))
+    }
+}
+
+/// A single `@@` hunk.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnifiedHunk {
+    /// The `@@ ... @@` line, used to identify the hunk in reports.
+    pub header: String,
+    /// 1-based first line of the hunk in the original file.
+    pub old_start: usize,
+    /// Context and removed lines, in order.
+    pub old_lines: Vec<String>,
+    /// Context and added lines, in order.
+    pub new_lines: Vec<String>,
+}
+
+/// Whether a single hunk could be located in its target file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HunkOutcome {
+    pub path: PathBuf,
+    pub header: String,
+    pub applied: bool,
+    pub error: Option<String>,
+}
+
+/// The computed result of a unified diff, not yet written to disk.
+#[derive(Debug)]
+pub struct UnifiedPlan {
+    pub affected: AffectedPaths,
+    pub file_diffs: Vec<FileDiff>,
+    pub hunks: Vec<HunkOutcome>,
+}
+
+impl UnifiedPlan {
+    /// Whether every hunk applied. Only clean plans should be written.
+    pub fn is_clean(&self) -> bool {
+        self.hunks.iter().all(|h| h.applied)
+    }
+}
+
+/// Whether `text` looks like a unified diff rather than a Codex-style patch.
+pub fn is_unified_diff(text: &str) -> bool {
+    let text = text.trim_start();
+    !text.starts_with("*** Begin Patch")
+        && (text.starts_with("--- ") || text.starts_with("diff --git "))
+}
+
+/// Parse a (possibly multi-file) unified diff.
+pub fn parse_unified_diff(text: &str) -> Result<Vec<UnifiedFilePatch>, ParseError> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut files: Vec<UnifiedFilePatch> = Vec::new();
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+        if let Some(old) = line.strip_prefix("--- ") {
+            let Some(new) = lines.get(i + 1).and_then(|l| l.strip_prefix("+++ ")) else {
+                return Err(ParseError::InvalidHunkError {
+                    message: "expected '+++' line after '---'".to_string(),
+                    line_number: i + 2,
+                });
+            };
+            files.push(UnifiedFilePatch {
+                old_path: parse_header_path(old, "a/"),
+                new_path: parse_header_path(new, "b/"),
+                hunks: Vec::new(),
+            });
+            i += 2;
+        } else if line.starts_with("@@ ") {
+            let Some(file) = files.last_mut() else {
+                return Err(ParseError::InvalidHunkError {
+                    message: "hunk before any '---'/'+++' file header".to_string(),
+                    line_number: i + 1,
+                });
+            };
+            let (hunk, consumed) = parse_hunk(&lines[i..], i + 1)?;
+            file.hunks.push(hunk);
+            i += consumed;
+        } else {
+            // `diff --git`, `index`, mode lines and any commentary around the diff.
+            i += 1;
+        }
+    }
+
+    if files.is_empty() {
+        return Err(ParseError::InvalidPatchError(
+            "no '---'/'+++' file headers found".to_string(),
+        ));
+    }
+    if let Some(file) = files.iter().find(|f| f.hunks.is_empty()) {
+        return Err(ParseError::InvalidPatchError(format!(
+            "no hunks for {}",
+            file.display_path().display()
+        )));
+    }
+    Ok(files)
+}
+
+/// Strip the `a/`/`b/` prefix and any trailing timestamp; `/dev/null` yields `None`.
+fn parse_header_path(raw: &str, prefix: &str) -> Option<PathBuf> {
+    let raw = raw.split('\t').next().unwrap_or(raw).trim();
+    if raw == "/dev/null" {
+        return None;
+    }
+    Some(PathBuf::from(raw.strip_prefix(prefix).unwrap_or(raw)))
+}
+
+/// Parse the hunk starting at `lines[0]`, returning it and the number of lines consumed.
+fn parse_hunk(lines: &[&str], line_number: usize) -> Result<(UnifiedHunk, usize), ParseError> {
+    let header = lines[0];
+    let invalid = |message: &str| ParseError::InvalidHunkError {
+        message: format!("{message}: '{header}'"),
+        line_number,
+    };
+    let ranges = header
+        .strip_prefix("@@ ")
+        .and_then(|rest| rest.split(" @@").next())
+        .ok_or_else(|| invalid("malformed hunk header"))?;
+    let (old_range, new_range) = ranges
+        .split_once(' ')
+        .ok_or_else(|| invalid("malformed hunk header"))?;
+    let (old_start, old_count) = old_range
+        .strip_prefix('-')
+        .and_then(parse_range)
+        .ok_or_else(|| invalid("malformed old range"))?;
+    let (_, new_count) = new_range
+        .strip_prefix('+')
+        .and_then(parse_range)
+        .ok_or_else(|| invalid("malformed new range"))?;
+
+    let mut old_lines = Vec::with_capacity(old_count);
+    let mut new_lines = Vec::with_capacity(new_count);
+    let mut consumed = 1;
+    while old_lines.len() < old_count || new_lines.len() < new_count {
+        let Some(line) = lines.get(consumed) else {
+            return Err(invalid("hunk ends before its line counts are satisfied"));
+        };
+        consumed += 1;
+        match line.chars().next() {
+            Some('+') => new_lines.push(line[1..].to_string()),
+            Some('-') => old_lines.push(line[1..].to_string()),
+            Some(' ') => {
+                old_lines.push(line[1..].to_string());
+                new_lines.push(line[1..].to_string());
+            }
+            // Some editors strip the single space from blank context lines.
+            None => {
+                old_lines.push(String::new());
+                new_lines.push(String::new());
+            }
+            Some('\\') => {}
+            Some(_) => return Err(invalid("unexpected line inside hunk")),
+        }
+    }
+    // A trailing "\ No newline at end of file" belongs to this hunk.
+    if lines.get(consumed).is_some_and(|l| l.starts_with('\\')) {
+        consumed += 1;
+    }
+
+    Ok((
+        UnifiedHunk {
+            header: header.to_string(),
+            old_start,
+            old_lines,
+            new_lines,
+        },
+        consumed,
+    ))
+}
+
+/// Parse `start[,count]`; the count defaults to 1.
+fn parse_range(range: &str) -> Option<(usize, usize)> {
+    match range.split_once(',') {
+        Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+        None => Some((range.parse().ok()?, 1)),
+    }
+}
+
+/// Locate every hunk and compute the resulting contents without touching the filesystem.
+///
+/// Hunks that cannot be located are reported as failed in [`UnifiedPlan::hunks`]; the
+/// remaining hunks of the same file are still attempted so the report is complete.
+/// **Caller must validate** that all paths are within the sandbox before writing the plan.
+pub fn plan_unified_diff(files: &[UnifiedFilePatch], cwd: &Path) -> UnifiedPlan {
+    let mut plan = UnifiedPlan {
+        affected: AffectedPaths {
+            added: Vec::new(),
+            modified: Vec::new(),
+            deleted: Vec::new(),
+        },
+        file_diffs: Vec::new(),
+        hunks: Vec::new(),
+    };
+
+    for file in files {
+        let target = cwd.join(file.display_path());
+        let original = match &file.old_path {
+            None if target.exists() => Err(format!("{} already exists", target.display())),
+            None => Ok(String::new()),
+            Some(old) => std::fs::read_to_string(cwd.join(old))
+                .map_err(|e| format!("failed to read {}: {e}", cwd.join(old).display())),
+        };
+        let original = match original {
+            Ok(contents) => contents,
+            Err(error) => {
+                plan.hunks.extend(file.hunks.iter().map(|h| HunkOutcome {
+                    path: target.clone(),
+                    header: h.header.clone(),
+                    applied: false,
+                    error: Some(error.clone()),
+                }));
+                continue;
+            }
+        };
+
+        let mut lines: Vec<String> = original.split('\n').map(String::from).collect();
+        if lines.last().is_some_and(String::is_empty) {
+            lines.pop();
+        }
+
+        let mut replacements = Vec::with_capacity(file.hunks.len());
+        let mut cursor = 0;
+        for hunk in &file.hunks {
+            let hint = hunk.old_start.saturating_sub(1).max(cursor);
+            let found = if hunk.old_lines.is_empty() {
+                // Pure insertion: `-l,0` means "after line l".
+                Some(hunk.old_start.min(lines.len()).max(cursor))
+            } else {
+                seek_sequence(&lines, &hunk.old_lines, hint, false)
+                    .or_else(|| seek_sequence(&lines, &hunk.old_lines, cursor, false))
+            };
+            let outcome = match found {
+                Some(start) => {
+                    replacements.push((start, hunk.old_lines.len(), hunk.new_lines.clone()));
+                    cursor = start + hunk.old_lines.len();
+                    HunkOutcome {
+                        path: target.clone(),
+                        header: hunk.header.clone(),
+                        applied: true,
+                        error: None,
+                    }
+                }
+                None => HunkOutcome {
+                    path: target.clone(),
+                    header: hunk.header.clone(),
+                    applied: false,
+                    error: Some(format!(
+                        "context not found in {}:\n{}",
+                        target.display(),
+                        hunk.old_lines.join("\n")
+                    )),
+                },
+            };
+            plan.hunks.push(outcome);
+        }
+
+        let new_content = if file.new_path.is_none() {
+            String::new()
+        } else {
+            let mut new_lines = apply_replacements(lines, &replacements);
+            if !new_lines.last().is_some_and(String::is_empty) {
+                new_lines.push(String::new());
+            }
+            new_lines.join("\n")
+        };
+
+        match (&file.old_path, &file.new_path) {
+            (None, _) => plan.affected.added.push(target.clone()),
+            (Some(_), None) => plan.affected.deleted.push(target.clone()),
+            (Some(old), Some(new)) => {
+                if old != new {
+                    plan.affected.deleted.push(cwd.join(old));
+                }
+                plan.affected.modified.push(target.clone());
+            }
+        }
+        plan.file_diffs.push(FileDiff {
+            path: target,
+            old_content: original,
+            new_content,
+        });
+    }
+
+    plan
+}
+
+/// Write a clean plan to disk, creating parent directories as needed.
+///
+/// Refuses to write anything if any hunk failed, so a unified diff applies atomically at the
+/// hunk level.
+pub fn write_planned_files(plan: &UnifiedPlan) -> Result<(), ApplyPatchError> {
+    if let Some(failed) = plan.hunks.iter().find(|h| !h.applied) {
+        return Err(ApplyPatchError::ComputeReplacements(format!(
+            "hunk {} in {} did not apply",
+            failed.header,
+            failed.path.display()
+        )));
+    }
+
+    for diff in &plan.file_diffs {
+        if plan.affected.deleted.contains(&diff.path) {
+            continue;
+        }
+        if let Some(parent) = diff.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&diff.path, &diff.new_content)?;
+    }
+    for path in &plan.affected.deleted {
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_multi_file_diff() {
+        let diff = "diff --git a/src/a.rs b/src/a.rs\n\
+                    index 1111111..2222222 100644\n\
+                    --- a/src/a.rs\n\
+                    +++ b/src/a.rs\n\
+                    @@ -1,2 +1,2 @@ fn main\n \
+                    keep\n\
+                    -old\n\
+                    +new\n\
+                    --- /dev/null\n\
+                    +++ b/new.txt\n\
+                    @@ -0,0 +1 @@\n\
+                    +hello\n";
+        assert!(is_unified_diff(diff));
+        let files = parse_unified_diff(diff).unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].new_path, Some(PathBuf::from("src/a.rs")));
+        assert_eq!(files[0].hunks[0].old_lines, vec!["keep", "old"]);
+        assert_eq!(files[0].hunks[0].new_lines, vec!["keep", "new"]);
+        assert_eq!(files[1].old_path, None);
+        assert_eq!(files[1].hunks[0].new_lines, vec!["hello"]);
+    }
+
+    #[test]
+    fn test_plan_reports_failed_hunk_and_write_refuses() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("f.txt"), "a\nb\nc\n").unwrap();
+        let diff = "--- a/f.txt\n+++ b/f.txt\n@@ -1,2 +1,2 @@\n a\n-b\n+B\n@@ -3 +3 @@\n-zzz\n+Z\n";
+        let files = parse_unified_diff(diff).unwrap();
+
+        let plan = plan_unified_diff(&files, dir.path());
+        assert!(!plan.is_clean());
+        assert!(plan.hunks[0].applied);
+        assert!(!plan.hunks[1].applied);
+        assert!(write_planned_files(&plan).is_err());
+        assert_eq!(
+            fs::read_to_string(dir.path().join("f.txt")).unwrap(),
+            "a\nb\nc\n"
+        );
+    }
+
+    #[test]
+    fn test_plan_uses_header_position_for_repeated_context() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("f.txt"), "x\ny\nx\ny\n").unwrap();
+        let diff = "--- a/f.txt\n+++ b/f.txt\n@@ -3,2 +3,2 @@\n x\n-y\n+Y\n";
+        let files = parse_unified_diff(diff).unwrap();
+
+        let plan = plan_unified_diff(&files, dir.path());
+        assert!(plan.is_clean());
+        write_planned_files(&plan).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.path().join("f.txt")).unwrap(),
+            "x\ny\nx\nY\n"
+        );
+    }
+}
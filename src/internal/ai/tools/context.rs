@@ -225,6 +225,18 @@ fn default_limit() -> usize {
     2000
 }
 
+/// Arguments for the write_file tool.
+#[derive(Clone, Deserialize, Debug)]
+pub struct WriteFileArgs {
+    /// Absolute path to the file to write.
+    pub file_path: String,
+    /// The complete new content of the file.
+    pub content: String,
+    /// Return the diff preview without writing (default: false).
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
 /// Arguments for the list_dir tool.
 #[derive(Clone, Deserialize, Debug)]
 pub struct ListDirArgs {
@@ -267,6 +279,66 @@ pub struct ShellArgs {
     pub timeout_ms: Option<u64>,
 }
 
+/// Arguments for the cargo_check tool.
+#[derive(Clone, Deserialize, Debug, Default)]
+pub struct CargoCheckArgs {
+    /// Maximum number of errors to return. Defaults to 10.
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+/// Arguments for the fetch_url tool.
+#[derive(Clone, Deserialize, Debug)]
+pub struct FetchUrlArgs {
+    /// Absolute http(s) URL to fetch. The host must be on the handler's allowlist.
+    pub url: String,
+}
+
+/// Arguments for the remember tool.
+#[derive(Clone, Deserialize, Debug)]
+pub struct RememberArgs {
+    /// Short identifier for the fact, e.g. "build command". Reusing a key replaces it.
+    pub key: String,
+    /// The fact to remember.
+    pub value: String,
+}
+
+/// Arguments for the recall tool.
+#[derive(Clone, Deserialize, Debug)]
+pub struct RecallArgs {
+    /// Keywords to look up in stored keys and values.
+    pub query: String,
+    /// Maximum number of memories to return.
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+/// Operation requested from the ai_history tool.
+#[derive(Clone, Copy, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AiHistoryOperation {
+    GetIntent,
+    ListTasks,
+    GetTask,
+    UpdateTaskStatus,
+}
+
+/// Arguments for the ai_history tool.
+#[derive(Clone, Deserialize, Debug)]
+pub struct AiHistoryArgs {
+    pub operation: AiHistoryOperation,
+    /// Intent id for `get_intent`, task id for `get_task` and `update_task_status`.
+    /// A unique prefix is enough.
+    #[serde(default)]
+    pub id: Option<String>,
+    /// Only list tasks that belong to this intent.
+    #[serde(default)]
+    pub intent_id: Option<String>,
+    /// Status filter for `list_tasks`, new status for `update_task_status`.
+    #[serde(default)]
+    pub status: Option<String>,
+}
+
 /// Arguments for the grep_files tool.
 #[derive(Clone, Deserialize, Debug)]
 pub struct GrepFilesArgs {
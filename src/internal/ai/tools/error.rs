@@ -2,6 +2,7 @@
 
 use std::path::PathBuf;
 
+use serde_json::{Value, json};
 use thiserror::Error;
 
 use super::apply_patch::ApplyPatchError;
@@ -55,6 +56,91 @@ impl From<ApplyPatchError> for ToolError {
 /// Result type for tool operations.
 pub type ToolResult<T> = Result<T, ToolError>;
 
+/// Why a [`Tool::call`](super::Tool::call) failed.
+///
+/// The variant decides how the agent loop reacts: everything except
+/// [`ToolCallError::Internal`] is reported back to the model as the tool result so it can
+/// correct itself, while internal failures abort the run.
+#[derive(Debug, Error)]
+pub enum ToolCallError {
+    /// The model passed arguments the tool cannot use.
+    #[error("Invalid arguments: {details}")]
+    InvalidArguments { details: String },
+
+    /// The call was refused, e.g. a path outside the working directory.
+    #[error("Not permitted: {reason}")]
+    NotPermitted { reason: String },
+
+    /// The tool ran and failed; `retryable` tells the model whether trying again may help.
+    #[error("Tool execution failed: {message}")]
+    Execution { message: String, retryable: bool },
+
+    /// The tool did not finish in time. Always retryable.
+    #[error("Tool timed out")]
+    Timeout,
+
+    /// A bug or environment failure the model cannot work around.
+    #[error(transparent)]
+    Internal(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl ToolCallError {
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ToolCallError::Execution { retryable, .. } => *retryable,
+            ToolCallError::Timeout => true,
+            _ => false,
+        }
+    }
+
+    /// The tool result to show the model, or `None` when the run must fail instead.
+    pub fn model_report(&self) -> Option<Value> {
+        match self {
+            ToolCallError::Internal(_) => None,
+            _ => Some(json!({
+                "error": self.to_string(),
+                "retryable": self.is_retryable(),
+            })),
+        }
+    }
+}
+
+/// Errors of tools written against the old boxed signature keep failing the run.
+impl From<Box<dyn std::error::Error + Send + Sync>> for ToolCallError {
+    fn from(err: Box<dyn std::error::Error + Send + Sync>) -> Self {
+        ToolCallError::Internal(err)
+    }
+}
+
+impl From<ToolError> for ToolCallError {
+    fn from(err: ToolError) -> Self {
+        match err {
+            ToolError::InvalidArguments(details) | ToolError::ParseError(details) => {
+                ToolCallError::InvalidArguments { details }
+            }
+            ToolError::PathNotAbsolute(_) | ToolError::PathOutsideWorkingDir(_) => {
+                ToolCallError::NotPermitted {
+                    reason: err.to_string(),
+                }
+            }
+            ToolError::ExecutionFailed(message) => ToolCallError::Execution {
+                message,
+                retryable: false,
+            },
+            ToolError::Io(ref io) => ToolCallError::Execution {
+                retryable: matches!(
+                    io.kind(),
+                    std::io::ErrorKind::Interrupted | std::io::ErrorKind::WouldBlock
+                ),
+                message: err.to_string(),
+            },
+            ToolError::ToolNotFound(_)
+            | ToolError::IncompatiblePayload(_)
+            | ToolError::Other(_) => ToolCallError::Internal(Box::new(err)),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -75,4 +161,33 @@ mod tests {
         assert!(matches!(tool_err, ToolError::Io(_)));
         assert_eq!(tool_err.to_string(), "IO error: file not found");
     }
+
+    #[test]
+    fn test_tool_call_error_from_tool_error() {
+        let err: ToolCallError = ToolError::ParseError("expected object".to_string()).into();
+        assert!(matches!(err, ToolCallError::InvalidArguments { .. }));
+
+        let err: ToolCallError = ToolError::PathOutsideWorkingDir(PathBuf::from("/etc")).into();
+        assert_eq!(
+            err.to_string(),
+            "Not permitted: Path outside working directory: /etc"
+        );
+
+        let err: ToolCallError = ToolError::Other("bug".to_string()).into();
+        assert!(err.model_report().is_none());
+    }
+
+    #[test]
+    fn test_tool_call_error_model_report() {
+        let report = ToolCallError::Timeout.model_report().unwrap();
+        assert_eq!(
+            report,
+            json!({"error": "Tool timed out", "retryable": true})
+        );
+
+        let boxed: Box<dyn std::error::Error + Send + Sync> = "disk on fire".into();
+        let err = ToolCallError::from(boxed);
+        assert_eq!(err.to_string(), "disk on fire");
+        assert!(err.model_report().is_none());
+    }
 }
@@ -0,0 +1,403 @@
+//! Handler for the ai_history tool: lets an agent look up the intent and tasks it is
+//! working on, and move a task to a new status.
+//!
+//! Reads are free; `update_task_status` changes objects other agents and users see, so
+//! [`is_mutating`](ToolHandler::is_mutating) reports it and PreToolUse hooks receive the
+//! `operation` in the tool input to decide whether to let it through.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use git_internal::internal::object::{intent::Intent, task::Task, types::ActorRef};
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+
+use super::parse_arguments;
+use crate::{
+    internal::ai::{
+        history::{HistoryManager, ResolveError},
+        mcp::server::LibraMcpServer,
+        task::{
+            STATUS_UPDATED_BY_TAG, actor_label, attribute_status_change, check_status_transition,
+            parse_task_status,
+        },
+        tools::{
+            context::{
+                AiHistoryArgs, AiHistoryOperation, ToolInvocation, ToolKind, ToolOutput,
+                ToolPayload,
+            },
+            error::{ToolError, ToolResult},
+            registry::ToolHandler,
+            spec::ToolSpec,
+        },
+    },
+    utils::{storage::Storage, storage_ext::StorageExt},
+};
+
+/// Handler that reads intents and tasks from the AI history, acting as `actor`.
+pub struct AiHistoryHandler {
+    history: Arc<HistoryManager>,
+    storage: Arc<dyn Storage + Send + Sync>,
+    actor: ActorRef,
+}
+
+impl AiHistoryHandler {
+    pub fn new(
+        history: Arc<HistoryManager>,
+        storage: Arc<dyn Storage + Send + Sync>,
+        actor: ActorRef,
+    ) -> Self {
+        Self {
+            history,
+            storage,
+            actor,
+        }
+    }
+
+    /// Share the history of `server`, or `None` when it runs without one.
+    pub fn from_mcp_server(server: &LibraMcpServer, actor: ActorRef) -> Option<Self> {
+        Some(Self::new(
+            server.intent_history_manager.clone()?,
+            server.storage.clone()?,
+            actor,
+        ))
+    }
+
+    /// Load the `kind` object whose id is `id` or starts with it.
+    async fn load<T: DeserializeOwned + Send + Sync>(
+        &self,
+        kind: &str,
+        id: &str,
+    ) -> ToolResult<(String, T)> {
+        let not_found = || ToolError::InvalidArguments(format!("no {kind} with id '{id}'"));
+        let full_id = match self.history.resolve_prefix(id).await {
+            Ok(full_id) => full_id,
+            Err(ResolveError::NotFound(_)) => return Err(not_found()),
+            Err(e) => return Err(ToolError::InvalidArguments(e.to_string())),
+        };
+        let hash = self
+            .history
+            .get_object_hash(kind, &full_id)
+            .await
+            .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?
+            .ok_or_else(not_found)?;
+        let object = self
+            .storage
+            .get_json(&hash)
+            .await
+            .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
+        Ok((full_id, object))
+    }
+
+    async fn list_tasks(&self, args: &AiHistoryArgs) -> ToolResult<Value> {
+        let status = args
+            .status
+            .as_deref()
+            .map(parse_task_status)
+            .transpose()
+            .map_err(ToolError::InvalidArguments)?;
+        let objects = self
+            .history
+            .list_objects("task")
+            .await
+            .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
+
+        let mut tasks = Vec::new();
+        for (_, hash) in objects {
+            let Ok(task) = self.storage.get_json::<Task>(&hash).await else {
+                continue;
+            };
+            if status.as_ref().is_some_and(|s| task.status() != s) {
+                continue;
+            }
+            if let Some(intent_id) = &args.intent_id
+                && !task
+                    .intent()
+                    .is_some_and(|id| id.to_string().starts_with(intent_id.as_str()))
+            {
+                continue;
+            }
+            tasks.push(task_summary(&task));
+        }
+        Ok(Value::Array(tasks))
+    }
+
+    async fn update_task_status(&self, id: &str, status: &str) -> ToolResult<Value> {
+        let status = parse_task_status(status).map_err(ToolError::InvalidArguments)?;
+        let (_, mut task) = self.load::<Task>("task", id).await?;
+        check_status_transition(&task, &status).map_err(ToolError::InvalidArguments)?;
+        task.set_status(status);
+        let task = attribute_status_change(task, &self.actor)
+            .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
+        self.storage
+            .put_tracked(&task, &self.history)
+            .await
+            .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
+        Ok(task_summary(&task))
+    }
+}
+
+fn required<'a>(value: &'a Option<String>, name: &str, operation: &str) -> ToolResult<&'a str> {
+    value
+        .as_deref()
+        .ok_or_else(|| ToolError::InvalidArguments(format!("{operation} needs '{name}'")))
+}
+
+fn intent_summary(id: &str, intent: &Intent) -> Value {
+    json!({
+        "id": id,
+        "prompt": intent.prompt(),
+        "status": intent.status().map(|s| s.as_str()),
+        "content": intent.content(),
+        "parent": intent.parent(),
+        "plan": intent.plan(),
+        "created_by": actor_label(intent.header().created_by()),
+    })
+}
+
+fn task_summary(task: &Task) -> Value {
+    json!({
+        "id": task.header().object_id(),
+        "title": task.title(),
+        "status": task.status().as_str(),
+        "intent": task.intent(),
+        "description": task.description(),
+        "constraints": task.constraints(),
+        "acceptance_criteria": task.acceptance_criteria(),
+        "dependencies": task.dependencies(),
+        "created_by": actor_label(task.header().created_by()),
+        "status_updated_by": task.header().tags().get(STATUS_UPDATED_BY_TAG),
+    })
+}
+
+fn function_arguments(payload: &ToolPayload) -> ToolResult<&str> {
+    match payload {
+        ToolPayload::Function { arguments } => Ok(arguments),
+        _ => Err(ToolError::IncompatiblePayload(
+            "ai_history handler only accepts Function payloads".to_string(),
+        )),
+    }
+}
+
+#[async_trait]
+impl ToolHandler for AiHistoryHandler {
+    fn kind(&self) -> ToolKind {
+        ToolKind::Function
+    }
+
+    async fn is_mutating(&self, invocation: &ToolInvocation) -> bool {
+        // Arguments that do not parse are rejected by `handle`; treat them as mutating.
+        match function_arguments(&invocation.payload).and_then(parse_arguments::<AiHistoryArgs>) {
+            Ok(args) => args.operation == AiHistoryOperation::UpdateTaskStatus,
+            Err(_) => true,
+        }
+    }
+
+    async fn handle(&self, invocation: ToolInvocation) -> ToolResult<ToolOutput> {
+        let args: AiHistoryArgs = parse_arguments(function_arguments(&invocation.payload)?)?;
+        let result = match args.operation {
+            AiHistoryOperation::GetIntent => {
+                let id = required(&args.id, "id", "get_intent")?;
+                let (id, intent) = self.load::<Intent>("intent", id).await?;
+                intent_summary(&id, &intent)
+            }
+            AiHistoryOperation::ListTasks => self.list_tasks(&args).await?,
+            AiHistoryOperation::GetTask => {
+                let id = required(&args.id, "id", "get_task")?;
+                let (_, task) = self.load::<Task>("task", id).await?;
+                task_summary(&task)
+            }
+            AiHistoryOperation::UpdateTaskStatus => {
+                let id = required(&args.id, "id", "update_task_status")?;
+                let status = required(&args.status, "status", "update_task_status")?;
+                self.update_task_status(id, status).await?
+            }
+        };
+        Ok(ToolOutput::success(result.to_string()))
+    }
+
+    fn schema(&self) -> ToolSpec {
+        ToolSpec::ai_history()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use git_internal::internal::object::task::TaskStatus;
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::{
+        internal::ai::{
+            agent::runtime::tool_loop::{ToolLoopConfig, run_tool_loop},
+            completion::{
+                AssistantContent, CompletionError, CompletionModel, CompletionRequest,
+                CompletionResponse, Message, UserContent,
+                message::{Function, Text, ToolCall},
+            },
+            tools::ToolRegistry,
+        },
+        utils::storage::local::LocalStorage,
+    };
+
+    /// Lists the draft tasks, marks `task_id` as running, then answers.
+    #[derive(Clone)]
+    struct PlanFollowingModel {
+        task_id: String,
+    }
+
+    impl CompletionModel for PlanFollowingModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            request: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            let tool_results = request
+                .chat_history
+                .iter()
+                .filter(|msg| match msg {
+                    Message::User { content } => content
+                        .iter()
+                        .any(|c| matches!(c, UserContent::ToolResult(_))),
+                    _ => false,
+                })
+                .count();
+            let call = |arguments: Value| {
+                AssistantContent::ToolCall(ToolCall {
+                    id: format!("call_{tool_results}"),
+                    name: "ai_history".to_string(),
+                    function: Function {
+                        name: "ai_history".to_string(),
+                        arguments,
+                    },
+                })
+            };
+            let content = match tool_results {
+                0 => call(json!({ "operation": "list_tasks", "status": "draft" })),
+                1 => call(json!({
+                    "operation": "update_task_status",
+                    "id": self.task_id,
+                    "status": "running",
+                })),
+                _ => AssistantContent::Text(Text {
+                    text: "started".to_string(),
+                }),
+            };
+            Ok(CompletionResponse {
+                content: vec![content],
+                raw_response: (),
+            })
+        }
+    }
+
+    fn invocation(arguments: Value) -> ToolInvocation {
+        ToolInvocation::new(
+            "call",
+            "ai_history",
+            ToolPayload::Function {
+                arguments: arguments.to_string(),
+            },
+            std::env::temp_dir(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_agent_lists_tasks_and_marks_one_running() {
+        let dir = TempDir::new().unwrap();
+        let repo_path = dir.path().join(".libra");
+        std::fs::create_dir(&repo_path).unwrap();
+        let storage = Arc::new(LocalStorage::new(repo_path.join("objects")));
+        let history = Arc::new(HistoryManager::new(storage.clone(), repo_path));
+
+        let planner = ActorRef::agent("planner").unwrap();
+        let intent = Intent::new(ActorRef::human("alice").unwrap(), "add caching").unwrap();
+        storage.put_tracked(&intent, &history).await.unwrap();
+        let mut task = Task::new(planner.clone(), "cache layer", None).unwrap();
+        task.set_intent(Some(intent.header().object_id()));
+        storage.put_tracked(&task, &history).await.unwrap();
+        let task_id = task.header().object_id().to_string();
+
+        let handler = Arc::new(AiHistoryHandler::new(
+            history.clone(),
+            storage.clone(),
+            ActorRef::agent("coder").unwrap(),
+        ));
+        let mut registry = ToolRegistry::with_working_dir(dir.path().to_path_buf());
+        registry.register("ai_history", handler.clone());
+
+        let answer = run_tool_loop(
+            &PlanFollowingModel {
+                task_id: task_id.clone(),
+            },
+            "start the next task",
+            &registry,
+            ToolLoopConfig::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(answer, "started");
+
+        let hash = history
+            .get_object_hash("task", &task_id)
+            .await
+            .unwrap()
+            .unwrap();
+        let stored: Task = storage.get_json(&hash).await.unwrap();
+        assert_eq!(stored.status(), &TaskStatus::Running);
+        assert_eq!(stored.header().created_by(), &planner);
+        assert_eq!(
+            stored.header().tags().get(STATUS_UPDATED_BY_TAG).unwrap(),
+            "agent:coder"
+        );
+
+        // Reads see the change; the transition rules still apply.
+        let output = handler
+            .handle(invocation(json!({
+                "operation": "list_tasks",
+                "intent_id": intent.header().object_id().to_string(),
+            })))
+            .await
+            .unwrap();
+        let tasks: Value = serde_json::from_str(output.as_text().unwrap()).unwrap();
+        assert_eq!(tasks[0]["status"], "running");
+        assert_eq!(tasks[0]["status_updated_by"], "agent:coder");
+        let err = handler
+            .handle(invocation(json!({
+                "operation": "update_task_status",
+                "id": task_id,
+                "status": "draft",
+            })))
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("cannot move from running to draft")
+        );
+
+        let intent_output = handler
+            .handle(invocation(json!({
+                "operation": "get_intent",
+                "id": intent.header().object_id().to_string(),
+            })))
+            .await
+            .unwrap();
+        assert!(
+            intent_output
+                .as_text()
+                .unwrap()
+                .contains("\"prompt\":\"add caching\"")
+        );
+
+        assert!(
+            handler
+                .is_mutating(&invocation(json!({ "operation": "update_task_status" })))
+                .await
+        );
+        assert!(
+            !handler
+                .is_mutating(&invocation(json!({ "operation": "get_task" })))
+                .await
+        );
+    }
+}
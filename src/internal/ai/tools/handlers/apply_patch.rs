@@ -1,7 +1,11 @@
-//! Handler for the apply_patch tool using Codex-style format.
+//! Handler for the apply_patch tool using Codex-style format or standard unified diffs.
+
+use std::path::PathBuf;
 
 use async_trait::async_trait;
+use serde::Deserialize;
 
+use super::{file_change_metadata, format_diff_preview};
 use crate::internal::ai::tools::{
     apply_patch::{self, ApplyPatchArgs},
     context::{ToolInvocation, ToolKind, ToolOutput, ToolPayload},
@@ -36,12 +40,16 @@ impl ToolHandler for ApplyPatchHandler {
 
         // Accept both Function-style JSON arguments and Custom/freeform input to
         // stay compatible with Codex-style tool calls.
-        let patch_text = match payload {
+        let (patch_text, dry_run) = match payload {
             ToolPayload::Function { arguments } => parse_patch_text_from_arguments(&arguments)?,
-            ToolPayload::Custom { input } => input,
+            ToolPayload::Custom { input } => (input, false),
             _ => unreachable!("matches_kind limits payload types"),
         };
 
+        if apply_patch::is_unified_diff(&patch_text) {
+            return apply_unified_diff(&patch_text, working_dir, dry_run).await;
+        }
+
         // Parse the patch first, then validate all paths BEFORE any filesystem I/O.
         let parsed = apply_patch::parse_patch(&patch_text)
             .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
@@ -52,6 +60,17 @@ impl ToolHandler for ApplyPatchHandler {
             }
         }
 
+        if dry_run {
+            let hunks = parsed.hunks.clone();
+            let file_diffs = tokio::task::spawn_blocking(move || {
+                apply_patch::preview_hunks(&hunks, &working_dir)
+            })
+            .await
+            .map_err(|e| ToolError::ExecutionFailed(e.to_string()))??;
+            return Ok(ToolOutput::success(format_diff_preview(&file_diffs))
+                .with_metadata(file_change_metadata(&file_diffs, true)));
+        }
+
         // All paths validated — safe to apply.
         let working_dir_for_task = working_dir.clone();
         let hunks = parsed.hunks.clone();
@@ -61,30 +80,10 @@ impl ToolHandler for ApplyPatchHandler {
         .await
         .map_err(|e| ToolError::ExecutionFailed(e.to_string()))??;
 
-        // Build unified diffs for TUI display (metadata — not sent to model).
-        let diffs_json: Vec<serde_json::Value> = result
-            .file_diffs
-            .iter()
-            .map(|fd| {
-                let patch = diffy::create_patch(&fd.old_content, &fd.new_content);
-                let kind = if fd.old_content.is_empty() {
-                    "add"
-                } else if fd.new_content.is_empty() {
-                    "delete"
-                } else {
-                    "update"
-                };
-                serde_json::json!({
-                    "path": fd.path.display().to_string(),
-                    "diff": patch.to_string(),
-                    "type": kind,
-                })
-            })
-            .collect();
-
+        // Unified diffs for TUI display and changed paths for hooks (metadata — not sent to model).
         let output = apply_patch::format_summary(&result.affected);
-        let metadata = serde_json::json!({ "diffs": diffs_json });
-        Ok(ToolOutput::success(output).with_metadata(metadata))
+        Ok(ToolOutput::success(output)
+            .with_metadata(file_change_metadata(&result.file_diffs, false)))
     }
 
     fn schema(&self) -> ToolSpec {
@@ -92,19 +91,101 @@ impl ToolHandler for ApplyPatchHandler {
     }
 }
 
-fn parse_patch_text_from_arguments(arguments: &str) -> Result<String, ToolError> {
+/// Apply (or preview) a standard unified diff, reporting the outcome of every hunk.
+async fn apply_unified_diff(
+    patch_text: &str,
+    working_dir: PathBuf,
+    dry_run: bool,
+) -> Result<ToolOutput, ToolError> {
+    let files = apply_patch::parse_unified_diff(patch_text)
+        .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
+    for file in &files {
+        for path in file.resolved_paths(&working_dir) {
+            validate_path(&path, &working_dir)?;
+        }
+    }
+
+    let plan = tokio::task::spawn_blocking(move || {
+        let plan = apply_patch::plan_unified_diff(&files, &working_dir);
+        if plan.is_clean() && !dry_run {
+            apply_patch::write_planned_files(&plan)?;
+        }
+        Ok::<_, apply_patch::ApplyPatchError>(plan)
+    })
+    .await
+    .map_err(|e| ToolError::ExecutionFailed(e.to_string()))??;
+
+    let mut metadata = file_change_metadata(&plan.file_diffs, dry_run);
+    metadata["hunks"] = serde_json::json!(
+        plan.hunks
+            .iter()
+            .map(|h| serde_json::json!({
+                "path": h.path.display().to_string(),
+                "header": h.header,
+                "applied": h.applied,
+                "error": h.error,
+            }))
+            .collect::<Vec<_>>()
+    );
+
+    let report = format_hunk_report(&plan.hunks);
+    if !plan.is_clean() {
+        let failed = plan.hunks.iter().filter(|h| !h.applied).count();
+        let output = format!(
+            "Patch not applied: {failed} of {} hunks failed. No files were changed.\n{report}",
+            plan.hunks.len()
+        );
+        return Ok(ToolOutput::failure(output).with_metadata(metadata));
+    }
+
+    let output = if dry_run {
+        format!("{}{report}", format_diff_preview(&plan.file_diffs))
+    } else {
+        format!("{}{report}", apply_patch::format_summary(&plan.affected))
+    };
+    Ok(ToolOutput::success(output).with_metadata(metadata))
+}
+
+fn format_hunk_report(hunks: &[apply_patch::HunkOutcome]) -> String {
+    let mut out = String::from("Hunks:\n");
+    for hunk in hunks {
+        let status = if hunk.applied { "ok" } else { "FAILED" };
+        out.push_str(&format!(
+            "{status} {} {}\n",
+            hunk.path.display(),
+            hunk.header
+        ));
+        if let Some(error) = &hunk.error {
+            out.push_str(&format!("  {error}\n"));
+        }
+    }
+    out
+}
+
+/// Extract the patch text and the `dry_run` flag from function-call arguments.
+fn parse_patch_text_from_arguments(arguments: &str) -> Result<(String, bool), ToolError> {
     // 1) Preferred: JSON object, supports aliases like `patch`.
     if let Ok(args) = serde_json::from_str::<ApplyPatchArgs>(arguments) {
-        return Ok(args.input);
+        let dry_run = serde_json::from_str::<DryRun>(arguments)
+            .map(|d| d.dry_run)
+            .unwrap_or(false);
+        return Ok((args.input, dry_run));
     }
 
     // 2) JSON string (Codex-style freeform patch encoded as JSON).
     if let Ok(s) = serde_json::from_str::<String>(arguments) {
-        return Ok(s);
+        return Ok((s, false));
     }
 
     // 3) Raw text (non-JSON) – accept for compatibility.
-    Ok(arguments.to_string())
+    Ok((arguments.to_string(), false))
+}
+
+/// The optional `dry_run` argument, parsed separately from [`ApplyPatchArgs`].
+#[derive(Deserialize)]
+struct DryRun {
+    #[serde(default)]
+    dry_run: bool,
 }
 
 #[cfg(test)]
@@ -493,4 +574,129 @@ mod tests {
         let content = fs::read_to_string(&file_path).unwrap();
         assert_eq!(content, "x\nz\n");
     }
+
+    fn unified_invocation(
+        working_dir: &std::path::Path,
+        diff: &str,
+        dry_run: bool,
+    ) -> ToolInvocation {
+        ToolInvocation::new(
+            "call-1",
+            "apply_patch",
+            ToolPayload::Function {
+                arguments: serde_json::json!({ "input": diff, "dry_run": dry_run }).to_string(),
+            },
+            working_dir.to_path_buf(),
+        )
+    }
+
+    fn metadata(output: &ToolOutput) -> &serde_json::Value {
+        match output {
+            ToolOutput::Function {
+                metadata: Some(meta),
+                ..
+            } => meta,
+            _ => panic!("expected metadata"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_apply_patch_unified_diff() {
+        let temp_dir = TempDir::new().unwrap();
+        let working_dir = temp_dir.path();
+        fs::write(working_dir.join("a.txt"), "one\ntwo\nthree\n").unwrap();
+        let diff = "--- a/a.txt\n+++ b/a.txt\n@@ -1,3 +1,3 @@\n one\n-two\n+TWO\n three\n\
+                    --- /dev/null\n+++ b/dir/new.txt\n@@ -0,0 +1 @@\n+created\n";
+
+        let output = ApplyPatchHandler
+            .handle(unified_invocation(working_dir, diff, false))
+            .await
+            .unwrap();
+
+        assert!(output.is_success());
+        assert!(output.as_text().unwrap().contains("ok"));
+        assert_eq!(
+            fs::read_to_string(working_dir.join("a.txt")).unwrap(),
+            "one\nTWO\nthree\n"
+        );
+        assert_eq!(
+            fs::read_to_string(working_dir.join("dir/new.txt")).unwrap(),
+            "created\n"
+        );
+        let changed = metadata(&output)["changed_paths"].as_array().unwrap();
+        assert_eq!(changed.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_apply_patch_dry_run_previews_without_writing() {
+        let temp_dir = TempDir::new().unwrap();
+        let working_dir = temp_dir.path();
+        fs::write(working_dir.join("a.txt"), "one\ntwo\n").unwrap();
+
+        let diff = "--- a/a.txt\n+++ b/a.txt\n@@ -1,2 +1,2 @@\n one\n-two\n+TWO\n";
+        let output = ApplyPatchHandler
+            .handle(unified_invocation(working_dir, diff, true))
+            .await
+            .unwrap();
+        let text = output.as_text().unwrap();
+        assert!(text.contains("Dry run"));
+        assert!(text.contains("-two"));
+        assert!(text.contains("+TWO"));
+        assert_eq!(metadata(&output)["dry_run"], true);
+        assert_eq!(
+            fs::read_to_string(working_dir.join("a.txt")).unwrap(),
+            "one\ntwo\n"
+        );
+
+        // Codex-style patches support dry runs too.
+        let patch = wrap_patch("*** Update File: a.txt\n@@\n one\n-two\n+2");
+        let output = ApplyPatchHandler
+            .handle(unified_invocation(working_dir, &patch, true))
+            .await
+            .unwrap();
+        assert!(output.as_text().unwrap().contains("+2"));
+        assert_eq!(
+            fs::read_to_string(working_dir.join("a.txt")).unwrap(),
+            "one\ntwo\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_apply_patch_reports_failed_hunk() {
+        let temp_dir = TempDir::new().unwrap();
+        let working_dir = temp_dir.path();
+        fs::write(working_dir.join("a.txt"), "one\ntwo\nthree\n").unwrap();
+
+        let diff = "--- a/a.txt\n+++ b/a.txt\n@@ -1,2 +1,2 @@\n one\n-two\n+TWO\n\
+                    @@ -3 +3 @@\n-missing\n+MISSING\n";
+        let output = ApplyPatchHandler
+            .handle(unified_invocation(working_dir, diff, false))
+            .await
+            .unwrap();
+
+        assert!(!output.is_success());
+        let text = output.as_text().unwrap();
+        assert!(text.contains("1 of 2 hunks failed"));
+        assert!(text.contains("ok ") && text.contains("FAILED "));
+        let hunks = metadata(&output)["hunks"].as_array().unwrap();
+        assert_eq!(hunks[0]["applied"], true);
+        assert_eq!(hunks[1]["applied"], false);
+        // A partially applicable patch leaves the file untouched.
+        assert_eq!(
+            fs::read_to_string(working_dir.join("a.txt")).unwrap(),
+            "one\ntwo\nthree\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_apply_patch_unified_diff_escape_blocked() {
+        let temp_dir = TempDir::new().unwrap();
+
+        let diff = "--- /dev/null\n+++ b/../evil.txt\n@@ -0,0 +1 @@\n+malicious\n";
+        let result = ApplyPatchHandler
+            .handle(unified_invocation(temp_dir.path(), diff, false))
+            .await;
+        assert!(matches!(result, Err(ToolError::PathOutsideWorkingDir(_))));
+        assert!(!temp_dir.path().join("../evil.txt").exists());
+    }
 }
@@ -0,0 +1,312 @@
+//! Handler for the cargo_check tool.
+//!
+//! Runs `cargo check --message-format=json` through the shell tool's executor and condenses
+//! the compiler messages into [`CargoDiagnostic`]s. Cargo interleaves its JSON messages with
+//! human-readable output (progress lines, build script output, wrappers that print their own
+//! text), so every line that is not a JSON compiler message is skipped.
+
+use std::{collections::HashSet, path::Path};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use super::{parse_arguments, shell::run_shell};
+use crate::internal::ai::tools::{
+    context::{CargoCheckArgs, ToolInvocation, ToolKind, ToolOutput, ToolPayload},
+    error::{ToolError, ToolResult},
+    registry::ToolHandler,
+    spec::ToolSpec,
+};
+
+/// The command run by [`CargoCheckHandler::default`].
+pub const CARGO_CHECK_COMMAND: &str = "cargo check --message-format=json";
+
+/// Errors returned when the call does not give a limit.
+pub const DEFAULT_ERROR_LIMIT: usize = 10;
+
+/// A full check of a large workspace takes far longer than the shell tool's default timeout.
+const CHECK_TIMEOUT_MS: u64 = 10 * 60 * 1000;
+
+/// Handler that runs `cargo check` and reports the first errors.
+pub struct CargoCheckHandler {
+    command: String,
+}
+
+impl Default for CargoCheckHandler {
+    fn default() -> Self {
+        Self::with_command(CARGO_CHECK_COMMAND)
+    }
+}
+
+impl CargoCheckHandler {
+    /// Run `command` instead of [`CARGO_CHECK_COMMAND`]; it must print cargo's JSON messages.
+    pub fn with_command(command: impl Into<String>) -> Self {
+        Self {
+            command: command.into(),
+        }
+    }
+
+    /// Run the check in `working_dir` and parse everything it reported.
+    pub async fn check(&self, working_dir: &Path) -> ToolResult<Vec<CargoDiagnostic>> {
+        let output = run_shell(&self.command, working_dir, Some(CHECK_TIMEOUT_MS)).await?;
+        if output.timed_out {
+            return Err(ToolError::ExecutionFailed(format!(
+                "'{}' timed out",
+                self.command
+            )));
+        }
+        let diagnostics = parse_diagnostics(&format!("{}\n{}", output.stdout, output.stderr));
+        // Without a single diagnostic a failure is not a compile error: cargo could not
+        // resolve the manifest, or the command itself is wrong.
+        if output.exit_code != 0 && diagnostics.is_empty() {
+            return Err(ToolError::ExecutionFailed(format!(
+                "'{}' failed with exit code {}:\n{}",
+                self.command,
+                output.exit_code,
+                output.stderr.trim_end()
+            )));
+        }
+        Ok(diagnostics)
+    }
+}
+
+#[async_trait]
+impl ToolHandler for CargoCheckHandler {
+    fn kind(&self) -> ToolKind {
+        ToolKind::Function
+    }
+
+    /// Build scripts and procedural macros run arbitrary code, and the check writes `target/`.
+    async fn is_mutating(&self, _invocation: &ToolInvocation) -> bool {
+        true
+    }
+
+    async fn handle(&self, invocation: ToolInvocation) -> ToolResult<ToolOutput> {
+        let ToolInvocation {
+            payload,
+            working_dir,
+            ..
+        } = invocation;
+
+        let arguments = match payload {
+            ToolPayload::Function { arguments } => arguments,
+            _ => {
+                return Err(ToolError::IncompatiblePayload(
+                    "cargo_check handler only accepts Function payloads".to_string(),
+                ));
+            }
+        };
+        let args: CargoCheckArgs = parse_arguments(&arguments)?;
+        let limit = args.limit.unwrap_or(DEFAULT_ERROR_LIMIT);
+
+        let diagnostics = self.check(&working_dir).await?;
+        let total_errors = diagnostics.iter().filter(|d| d.is_error()).count();
+        let report = serde_json::json!({
+            "errors": top_errors(&diagnostics, limit),
+            "total_errors": total_errors,
+            "warnings": diagnostics.len() - total_errors,
+        });
+        Ok(ToolOutput::success(report.to_string()))
+    }
+
+    fn schema(&self) -> ToolSpec {
+        ToolSpec::cargo_check()
+    }
+}
+
+/// One compiler message, reduced to its primary span.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CargoDiagnostic {
+    /// `error`, `warning`, or `error: internal compiler error`.
+    pub level: String,
+    /// The error code, like `E0308`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    pub message: String,
+    /// Path relative to the package root, as cargo reports it.
+    pub file: String,
+    pub line_start: usize,
+    pub line_end: usize,
+    pub column_start: usize,
+    pub column_end: usize,
+    /// Replacement text the compiler suggests for the first span that has one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suggestion: Option<String>,
+}
+
+impl CargoDiagnostic {
+    pub fn is_error(&self) -> bool {
+        self.level.starts_with("error")
+    }
+}
+
+/// Parse the compiler messages in `output`, in the order cargo emitted them.
+///
+/// Lines that are not JSON, JSON messages other than `compiler-message`, and messages without
+/// a primary span (such as "aborting due to 2 previous errors") are skipped. A message repeated
+/// for several targets of the same package is kept once.
+pub fn parse_diagnostics(output: &str) -> Vec<CargoDiagnostic> {
+    let mut seen = HashSet::new();
+    output
+        .lines()
+        .filter(|line| line.trim_start().starts_with('{'))
+        .filter_map(|line| serde_json::from_str::<CargoMessage>(line).ok())
+        .filter(|message| message.reason == "compiler-message")
+        .filter_map(|message| message.message.and_then(RawDiagnostic::into_diagnostic))
+        .filter(|diagnostic| seen.insert(diagnostic.clone()))
+        .collect()
+}
+
+/// The first `limit` errors among `diagnostics`.
+pub fn top_errors(diagnostics: &[CargoDiagnostic], limit: usize) -> Vec<&CargoDiagnostic> {
+    diagnostics
+        .iter()
+        .filter(|d| d.is_error())
+        .take(limit)
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct CargoMessage {
+    reason: String,
+    #[serde(default)]
+    message: Option<RawDiagnostic>,
+}
+
+#[derive(Deserialize)]
+struct RawDiagnostic {
+    message: String,
+    level: String,
+    #[serde(default)]
+    code: Option<RawCode>,
+    #[serde(default)]
+    spans: Vec<RawSpan>,
+    #[serde(default)]
+    children: Vec<RawDiagnostic>,
+}
+
+#[derive(Deserialize)]
+struct RawCode {
+    code: String,
+}
+
+#[derive(Deserialize)]
+struct RawSpan {
+    file_name: String,
+    line_start: usize,
+    line_end: usize,
+    column_start: usize,
+    column_end: usize,
+    is_primary: bool,
+    #[serde(default)]
+    suggested_replacement: Option<String>,
+}
+
+impl RawDiagnostic {
+    fn into_diagnostic(self) -> Option<CargoDiagnostic> {
+        let suggestion = self.suggestion();
+        let span = self.spans.into_iter().find(|span| span.is_primary)?;
+        Some(CargoDiagnostic {
+            level: self.level,
+            code: self.code.map(|code| code.code),
+            message: self.message,
+            file: span.file_name,
+            line_start: span.line_start,
+            line_end: span.line_end,
+            column_start: span.column_start,
+            column_end: span.column_end,
+            suggestion,
+        })
+    }
+
+    fn suggestion(&self) -> Option<String> {
+        self.spans
+            .iter()
+            .find_map(|span| span.suggested_replacement.clone())
+            .or_else(|| self.children.iter().find_map(RawDiagnostic::suggestion))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    /// Captured from `cargo check --message-format=json` on a crate with two errors and a
+    /// warning, interleaved with the human-readable lines a wrapper script printed.
+    const FIXTURE: &str = include_str!("../../../../../tests/data/ai/cargo_check.jsonl");
+
+    #[test]
+    fn test_parse_diagnostics_from_mixed_output() {
+        let diagnostics = parse_diagnostics(FIXTURE);
+        assert_eq!(diagnostics.len(), 3, "{diagnostics:#?}");
+
+        let mismatch = &diagnostics[0];
+        assert_eq!(mismatch.level, "error");
+        assert_eq!(mismatch.code.as_deref(), Some("E0308"));
+        assert_eq!(mismatch.message, "mismatched types");
+        assert_eq!(mismatch.file, "src/main.rs");
+        assert_eq!(
+            (
+                mismatch.line_start,
+                mismatch.column_start,
+                mismatch.column_end
+            ),
+            (2, 18, 25)
+        );
+        assert_eq!(
+            mismatch.suggestion.as_deref(),
+            Some("count().try_into().unwrap()")
+        );
+
+        let unresolved = &diagnostics[1];
+        assert_eq!(unresolved.code.as_deref(), Some("E0425"));
+        assert_eq!(unresolved.suggestion, None);
+        assert_eq!(diagnostics[2].level, "warning");
+
+        let errors = top_errors(&diagnostics, 1);
+        assert_eq!(errors, [mismatch]);
+        assert_eq!(top_errors(&diagnostics, 10).len(), 2);
+    }
+
+    #[test]
+    fn test_parse_diagnostics_skips_noise() {
+        let output = "   Compiling demo v0.1.0\n\
+                      {not json\n\
+                      {\"reason\":\"build-finished\",\"success\":false}\n\
+                      {\"reason\":\"compiler-message\",\"message\":{\"message\":\"aborting due to 2 previous errors\",\"level\":\"error\",\"code\":null,\"spans\":[],\"children\":[]}}\n";
+        assert!(parse_diagnostics(output).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_handler_reports_top_errors() {
+        let temp = TempDir::new().unwrap();
+        std::fs::write(temp.path().join("check.jsonl"), FIXTURE).unwrap();
+        let handler = CargoCheckHandler::with_command("cat check.jsonl; exit 101");
+        let invocation = ToolInvocation::new(
+            "call-1",
+            "cargo_check",
+            ToolPayload::Function {
+                arguments: r#"{"limit": 1}"#.to_string(),
+            },
+            temp.path().to_path_buf(),
+        );
+
+        let output = handler.handle(invocation).await.unwrap();
+        let report: serde_json::Value = serde_json::from_str(output.as_text().unwrap()).unwrap();
+        assert_eq!(report["total_errors"], 2);
+        assert_eq!(report["warnings"], 1);
+        assert_eq!(report["errors"].as_array().unwrap().len(), 1);
+        assert_eq!(report["errors"][0]["code"], "E0308");
+
+        let failing = CargoCheckHandler::with_command(
+            "echo 'error: could not find Cargo.toml' >&2; exit 101",
+        );
+        let err = failing.check(temp.path()).await.unwrap_err();
+        assert!(
+            err.to_string().contains("could not find Cargo.toml"),
+            "{err}"
+        );
+    }
+}
@@ -0,0 +1,633 @@
+//! Handler for the fetch_url tool.
+//!
+//! Fetches a single web page for research-style agents. Every request, including each
+//! redirect hop, must target a host on an explicit allowlist, which limits where an agent
+//! can send data and which pages can inject content into its context. Responses are capped
+//! in size and time; HTML is converted to readable text while JSON and plain text pass
+//! through unchanged.
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::{Url, header};
+
+use super::parse_arguments;
+use crate::internal::ai::tools::{
+    context::{FetchUrlArgs, ToolInvocation, ToolKind, ToolOutput, ToolPayload},
+    error::{ToolError, ToolResult},
+    registry::ToolHandler,
+    spec::ToolSpec,
+};
+
+/// Default cap on the response body: 512 KiB.
+const DEFAULT_MAX_BYTES: usize = 512 * 1024;
+/// Default deadline for the whole fetch, redirects included.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(15);
+/// Default number of redirects followed before giving up.
+const DEFAULT_MAX_REDIRECTS: usize = 5;
+
+/// Handler for fetching allowlisted web pages. Create it with [`FetchUrlHandler::builder`].
+pub struct FetchUrlHandler {
+    allowed_domains: Vec<String>,
+    max_bytes: usize,
+    timeout: Duration,
+    max_redirects: usize,
+    client: reqwest::Client,
+}
+
+/// Builder for [`FetchUrlHandler`]. At least one allowed domain is required.
+#[derive(Debug, Clone)]
+pub struct FetchUrlHandlerBuilder {
+    allowed_domains: Vec<String>,
+    max_bytes: usize,
+    timeout: Duration,
+    max_redirects: usize,
+}
+
+impl FetchUrlHandlerBuilder {
+    /// Allow requests to `domain` and its subdomains (e.g. `rust-lang.org` also allows
+    /// `doc.rust-lang.org`).
+    pub fn allow_domain(mut self, domain: impl Into<String>) -> Self {
+        let domain = domain.into().trim().trim_matches('.').to_ascii_lowercase();
+        if !domain.is_empty() {
+            self.allowed_domains.push(domain);
+        }
+        self
+    }
+
+    /// Allow every domain in `domains`; see [`allow_domain`](Self::allow_domain).
+    pub fn allow_domains<I, S>(self, domains: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        domains.into_iter().fold(self, Self::allow_domain)
+    }
+
+    /// Sets the maximum number of body bytes kept; longer responses are truncated.
+    pub fn max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Sets the deadline for the whole fetch, redirects included.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets how many redirects are followed.
+    pub fn max_redirects(mut self, max_redirects: usize) -> Self {
+        self.max_redirects = max_redirects;
+        self
+    }
+
+    /// Builds the handler. Fails if no domain was allowed.
+    pub fn build(self) -> ToolResult<FetchUrlHandler> {
+        if self.allowed_domains.is_empty() {
+            return Err(ToolError::InvalidArguments(
+                "fetch_url requires at least one allowed domain".to_string(),
+            ));
+        }
+        // Redirects are followed by hand so every hop is checked against the allowlist.
+        let client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .timeout(self.timeout)
+            .build()
+            .map_err(|e| ToolError::Other(e.to_string()))?;
+        Ok(FetchUrlHandler {
+            allowed_domains: self.allowed_domains,
+            max_bytes: self.max_bytes,
+            timeout: self.timeout,
+            max_redirects: self.max_redirects,
+            client,
+        })
+    }
+}
+
+/// A fetched response, before rendering for the model.
+struct Fetched {
+    url: Url,
+    status: u16,
+    content_type: String,
+    body: Vec<u8>,
+    truncated: bool,
+}
+
+impl FetchUrlHandler {
+    /// Starts a builder with the default size cap, timeout and redirect limit.
+    pub fn builder() -> FetchUrlHandlerBuilder {
+        FetchUrlHandlerBuilder {
+            allowed_domains: Vec::new(),
+            max_bytes: DEFAULT_MAX_BYTES,
+            timeout: DEFAULT_TIMEOUT,
+            max_redirects: DEFAULT_MAX_REDIRECTS,
+        }
+    }
+
+    /// Whether `url` uses http(s) and targets an allowed host.
+    pub fn is_allowed(&self, url: &Url) -> bool {
+        if !matches!(url.scheme(), "http" | "https") {
+            return false;
+        }
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        self.allowed_domains
+            .iter()
+            .any(|d| host == *d || host.ends_with(&format!(".{d}")))
+    }
+
+    async fn fetch(&self, mut url: Url) -> ToolResult<Fetched> {
+        let mut redirects = 0;
+        let mut response = loop {
+            let response =
+                self.client.get(url.clone()).send().await.map_err(|e| {
+                    ToolError::ExecutionFailed(format!("request to {url} failed: {e}"))
+                })?;
+            if !response.status().is_redirection() {
+                break response;
+            }
+            let Some(location) = response
+                .headers()
+                .get(header::LOCATION)
+                .and_then(|v| v.to_str().ok())
+            else {
+                break response;
+            };
+            let next = url.join(location).map_err(|e| {
+                ToolError::ExecutionFailed(format!("invalid redirect location '{location}': {e}"))
+            })?;
+            if !self.is_allowed(&next) {
+                return Err(ToolError::ExecutionFailed(format!(
+                    "redirect from {url} to {next} blocked: host is not on the allowlist"
+                )));
+            }
+            redirects += 1;
+            if redirects > self.max_redirects {
+                return Err(ToolError::ExecutionFailed(format!(
+                    "too many redirects (limit {})",
+                    self.max_redirects
+                )));
+            }
+            url = next;
+        };
+
+        let status = response.status().as_u16();
+        let content_type = response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+
+        let mut body = Vec::new();
+        let mut truncated = false;
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|e| ToolError::ExecutionFailed(format!("reading {url} failed: {e}")))?
+        {
+            let room = self.max_bytes - body.len();
+            if chunk.len() > room {
+                body.extend_from_slice(&chunk[..room]);
+                truncated = true;
+                break;
+            }
+            body.extend_from_slice(&chunk);
+        }
+
+        Ok(Fetched {
+            url,
+            status,
+            content_type,
+            body,
+            truncated,
+        })
+    }
+}
+
+#[async_trait]
+impl ToolHandler for FetchUrlHandler {
+    fn kind(&self) -> ToolKind {
+        ToolKind::Function
+    }
+
+    async fn handle(&self, invocation: ToolInvocation) -> ToolResult<ToolOutput> {
+        let arguments = match invocation.payload {
+            ToolPayload::Function { arguments } => arguments,
+            _ => {
+                return Err(ToolError::IncompatiblePayload(
+                    "fetch_url handler only accepts Function payloads".to_string(),
+                ));
+            }
+        };
+        let args: FetchUrlArgs = parse_arguments(&arguments)?;
+
+        let url = Url::parse(&args.url)
+            .map_err(|e| ToolError::InvalidArguments(format!("invalid url '{}': {e}", args.url)))?;
+        if !self.is_allowed(&url) {
+            return Err(ToolError::InvalidArguments(format!(
+                "{url} is not on the fetch allowlist ({})",
+                self.allowed_domains.join(", ")
+            )));
+        }
+
+        let fetched = tokio::time::timeout(self.timeout, self.fetch(url))
+            .await
+            .map_err(|_| {
+                ToolError::ExecutionFailed(format!(
+                    "fetch timed out after {} ms",
+                    self.timeout.as_millis()
+                ))
+            })??;
+
+        let mime = fetched
+            .content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim();
+        let raw = String::from_utf8_lossy(&fetched.body);
+        let text = match mime {
+            "text/html" | "application/xhtml+xml" => html_to_text(&raw),
+            "" | "application/json" | "application/xml" => raw.into_owned(),
+            m if m.starts_with("text/") || m.ends_with("+json") => raw.into_owned(),
+            other => {
+                return Ok(ToolOutput::failure(format!(
+                    "URL: {}\nStatus: {}\nUnsupported content type: {other}",
+                    fetched.url, fetched.status
+                )));
+            }
+        };
+
+        let content = format!(
+            "URL: {}\nStatus: {}\nTruncated: {}\n\n{}",
+            fetched.url, fetched.status, fetched.truncated, text
+        );
+        let metadata = serde_json::json!({
+            "url": fetched.url.as_str(),
+            "status": fetched.status,
+            "content_type": fetched.content_type,
+            "truncated": fetched.truncated,
+        });
+        let output = if (200..300).contains(&fetched.status) {
+            ToolOutput::success(content)
+        } else {
+            ToolOutput::failure(content)
+        };
+        Ok(output.with_metadata(metadata))
+    }
+
+    fn schema(&self) -> ToolSpec {
+        ToolSpec::fetch_url()
+    }
+}
+
+/// Convert HTML to readable text.
+///
+/// Scripts, styles and comments are dropped. Headings become Markdown `#` lines, `<pre>`
+/// blocks become fenced code blocks with whitespace preserved, inline `<code>` is wrapped
+/// in backticks, and block elements start new lines.
+pub fn html_to_text(html: &str) -> String {
+    let mut out = String::with_capacity(html.len() / 2);
+    let mut rest = html;
+    let mut in_pre = false;
+
+    while let Some(lt) = rest.find('<') {
+        push_text(&mut out, &rest[..lt], in_pre);
+        rest = &rest[lt..];
+
+        if let Some(after) = rest.strip_prefix("<!--") {
+            rest = after.find("-->").map_or("", |end| &after[end + 3..]);
+            continue;
+        }
+        let Some(gt) = rest.find('>') else {
+            push_text(&mut out, rest, in_pre);
+            rest = "";
+            break;
+        };
+        let tag = &rest[1..gt];
+        rest = &rest[gt + 1..];
+
+        let closing = tag.starts_with('/');
+        let name = tag
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+
+        match name.as_str() {
+            "script" | "style" | "noscript" | "template" if !closing => {
+                let close = format!("</{name}");
+                rest = find_ascii_case_insensitive(rest, &close)
+                    .and_then(|i| rest[i..].find('>').map(|j| &rest[i + j + 1..]))
+                    .unwrap_or("");
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                start_block(&mut out);
+                if !closing {
+                    let level = name[1..].parse().unwrap_or(1);
+                    out.push_str(&"#".repeat(level));
+                    out.push(' ');
+                }
+            }
+            "pre" if !closing => {
+                start_block(&mut out);
+                out.push_str("```\n");
+                in_pre = true;
+            }
+            "pre" => {
+                start_line(&mut out);
+                out.push_str("```\n");
+                in_pre = false;
+            }
+            "code" if !in_pre => out.push('`'),
+            "br" => out.push('\n'),
+            "li" if !closing => {
+                start_line(&mut out);
+                out.push_str("- ");
+            }
+            "p" | "div" | "section" | "article" | "header" | "footer" | "nav" | "main"
+            | "aside" | "blockquote" | "ul" | "ol" | "table" | "dl" | "hr" => start_block(&mut out),
+            "tr" | "dt" | "dd" | "li" | "title" => start_line(&mut out),
+            _ => {}
+        }
+    }
+    push_text(&mut out, rest, in_pre);
+
+    // Tidy up: strip trailing spaces and collapse runs of blank lines.
+    let mut tidy = String::with_capacity(out.len());
+    let mut blank_run = 0;
+    for line in out.lines() {
+        let line = line.trim_end();
+        if line.is_empty() {
+            blank_run += 1;
+            if blank_run > 1 {
+                continue;
+            }
+        } else {
+            blank_run = 0;
+        }
+        tidy.push_str(line);
+        tidy.push('\n');
+    }
+    tidy.trim().to_string()
+}
+
+fn push_text(out: &mut String, text: &str, in_pre: bool) {
+    let text = decode_entities(text);
+    if in_pre {
+        out.push_str(&text);
+        return;
+    }
+    // Collapse whitespace runs to one space, never doubling up or starting a line with one.
+    let mut pending_space = false;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            pending_space = true;
+            continue;
+        }
+        if pending_space && !out.is_empty() && !out.ends_with(char::is_whitespace) {
+            out.push(' ');
+        }
+        pending_space = false;
+        out.push(c);
+    }
+    if pending_space && !out.is_empty() && !out.ends_with(char::is_whitespace) {
+        out.push(' ');
+    }
+}
+
+fn start_line(out: &mut String) {
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+}
+
+fn start_block(out: &mut String) {
+    start_line(out);
+    if !out.is_empty() && !out.ends_with("\n\n") {
+        out.push('\n');
+    }
+}
+
+fn find_ascii_case_insensitive(haystack: &str, needle: &str) -> Option<usize> {
+    haystack
+        .as_bytes()
+        .windows(needle.len())
+        .position(|w| w.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
+fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let decoded = rest.find(';').filter(|&end| end <= 10).and_then(|end| {
+            let entity = &rest[1..end];
+            let ch = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" | "#39" => Some('\''),
+                "nbsp" => Some(' '),
+                _ => entity
+                    .strip_prefix("#x")
+                    .or_else(|| entity.strip_prefix("#X"))
+                    .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                    .or_else(|| entity.strip_prefix('#').and_then(|d| d.parse().ok()))
+                    .and_then(char::from_u32),
+            };
+            ch.map(|c| (c, end))
+        });
+        match decoded {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, path::PathBuf};
+
+    use axum::{
+        Json, Router,
+        http::header::CONTENT_TYPE,
+        response::{Html, IntoResponse, Redirect},
+        routing::get,
+    };
+
+    use super::*;
+
+    const PAGE: &str = r#"<!DOCTYPE html>
+<html><head><title>Docs</title><style>body { color: red; }</style>
+<script>alert("injected");</script></head>
+<body>
+<h1>Getting &amp; Started</h1>
+<p>Install the <code>libra</code> binary.</p>
+<!-- hidden comment -->
+<h2>Example</h2>
+<pre>fn main() {
+    println!("hi");
+}</pre>
+<ul><li>first</li><li>second</li></ul>
+</body></html>"#;
+
+    /// Serve fixture routes on an ephemeral localhost port.
+    async fn fixture_server() -> SocketAddr {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = Router::new()
+            .route("/page.html", get(|| async { Html(PAGE) }))
+            .route(
+                "/data.json",
+                get(|| async { Json(serde_json::json!({ "name": "libra" })) }),
+            )
+            .route(
+                "/big.txt",
+                get(|| async { ([(CONTENT_TYPE, "text/plain")], "x".repeat(10_000)) }),
+            )
+            .route(
+                "/moved",
+                get(|| async { Redirect::temporary("/page.html") }),
+            )
+            .route(
+                "/escape",
+                get(move || async move {
+                    // `localhost` resolves to the same server but is not allowlisted.
+                    Redirect::temporary(&format!("http://localhost:{}/page.html", addr.port()))
+                        .into_response()
+                }),
+            );
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        addr
+    }
+
+    fn handler() -> FetchUrlHandler {
+        FetchUrlHandler::builder()
+            .allow_domain("127.0.0.1")
+            .max_bytes(1024)
+            .timeout(Duration::from_secs(5))
+            .build()
+            .unwrap()
+    }
+
+    fn invocation(url: String) -> ToolInvocation {
+        ToolInvocation::new(
+            "call-1",
+            "fetch_url",
+            ToolPayload::Function {
+                arguments: serde_json::json!({ "url": url }).to_string(),
+            },
+            PathBuf::from("/tmp"),
+        )
+    }
+
+    fn metadata(output: &ToolOutput) -> &serde_json::Value {
+        match output {
+            ToolOutput::Function {
+                metadata: Some(meta),
+                ..
+            } => meta,
+            _ => panic!("expected metadata"),
+        }
+    }
+
+    #[test]
+    fn test_builder_requires_allowlist() {
+        assert!(FetchUrlHandler::builder().build().is_err());
+        let handler = FetchUrlHandler::builder()
+            .allow_domains(["rust-lang.org"])
+            .build()
+            .unwrap();
+        assert!(handler.is_allowed(&Url::parse("https://doc.rust-lang.org/std").unwrap()));
+        assert!(!handler.is_allowed(&Url::parse("https://evil-rust-lang.org").unwrap()));
+        assert!(!handler.is_allowed(&Url::parse("file:///etc/passwd").unwrap()));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_rejects_host_outside_allowlist() {
+        let addr = fixture_server().await;
+        let result = handler()
+            .handle(invocation(format!(
+                "http://localhost:{}/page.html",
+                addr.port()
+            )))
+            .await;
+        assert!(matches!(result, Err(ToolError::InvalidArguments(_))));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_extracts_html_text() {
+        let addr = fixture_server().await;
+        let output = handler()
+            .handle(invocation(format!("http://{addr}/moved")))
+            .await
+            .unwrap();
+
+        assert!(output.is_success());
+        let text = output.as_text().unwrap();
+        assert!(text.starts_with(&format!("URL: http://{addr}/page.html\nStatus: 200\n")));
+        assert!(text.contains("# Getting & Started"));
+        assert!(text.contains("Install the `libra` binary."));
+        assert!(text.contains("## Example"));
+        assert!(text.contains("```\nfn main() {\n    println!(\"hi\");\n}\n```"));
+        assert!(text.contains("- first\n- second"));
+        assert!(!text.contains("injected"));
+        assert!(!text.contains("color: red"));
+        assert!(!text.contains("hidden comment"));
+        assert_eq!(metadata(&output)["truncated"], false);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_passes_json_through() {
+        let addr = fixture_server().await;
+        let output = handler()
+            .handle(invocation(format!("http://{addr}/data.json")))
+            .await
+            .unwrap();
+        assert!(output.as_text().unwrap().ends_with(r#"{"name":"libra"}"#));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_blocks_redirect_outside_allowlist() {
+        let addr = fixture_server().await;
+        let result = handler()
+            .handle(invocation(format!("http://{addr}/escape")))
+            .await;
+        match result {
+            Err(ToolError::ExecutionFailed(msg)) => assert!(msg.contains("blocked"), "{msg}"),
+            other => panic!("expected blocked redirect, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_truncates_large_body() {
+        let addr = fixture_server().await;
+        let output = handler()
+            .handle(invocation(format!("http://{addr}/big.txt")))
+            .await
+            .unwrap();
+        let text = output.as_text().unwrap();
+        assert!(text.contains("Truncated: true"));
+        assert_eq!(text.split_once("\n\n").unwrap().1, "x".repeat(1024));
+        assert_eq!(metadata(&output)["truncated"], true);
+    }
+}
@@ -0,0 +1,58 @@
+//! Handler for the `finish_task` tool, the completion signal of `libra ai work`.
+
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+
+use super::parse_arguments;
+use crate::internal::ai::{
+    tools::{
+        ToolResult,
+        context::{ToolInvocation, ToolKind, ToolOutput, ToolPayload},
+        error::ToolError,
+        registry::ToolHandler,
+        spec::ToolSpec,
+    },
+    work::TaskCompletion,
+};
+
+/// Keeps the [`TaskCompletion`] the model reported; a later call replaces an earlier one.
+#[derive(Default)]
+pub struct FinishTaskHandler {
+    completion: Arc<Mutex<Option<TaskCompletion>>>,
+}
+
+impl FinishTaskHandler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The slot the reported completion is written to.
+    pub fn completion(&self) -> Arc<Mutex<Option<TaskCompletion>>> {
+        self.completion.clone()
+    }
+}
+
+#[async_trait]
+impl ToolHandler for FinishTaskHandler {
+    fn kind(&self) -> ToolKind {
+        ToolKind::Function
+    }
+
+    async fn handle(&self, invocation: ToolInvocation) -> ToolResult<ToolOutput> {
+        let ToolPayload::Function { arguments } = &invocation.payload else {
+            return Err(ToolError::IncompatiblePayload(
+                "finish_task requires Function payload".into(),
+            ));
+        };
+        let completion: TaskCompletion = parse_arguments(arguments)?;
+        *self.completion.lock().unwrap_or_else(|e| e.into_inner()) = Some(completion);
+        Ok(ToolOutput::success(
+            "Recorded. Reply with a one-line summary and make no further tool calls.",
+        ))
+    }
+
+    fn schema(&self) -> ToolSpec {
+        ToolSpec::finish_task()
+    }
+}
@@ -0,0 +1,186 @@
+//! Handlers for the remember and recall tools.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use super::parse_arguments;
+use crate::internal::ai::{
+    memory::MemoryStore,
+    tools::{
+        context::{RecallArgs, RememberArgs, ToolInvocation, ToolKind, ToolOutput, ToolPayload},
+        error::ToolError,
+        registry::ToolHandler,
+        spec::ToolSpec,
+    },
+};
+
+/// Number of memories recall returns when the model does not pass a limit.
+const DEFAULT_RECALL_LIMIT: usize = 5;
+
+/// Handler that saves a fact to the [`MemoryStore`].
+pub struct RememberHandler {
+    store: Arc<MemoryStore>,
+}
+
+impl RememberHandler {
+    pub fn new(store: Arc<MemoryStore>) -> Self {
+        Self { store }
+    }
+}
+
+/// Handler that looks up facts in the [`MemoryStore`].
+pub struct RecallHandler {
+    store: Arc<MemoryStore>,
+}
+
+impl RecallHandler {
+    pub fn new(store: Arc<MemoryStore>) -> Self {
+        Self { store }
+    }
+}
+
+fn function_arguments(payload: ToolPayload, tool: &str) -> Result<String, ToolError> {
+    match payload {
+        ToolPayload::Function { arguments } => Ok(arguments),
+        _ => Err(ToolError::IncompatiblePayload(format!(
+            "{tool} handler only accepts Function payloads"
+        ))),
+    }
+}
+
+#[async_trait]
+impl ToolHandler for RememberHandler {
+    fn kind(&self) -> ToolKind {
+        ToolKind::Function
+    }
+
+    async fn handle(&self, invocation: ToolInvocation) -> Result<ToolOutput, ToolError> {
+        let arguments = function_arguments(invocation.payload, "remember")?;
+        let args: RememberArgs = parse_arguments(&arguments)?;
+
+        let store = Arc::clone(&self.store);
+        let key = args.key.clone();
+        tokio::task::spawn_blocking(move || store.remember(&args.key, &args.value))
+            .await
+            .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::InvalidInput => ToolError::InvalidArguments(e.to_string()),
+                _ => ToolError::Io(e),
+            })?;
+
+        Ok(ToolOutput::success(format!(
+            "Remembered \"{}\"",
+            key.trim()
+        )))
+    }
+
+    fn schema(&self) -> ToolSpec {
+        ToolSpec::remember()
+    }
+}
+
+#[async_trait]
+impl ToolHandler for RecallHandler {
+    fn kind(&self) -> ToolKind {
+        ToolKind::Function
+    }
+
+    async fn handle(&self, invocation: ToolInvocation) -> Result<ToolOutput, ToolError> {
+        let arguments = function_arguments(invocation.payload, "recall")?;
+        let args: RecallArgs = parse_arguments(&arguments)?;
+        let limit = args.limit.unwrap_or(DEFAULT_RECALL_LIMIT).max(1);
+
+        let store = Arc::clone(&self.store);
+        let entries = tokio::task::spawn_blocking(move || store.recall(&args.query, limit))
+            .await
+            .map_err(|e| ToolError::ExecutionFailed(e.to_string()))??;
+
+        if entries.is_empty() {
+            return Ok(ToolOutput::success("No matching memories."));
+        }
+        let text = entries
+            .iter()
+            .map(|entry| format!("- {}: {}", entry.key, entry.value))
+            .collect::<Vec<_>>()
+            .join("\n");
+        Ok(ToolOutput::success(text))
+    }
+
+    fn schema(&self) -> ToolSpec {
+        ToolSpec::recall()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn invocation(working_dir: &Path, tool: &str, arguments: serde_json::Value) -> ToolInvocation {
+        ToolInvocation::new(
+            "call-1",
+            tool,
+            ToolPayload::Function {
+                arguments: arguments.to_string(),
+            },
+            working_dir.to_path_buf(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_remember_then_recall_in_new_store() {
+        let temp_dir = TempDir::new().unwrap();
+        let remember = RememberHandler::new(Arc::new(MemoryStore::new(temp_dir.path())));
+        let output = remember
+            .handle(invocation(
+                temp_dir.path(),
+                "remember",
+                serde_json::json!({ "key": "indent style", "value": "the user prefers tabs" }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(output.as_text().unwrap(), "Remembered \"indent style\"");
+
+        let recall = RecallHandler::new(Arc::new(MemoryStore::new(temp_dir.path())));
+        let output = recall
+            .handle(invocation(
+                temp_dir.path(),
+                "recall",
+                serde_json::json!({ "query": "tabs or spaces?" }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(
+            output.as_text().unwrap(),
+            "- indent style: the user prefers tabs"
+        );
+
+        let output = recall
+            .handle(invocation(
+                temp_dir.path(),
+                "recall",
+                serde_json::json!({ "query": "deploy" }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(output.as_text().unwrap(), "No matching memories.");
+    }
+
+    #[tokio::test]
+    async fn test_remember_rejects_empty_key() {
+        let temp_dir = TempDir::new().unwrap();
+        let remember = RememberHandler::new(Arc::new(MemoryStore::new(temp_dir.path())));
+        let result = remember
+            .handle(invocation(
+                temp_dir.path(),
+                "remember",
+                serde_json::json!({ "key": "", "value": "x" }),
+            ))
+            .await;
+        assert!(matches!(result, Err(ToolError::InvalidArguments(_))));
+    }
+}
@@ -1,22 +1,34 @@
 //! Tool handler implementations.
 
+pub mod ai_history;
 pub mod apply_patch;
+pub mod cargo_check;
+pub mod fetch_url;
+pub mod finish_task;
 pub mod grep_files;
 pub mod list_dir;
 pub mod mcp_bridge;
+pub mod memory;
 pub mod plan;
 pub mod read_file;
 pub mod request_user_input;
 pub mod shell;
+pub mod write_file;
 
+pub use ai_history::AiHistoryHandler;
 pub use apply_patch::ApplyPatchHandler;
+pub use cargo_check::CargoCheckHandler;
+pub use fetch_url::{FetchUrlHandler, FetchUrlHandlerBuilder};
+pub use finish_task::FinishTaskHandler;
 pub use grep_files::GrepFilesHandler;
 pub use list_dir::ListDirHandler;
 pub use mcp_bridge::McpBridgeHandler;
+pub use memory::{RecallHandler, RememberHandler};
 pub use plan::PlanHandler;
 pub use read_file::ReadFileHandler;
 pub use request_user_input::RequestUserInputHandler;
 pub use shell::ShellHandler;
+pub use write_file::WriteFileHandler;
 
 /// Helper function to parse JSON arguments for tool handlers.
 pub fn parse_arguments<T: serde::de::DeserializeOwned>(
@@ -29,3 +41,56 @@ pub fn parse_arguments<T: serde::de::DeserializeOwned>(
         ))
     })
 }
+
+/// Build the metadata shared by file-editing tools: a unified diff per file for the TUI
+/// (`diffs`) and the list of paths the call changed, or would change on a dry run
+/// (`changed_paths`), for hooks and observers.
+pub fn file_change_metadata(
+    file_diffs: &[crate::internal::ai::tools::apply_patch::FileDiff],
+    dry_run: bool,
+) -> serde_json::Value {
+    let diffs: Vec<serde_json::Value> = file_diffs
+        .iter()
+        .map(|fd| {
+            let patch = diffy::create_patch(&fd.old_content, &fd.new_content);
+            let kind = if fd.old_content.is_empty() {
+                "add"
+            } else if fd.new_content.is_empty() {
+                "delete"
+            } else {
+                "update"
+            };
+            serde_json::json!({
+                "path": fd.path.display().to_string(),
+                "diff": patch.to_string(),
+                "type": kind,
+            })
+        })
+        .collect();
+    let changed_paths: Vec<String> = file_diffs
+        .iter()
+        .map(|fd| fd.path.display().to_string())
+        .collect();
+    serde_json::json!({
+        "diffs": diffs,
+        "changed_paths": changed_paths,
+        "dry_run": dry_run,
+    })
+}
+
+/// Render the diffs a dry run would apply, for the text returned to the model.
+pub fn format_diff_preview(
+    file_diffs: &[crate::internal::ai::tools::apply_patch::FileDiff],
+) -> String {
+    let mut out = String::from("Dry run. No files were changed. Resulting diff:\n");
+    for fd in file_diffs {
+        out.push_str(&format!("--- {}\n", fd.path.display()));
+        let patch = diffy::create_patch(&fd.old_content, &fd.new_content);
+        // Skip diffy's own `---`/`+++` header lines; the path header above replaces them.
+        for line in patch.to_string().lines().skip(2) {
+            out.push_str(line);
+            out.push('\n');
+        }
+    }
+    out
+}
@@ -87,11 +87,11 @@ impl ToolHandler for ShellHandler {
 
 // ── Internal types ────────────────────────────────────────────────────────────
 
-struct ExecOutput {
-    exit_code: i32,
-    stdout: String,
-    stderr: String,
-    timed_out: bool,
+pub(crate) struct ExecOutput {
+    pub(crate) exit_code: i32,
+    pub(crate) stdout: String,
+    pub(crate) stderr: String,
+    pub(crate) timed_out: bool,
 }
 
 #[derive(Default, Clone)]
@@ -124,7 +124,11 @@ fn format_output(exit_code: i32, stdout: &str, stderr: &str, timed_out: bool) ->
 
 // ── Execution ─────────────────────────────────────────────────────────────────
 
-async fn run_shell(command: &str, cwd: &Path, timeout_ms: Option<u64>) -> ToolResult<ExecOutput> {
+pub(crate) async fn run_shell(
+    command: &str,
+    cwd: &Path,
+    timeout_ms: Option<u64>,
+) -> ToolResult<ExecOutput> {
     let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string());
     let timeout_dur = Duration::from_millis(timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS));
 
@@ -0,0 +1,238 @@
+//! Handler for the write_file tool.
+
+use std::path::Path;
+
+use async_trait::async_trait;
+
+use super::{file_change_metadata, format_diff_preview, parse_arguments};
+use crate::internal::ai::tools::{
+    apply_patch::FileDiff,
+    context::{ToolInvocation, ToolKind, ToolOutput, ToolPayload, WriteFileArgs},
+    error::ToolError,
+    registry::ToolHandler,
+    spec::ToolSpec,
+    utils::validate_path,
+};
+
+/// Handler for creating or overwriting whole files.
+pub struct WriteFileHandler;
+
+/// Largest content accepted by a single write_file call.
+pub const MAX_WRITE_FILE_BYTES: usize = 1024 * 1024;
+
+#[async_trait]
+impl ToolHandler for WriteFileHandler {
+    fn kind(&self) -> ToolKind {
+        ToolKind::Function
+    }
+
+    async fn handle(&self, invocation: ToolInvocation) -> Result<ToolOutput, ToolError> {
+        let ToolInvocation {
+            payload,
+            working_dir,
+            ..
+        } = invocation;
+
+        let arguments = match payload {
+            ToolPayload::Function { arguments } => arguments,
+            _ => {
+                return Err(ToolError::IncompatiblePayload(
+                    "write_file handler only accepts Function payloads".to_string(),
+                ));
+            }
+        };
+
+        let args: WriteFileArgs = parse_arguments(&arguments)?;
+
+        if args.content.len() > MAX_WRITE_FILE_BYTES {
+            return Err(ToolError::InvalidArguments(format!(
+                "content is {} bytes, the limit is {MAX_WRITE_FILE_BYTES} bytes",
+                args.content.len()
+            )));
+        }
+
+        let path = Path::new(&args.file_path);
+        validate_path(path, &working_dir)?;
+
+        if path.is_dir() {
+            return Err(ToolError::InvalidArguments(format!(
+                "{} is a directory",
+                path.display()
+            )));
+        }
+
+        let existed = path.exists();
+        let old_content = if existed {
+            tokio::fs::read_to_string(path).await?
+        } else {
+            String::new()
+        };
+        let file_diffs = [FileDiff {
+            path: path.to_path_buf(),
+            old_content,
+            new_content: args.content,
+        }];
+
+        if args.dry_run {
+            return Ok(ToolOutput::success(format_diff_preview(&file_diffs))
+                .with_metadata(file_change_metadata(&file_diffs, true)));
+        }
+
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(path, &file_diffs[0].new_content).await?;
+
+        let verb = if existed { "Overwrote" } else { "Created" };
+        let output = format!(
+            "{verb} {} ({} bytes)",
+            path.display(),
+            file_diffs[0].new_content.len()
+        );
+        Ok(ToolOutput::success(output).with_metadata(file_change_metadata(&file_diffs, false)))
+    }
+
+    fn schema(&self) -> ToolSpec {
+        ToolSpec::write_file()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn invocation(working_dir: &Path, arguments: serde_json::Value) -> ToolInvocation {
+        ToolInvocation::new(
+            "call-1",
+            "write_file",
+            ToolPayload::Function {
+                arguments: arguments.to_string(),
+            },
+            working_dir.to_path_buf(),
+        )
+    }
+
+    fn changed_paths(output: &ToolOutput) -> Vec<String> {
+        let ToolOutput::Function {
+            metadata: Some(meta),
+            ..
+        } = output
+        else {
+            panic!("expected metadata");
+        };
+        serde_json::from_value(meta["changed_paths"].clone()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_write_file_creates_parent_dirs() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("a/b/new.txt");
+
+        let output = WriteFileHandler
+            .handle(invocation(
+                temp_dir.path(),
+                serde_json::json!({ "file_path": path, "content": "hello\n" }),
+            ))
+            .await
+            .unwrap();
+
+        assert!(output.as_text().unwrap().starts_with("Created"));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "hello\n");
+        assert_eq!(changed_paths(&output), vec![path.display().to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_write_file_overwrites_existing() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("file.txt");
+        fs::write(&path, "old\n").unwrap();
+
+        let output = WriteFileHandler
+            .handle(invocation(
+                temp_dir.path(),
+                serde_json::json!({ "file_path": path, "content": "new\n" }),
+            ))
+            .await
+            .unwrap();
+
+        assert!(output.as_text().unwrap().starts_with("Overwrote"));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new\n");
+    }
+
+    #[tokio::test]
+    async fn test_write_file_dry_run_previews_without_writing() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("file.txt");
+        fs::write(&path, "keep\nold\n").unwrap();
+
+        let output = WriteFileHandler
+            .handle(invocation(
+                temp_dir.path(),
+                serde_json::json!({ "file_path": path, "content": "keep\nnew\n", "dry_run": true }),
+            ))
+            .await
+            .unwrap();
+
+        let text = output.as_text().unwrap();
+        assert!(text.contains("Dry run"));
+        assert!(text.contains("-old"));
+        assert!(text.contains("+new"));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "keep\nold\n");
+        assert_eq!(changed_paths(&output), vec![path.display().to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_write_file_rejects_sandbox_escape() {
+        let temp_dir = TempDir::new().unwrap();
+        let outside_dir = TempDir::new().unwrap();
+        let outside = outside_dir.path().join("evil.txt");
+
+        let result = WriteFileHandler
+            .handle(invocation(
+                temp_dir.path(),
+                serde_json::json!({ "file_path": outside, "content": "x" }),
+            ))
+            .await;
+        assert!(matches!(result, Err(ToolError::PathOutsideWorkingDir(_))));
+        assert!(!outside.exists());
+
+        let traversal = temp_dir.path().join("../evil.txt");
+        let result = WriteFileHandler
+            .handle(invocation(
+                temp_dir.path(),
+                serde_json::json!({ "file_path": traversal, "content": "x" }),
+            ))
+            .await;
+        assert!(matches!(result, Err(ToolError::PathOutsideWorkingDir(_))));
+
+        let result = WriteFileHandler
+            .handle(invocation(
+                temp_dir.path(),
+                serde_json::json!({ "file_path": "relative.txt", "content": "x" }),
+            ))
+            .await;
+        assert!(matches!(result, Err(ToolError::PathNotAbsolute(_))));
+    }
+
+    #[tokio::test]
+    async fn test_write_file_rejects_oversized_content() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("big.txt");
+
+        let result = WriteFileHandler
+            .handle(invocation(
+                temp_dir.path(),
+                serde_json::json!({
+                    "file_path": path,
+                    "content": "x".repeat(MAX_WRITE_FILE_BYTES + 1),
+                }),
+            ))
+            .await;
+        assert!(matches!(result, Err(ToolError::InvalidArguments(_))));
+        assert!(!path.exists());
+    }
+}
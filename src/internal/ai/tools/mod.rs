@@ -1,6 +1,6 @@
 //! Tool calling infrastructure for AI agents.
 
-use std::{error::Error, sync::Arc};
+use std::sync::{Arc, RwLock};
 
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -11,15 +11,17 @@ pub mod error;
 pub mod handlers;
 pub mod registry;
 pub mod spec;
+pub mod stats;
 pub mod utils;
 
 pub use context::{
-    GrepFilesArgs, ListDirArgs, ReadFileArgs, ShellArgs, ToolInvocation, ToolKind, ToolOutput,
-    ToolPayload,
+    CargoCheckArgs, GrepFilesArgs, ListDirArgs, ReadFileArgs, ShellArgs, ToolInvocation, ToolKind,
+    ToolOutput, ToolPayload,
 };
-pub use error::{ToolError, ToolResult};
+pub use error::{ToolCallError, ToolError, ToolResult};
 pub use registry::{ToolHandler, ToolRegistry, ToolRegistryBuilder};
 pub use spec::{FunctionDefinition, FunctionParameters, ToolSpec, ToolSpecBuilder};
+pub use stats::{ToolStats, ToolUsage};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolDefinition {
@@ -39,12 +41,120 @@ pub trait Tool: Send + Sync {
 
     fn definition(&self) -> ToolDefinition;
 
-    fn call(&self, args: Value) -> Result<Value, Box<dyn Error + Send + Sync>>;
+    /// Run the tool. The error variant decides whether the model gets to see the failure;
+    /// see [`ToolCallError`].
+    fn call(&self, args: Value) -> Result<Value, ToolCallError>;
 }
 
+/// A tool offered to the model under a different definition than its own.
+///
+/// The model sees `definition` (for example a schema without internal parameters),
+/// while calls are dispatched to the wrapped tool. The definition's name is replaced
+/// with the tool's real name so dispatch by name keeps working.
+pub struct ToolWithDefinition {
+    tool: Arc<dyn Tool>,
+    definition: ToolDefinition,
+}
+
+impl ToolWithDefinition {
+    pub fn new(tool: Arc<dyn Tool>, definition: ToolDefinition) -> Self {
+        let definition = ToolDefinition {
+            name: tool.name(),
+            ..definition
+        };
+        Self { tool, definition }
+    }
+}
+
+impl Tool for ToolWithDefinition {
+    fn name(&self) -> String {
+        self.definition.name.clone()
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        self.definition.clone()
+    }
+
+    fn call(&self, args: Value) -> Result<Value, ToolCallError> {
+        self.tool.call(args)
+    }
+}
+
+/// A list of tools as [`ToolSet::tools`] hands it out.
+pub type ToolList = Arc<Vec<Arc<dyn Tool>>>;
+
+/// The tools an [`Agent`](crate::internal::ai::agent::Agent) offers the model.
+///
+/// Clones share one live list: a tool added or removed through any clone (for example by a
+/// [`ChatAgent`](crate::internal::ai::agent::ChatAgent) between turns) is seen by all of them
+/// from their next request. Use [`ToolSet::snapshot`] for an independent copy.
 #[derive(Default, Clone)]
 pub struct ToolSet {
-    pub tools: Vec<Arc<dyn Tool>>,
+    /// Replaced wholesale on every change, so a list handed out by [`ToolSet::tools`] never
+    /// changes underneath its reader.
+    tools: Arc<RwLock<ToolList>>,
+    /// Usage recorded by the agent loop; shared between clones of the set.
+    pub stats: ToolStats,
+}
+
+impl ToolSet {
+    pub fn new(tools: impl IntoIterator<Item = Arc<dyn Tool>>) -> Self {
+        let set = Self::default();
+        for tool in tools {
+            set.add(tool);
+        }
+        set
+    }
+
+    /// The current tools, unaffected by later changes to the set.
+    pub fn tools(&self) -> ToolList {
+        self.tools.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Definitions of the current tools, in the order they were added.
+    pub fn definitions(&self) -> Vec<ToolDefinition> {
+        self.tools().iter().map(|tool| tool.definition()).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.tools().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tools().is_empty()
+    }
+
+    /// Append `tool`. Calls go to the first tool with a matching name.
+    pub fn add(&self, tool: Arc<dyn Tool>) {
+        self.update(|tools| tools.push(tool));
+    }
+
+    /// Remove every tool called `name`; returns whether any was present.
+    pub fn remove(&self, name: &str) -> bool {
+        let mut removed = false;
+        self.update(|tools| {
+            let before = tools.len();
+            tools.retain(|tool| tool.name() != name);
+            removed = tools.len() != before;
+        });
+        removed
+    }
+
+    /// An independent copy of the current tools; changes to either set no longer affect
+    /// the other. Usage counters stay shared.
+    pub fn snapshot(&self) -> ToolSet {
+        ToolSet {
+            tools: Arc::new(RwLock::new(self.tools())),
+            stats: self.stats.clone(),
+        }
+    }
+
+    fn update(&self, change: impl FnOnce(&mut Vec<Arc<dyn Tool>>)) {
+        let mut current = self.tools.write().unwrap_or_else(|e| e.into_inner());
+        let mut tools = current.as_ref().clone();
+        change(&mut tools);
+        *current = Arc::new(tools);
+    }
 }
 
 #[cfg(test)]
@@ -65,6 +175,18 @@ mod tests {
         let patch_spec = ToolSpec::apply_patch();
         assert_eq!(patch_spec.function.name, "apply_patch");
 
+        let fetch_spec = ToolSpec::fetch_url();
+        assert_eq!(fetch_spec.function.name, "fetch_url");
+
+        let write_spec = ToolSpec::write_file();
+        assert_eq!(write_spec.function.name, "write_file");
+
+        let remember_spec = ToolSpec::remember();
+        assert_eq!(remember_spec.function.name, "remember");
+
+        let recall_spec = ToolSpec::recall();
+        assert_eq!(recall_spec.function.name, "recall");
+
         let shell_spec = ToolSpec::shell();
         assert_eq!(shell_spec.function.name, "shell");
     }
@@ -8,6 +8,7 @@ use super::{
     context::{ToolInvocation, ToolKind, ToolOutput, ToolPayload},
     error::{ToolError, ToolResult},
     spec::ToolSpec,
+    stats::ToolStats,
 };
 
 /// Handler trait that all tools must implement.
@@ -54,6 +55,8 @@ pub struct ToolRegistry {
     handlers: HashMap<String, Arc<dyn ToolHandler>>,
     /// Working directory for file operations.
     working_dir: std::path::PathBuf,
+    /// Usage recorded by the tool loop; shared between clones of the registry.
+    stats: ToolStats,
 }
 
 impl ToolRegistry {
@@ -69,6 +72,7 @@ impl ToolRegistry {
         Ok(Self {
             handlers: HashMap::new(),
             working_dir: std::env::current_dir()?,
+            stats: ToolStats::default(),
         })
     }
 
@@ -77,6 +81,7 @@ impl ToolRegistry {
         Self {
             handlers: HashMap::new(),
             working_dir,
+            stats: ToolStats::default(),
         }
     }
 
@@ -155,6 +160,11 @@ impl ToolRegistry {
         self.working_dir = dir;
     }
 
+    /// Per-tool usage accumulated by tool loops run against this registry.
+    pub fn stats(&self) -> &ToolStats {
+        &self.stats
+    }
+
     /// Check if a tool is registered.
     pub fn contains_tool(&self, name: &str) -> bool {
         self.handlers.contains_key(name)
@@ -108,6 +108,24 @@ impl ToolSpec {
         ))
     }
 
+    /// Create a ToolSpec for cargo_check.
+    pub fn cargo_check() -> Self {
+        Self::new(
+            "cargo_check",
+            "Run `cargo check` in the working directory and return the first compiler errors \
+             as JSON objects with file, line and column span, level, code, message and the \
+             compiler's suggested replacement, if any.",
+        )
+        .with_parameters(FunctionParameters::object(
+            [(
+                "limit",
+                "number",
+                "Maximum number of errors to return (default: 10)",
+            )],
+            [("limit", false)],
+        ))
+    }
+
     /// Create a ToolSpec for update_plan.
     pub fn update_plan() -> Self {
         Self {
@@ -319,14 +337,111 @@ It is important to remember:
 - File references can only be relative, NEVER ABSOLUTE.
 - IMPORTANT: When writing context or removed lines from `read_file` output, strip the `L{n}: ` line-number prefix. For example, `L3:     my_func():` becomes `    my_func():` (preserve any indentation that follows the prefix).
 - IMPORTANT: Blank lines MUST be included as context lines. In `read_file` output a blank line appears as `L{n}: ` (nothing after the space). Represent it in the patch as a single space ` ` on its own line. Do NOT skip blank lines -- omitting them will cause the patch to fail to locate the target region.
+
+A standard unified diff (`--- a/<path>` / `+++ b/<path>` headers with `@@ -l,s +l,s @@` hunks) is also accepted. It is applied only if every hunk applies, and the result reports each hunk.
+Set `dry_run` to true to get the resulting diff without changing any file.
 "#,
         )
         .with_parameters(FunctionParameters::object(
-            [("input", "string", "The entire contents of the apply_patch command")],
+            [
+                ("input", "string", "The entire contents of the apply_patch command"),
+                ("dry_run", "boolean", "Return the resulting diff without writing any file (default: false)"),
+            ],
             [("input", true)],
         ))
     }
 
+    /// Create a ToolSpec for fetch_url.
+    pub fn fetch_url() -> Self {
+        Self::new(
+            "fetch_url",
+            "Fetch a web page from an allowlisted domain. HTML is converted to readable text; JSON and plain text are returned as-is. \
+             The result starts with the final URL, HTTP status and whether the body was truncated.",
+        )
+        .with_parameters(FunctionParameters::object(
+            [("url", "string", "Absolute http(s) URL to fetch")],
+            [("url", true)],
+        ))
+    }
+
+    /// Create a ToolSpec for write_file.
+    pub fn write_file() -> Self {
+        Self::new(
+            "write_file",
+            "Create a file or overwrite it with the given content, creating missing parent directories. Prefer apply_patch for small edits to existing files.",
+        )
+        .with_parameters(FunctionParameters::object(
+            [
+                ("file_path", "string", "Absolute path to the file to write"),
+                ("content", "string", "The complete new content of the file"),
+                ("dry_run", "boolean", "Return the resulting diff without writing the file (default: false)"),
+            ],
+            [("file_path", true), ("content", true)],
+        ))
+    }
+
+    /// Create a ToolSpec for remember.
+    pub fn remember() -> Self {
+        Self::new(
+            "remember",
+            "Save a short fact that should persist across sessions, such as a user preference or a project convention. Reusing a key replaces the old value.",
+        )
+        .with_parameters(FunctionParameters::object(
+            [
+                ("key", "string", "Short identifier for the fact, e.g. \"build command\""),
+                ("value", "string", "The fact to remember"),
+            ],
+            [("key", true), ("value", true)],
+        ))
+    }
+
+    /// Create a ToolSpec for recall.
+    pub fn recall() -> Self {
+        Self::new(
+            "recall",
+            "Look up facts saved with remember. Matches keywords against stored keys and values, best match first.",
+        )
+        .with_parameters(FunctionParameters::object(
+            [
+                ("query", "string", "Keywords to search for"),
+                ("limit", "integer", "Maximum number of memories to return (default: 5)"),
+            ],
+            [("query", true)],
+        ))
+    }
+
+    /// Create a ToolSpec for ai_history.
+    pub fn ai_history() -> Self {
+        Self::new(
+            "ai_history",
+            "Look up the intent and tasks recorded in the AI history, or update a task's status. Results are JSON.",
+        )
+        .with_parameters(FunctionParameters::object(
+            [
+                ("operation", "string", "One of get_intent, list_tasks, get_task, update_task_status"),
+                ("id", "string", "Intent id for get_intent; task id for get_task and update_task_status. A unique prefix is enough"),
+                ("intent_id", "string", "list_tasks: only tasks of this intent"),
+                ("status", "string", "list_tasks: status filter; update_task_status: the new status (draft, running, done, failed, cancelled)"),
+            ],
+            [("operation", true)],
+        ))
+    }
+
+    /// Create a ToolSpec for finish_task.
+    pub fn finish_task() -> Self {
+        Self::new(
+            "finish_task",
+            "Report that you are done with the task, or that you cannot finish it. Call it once, as your last tool call.",
+        )
+        .with_parameters(FunctionParameters::object(
+            [
+                ("status", "string", "done if the task is complete, blocked if it cannot be completed"),
+                ("summary", "string", "What you changed, or what blocks the task"),
+            ],
+            [("status", true), ("summary", true)],
+        ))
+    }
+
     /// Convert to a JSON value for API requests.
     pub fn to_json(&self) -> Value {
         json!(self)
@@ -0,0 +1,177 @@
+//! Per-tool usage statistics collected by the agent tool-calling loops.
+//!
+//! Both [`Agent::run_with_history`](crate::internal::ai::agent::Agent) (over a
+//! [`ToolSet`](super::ToolSet)) and
+//! [`run_tool_loop`](crate::internal::ai::agent::run_tool_loop) (over a
+//! [`ToolRegistry`](super::ToolRegistry)) time every executed tool call and record it
+//! into the [`ToolStats`] attached to the tool collection. Recording takes a mutex once
+//! per completed call; nothing is locked while the tool runs.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// Aggregated usage of a single tool.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ToolUsage {
+    /// Number of completed calls, including failed ones.
+    pub calls: u64,
+    /// Number of calls that returned an error or a failure output.
+    pub errors: u64,
+    /// Sum of all call durations.
+    pub total_duration: Duration,
+    /// Sum of the sizes of all results, in bytes.
+    pub result_bytes: u64,
+    /// Individual call durations, in completion order.
+    pub durations: Vec<Duration>,
+}
+
+impl ToolUsage {
+    /// Duration at percentile `p` (0.0..=100.0) using the nearest-rank method.
+    pub fn percentile(&self, p: f64) -> Duration {
+        if self.durations.is_empty() {
+            return Duration::ZERO;
+        }
+        let mut sorted = self.durations.clone();
+        sorted.sort_unstable();
+        let rank = ((p.clamp(0.0, 100.0) / 100.0) * sorted.len() as f64).ceil() as usize;
+        sorted[rank.saturating_sub(1).min(sorted.len() - 1)]
+    }
+
+    /// Mean call duration.
+    pub fn mean_duration(&self) -> Duration {
+        if self.calls == 0 {
+            return Duration::ZERO;
+        }
+        self.total_duration / self.calls as u32
+    }
+}
+
+/// Shared, thread-safe per-tool counters.
+///
+/// Cloning a `ToolStats` yields a handle to the same counters, so stats recorded through
+/// a cloned [`Agent`](crate::internal::ai::agent::Agent) or registry are visible from the
+/// original.
+#[derive(Debug, Clone, Default)]
+pub struct ToolStats {
+    usage: Arc<Mutex<BTreeMap<String, ToolUsage>>>,
+}
+
+impl ToolStats {
+    /// Record one completed call of `tool`.
+    pub fn record(&self, tool: &str, duration: Duration, success: bool, result_bytes: usize) {
+        let mut usage = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = usage.entry(tool.to_string()).or_default();
+        entry.calls += 1;
+        if !success {
+            entry.errors += 1;
+        }
+        entry.total_duration += duration;
+        entry.result_bytes += result_bytes as u64;
+        entry.durations.push(duration);
+    }
+
+    /// Usage of a single tool, if it has been called.
+    pub fn get(&self, tool: &str) -> Option<ToolUsage> {
+        self.usage
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(tool)
+            .cloned()
+    }
+
+    /// Copy of the usage of every called tool, keyed by tool name.
+    pub fn snapshot(&self) -> BTreeMap<String, ToolUsage> {
+        self.usage.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Whether no calls have been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.usage
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .is_empty()
+    }
+
+    /// Discard all recorded calls.
+    pub fn reset(&self) {
+        self.usage.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+
+    /// Render the recorded usage as a fixed-width table, one tool per line.
+    pub fn report(&self) -> String {
+        let snapshot = self.snapshot();
+        if snapshot.is_empty() {
+            return "No tool calls recorded.\n".to_string();
+        }
+
+        let width = snapshot.keys().map(String::len).max().unwrap_or(0).max(4);
+        let mut out = format!(
+            "{:<width$}  {:>6}  {:>6}  {:>10}  {:>10}  {:>10}  {:>10}\n",
+            "tool", "calls", "errors", "total", "p50", "p95", "bytes"
+        );
+        for (name, usage) in &snapshot {
+            let _ = writeln!(
+                out,
+                "{:<width$}  {:>6}  {:>6}  {:>10}  {:>10}  {:>10}  {:>10}",
+                name,
+                usage.calls,
+                usage.errors,
+                format_duration(usage.total_duration),
+                format_duration(usage.percentile(50.0)),
+                format_duration(usage.percentile(95.0)),
+                usage.result_bytes,
+            );
+        }
+        out
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    format!("{:.1}ms", duration.as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_aggregates_per_tool() {
+        let stats = ToolStats::default();
+        stats.record("read_file", Duration::from_millis(10), true, 100);
+        stats.record("read_file", Duration::from_millis(30), false, 5);
+        stats.record("shell", Duration::from_millis(20), true, 7);
+
+        let read = stats.get("read_file").unwrap();
+        assert_eq!(read.calls, 2);
+        assert_eq!(read.errors, 1);
+        assert_eq!(read.total_duration, Duration::from_millis(40));
+        assert_eq!(read.result_bytes, 105);
+        assert_eq!(read.mean_duration(), Duration::from_millis(20));
+        assert_eq!(stats.snapshot().len(), 2);
+
+        let report = stats.report();
+        assert!(report.lines().next().unwrap().starts_with("tool"));
+        assert!(report.contains("read_file"));
+        assert!(report.contains("shell"));
+
+        stats.reset();
+        assert!(stats.is_empty());
+    }
+
+    #[test]
+    fn test_percentile_nearest_rank() {
+        let stats = ToolStats::default();
+        for ms in [5, 1, 4, 2, 3] {
+            stats.record("t", Duration::from_millis(ms), true, 0);
+        }
+        let usage = stats.get("t").unwrap();
+        assert_eq!(usage.percentile(0.0), Duration::from_millis(1));
+        assert_eq!(usage.percentile(50.0), Duration::from_millis(3));
+        assert_eq!(usage.percentile(95.0), Duration::from_millis(5));
+        assert_eq!(ToolUsage::default().percentile(50.0), Duration::ZERO);
+    }
+}
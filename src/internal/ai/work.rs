@@ -0,0 +1,681 @@
+//! Executing a planned task with an agent: `libra ai work <task-id>`.
+//!
+//! [`TaskWork::run`] loads a [`Task`] from the AI history and prompts an agent with it under
+//! an implementation profile ([`WORK_PROFILE`] unless `--profile` or [`WORK_PROFILE_KEY`]
+//! names another). The preamble adds the goal of the task's intent and a summary of the
+//! other tasks planned for it. The agent gets the file and shell tools, `ai_history`, and
+//! `finish_task`.
+//!
+//! The model reports how the task ended with `finish_task`, or by ending its answer with
+//! the same fields as a JSON block (see [`TaskCompletion`]). A draft task is marked running
+//! when work starts; `done` then marks it done and `blocked` marks it failed, since tasks
+//! have no blocked status. An answer without either signal counts as blocked. The outcome
+//! is stored as an [`AgentRunRecord`] linked to the task. When the run itself fails, the
+//! task stays running so it can be worked on again.
+//!
+//! A dry run only offers the read-only tools, changes no status and records nothing.
+
+use std::{fmt, path::PathBuf, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use chrono::Utc;
+use git_internal::internal::object::{
+    intent::Intent,
+    task::{Task, TaskStatus},
+    types::ActorRef,
+};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use thiserror::Error;
+
+use crate::{
+    internal::ai::{
+        agent::{
+            profile::{AgentProfile, parse_agent_profile},
+            runtime::{
+                step_history::AgentRunRecord,
+                tool_loop::{
+                    NoopObserver, ToolLoopConfig, ToolLoopObserver, run_tool_loop_with_observer,
+                },
+            },
+        },
+        completion::{CompletionError, CompletionModel},
+        history::HistoryManager,
+        task::{attribute_status_change, check_status_transition},
+        tools::{
+            ToolHandler, ToolInvocation, ToolKind, ToolOutput, ToolRegistry, ToolRegistryBuilder,
+            ToolResult, ToolSpec,
+            error::ToolError,
+            handlers::{
+                AiHistoryHandler, ApplyPatchHandler, FinishTaskHandler, GrepFilesHandler,
+                ListDirHandler, ReadFileHandler, ShellHandler, WriteFileHandler,
+            },
+        },
+    },
+    utils::storage_ext::StorageExt,
+};
+
+/// Name of the profile tasks are worked on with by default.
+pub const WORK_PROFILE: &str = "implementer";
+
+/// Config key (`libra config ai.work.profile <name>`) for the profile tasks are worked on
+/// with.
+pub const WORK_PROFILE_KEY: &str = "ai.work.profile";
+
+/// Agent id status changes and run records are attributed to.
+pub const WORK_ACTOR: &str = "libra-work";
+
+const EMBEDDED_PROFILE: &str = include_str!("agent/profile/embedded/implementer.md");
+
+/// Appended to every work preamble, so profiles need not explain the completion signal.
+const FINISH_INSTRUCTIONS: &str = "## Finishing\n\n\
+When the task is complete, call finish_task with status \"done\" and a summary of what you \
+changed. If you cannot complete it, call finish_task with status \"blocked\" and explain \
+what is missing. Without the finish_task tool, end your answer with the same fields as a \
+JSON block instead: {\"status\": \"done\", \"summary\": \"...\"}.";
+
+/// The embedded [`WORK_PROFILE`], used when no loaded profile has that name.
+pub fn embedded_profile() -> AgentProfile {
+    parse_agent_profile(EMBEDDED_PROFILE).expect("embedded implementer profile is valid")
+}
+
+/// The profile called `name` among `profiles`, falling back to the embedded one for
+/// [`WORK_PROFILE`].
+pub fn work_profile(profiles: Vec<AgentProfile>, name: &str) -> Option<AgentProfile> {
+    profiles
+        .into_iter()
+        .find(|profile| profile.name == name)
+        .or_else(|| (name == WORK_PROFILE).then(embedded_profile))
+}
+
+/// How the agent says the task ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CompletionStatus {
+    Done,
+    Blocked,
+}
+
+impl fmt::Display for CompletionStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompletionStatus::Done => f.write_str("done"),
+            CompletionStatus::Blocked => f.write_str("blocked"),
+        }
+    }
+}
+
+/// The completion signal: the arguments of `finish_task`, or the JSON block an answer ends
+/// with.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TaskCompletion {
+    pub status: CompletionStatus,
+    /// What was changed, or what blocks the task.
+    pub summary: String,
+}
+
+impl TaskCompletion {
+    /// The signal at the end of `answer`: a fenced code block, or the text from the last
+    /// line starting with `{`.
+    pub fn from_answer(answer: &str) -> Option<Self> {
+        let answer = answer.trim();
+        let block = match answer.strip_suffix("```") {
+            Some(rest) => {
+                let body = &rest[rest.rfind("```")? + 3..];
+                body.strip_prefix("json").unwrap_or(body)
+            }
+            None => &answer[answer.rfind("\n{").map_or(0, |i| i + 1)..],
+        };
+        serde_json::from_str(block.trim()).ok()
+    }
+
+    /// Status the task is moved to.
+    pub fn task_status(&self) -> TaskStatus {
+        match self.status {
+            CompletionStatus::Done => TaskStatus::Done,
+            CompletionStatus::Blocked => TaskStatus::Failed,
+        }
+    }
+}
+
+/// Why a task could not be worked on.
+#[derive(Debug, Error)]
+pub enum WorkError {
+    /// The id names no single task, or the task cannot move to the needed status.
+    #[error("{0}")]
+    Task(String),
+    #[error("AI history: {0}")]
+    History(String),
+    #[error(transparent)]
+    Agent(#[from] CompletionError),
+}
+
+/// Settings of one work run.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TaskWork {
+    /// Offer only read-only tools, and change or record nothing.
+    pub dry_run: bool,
+    /// Maximum number of model round-trips; `None` uses the tool loop's default.
+    pub max_steps: Option<usize>,
+    /// Wall-clock ceiling for the agent; see [`ToolLoopConfig::deadline`].
+    pub deadline: Option<Duration>,
+}
+
+/// The result of [`TaskWork::run`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorkOutcome {
+    /// Full id of the task.
+    pub task_id: String,
+    pub completion: TaskCompletion,
+    /// The model's final answer.
+    pub answer: String,
+    /// Status the task was left in; `None` for a dry run.
+    pub status: Option<TaskStatus>,
+    /// Id of the stored [`AgentRunRecord`]; `None` for a dry run.
+    pub run_id: Option<String>,
+}
+
+impl TaskWork {
+    /// Work on the task whose id is `task_id` or starts with it, using `model` under
+    /// `profile`. File and shell tools operate in `working_dir`.
+    pub async fn run<M: CompletionModel>(
+        &self,
+        model: &M,
+        profile: &AgentProfile,
+        history: Arc<HistoryManager>,
+        working_dir: PathBuf,
+        task_id: &str,
+    ) -> Result<WorkOutcome, WorkError> {
+        self.run_with_observer(
+            model,
+            profile,
+            history,
+            working_dir,
+            task_id,
+            &mut NoopObserver,
+        )
+        .await
+    }
+
+    /// [`TaskWork::run`], reporting the agent's progress to `observer`.
+    pub async fn run_with_observer<M: CompletionModel>(
+        &self,
+        model: &M,
+        profile: &AgentProfile,
+        history: Arc<HistoryManager>,
+        working_dir: PathBuf,
+        task_id: &str,
+        observer: &mut dyn ToolLoopObserver,
+    ) -> Result<WorkOutcome, WorkError> {
+        let actor = ActorRef::agent(WORK_ACTOR).map_err(WorkError::Task)?;
+        let (task_id, mut task) = load_task(&history, task_id).await?;
+        if !matches!(task.status(), TaskStatus::Draft | TaskStatus::Running) {
+            return Err(WorkError::Task(format!(
+                "task {task_id} is already {}",
+                task.status()
+            )));
+        }
+        let preamble = work_preamble(&history, profile, &task_id, &task).await?;
+        if !self.dry_run && task.status() == &TaskStatus::Draft {
+            task = update_status(&history, task, TaskStatus::Running, &actor).await?;
+        }
+
+        let finish = FinishTaskHandler::new();
+        let reported = finish.completion();
+        let registry = self.registry(working_dir, &history, actor.clone(), finish);
+        let mut allowed_tools = profile.tools.clone();
+        if !allowed_tools.iter().any(|tool| tool == "finish_task") {
+            allowed_tools.push("finish_task".to_string());
+        }
+        let defaults = ToolLoopConfig::default();
+        let config = ToolLoopConfig {
+            preamble: Some(preamble),
+            max_steps: self.max_steps.or(defaults.max_steps),
+            allowed_tools: Some(allowed_tools),
+            deadline: self.deadline,
+            ..defaults
+        };
+        let answer = run_tool_loop_with_observer(
+            model,
+            task_prompt(&task_id, &task),
+            &registry,
+            config,
+            observer,
+        )
+        .await?;
+
+        let reported = reported.lock().unwrap_or_else(|e| e.into_inner()).take();
+        let completion = reported
+            .or_else(|| TaskCompletion::from_answer(&answer))
+            .unwrap_or_else(|| TaskCompletion {
+                status: CompletionStatus::Blocked,
+                summary: "the agent finished without reporting whether the task is done"
+                    .to_string(),
+            });
+        if self.dry_run {
+            return Ok(WorkOutcome {
+                task_id,
+                completion,
+                answer,
+                status: None,
+                run_id: None,
+            });
+        }
+
+        // Reload: the agent may have changed the status itself through ai_history.
+        let (_, task) = load_task(&history, &task_id).await?;
+        let status = completion.task_status();
+        if task.status() != &status {
+            update_status(&history, task, status.clone(), &actor).await?;
+        }
+        let record = AgentRunRecord {
+            run_id: uuid::Uuid::new_v4().simple().to_string(),
+            profile: Some(profile.name.clone()),
+            answer: serde_json::to_string(&completion)
+                .map_err(|e| WorkError::History(e.to_string()))?,
+            commit: None,
+            task: Some(task_id.clone()),
+            created_at: Utc::now(),
+        };
+        history
+            .get_storage()
+            .put_tracked(&record, &history)
+            .await
+            .map_err(|e| WorkError::History(e.to_string()))?;
+        Ok(WorkOutcome {
+            task_id,
+            completion,
+            answer,
+            status: Some(status),
+            run_id: Some(record.run_id),
+        })
+    }
+
+    fn registry(
+        &self,
+        working_dir: PathBuf,
+        history: &Arc<HistoryManager>,
+        actor: ActorRef,
+        finish: FinishTaskHandler,
+    ) -> ToolRegistry {
+        let ai_history = Arc::new(AiHistoryHandler::new(
+            history.clone(),
+            history.get_storage(),
+            actor,
+        ));
+        let builder = ToolRegistryBuilder::with_working_dir(working_dir)
+            .register("read_file", Arc::new(ReadFileHandler))
+            .register("list_dir", Arc::new(ListDirHandler))
+            .register("grep_files", Arc::new(GrepFilesHandler))
+            .register("finish_task", Arc::new(finish));
+        if self.dry_run {
+            return builder
+                .register("ai_history", Arc::new(ReadOnlyTool(ai_history)))
+                .build();
+        }
+        builder
+            .register("ai_history", ai_history)
+            .register("apply_patch", Arc::new(ApplyPatchHandler))
+            .register("write_file", Arc::new(WriteFileHandler))
+            .register("shell", Arc::new(ShellHandler))
+            .build()
+    }
+}
+
+/// Refuses the calls of a tool that would change something.
+struct ReadOnlyTool(Arc<dyn ToolHandler>);
+
+#[async_trait]
+impl ToolHandler for ReadOnlyTool {
+    fn kind(&self) -> ToolKind {
+        self.0.kind()
+    }
+
+    async fn is_mutating(&self, invocation: &ToolInvocation) -> bool {
+        self.0.is_mutating(invocation).await
+    }
+
+    async fn handle(&self, invocation: ToolInvocation) -> ToolResult<ToolOutput> {
+        if self.0.is_mutating(&invocation).await {
+            return Err(ToolError::ExecutionFailed(format!(
+                "{} would make changes, which a dry run does not allow",
+                invocation.tool_name
+            )));
+        }
+        self.0.handle(invocation).await
+    }
+
+    fn schema(&self) -> ToolSpec {
+        self.0.schema()
+    }
+}
+
+/// Load the `kind` object stored under the full id `id`, if any.
+async fn load_object<T: DeserializeOwned + Send + Sync>(
+    history: &HistoryManager,
+    kind: &str,
+    id: &str,
+) -> Result<Option<T>, WorkError> {
+    let Some(hash) = history
+        .get_object_hash(kind, id)
+        .await
+        .map_err(|e| WorkError::History(e.to_string()))?
+    else {
+        return Ok(None);
+    };
+    let object = history
+        .get_storage()
+        .get_json(&hash)
+        .await
+        .map_err(|e| WorkError::History(e.to_string()))?;
+    Ok(Some(object))
+}
+
+/// The task whose id is `id` or starts with it, with its full id.
+async fn load_task(history: &HistoryManager, id: &str) -> Result<(String, Task), WorkError> {
+    let full_id = history
+        .resolve_prefix(id)
+        .await
+        .map_err(|e| WorkError::Task(e.to_string()))?;
+    match load_object(history, "task", &full_id).await? {
+        Some(task) => Ok((full_id, task)),
+        None => Err(WorkError::Task(format!("'{id}' is not a task"))),
+    }
+}
+
+/// Move `task` to `status` on behalf of `actor` and store it.
+async fn update_status(
+    history: &HistoryManager,
+    mut task: Task,
+    status: TaskStatus,
+    actor: &ActorRef,
+) -> Result<Task, WorkError> {
+    check_status_transition(&task, &status).map_err(WorkError::Task)?;
+    task.set_status(status);
+    let task =
+        attribute_status_change(task, actor).map_err(|e| WorkError::History(e.to_string()))?;
+    history
+        .get_storage()
+        .put_tracked(&task, history)
+        .await
+        .map_err(|e| WorkError::History(e.to_string()))?;
+    Ok(task)
+}
+
+/// The profile's prompt, then the intent's goal and the other tasks planned for it, and the
+/// profile's language and tone last.
+async fn work_preamble(
+    history: &HistoryManager,
+    profile: &AgentProfile,
+    task_id: &str,
+    task: &Task,
+) -> Result<String, WorkError> {
+    let mut preamble = profile.system_prompt.trim_end().to_string();
+    if let Some(intent_id) = task.intent() {
+        let intent_id = intent_id.to_string();
+        if let Some(intent) = load_object::<Intent>(history, "intent", &intent_id).await? {
+            preamble.push_str(&format!("\n\n## Goal\n\n{}", intent.prompt()));
+            if let Some(content) = intent.content() {
+                preamble.push_str(&format!("\n\n{content}"));
+            }
+        }
+
+        let mut siblings = Vec::new();
+        let objects = history
+            .list_objects("task")
+            .await
+            .map_err(|e| WorkError::History(e.to_string()))?;
+        for (id, hash) in objects {
+            if id == task_id {
+                continue;
+            }
+            let Ok(sibling) = history.get_storage().get_json::<Task>(&hash).await else {
+                continue;
+            };
+            if sibling.intent().is_some_and(|i| i.to_string() == intent_id) {
+                siblings.push(format!(
+                    "- [{}] {} ({id})",
+                    sibling.status(),
+                    sibling.title()
+                ));
+            }
+        }
+        if !siblings.is_empty() {
+            preamble.push_str("\n\n## Other Tasks of This Goal\n\n");
+            preamble.push_str(&siblings.join("\n"));
+        }
+    }
+    preamble.push_str("\n\n");
+    preamble.push_str(FINISH_INSTRUCTIONS);
+    if let Some(instruction) = profile.style_instruction() {
+        preamble.push_str("\n\n");
+        preamble.push_str(&instruction);
+    }
+    Ok(preamble)
+}
+
+fn task_prompt(task_id: &str, task: &Task) -> String {
+    let mut prompt = format!("Work on task {task_id}: {}", task.title());
+    if let Some(description) = task.description() {
+        prompt.push_str(&format!("\n\n{description}"));
+    }
+    for (heading, items) in [
+        ("Constraints", task.constraints()),
+        ("Acceptance criteria", task.acceptance_criteria()),
+    ] {
+        if !items.is_empty() {
+            prompt.push_str(&format!("\n\n{heading}:"));
+            for item in items {
+                prompt.push_str(&format!("\n- {item}"));
+            }
+        }
+    }
+    prompt
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::{
+        internal::ai::{
+            agent::runtime::step_history::AGENT_RUN_TYPE,
+            eval::{ReplayModel, ReplayToolCall, ReplayTurn},
+            task::STATUS_UPDATED_BY_TAG,
+        },
+        utils::storage::local::LocalStorage,
+    };
+
+    fn call(name: &str, arguments: serde_json::Value) -> ReplayTurn {
+        ReplayTurn {
+            text: None,
+            tool_calls: vec![ReplayToolCall {
+                name: name.to_string(),
+                arguments,
+                result: None,
+            }],
+        }
+    }
+
+    fn answer(text: &str) -> ReplayTurn {
+        ReplayTurn {
+            text: Some(text.to_string()),
+            tool_calls: Vec::new(),
+        }
+    }
+
+    async fn stored_task(history: &HistoryManager, id: &str) -> Task {
+        load_object(history, "task", id).await.unwrap().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_work_drives_tasks_to_done_and_blocked() {
+        let dir = TempDir::new().unwrap();
+        let repo_path = dir.path().join(".libra");
+        std::fs::create_dir(&repo_path).unwrap();
+        let storage = Arc::new(LocalStorage::new(repo_path.join("objects")));
+        let history = Arc::new(HistoryManager::new(storage.clone(), repo_path));
+
+        let planner = ActorRef::agent("planner").unwrap();
+        let intent = Intent::new(ActorRef::human("alice").unwrap(), "add caching").unwrap();
+        storage.put_tracked(&intent, &history).await.unwrap();
+        let mut tasks = Vec::new();
+        for title in ["cache layer", "cache docs", "cache metrics"] {
+            let mut task = Task::new(planner.clone(), title, None).unwrap();
+            task.set_intent(Some(intent.header().object_id()));
+            task.add_acceptance_criterion("tests pass");
+            storage.put_tracked(&task, &history).await.unwrap();
+            tasks.push(task.header().object_id().to_string());
+        }
+
+        let profile = embedded_profile();
+        let task = stored_task(&history, &tasks[0]).await;
+        let preamble = work_preamble(&history, &profile, &tasks[0], &task)
+            .await
+            .unwrap();
+        assert!(preamble.contains("## Goal\n\nadd caching"), "{preamble}");
+        assert!(preamble.contains(&format!("- [draft] cache docs ({})", tasks[1])));
+        assert!(!preamble.contains("] cache layer ("));
+        assert!(task_prompt(&tasks[0], &task).ends_with("Acceptance criteria:\n- tests pass"));
+
+        // Done through the finish tool.
+        let model = ReplayModel::new(vec![
+            call(
+                "ai_history",
+                json!({ "operation": "get_task", "id": tasks[0] }),
+            ),
+            call(
+                "finish_task",
+                json!({ "status": "done", "summary": "added the cache layer" }),
+            ),
+            answer("Added the cache layer."),
+        ]);
+        let work = TaskWork::default();
+        let outcome = work
+            .run(
+                &model,
+                &profile,
+                history.clone(),
+                dir.path().into(),
+                &tasks[0],
+            )
+            .await
+            .unwrap();
+        assert_eq!(outcome.completion.status, CompletionStatus::Done);
+        assert_eq!(outcome.status, Some(TaskStatus::Done));
+        let task = stored_task(&history, &tasks[0]).await;
+        assert_eq!(task.status(), &TaskStatus::Done);
+        assert_eq!(
+            task.header().tags().get(STATUS_UPDATED_BY_TAG).unwrap(),
+            "agent:libra-work"
+        );
+
+        // Blocked through a final JSON block; tasks have no blocked status.
+        let model = ReplayModel::new(vec![answer(
+            "I could not finish.\n\n```json\n{\"status\": \"blocked\", \"summary\": \"needs an API key\"}\n```",
+        )]);
+        let outcome = work
+            .run(
+                &model,
+                &profile,
+                history.clone(),
+                dir.path().into(),
+                &tasks[1],
+            )
+            .await
+            .unwrap();
+        assert_eq!(outcome.completion.summary, "needs an API key");
+        assert_eq!(
+            stored_task(&history, &tasks[1]).await.status(),
+            &TaskStatus::Failed
+        );
+        let err = work
+            .run(
+                &model,
+                &profile,
+                history.clone(),
+                dir.path().into(),
+                &tasks[1],
+            )
+            .await
+            .unwrap_err();
+        assert!(err.to_string().ends_with("is already failed"), "{err}");
+
+        let runs = history.list_objects(AGENT_RUN_TYPE).await.unwrap();
+        let mut records = Vec::new();
+        for (_, hash) in runs {
+            let record: AgentRunRecord = storage.get_json(&hash).await.unwrap();
+            records.push((record.task.unwrap(), record.answer));
+        }
+        records.sort();
+        let mut expected = vec![
+            (
+                tasks[0].clone(),
+                r#"{"status":"done","summary":"added the cache layer"}"#.to_string(),
+            ),
+            (
+                tasks[1].clone(),
+                r#"{"status":"blocked","summary":"needs an API key"}"#.to_string(),
+            ),
+        ];
+        expected.sort();
+        assert_eq!(records, expected);
+
+        // A dry run cannot change statuses, even through ai_history, and records nothing.
+        let model = ReplayModel::new(vec![
+            call(
+                "ai_history",
+                json!({ "operation": "update_task_status", "id": tasks[2], "status": "done" }),
+            ),
+            call(
+                "finish_task",
+                json!({ "status": "done", "summary": "nothing to do" }),
+            ),
+            answer("Done."),
+        ]);
+        let dry_run = TaskWork {
+            dry_run: true,
+            ..Default::default()
+        };
+        let outcome = dry_run
+            .run(
+                &model,
+                &profile,
+                history.clone(),
+                dir.path().into(),
+                &tasks[2],
+            )
+            .await
+            .unwrap();
+        assert_eq!(outcome.completion.status, CompletionStatus::Done);
+        assert_eq!((outcome.status, outcome.run_id), (None, None));
+        assert_eq!(
+            stored_task(&history, &tasks[2]).await.status(),
+            &TaskStatus::Draft
+        );
+        assert_eq!(history.list_objects(AGENT_RUN_TYPE).await.unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_completion_from_answer() {
+        let done = TaskCompletion {
+            status: CompletionStatus::Done,
+            summary: "ok".to_string(),
+        };
+        assert_eq!(
+            TaskCompletion::from_answer("All set.\n{\"status\": \"done\", \"summary\": \"ok\"}"),
+            Some(done.clone())
+        );
+        assert_eq!(
+            TaskCompletion::from_answer("```\n{\"status\": \"done\", \"summary\": \"ok\"}\n```"),
+            Some(done)
+        );
+        assert_eq!(TaskCompletion::from_answer("All set."), None);
+        assert_eq!(
+            TaskCompletion::from_answer(r#"{"status": "finished", "summary": "ok"}"#),
+            None
+        );
+    }
+}
@@ -336,6 +336,7 @@ impl Config {
         let config_entries = config::Entity::find()
             .filter(config::Column::Configuration.eq("branch"))
             .filter(config::Column::Name.eq(name))
+            .filter(config::Column::Key.is_in(["merge", "remote"]))
             .all(db)
             .await
             .unwrap();
@@ -0,0 +1,116 @@
+//! Config-driven default values for command-line flags (e.g. `shortlog.numbered`).
+//!
+//! Each command that supports configured defaults implements [`ApplyConfigDefaults`] and
+//! declares which config keys feed which of its arguments. After clap has parsed the
+//! command line, [`apply_config_defaults`] fills in every declared argument the user did
+//! not pass explicitly. Precedence, highest first:
+//!
+//! `command line > environment (LIBRA_CONFIG_<SECTION>_<KEY>) > local > global > system`
+
+use clap::{ArgMatches, parser::ValueSource};
+
+use crate::command::config::get_config_cascaded;
+
+/// A single config key that provides a default for a command-line argument.
+#[derive(Debug, Clone, Copy)]
+pub struct ConfigDefault {
+    /// Config key in `section.key` form, e.g. `shortlog.numbered`.
+    pub key: &'static str,
+    /// Clap id of the argument the value feeds.
+    pub arg: &'static str,
+    /// Clap id of the argument that explicitly negates `arg` (e.g. `--no-numbered`), if any.
+    /// Passing it on the command line suppresses the configured value.
+    pub negation: Option<&'static str>,
+}
+
+/// Implemented by argument structs whose flags can default from configuration.
+pub trait ApplyConfigDefaults {
+    /// Config keys consulted by this command, in application order.
+    const CONFIG_DEFAULTS: &'static [ConfigDefault];
+
+    /// Apply a configured `value` to the argument with clap id `arg`.
+    fn apply_config_default(&mut self, arg: &str, value: &str) -> Result<(), String>;
+}
+
+/// Fill arguments not given on the command line from the environment or config store.
+///
+/// `matches` must be the [`ArgMatches`] the arguments were parsed from; it is used to tell
+/// explicit flags apart from clap defaults.
+pub async fn apply_config_defaults<T: ApplyConfigDefaults>(
+    args: &mut T,
+    matches: &ArgMatches,
+) -> Result<(), String> {
+    for default in T::CONFIG_DEFAULTS {
+        let negated = default
+            .negation
+            .is_some_and(|negation| is_explicit(matches, negation));
+        if negated || is_explicit(matches, default.arg) {
+            continue;
+        }
+        if let Some(value) = lookup(default.key).await {
+            args.apply_config_default(default.arg, &value)
+                .map_err(|e| format!("bad config value for '{}': {e}", default.key))?;
+        }
+    }
+    Ok(())
+}
+
+/// Resolve a config key from the environment first, then the cascaded config scopes.
+///
+/// Like `libra config`, a key with more than one dot is read as `section.name.key`, e.g.
+/// `ai.precommit.severity`.
+pub async fn lookup(key: &str) -> Option<String> {
+    if let Ok(value) = std::env::var(env_var_name(key)) {
+        return Some(value);
+    }
+    let (section, rest) = key.split_once('.')?;
+    let (name, key) = match rest.rsplit_once('.') {
+        Some((name, key)) => (Some(name), key),
+        None => (None, rest),
+    };
+    get_config_cascaded(section, name, key).await.ok().flatten()
+}
+
+/// Environment variable that overrides `key`, e.g. `LIBRA_CONFIG_SHORTLOG_NUMBERED`.
+pub fn env_var_name(key: &str) -> String {
+    format!(
+        "LIBRA_CONFIG_{}",
+        key.replace(['.', '-'], "_").to_ascii_uppercase()
+    )
+}
+
+/// Parse a git-style boolean config value.
+pub fn parse_bool(value: &str) -> Result<bool, String> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "true" | "yes" | "on" | "1" => Ok(true),
+        "false" | "no" | "off" | "0" | "" => Ok(false),
+        other => Err(format!("'{other}' is not a boolean")),
+    }
+}
+
+fn is_explicit(matches: &ArgMatches, id: &str) -> bool {
+    matches.value_source(id) == Some(ValueSource::CommandLine)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_env_var_name() {
+        assert_eq!(
+            env_var_name("shortlog.numbered"),
+            "LIBRA_CONFIG_SHORTLOG_NUMBERED"
+        );
+        assert_eq!(env_var_name("log.show-sig"), "LIBRA_CONFIG_LOG_SHOW_SIG");
+    }
+
+    #[test]
+    fn test_parse_bool() {
+        assert_eq!(parse_bool("true"), Ok(true));
+        assert_eq!(parse_bool("Yes"), Ok(true));
+        assert_eq!(parse_bool("0"), Ok(false));
+        assert_eq!(parse_bool("off"), Ok(false));
+        assert!(parse_bool("maybe").is_err());
+    }
+}
@@ -0,0 +1,336 @@
+//! Commit filters shared by `log` and `shortlog`, so both select commits the same way.
+//!
+//! A [`CommitFilter`] is built from independent predicates. [`CommitFilter::matches_cheap`]
+//! checks the ones that only read the commit object (date window, merges, author) and
+//! [`CommitFilter::matches_paths`] the ones that diff trees; [`CommitFilter::matches`]
+//! runs them in that order, so a commit outside the date window is never diffed.
+
+use std::path::PathBuf;
+
+use clap::ValueEnum;
+use git_internal::internal::object::commit::Commit;
+use regex::{Regex, RegexBuilder};
+use wax::{Any, Pattern};
+
+use crate::{
+    command::log::{FileChange, get_changed_files_for_commit},
+    utils::util,
+};
+
+/// Timestamp(s) checked by the `since`/`until` window.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum DateMatch {
+    /// Either the author or the committer timestamp is in the window
+    Any,
+    /// The author timestamp is in the window
+    Author,
+    /// The committer timestamp is in the window, like `git log`
+    #[default]
+    Committer,
+}
+
+/// Selects commits by date, author, parent count and touched paths; see the module docs.
+#[derive(Debug, Default)]
+pub struct CommitFilter<'g> {
+    since: Option<i64>,
+    until: Option<i64>,
+    date_match: DateMatch,
+    author: Option<Regex>,
+    merges: Option<bool>,
+    paths: Vec<PathBuf>,
+    path_glob: Option<Any<'g>>,
+    max_count: Option<usize>,
+}
+
+impl<'g> CommitFilter<'g> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep commits at or after `since` (seconds since the epoch).
+    pub fn since(mut self, since: Option<i64>) -> Self {
+        self.since = since;
+        self
+    }
+
+    /// Keep commits at or before `until` (seconds since the epoch).
+    pub fn until(mut self, until: Option<i64>) -> Self {
+        self.until = until;
+        self
+    }
+
+    /// Which timestamp [`since`](Self::since) and [`until`](Self::until) apply to.
+    pub fn date_match(mut self, date_match: DateMatch) -> Self {
+        self.date_match = date_match;
+        self
+    }
+
+    /// Keep commits whose author `name <email>` matches `pattern`, a case-insensitive
+    /// regular expression. A plain name matches as a substring.
+    pub fn author(mut self, pattern: Option<&str>) -> Result<Self, String> {
+        self.author = pattern
+            .map(|pattern| {
+                RegexBuilder::new(pattern)
+                    .case_insensitive(true)
+                    .build()
+                    .map_err(|e| format!("invalid --author pattern '{pattern}': {e}"))
+            })
+            .transpose()?;
+        Ok(self)
+    }
+
+    /// `Some(true)` keeps only merge commits, `Some(false)` only non-merges.
+    pub fn merges(mut self, merges: Option<bool>) -> Self {
+        self.merges = merges;
+        self
+    }
+
+    /// Keep commits that change one of `paths` or anything below them, compared with their
+    /// first parent.
+    pub fn paths(mut self, paths: Vec<PathBuf>) -> Self {
+        self.paths = paths;
+        self
+    }
+
+    /// Keep commits that change a path matching `glob`, compared with their first parent.
+    /// A path also matches when one of its parent directories does.
+    pub fn path_glob(mut self, glob: Option<Any<'g>>) -> Self {
+        self.path_glob = glob;
+        self
+    }
+
+    /// Stop after this many matching commits; see [`limit_reached`](Self::limit_reached).
+    pub fn max_count(mut self, max_count: Option<usize>) -> Self {
+        self.max_count = max_count;
+        self
+    }
+
+    /// The paths set by [`paths`](Self::paths).
+    pub fn path_list(&self) -> &[PathBuf] {
+        &self.paths
+    }
+
+    /// Replace the paths, e.g. when `log --follow` crosses a rename.
+    pub fn set_paths(&mut self, paths: Vec<PathBuf>) {
+        self.paths = paths;
+    }
+
+    /// Whether any predicate needs a tree diff.
+    pub fn has_path_filter(&self) -> bool {
+        !self.paths.is_empty() || self.path_glob.is_some()
+    }
+
+    /// Whether a walk that has already found `matched` commits can stop.
+    pub fn limit_reached(&self, matched: usize) -> bool {
+        self.max_count.is_some_and(|max| matched >= max)
+    }
+
+    /// The predicates that only read the commit: date window, merges, then author.
+    pub fn matches_cheap(&self, commit: &Commit) -> bool {
+        self.in_window(commit) && self.merge_matches(commit) && self.author_matches(commit)
+    }
+
+    /// The path predicates. `changes` may hold the first-parent changes of `commit` already
+    /// restricted to [`path_list`](Self::path_list), saving a second diff.
+    pub async fn matches_paths(&self, commit: &Commit, changes: Option<&[FileChange]>) -> bool {
+        if !self.paths.is_empty() {
+            let touched = match changes {
+                Some(changes) => !changes.is_empty(),
+                None => !get_changed_files_for_commit(commit, &self.paths)
+                    .await
+                    .is_empty(),
+            };
+            if !touched {
+                return false;
+            }
+        }
+        if let Some(glob) = &self.path_glob {
+            return get_changed_files_for_commit(commit, &[])
+                .await
+                .iter()
+                .any(|change| change.path.ancestors().any(|p| glob.is_match(p)));
+        }
+        true
+    }
+
+    /// The path predicates against `changed`, every path the first-parent diff of a commit
+    /// touches, for callers that diff the commit themselves.
+    pub fn matches_changed_paths(&self, changed: &[PathBuf]) -> bool {
+        let in_paths = |path: &PathBuf| self.paths.iter().any(|p| util::is_sub_path(path, p));
+        (self.paths.is_empty() || changed.iter().any(in_paths))
+            && self.path_glob.as_ref().is_none_or(|glob| {
+                changed
+                    .iter()
+                    .any(|path| path.ancestors().any(|p| glob.is_match(p)))
+            })
+    }
+
+    /// All predicates, cheap ones first.
+    pub async fn matches(&self, commit: &Commit, changes: Option<&[FileChange]>) -> bool {
+        self.matches_cheap(commit) && self.matches_paths(commit, changes).await
+    }
+
+    fn in_window(&self, commit: &Commit) -> bool {
+        let in_window = |ts: usize| {
+            let ts = ts as i64;
+            self.since.is_none_or(|since| ts >= since) && self.until.is_none_or(|until| ts <= until)
+        };
+        match self.date_match {
+            DateMatch::Any => {
+                in_window(commit.author.timestamp) || in_window(commit.committer.timestamp)
+            }
+            DateMatch::Author => in_window(commit.author.timestamp),
+            DateMatch::Committer => in_window(commit.committer.timestamp),
+        }
+    }
+
+    fn merge_matches(&self, commit: &Commit) -> bool {
+        self.merges
+            .is_none_or(|merges| merges == (commit.parent_commit_ids.len() > 1))
+    }
+
+    fn author_matches(&self, commit: &Commit) -> bool {
+        self.author.as_ref().is_none_or(|author| {
+            author.is_match(&format!("{} <{}>", commit.author.name, commit.author.email))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use git_internal::{hash::ObjectHash, internal::object::signature::Signature};
+    use serial_test::serial;
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::{
+        command::{
+            add::{self, AddArgs},
+            commit::{self, CommitArgs},
+            load_object,
+        },
+        internal::head::Head,
+        utils::test,
+    };
+
+    fn signature(kind: &str, name: &str, timestamp: i64) -> Signature {
+        let email = name.to_lowercase();
+        Signature::from_data(
+            format!("{kind} {name} <{email}@oa.org> {timestamp} +0000").into_bytes(),
+        )
+        .unwrap()
+    }
+
+    fn commit(name: &str, authored: i64, committed: i64, parents: usize) -> Commit {
+        Commit::new(
+            signature("author", name, authored),
+            signature("committer", name, committed),
+            ObjectHash::new(&[1; 20]),
+            vec![ObjectHash::new(&[2; 20]); parents],
+            "subject",
+        )
+    }
+
+    #[test]
+    fn test_date_window_follows_date_match() {
+        let late_commit = commit("Alice", 100, 200, 1);
+        let since = |date_match| CommitFilter::new().since(Some(150)).date_match(date_match);
+        assert!(since(DateMatch::Committer).matches_cheap(&late_commit));
+        assert!(!since(DateMatch::Author).matches_cheap(&late_commit));
+        assert!(since(DateMatch::Any).matches_cheap(&late_commit));
+
+        let until = CommitFilter::new().until(Some(150));
+        assert!(!until.matches_cheap(&late_commit));
+        assert!(
+            until
+                .date_match(DateMatch::Author)
+                .matches_cheap(&late_commit)
+        );
+    }
+
+    #[test]
+    fn test_author_is_a_case_insensitive_regex() {
+        let alice = commit("Alice", 0, 0, 1);
+        let author = |pattern| CommitFilter::new().author(Some(pattern)).unwrap();
+        assert!(author("alice").matches_cheap(&alice));
+        assert!(author("@OA.org>$").matches_cheap(&alice));
+        assert!(!author("^bob").matches_cheap(&alice));
+        assert!(CommitFilter::new().author(Some("(")).is_err());
+        assert!(
+            CommitFilter::new()
+                .author(None)
+                .unwrap()
+                .matches_cheap(&alice)
+        );
+    }
+
+    #[test]
+    fn test_merges_and_max_count() {
+        let merge = commit("Alice", 0, 0, 2);
+        let plain = commit("Alice", 0, 0, 1);
+        let merges = |only| CommitFilter::new().merges(Some(only));
+        assert!(merges(true).matches_cheap(&merge));
+        assert!(!merges(true).matches_cheap(&plain));
+        assert!(merges(false).matches_cheap(&plain));
+        assert!(!merges(false).matches_cheap(&merge));
+
+        let limited = CommitFilter::new().max_count(Some(2));
+        assert!(!limited.limit_reached(1));
+        assert!(limited.limit_reached(2));
+        assert!(!CommitFilter::new().limit_reached(usize::MAX));
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_path_prefix_and_glob() {
+        let temp = tempdir().unwrap();
+        test::setup_with_new_libra_in(temp.path()).await;
+        let _guard = test::ChangeDirGuard::new(temp.path());
+        let mut commits = Vec::new();
+        for path in ["src/lib.rs", "docs/guide.md"] {
+            fs::create_dir_all(temp.path().join(path).parent().unwrap()).unwrap();
+            fs::write(temp.path().join(path), path).unwrap();
+            add::execute(AddArgs {
+                pathspec: vec![path.to_string()],
+                all: false,
+                update: false,
+                refresh: false,
+                force: false,
+                verbose: false,
+                dry_run: false,
+                ignore_errors: false,
+            })
+            .await;
+            commit::execute(CommitArgs {
+                message: Some(format!("add {path}")),
+                ..Default::default()
+            })
+            .await;
+            let id = Head::current_commit().await.unwrap();
+            commits.push(load_object::<Commit>(&id).unwrap());
+        }
+
+        let prefix = CommitFilter::new().paths(vec![PathBuf::from("src")]);
+        assert!(prefix.has_path_filter());
+        assert!(prefix.matches_paths(&commits[0], None).await);
+        assert!(!prefix.matches_paths(&commits[1], None).await);
+        // Precomputed changes are trusted instead of diffing again.
+        assert!(!prefix.matches_paths(&commits[0], Some(&[])).await);
+
+        let glob = CommitFilter::new().path_glob(Some(wax::any(["docs"]).unwrap()));
+        assert!(!glob.matches_paths(&commits[0], None).await);
+        assert!(glob.matches_paths(&commits[1], None).await);
+        let glob = CommitFilter::new().path_glob(Some(wax::any(["**/*.rs"]).unwrap()));
+        assert!(glob.matches_paths(&commits[0], None).await);
+        assert!(!CommitFilter::new().has_path_filter());
+
+        // Paths diffed by the caller go through the same predicates.
+        let changed = [PathBuf::from("src/lib.rs")];
+        assert!(prefix.matches_changed_paths(&changed));
+        assert!(glob.matches_changed_paths(&changed));
+        assert!(!glob.matches_changed_paths(&[PathBuf::from("docs/guide.md")]));
+        assert!(CommitFilter::new().matches_changed_paths(&[]));
+    }
+}
@@ -0,0 +1,289 @@
+//! Export of the commit graph shown by `log` as Graphviz DOT or a Mermaid flowchart.
+//!
+//! Every commit becomes a node labeled with its abbreviated hash and subject, with an edge
+//! to each parent that is part of the export; parents filtered out by `--max-count` or the
+//! other filters are left out rather than drawn as dangling nodes. Edges point from child to
+//! parent, newest commit on top. Edges to second and later parents, the ones a merge brought
+//! in, are dashed (DOT) or dotted (Mermaid) so merges stand out.
+//!
+//! Decorations become separate colored nodes linked to their commit, in the colors `log`
+//! uses on a terminal: cyan for HEAD, green for local branches, red for remote-tracking
+//! branches and yellow for tags.
+//!
+//! Labels are escaped for each format: DOT takes backslash escapes inside quoted strings,
+//! while Mermaid only understands entity codes such as `#quot;`.
+
+use std::io::{self, Write};
+
+use clap::ValueEnum;
+
+/// The format `log --output` writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum GraphFormat {
+    /// Graphviz DOT, for `dot -Tsvg`
+    Dot,
+    /// A Mermaid flowchart, for Markdown renderers
+    Mermaid,
+}
+
+/// What a decoration names, which decides its color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DecorationKind {
+    Head,
+    Tag,
+    Remote,
+    Branch,
+}
+
+impl DecorationKind {
+    /// DOT fill color.
+    fn color(self) -> &'static str {
+        match self {
+            DecorationKind::Head => "cyan3",
+            DecorationKind::Tag => "gold",
+            DecorationKind::Remote => "firebrick1",
+            DecorationKind::Branch => "palegreen3",
+        }
+    }
+
+    /// Mermaid class name.
+    fn class(self) -> &'static str {
+        match self {
+            DecorationKind::Head => "head",
+            DecorationKind::Tag => "tag",
+            DecorationKind::Remote => "remote",
+            DecorationKind::Branch => "branch",
+        }
+    }
+}
+
+/// A ref label attached to a commit, such as `HEAD -> main` or `tag: v1.0`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decoration {
+    pub kind: DecorationKind,
+    pub label: String,
+}
+
+/// One exported commit.
+#[derive(Debug, Clone)]
+pub struct GraphNode {
+    /// Abbreviated hash, also used as the node id.
+    pub id: String,
+    pub subject: String,
+    /// Abbreviated hashes of the parents, first parent first.
+    pub parents: Vec<String>,
+    pub decorations: Vec<Decoration>,
+}
+
+/// Write `nodes`, newest first, to `writer` in `format`.
+pub fn write_graph(
+    writer: &mut impl Write,
+    format: GraphFormat,
+    nodes: &[GraphNode],
+) -> io::Result<()> {
+    match format {
+        GraphFormat::Dot => write_dot(writer, nodes),
+        GraphFormat::Mermaid => write_mermaid(writer, nodes),
+    }
+}
+
+/// `(parent, is_merge_edge)` for the parents of `node` that are exported.
+fn exported_parents<'a>(
+    node: &'a GraphNode,
+    nodes: &'a [GraphNode],
+) -> impl Iterator<Item = (&'a str, bool)> + 'a {
+    node.parents
+        .iter()
+        .enumerate()
+        .filter(|(_, parent)| nodes.iter().any(|n| &n.id == *parent))
+        .map(|(i, parent)| (parent.as_str(), i > 0))
+}
+
+/// `text` escaped for a double-quoted DOT string.
+pub fn escape_dot(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' | '\r' => out.push(' '),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// `text` escaped for a double-quoted Mermaid label.
+pub fn escape_mermaid(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            // Entity codes start with `#`, so a literal one is escaped first.
+            '#' => out.push_str("#35;"),
+            '"' => out.push_str("#quot;"),
+            '<' => out.push_str("#lt;"),
+            '>' => out.push_str("#gt;"),
+            '\n' | '\r' => out.push(' '),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn write_dot(writer: &mut impl Write, nodes: &[GraphNode]) -> io::Result<()> {
+    writeln!(writer, "digraph commits {{")?;
+    writeln!(writer, "  node [shape=box, fontname=\"monospace\"];")?;
+    for node in nodes {
+        writeln!(
+            writer,
+            "  \"{}\" [label=\"{}\"];",
+            node.id,
+            escape_dot(&format!("{} {}", node.id, node.subject))
+        )?;
+    }
+    for node in nodes {
+        for (parent, merge) in exported_parents(node, nodes) {
+            let style = if merge {
+                " [style=dashed, color=\"gray40\"]"
+            } else {
+                ""
+            };
+            writeln!(writer, "  \"{}\" -> \"{parent}\"{style};", node.id)?;
+        }
+    }
+    for node in nodes {
+        for decoration in &node.decorations {
+            let label = escape_dot(&decoration.label);
+            writeln!(
+                writer,
+                "  \"ref:{label}\" [label=\"{label}\", shape=cds, style=filled, fillcolor=\"{}\"];",
+                decoration.kind.color()
+            )?;
+            writeln!(
+                writer,
+                "  \"ref:{label}\" -> \"{}\" [style=dotted, arrowhead=none];",
+                node.id
+            )?;
+            writeln!(
+                writer,
+                "  {{ rank=same; \"ref:{label}\"; \"{}\"; }}",
+                node.id
+            )?;
+        }
+    }
+    writeln!(writer, "}}")
+}
+
+fn write_mermaid(writer: &mut impl Write, nodes: &[GraphNode]) -> io::Result<()> {
+    writeln!(writer, "flowchart TB")?;
+    for node in nodes {
+        writeln!(
+            writer,
+            "  c{}[\"{}\"]",
+            node.id,
+            escape_mermaid(&format!("{} {}", node.id, node.subject))
+        )?;
+    }
+    for node in nodes {
+        for (parent, merge) in exported_parents(node, nodes) {
+            let arrow = if merge { "-.->" } else { "-->" };
+            writeln!(writer, "  c{} {arrow} c{parent}", node.id)?;
+        }
+    }
+    let mut refs = 0;
+    for node in nodes {
+        for decoration in &node.decorations {
+            writeln!(
+                writer,
+                "  r{refs}([\"{}\"]):::{}",
+                escape_mermaid(&decoration.label),
+                decoration.kind.class()
+            )?;
+            writeln!(writer, "  r{refs} --- c{}", node.id)?;
+            refs += 1;
+        }
+    }
+    if refs > 0 {
+        writeln!(writer, "  classDef head fill:#00acc1,color:#fff")?;
+        writeln!(writer, "  classDef tag fill:#fdd835,color:#000")?;
+        writeln!(writer, "  classDef remote fill:#e53935,color:#fff")?;
+        writeln!(writer, "  classDef branch fill:#43a047,color:#fff")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(id: &str, subject: &str, parents: &[&str]) -> GraphNode {
+        GraphNode {
+            id: id.to_string(),
+            subject: subject.to_string(),
+            parents: parents.iter().map(|p| p.to_string()).collect(),
+            decorations: Vec::new(),
+        }
+    }
+
+    fn render(format: GraphFormat, nodes: &[GraphNode]) -> String {
+        let mut buf = Vec::new();
+        write_graph(&mut buf, format, nodes).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn test_escaping() {
+        let subject = r#"Say "hi" to <C:\tmp> #1"#;
+        assert_eq!(escape_dot(subject), r#"Say \"hi\" to <C:\\tmp> #1"#);
+        assert_eq!(
+            escape_mermaid(subject),
+            r"Say #quot;hi#quot; to #lt;C:\tmp#gt; #35;1"
+        );
+    }
+
+    #[test]
+    fn test_merge_edges_and_missing_parents() {
+        let mut merge = node("ccc", "Merge", &["bbb", "aaa"]);
+        merge.decorations.push(Decoration {
+            kind: DecorationKind::Head,
+            label: "HEAD -> main".to_string(),
+        });
+        // `zzz` was filtered out, so `aaa` gets no edge to it.
+        let nodes = [
+            merge,
+            node("bbb", "Second", &["aaa"]),
+            node("aaa", "First", &["zzz"]),
+        ];
+
+        let dot = render(GraphFormat::Dot, &nodes);
+        assert!(dot.contains("  \"ccc\" -> \"bbb\";\n"), "{dot}");
+        assert!(
+            dot.contains("  \"ccc\" -> \"aaa\" [style=dashed, color=\"gray40\"];\n"),
+            "{dot}"
+        );
+        assert!(!dot.contains("zzz"), "{dot}");
+        assert!(
+            dot.contains("\"ref:HEAD -> main\" [label=\"HEAD -> main\", shape=cds, style=filled, fillcolor=\"cyan3\"]"),
+            "{dot}"
+        );
+
+        let mermaid = render(GraphFormat::Mermaid, &nodes);
+        assert_eq!(
+            mermaid,
+            "flowchart TB
+  cccc[\"ccc Merge\"]
+  cbbb[\"bbb Second\"]
+  caaa[\"aaa First\"]
+  cccc --> cbbb
+  cccc -.-> caaa
+  cbbb --> caaa
+  r0([\"HEAD -#gt; main\"]):::head
+  r0 --- cccc
+  classDef head fill:#00acc1,color:#fff
+  classDef tag fill:#fdd835,color:#000
+  classDef remote fill:#e53935,color:#fff
+  classDef branch fill:#43a047,color:#fff
+"
+        );
+    }
+}
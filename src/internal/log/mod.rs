@@ -1,3 +1,5 @@
-//! Log helpers for date parsing and output formatting shared by the log command.
+//! Log helpers for date parsing, commit filtering and output formatting shared by the log and shortlog commands.
 pub mod date_parser;
+pub mod filter;
 pub mod formatter;
+pub mod graph_export;
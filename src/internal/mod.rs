@@ -1,13 +1,17 @@
-//! Internal layer exports for branch, config, database, HEAD, protocol clients, reflog management, and tag handling.
+//! Internal layer exports for branch, config, config-driven command defaults, database, HEAD, protocol clients, reflog management, atomic ref transactions, interactive rebase todo lists, branch protection rules, and tag handling.
 
 pub mod ai;
 pub mod branch;
 pub mod config;
+pub mod config_defaults;
 pub mod db;
 pub mod head;
 pub mod log;
 pub mod model;
+pub mod protect;
 pub mod protocol;
+pub mod rebase_todo;
+pub mod ref_transaction;
 pub mod reflog;
 pub mod tag;
 pub mod tui;
@@ -0,0 +1,165 @@
+//! Branch protection rules checked before destructive operations.
+//!
+//! A branch is protected by setting `branch.<pattern>.protected` to true, where the pattern
+//! is either a branch name (`branch.main.protected`) or a glob with the same syntax as the
+//! `--path` globs of `log` and `shortlog`: `*` and `?` stay within one path component, `**`
+//! crosses slashes, and `[...]` matches a character class (`branch.release/*.protected`).
+//! An exact-name entry wins over every glob, so `branch.release/scratch.protected false`
+//! exempts one branch from `release/*`.
+//!
+//! Commands that delete or rewrite a branch call [`check`] with the branch and a short verb
+//! for what they are about to do. Without `--override-protection` the call fails with a
+//! [`ProtectionError`] naming the rule; with it, the command goes ahead and passes the rule to
+//! [`annotate`] so the reflog entry it writes records the override. A protected pattern that
+//! is not a valid glob fails every check, override or not, until it is fixed or unset.
+
+use std::fmt::{self, Display, Formatter};
+
+use thiserror::Error;
+use wax::{Glob, Pattern};
+
+use crate::internal::{config::Config, config_defaults::parse_bool};
+
+/// The flag every protected operation accepts.
+pub const OVERRIDE_FLAG: &str = "--override-protection";
+
+/// A `branch.<pattern>.protected` entry that matched a branch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProtectionRule {
+    pub pattern: String,
+}
+
+impl Display for ProtectionRule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "branch.{}.protected", self.pattern)
+    }
+}
+
+/// Returned by [`check`] when a branch may not be touched.
+#[derive(Debug, Error)]
+pub enum ProtectionError {
+    /// The branch is protected and the override was not given.
+    #[error(
+        "refusing to {operation} protected branch '{branch}' (rule {rule}); use {OVERRIDE_FLAG} to proceed anyway"
+    )]
+    Protected {
+        branch: String,
+        operation: String,
+        rule: ProtectionRule,
+    },
+
+    /// A protected pattern is not a valid glob, so it is unknown which branches it covers.
+    #[error("invalid protection rule {rule}: {message}; fix or unset it")]
+    InvalidPattern {
+        rule: ProtectionRule,
+        message: String,
+    },
+}
+
+/// Whether `branch` matches the glob `pattern`.
+pub fn matches_pattern(pattern: &str, branch: &str) -> Result<bool, ProtectionError> {
+    let glob = Glob::new(pattern).map_err(|e| ProtectionError::InvalidPattern {
+        rule: ProtectionRule {
+            pattern: pattern.to_string(),
+        },
+        message: e.to_string(),
+    })?;
+    Ok(glob.is_match(branch))
+}
+
+/// The rule protecting `branch`, if any.
+pub async fn matching_rule(branch: &str) -> Result<Option<ProtectionRule>, ProtectionError> {
+    let entries: Vec<(String, bool)> = Config::list_all()
+        .await
+        .into_iter()
+        .filter_map(|(key, value)| {
+            let pattern = key.strip_prefix("branch.")?.strip_suffix(".protected")?;
+            Some((pattern.to_string(), parse_bool(&value).unwrap_or(false)))
+        })
+        .collect();
+
+    if let Some((pattern, protected)) = entries.iter().rev().find(|(p, _)| p == branch) {
+        return Ok(protected.then(|| ProtectionRule {
+            pattern: pattern.clone(),
+        }));
+    }
+    for (pattern, protected) in entries {
+        if protected && matches_pattern(&pattern, branch)? {
+            return Ok(Some(ProtectionRule { pattern }));
+        }
+    }
+    Ok(None)
+}
+
+/// Check whether `operation` (a verb such as "delete" or "reset") may touch `branch`.
+///
+/// Returns `Ok(None)` when the branch is not protected and `Ok(Some(rule))` when it is but
+/// `override_protection` was given; the override is reported on stderr.
+pub async fn check(
+    branch: &str,
+    operation: &str,
+    override_protection: bool,
+) -> Result<Option<ProtectionRule>, ProtectionError> {
+    let Some(rule) = matching_rule(branch).await? else {
+        return Ok(None);
+    };
+    if !override_protection {
+        return Err(ProtectionError::Protected {
+            branch: branch.to_string(),
+            operation: operation.to_string(),
+            rule,
+        });
+    }
+    eprintln!("warning: overriding {rule} to {operation} branch '{branch}'");
+    Ok(Some(rule))
+}
+
+/// `message` with a note of the overridden rule appended, for reflog entries.
+pub fn annotate(message: &str, rule: Option<&ProtectionRule>) -> String {
+    match rule {
+        Some(rule) => format!("{message} (override-protection: {rule})"),
+        None => message.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_pattern() {
+        let matches = |pattern, branch| matches_pattern(pattern, branch).unwrap();
+        assert!(matches("main", "main"));
+        assert!(!matches("main", "main2"));
+        assert!(matches("release/*", "release/1.0"));
+        assert!(!matches("release/*", "release/1.x/hotfix"));
+        assert!(matches("release/**", "release/1.x/hotfix"));
+        assert!(!matches("release/*", "releases/1.0"));
+        assert!(matches("*-stable", "v2-stable"));
+        assert!(!matches("*-stable", "v2-stable-old"));
+        assert!(matches("feat/*/done", "feat/x/done"));
+        assert!(matches("v?", "v2"));
+        assert!(!matches("v?", "v10"));
+        assert!(matches("hotfix-[0-9]", "hotfix-7"));
+        assert!(!matches("hotfix-[0-9]", "hotfix-x"));
+
+        let err = matches_pattern("release/[", "release/1").unwrap_err();
+        assert!(matches!(err, ProtectionError::InvalidPattern { .. }));
+        assert!(
+            err.to_string()
+                .starts_with("invalid protection rule branch.release/[.protected: ")
+        );
+    }
+
+    #[test]
+    fn test_annotate() {
+        let rule = ProtectionRule {
+            pattern: "release/*".to_string(),
+        };
+        assert_eq!(annotate("moving to HEAD~1", None), "moving to HEAD~1");
+        assert_eq!(
+            annotate("moving to HEAD~1", Some(&rule)),
+            "moving to HEAD~1 (override-protection: branch.release/*.protected)"
+        );
+    }
+}
@@ -0,0 +1,409 @@
+//! Todo list of an interactive rebase (`libra rebase -i`).
+//!
+//! [`generate_todo`] writes one `pick <short-hash> <subject>` line per commit to replay,
+//! oldest first, followed by a commented help block. The user edits it through a
+//! [`RebaseEditor`] and [`parse_todo`] turns the result into [`TodoItem`]s, rejecting the
+//! first bad line with a [`TodoError`] that names it. Commands (with their one-letter
+//! abbreviations):
+//!
+//! ```text
+//! pick   (p) <commit>   use the commit
+//! reword (r) <commit>   use the commit, but edit its message
+//! squash (s) <commit>   meld into the previous commit, editing the combined message
+//! fixup  (f) <commit>   meld into the previous commit, keeping the previous message
+//! drop   (d) <commit>   remove the commit
+//! exec   (x) <command>  run a shell command; the rebase stops if it fails
+//! ```
+//!
+//! The rebase state stores the parsed list with full hashes (see [`TodoItem`]'s `Display`),
+//! so `--continue` after a stop resumes the same plan.
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    process::Command,
+    str::FromStr,
+};
+
+use git_internal::{hash::ObjectHash, internal::object::commit::Commit};
+use thiserror::Error;
+
+use crate::{command::load_object, common_utils::parse_commit_msg, utils::util};
+
+/// One step of an interactive rebase.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TodoItem {
+    Pick(ObjectHash),
+    Reword(ObjectHash),
+    Squash(ObjectHash),
+    Fixup(ObjectHash),
+    Drop(ObjectHash),
+    Exec(String),
+}
+
+impl TodoItem {
+    /// The commit the step replays, or `None` for `exec`.
+    pub fn commit(&self) -> Option<&ObjectHash> {
+        match self {
+            TodoItem::Pick(commit)
+            | TodoItem::Reword(commit)
+            | TodoItem::Squash(commit)
+            | TodoItem::Fixup(commit)
+            | TodoItem::Drop(commit) => Some(commit),
+            TodoItem::Exec(_) => None,
+        }
+    }
+
+    fn command(&self) -> &'static str {
+        match self {
+            TodoItem::Pick(_) => "pick",
+            TodoItem::Reword(_) => "reword",
+            TodoItem::Squash(_) => "squash",
+            TodoItem::Fixup(_) => "fixup",
+            TodoItem::Drop(_) => "drop",
+            TodoItem::Exec(_) => "exec",
+        }
+    }
+}
+
+impl fmt::Display for TodoItem {
+    /// `<command> <full hash>`, or `exec <command>`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TodoItem::Exec(command) => write!(f, "exec {command}"),
+            item => write!(f, "{} {}", item.command(), item.commit().unwrap()),
+        }
+    }
+}
+
+impl FromStr for TodoItem {
+    type Err = String;
+
+    /// Parse a stored step. A bare hash is a `pick`, as written by non-interactive rebases
+    /// before steps had commands.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let Some((command, rest)) = s.split_once(' ') else {
+            return ObjectHash::from_str(s)
+                .map(TodoItem::Pick)
+                .map_err(|e| format!("Invalid commit hash '{s}': {e}"));
+        };
+        if command == "exec" {
+            return Ok(TodoItem::Exec(rest.to_string()));
+        }
+        let commit = ObjectHash::from_str(rest.trim())
+            .map_err(|e| format!("Invalid commit hash '{}': {e}", rest.trim()))?;
+        match command {
+            "pick" => Ok(TodoItem::Pick(commit)),
+            "reword" => Ok(TodoItem::Reword(commit)),
+            "squash" => Ok(TodoItem::Squash(commit)),
+            "fixup" => Ok(TodoItem::Fixup(commit)),
+            "drop" => Ok(TodoItem::Drop(commit)),
+            other => Err(format!("unknown rebase step '{other}'")),
+        }
+    }
+}
+
+/// A line of an edited todo list that cannot be used.
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("line {line}: {message}\n    {text}")]
+pub struct TodoError {
+    /// 1-based line number in the edited text.
+    pub line: usize,
+    /// The offending line.
+    pub text: String,
+    pub message: String,
+}
+
+const HELP: &str = "\
+#
+# Commands:
+# p, pick <commit> = use commit
+# r, reword <commit> = use commit, but edit the commit message
+# s, squash <commit> = use commit, but meld into previous commit
+# f, fixup <commit> = like \"squash\", but keep only the previous commit's message
+# d, drop <commit> = remove commit
+# x, exec <command> = run command (the rest of the line) using shell
+#
+# These lines can be re-ordered; they are executed from top to bottom.
+# If you remove a line here THAT COMMIT WILL BE LOST.
+# However, if you remove everything, the rebase will be aborted.
+";
+
+/// The initial todo list for replaying `commits` (oldest first) onto `onto`.
+pub fn generate_todo(commits: &[ObjectHash], onto: &ObjectHash) -> String {
+    let mut todo = String::new();
+    for commit in commits {
+        let subject = load_object::<Commit>(commit)
+            .map(|c| subject(&c.message).to_string())
+            .unwrap_or_default();
+        todo.push_str(&format!("pick {} {subject}\n", short_hash(commit)));
+    }
+    let count = commits.len();
+    todo.push_str(&format!(
+        "\n# Rebase {} onto {} ({count} command{})\n",
+        match (commits.first(), commits.last()) {
+            (Some(first), Some(last)) => format!("{}..{}", short_hash(first), short_hash(last)),
+            _ => "nothing".to_string(),
+        },
+        short_hash(onto),
+        if count == 1 { "" } else { "s" }
+    ));
+    todo.push_str(HELP);
+    todo
+}
+
+/// Parse an edited todo list. Commit names are resolved against `commits`, the commits
+/// being rebased: a unique prefix of one of them (at least four characters) is accepted.
+/// Blank lines and `#` comments are ignored; text after the commit name is ignored too.
+pub fn parse_todo(text: &str, commits: &[ObjectHash]) -> Result<Vec<TodoItem>, TodoError> {
+    let mut items = Vec::new();
+    for (index, raw) in text.lines().enumerate() {
+        let line = raw.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let error = |message: String| TodoError {
+            line: index + 1,
+            text: raw.to_string(),
+            message,
+        };
+        let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+
+        if matches!(command, "exec" | "x") {
+            if rest.is_empty() {
+                return Err(error("'exec' needs a command".to_string()));
+            }
+            items.push(TodoItem::Exec(rest.to_string()));
+            continue;
+        }
+
+        let make: fn(ObjectHash) -> TodoItem = match command {
+            "pick" | "p" => TodoItem::Pick,
+            "reword" | "r" => TodoItem::Reword,
+            "squash" | "s" => TodoItem::Squash,
+            "fixup" | "f" => TodoItem::Fixup,
+            "drop" | "d" => TodoItem::Drop,
+            other => return Err(error(format!("unknown command '{other}'"))),
+        };
+        let Some(name) = rest.split_whitespace().next() else {
+            return Err(error(format!("'{command}' needs a commit")));
+        };
+        let commit = resolve_commit(name, commits).map_err(error)?;
+        let item = make(commit);
+        if matches!(item, TodoItem::Squash(_) | TodoItem::Fixup(_))
+            && !items
+                .iter()
+                .any(|item| item.commit().is_some() && !matches!(item, TodoItem::Drop(_)))
+        {
+            return Err(error(format!(
+                "cannot '{}' without a previous commit",
+                item.command()
+            )));
+        }
+        items.push(item);
+    }
+    Ok(items)
+}
+
+fn resolve_commit(name: &str, commits: &[ObjectHash]) -> Result<ObjectHash, String> {
+    if name.len() < 4 {
+        return Err(format!("commit name '{name}' is too short"));
+    }
+    let mut matches = commits
+        .iter()
+        .filter(|commit| commit.to_string().starts_with(name));
+    match (matches.next(), matches.next()) {
+        (Some(commit), None) => Ok(*commit),
+        (Some(_), Some(_)) => Err(format!("commit name '{name}' is ambiguous")),
+        (None, _) => Err(format!("'{name}' is not one of the commits being rebased")),
+    }
+}
+
+fn short_hash(hash: &ObjectHash) -> String {
+    hash.to_string().chars().take(7).collect()
+}
+
+fn subject(message: &str) -> &str {
+    parse_commit_msg(message)
+        .0
+        .lines()
+        .next()
+        .unwrap_or_default()
+}
+
+/// Strip `#` comment lines and surrounding blank lines from an edited commit message.
+pub fn clean_message(message: &str) -> String {
+    message
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+/// Where an interactive rebase asks the user for input. Tests supply the edits directly.
+pub trait RebaseEditor {
+    /// Let the user edit the generated todo list; returns the edited text.
+    fn edit_todo(&mut self, todo: &str) -> Result<String, String>;
+
+    /// Let the user edit the message of a reworded or squashed commit; returns the edited
+    /// message, comments included.
+    fn edit_message(&mut self, message: &str) -> Result<String, String>;
+}
+
+/// Edits in the user's editor: `$LIBRA_EDITOR`, `$VISUAL` or `$EDITOR`, else `vi`. The
+/// text is written to a file below `.libra` that the editor is run on.
+#[derive(Debug, Default)]
+pub struct ExternalEditor;
+
+impl ExternalEditor {
+    fn edit_file(&self, file: &Path, text: &str) -> Result<String, String> {
+        std::fs::write(file, text)
+            .map_err(|e| format!("failed to write {}: {e}", file.display()))?;
+        let editor = ["LIBRA_EDITOR", "VISUAL", "EDITOR"]
+            .iter()
+            .find_map(|key| std::env::var(key).ok().filter(|value| !value.is_empty()))
+            .unwrap_or_else(|| "vi".to_string());
+
+        #[cfg(not(target_os = "windows"))]
+        let status = Command::new("sh")
+            .arg("-c")
+            .arg(format!("{editor} \"$1\""))
+            .arg(&editor)
+            .arg(file)
+            .status();
+        #[cfg(target_os = "windows")]
+        let status = Command::new("cmd")
+            .arg("/C")
+            .arg(&editor)
+            .arg(file)
+            .status();
+
+        match status {
+            Ok(status) if status.success() => {}
+            Ok(status) => return Err(format!("editor '{editor}' exited with {status}")),
+            Err(e) => return Err(format!("failed to run editor '{editor}': {e}")),
+        }
+        let edited = std::fs::read_to_string(file)
+            .map_err(|e| format!("failed to read {}: {e}", file.display()))?;
+        let _ = std::fs::remove_file(file);
+        Ok(edited)
+    }
+
+    fn file(name: &str) -> PathBuf {
+        util::storage_path().join(name)
+    }
+}
+
+impl RebaseEditor for ExternalEditor {
+    fn edit_todo(&mut self, todo: &str) -> Result<String, String> {
+        self.edit_file(&Self::file("rebase-todo"), todo)
+    }
+
+    fn edit_message(&mut self, message: &str) -> Result<String, String> {
+        self.edit_file(&Self::file("REBASE_EDITMSG"), message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commits() -> Vec<ObjectHash> {
+        ["1111aaaa", "2222bbbb", "3333cccc"]
+            .iter()
+            .map(|prefix| ObjectHash::from_str(&format!("{prefix}{}", "0".repeat(32))).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_todo_commands_and_abbreviations() {
+        let commits = commits();
+        let todo = "\
+# comment
+r 2222bbbb Second
+
+pick 1111aaaa First
+f 3333ccc
+exec make test
+d 2222b
+";
+        assert_eq!(
+            parse_todo(todo, &commits).unwrap(),
+            [
+                TodoItem::Reword(commits[1]),
+                TodoItem::Pick(commits[0]),
+                TodoItem::Fixup(commits[2]),
+                TodoItem::Exec("make test".to_string()),
+                TodoItem::Drop(commits[1]),
+            ]
+        );
+        assert_eq!(parse_todo("# all removed\n", &commits).unwrap(), []);
+    }
+
+    #[test]
+    fn test_parse_todo_errors_name_the_line() {
+        let commits = commits();
+        let cases = [
+            (
+                "pick 1111aaaa\nsquish 2222bbbb x\n",
+                2,
+                "unknown command 'squish'",
+            ),
+            ("pick\n", 1, "'pick' needs a commit"),
+            (
+                "\npick 9999\n",
+                2,
+                "'9999' is not one of the commits being rebased",
+            ),
+            ("pick 111\n", 1, "commit name '111' is too short"),
+            (
+                "drop 1111aaaa\nsquash 2222bbbb\n",
+                2,
+                "cannot 'squash' without a previous commit",
+            ),
+            ("pick 1111aaaa\nexec\n", 2, "'exec' needs a command"),
+        ];
+        for (todo, line, message) in cases {
+            let error = parse_todo(todo, &commits).unwrap_err();
+            assert_eq!(
+                (error.line, error.message.as_str()),
+                (line, message),
+                "{todo}"
+            );
+        }
+        let error = parse_todo("pick 1111aaaa\nsquish 2222bbbb x\n", &commits).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "line 2: unknown command 'squish'\n    squish 2222bbbb x"
+        );
+    }
+
+    #[test]
+    fn test_stored_steps_round_trip() {
+        let commits = commits();
+        for item in [
+            TodoItem::Pick(commits[0]),
+            TodoItem::Squash(commits[1]),
+            TodoItem::Exec("cargo test -- --nocapture".to_string()),
+        ] {
+            assert_eq!(item.to_string().parse::<TodoItem>().unwrap(), item);
+        }
+        // Steps stored before the todo list had commands are picks.
+        assert_eq!(
+            commits[2].to_string().parse::<TodoItem>().unwrap(),
+            TodoItem::Pick(commits[2])
+        );
+    }
+
+    #[test]
+    fn test_clean_message() {
+        assert_eq!(
+            clean_message("\nFix parser\n\n# Please enter the message\nDetails\n\n"),
+            "Fix parser\n\nDetails"
+        );
+    }
+}
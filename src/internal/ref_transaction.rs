@@ -0,0 +1,545 @@
+//! Atomic updates of several refs at once, with compare-and-swap checks and reflog entries.
+//!
+//! A [`RefTransaction`] queues ref operations, takes a lock file per ref in [`prepare`],
+//! and applies everything in one database transaction in [`commit`]: either every ref
+//! moves and every reflog entry is written, or nothing changes. Lock files live next to
+//! where git keeps loose refs (`.libra/refs/heads/main.lock`, `.libra/HEAD.lock`) and are
+//! created exclusively, so two transactions touching the same ref cannot both prepare.
+//! Dropping a transaction before [`commit`] releases its locks and leaves every ref as it
+//! was; a process that dies while holding a lock leaves the lock file behind, like git,
+//! and the ref stays busy until the file is removed.
+//!
+//! [`prepare`]: RefTransaction::prepare
+//! [`commit`]: RefTransaction::commit
+
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+    str::FromStr,
+};
+
+use git_internal::hash::{ObjectHash, get_hash_kind};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, ConnectionTrait, DbErr, EntityTrait,
+    QueryFilter, TransactionTrait,
+};
+use thiserror::Error;
+
+use crate::{
+    internal::{
+        branch::Branch,
+        db::get_db_conn_instance,
+        head::Head,
+        model::reference,
+        reflog::{self, Reflog, ReflogAction, ReflogContext, ReflogError},
+    },
+    utils::util,
+};
+
+/// A ref a [`RefTransaction`] can move.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RefName {
+    /// A local branch (`remote: None`) or a remote-tracking branch.
+    Branch {
+        name: String,
+        remote: Option<String>,
+    },
+    Tag(String),
+}
+
+impl RefName {
+    pub fn branch(name: impl Into<String>) -> Self {
+        RefName::Branch {
+            name: name.into(),
+            remote: None,
+        }
+    }
+
+    pub fn remote_branch(remote: impl Into<String>, name: impl Into<String>) -> Self {
+        RefName::Branch {
+            name: name.into(),
+            remote: Some(remote.into()),
+        }
+    }
+
+    pub fn tag(name: impl Into<String>) -> Self {
+        RefName::Tag(name.into())
+    }
+
+    /// The full ref name, e.g. `refs/heads/main`. Remote-tracking branches stored under
+    /// their full name (as fetch does) keep it.
+    pub fn full_name(&self) -> String {
+        match self {
+            RefName::Branch { name, remote: None } => format!("refs/heads/{name}"),
+            RefName::Branch {
+                name,
+                remote: Some(_),
+            } if name.starts_with("refs/") => name.clone(),
+            RefName::Branch {
+                name,
+                remote: Some(remote),
+            } => format!("refs/remotes/{remote}/{name}"),
+            RefName::Tag(name) => format!("refs/tags/{name}"),
+        }
+    }
+}
+
+/// Why a [`RefTransaction`] did not apply.
+#[derive(Debug, Error)]
+pub enum RefTransactionError {
+    /// Another writer holds the lock file of this ref.
+    #[error("cannot lock ref '{0}': it is locked by another process")]
+    Locked(String),
+    /// The ref does not point where the transaction expected.
+    #[error("cannot update ref '{name}': expected {expected}, found {actual}")]
+    Stale {
+        name: String,
+        expected: String,
+        actual: String,
+    },
+    #[error("cannot create ref '{0}': it already exists")]
+    AlreadyExists(String),
+    #[error("cannot delete ref '{0}': it does not exist")]
+    Missing(String),
+    #[error("ref '{0}' is changed more than once in the transaction")]
+    Duplicate(String),
+    #[error("cannot write lock file for ref '{name}': {source}")]
+    Io { name: String, source: io::Error },
+    #[error("database error: {0}")]
+    Database(#[from] DbErr),
+    #[error("failed to write reflog: {0}")]
+    Reflog(#[from] ReflogError),
+}
+
+/// What happens to one ref.
+enum Change {
+    /// Point the ref at `new`, creating it if needed; with `expected`, only from there.
+    Update {
+        new: ObjectHash,
+        expected: Option<ObjectHash>,
+    },
+    /// Create the ref, which must not exist yet.
+    Create(ObjectHash),
+    /// Remove the ref, which must exist; with `expected`, it must point there.
+    Delete { expected: Option<ObjectHash> },
+}
+
+struct RefOp {
+    name: RefName,
+    change: Change,
+    reflog: Option<ReflogAction>,
+}
+
+/// Where HEAD points once the transaction commits.
+struct HeadOp {
+    head: Head,
+    remote: Option<String>,
+    reflog: Option<ReflogAction>,
+}
+
+/// A lock file that is removed when dropped.
+struct RefLock(PathBuf);
+
+impl RefLock {
+    /// Creates the lock file at `path`; `Ok(None)` if it already exists.
+    fn acquire(path: PathBuf) -> io::Result<Option<Self>> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => {
+                writeln!(file, "{}", std::process::id())?;
+                Ok(Some(RefLock(path)))
+            }
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl Drop for RefLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+/// Ref changes that are applied together or not at all; see the module docs.
+#[derive(Default)]
+pub struct RefTransaction {
+    ops: Vec<RefOp>,
+    head: Option<HeadOp>,
+    locks: Vec<RefLock>,
+}
+
+impl RefTransaction {
+    pub fn begin() -> Self {
+        Self::default()
+    }
+
+    /// Point `name` at `new`, creating the ref if it does not exist. With `expected`, the
+    /// ref must currently point at that commit.
+    pub fn update(
+        &mut self,
+        name: RefName,
+        new: ObjectHash,
+        expected: Option<ObjectHash>,
+        reflog: Option<ReflogAction>,
+    ) -> &mut Self {
+        self.push(name, Change::Update { new, expected }, reflog)
+    }
+
+    /// Create `name` at `new`; fails if the ref already exists.
+    pub fn create(
+        &mut self,
+        name: RefName,
+        new: ObjectHash,
+        reflog: Option<ReflogAction>,
+    ) -> &mut Self {
+        self.push(name, Change::Create(new), reflog)
+    }
+
+    /// Delete `name`; fails if it does not exist or, with `expected`, points elsewhere.
+    pub fn delete(
+        &mut self,
+        name: RefName,
+        expected: Option<ObjectHash>,
+        reflog: Option<ReflogAction>,
+    ) -> &mut Self {
+        self.push(name, Change::Delete { expected }, reflog)
+    }
+
+    /// Point HEAD (or the HEAD of `remote`) at `head`. For the local HEAD, the reflog entry,
+    /// if any, goes to `HEAD` and records the commits HEAD resolved to before and after the
+    /// transaction; remote HEADs have no reflog.
+    pub fn set_head(
+        &mut self,
+        head: Head,
+        remote: Option<&str>,
+        reflog: Option<ReflogAction>,
+    ) -> &mut Self {
+        self.head = Some(HeadOp {
+            head,
+            remote: remote.map(str::to_owned),
+            reflog,
+        });
+        self
+    }
+
+    fn push(&mut self, name: RefName, change: Change, reflog: Option<ReflogAction>) -> &mut Self {
+        self.ops.push(RefOp {
+            name,
+            change,
+            reflog,
+        });
+        self
+    }
+
+    /// Take the lock file of every queued ref, in name order.
+    ///
+    /// If any lock is busy, the locks taken so far are released again and the error names
+    /// the busy ref. Calling this again after success does nothing.
+    pub fn prepare(&mut self) -> Result<(), RefTransactionError> {
+        if !self.locks.is_empty() {
+            return Ok(());
+        }
+        let mut names: Vec<String> = self.ops.iter().map(|op| op.name.full_name()).collect();
+        names.sort();
+        if let Some(pair) = names.windows(2).find(|pair| pair[0] == pair[1]) {
+            return Err(RefTransactionError::Duplicate(pair[0].clone()));
+        }
+        if let Some(head) = &self.head {
+            names.push(match &head.remote {
+                Some(remote) => format!("refs/remotes/{remote}/HEAD"),
+                None => reflog::HEAD.to_string(),
+            });
+        }
+
+        let storage =
+            util::try_get_storage_path(None).map_err(|source| RefTransactionError::Io {
+                name: names.first().cloned().unwrap_or_default(),
+                source,
+            })?;
+        let mut locks = Vec::with_capacity(names.len());
+        for name in names {
+            let path = storage.join(format!("{name}.lock"));
+            match RefLock::acquire(path) {
+                Ok(Some(lock)) => locks.push(lock),
+                // Dropping `locks` releases everything taken so far.
+                Ok(None) => return Err(RefTransactionError::Locked(name)),
+                Err(source) => return Err(RefTransactionError::Io { name, source }),
+            }
+        }
+        self.locks = locks;
+        Ok(())
+    }
+
+    /// Check every expected value and apply all changes and reflog entries in one database
+    /// transaction, then release the locks. Prepares first if [`prepare`] was not called.
+    ///
+    /// [`prepare`]: RefTransaction::prepare
+    pub async fn commit(mut self) -> Result<(), RefTransactionError> {
+        self.prepare()?;
+        let db = get_db_conn_instance().await;
+        let txn = db.begin().await?;
+        if self.ops.iter().any(|op| op.reflog.is_some())
+            || self.head.as_ref().is_some_and(|head| head.reflog.is_some())
+        {
+            reflog::ensure_reflog_table_exists(&txn).await?;
+        }
+        let head_before = Head::current_commit_with_conn(&txn).await;
+
+        for op in self.ops.drain(..) {
+            let full_name = op.name.full_name();
+            let current = current_target(&txn, &op.name).await?;
+            let new = match op.change {
+                Change::Update { new, expected } => {
+                    check_expected(&full_name, expected, current)?;
+                    Some(new)
+                }
+                Change::Create(new) => {
+                    if current.is_some() {
+                        return Err(RefTransactionError::AlreadyExists(full_name));
+                    }
+                    Some(new)
+                }
+                Change::Delete { expected } => {
+                    if current.is_none() {
+                        return Err(RefTransactionError::Missing(full_name));
+                    }
+                    check_expected(&full_name, expected, current)?;
+                    None
+                }
+            };
+            set_target(&txn, &op.name, new).await?;
+            if let Some(action) = op.reflog {
+                let context = ReflogContext {
+                    old_oid: oid_or_zero(current),
+                    new_oid: oid_or_zero(new),
+                    action,
+                };
+                Reflog::insert_single_entry(&txn, &context, &full_name).await?;
+            }
+        }
+
+        if let Some(head) = self.head.take() {
+            Head::update_with_conn(&txn, head.head, head.remote.as_deref()).await;
+            if let Some(action) = head.reflog
+                && head.remote.is_none()
+            {
+                let context = ReflogContext {
+                    old_oid: oid_or_zero(head_before),
+                    new_oid: oid_or_zero(Head::current_commit_with_conn(&txn).await),
+                    action,
+                };
+                Reflog::insert_single_entry(&txn, &context, reflog::HEAD).await?;
+            }
+        }
+
+        txn.commit().await?;
+        Ok(())
+    }
+
+    /// Give up the transaction: release its locks without changing any ref.
+    pub fn abort(self) {}
+}
+
+fn oid_or_zero(oid: Option<ObjectHash>) -> String {
+    oid.map_or_else(
+        || ObjectHash::zero_str(get_hash_kind()),
+        |oid| oid.to_string(),
+    )
+}
+
+fn check_expected(
+    name: &str,
+    expected: Option<ObjectHash>,
+    current: Option<ObjectHash>,
+) -> Result<(), RefTransactionError> {
+    match expected {
+        Some(expected) if current != Some(expected) => Err(RefTransactionError::Stale {
+            name: name.to_string(),
+            expected: expected.to_string(),
+            actual: current.map_or_else(|| "no ref".to_string(), |c| c.to_string()),
+        }),
+        _ => Ok(()),
+    }
+}
+
+fn tag_query(full_name: &str) -> sea_orm::Select<reference::Entity> {
+    reference::Entity::find()
+        .filter(reference::Column::Name.eq(full_name))
+        .filter(reference::Column::Kind.eq(reference::ConfigKind::Tag))
+}
+
+/// Where `name` points, or `None` if the ref does not exist.
+async fn current_target<C: ConnectionTrait>(
+    db: &C,
+    name: &RefName,
+) -> Result<Option<ObjectHash>, DbErr> {
+    Ok(match name {
+        RefName::Branch { name, remote } => {
+            Branch::find_branch_with_conn(db, name, remote.as_deref())
+                .await
+                .map(|branch| branch.commit)
+        }
+        RefName::Tag(_) => tag_query(&name.full_name())
+            .one(db)
+            .await?
+            .and_then(|model| model.commit)
+            .and_then(|commit| ObjectHash::from_str(&commit).ok()),
+    })
+}
+
+/// Point `name` at `target`, or delete it for `None`.
+async fn set_target<C: ConnectionTrait>(
+    db: &C,
+    name: &RefName,
+    target: Option<ObjectHash>,
+) -> Result<(), DbErr> {
+    match (name, target) {
+        (RefName::Branch { name, remote }, Some(target)) => {
+            Branch::update_branch_with_conn(db, name, &target.to_string(), remote.as_deref()).await;
+        }
+        (RefName::Branch { name, remote }, None) => {
+            Branch::delete_branch_with_conn(db, name, remote.as_deref()).await;
+        }
+        (RefName::Tag(_), Some(target)) => {
+            let full_name = name.full_name();
+            match tag_query(&full_name).one(db).await? {
+                Some(model) => {
+                    let mut model: reference::ActiveModel = model.into();
+                    model.commit = Set(Some(target.to_string()));
+                    model.update(db).await?;
+                }
+                None => {
+                    reference::ActiveModel {
+                        name: Set(Some(full_name)),
+                        kind: Set(reference::ConfigKind::Tag),
+                        commit: Set(Some(target.to_string())),
+                        ..Default::default()
+                    }
+                    .insert(db)
+                    .await?;
+                }
+            }
+        }
+        (RefName::Tag(_), None) => {
+            reference::Entity::delete_many()
+                .filter(reference::Column::Name.eq(name.full_name()))
+                .filter(reference::Column::Kind.eq(reference::ConfigKind::Tag))
+                .exec(db)
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serial_test::serial;
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::utils::test;
+
+    fn id(byte: u8) -> ObjectHash {
+        ObjectHash::new(&[byte; 20])
+    }
+
+    async fn branch_at(name: &str) -> Option<ObjectHash> {
+        Branch::find_branch(name, None).await.map(|b| b.commit)
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_commit_moves_refs_together_with_reflog() {
+        let temp = tempdir().unwrap();
+        test::setup_with_new_libra_in(temp.path()).await;
+        let _guard = test::ChangeDirGuard::new(temp.path());
+        Branch::update_branch("main", &id(1).to_string(), None).await;
+        Branch::update_branch("old", &id(1).to_string(), None).await;
+
+        let mut txn = RefTransaction::begin();
+        txn.update(
+            RefName::branch("main"),
+            id(2),
+            Some(id(1)),
+            Some(ReflogAction::Fetch),
+        )
+        .create(RefName::branch("topic"), id(3), None)
+        .create(RefName::tag("v1"), id(2), None)
+        .delete(RefName::branch("old"), Some(id(1)), None);
+        txn.commit().await.unwrap();
+
+        assert_eq!(branch_at("main").await, Some(id(2)));
+        assert_eq!(branch_at("topic").await, Some(id(3)));
+        assert_eq!(branch_at("old").await, None);
+        let db = get_db_conn_instance().await;
+        assert_eq!(
+            current_target(db, &RefName::tag("v1")).await.unwrap(),
+            Some(id(2))
+        );
+        let entries = Reflog::find_all(db, "refs/heads/main").await.unwrap();
+        assert_eq!(entries[0].old_oid, id(1).to_string());
+        assert_eq!(entries[0].new_oid, id(2).to_string());
+        assert!(!temp.path().join(".libra/refs/heads/main.lock").exists());
+
+        // A stale expectation rolls back the changes queued before it.
+        let mut txn = RefTransaction::begin();
+        txn.update(RefName::branch("topic"), id(4), None, None)
+            .update(RefName::branch("main"), id(4), Some(id(1)), None);
+        let err = txn.commit().await.unwrap_err();
+        assert!(
+            matches!(err, RefTransactionError::Stale { ref name, .. } if name == "refs/heads/main")
+        );
+        assert_eq!(branch_at("topic").await, Some(id(3)));
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_busy_lock_aborts_without_partial_updates() {
+        let temp = tempdir().unwrap();
+        test::setup_with_new_libra_in(temp.path()).await;
+        let _guard = test::ChangeDirGuard::new(temp.path());
+        Branch::update_branch("main", &id(1).to_string(), None).await;
+        Branch::update_branch("topic", &id(1).to_string(), None).await;
+
+        // A concurrent writer holds the lock of `topic`.
+        let busy = temp.path().join(".libra/refs/heads/topic.lock");
+        fs::create_dir_all(busy.parent().unwrap()).unwrap();
+        fs::write(&busy, "4242\n").unwrap();
+
+        let mut txn = RefTransaction::begin();
+        txn.update(RefName::branch("main"), id(2), None, None)
+            .update(RefName::branch("topic"), id(2), None, None);
+        let err = txn.commit().await.unwrap_err();
+        assert!(matches!(err, RefTransactionError::Locked(ref name) if name == "refs/heads/topic"));
+        assert_eq!(branch_at("main").await, Some(id(1)));
+        assert_eq!(branch_at("topic").await, Some(id(1)));
+        assert!(!temp.path().join(".libra/refs/heads/main.lock").exists());
+        assert!(busy.exists());
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_drop_before_commit_leaves_refs_untouched() {
+        let temp = tempdir().unwrap();
+        test::setup_with_new_libra_in(temp.path()).await;
+        let _guard = test::ChangeDirGuard::new(temp.path());
+        Branch::update_branch("main", &id(1).to_string(), None).await;
+
+        let mut txn = RefTransaction::begin();
+        txn.update(RefName::branch("main"), id(2), Some(id(1)), None)
+            .set_head(Head::Detached(id(2)), None, None);
+        txn.prepare().unwrap();
+        assert!(temp.path().join(".libra/refs/heads/main.lock").exists());
+        assert!(temp.path().join(".libra/HEAD.lock").exists());
+        drop(txn);
+
+        assert_eq!(branch_at("main").await, Some(id(1)));
+        assert!(matches!(Head::current().await, Head::Branch(_)));
+        assert!(!temp.path().join(".libra/refs/heads/main.lock").exists());
+        assert!(!temp.path().join(".libra/HEAD.lock").exists());
+    }
+}
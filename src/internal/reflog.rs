@@ -82,9 +82,10 @@ impl Display for ReflogContext {
             ),
             ReflogAction::Fetch => write!(f, "fast-forward"),
             ReflogAction::Pull => write!(f, "fast-forward"),
-            ReflogAction::Push => write!(f, "push"),
+            ReflogAction::Push { message } => write!(f, "{message}"),
             ReflogAction::Rebase { state, details } => write!(f, "({state}) {details}"),
             ReflogAction::Clone { from } => write!(f, "from {from}"),
+            ReflogAction::FilterRepo { details } => write!(f, "{details}"),
         }
     }
 }
@@ -100,8 +101,9 @@ pub enum ReflogAction {
     Rebase { state: String, details: String },
     Fetch,
     Pull,
-    Push,
+    Push { message: String },
     Clone { from: String },
+    FilterRepo { details: String },
 }
 
 #[derive(Copy, Clone)]
@@ -119,6 +121,7 @@ pub enum ReflogActionKind {
     Pull,
     Push,
     Clone,
+    FilterRepo,
 }
 
 impl Display for ReflogActionKind {
@@ -135,6 +138,7 @@ impl Display for ReflogActionKind {
             Self::Pull => write!(f, "pull"),
             Self::Push => write!(f, "push"),
             Self::Clone => write!(f, "clone"),
+            Self::FilterRepo => write!(f, "filter-repo"),
         }
     }
 }
@@ -152,7 +156,8 @@ impl ReflogAction {
             Self::Rebase { .. } => ReflogActionKind::Rebase,
             Self::Checkout { .. } => ReflogActionKind::Checkout,
             Self::Fetch => ReflogActionKind::Fetch,
-            Self::Push => ReflogActionKind::Push,
+            Self::Push { .. } => ReflogActionKind::Push,
+            Self::FilterRepo { .. } => ReflogActionKind::FilterRepo,
         }
     }
 }
@@ -339,7 +344,9 @@ async fn reflog_table_exists<C: ConnectionTrait>(db_conn: &C) -> Result<bool, Re
 
 /// Ensures that the 'reflog' table and its associated indexes exist in the database.
 /// If they do not exist, they will be created.
-async fn ensure_reflog_table_exists<C: ConnectionTrait>(db: &C) -> Result<(), ReflogError> {
+pub(crate) async fn ensure_reflog_table_exists<C: ConnectionTrait>(
+    db: &C,
+) -> Result<(), ReflogError> {
     if reflog_table_exists(db).await? {
         return Ok(());
     }
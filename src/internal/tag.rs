@@ -182,6 +182,44 @@ pub async fn delete(name: &str) -> Result<(), anyhow::Error> {
     }
 }
 
+/// Points an existing tag reference at a different object (a commit or a tag object).
+pub async fn update_target(name: &str, target: &ObjectHash) -> Result<(), anyhow::Error> {
+    let db_conn = get_db_conn_instance().await;
+    let model = reference::Entity::find()
+        .filter(reference::Column::Name.eq(format!("{}{}", TAG_REF_PREFIX, name)))
+        .filter(reference::Column::Kind.eq(reference::ConfigKind::Tag))
+        .one(db_conn)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("tag '{}' not found", name))?;
+
+    let mut model: reference::ActiveModel = model.into();
+    model.commit = Set(Some(target.to_string()));
+    model.update(db_conn).await?;
+    Ok(())
+}
+
+/// Creates a tag reference to `target`, or moves it there if the tag already exists.
+pub async fn set_target(name: &str, target: &ObjectHash) -> Result<(), anyhow::Error> {
+    let db_conn = get_db_conn_instance().await;
+    let exists = reference::Entity::find()
+        .filter(reference::Column::Name.eq(format!("{}{}", TAG_REF_PREFIX, name)))
+        .filter(reference::Column::Kind.eq(reference::ConfigKind::Tag))
+        .one(db_conn)
+        .await?;
+    if exists.is_some() {
+        return update_target(name, target).await;
+    }
+
+    let new_ref = reference::ActiveModel {
+        name: Set(Some(format!("{}{}", TAG_REF_PREFIX, name))),
+        kind: Set(reference::ConfigKind::Tag),
+        commit: Set(Some(target.to_string())),
+        ..Default::default()
+    };
+    new_ref.insert(db_conn).await?;
+    Ok(())
+}
+
 /// Finds a tag by name and returns the tag object and the final commit
 pub async fn find_tag_and_commit(name: &str) -> Result<Option<(TagObject, Commit)>, GitError> {
     let db_conn = get_db_conn_instance().await;
@@ -1056,8 +1056,8 @@ impl<M: CompletionModel + Clone + 'static> App<M> {
                 tool_name,
                 result,
             } => {
-                // For successful apply_patch, insert a visual diff cell.
-                if tool_name == "apply_patch"
+                // For successful apply_patch/write_file, insert a visual diff cell.
+                if (tool_name == "apply_patch" || tool_name == "write_file")
                     && let Ok(ref output) = result
                 {
                     self.try_insert_diff_cell(output);
@@ -1220,6 +1220,10 @@ impl<M: CompletionModel + Clone + 'static> App<M> {
         else {
             return;
         };
+        // Dry runs only preview changes; nothing was written.
+        if meta.get("dry_run").and_then(|v| v.as_bool()) == Some(true) {
+            return;
+        }
         let Some(diffs) = meta.get("diffs").and_then(|v| v.as_array()) else {
             return;
         };
@@ -0,0 +1,897 @@
+//! Reader and writer for the `git fast-import` stream format, as produced by `git fast-export`.
+//!
+//! [`StreamReader`] parses one command at a time from any [`BufRead`], so callers can convert
+//! objects as they arrive instead of buffering the whole stream. Only blob contents (and commit
+//! or tag messages) are held in memory, one object at a time. [`StreamWriter`] emits the same
+//! commands, in a form both `git fast-import` and [`StreamReader`] accept.
+//!
+//! Supported commands: `blob`, `commit` (with `M`, `D`, `C`, `R` and `deleteall`), `tag`,
+//! `reset`, `feature`, `option`, `progress`, `checkpoint` and `done`. Commands that need a
+//! response channel (`cat-blob`, `ls`, `get-mark`) and notes are rejected.
+
+use std::io::{self, BufRead, Write};
+
+use git_internal::internal::object::{
+    signature::{Signature, SignatureType},
+    tree::TreeItemMode,
+};
+
+/// Where a file's content comes from in an `M` file change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DataRef {
+    /// A blob (or, for gitlinks, a commit) defined earlier with `mark :<n>`.
+    Mark(u64),
+    /// An object id given in hex.
+    Oid(String),
+    /// The content follows the file change as a `data` command.
+    Inline(Vec<u8>),
+}
+
+/// A commit named in `from`, `merge` or `reset`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommitRef {
+    Mark(u64),
+    Oid(String),
+    /// A ref name such as `refs/heads/main`, resolved against the refs of the stream.
+    Ref(String),
+}
+
+/// One file change of a `commit` command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileChange {
+    Modify {
+        mode: TreeItemMode,
+        data: DataRef,
+        path: String,
+    },
+    Delete {
+        path: String,
+    },
+    Copy {
+        source: String,
+        dest: String,
+    },
+    Rename {
+        source: String,
+        dest: String,
+    },
+    DeleteAll,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlobCommand {
+    pub mark: Option<u64>,
+    pub original_oid: Option<String>,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitCommand {
+    pub ref_name: String,
+    pub mark: Option<u64>,
+    pub original_oid: Option<String>,
+    pub author: Option<Signature>,
+    pub committer: Signature,
+    pub encoding: Option<String>,
+    pub message: Vec<u8>,
+    pub from: Option<CommitRef>,
+    pub merges: Vec<CommitRef>,
+    pub changes: Vec<FileChange>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagCommand {
+    pub name: String,
+    pub mark: Option<u64>,
+    pub original_oid: Option<String>,
+    pub from: CommitRef,
+    pub tagger: Option<Signature>,
+    pub message: Vec<u8>,
+}
+
+/// A top-level command of the stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Blob(BlobCommand),
+    Commit(Box<CommitCommand>),
+    Tag(TagCommand),
+    Reset {
+        ref_name: String,
+        from: Option<CommitRef>,
+    },
+    Feature(String),
+    Option(String),
+    Progress(String),
+    Checkpoint,
+    Done,
+}
+
+/// Parses a fast-import stream one command at a time.
+pub struct StreamReader<R> {
+    reader: R,
+    /// A line read ahead while looking for the end of the previous command.
+    pending: Option<Vec<u8>>,
+    line_no: usize,
+    done: bool,
+}
+
+impl<R: BufRead> StreamReader<R> {
+    pub fn new(reader: R) -> Self {
+        StreamReader {
+            reader,
+            pending: None,
+            line_no: 0,
+            done: false,
+        }
+    }
+
+    /// Returns the next command, or `None` at the end of the stream or after `done`.
+    pub fn next_command(&mut self) -> io::Result<Option<Command>> {
+        if self.done {
+            return Ok(None);
+        }
+        let line = loop {
+            match self.next_line()? {
+                None => return Ok(None),
+                // Blank lines separate commands and `#` starts a comment.
+                Some(line) if line.is_empty() || line.starts_with(b"#") => continue,
+                Some(line) => break line,
+            }
+        };
+        let line = self.utf8(line)?;
+
+        let command = if line == "blob" {
+            Command::Blob(self.parse_blob()?)
+        } else if let Some(ref_name) = line.strip_prefix("commit ") {
+            Command::Commit(Box::new(self.parse_commit(ref_name.to_string())?))
+        } else if let Some(name) = line.strip_prefix("tag ") {
+            Command::Tag(self.parse_tag(name.to_string())?)
+        } else if let Some(ref_name) = line.strip_prefix("reset ") {
+            let from = self.optional_commit_ref("from")?;
+            Command::Reset {
+                ref_name: ref_name.to_string(),
+                from,
+            }
+        } else if let Some(feature) = line.strip_prefix("feature ") {
+            Command::Feature(feature.to_string())
+        } else if let Some(option) = line.strip_prefix("option ") {
+            Command::Option(option.to_string())
+        } else if let Some(message) = line.strip_prefix("progress ") {
+            Command::Progress(message.to_string())
+        } else if line == "checkpoint" {
+            Command::Checkpoint
+        } else if line == "done" {
+            self.done = true;
+            Command::Done
+        } else {
+            return Err(self.error(format!("unsupported command '{line}'")));
+        };
+        Ok(Some(command))
+    }
+
+    fn parse_blob(&mut self) -> io::Result<BlobCommand> {
+        let mark = self.optional_mark()?;
+        let original_oid = self.optional_value("original-oid")?;
+        let data = self.expect_data()?;
+        Ok(BlobCommand {
+            mark,
+            original_oid,
+            data,
+        })
+    }
+
+    fn parse_commit(&mut self, ref_name: String) -> io::Result<CommitCommand> {
+        let mark = self.optional_mark()?;
+        let original_oid = self.optional_value("original-oid")?;
+        let author = self
+            .optional_value("author")?
+            .map(|ident| self.parse_ident(&ident, SignatureType::Author))
+            .transpose()?;
+        let committer = match self.optional_value("committer")? {
+            Some(ident) => self.parse_ident(&ident, SignatureType::Committer)?,
+            None => return Err(self.error("commit without a committer")),
+        };
+        let encoding = self.optional_value("encoding")?;
+        let message = self.expect_data()?;
+        let from = self.optional_commit_ref("from")?;
+        let mut merges = Vec::new();
+        while let Some(merge) = self.optional_commit_ref("merge")? {
+            merges.push(merge);
+        }
+
+        let mut changes = Vec::new();
+        while let Some(line) = self.next_line()? {
+            if line.is_empty() {
+                break;
+            }
+            match self.parse_file_change(&line)? {
+                Some(change) => changes.push(change),
+                None => {
+                    self.pending = Some(line);
+                    break;
+                }
+            }
+        }
+
+        Ok(CommitCommand {
+            ref_name,
+            mark,
+            original_oid,
+            author,
+            committer,
+            encoding,
+            message,
+            from,
+            merges,
+            changes,
+        })
+    }
+
+    fn parse_tag(&mut self, name: String) -> io::Result<TagCommand> {
+        let mark = self.optional_mark()?;
+        let from = self
+            .optional_commit_ref("from")?
+            .ok_or_else(|| self.error(format!("tag '{name}' without a 'from' line")))?;
+        let original_oid = self.optional_value("original-oid")?;
+        let tagger = self
+            .optional_value("tagger")?
+            .map(|ident| self.parse_ident(&ident, SignatureType::Tagger))
+            .transpose()?;
+        let message = self.expect_data()?;
+        Ok(TagCommand {
+            name,
+            mark,
+            original_oid,
+            from,
+            tagger,
+            message,
+        })
+    }
+
+    /// Parses a file change, or returns `None` if `line` starts the next command.
+    fn parse_file_change(&mut self, line: &[u8]) -> io::Result<Option<FileChange>> {
+        if line == b"deleteall" {
+            return Ok(Some(FileChange::DeleteAll));
+        }
+        let Some((&op, rest)) = line.split_first() else {
+            return Ok(None);
+        };
+        if rest.first() != Some(&b' ') {
+            return Ok(None);
+        }
+        let rest = &rest[1..];
+        let change = match op {
+            b'M' => {
+                let mut fields = rest.splitn(3, |b| *b == b' ');
+                let (Some(mode), Some(data), Some(path)) =
+                    (fields.next(), fields.next(), fields.next())
+                else {
+                    return Err(self.error("malformed 'M' file change"));
+                };
+                let mode = self.parse_mode(mode)?;
+                let path = self.parse_path(path)?;
+                let data = match data {
+                    b"inline" => DataRef::Inline(self.expect_data()?),
+                    mark if mark.starts_with(b":") => DataRef::Mark(self.parse_mark(mark)?),
+                    oid => DataRef::Oid(self.utf8(oid.to_vec())?),
+                };
+                FileChange::Modify { mode, data, path }
+            }
+            b'D' => FileChange::Delete {
+                path: self.parse_path(rest)?,
+            },
+            b'C' | b'R' => {
+                let (source, dest) = self.parse_path_pair(rest)?;
+                if op == b'C' {
+                    FileChange::Copy { source, dest }
+                } else {
+                    FileChange::Rename { source, dest }
+                }
+            }
+            b'N' => return Err(self.error("notes are not supported")),
+            _ => return Ok(None),
+        };
+        Ok(Some(change))
+    }
+
+    /// Splits the `<source> <dest>` of `C`/`R`, where a source with spaces must be quoted.
+    fn parse_path_pair(&self, rest: &[u8]) -> io::Result<(String, String)> {
+        let (source, dest) = if rest.starts_with(b"\"") {
+            let (source, used) = unquote(rest).map_err(|e| self.error(e))?;
+            let dest = rest[used..]
+                .strip_prefix(b" ")
+                .ok_or_else(|| self.error("missing destination path"))?;
+            (source, dest)
+        } else {
+            let space = rest
+                .iter()
+                .position(|b| *b == b' ')
+                .ok_or_else(|| self.error("missing destination path"))?;
+            (rest[..space].to_vec(), &rest[space + 1..])
+        };
+        Ok((self.utf8(source)?, self.parse_path(dest)?))
+    }
+
+    fn parse_path(&self, raw: &[u8]) -> io::Result<String> {
+        let path = if raw.starts_with(b"\"") {
+            let (path, used) = unquote(raw).map_err(|e| self.error(e))?;
+            if used != raw.len() {
+                return Err(self.error("trailing characters after quoted path"));
+            }
+            path
+        } else {
+            raw.to_vec()
+        };
+        if path.is_empty() {
+            return Err(self.error("empty path"));
+        }
+        self.utf8(path)
+    }
+
+    fn parse_mode(&self, raw: &[u8]) -> io::Result<TreeItemMode> {
+        match raw {
+            b"100644" | b"644" => Ok(TreeItemMode::Blob),
+            b"100755" | b"755" => Ok(TreeItemMode::BlobExecutable),
+            b"120000" => Ok(TreeItemMode::Link),
+            b"160000" => Ok(TreeItemMode::Commit),
+            b"040000" | b"40000" => Ok(TreeItemMode::Tree),
+            _ => Err(self.error(format!(
+                "unsupported file mode '{}'",
+                String::from_utf8_lossy(raw)
+            ))),
+        }
+    }
+
+    /// Parses `Name <email> <seconds> <±hhmm>`; the name may be empty.
+    fn parse_ident(&self, ident: &str, signature_type: SignatureType) -> io::Result<Signature> {
+        let malformed = || self.error(format!("malformed identity '{ident}'"));
+        let open = ident.find('<').ok_or_else(malformed)?;
+        let close = ident[open..].find('>').ok_or_else(malformed)? + open;
+        let mut when = ident[close + 1..].split_whitespace();
+        let (Some(timestamp), Some(timezone), None) = (when.next(), when.next(), when.next())
+        else {
+            return Err(malformed());
+        };
+        Ok(Signature {
+            signature_type,
+            name: ident[..open].trim_end().to_string(),
+            email: ident[open + 1..close].to_string(),
+            timestamp: timestamp.parse().map_err(|_| malformed())?,
+            timezone: timezone.to_string(),
+        })
+    }
+
+    fn parse_mark(&self, raw: &[u8]) -> io::Result<u64> {
+        std::str::from_utf8(raw)
+            .ok()
+            .and_then(|s| s.strip_prefix(':'))
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(|| self.error(format!("invalid mark '{}'", String::from_utf8_lossy(raw))))
+    }
+
+    fn optional_mark(&mut self) -> io::Result<Option<u64>> {
+        self.optional_value("mark")?
+            .map(|mark| self.parse_mark(mark.as_bytes()))
+            .transpose()
+    }
+
+    fn optional_commit_ref(&mut self, keyword: &str) -> io::Result<Option<CommitRef>> {
+        let Some(value) = self.optional_value(keyword)? else {
+            return Ok(None);
+        };
+        let commit = if value.starts_with(':') {
+            CommitRef::Mark(self.parse_mark(value.as_bytes())?)
+        } else if is_hex_oid(&value) {
+            CommitRef::Oid(value)
+        } else {
+            CommitRef::Ref(value)
+        };
+        Ok(Some(commit))
+    }
+
+    /// Consumes `<keyword> <value>` if it is the next line, otherwise leaves the line unread.
+    fn optional_value(&mut self, keyword: &str) -> io::Result<Option<String>> {
+        let Some(line) = self.next_line()? else {
+            return Ok(None);
+        };
+        let prefix = format!("{keyword} ");
+        if line.starts_with(prefix.as_bytes()) {
+            let value = self.utf8(line[prefix.len()..].to_vec())?;
+            Ok(Some(value))
+        } else {
+            self.pending = Some(line);
+            Ok(None)
+        }
+    }
+
+    /// Reads `data <count>` or `data <<<delimiter>` and the payload that follows it.
+    fn expect_data(&mut self) -> io::Result<Vec<u8>> {
+        let line = self
+            .next_line()?
+            .ok_or_else(|| self.error("unexpected end of stream, expected 'data'"))?;
+        let Some(spec) = line.strip_prefix(b"data ") else {
+            return Err(self.error(format!(
+                "expected 'data', found '{}'",
+                String::from_utf8_lossy(&line)
+            )));
+        };
+
+        if let Some(delimiter) = spec.strip_prefix(b"<<") {
+            let delimiter = delimiter.to_vec();
+            let mut data = Vec::new();
+            loop {
+                let line = self
+                    .next_line()?
+                    .ok_or_else(|| self.error("unterminated delimited data"))?;
+                if line == delimiter {
+                    return Ok(data);
+                }
+                data.extend_from_slice(&line);
+                data.push(b'\n');
+            }
+        }
+
+        let len: usize = std::str::from_utf8(spec)
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(|| self.error("invalid data length"))?;
+        let mut data = vec![0; len];
+        self.reader.read_exact(&mut data)?;
+        self.line_no += data.iter().filter(|b| **b == b'\n').count();
+        // A single LF may follow the payload.
+        if self.reader.fill_buf()?.first() == Some(&b'\n') {
+            self.reader.consume(1);
+            self.line_no += 1;
+        }
+        Ok(data)
+    }
+
+    fn next_line(&mut self) -> io::Result<Option<Vec<u8>>> {
+        if let Some(line) = self.pending.take() {
+            return Ok(Some(line));
+        }
+        let mut line = Vec::new();
+        if self.reader.read_until(b'\n', &mut line)? == 0 {
+            return Ok(None);
+        }
+        self.line_no += 1;
+        if line.last() == Some(&b'\n') {
+            line.pop();
+        }
+        Ok(Some(line))
+    }
+
+    fn utf8(&self, bytes: Vec<u8>) -> io::Result<String> {
+        String::from_utf8(bytes).map_err(|e| {
+            self.error(format!(
+                "non-UTF-8 text '{}'",
+                String::from_utf8_lossy(e.as_bytes())
+            ))
+        })
+    }
+
+    fn error(&self, message: impl std::fmt::Display) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("fast-import stream line {}: {message}", self.line_no),
+        )
+    }
+}
+
+/// Writes fast-import commands.
+pub struct StreamWriter<W> {
+    writer: W,
+}
+
+impl<W: Write> StreamWriter<W> {
+    pub fn new(writer: W) -> Self {
+        StreamWriter { writer }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    pub fn write_command(&mut self, command: &Command) -> io::Result<()> {
+        match command {
+            Command::Blob(blob) => {
+                self.line("blob")?;
+                self.optional_mark(blob.mark)?;
+                self.optional_line("original-oid", blob.original_oid.as_deref())?;
+                self.data(&blob.data)
+            }
+            Command::Commit(commit) => {
+                self.line(&format!("commit {}", commit.ref_name))?;
+                self.optional_mark(commit.mark)?;
+                self.optional_line("original-oid", commit.original_oid.as_deref())?;
+                if let Some(author) = &commit.author {
+                    self.ident("author", author)?;
+                }
+                self.ident("committer", &commit.committer)?;
+                self.optional_line("encoding", commit.encoding.as_deref())?;
+                self.data(&commit.message)?;
+                if let Some(from) = &commit.from {
+                    self.line(&format!("from {}", commit_ref(from)))?;
+                }
+                for merge in &commit.merges {
+                    self.line(&format!("merge {}", commit_ref(merge)))?;
+                }
+                for change in &commit.changes {
+                    self.file_change(change)?;
+                }
+                self.line("")
+            }
+            Command::Tag(tag) => {
+                self.line(&format!("tag {}", tag.name))?;
+                self.optional_mark(tag.mark)?;
+                self.line(&format!("from {}", commit_ref(&tag.from)))?;
+                self.optional_line("original-oid", tag.original_oid.as_deref())?;
+                if let Some(tagger) = &tag.tagger {
+                    self.ident("tagger", tagger)?;
+                }
+                self.data(&tag.message)
+            }
+            Command::Reset { ref_name, from } => {
+                self.line(&format!("reset {ref_name}"))?;
+                if let Some(from) = from {
+                    self.line(&format!("from {}", commit_ref(from)))?;
+                }
+                self.line("")
+            }
+            Command::Feature(feature) => self.line(&format!("feature {feature}")),
+            Command::Option(option) => self.line(&format!("option {option}")),
+            Command::Progress(message) => self.line(&format!("progress {message}")),
+            Command::Checkpoint => self.line("checkpoint"),
+            Command::Done => self.line("done"),
+        }
+    }
+
+    fn file_change(&mut self, change: &FileChange) -> io::Result<()> {
+        match change {
+            FileChange::Modify { mode, data, path } => {
+                let mode = String::from_utf8_lossy(mode.to_bytes());
+                let path = quote_path(path, false);
+                match data {
+                    DataRef::Mark(mark) => self.line(&format!("M {mode} :{mark} {path}")),
+                    DataRef::Oid(oid) => self.line(&format!("M {mode} {oid} {path}")),
+                    DataRef::Inline(data) => {
+                        self.line(&format!("M {mode} inline {path}"))?;
+                        self.data(data)
+                    }
+                }
+            }
+            FileChange::Delete { path } => self.line(&format!("D {}", quote_path(path, false))),
+            FileChange::Copy { source, dest } => self.line(&format!(
+                "C {} {}",
+                quote_path(source, true),
+                quote_path(dest, false)
+            )),
+            FileChange::Rename { source, dest } => self.line(&format!(
+                "R {} {}",
+                quote_path(source, true),
+                quote_path(dest, false)
+            )),
+            FileChange::DeleteAll => self.line("deleteall"),
+        }
+    }
+
+    fn ident(&mut self, keyword: &str, signature: &Signature) -> io::Result<()> {
+        let name = if signature.name.is_empty() {
+            String::new()
+        } else {
+            format!("{} ", signature.name)
+        };
+        self.line(&format!(
+            "{keyword} {name}<{}> {} {}",
+            signature.email, signature.timestamp, signature.timezone
+        ))
+    }
+
+    /// Writes `data <count>` and the exact payload, followed by the optional LF.
+    fn data(&mut self, data: &[u8]) -> io::Result<()> {
+        self.line(&format!("data {}", data.len()))?;
+        self.writer.write_all(data)?;
+        self.writer.write_all(b"\n")
+    }
+
+    fn optional_mark(&mut self, mark: Option<u64>) -> io::Result<()> {
+        match mark {
+            Some(mark) => self.line(&format!("mark :{mark}")),
+            None => Ok(()),
+        }
+    }
+
+    fn optional_line(&mut self, keyword: &str, value: Option<&str>) -> io::Result<()> {
+        match value {
+            Some(value) => self.line(&format!("{keyword} {value}")),
+            None => Ok(()),
+        }
+    }
+
+    fn line(&mut self, line: &str) -> io::Result<()> {
+        self.writer.write_all(line.as_bytes())?;
+        self.writer.write_all(b"\n")
+    }
+}
+
+fn commit_ref(commit: &CommitRef) -> String {
+    match commit {
+        CommitRef::Mark(mark) => format!(":{mark}"),
+        CommitRef::Oid(oid) | CommitRef::Ref(oid) => oid.clone(),
+    }
+}
+
+fn is_hex_oid(value: &str) -> bool {
+    matches!(value.len(), 40 | 64) && value.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Quotes `path` C-style when it could not be read back verbatim; `space_ends` is for the
+/// source path of `C`/`R`, where an unquoted space would end the path.
+fn quote_path(path: &str, space_ends: bool) -> String {
+    let needs_quotes = path.starts_with('"')
+        || (space_ends && path.contains(' '))
+        || path.chars().any(|c| c == '\\' || c.is_ascii_control());
+    if !needs_quotes {
+        return path.to_string();
+    }
+    let mut quoted = String::from("\"");
+    for c in path.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_ascii_control() => quoted.push_str(&format!("\\{:03o}", c as u8)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Decodes a C-style quoted path at the start of `raw`, returning it and the bytes consumed.
+fn unquote(raw: &[u8]) -> Result<(Vec<u8>, usize), String> {
+    let mut out = Vec::new();
+    let mut i = 1;
+    while i < raw.len() {
+        match raw[i] {
+            b'"' => return Ok((out, i + 1)),
+            b'\\' => {
+                let escaped = *raw.get(i + 1).ok_or("unterminated escape in quoted path")?;
+                i += 2;
+                match escaped {
+                    b'n' => out.push(b'\n'),
+                    b't' => out.push(b'\t'),
+                    b'r' => out.push(b'\r'),
+                    b'a' => out.push(0x07),
+                    b'b' => out.push(0x08),
+                    b'f' => out.push(0x0c),
+                    b'v' => out.push(0x0b),
+                    b'"' | b'\\' => out.push(escaped),
+                    b'0'..=b'3' => {
+                        let digits = raw
+                            .get(i - 1..i + 2)
+                            .filter(|d| d.iter().all(|b| (b'0'..=b'7').contains(b)))
+                            .ok_or("invalid octal escape in quoted path")?;
+                        out.push(digits.iter().fold(0u8, |acc, d| acc * 8 + (d - b'0')));
+                        i += 2;
+                    }
+                    other => {
+                        return Err(format!(
+                            "unknown escape '\\{}' in quoted path",
+                            other as char
+                        ));
+                    }
+                }
+            }
+            byte => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    Err("unterminated quoted path".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_all(stream: &[u8]) -> Vec<Command> {
+        let mut reader = StreamReader::new(stream);
+        let mut commands = Vec::new();
+        while let Some(command) = reader.next_command().unwrap() {
+            commands.push(command);
+        }
+        commands
+    }
+
+    #[test]
+    fn test_parse_blob_commit_and_tag() {
+        let stream = b"feature done\n\
+blob\nmark :1\noriginal-oid 0123456789012345678901234567890123456789\ndata 6\nhello\n\n\
+reset refs/heads/main\n\
+commit refs/heads/main\nmark :2\n\
+author A U Thor <author@example.com> 1700000000 +0800\n\
+committer <c@example.com> 1700000001 -0130\n\
+data <<EOF\nfirst line\nsecond line\nEOF\n\
+M 100644 :1 \"dir/with space\\303\\251.txt\"\n\
+M 755 inline run.sh\ndata 3\nabc\n\
+D old.txt\nR \"a b\" c\nC x y z\ndeleteall\n\n\
+tag v1\nmark :3\nfrom :2\ntagger T <t@example.com> 1700000002 +0000\ndata 4\nrel\n\n\
+progress halfway\ndone\nignored after done\n";
+        let commands = parse_all(stream);
+        assert_eq!(commands.len(), 7, "{commands:#?}");
+        assert_eq!(commands[0], Command::Feature("done".into()));
+        let Command::Blob(blob) = &commands[1] else {
+            panic!("expected blob")
+        };
+        assert_eq!(blob.mark, Some(1));
+        assert_eq!(blob.data, b"hello\n");
+        assert_eq!(
+            commands[2],
+            Command::Reset {
+                ref_name: "refs/heads/main".into(),
+                from: None
+            }
+        );
+
+        let Command::Commit(commit) = &commands[3] else {
+            panic!("expected commit")
+        };
+        assert_eq!(commit.mark, Some(2));
+        let author = commit.author.as_ref().unwrap();
+        assert_eq!(author.name, "A U Thor");
+        assert_eq!(author.timezone, "+0800");
+        assert_eq!(commit.committer.name, "");
+        assert_eq!(commit.committer.email, "c@example.com");
+        assert_eq!(commit.message, b"first line\nsecond line\n");
+        assert_eq!(
+            commit.changes,
+            vec![
+                FileChange::Modify {
+                    mode: TreeItemMode::Blob,
+                    data: DataRef::Mark(1),
+                    path: "dir/with space\u{e9}.txt".into(),
+                },
+                FileChange::Modify {
+                    mode: TreeItemMode::BlobExecutable,
+                    data: DataRef::Inline(b"abc".to_vec()),
+                    path: "run.sh".into(),
+                },
+                FileChange::Delete {
+                    path: "old.txt".into()
+                },
+                FileChange::Rename {
+                    source: "a b".into(),
+                    dest: "c".into()
+                },
+                FileChange::Copy {
+                    source: "x".into(),
+                    dest: "y z".into()
+                },
+                FileChange::DeleteAll,
+            ]
+        );
+
+        let Command::Tag(tag) = &commands[4] else {
+            panic!("expected tag")
+        };
+        assert_eq!(tag.from, CommitRef::Mark(2));
+        assert_eq!(tag.message, b"rel\n");
+        assert_eq!(commands[5], Command::Progress("halfway".into()));
+        assert_eq!(commands[6], Command::Done);
+    }
+
+    #[test]
+    fn test_commit_ends_at_next_command_without_blank_line() {
+        let stream = b"commit refs/heads/main\n\
+committer C <c@x> 1 +0000\ndata 0\n\
+from 1111111111111111111111111111111111111111\nmerge refs/heads/topic\n\
+M 644 :1 a\n\
+reset refs/tags/v1\nfrom :5\n";
+        let commands = parse_all(stream);
+        let Command::Commit(commit) = &commands[0] else {
+            panic!("expected commit")
+        };
+        assert_eq!(
+            commit.from,
+            Some(CommitRef::Oid(
+                "1111111111111111111111111111111111111111".into()
+            ))
+        );
+        assert_eq!(
+            commit.merges,
+            vec![CommitRef::Ref("refs/heads/topic".into())]
+        );
+        assert_eq!(commit.changes.len(), 1);
+        assert_eq!(
+            commands[1],
+            Command::Reset {
+                ref_name: "refs/tags/v1".into(),
+                from: Some(CommitRef::Mark(5))
+            }
+        );
+    }
+
+    #[test]
+    fn test_errors_report_line_numbers() {
+        let mut reader = StreamReader::new(&b"blob\nmark :1\ndata 2\nab\nls \"a\"\n"[..]);
+        assert!(reader.next_command().unwrap().is_some());
+        let err = reader.next_command().unwrap_err();
+        assert!(err.to_string().contains("line 5"), "{err}");
+        assert!(err.to_string().contains("unsupported command"), "{err}");
+
+        let mut reader = StreamReader::new(&b"commit refs/heads/main\ndata 0\n"[..]);
+        assert!(reader.next_command().is_err());
+    }
+
+    #[test]
+    fn test_written_stream_parses_back() {
+        let signature = |signature_type, name: &str| Signature {
+            signature_type,
+            name: name.to_string(),
+            email: "someone@example.com".to_string(),
+            timestamp: 1700000000,
+            timezone: "-0130".to_string(),
+        };
+        let commands = vec![
+            Command::Feature("done".into()),
+            Command::Blob(BlobCommand {
+                mark: Some(1),
+                original_oid: None,
+                data: vec![0, 159, 146, 150, b'\n', b'\n'],
+            }),
+            Command::Commit(Box::new(CommitCommand {
+                ref_name: "refs/heads/main".into(),
+                mark: Some(2),
+                original_oid: None,
+                author: Some(signature(SignatureType::Author, "")),
+                committer: signature(SignatureType::Committer, "C O Mitter"),
+                encoding: Some("ISO-8859-1".into()),
+                message: b"subject\n\nbody without trailing newline".to_vec(),
+                from: Some(CommitRef::Oid("1".repeat(40))),
+                merges: vec![CommitRef::Mark(7)],
+                changes: vec![
+                    FileChange::Delete {
+                        path: "\"quoted\" name".into(),
+                    },
+                    FileChange::Modify {
+                        mode: TreeItemMode::Blob,
+                        data: DataRef::Mark(1),
+                        path: "dir/tab\tand\\slash".into(),
+                    },
+                    FileChange::Modify {
+                        mode: TreeItemMode::Link,
+                        data: DataRef::Inline(b"target".to_vec()),
+                        path: "with space".into(),
+                    },
+                    FileChange::Rename {
+                        source: "with space".into(),
+                        dest: "moved here".into(),
+                    },
+                ],
+            })),
+            Command::Tag(TagCommand {
+                name: "v1".into(),
+                mark: Some(3),
+                original_oid: None,
+                from: CommitRef::Mark(2),
+                tagger: Some(signature(SignatureType::Tagger, "T")),
+                message: b"release\n".to_vec(),
+            }),
+            Command::Reset {
+                ref_name: "refs/tags/light".into(),
+                from: Some(CommitRef::Mark(2)),
+            },
+            Command::Done,
+        ];
+
+        let mut writer = StreamWriter::new(Vec::new());
+        for command in &commands {
+            writer.write_command(command).unwrap();
+        }
+        let stream = writer.into_inner();
+        assert_eq!(parse_all(&stream), commands);
+    }
+}
@@ -0,0 +1,230 @@
+//! Crash-safe index writes: the new index is written to `index.lock`, fsynced and renamed over
+//! the real index, so readers only ever see a complete file and a second writer is refused.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use git_internal::{errors::GitError, internal::index::Index};
+use thiserror::Error;
+
+use crate::utils::stat_cache;
+
+#[derive(Debug, Error)]
+pub enum IndexLockError {
+    #[error(
+        "unable to create '{}': another libra process holds the index lock (created {})\n\
+         If no other libra process is running, remove the stale lock with 'libra status --force-unlock'",
+        path.display(),
+        format_age(*age)
+    )]
+    Locked {
+        path: PathBuf,
+        age: Option<Duration>,
+    },
+    #[error("failed to write '{}': {source}", path.display())]
+    Io { path: PathBuf, source: io::Error },
+    #[error("failed to write index: {0}")]
+    Write(#[from] GitError),
+}
+
+/// Exclusive right to rewrite an index file, held as `<index>.lock`.
+///
+/// The lock file is removed on drop unless [`IndexLock::commit`] moved it into place, so an
+/// aborted write leaves the old index untouched.
+#[derive(Debug)]
+pub struct IndexLock {
+    index_file: PathBuf,
+    lock_file: PathBuf,
+    committed: bool,
+}
+
+impl IndexLock {
+    /// Take the lock for `index_file`, failing with [`IndexLockError::Locked`] if another
+    /// process holds it.
+    pub fn acquire(index_file: impl AsRef<Path>) -> Result<Self, IndexLockError> {
+        let index_file = index_file.as_ref().to_path_buf();
+        let lock_file = lock_path(&index_file);
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&lock_file)
+        {
+            Ok(_) => Ok(IndexLock {
+                index_file,
+                lock_file,
+                committed: false,
+            }),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                let age = fs::metadata(&lock_file)
+                    .and_then(|meta| meta.modified())
+                    .ok()
+                    .and_then(|created| SystemTime::now().duration_since(created).ok());
+                Err(IndexLockError::Locked {
+                    path: lock_file,
+                    age,
+                })
+            }
+            Err(source) => Err(IndexLockError::Io {
+                path: lock_file,
+                source,
+            }),
+        }
+    }
+
+    /// Path of the lock file the new index is staged in.
+    pub fn path(&self) -> &Path {
+        &self.lock_file
+    }
+
+    /// Write `index` to the lock file, flush it to disk and atomically rename it over the index.
+    ///
+    /// Entries modified no earlier than the write itself are smudged first (see
+    /// [`stat_cache::smudge_racy_entries`]) so a later status cannot trust their stat data.
+    pub fn commit(mut self, index: &mut Index) -> Result<(), IndexLockError> {
+        index.to_file(&self.lock_file)?;
+        let written_at = fs::metadata(&self.lock_file)
+            .and_then(|meta| meta.modified())
+            .map_err(|source| self.io_error(source))?;
+        if stat_cache::smudge_racy_entries(index, written_at) {
+            index.to_file(&self.lock_file)?;
+        }
+        File::open(&self.lock_file)
+            .and_then(|file| file.sync_all())
+            .map_err(|source| self.io_error(source))?;
+        fs::rename(&self.lock_file, &self.index_file).map_err(|source| IndexLockError::Io {
+            path: self.index_file.clone(),
+            source,
+        })?;
+        self.committed = true;
+        // make the rename itself durable; not every platform can open a directory for syncing
+        #[cfg(unix)]
+        if let Some(dir) = self.index_file.parent()
+            && let Ok(dir) = File::open(dir)
+        {
+            let _ = dir.sync_all();
+        }
+        Ok(())
+    }
+
+    fn io_error(&self, source: io::Error) -> IndexLockError {
+        IndexLockError::Io {
+            path: self.lock_file.clone(),
+            source,
+        }
+    }
+}
+
+impl Drop for IndexLock {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = fs::remove_file(&self.lock_file);
+        }
+    }
+}
+
+/// Write `index` to `index_file` through an [`IndexLock`].
+///
+/// Only for an index built from scratch: a caller that loads, edits and saves the index must
+/// acquire the lock before loading and commit through it, or a concurrent writer is lost.
+pub fn save_index(index: &mut Index, index_file: impl AsRef<Path>) -> Result<(), IndexLockError> {
+    IndexLock::acquire(index_file)?.commit(index)
+}
+
+/// Remove a stale lock left behind by a crashed process. Returns whether a lock existed.
+pub fn force_unlock(index_file: impl AsRef<Path>) -> io::Result<bool> {
+    match fs::remove_file(lock_path(index_file.as_ref())) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+fn lock_path(index_file: &Path) -> PathBuf {
+    let mut name = index_file.as_os_str().to_owned();
+    name.push(".lock");
+    PathBuf::from(name)
+}
+
+fn format_age(age: Option<Duration>) -> String {
+    let Some(age) = age else {
+        return "at an unknown time".to_string();
+    };
+    let secs = age.as_secs();
+    match secs {
+        0..60 => format!("{secs} seconds ago"),
+        60..3600 => format!("{} minutes ago", secs / 60),
+        3600..86400 => format!("{} hours ago", secs / 3600),
+        _ => format!("{} days ago", secs / 86400),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use git_internal::{hash::ObjectHash, internal::index::IndexEntry};
+
+    use super::*;
+
+    fn index_with(names: &[&str]) -> Index {
+        let mut index = Index::new();
+        for name in names {
+            let hash = ObjectHash::new(name.as_bytes());
+            index.add(IndexEntry::new_from_blob(name.to_string(), hash, 1));
+        }
+        index
+    }
+
+    #[test]
+    fn test_second_lock_is_refused_with_age() {
+        let temp = tempfile::tempdir().unwrap();
+        let index_file = temp.path().join("index");
+        let lock = IndexLock::acquire(&index_file).unwrap();
+
+        let err = IndexLock::acquire(&index_file).unwrap_err();
+        assert!(matches!(err, IndexLockError::Locked { age: Some(_), .. }));
+        let message = err.to_string();
+        assert!(message.contains("another libra process holds the index lock"));
+        assert!(message.contains("seconds ago"));
+        assert!(message.contains("--force-unlock"));
+
+        drop(lock);
+        assert!(!lock_path(&index_file).exists());
+        IndexLock::acquire(&index_file).unwrap();
+    }
+
+    #[test]
+    fn test_dropped_write_leaves_index_intact() {
+        let temp = tempfile::tempdir().unwrap();
+        let index_file = temp.path().join("index");
+        save_index(&mut index_with(&["a.txt"]), &index_file).unwrap();
+
+        // a writer that dies halfway: partial bytes in the lock, never committed
+        let lock = IndexLock::acquire(&index_file).unwrap();
+        fs::write(lock.path(), b"DIRC\0\0").unwrap();
+        drop(lock);
+
+        let index = Index::load(&index_file).unwrap();
+        assert!(index.tracked("a.txt", 0));
+        assert!(!lock_path(&index_file).exists());
+    }
+
+    #[test]
+    fn test_force_unlock_clears_stale_lock() {
+        let temp = tempfile::tempdir().unwrap();
+        let index_file = temp.path().join("index");
+        // a crashed process never runs its destructor
+        std::mem::forget(IndexLock::acquire(&index_file).unwrap());
+
+        let mut index = index_with(&["a.txt", "b.txt"]);
+        assert!(save_index(&mut index, &index_file).is_err());
+        assert!(!index_file.exists());
+
+        assert!(force_unlock(&index_file).unwrap());
+        assert!(!force_unlock(&index_file).unwrap());
+        save_index(&mut index, &index_file).unwrap();
+        assert_eq!(Index::load(&index_file).unwrap().size(), 2);
+    }
+}
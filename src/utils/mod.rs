@@ -1,13 +1,17 @@
-//! Utilities module aggregator exposing storage, path, object, LFS, and testing helpers.
+//! Utilities module aggregator exposing storage, path, object, fast-import stream, LFS, and testing helpers.
 
 pub mod client_storage;
 pub mod convert;
+pub mod fast_import;
 pub mod ignore;
+pub mod index_lock;
 pub mod lfs;
 pub mod object;
 pub mod object_ext;
 pub mod path;
 pub mod path_ext;
+pub mod rename;
+pub mod stat_cache;
 pub mod storage;
 pub mod storage_ext;
 pub mod test;
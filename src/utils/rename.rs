@@ -0,0 +1,78 @@
+//! Rename detection between two trees, used to follow a single file across moves
+//! (`log --follow`, `blame --follow`).
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use git_internal::{
+    hash::ObjectHash,
+    internal::object::{blob::Blob, tree::Tree},
+};
+
+use crate::{command::load_object, utils::object_ext::TreeExt};
+
+/// Lowest [`similarity`] at which a deleted file counts as the source of a new one, like
+/// git's default of 50%.
+pub const RENAME_THRESHOLD: u8 = 50;
+
+/// Percentage of lines two contents share: twice the common lines over the lines of both.
+/// Two empty contents are identical.
+pub fn similarity(old: &[u8], new: &[u8]) -> u8 {
+    let old = String::from_utf8_lossy(old);
+    let new = String::from_utf8_lossy(new);
+    let total = old.lines().count() + new.lines().count();
+    if total == 0 {
+        return 100;
+    }
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for line in old.lines() {
+        *counts.entry(line).or_default() += 1;
+    }
+    let mut common = 0;
+    for line in new.lines() {
+        if let Some(count) = counts.get_mut(line)
+            && *count > 0
+        {
+            *count -= 1;
+            common += 1;
+        }
+    }
+    (common * 200 / total) as u8
+}
+
+/// The path `new_path` of `new_tree` had in `old_tree`, if the file was renamed there.
+///
+/// Only paths that exist in `old_tree` but not in `new_tree` are candidates. A candidate
+/// with the same blob wins; otherwise the most similar one at or above
+/// [`RENAME_THRESHOLD`], ties going to the first path in tree order. Returns `None` when
+/// `new_path` already exists in `old_tree` or is missing from `new_tree`.
+pub fn find_rename_source(old_tree: &Tree, new_tree: &Tree, new_path: &Path) -> Option<PathBuf> {
+    let old_items = old_tree.get_plain_items();
+    let new_items = new_tree.get_plain_items();
+    if old_items.iter().any(|(path, _)| path == new_path) {
+        return None;
+    }
+    let (_, new_blob) = new_items.iter().find(|(path, _)| path == new_path)?;
+    let candidates: Vec<&(PathBuf, ObjectHash)> = old_items
+        .iter()
+        .filter(|(path, _)| !new_items.iter().any(|(new, _)| new == path))
+        .collect();
+    if let Some((path, _)) = candidates.iter().find(|(_, blob)| blob == new_blob) {
+        return Some(path.clone());
+    }
+
+    let new_data = load_object::<Blob>(new_blob).ok()?.data;
+    let mut best: Option<(u8, &PathBuf)> = None;
+    for (path, blob) in candidates {
+        let Ok(old) = load_object::<Blob>(blob) else {
+            continue;
+        };
+        let score = similarity(&old.data, &new_data);
+        if score >= RENAME_THRESHOLD && best.is_none_or(|(best, _)| score > best) {
+            best = Some((score, path));
+        }
+    }
+    best.map(|(_, path)| path.clone())
+}
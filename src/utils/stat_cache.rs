@@ -0,0 +1,173 @@
+//! Stat cache over index entries: when a file's size, timestamps and inode still match what the
+//! index recorded, its content is trusted to be unchanged and is not hashed again.
+//!
+//! Timestamps are only as precise as the filesystem, so a file written in the same tick as the
+//! index cannot be told apart from one modified right after it. Like git, such "racily clean"
+//! entries are never trusted on read, and are smudged (size zeroed) whenever the index is
+//! rewritten so that later, newer index files do not hide the race.
+
+use std::{
+    fs::Metadata,
+    io,
+    path::Path,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::SystemTime,
+};
+
+use git_internal::{
+    hash::ObjectHash,
+    internal::index::{Index, IndexEntry, Time},
+};
+
+use crate::command::calc_file_blob_hash;
+
+static CONTENT_HASHES: AtomicUsize = AtomicUsize::new(0);
+
+/// Decides whether index entries can be trusted without reading file content.
+pub struct StatCache {
+    index_mtime: Option<SystemTime>,
+}
+
+impl StatCache {
+    /// Cache judged against the modification time of `index_file` as it is on disk now.
+    pub fn new(index_file: impl AsRef<Path>) -> Self {
+        let index_mtime = index_file
+            .as_ref()
+            .metadata()
+            .and_then(|meta| meta.modified())
+            .ok();
+        StatCache { index_mtime }
+    }
+
+    /// Whether `entry` still describes the file with metadata `meta` (from `symlink_metadata`),
+    /// so its recorded hash can be used as is.
+    pub fn is_fresh(&self, entry: &IndexEntry, meta: &Metadata) -> bool {
+        stat_matches(entry, meta) && !self.is_racy(meta)
+    }
+
+    fn is_racy(&self, meta: &Metadata) -> bool {
+        match (self.index_mtime, meta.modified()) {
+            (Some(index_mtime), Ok(mtime)) => mtime >= index_mtime,
+            _ => true,
+        }
+    }
+}
+
+/// Compare the stat data recorded in `entry` with `meta`: size, mtime, creation time where the
+/// platform reports one, and inode and executable bit on unix.
+pub fn stat_matches(entry: &IndexEntry, meta: &Metadata) -> bool {
+    if entry.size != meta.len() as u32 {
+        return false;
+    }
+    match meta.modified() {
+        Ok(mtime) if entry.mtime == Time::from_system_time(mtime) => {}
+        _ => return false,
+    }
+    if let Ok(ctime) = meta.created()
+        && entry.ctime != Time::from_system_time(ctime)
+    {
+        return false;
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        if entry.ino != 0 && entry.ino != meta.ino() as u32 {
+            return false;
+        }
+        if meta.is_file() && (entry.mode == 0o100755) != (meta.mode() & 0o111 != 0) {
+            return false;
+        }
+    }
+    true
+}
+
+/// Hash a worktree file as a blob, counting the call for [`content_hashes`].
+pub fn hash_file(path: impl AsRef<Path>) -> io::Result<ObjectHash> {
+    CONTENT_HASHES.fetch_add(1, Ordering::Relaxed);
+    calc_file_blob_hash(path)
+}
+
+/// Number of worktree files hashed through [`hash_file`] by this process.
+pub fn content_hashes() -> usize {
+    CONTENT_HASHES.load(Ordering::Relaxed)
+}
+
+/// Zero the size of every entry modified no earlier than `written_at`, the timestamp of the index
+/// file being written, so its stat data never matches again. Returns whether any was smudged.
+pub fn smudge_racy_entries(index: &mut Index, written_at: SystemTime) -> bool {
+    let written_at = time_parts(&Time::from_system_time(written_at));
+    let racy: Vec<(String, u8)> = (0..=3)
+        .flat_map(|stage| index.tracked_entries(stage))
+        .filter(|entry| entry.size != 0 && time_parts(&entry.mtime) >= written_at)
+        .map(|entry| (entry.name.clone(), entry.flags.stage))
+        .collect();
+    for (name, stage) in &racy {
+        if let Some(mut entry) = index.remove(name, *stage) {
+            entry.size = 0;
+            index.add(entry);
+        }
+    }
+    !racy.is_empty()
+}
+
+/// `Time` keeps its fields private; its `Display` form is `seconds:nanos`.
+fn time_parts(time: &Time) -> (u32, u32) {
+    let text = time.to_string();
+    let (seconds, nanos) = text.split_once(':').unwrap_or((&text, "0"));
+    (seconds.parse().unwrap_or(0), nanos.parse().unwrap_or(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, time::Duration};
+
+    use super::*;
+
+    #[test]
+    fn test_fresh_entry_needs_no_hash_until_touched() {
+        let temp = tempfile::tempdir().unwrap();
+        let file = temp.path().join("a.txt");
+        fs::write(&file, "hello\n").unwrap();
+        let hash = ObjectHash::new(b"hello\n");
+        let entry = IndexEntry::new_from_file(Path::new("a.txt"), hash, temp.path()).unwrap();
+        let index_file = temp.path().join("index");
+        std::thread::sleep(Duration::from_millis(10));
+        fs::write(&index_file, b"index").unwrap();
+
+        let cache = StatCache::new(&index_file);
+        assert!(cache.is_fresh(&entry, &file.symlink_metadata().unwrap()));
+
+        // same size, new timestamp: content has to be checked
+        std::thread::sleep(Duration::from_millis(10));
+        fs::write(&file, "HELLO\n").unwrap();
+        assert!(!cache.is_fresh(&entry, &file.symlink_metadata().unwrap()));
+    }
+
+    #[test]
+    fn test_racy_entries_are_not_trusted_and_get_smudged() {
+        let temp = tempfile::tempdir().unwrap();
+        let file = temp.path().join("a.txt");
+        fs::write(&file, "hello\n").unwrap();
+        let hash = ObjectHash::new(b"hello\n");
+        let entry = IndexEntry::new_from_file(Path::new("a.txt"), hash, temp.path()).unwrap();
+        let mtime = file.metadata().unwrap().modified().unwrap();
+
+        // an index written in the same tick as the file
+        let index_file = temp.path().join("index");
+        fs::write(&index_file, b"index").unwrap();
+        let file_time = fs::File::options().write(true).open(&index_file).unwrap();
+        file_time.set_modified(mtime).unwrap();
+        let cache = StatCache::new(&index_file);
+        assert!(stat_matches(&entry, &file.symlink_metadata().unwrap()));
+        assert!(!cache.is_fresh(&entry, &file.symlink_metadata().unwrap()));
+
+        let mut index = Index::new();
+        index.add(entry);
+        assert!(!smudge_racy_entries(
+            &mut index,
+            mtime + Duration::from_secs(1)
+        ));
+        assert!(smudge_racy_entries(&mut index, mtime));
+        assert_eq!(index.get("a.txt", 0).unwrap().size, 0);
+    }
+}
@@ -8,5 +8,9 @@
 # Example
 # Write-Host "Running pre-commit hook"
 
+# AI review of the staged changes (needs `libra config ai.precommit.provider <provider>`;
+# skipped when unset or when LIBRA_SKIP_AI_REVIEW=1)
+# libra ai pre-commit-review; if ($LASTEXITCODE -ne 0) { exit 1 }
+
 
 exit 0
@@ -7,5 +7,9 @@
 # Example
 # echo "Running pre-commit hook"
 
+# AI review of the staged changes (needs `libra config ai.precommit.provider <provider>`;
+# skipped when unset or when LIBRA_SKIP_AI_REVIEW=1)
+# libra ai pre-commit-review || exit 1
+
 
 exit 0
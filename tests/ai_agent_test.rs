@@ -18,7 +18,7 @@ use libra::internal::ai::{
     },
     node_adapter::AgentAction,
     providers::gemini::Client,
-    tools::{Tool, ToolDefinition, ToolSet},
+    tools::{Tool, ToolCallError, ToolDefinition, ToolSet},
 };
 use serde_json::json;
 
@@ -134,10 +134,7 @@ impl Tool for WeatherTool {
         }
     }
 
-    fn call(
-        &self,
-        _args: serde_json::Value,
-    ) -> Result<serde_json::Value, Box<dyn std::error::Error + Send + Sync>> {
+    fn call(&self, _args: serde_json::Value) -> Result<serde_json::Value, ToolCallError> {
         self.called.store(true, Ordering::SeqCst);
         Ok(json!({ "temperature": "22", "unit": "celsius", "description": "Sunny" }))
     }
@@ -167,8 +164,8 @@ fn test_gemini_agent_with_tools() {
 
     let tool_called = Arc::new(AtomicBool::new(false));
 
-    let mut tool_set = ToolSet::default();
-    tool_set.tools.push(std::sync::Arc::new(WeatherTool {
+    let tool_set = ToolSet::default();
+    tool_set.add(std::sync::Arc::new(WeatherTool {
         called: tool_called.clone(),
     }));
 
@@ -270,18 +267,15 @@ mod error_tests {
                 parameters: json!({"type": "object"}),
             }
         }
-        fn call(
-            &self,
-            _args: serde_json::Value,
-        ) -> Result<serde_json::Value, Box<dyn std::error::Error + Send + Sync>> {
+        fn call(&self, _args: serde_json::Value) -> Result<serde_json::Value, ToolCallError> {
             Ok(json!({"result": "ok"}))
         }
     }
 
     #[tokio::test]
     async fn test_max_steps_exceeded() {
-        let mut tools = ToolSet::default();
-        tools.tools.push(std::sync::Arc::new(MockTool));
+        let tools = ToolSet::default();
+        tools.add(std::sync::Arc::new(MockTool));
 
         let agent = AgentBuilder::new(MockLoopModel)
             .tools(tools)
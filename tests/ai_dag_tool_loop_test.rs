@@ -1,6 +1,7 @@
 use std::{
     collections::VecDeque,
     sync::{Arc, Mutex},
+    time::{Duration, Instant},
 };
 
 use async_trait::async_trait;
@@ -9,6 +10,7 @@ use dagrs::{
 };
 use libra::internal::ai::{
     ToolLoopAction,
+    budget::SharedBudget,
     completion::{
         AssistantContent, CompletionError, CompletionModel, CompletionRequest, CompletionResponse,
         Function, Text, ToolCall,
@@ -131,3 +133,143 @@ fn test_dag_tool_loop_action_applies_patch() {
     let output = outputs.get(&b_id).unwrap().clone().unwrap();
     assert_eq!(&*output, "done");
 }
+
+#[test]
+fn test_dag_shared_budget_fails_only_the_branch_that_exhausts_it() {
+    let temp_dir = TempDir::new().unwrap();
+    // Each branch answers in a single call; the budget covers two of the three.
+    let budget = SharedBudget::new().with_max_calls(2);
+
+    let mut node_table = NodeTable::new();
+    let mut graph = Graph::new();
+    let mut branch_ids = Vec::new();
+    for branch in 0..3 {
+        let scripted = ScriptedModel::new(vec![CompletionResponse {
+            content: vec![AssistantContent::Text(Text {
+                text: format!("branch {branch} done"),
+            })],
+            raw_response: (),
+        }]);
+        let registry = ToolRegistryBuilder::with_working_dir(temp_dir.path().to_path_buf()).build();
+        let action = ToolLoopAction::new(scripted, registry, None, Some(0.0), Some(4))
+            .with_budget(budget.clone());
+        let node = DefaultNode::with_action(format!("branch-{branch}"), action, &mut node_table);
+        branch_ids.push(node.id());
+        graph.add_node(node);
+    }
+
+    // The graph reports the failed branch; the other two still finish.
+    let _ = graph.start();
+
+    let outputs = graph.get_outputs();
+    let errors: Vec<String> = branch_ids
+        .iter()
+        .filter_map(|id| outputs.get(id).and_then(|output| output.get_err()))
+        .collect();
+    assert_eq!(errors.len(), 1, "outputs: {outputs:?}");
+    assert!(
+        errors[0].contains("Budget exceeded: 2 of 2 model calls used"),
+        "{}",
+        errors[0]
+    );
+
+    let results = graph.get_results::<String>();
+    let finished = branch_ids
+        .iter()
+        .filter(|id| results.get(id).is_some_and(|result| result.is_some()))
+        .count();
+    assert_eq!(finished, 2);
+
+    let snapshot = budget.snapshot();
+    assert_eq!(
+        (snapshot.calls_used, snapshot.calls_remaining()),
+        (2, Some(0))
+    );
+}
+
+/// Answers with `first`, then never answers again.
+#[derive(Clone)]
+struct StallingModel {
+    first: Arc<Mutex<Option<CompletionResponse<()>>>>,
+}
+
+impl CompletionModel for StallingModel {
+    type Response = ();
+
+    async fn completion(
+        &self,
+        _request: CompletionRequest,
+    ) -> Result<CompletionResponse<Self::Response>, CompletionError> {
+        let first = self.first.lock().unwrap().take();
+        match first {
+            Some(response) => Ok(response),
+            None => std::future::pending().await,
+        }
+    }
+}
+
+#[test]
+fn test_dag_deadline_fails_node_with_partial_result() {
+    let temp_dir = TempDir::new().unwrap();
+    let file_path = temp_dir.path().join("target.txt");
+    std::fs::write(&file_path, "line 1\n").unwrap();
+
+    let patch = "*** Begin Patch
+*** Update File: target.txt
+@@
+-line 1
++line one
+*** End Patch";
+    let model = StallingModel {
+        first: Arc::new(Mutex::new(Some(CompletionResponse {
+            content: vec![
+                AssistantContent::Text(Text {
+                    text: "Renaming the first line.".to_string(),
+                }),
+                AssistantContent::ToolCall(ToolCall {
+                    id: "call-1".to_string(),
+                    name: "apply_patch".to_string(),
+                    function: Function {
+                        name: "apply_patch".to_string(),
+                        arguments: serde_json::json!({ "input": patch }),
+                    },
+                }),
+            ],
+            raw_response: (),
+        }))),
+    };
+    let registry = ToolRegistryBuilder::with_working_dir(temp_dir.path().to_path_buf())
+        .register("apply_patch", Arc::new(ApplyPatchHandler))
+        .build();
+    // The node timeout alone would stop the node long before the deadline; with both, the
+    // deadline decides so the loop can report what it finished.
+    let action = ToolLoopAction::new(model, registry, None, Some(0.0), Some(4))
+        .with_deadline(Duration::from_millis(200))
+        .with_node_timeout(Duration::from_millis(1));
+
+    let mut node_table = NodeTable::new();
+    let node = DefaultNode::with_action("ai".to_string(), action, &mut node_table);
+    let node_id = node.id();
+    let mut graph = Graph::new();
+    graph.add_node(node);
+
+    let started = Instant::now();
+    let _ = graph.start();
+    assert!(started.elapsed() < Duration::from_secs(5));
+
+    let outputs = graph.get_outputs();
+    let error = outputs
+        .get(&node_id)
+        .and_then(|output| output.get_err())
+        .unwrap();
+    assert!(
+        error.starts_with("Deadline exceeded: the run did not finish within 200 ms"),
+        "{error}"
+    );
+    assert!(
+        error.ends_with("Partial result:\nRenaming the first line."),
+        "{error}"
+    );
+    // Work finished before the deadline is kept.
+    assert_eq!(std::fs::read_to_string(&file_path).unwrap(), "line one\n");
+}
@@ -0,0 +1,17 @@
+//! Replays the agent evaluation scenarios in `tests/ai_scenarios` against the embedded
+//! profiles. Run live scenarios with `libra ai eval --live`.
+
+use std::path::Path;
+
+use libra::internal::ai::{agent::profile::load_embedded_profiles, eval::run_replay_scenarios};
+
+#[tokio::test]
+async fn replay_scenarios_pass() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/ai_scenarios");
+    let report = run_replay_scenarios(&dir, load_embedded_profiles())
+        .await
+        .unwrap();
+
+    assert!(report.passed() >= 2, "{report}");
+    assert_eq!(report.failed(), 0, "{report}");
+}
@@ -0,0 +1,170 @@
+//! Tests `libra ai` through the binary: job queue management (submit, list, cancel, run and
+//! show, for prompts and queued subcommands), `ai eval` reporting and the no-op paths of
+//! `ai pre-commit-review`.
+
+use std::{path::Path, process::Output};
+
+use super::*;
+
+/// Runs `libra ai` with no provider API key in the environment.
+fn ai(dir: &Path, args: &[&str]) -> Output {
+    libra_command(dir)
+        .env_remove("GEMINI_API_KEY")
+        .arg("ai")
+        .args(args)
+        .output()
+        .expect("Failed to execute libra binary")
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn test_ai_job_queue() {
+    let temp = tempdir().unwrap();
+    run_libra(temp.path(), &["init"]);
+
+    let submit = |operation: &[&str]| {
+        let args = [&["job", "submit", "--"], operation].concat();
+        let out = stdout(&ai(temp.path(), &args));
+        out.trim()
+            .strip_prefix("Submitted job ")
+            .unwrap_or_else(|| panic!("unexpected submit output: {out}"))
+            .to_string()
+    };
+    let first = submit(&["prompt", "--provider", "gemini", "first"]);
+    let second = submit(&["prompt", "--provider", "gemini", "second"]);
+    assert!(
+        temp.path()
+            .join(".libra/ai/jobs")
+            .join(format!("{first}.json"))
+            .exists()
+    );
+
+    // Other `ai` subcommands queue too, and run offline when they need no provider.
+    let scenarios = temp.path().join("scenarios");
+    std::fs::create_dir(&scenarios).unwrap();
+    std::fs::copy(
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/ai_scenarios/planner_phased_plan.yaml"),
+        scenarios.join("planner.yaml"),
+    )
+    .unwrap();
+    let eval = submit(&["eval", "--dir", scenarios.to_str().unwrap()]);
+
+    // Malformed operations are rejected at submission.
+    for bad in [&["summarize"][..], &["explain"], &["work", "--bogus", "t1"]] {
+        let output = ai(temp.path(), &[&["job", "submit", "--"][..], bad].concat());
+        assert!(
+            String::from_utf8_lossy(&output.stderr).contains("fatal:"),
+            "{bad:?} was accepted"
+        );
+    }
+
+    let list = run_libra(temp.path(), &["ai", "job", "list"]);
+    let lines = list.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 3, "{list}");
+    assert!(lines[0].starts_with(&first) && lines[0].contains("pending"));
+    assert!(lines[1].starts_with(&second) && lines[1].ends_with("prompt --provider gemini second"));
+
+    let cancel = run_libra(temp.path(), &["ai", "job", "cancel", &second]);
+    assert_eq!(cancel.trim(), format!("Cancelled job {second}"));
+
+    // Without an API key the prompt fails; the cancelled one is skipped.
+    let run = stdout(&ai(temp.path(), &["job", "run"]));
+    assert_eq!(
+        run.lines().collect::<Vec<_>>(),
+        [format!("{first} failed"), format!("{eval} succeeded")]
+    );
+
+    let show = run_libra(temp.path(), &["ai", "job", "show", &first]);
+    assert!(show.contains("Status:    failed"), "{show}");
+    assert!(
+        show.contains("Error:     GEMINI_API_KEY is not set"),
+        "{show}"
+    );
+
+    let show = run_libra(temp.path(), &["ai", "job", "show", &second]);
+    assert!(show.contains("Status:    cancelled"), "{show}");
+
+    // The subcommand's output is captured in the log and summarized as the result.
+    let show = run_libra(temp.path(), &["ai", "job", "show", &eval]);
+    assert!(show.contains("Status:    succeeded"), "{show}");
+    assert!(
+        show.contains("Result:    1 passed, 0 failed, 0 skipped"),
+        "{show}"
+    );
+    assert!(show.contains("PASS planner_outlines_phases"), "{show}");
+
+    let again = stdout(&ai(temp.path(), &["job", "run"]));
+    assert_eq!(again.trim(), "No pending jobs");
+}
+
+#[test]
+fn test_ai_eval_reports_scenarios() {
+    let temp = tempdir().unwrap();
+    run_libra(temp.path(), &["init"]);
+    let scenarios = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/ai_scenarios");
+    std::fs::copy(
+        scenarios.join("planner_phased_plan.yaml"),
+        temp.path().join("a_planner.yaml"),
+    )
+    .unwrap();
+    std::fs::write(
+        temp.path().join("b_wrong.yaml"),
+        "name: wrong_answer\nprompt: hi\ntranscript:\n  - text: hello\n\
+         assert:\n  - answer_equals: goodbye\n",
+    )
+    .unwrap();
+    std::fs::write(
+        temp.path().join("c_live.yaml"),
+        "name: live_only\nprompt: hi\nlive: true\n",
+    )
+    .unwrap();
+
+    let dir = temp.path().to_str().unwrap();
+    let output = ai(temp.path(), &["eval", "--dir", dir]);
+    let out = stdout(&output);
+    assert!(out.contains("PASS planner_outlines_phases\n"), "{out}");
+    assert!(out.contains("FAIL wrong_answer"), "{out}");
+    assert!(out.contains("  - answer differs from expected\n"), "{out}");
+    assert!(out.contains("    -goodbye\n    +hello\n"), "{out}");
+    assert!(out.contains("SKIP live_only (live scenario)"), "{out}");
+    assert!(out.ends_with("1 passed, 1 failed, 1 skipped\n"), "{out}");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("fatal: 1 scenario(s) failed"));
+
+    let live = ai(temp.path(), &["eval", "--dir", dir, "--live"]);
+    assert!(String::from_utf8_lossy(&live.stderr).contains("fatal: GEMINI_API_KEY is not set"));
+}
+
+#[test]
+fn test_ai_pre_commit_review_never_blocks_without_provider() {
+    let temp = tempdir().unwrap();
+    run_libra(temp.path(), &["init"]);
+    std::fs::write(temp.path().join("a.txt"), "hello\n").unwrap();
+    run_libra(temp.path(), &["add", "a.txt"]);
+
+    let output = ai(temp.path(), &["pre-commit-review"]);
+    assert!(output.status.success());
+    assert!(
+        stdout(&output).contains("AI review skipped: no provider configured"),
+        "{}",
+        stdout(&output)
+    );
+
+    let skipped = libra_command(temp.path())
+        .env("LIBRA_SKIP_AI_REVIEW", "1")
+        .env("LIBRA_CONFIG_AI_PRECOMMIT_PROVIDER", "gemini")
+        .args(["ai", "pre-commit-review"])
+        .output()
+        .unwrap();
+    assert!(skipped.status.success());
+    assert!(stdout(&skipped).contains("LIBRA_SKIP_AI_REVIEW is set"));
+
+    // A configured provider without its API key reports the problem but lets the commit pass.
+    let config = &["config", "ai.precommit.provider", "gemini"];
+    run_libra(temp.path(), config);
+    let no_key = ai(temp.path(), &["pre-commit-review"]);
+    assert!(no_key.status.success());
+    assert!(String::from_utf8_lossy(&no_key.stderr).contains("GEMINI_API_KEY is not set"));
+}
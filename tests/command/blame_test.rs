@@ -1,6 +1,6 @@
 //! Tests blame output to ensure line attribution and formatting for specified commits and ranges.
 
-use std::{fs, io::Write};
+use std::{fs, io::Write, path::PathBuf};
 
 use libra::command::{
     add::{self, AddArgs},
@@ -100,6 +100,8 @@ async fn blame_runs_with_sha1() {
         file: "foo.txt".into(),
         commit: "HEAD".into(),
         line_range: None,
+        no_cache: false,
+        follow: false,
     })
     .await;
 }
@@ -116,6 +118,8 @@ async fn blame_runs_with_sha256() {
         file: "foo.txt".into(),
         commit: "HEAD".into(),
         line_range: None,
+        no_cache: false,
+        follow: false,
     })
     .await;
 }
@@ -134,3 +138,108 @@ async fn blame_rejects_sha1_length_on_sha256_repo() {
         "expect get_target_commit to reject SHA-1 length hash in SHA-256 repo"
     );
 }
+
+#[tokio::test]
+#[serial]
+async fn blame_reuses_cached_line_mappings() {
+    let repo = tempdir().unwrap();
+    let _guard = setup_repo_with_hash(&repo, "sha1").await;
+    let (first, second) = prepare_history().await;
+    let args = |no_cache: bool| BlameArgs {
+        file: "foo.txt".into(),
+        commit: "HEAD".into(),
+        line_range: None,
+        no_cache,
+        follow: false,
+    };
+
+    let uncached = blame::blame_file(&args(true)).await.unwrap();
+    assert_eq!(uncached.diffs, 1);
+    assert!(!repo.path().join(".libra/info/blame-cache").exists());
+    let commits: Vec<_> = uncached.lines.iter().map(|l| l.commit_id).collect();
+    assert_eq!(commits, [first, second]);
+
+    let cold = blame::blame_file(&args(false)).await.unwrap();
+    assert_eq!(cold.diffs, 1);
+    assert!(repo.path().join(".libra/info/blame-cache").exists());
+    let warm = blame::blame_file(&args(false)).await.unwrap();
+    assert_eq!(warm.diffs, 0);
+    assert_eq!(warm.lines, uncached.lines);
+
+    // After another edit only the new version pair is diffed.
+    fs::write("foo.txt", "line0\nline1\nline2-modified\n").unwrap();
+    add::execute(AddArgs {
+        pathspec: vec!["foo.txt".into()],
+        all: false,
+        update: false,
+        refresh: false,
+        force: false,
+        verbose: false,
+        dry_run: false,
+        ignore_errors: false,
+    })
+    .await;
+    commit::execute(CommitArgs {
+        message: Some("prepend".into()),
+        ..Default::default()
+    })
+    .await;
+    let third = get_target_commit("HEAD").await.unwrap();
+    let after_edit = blame::blame_file(&args(false)).await.unwrap();
+    assert_eq!(after_edit.diffs, 1);
+    assert_eq!(
+        after_edit.lines,
+        blame::blame_file(&args(true)).await.unwrap().lines
+    );
+    assert_eq!(after_edit.lines[0].commit_id, third);
+}
+
+#[tokio::test]
+#[serial]
+async fn blame_follow_attributes_lines_across_rename() {
+    let repo = tempdir().unwrap();
+    let _guard = setup_repo_with_hash(&repo, "sha1").await;
+    let (first, second) = prepare_history().await;
+
+    // Rename foo.txt to bar.txt and append a line in the same commit.
+    fs::remove_file("foo.txt").unwrap();
+    fs::write("bar.txt", "line1\nline2-modified\nline3\n").unwrap();
+    add::execute(AddArgs {
+        pathspec: vec![],
+        all: true,
+        update: false,
+        refresh: false,
+        force: false,
+        verbose: false,
+        dry_run: false,
+        ignore_errors: false,
+    })
+    .await;
+    commit::execute(CommitArgs {
+        message: Some("rename".into()),
+        ..Default::default()
+    })
+    .await;
+    let third = get_target_commit("HEAD").await.unwrap();
+    let args = |follow: bool| BlameArgs {
+        file: "bar.txt".into(),
+        commit: "HEAD".into(),
+        line_range: None,
+        no_cache: true,
+        follow,
+    };
+
+    let unfollowed = blame::blame_file(&args(false)).await.unwrap();
+    assert!(unfollowed.lines.iter().all(|l| l.commit_id == third));
+    assert!(unfollowed.renames.is_empty());
+
+    let followed = blame::blame_file(&args(true)).await.unwrap();
+    let commits: Vec<_> = followed.lines.iter().map(|l| l.commit_id).collect();
+    assert_eq!(commits, [first, second, third]);
+    let paths: Vec<_> = followed.lines.iter().map(|l| l.path.clone()).collect();
+    assert_eq!(paths, ["foo.txt", "foo.txt", "bar.txt"].map(PathBuf::from));
+    assert_eq!(
+        followed.renames,
+        [(PathBuf::from("foo.txt"), PathBuf::from("bar.txt"))]
+    );
+}
@@ -66,6 +66,7 @@ async fn test_branch() {
             all: false,
             contains: vec![],
             no_contains: vec![],
+            override_protection: false,
         };
         execute(args).await;
 
@@ -98,6 +99,7 @@ async fn test_branch() {
             all: false,
             contains: vec![],
             no_contains: vec![],
+            override_protection: false,
         };
         execute(args).await;
         let second_branch = Branch::find_branch(&second_branch_name, None)
@@ -122,6 +124,7 @@ async fn test_branch() {
         all: false,
         contains: vec![],
         no_contains: vec![],
+        override_protection: false,
     };
     execute(args).await;
 
@@ -171,6 +174,7 @@ async fn test_create_branch_from_remote() {
         all: false,
         contains: vec![],
         no_contains: vec![],
+        override_protection: false,
     };
     execute(args).await;
 
@@ -217,6 +221,7 @@ async fn test_invalid_branch_name() {
         all: false,
         contains: vec![],
         no_contains: vec![],
+        override_protection: false,
     };
     execute(args).await;
 
@@ -265,6 +270,7 @@ async fn test_branch_rename() {
         all: false,
         contains: vec![],
         no_contains: vec![],
+        override_protection: false,
     };
     execute(args).await;
 
@@ -287,6 +293,7 @@ async fn test_branch_rename() {
         all: false,
         contains: vec![],
         no_contains: vec![],
+        override_protection: false,
     };
     execute(args).await;
 
@@ -350,6 +357,7 @@ async fn test_rename_current_branch() {
         all: false,
         contains: vec![],
         no_contains: vec![],
+        override_protection: false,
     };
     execute(args).await;
 
@@ -411,6 +419,7 @@ async fn test_rename_to_existing_branch() {
         all: false,
         contains: vec![],
         no_contains: vec![],
+        override_protection: false,
     };
     execute(args).await;
 
@@ -427,6 +436,7 @@ async fn test_rename_to_existing_branch() {
         all: false,
         contains: vec![],
         no_contains: vec![],
+        override_protection: false,
     };
     execute(args).await;
 
@@ -444,6 +454,7 @@ async fn test_rename_to_existing_branch() {
         all: false,
         contains: vec![],
         no_contains: vec![],
+        override_protection: false,
     };
     execute(args).await;
 
@@ -491,6 +502,7 @@ async fn test_list_all_branches() {
         all: false,
         contains: vec![],
         no_contains: vec![],
+        override_protection: false,
     };
     execute(args).await;
 
@@ -520,6 +532,7 @@ async fn test_list_all_branches() {
         all: true,
         contains: vec![],
         no_contains: vec![],
+        override_protection: false,
     };
     execute(args).await; // This will print to stdout, which is fine for tests
 
@@ -575,6 +588,7 @@ async fn test_branch_delete_safe() {
         all: false,
         contains: vec![],
         no_contains: vec![],
+        override_protection: false,
     })
     .await;
 
@@ -625,6 +639,7 @@ async fn test_branch_delete_safe() {
         all: false,
         contains: vec![],
         no_contains: vec![],
+        override_protection: false,
     })
     .await;
 
@@ -666,6 +681,7 @@ async fn test_branch_delete_safe() {
         all: false,
         contains: vec![],
         no_contains: vec![],
+        override_protection: false,
     })
     .await;
 
@@ -741,6 +757,7 @@ async fn test_branch_contains_commit_filter() {
         all: false,
         contains: vec![],
         no_contains: vec![],
+        override_protection: false,
     })
     .await;
 
@@ -0,0 +1,170 @@
+//! Tests churn ranking of repeatedly edited files, directory rollup, JSON output, and merging
+//! churn across renames.
+
+use std::{fs, path::Path};
+
+use super::*;
+
+const ALICE: &str = "Alice <alice@example.com>";
+const BOB: &str = "Bob <bob@example.com>";
+
+/// src/core.rs is edited in every commit, src/util.rs twice, the rest once.
+fn build_fixture(dir: &Path) {
+    run_libra(dir, &["init"]);
+    commit_files(
+        dir,
+        Some(ALICE),
+        "initial",
+        &[
+            ("README.md", "hello\n"),
+            ("src/core.rs", "1\n"),
+            ("src/util.rs", "u\n"),
+            ("src/cmd/run.rs", "run\n"),
+        ],
+    );
+    commit_files(dir, Some(BOB), "two", &[("src/core.rs", "1\n2\n")]);
+    commit_files(dir, Some(ALICE), "three", &[("src/core.rs", "1\n2\n3\n")]);
+    commit_files(
+        dir,
+        Some(BOB),
+        "drop two",
+        &[("src/core.rs", "1\n3\n"), ("src/util.rs", "u\nv\n")],
+    );
+}
+
+/// The non-header rows of the table, with columns split on whitespace.
+fn rows(output: &str) -> Vec<Vec<String>> {
+    output
+        .lines()
+        .skip(1)
+        .map(|line| line.split_whitespace().map(String::from).collect())
+        .collect()
+}
+
+fn row(cells: &[&str]) -> Vec<String> {
+    cells.iter().map(|cell| cell.to_string()).collect()
+}
+
+#[test]
+fn test_churn_ranks_files() {
+    let temp = tempdir().unwrap();
+    let dir = temp.path();
+    build_fixture(dir);
+
+    let output = run_libra(dir, &["churn"]);
+    assert!(output.starts_with("commits"), "{output}");
+    assert_eq!(
+        rows(&output),
+        vec![
+            row(&["4", "3", "1", "2", "src/core.rs"]),
+            row(&["2", "2", "0", "2", "src/util.rs"]),
+            row(&["1", "1", "0", "1", "README.md"]),
+            row(&["1", "1", "0", "1", "src/cmd/run.rs"]),
+        ]
+    );
+
+    let output = run_libra(dir, &["churn", "--top", "2", "--author", "bob"]);
+    assert_eq!(
+        rows(&output),
+        vec![
+            row(&["2", "1", "1", "1", "src/core.rs"]),
+            row(&["1", "1", "0", "1", "src/util.rs"]),
+        ]
+    );
+
+    let output = run_libra(dir, &["churn", "src/cmd"]);
+    assert_eq!(
+        rows(&output),
+        vec![row(&["1", "1", "0", "1", "src/cmd/run.rs"])]
+    );
+}
+
+#[test]
+fn test_churn_by_dir() {
+    let temp = tempdir().unwrap();
+    let dir = temp.path();
+    build_fixture(dir);
+
+    let output = run_libra(dir, &["churn", "--by-dir"]);
+    assert_eq!(
+        rows(&output),
+        vec![
+            row(&["4", "6", "1", "2", "src"]),
+            row(&["1", "1", "0", "1", "."]),
+        ]
+    );
+
+    let output = run_libra(dir, &["churn", "--by-dir=2"]);
+    assert_eq!(
+        rows(&output),
+        vec![
+            row(&["4", "5", "1", "2", "src"]),
+            row(&["1", "1", "0", "1", "."]),
+            row(&["1", "1", "0", "1", "src/cmd"]),
+        ]
+    );
+}
+
+#[test]
+fn test_churn_json() {
+    let temp = tempdir().unwrap();
+    let dir = temp.path();
+    build_fixture(dir);
+
+    let output = run_libra(dir, &["churn", "--json", "--top", "1"]);
+    let entries: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(
+        entries,
+        serde_json::json!([{
+            "path": "src/core.rs",
+            "commits": 4,
+            "insertions": 3,
+            "deletions": 1,
+            "authors": 2,
+        }])
+    );
+}
+
+#[test]
+fn test_churn_merges_renames() {
+    let temp = tempdir().unwrap();
+    let dir = temp.path();
+    run_libra(dir, &["init"]);
+    commit_files(
+        dir,
+        Some(ALICE),
+        "notes",
+        &[("notes.txt", "a\nb\nc\nd\ne\n")],
+    );
+    commit_files(
+        dir,
+        Some(BOB),
+        "add f",
+        &[("notes.txt", "a\nb\nc\nd\ne\nf\n")],
+    );
+    fs::create_dir(dir.join("docs")).unwrap();
+    run_libra(dir, &["mv", "notes.txt", "docs/notes.txt"]);
+    run_libra(dir, &["commit", "-m", "move", "--author", ALICE]);
+    commit_files(
+        dir,
+        Some(ALICE),
+        "add g",
+        &[("docs/notes.txt", "a\nb\nc\nd\ne\nf\ng\n")],
+    );
+
+    // Without rename detection the move deletes one file and adds another.
+    let output = run_libra(dir, &["churn"]);
+    assert_eq!(
+        rows(&output),
+        vec![
+            row(&["3", "6", "6", "2", "notes.txt"]),
+            row(&["2", "7", "0", "1", "docs/notes.txt"]),
+        ]
+    );
+
+    let output = run_libra(dir, &["churn", "-M"]);
+    assert_eq!(
+        rows(&output),
+        vec![row(&["4", "7", "0", "2", "docs/notes.txt"])]
+    );
+}
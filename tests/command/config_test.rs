@@ -955,3 +955,126 @@ fn test_config_system_rejects_relative_programdata() {
     let _guard = EnvVarGuard::set("PROGRAMDATA", std::ffi::OsStr::new("relative/path"));
     assert_eq!(config::ConfigScope::System.get_config_path(), None);
 }
+
+/// Parse `argv` for `T` and apply config-driven defaults like the CLI dispatcher does.
+async fn parse_with_config_defaults<T>(argv: &[&str]) -> T
+where
+    T: clap::Parser + libra::internal::config_defaults::ApplyConfigDefaults,
+{
+    let matches = T::command().try_get_matches_from(argv).unwrap();
+    let mut args = T::from_arg_matches(&matches).unwrap();
+    libra::internal::config_defaults::apply_config_defaults(&mut args, &matches)
+        .await
+        .unwrap();
+    args
+}
+
+#[tokio::test]
+#[serial]
+async fn test_config_defaults_precedence_for_shortlog_numbered() {
+    let temp_path = tempdir().unwrap();
+    test::setup_with_new_libra_in(temp_path.path()).await;
+    let _guard = test::ChangeDirGuard::new(temp_path.path());
+
+    let global_db_dir = tempdir().unwrap();
+    let system_db_dir = tempdir().unwrap();
+    let _scoped = ScopedConfigPathGuard::new(
+        &global_db_dir.path().join("global_config_defaults.db"),
+        &system_db_dir.path().join("system_config_defaults.db"),
+    );
+
+    // Nothing configured: clap default.
+    let args: ShortlogArgs = parse_with_config_defaults(&["shortlog"]).await;
+    assert!(!args.numbered);
+
+    // Global config applies when local is unset.
+    exec_async(vec!["config", "--global", "shortlog.numbered", "true"])
+        .await
+        .unwrap();
+    let args: ShortlogArgs = parse_with_config_defaults(&["shortlog"]).await;
+    assert!(args.numbered);
+
+    // Local config overrides global.
+    exec_async(vec!["config", "shortlog.numbered", "false"])
+        .await
+        .unwrap();
+    let args: ShortlogArgs = parse_with_config_defaults(&["shortlog"]).await;
+    assert!(!args.numbered);
+
+    // Environment overrides local config.
+    {
+        let _env = EnvVarGuard::set(
+            "LIBRA_CONFIG_SHORTLOG_NUMBERED",
+            std::ffi::OsStr::new("yes"),
+        );
+        let args: ShortlogArgs = parse_with_config_defaults(&["shortlog"]).await;
+        assert!(args.numbered);
+    }
+
+    // Command line overrides everything, in both directions.
+    exec_async(vec!["config", "shortlog.numbered", "true"])
+        .await
+        .unwrap();
+    let args: ShortlogArgs = parse_with_config_defaults(&["shortlog", "--no-numbered"]).await;
+    assert!(!args.numbered);
+
+    exec_async(vec!["config", "shortlog.numbered", "false"])
+        .await
+        .unwrap();
+    let args: ShortlogArgs = parse_with_config_defaults(&["shortlog", "-n"]).await;
+    assert!(args.numbered);
+
+    // The last of `--numbered`/`--no-numbered` wins, as in git.
+    let args: ShortlogArgs =
+        parse_with_config_defaults(&["shortlog", "--no-numbered", "--numbered"]).await;
+    assert!(args.numbered);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_config_defaults_for_status_and_log() {
+    use libra::command::status::StatusArgs;
+
+    let temp_path = tempdir().unwrap();
+    test::setup_with_new_libra_in(temp_path.path()).await;
+    let _guard = test::ChangeDirGuard::new(temp_path.path());
+
+    exec_async(vec!["config", "status.short", "true"])
+        .await
+        .unwrap();
+    let args: StatusArgs = parse_with_config_defaults(&["status"]).await;
+    assert!(args.short);
+    let args: StatusArgs = parse_with_config_defaults(&["status", "--no-short"]).await;
+    assert!(!args.short);
+    // A configured short format never conflicts with an explicit --porcelain.
+    let args: StatusArgs = parse_with_config_defaults(&["status", "--porcelain"]).await;
+    assert!(!args.short);
+    assert!(args.porcelain.is_some());
+
+    exec_async(vec!["config", "log.decorate", "full"])
+        .await
+        .unwrap();
+    let args: LogArgs = parse_with_config_defaults(&["log"]).await;
+    assert_eq!(args.decorate.as_deref(), Some("full"));
+    let args: LogArgs = parse_with_config_defaults(&["log", "--decorate=short"]).await;
+    assert_eq!(args.decorate.as_deref(), Some("short"));
+    let args: LogArgs = parse_with_config_defaults(&["log", "--no-decorate"]).await;
+    assert_eq!(args.decorate, None);
+    assert!(args.no_decorate);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_config_defaults_reject_invalid_value() {
+    let temp_path = tempdir().unwrap();
+    test::setup_with_new_libra_in(temp_path.path()).await;
+    let _guard = test::ChangeDirGuard::new(temp_path.path());
+
+    exec_async(vec!["config", "shortlog.numbered", "sometimes"])
+        .await
+        .unwrap();
+    let result = exec_async(vec!["shortlog"]).await;
+    assert!(
+        matches!(result, Err(GitError::InvalidArgument(msg)) if msg.contains("shortlog.numbered"))
+    );
+}
@@ -1,4 +1,4 @@
-//! Tests diff command across commits, stage, and working tree with algorithm and pathspec options.
+//! Tests diff command across commits, stage, and working tree with algorithm and pathspec options, every invocation mode, output formats, revision/path disambiguation and `--exit-code`.
 
 use std::{fs, io::Write};
 
@@ -439,3 +439,246 @@ async fn test_diff_algorithms() {
         "MyersMinimal output file should exist"
     );
 }
+
+/// Commit `v1` and then `v2` of `notes.txt` (with an unchanging `other.txt` in the first
+/// commit), stage `v3`, leave `v4` in the working tree and return both commits.
+async fn setup_versioned_fixture() -> (ObjectHash, ObjectHash) {
+    let mut commits = Vec::new();
+    for version in ["v1", "v2", "v3"] {
+        create_file("notes.txt", &format!("{version}\nshared\n"));
+        create_file("other.txt", "other\n");
+        add::execute(AddArgs {
+            pathspec: vec![String::from(".")],
+            all: false,
+            update: false,
+            refresh: false,
+            force: false,
+            verbose: false,
+            dry_run: false,
+            ignore_errors: false,
+        })
+        .await;
+        if version != "v3" {
+            commit::execute(CommitArgs {
+                message: Some(format!("notes {version}")),
+                ..Default::default()
+            })
+            .await;
+            commits.push(Head::current_commit().await.unwrap());
+        }
+    }
+    modify_file("notes.txt", "v4\nshared\n");
+    (commits[0], commits[1])
+}
+
+/// Run `libra diff <argv>` in-process; returns the rendered output and whether anything
+/// differs.
+async fn run_diff(argv: &[&str]) -> Result<(String, bool), String> {
+    let args = DiffArgs::try_parse_from(std::iter::once("diff").chain(argv.iter().copied()))
+        .map_err(|e| e.to_string())?;
+    let mut buf = Vec::new();
+    let differs = diff::execute_to(args, &mut buf).await?;
+    Ok((String::from_utf8(buf).unwrap(), differs))
+}
+
+/// The `(removed, added)` lines of a patch, without headers.
+fn changed_lines(patch: &str) -> (Vec<&str>, Vec<&str>) {
+    let body = patch
+        .lines()
+        .filter(|line| !line.starts_with("---") && !line.starts_with("+++"));
+    let (removed, added): (Vec<&str>, Vec<&str>) = body
+        .filter(|line| line.starts_with('-') || line.starts_with('+'))
+        .partition(|line| line.starts_with('-'));
+    (removed, added)
+}
+
+#[tokio::test]
+#[serial]
+/// Every invocation mode compares the expected pair of versions.
+async fn test_diff_modes_select_expected_sides() {
+    let test_dir = tempdir().unwrap();
+    test::setup_with_new_libra_in(test_dir.path()).await;
+    let _guard = test::ChangeDirGuard::new(test_dir.path());
+    let (first, second) = setup_versioned_fixture().await;
+    let (first, second) = (first.to_string(), second.to_string());
+    let range = format!("{first}..{second}");
+    let open_range = format!("{first}..");
+
+    let cases: [(Vec<&str>, &str, &str); 8] = [
+        (vec![], "-v3", "+v4"),
+        (vec!["--cached"], "-v2", "+v3"),
+        (vec!["--staged"], "-v2", "+v3"),
+        (vec!["--cached", &first], "-v1", "+v3"),
+        (vec![&first], "-v1", "+v4"),
+        (vec![&first, &second], "-v1", "+v2"),
+        (vec![&range], "-v1", "+v2"),
+        (vec![&open_range], "-v1", "+v2"),
+    ];
+    for (argv, removed, added) in cases {
+        let (output, differs) = run_diff(&argv).await.unwrap();
+        assert!(differs, "{argv:?}");
+        assert_eq!(
+            changed_lines(&output),
+            (vec![removed], vec![added]),
+            "{argv:?}:\n{output}"
+        );
+        assert!(output.contains("diff --git a/notes.txt b/notes.txt"));
+    }
+
+    // Legacy --old/--new select the same sides as positional revisions.
+    let (output, _) = run_diff(&["--old", &first, "--staged"]).await.unwrap();
+    assert_eq!(changed_lines(&output), (vec!["-v1"], vec!["+v3"]));
+    assert!(run_diff(&["--old", &first, &second]).await.is_err());
+    assert!(run_diff(&["--cached", &first, &second]).await.is_err());
+}
+
+#[tokio::test]
+#[serial]
+/// The pathspec and output formats apply to every mode.
+async fn test_diff_pathspec_and_formats() {
+    let test_dir = tempdir().unwrap();
+    test::setup_with_new_libra_in(test_dir.path()).await;
+    let _guard = test::ChangeDirGuard::new(test_dir.path());
+    let (first, _) = setup_versioned_fixture().await;
+    let first = first.to_string();
+    modify_file("other.txt", "changed\n");
+
+    let (output, _) = run_diff(&[&first]).await.unwrap();
+    assert!(output.contains("b/other.txt"));
+    for argv in [
+        vec![&first as &str, "notes.txt"],
+        vec![&first, "--", "notes.txt"],
+    ] {
+        let (output, _) = run_diff(&argv).await.unwrap();
+        assert!(!output.contains("other.txt"), "{argv:?}:\n{output}");
+        assert!(output.contains("b/notes.txt"));
+    }
+    let (output, differs) = run_diff(&["--cached", "--", "other.txt"]).await.unwrap();
+    assert!(!differs);
+    assert!(output.is_empty());
+
+    let (output, _) = run_diff(&["--numstat", &first]).await.unwrap();
+    assert_eq!(
+        output.lines().collect::<Vec<_>>(),
+        ["1\t1\tnotes.txt", "1\t1\tother.txt"]
+    );
+    let (output, _) = run_diff(&["--stat", "--", "notes.txt"]).await.unwrap();
+    assert!(output.contains(" notes.txt |   2"), "{output}");
+    assert!(output.contains("1 file changed"), "{output}");
+    let (output, _) = run_diff(&["--word-diff", "--cached"]).await.unwrap();
+    assert!(output.contains("[-v2-]{+v3+}"), "{output}");
+    assert!(output.lines().any(|line| line == "shared"), "{output}");
+
+    let (output, _) = run_diff(&["--color=always"]).await.unwrap();
+    assert!(output.contains("\u{1b}["), "{output}");
+    let (output, _) = run_diff(&["--color=never"]).await.unwrap();
+    assert!(!output.contains("\u{1b}["), "{output}");
+}
+
+#[tokio::test]
+#[serial]
+/// `--no-index` compares two files outside any repository.
+async fn test_diff_no_index() {
+    let test_dir = tempdir().unwrap();
+    let _guard = test::ChangeDirGuard::new(test_dir.path());
+    create_file("left.txt", "same\nleft\n");
+    create_file("right.txt", "same\nright\n");
+    create_file("copy.txt", "same\nleft\n");
+
+    let (output, differs) = run_diff(&["--no-index", "left.txt", "right.txt"])
+        .await
+        .unwrap();
+    assert!(differs);
+    assert!(
+        output.starts_with("diff --git a/left.txt b/right.txt\n--- a/left.txt\n+++ b/right.txt\n")
+    );
+    assert_eq!(changed_lines(&output), (vec!["-left"], vec!["+right"]));
+
+    let (output, _) = run_diff(&["--no-index", "--numstat", "left.txt", "right.txt"])
+        .await
+        .unwrap();
+    assert_eq!(output, "1\t1\tright.txt\n");
+
+    let (output, differs) = run_diff(&["--no-index", "left.txt", "copy.txt"])
+        .await
+        .unwrap();
+    assert!(!differs);
+    assert!(output.is_empty());
+
+    assert!(run_diff(&["--no-index", "left.txt"]).await.is_err());
+    assert!(
+        run_diff(&["--no-index", "left.txt", "missing.txt"])
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+#[serial]
+/// An argument naming both a revision and a file needs `--`.
+async fn test_diff_revision_path_ambiguity() {
+    let test_dir = tempdir().unwrap();
+    test::setup_with_new_libra_in(test_dir.path()).await;
+    let _guard = test::ChangeDirGuard::new(test_dir.path());
+    let (first, _) = setup_versioned_fixture().await;
+    Branch::update_branch("topic", &first.to_string(), None).await;
+    create_file("topic", "a file named like the branch\n");
+
+    let err = run_diff(&["topic"]).await.unwrap_err();
+    assert!(
+        err.starts_with("ambiguous argument 'topic': both revision and filename"),
+        "{err}"
+    );
+
+    // Before `--` it is a revision ...
+    let (output, _) = run_diff(&["topic", "--", "notes.txt"]).await.unwrap();
+    assert_eq!(changed_lines(&output), (vec!["-v1"], vec!["+v4"]));
+    // ... after it a path.
+    let (output, _) = run_diff(&["--", "topic"]).await.unwrap();
+    assert!(output.contains("b/topic"), "{output}");
+    assert!(!output.contains("notes.txt"), "{output}");
+
+    let err = run_diff(&["no-such-thing"]).await.unwrap_err();
+    assert!(
+        err.contains("unknown revision or path not in the working tree"),
+        "{err}"
+    );
+    let err = run_diff(&["notes.txt", "--", "notes.txt"])
+        .await
+        .unwrap_err();
+    assert_eq!(err, "bad revision 'notes.txt'");
+    // A file that is not a revision is a path even without `--`.
+    let (output, _) = run_diff(&["notes.txt"]).await.unwrap();
+    assert_eq!(changed_lines(&output), (vec!["-v3"], vec!["+v4"]));
+}
+
+#[tokio::test]
+#[serial]
+/// `--exit-code` makes the process exit with 1 only when there are differences.
+async fn test_diff_exit_code() {
+    let test_dir = tempdir().unwrap();
+    test::setup_with_new_libra_in(test_dir.path()).await;
+    let _guard = test::ChangeDirGuard::new(test_dir.path());
+    let (first, second) = setup_versioned_fixture().await;
+    let (first, second) = (first.to_string(), second.to_string());
+
+    let status = |argv: &[&str]| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_libra"))
+            .current_dir(test_dir.path())
+            .arg("diff")
+            .args(argv)
+            .output()
+            .unwrap()
+            .status
+            .code()
+    };
+    assert_eq!(status(&["--exit-code"]), Some(1));
+    assert_eq!(status(&["--exit-code", &first, &second]), Some(1));
+    assert_eq!(status(&["--exit-code", &second, &second]), Some(0));
+    assert_eq!(
+        status(&["--exit-code", "--cached", "--", "other.txt"]),
+        Some(0)
+    );
+    // Without --exit-code differences are not an error.
+    assert_eq!(status(&[&first, &second]), Some(0));
+}
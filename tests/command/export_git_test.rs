@@ -0,0 +1,201 @@
+//! Tests `libra export-git`: round trips through `import-git --stream` and `git fast-import`,
+//! incremental exports with a marks file, and `--ref` selection.
+
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use libra::{internal::tag, utils::object_ext::TreeExt};
+
+use super::*;
+
+const BINARY: &[u8] = b"\x00\x01\xff\xfebinary\r\n\x00 no trailing newline";
+
+/// A repository with the fixture history (merge, rename, tags) plus a binary file on `main`.
+fn build_source(dir: &Path) {
+    let stream =
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/data/fast-import/history.stream");
+    run_libra(dir, &["init"]);
+    run_libra(dir, &["import-git", "--stream", stream.to_str().unwrap()]);
+    fs::write(dir.join("bin.dat"), BINARY).unwrap();
+    run_libra(dir, &["add", "bin.dat"]);
+    run_libra(dir, &["commit", "-m", "add binary"]);
+}
+
+/// Collects branch and tag targets, which only match if every object hashed the same.
+async fn ref_targets(dir: &Path) -> Vec<(String, ObjectHash)> {
+    let _guard = ChangeDirGuard::new(dir);
+    let mut targets: Vec<_> = Branch::list_branches(None)
+        .await
+        .into_iter()
+        .map(|b| (b.name, b.commit))
+        .collect();
+    for t in tag::list().await.unwrap() {
+        let id = match t.object {
+            tag::TagObject::Commit(commit) => commit.id,
+            tag::TagObject::Tag(tag_object) => tag_object.id,
+            other => panic!("unexpected tag target {other:?}"),
+        };
+        targets.push((format!("tag:{}", t.name), id));
+    }
+    targets.sort();
+    targets
+}
+
+fn count_lines(stream: &str, prefix: &str) -> usize {
+    stream.lines().filter(|l| l.starts_with(prefix)).count()
+}
+
+#[tokio::test]
+#[serial]
+async fn test_export_git_round_trips_through_import() {
+    let temp = tempdir().unwrap();
+    let source = temp.path().join("source");
+    let dest = temp.path().join("dest");
+    fs::create_dir_all(&source).unwrap();
+    fs::create_dir_all(&dest).unwrap();
+    build_source(&source);
+
+    let stream_path = temp.path().join("export.stream");
+    let output = run_libra_output(
+        &source,
+        &["export-git", "--output", stream_path.to_str().unwrap()],
+    );
+    assert!(
+        String::from_utf8_lossy(&output.stderr)
+            .contains("Exported 5 commits, 4 blobs and 1 tags (4 refs)")
+    );
+    let stream = fs::read(&stream_path).unwrap();
+    let needle = format!("data {}\n", BINARY.len()).into_bytes();
+    assert!(stream.windows(needle.len()).any(|w| w == needle));
+
+    run_libra(&dest, &["init"]);
+    run_libra(
+        &dest,
+        &["import-git", "--stream", stream_path.to_str().unwrap()],
+    );
+    assert_eq!(ref_targets(&source).await, ref_targets(&dest).await);
+
+    let _guard = ChangeDirGuard::new(&dest);
+    let main = Branch::find_branch("main", None).await.unwrap().commit;
+    let head: Commit = load_object(&main).unwrap();
+    assert_eq!(head.message.trim(), "add binary");
+    let merge: Commit = load_object(&head.parent_commit_ids[0]).unwrap();
+    assert_eq!(merge.parent_commit_ids.len(), 2);
+    assert_eq!(merge.author.name, "Carol");
+    assert_eq!(merge.author.timezone, "+0000");
+
+    let tree: Tree = load_object(&head.tree_id).unwrap();
+    let mut paths: Vec<_> = tree
+        .get_plain_items()
+        .into_iter()
+        .map(|(path, _)| path.to_string_lossy().into_owned())
+        .collect();
+    paths.sort();
+    assert_eq!(
+        paths,
+        ["bin.dat", "docs/README", "run.sh", "src/main file.rs"]
+    );
+    assert_eq!(fs::read(dest.join("bin.dat")).unwrap(), BINARY);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_export_git_incremental_marks_and_ref_selection() {
+    let temp = tempdir().unwrap();
+    let source = temp.path().join("source");
+    let dest = temp.path().join("dest");
+    fs::create_dir_all(&source).unwrap();
+    fs::create_dir_all(&dest).unwrap();
+    build_source(&source);
+    let marks = temp.path().join("marks");
+    let marks = marks.to_str().unwrap();
+
+    let first = run_libra_output(&source, &["export-git", "--marks", marks]);
+    let first = first.stdout;
+    assert_eq!(count_lines(&String::from_utf8_lossy(&first), "commit "), 5);
+    assert!(fs::read_to_string(marks).unwrap().starts_with(":1 "));
+
+    fs::write(source.join("new.txt"), "new\n").unwrap();
+    run_libra(&source, &["add", "new.txt"]);
+    run_libra(&source, &["commit", "-m", "after export"]);
+    let second = run_libra_output(&source, &["export-git", "--marks", marks]);
+    let second = second.stdout;
+    let second_text = String::from_utf8(second.clone()).unwrap();
+    assert_eq!(count_lines(&second_text, "commit "), 1, "{second_text}");
+    assert_eq!(count_lines(&second_text, "blob"), 1, "{second_text}");
+    assert_eq!(count_lines(&second_text, "tag "), 0, "{second_text}");
+    assert!(second_text.contains("from :"), "{second_text}");
+
+    // Importing both streams in order rebuilds the same repository.
+    run_libra(&dest, &["init"]);
+    for (name, stream) in [("first", &first), ("second", &second)] {
+        let path = temp.path().join(name);
+        fs::write(&path, stream).unwrap();
+        run_libra(&dest, &["import-git", "--stream", path.to_str().unwrap()]);
+    }
+    assert_eq!(ref_targets(&source).await, ref_targets(&dest).await);
+    assert_eq!(fs::read_to_string(dest.join("new.txt")).unwrap(), "new\n");
+
+    let topic_only = run_libra(&source, &["export-git", "--ref", "topic"]);
+    assert!(topic_only.contains("refs/heads/topic"));
+    assert!(!topic_only.contains("refs/heads/main"));
+    assert!(!topic_only.contains("refs/tags/"));
+    assert_eq!(count_lines(&topic_only, "commit "), 2);
+
+    let missing = libra_output(&source, &["export-git", "--ref", "nope"]);
+    assert!(String::from_utf8_lossy(&missing.stderr).contains("ref 'nope' not found"));
+}
+
+#[tokio::test]
+#[serial]
+async fn test_export_git_stream_is_accepted_by_git_fast_import() {
+    let temp = tempdir().unwrap();
+    let source = temp.path().join("source");
+    let bare = temp.path().join("bare.git");
+    fs::create_dir_all(&source).unwrap();
+    build_source(&source);
+
+    let stream = run_libra_output(&source, &["export-git"]).stdout;
+    assert!(
+        Command::new("git")
+            .args(["init", "-q", "--bare", bare.to_str().unwrap()])
+            .status()
+            .unwrap()
+            .success()
+    );
+    let mut fast_import = Command::new("git")
+        .current_dir(&bare)
+        .args(["fast-import", "--quiet"])
+        .stdin(Stdio::piped())
+        .spawn()
+        .unwrap();
+    fast_import
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(&stream)
+        .unwrap();
+    assert!(fast_import.wait().unwrap().success());
+
+    let git = |args: &[&str]| {
+        let output = Command::new("git")
+            .current_dir(&bare)
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "git {args:?}: {output:?}");
+        output.stdout
+    };
+    git(&["fsck", "--strict"]);
+    // Same objects in, same ids out.
+    for (name, id) in ref_targets(&source).await {
+        let rev = name.strip_prefix("tag:").unwrap_or(&name).to_string();
+        let git_id = String::from_utf8(git(&["rev-parse", &rev])).unwrap();
+        assert_eq!(git_id.trim(), id.to_string(), "{name}");
+    }
+    assert_eq!(git(&["cat-file", "blob", "main:bin.dat"]), BINARY);
+}
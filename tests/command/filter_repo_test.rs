@@ -0,0 +1,186 @@
+//! Tests filter-repo history rewriting: path removal, author replacement, ref updates and safety checks.
+
+use std::{collections::HashMap, fs, path::Path};
+
+use libra::{internal::tag, utils::object_ext::TreeExt};
+
+use super::*;
+
+/// master:  C1 (a.txt, secret.txt) - C2 - C5
+/// feature:                           \- C3 (touches only secret.txt) - C4
+/// v1 is an annotated tag on C2.
+fn build_fixture(dir: &Path) {
+    run_libra(dir, &["init"]);
+    let alice = Some("Alice <alice@exmaple.com>");
+    let bob = Some("Bob <bob@example.com>");
+    commit_files(
+        dir,
+        alice,
+        "C1",
+        &[("a.txt", "a1"), ("secret.txt", "password=1")],
+    );
+    commit_files(dir, bob, "C2", &[("a.txt", "a2")]);
+    run_libra(dir, &["tag", "v1", "-m", "release 1"]);
+    run_libra(dir, &["switch", "-c", "feature"]);
+    commit_files(dir, alice, "C3", &[("secret.txt", "password=2")]);
+    commit_files(dir, alice, "C4", &[("c.txt", "c")]);
+    run_libra(dir, &["switch", "master"]);
+    commit_files(dir, bob, "C5", &[("b.txt", "b")]);
+}
+
+async fn branch_tip(name: &str) -> ObjectHash {
+    Branch::find_branch(name, None).await.unwrap().commit
+}
+
+/// Follows first parents from `tip`, newest first.
+fn first_parent_chain(tip: ObjectHash) -> Vec<Commit> {
+    let mut chain = Vec::new();
+    let mut next = Some(tip);
+    while let Some(id) = next {
+        let commit: Commit = load_object(&id).unwrap();
+        next = commit.parent_commit_ids.first().copied();
+        chain.push(commit);
+    }
+    chain
+}
+
+fn subjects(chain: &[Commit]) -> Vec<String> {
+    chain.iter().map(|c| c.message.trim().to_string()).collect()
+}
+
+#[tokio::test]
+#[serial]
+async fn test_filter_repo_removes_path_and_fixes_author() {
+    let temp = tempdir().unwrap();
+    let dir = temp.path();
+    build_fixture(dir);
+
+    let _guard = ChangeDirGuard::new(dir);
+    let old_master = branch_tip("master").await;
+    let old_feature = branch_tip("feature").await;
+
+    // A dry run reports the plan and leaves everything in place.
+    let dry_out = run_libra(
+        dir,
+        &[
+            "filter-repo",
+            "--dry-run",
+            "--path-remove",
+            "secret.txt",
+            "--replace-author",
+            "Alice <alice@exmaple.com>:Alice <alice@example.com>",
+        ],
+    );
+    assert!(
+        dry_out.contains("Would rewrite 4 of 5 commits (1 dropped as empty)"),
+        "{dry_out}"
+    );
+    assert!(dry_out.contains("refs/heads/master"), "{dry_out}");
+    assert_eq!(branch_tip("master").await, old_master);
+    assert!(!dir.join(".libra/filter-map").exists());
+
+    // A dirty work tree is refused.
+    fs::write(dir.join("a.txt"), "local edit").unwrap();
+    let refused = libra_output(dir, &["filter-repo", "--path-remove", "secret.txt"]);
+    assert!(String::from_utf8_lossy(&refused.stderr).contains("uncommitted changes"));
+    assert_eq!(branch_tip("master").await, old_master);
+    run_libra(dir, &["restore", "a.txt"]);
+
+    run_libra(
+        dir,
+        &[
+            "filter-repo",
+            "--path-remove",
+            "secret.txt",
+            "--replace-author",
+            "Alice <alice@exmaple.com>:Alice <alice@example.com>",
+        ],
+    );
+
+    let new_master = branch_tip("master").await;
+    let new_feature = branch_tip("feature").await;
+    assert_ne!(new_master, old_master);
+    assert_ne!(new_feature, old_feature);
+
+    // Topology: C3 only touched secret.txt and is gone; feature still forks from C2.
+    let master_chain = first_parent_chain(new_master);
+    let feature_chain = first_parent_chain(new_feature);
+    assert_eq!(subjects(&master_chain), ["C5", "C2", "C1"]);
+    assert_eq!(subjects(&feature_chain), ["C4", "C2", "C1"]);
+    assert_eq!(feature_chain[1].id, master_chain[1].id);
+
+    for commit in master_chain.iter().chain(&feature_chain) {
+        let tree: Tree = load_object(&commit.tree_id).unwrap();
+        let paths = tree.get_plain_items();
+        assert!(
+            paths
+                .iter()
+                .all(|(path, _)| path != Path::new("secret.txt")),
+            "{} still has secret.txt",
+            commit.message.trim()
+        );
+        assert!(paths.iter().any(|(path, _)| path == Path::new("a.txt")));
+        assert_ne!(commit.author.email, "alice@exmaple.com");
+    }
+    assert_eq!(master_chain[2].author.email, "alice@example.com");
+    assert_eq!(master_chain[1].author.email, "bob@example.com");
+
+    // The annotated tag was recreated on the rewritten C2.
+    let (_, tagged) = tag::find_tag_and_commit("v1").await.unwrap().unwrap();
+    assert_eq!(tagged.id, master_chain[1].id);
+
+    // The work tree follows the rewritten HEAD.
+    assert!(!dir.join("secret.txt").exists());
+    assert!(changes_to_be_committed().await.is_empty());
+
+    // The map records every commit, with zeros for the dropped one.
+    let map = fs::read_to_string(dir.join(".libra/filter-map")).unwrap();
+    let entries: HashMap<String, String> = map
+        .lines()
+        .skip(1)
+        .map(|line| {
+            let (old, new) = line.split_once(' ').unwrap();
+            (old.to_string(), new.to_string())
+        })
+        .collect();
+    assert_eq!(entries.len(), 5, "{map}");
+    assert_eq!(entries[&old_master.to_string()], new_master.to_string());
+    assert_eq!(entries[&old_feature.to_string()], new_feature.to_string());
+    let dropped: Vec<_> = entries
+        .values()
+        .filter(|new| new.chars().all(|c| c == '0'))
+        .collect();
+    assert_eq!(dropped.len(), 1);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_filter_repo_message_filter_and_keep_empty() {
+    let temp = tempdir().unwrap();
+    let dir = temp.path();
+    build_fixture(dir);
+
+    let _guard = ChangeDirGuard::new(dir);
+    run_libra(
+        dir,
+        &[
+            "filter-repo",
+            "--path-remove",
+            "secret.txt",
+            "--keep-empty",
+            "--message-filter",
+            "\\bC(\\d)\\b==>commit $1",
+        ],
+    );
+
+    let feature_chain = first_parent_chain(branch_tip("feature").await);
+    assert_eq!(
+        subjects(&feature_chain),
+        ["commit 4", "commit 3", "commit 2", "commit 1"]
+    );
+    // C3 is kept, now with the same tree as its parent.
+    assert_eq!(feature_chain[1].tree_id, feature_chain[2].tree_id);
+
+    let missing = libra_output(dir, &["filter-repo"]);
+    assert!(String::from_utf8_lossy(&missing.stderr).contains("no filters given"));
+}
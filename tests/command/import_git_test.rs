@@ -0,0 +1,231 @@
+//! Tests `libra import-git` from a checked-in fast-export stream and from a real Git repository,
+//! including hash preservation, ref recreation and incremental re-imports.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use libra::internal::tag;
+
+use super::*;
+
+fn git(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .current_dir(dir)
+        .args(args)
+        .env("GIT_AUTHOR_NAME", "Merger")
+        .env("GIT_AUTHOR_EMAIL", "merger@example.com")
+        .env("GIT_COMMITTER_NAME", "Committer")
+        .env("GIT_COMMITTER_EMAIL", "committer@example.com")
+        .output()
+        .expect("Failed to execute git");
+    assert!(
+        output.status.success(),
+        "git {args:?} failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+/// Commits the staged changes with a fixed author and date, so the ids are stable.
+fn git_commit(dir: &Path, when: u64, author: &str, email: &str, message: &str) {
+    let date = format!("{when} +0800");
+    let output = Command::new("git")
+        .current_dir(dir)
+        .args(["commit", "-q", "-m", message])
+        .env("GIT_AUTHOR_NAME", author)
+        .env("GIT_AUTHOR_EMAIL", email)
+        .env("GIT_AUTHOR_DATE", &date)
+        .env("GIT_COMMITTER_NAME", "Committer")
+        .env("GIT_COMMITTER_EMAIL", "committer@example.com")
+        .env("GIT_COMMITTER_DATE", &date)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+}
+
+/// main:     initial - add script - edit lib - merge feature
+/// feature:                 \- rename a.txt -/
+/// v1 is an annotated tag and `light` a lightweight tag on "add script".
+fn build_git_repo(dir: &Path) {
+    git(dir, &["init", "-q", "-b", "main"]);
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    fs::write(dir.join("a-b"), "dash\n").unwrap();
+    fs::create_dir_all(dir.join("src/lib")).unwrap();
+    fs::write(dir.join("src/lib/mod.rs"), "lib\n").unwrap();
+    fs::write(dir.join("src/lib.rs"), "root\n").unwrap();
+    git(dir, &["add", "."]);
+    git_commit(dir, 1700000000, "Alice", "alice@example.com", "initial");
+
+    fs::write(dir.join("run.sh"), "#!/bin/sh\n").unwrap();
+    git(dir, &["add", "run.sh"]);
+    git(dir, &["update-index", "--chmod=+x", "run.sh"]);
+    git_commit(dir, 1700000100, "Bob", "bob@example.com", "add script");
+    git(dir, &["tag", "-a", "v1", "-m", "release 1"]);
+    git(dir, &["tag", "light"]);
+
+    git(dir, &["checkout", "-q", "-b", "feature"]);
+    git(dir, &["mv", "a.txt", "b.txt"]);
+    git_commit(
+        dir,
+        1700000200,
+        "Alice",
+        "alice@example.com",
+        "rename a.txt",
+    );
+
+    git(dir, &["checkout", "-q", "main"]);
+    fs::write(dir.join("src/lib/mod.rs"), "lib\nmore\n").unwrap();
+    git(dir, &["add", "."]);
+    git_commit(dir, 1700000300, "Bob", "bob@example.com", "edit lib");
+    git(
+        dir,
+        &["merge", "-q", "--no-ff", "feature", "-m", "merge feature"],
+    );
+}
+
+/// Parses `shortlog -sne` output of either tool into `(count, identity)` pairs.
+fn shortlog_entries(output: &str) -> Vec<(u32, String)> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (count, ident) = line.trim().split_once(char::is_whitespace)?;
+            Some((count.parse().ok()?, ident.trim().to_string()))
+        })
+        .collect()
+}
+
+fn fixture_stream() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/data/fast-import/history.stream")
+}
+
+#[tokio::test]
+#[serial]
+async fn test_import_git_from_stream_fixture() {
+    let temp = tempdir().unwrap();
+    let dir = temp.path();
+    run_libra(dir, &["init"]);
+    let stream = fixture_stream();
+    let stream = stream.to_str().unwrap();
+
+    let output = run_libra(dir, &["import-git", "--stream", stream]);
+    assert!(
+        output.contains("Imported 4 commits, 3 blobs and 1 tags (0 already present)"),
+        "{output}"
+    );
+
+    // The default `master` does not exist in the stream, so HEAD moves to `main`.
+    let _guard = ChangeDirGuard::new(dir);
+    assert!(matches!(Head::current().await, Head::Branch(name) if name == "main"));
+    let main = Branch::find_branch("main", None).await.unwrap().commit;
+    let topic = Branch::find_branch("topic", None).await.unwrap().commit;
+
+    let merge: Commit = load_object(&main).unwrap();
+    assert_eq!(merge.message, "\nmerge topic\n");
+    assert_eq!(merge.parent_commit_ids.len(), 2);
+    assert_eq!(merge.parent_commit_ids[1], topic);
+    // The ids are the ones `git fast-import` assigns to the same stream.
+    assert_eq!(main.to_string(), "c3a6a1cc1b738837e9b83f1fff9933fe9f2a00a8");
+
+    let log = run_libra(dir, &["log", "--oneline"]);
+    let subjects: Vec<_> = log
+        .lines()
+        .map(|line| line.split_once(' ').unwrap().1)
+        .collect();
+    assert_eq!(subjects, ["merge topic", "rename", "add script", "initial"]);
+    assert_eq!(
+        shortlog_entries(&run_libra(dir, &["shortlog", "-sne"])),
+        [
+            (2, "Alice <alice@example.com>".to_string()),
+            (1, "Bob <bob@example.com>".to_string()),
+            (1, "Carol <carol@example.com>".to_string()),
+        ]
+    );
+
+    let (_, tagged) = tag::find_tag_and_commit("v1").await.unwrap().unwrap();
+    assert_eq!(tagged.message, "\nadd script\n\nwith a body\n");
+    let (_, snapshot) = tag::find_tag_and_commit("snapshot").await.unwrap().unwrap();
+    assert_eq!(snapshot.id, topic);
+
+    // The work tree is checked out from the imported HEAD.
+    assert_eq!(
+        fs::read_to_string(dir.join("docs/README")).unwrap(),
+        "hello\n"
+    );
+    assert!(dir.join("src/main file.rs").exists());
+    assert!(!dir.join("README").exists());
+    assert!(changes_to_be_committed().await.is_empty());
+
+    // Importing the same stream again only finds existing objects.
+    let again = run_libra(dir, &["import-git", "--stream", stream]);
+    assert!(
+        again.contains("Imported 0 commits, 0 blobs and 0 tags (8 already present)"),
+        "{again}"
+    );
+    assert!(!again.contains("refs/"), "{again}");
+}
+
+#[tokio::test]
+#[serial]
+async fn test_import_git_repository_preserves_history_and_is_incremental() {
+    let temp = tempdir().unwrap();
+    let source = temp.path().join("git-src");
+    let dest = temp.path().join("libra-dest");
+    fs::create_dir_all(&source).unwrap();
+    fs::create_dir_all(&dest).unwrap();
+    build_git_repo(&source);
+
+    run_libra(&dest, &["init"]);
+    let output = run_libra(&dest, &["import-git", source.to_str().unwrap()]);
+    assert!(output.contains("Imported 5 commits"), "{output}");
+
+    // Hashes are preserved, so branches and tags resolve to the same ids as in git.
+    let _guard = ChangeDirGuard::new(&dest);
+    for branch in ["main", "feature"] {
+        let tip = Branch::find_branch(branch, None).await.unwrap().commit;
+        assert_eq!(tip.to_string(), git(&source, &["rev-parse", branch]).trim());
+    }
+    let (v1, _) = tag::find_tag_and_commit("v1").await.unwrap().unwrap();
+    let tag::TagObject::Tag(v1) = v1 else {
+        panic!("v1 should be an annotated tag");
+    };
+    assert_eq!(v1.id.to_string(), git(&source, &["rev-parse", "v1"]).trim());
+    let (light, _) = tag::find_tag_and_commit("light").await.unwrap().unwrap();
+    assert!(matches!(light, tag::TagObject::Commit(_)));
+
+    assert_eq!(
+        run_libra(&dest, &["log", "--oneline"]),
+        git(&source, &["log", "--oneline"])
+    );
+    assert_eq!(
+        shortlog_entries(&run_libra(&dest, &["shortlog", "-sne"])),
+        shortlog_entries(&git(&source, &["shortlog", "-sne", "HEAD"]))
+    );
+    assert!(matches!(Head::current().await, Head::Branch(name) if name == "main"));
+    assert_eq!(fs::read_to_string(dest.join("b.txt")).unwrap(), "a\n");
+
+    // A second import only converts what was added since.
+    fs::write(source.join("new.txt"), "new\n").unwrap();
+    git(&source, &["add", "new.txt"]);
+    git_commit(
+        &source,
+        1700000500,
+        "Carol",
+        "carol@example.com",
+        "after import",
+    );
+    let incremental = run_libra(&dest, &["import-git", source.to_str().unwrap()]);
+    assert!(
+        incremental.contains("Imported 1 commits, 1 blobs and 0 tags"),
+        "{incremental}"
+    );
+    let main = Branch::find_branch("main", None).await.unwrap().commit;
+    assert_eq!(
+        main.to_string(),
+        git(&source, &["rev-parse", "main"]).trim()
+    );
+    assert_eq!(fs::read_to_string(dest.join("new.txt")).unwrap(), "new\n");
+    assert!(dest.join(".libra/import-git/marks").exists());
+}
@@ -1058,3 +1058,136 @@ async fn test_log_short_number_flag_with_double_dash_before_subcommand() {
     assert!(status.success(), "libra -- log -2 failed: {err}");
     assert_eq!(count_commit_lines(&out), 2);
 }
+
+#[tokio::test]
+#[serial]
+async fn test_log_follow_continues_across_rename() {
+    let temp_path = tempdir().unwrap();
+    test::setup_with_new_libra_in(temp_path.path()).await;
+    let dir = temp_path.path();
+    let run = |args: &[&str]| {
+        let (status, stdout, stderr) = run_libra_cmd(args, dir);
+        assert!(status.success(), "libra {args:?} failed: {stderr}");
+        stdout
+    };
+
+    std::fs::write(dir.join("old.txt"), "one\ntwo\nthree\nfour\n").unwrap();
+    run(&["add", "old.txt"]);
+    run(&["commit", "-m", "create old"]);
+    std::fs::write(dir.join("old.txt"), "one\ntwo\nthree\nfour\nfive\n").unwrap();
+    run(&["add", "old.txt"]);
+    run(&["commit", "-m", "extend old"]);
+    std::fs::remove_file(dir.join("old.txt")).unwrap();
+    std::fs::write(dir.join("new.txt"), "one\ntwo\nTHREE\nfour\nfive\n").unwrap();
+    run(&["add", "-A"]);
+    run(&["commit", "-m", "rename to new"]);
+
+    let (status, plain, _) = run_log_cmd(&["--oneline", "new.txt"], dir);
+    assert!(status.success());
+    assert_eq!(plain.lines().count(), 1, "{plain}");
+
+    let followed = run(&["log", "--oneline", "--follow", "new.txt"]);
+    let lines: Vec<&str> = followed.lines().collect();
+    assert_eq!(lines.len(), 4, "{followed}");
+    assert!(lines[0].ends_with("rename to new"), "{followed}");
+    assert_eq!(lines[1], "renamed from old.txt");
+    assert!(lines[2].ends_with("extend old"), "{followed}");
+    assert!(lines[3].ends_with("create old"), "{followed}");
+
+    let (status, _, stderr) = run_log_cmd(&["--follow"], dir);
+    assert!(status.success());
+    assert!(stderr.contains("--follow requires exactly one pathspec"));
+}
+
+#[tokio::test]
+#[serial]
+/// Exports the shortlog fixture graph, plus a commit whose subject needs escaping, as DOT
+/// and Mermaid
+async fn test_log_graph_export() {
+    use git_internal::internal::object::signature::SignatureType;
+    use libra::internal::log::date_parser::parse_date;
+
+    use super::shortlog_test::{create_signature, create_test_commit_tree as create_merge_tree};
+
+    let temp_path = tempdir().unwrap();
+    test::setup_with_new_libra_in(temp_path.path()).await;
+    let _guard = ChangeDirGuard::new(temp_path.path());
+
+    let merge_id = ObjectHash::from_str(&create_merge_tree().await).unwrap();
+    let merge = load_object::<Commit>(&merge_id).unwrap();
+    let mut quoted = Commit::new(
+        create_signature(SignatureType::Author, "SHY"),
+        create_signature(SignatureType::Committer, "SHY"),
+        ObjectHash::new(&[15; 20]),
+        vec![merge_id],
+        &format_commit_msg(r#"Say "hi" to <C:\tmp> #1"#, None),
+    );
+    quoted.committer.timestamp = parse_date("2026-01-15").unwrap() as usize;
+    save_object(&quoted, &quoted.id).unwrap();
+    let branch = match Head::current().await {
+        Head::Branch(name) => name,
+        _ => panic!("should be branch"),
+    };
+    Branch::update_branch(&branch, &quoted.id.to_string(), None).await;
+    // The merge of commit 13 into 14 brings in a second parent.
+    let (merged, merged_in) = (merge.id.to_string(), merge.parent_commit_ids[1].to_string());
+    Branch::update_branch("feature", &merged_in, None).await;
+
+    let dir = temp_path.path();
+    let (status, dot, stderr) = run_log_cmd(&["--output=dot", "--no-abbrev-commit"], dir);
+    assert!(status.success(), "{stderr}");
+    let commit_edges: Vec<&str> = dot
+        .lines()
+        .filter(|line| line.contains(" -> ") && !line.contains("\"ref:"))
+        .collect();
+    // 12 commits of the fixture are reachable, 3 of them merges, plus the quoted commit.
+    assert_eq!(
+        dot.lines().filter(|line| line.contains("[label=")).count(),
+        13 + 2
+    );
+    assert_eq!(commit_edges.len(), 15, "{dot}");
+    assert_eq!(
+        commit_edges.iter().filter(|e| e.contains("dashed")).count(),
+        3
+    );
+    assert!(
+        dot.contains(&format!(
+            "  \"{merged}\" -> \"{merged_in}\" [style=dashed, color=\"gray40\"];"
+        )),
+        "{dot}"
+    );
+    assert!(
+        dot.contains(&format!(
+            "[label=\"{} Say \\\"hi\\\" to <C:\\\\tmp> #1\"]",
+            quoted.id
+        )),
+        "{dot}"
+    );
+    assert!(
+        dot.contains(&format!("\"ref:HEAD -> {branch}\" [label=")),
+        "{dot}"
+    );
+    assert!(dot.contains("fillcolor=\"palegreen3\""), "{dot}");
+
+    // --max-count applies, dropping edges to parents outside the export.
+    let (status, mermaid, stderr) = run_log_cmd(&["--output=mermaid", "--max-count", "3"], dir);
+    assert!(status.success(), "{stderr}");
+    assert!(mermaid.starts_with("flowchart TB\n"), "{mermaid}");
+    let nodes: Vec<&str> = mermaid
+        .lines()
+        .filter(|line| line.starts_with("  c") && line.contains("[\""))
+        .collect();
+    assert_eq!(nodes.len(), 3, "{mermaid}");
+    assert!(
+        nodes[0].ends_with(r#" Say #quot;hi#quot; to #lt;C:\tmp#gt; #35;1"]"#),
+        "{mermaid}"
+    );
+    let edges = mermaid
+        .lines()
+        .filter(|line| line.contains("-->") || line.contains("-.->"));
+    assert_eq!(edges.count(), 2, "{mermaid}");
+    assert!(
+        mermaid.contains(":::head") && mermaid.contains(":::branch"),
+        "{mermaid}"
+    );
+}
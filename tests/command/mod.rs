@@ -1,5 +1,11 @@
 //! Shared test utilities and re-exports for the command integration test suite.
 
+use std::{
+    fs,
+    path::Path,
+    process::{Command, Output},
+};
+
 use git_internal::{
     hash::ObjectHash,
     internal::object::{commit::Commit, tree::Tree},
@@ -29,16 +35,21 @@ use libra::{
 use serial_test::serial;
 use tempfile::tempdir;
 mod add_test;
+mod ai_test;
 mod blame_test;
 mod branch_test;
 mod checkout_test;
 mod cherry_pick_test;
+mod churn_test;
 mod clean_test;
 mod clone_test;
 mod commit_test;
 mod config_test;
 mod diff_test;
+mod export_git_test;
 mod fetch_test;
+mod filter_repo_test;
+mod import_git_test;
 mod index_pack_test;
 mod init_from_git_test;
 mod init_separate_libra_dir_test;
@@ -48,6 +59,7 @@ mod log_test;
 mod merge_test;
 mod mv_test;
 mod open_test;
+mod protect_test;
 mod pull_test;
 mod push_test;
 mod rebase_test;
@@ -59,7 +71,59 @@ mod restore_test;
 mod revert_test;
 mod shortlog_test;
 mod show_test;
+mod stash_test;
+mod stats_test;
 mod status_test;
 mod switch_test;
 mod tag_test;
 mod worktree_test;
+
+/// A `libra` binary invocation in `dir`, for tests that need to adjust its environment.
+fn libra_command(dir: &Path) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_libra"));
+    command.current_dir(dir);
+    command
+}
+
+/// Runs the `libra` binary in `dir` and returns its raw output.
+fn libra_output(dir: &Path, args: &[&str]) -> Output {
+    libra_command(dir)
+        .args(args)
+        .output()
+        .expect("Failed to execute libra binary")
+}
+
+/// Runs the `libra` binary in `dir`, asserting that it neither fails nor reports a
+/// fatal error, and returns its output.
+fn run_libra_output(dir: &Path, args: &[&str]) -> Output {
+    let output = libra_output(dir, args);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        output.status.success() && !stderr.contains("fatal:"),
+        "libra {args:?} failed: {stderr}"
+    );
+    output
+}
+
+/// Like [`run_libra_output`], returning stdout as a string.
+fn run_libra(dir: &Path, args: &[&str]) -> String {
+    String::from_utf8_lossy(&run_libra_output(dir, args).stdout).into_owned()
+}
+
+/// Writes `files` under `dir`, stages them and commits them with `message`,
+/// optionally as `author`.
+fn commit_files(dir: &Path, author: Option<&str>, message: &str, files: &[(&str, &str)]) {
+    for (name, content) in files {
+        let path = dir.join(name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).unwrap();
+        }
+        fs::write(path, content).unwrap();
+        run_libra(dir, &["add", name]);
+    }
+    let mut args = vec!["commit", "-m", message];
+    if let Some(author) = author {
+        args.extend(["--author", author]);
+    }
+    run_libra(dir, &args);
+}
@@ -0,0 +1,189 @@
+//! Tests branch protection: refusal of destructive operations on protected branches, the
+//! --override-protection path, and the reflog annotation it leaves.
+
+use std::path::Path;
+
+use super::*;
+
+/// Runs a command expected to be refused and returns its stderr.
+fn refused(dir: &Path, args: &[&str], rule: &str) -> String {
+    let output = libra_output(dir, args);
+    let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+    assert!(
+        stderr.contains(rule) && stderr.contains("--override-protection"),
+        "libra {args:?} was not refused by {rule}: {stderr}"
+    );
+    stderr
+}
+
+/// The abbreviated HEAD commit and its summary.
+fn head(dir: &Path) -> String {
+    run_libra(dir, &["log", "-n", "1", "--oneline"])
+        .trim()
+        .to_string()
+}
+
+/// master: C1 - C2, protected; release/1.0 at C2, protected by "release/*"; topic: C1 - C3.
+fn build_fixture(dir: &Path) {
+    run_libra(dir, &["init"]);
+    commit_files(dir, None, "C1", &[("a.txt", "C1")]);
+    run_libra(dir, &["branch", "topic"]);
+    commit_files(dir, None, "C2", &[("b.txt", "C2")]);
+    run_libra(dir, &["branch", "release/1.0"]);
+    run_libra(dir, &["branch", "scratch"]);
+    run_libra(dir, &["switch", "topic"]);
+    commit_files(dir, None, "C3", &[("c.txt", "C3")]);
+    run_libra(dir, &["switch", "master"]);
+    run_libra(dir, &["config", "branch.master.protected", "true"]);
+    run_libra(dir, &["config", "branch.release/*.protected", "true"]);
+}
+
+#[test]
+fn test_protected_branch_delete() {
+    let temp = tempdir().unwrap();
+    let dir = temp.path();
+    build_fixture(dir);
+
+    let rule = "branch.release/*.protected";
+    refused(dir, &["branch", "-D", "release/1.0"], rule);
+    refused(dir, &["branch", "-d", "release/1.0"], rule);
+    assert!(run_libra(dir, &["branch"]).contains("release/1.0"));
+
+    run_libra(dir, &["branch", "-d", "scratch"]);
+    run_libra(
+        dir,
+        &["branch", "-D", "release/1.0", "--override-protection"],
+    );
+    let branches = run_libra(dir, &["branch"]);
+    assert!(!branches.contains("release/1.0"), "{branches}");
+    assert!(!branches.contains("scratch"), "{branches}");
+}
+
+#[test]
+fn test_invalid_protection_pattern_is_reported() {
+    let temp = tempdir().unwrap();
+    let dir = temp.path();
+    build_fixture(dir);
+    run_libra(dir, &["config", "branch.hotfix-[.protected", "true"]);
+
+    // The broken rule could cover any branch, so nothing is deleted until it is fixed.
+    for args in [
+        &["branch", "-d", "scratch"][..],
+        &["branch", "-d", "scratch", "--override-protection"],
+    ] {
+        let output = libra_output(dir, args);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains("invalid protection rule branch.hotfix-[.protected"),
+            "libra {args:?}: {stderr}"
+        );
+    }
+    assert!(run_libra(dir, &["branch"]).contains("scratch"));
+}
+
+#[test]
+fn test_protected_branch_reset_hard() {
+    let temp = tempdir().unwrap();
+    let dir = temp.path();
+    build_fixture(dir);
+    let before = head(dir);
+
+    refused(
+        dir,
+        &["reset", "--hard", "HEAD~1"],
+        "branch.master.protected",
+    );
+    assert_eq!(head(dir), before);
+
+    // Only a hard reset is guarded.
+    run_libra(dir, &["reset", "--soft", "HEAD~1"]);
+    run_libra(dir, &["reset", "--soft", "release/1.0"]);
+
+    run_libra(dir, &["reset", "--hard", "HEAD~1", "--override-protection"]);
+    assert_ne!(head(dir), before);
+    let reflog = run_libra(dir, &["reflog", "show"]);
+    assert!(
+        reflog.contains("moving to HEAD~1 (override-protection: branch.master.protected)"),
+        "{reflog}"
+    );
+}
+
+#[test]
+fn test_protected_branch_rebase() {
+    let temp = tempdir().unwrap();
+    let dir = temp.path();
+    build_fixture(dir);
+    let before = head(dir);
+
+    refused(dir, &["rebase", "topic"], "branch.master.protected");
+    assert_eq!(head(dir), before);
+
+    run_libra(dir, &["rebase", "topic", "--override-protection"]);
+    assert_ne!(head(dir), before);
+    let reflog = run_libra(dir, &["reflog", "show"]);
+    assert!(
+        reflog.contains("checkout topic (override-protection: branch.master.protected)"),
+        "{reflog}"
+    );
+}
+
+#[test]
+fn test_protected_branch_filter_repo() {
+    let temp = tempdir().unwrap();
+    let dir = temp.path();
+    build_fixture(dir);
+    let before = head(dir);
+
+    refused(
+        dir,
+        &["filter-repo", "--message-filter", "C==>D"],
+        "branch.master.protected",
+    );
+    assert_eq!(head(dir), before);
+    // A dry run moves nothing and is not refused.
+    run_libra(
+        dir,
+        &["filter-repo", "--message-filter", "C==>D", "--dry-run"],
+    );
+
+    run_libra(
+        dir,
+        &[
+            "filter-repo",
+            "--message-filter",
+            "C==>D",
+            "--override-protection",
+        ],
+    );
+    assert_ne!(head(dir), before);
+    let reflog = run_libra(dir, &["reflog", "show", "release/1.0"]);
+    assert!(
+        reflog.contains("rewrite history (override-protection: branch.release/*.protected)"),
+        "{reflog}"
+    );
+}
+
+#[test]
+fn test_protected_branch_force_push() {
+    let temp = tempdir().unwrap();
+    let dir = temp.path();
+    build_fixture(dir);
+    run_libra(
+        dir,
+        &[
+            "remote",
+            "add",
+            "origin",
+            "https://example.invalid/repo.git",
+        ],
+    );
+
+    // Refused before the remote is contacted.
+    refused(
+        dir,
+        &["push", "--force", "origin", "master"],
+        "branch.master.protected",
+    );
+    let output = libra_output(dir, &["push", "origin", "master", "--override-protection"]);
+    assert!(!output.status.success(), "override requires --force");
+}
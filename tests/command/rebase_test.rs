@@ -1,7 +1,7 @@
 //! Tests rebase command applying commits onto new bases and handling conflicts.
 
 #![cfg(test)]
-use std::fs;
+use std::{collections::VecDeque, fs};
 
 use libra::{
     command::rebase::{RebaseArgs, execute},
@@ -206,6 +206,8 @@ async fn test_basic_rebase() {
         continue_rebase: false,
         abort: false,
         skip: false,
+        interactive: false,
+        override_protection: false,
     })
     .await;
 
@@ -374,6 +376,8 @@ async fn test_rebase_preserves_untracked_files() {
         continue_rebase: false,
         abort: false,
         skip: false,
+        interactive: false,
+        override_protection: false,
     })
     .await;
 
@@ -467,6 +471,8 @@ async fn test_rebase_already_up_to_date() {
         continue_rebase: false,
         abort: false,
         skip: false,
+        interactive: false,
+        override_protection: false,
     })
     .await;
 
@@ -597,6 +603,8 @@ async fn test_rebase_abort_when_no_rebase_in_progress() {
         continue_rebase: false,
         abort: false,
         skip: false,
+        interactive: false,
+        override_protection: false,
     })
     .await;
 
@@ -614,6 +622,8 @@ async fn test_rebase_abort_when_no_rebase_in_progress() {
         continue_rebase: false,
         abort: true,
         skip: false,
+        interactive: false,
+        override_protection: false,
     })
     .await;
 
@@ -636,8 +646,6 @@ async fn test_rebase_abort_when_no_rebase_in_progress() {
 #[tokio::test]
 #[serial]
 async fn test_rebase_abort_restores_branch_after_finalize_failure() {
-    use std::collections::VecDeque;
-
     use libra::{
         command::rebase::RebaseState,
         internal::{branch::Branch, head::Head},
@@ -760,6 +768,8 @@ async fn test_rebase_abort_restores_branch_after_finalize_failure() {
         continue_rebase: false,
         abort: false,
         skip: false,
+        interactive: false,
+        override_protection: false,
     })
     .await;
 
@@ -799,6 +809,8 @@ async fn test_rebase_abort_restores_branch_after_finalize_failure() {
         continue_rebase: false,
         abort: true,
         skip: false,
+        interactive: false,
+        override_protection: false,
     })
     .await;
 
@@ -862,6 +874,8 @@ async fn test_rebase_continue_no_rebase() {
         continue_rebase: true,
         abort: false,
         skip: false,
+        interactive: false,
+        override_protection: false,
     })
     .await;
 
@@ -909,6 +923,8 @@ async fn test_rebase_skip_no_rebase() {
         continue_rebase: false,
         abort: false,
         skip: true,
+        interactive: false,
+        override_protection: false,
     })
     .await;
 
@@ -1042,6 +1058,8 @@ async fn test_rebase_with_conflict_and_abort() {
         continue_rebase: false,
         abort: false,
         skip: false,
+        interactive: false,
+        override_protection: false,
     })
     .await;
 
@@ -1066,6 +1084,8 @@ async fn test_rebase_with_conflict_and_abort() {
         continue_rebase: false,
         abort: true,
         skip: false,
+        interactive: false,
+        override_protection: false,
     })
     .await;
 
@@ -1223,6 +1243,8 @@ async fn test_rebase_binary_conflict_writes_markers() {
         continue_rebase: false,
         abort: false,
         skip: false,
+        interactive: false,
+        override_protection: false,
     })
     .await;
 
@@ -1250,6 +1272,8 @@ async fn test_rebase_binary_conflict_writes_markers() {
         continue_rebase: false,
         abort: true,
         skip: false,
+        interactive: false,
+        override_protection: false,
     })
     .await;
     assert!(
@@ -1421,6 +1445,8 @@ async fn test_rebase_with_conflict_and_skip() {
         continue_rebase: false,
         abort: false,
         skip: false,
+        interactive: false,
+        override_protection: false,
     })
     .await;
 
@@ -1437,6 +1463,8 @@ async fn test_rebase_with_conflict_and_skip() {
         continue_rebase: false,
         abort: false,
         skip: true,
+        interactive: false,
+        override_protection: false,
     })
     .await;
 
@@ -1580,6 +1608,8 @@ async fn test_rebase_with_conflict_and_continue() {
         continue_rebase: false,
         abort: false,
         skip: false,
+        interactive: false,
+        override_protection: false,
     })
     .await;
 
@@ -1617,6 +1647,8 @@ async fn test_rebase_with_conflict_and_continue() {
         continue_rebase: true,
         abort: false,
         skip: false,
+        interactive: false,
+        override_protection: false,
     })
     .await;
 
@@ -1832,6 +1864,8 @@ async fn test_rebase_multiple_commits_partial_conflict() {
         continue_rebase: false,
         abort: false,
         skip: false,
+        interactive: false,
+        override_protection: false,
     })
     .await;
 
@@ -1849,6 +1883,8 @@ async fn test_rebase_multiple_commits_partial_conflict() {
         continue_rebase: false,
         abort: false,
         skip: true,
+        interactive: false,
+        override_protection: false,
     })
     .await;
 
@@ -2004,6 +2040,8 @@ async fn test_rebase_state_persistence() {
         continue_rebase: false,
         abort: false,
         skip: false,
+        interactive: false,
+        override_protection: false,
     })
     .await;
 
@@ -2038,6 +2076,8 @@ async fn test_rebase_state_persistence() {
         continue_rebase: false,
         abort: true,
         skip: false,
+        interactive: false,
+        override_protection: false,
     })
     .await;
 
@@ -2152,6 +2192,8 @@ async fn test_rebase_fast_forward_branch_behind() {
         continue_rebase: false,
         abort: false,
         skip: false,
+        interactive: false,
+        override_protection: false,
     })
     .await;
 
@@ -2269,6 +2311,8 @@ async fn test_rebase_fast_forward_blocks_dirty_workdir() {
         continue_rebase: false,
         abort: false,
         skip: false,
+        interactive: false,
+        override_protection: false,
     })
     .await;
 
@@ -2386,6 +2430,8 @@ async fn test_rebase_fast_forward_blocks_untracked_overwrite() {
         continue_rebase: false,
         abort: false,
         skip: false,
+        interactive: false,
+        override_protection: false,
     })
     .await;
 
@@ -2530,6 +2576,8 @@ async fn test_rebase_blocks_dirty_workdir_non_fast_forward() {
         continue_rebase: false,
         abort: false,
         skip: false,
+        interactive: false,
+        override_protection: false,
     })
     .await;
 
@@ -2679,6 +2727,8 @@ async fn test_rebase_conflict_preserves_non_conflicting_workdir() {
         continue_rebase: false,
         abort: false,
         skip: false,
+        interactive: false,
+        override_protection: false,
     })
     .await;
 
@@ -2701,6 +2751,8 @@ async fn test_rebase_conflict_preserves_non_conflicting_workdir() {
         continue_rebase: false,
         abort: true,
         skip: false,
+        interactive: false,
+        override_protection: false,
     })
     .await;
 }
@@ -2848,6 +2900,8 @@ async fn test_rebase_conflict_does_not_overwrite_untracked_paths() {
         continue_rebase: false,
         abort: false,
         skip: false,
+        interactive: false,
+        override_protection: false,
     })
     .await;
 
@@ -2867,6 +2921,8 @@ async fn test_rebase_conflict_does_not_overwrite_untracked_paths() {
         continue_rebase: false,
         abort: true,
         skip: false,
+        interactive: false,
+        override_protection: false,
     })
     .await;
 }
@@ -2994,6 +3050,8 @@ async fn test_rebase_continue_requires_resolution() {
         continue_rebase: false,
         abort: false,
         skip: false,
+        interactive: false,
+        override_protection: false,
     })
     .await;
 
@@ -3012,6 +3070,8 @@ async fn test_rebase_continue_requires_resolution() {
         continue_rebase: true,
         abort: false,
         skip: false,
+        interactive: false,
+        override_protection: false,
     })
     .await;
 
@@ -3030,6 +3090,187 @@ async fn test_rebase_continue_requires_resolution() {
         continue_rebase: false,
         abort: true,
         skip: false,
+        interactive: false,
+        override_protection: false,
+    })
+    .await;
+}
+
+/// Supplies the edited todo list and reworded messages of an interactive rebase, and
+/// records every message it was asked to edit.
+struct ScriptedEditor {
+    todo: Box<dyn Fn(&str) -> String>,
+    messages: VecDeque<String>,
+    asked: Vec<String>,
+}
+
+impl libra::internal::rebase_todo::RebaseEditor for ScriptedEditor {
+    fn edit_todo(&mut self, todo: &str) -> Result<String, String> {
+        Ok((self.todo)(todo))
+    }
+
+    fn edit_message(&mut self, message: &str) -> Result<String, String> {
+        self.asked.push(message.to_string());
+        Ok(self
+            .messages
+            .pop_front()
+            .unwrap_or_else(|| message.to_string()))
+    }
+}
+
+async fn commit_file(name: &str, content: &str, message: &str) -> ObjectHash {
+    fs::write(name, content).unwrap();
+    add::execute(AddArgs {
+        pathspec: vec![name.to_string()],
+        all: false,
+        update: false,
+        verbose: false,
+        dry_run: false,
+        ignore_errors: false,
+        refresh: false,
+        force: false,
+    })
+    .await;
+    commit::execute(CommitArgs {
+        message: Some(message.to_string()),
+        ..Default::default()
+    })
+    .await;
+    Head::current_commit().await.unwrap()
+}
+
+#[tokio::test]
+#[serial]
+async fn test_interactive_rebase_rewords_squashes_drops_and_execs() {
+    use libra::command::rebase::{RebaseState, execute_with_editor};
+
+    let temp_path = tempdir().unwrap();
+    test::setup_with_new_libra_in(temp_path.path()).await;
+    let _guard = ChangeDirGuard::new(temp_path.path());
+
+    let base = commit_file("base.txt", "base", "A: base").await;
+    commit_file("b.txt", "b", "B: add b").await;
+    commit_file("c.txt", "c", "C: add c").await;
+    commit_file("d.txt", "d", "D: add d").await;
+    commit_file("e.txt", "e", "E: add e").await;
+    commit_file("f.txt", "f", "F: add f").await;
+
+    // B is picked, C reworded, E squashed into D, F dropped, and a command runs last.
+    let mut editor = ScriptedEditor {
+        todo: Box::new(|todo: &str| {
+            assert!(todo.contains("# Commands:"), "{todo}");
+            let mut lines: Vec<String> = todo
+                .lines()
+                .filter(|line| line.starts_with("pick "))
+                .map(str::to_string)
+                .collect();
+            assert_eq!(lines.len(), 5, "{todo}");
+            assert!(lines[0].ends_with(" B: add b"), "{todo}");
+            lines[1] = lines[1].replacen("pick", "reword", 1);
+            lines[3] = lines[3].replacen("pick", "squash", 1);
+            lines[4] = lines[4].replacen("pick", "drop", 1);
+            lines.push("exec test -f e.txt && touch exec-ran".to_string());
+            lines.join("\n")
+        }),
+        messages: VecDeque::from(["C: add c, reworded\n# comment\n".to_string()]),
+        asked: Vec::new(),
+    };
+    execute_with_editor(
+        RebaseArgs {
+            upstream: Some(base.to_string()),
+            continue_rebase: false,
+            abort: false,
+            skip: false,
+            interactive: true,
+            override_protection: false,
+        },
+        &mut editor,
+    )
+    .await;
+
+    assert!(!RebaseState::is_in_progress().await.unwrap());
+    assert!(matches!(Head::current().await, Head::Branch(name) if name == "master"));
+    let head = Head::current_commit().await.unwrap();
+    assert_eq!(
+        commit_messages_from_head(&head, 10),
+        [
+            "D: add d\n\nE: add e",
+            "C: add c, reworded",
+            "B: add b",
+            "A: base"
+        ]
+    );
+    assert_eq!(editor.asked.len(), 2);
+    assert_eq!(editor.asked[0], "C: add c\n");
+    assert!(
+        editor.asked[1].ends_with("D: add d\n\nE: add e\n"),
+        "{}",
+        editor.asked[1]
+    );
+    for present in ["b.txt", "c.txt", "d.txt", "e.txt", "exec-ran"] {
+        assert!(temp_path.path().join(present).exists(), "{present}");
+    }
+    assert!(!temp_path.path().join("f.txt").exists());
+}
+
+#[tokio::test]
+#[serial]
+async fn test_interactive_rebase_rejects_bad_todo_and_stops_on_failed_exec() {
+    use libra::command::rebase::{RebaseState, execute_with_editor};
+
+    let temp_path = tempdir().unwrap();
+    test::setup_with_new_libra_in(temp_path.path()).await;
+    let _guard = ChangeDirGuard::new(temp_path.path());
+
+    let base = commit_file("base.txt", "base", "A: base").await;
+    commit_file("b.txt", "b", "B: add b").await;
+    let orig_head = commit_file("c.txt", "c", "C: add c").await;
+    let args = || RebaseArgs {
+        upstream: Some(base.to_string()),
+        continue_rebase: false,
+        abort: false,
+        skip: false,
+        interactive: true,
+        override_protection: false,
+    };
+
+    // An invalid todo list changes nothing.
+    let mut editor = ScriptedEditor {
+        todo: Box::new(|todo: &str| todo.replacen("pick", "squish", 1)),
+        messages: VecDeque::new(),
+        asked: Vec::new(),
+    };
+    execute_with_editor(args(), &mut editor).await;
+    assert!(!RebaseState::is_in_progress().await.unwrap());
+    assert_eq!(Head::current_commit().await.unwrap(), orig_head);
+
+    // A failing exec stops the rebase after the steps before it.
+    let mut editor = ScriptedEditor {
+        todo: Box::new(|todo: &str| todo.replacen("\npick", "\nexec false\npick", 1)),
+        messages: VecDeque::new(),
+        asked: Vec::new(),
+    };
+    execute_with_editor(args(), &mut editor).await;
+    assert!(RebaseState::is_in_progress().await.unwrap());
+    let state = RebaseState::load().await.unwrap();
+    assert_eq!(state.todo.len(), 1);
+    assert_eq!(
+        commit_messages_from_head(&Head::current_commit().await.unwrap(), 10),
+        ["B: add b", "A: base"]
+    );
+
+    execute(RebaseArgs {
+        upstream: None,
+        continue_rebase: true,
+        abort: false,
+        skip: false,
+        interactive: false,
+        override_protection: false,
     })
     .await;
+    assert!(!RebaseState::is_in_progress().await.unwrap());
+    assert_eq!(
+        commit_messages_from_head(&Head::current_commit().await.unwrap(), 10),
+        ["C: add c", "B: add b", "A: base"]
+    );
 }
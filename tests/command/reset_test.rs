@@ -56,6 +56,7 @@ async fn setup_standard_repo(
         all: false,
         contains: vec![],
         no_contains: vec![],
+        override_protection: false,
     })
     .await;
 
@@ -99,6 +100,7 @@ async fn setup_standard_repo(
         all: false,
         contains: vec![],
         no_contains: vec![],
+        override_protection: false,
     })
     .await;
 
@@ -142,6 +144,7 @@ async fn setup_standard_repo(
         all: false,
         contains: vec![],
         no_contains: vec![],
+        override_protection: false,
     })
     .await;
 
@@ -185,6 +188,7 @@ async fn setup_standard_repo(
         all: false,
         contains: vec![],
         no_contains: vec![],
+        override_protection: false,
     })
     .await;
 
@@ -228,6 +232,7 @@ async fn test_reset_soft() {
         mixed: false,
         hard: false,
         pathspecs: vec![],
+        override_protection: false,
     })
     .await;
 
@@ -272,6 +277,7 @@ async fn test_reset_mixed() {
         mixed: false, // false means default (mixed)
         hard: false,
         pathspecs: vec![],
+        override_protection: false,
     })
     .await;
 
@@ -320,6 +326,7 @@ async fn test_reset_hard() {
         mixed: false,
         hard: true,
         pathspecs: vec![],
+        override_protection: false,
     })
     .await;
 
@@ -386,6 +393,7 @@ async fn test_reset_with_head_reference() {
         mixed: true,
         hard: false,
         pathspecs: vec![],
+        override_protection: false,
     })
     .await;
 
@@ -429,6 +437,7 @@ async fn test_reset_on_branch() {
                 mixed: false,
                 hard: false,
                 pathspecs: vec![],
+                override_protection: false,
             })
             .await;
 
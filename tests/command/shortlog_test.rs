@@ -4,22 +4,42 @@
 //! - Basic author aggregation
 //! - Output sorting (`-n`)
 //! - Output format (`-s`, `-e`)
-//! - Date filtering (`--since`, `--until`)
+//! - Date filtering (`--since`, `--until`, `--date-match`)
+//! - Path filtering (`--path <glob>`)
+//! - Author filtering (`--author`), selecting the same commits as `log`
+//! - Abbreviated commit hashes (`--abbrev[=<n>]`)
+//! - Co-authorship pairs (`--pairs`)
+//! - Per-author date ranges (`--dates`)
+//! - Commit body snippets (`--body[=<lines>]`)
+//! - Bypassing the pager (`--no-pager`)
+//! - Multi-repository reports (`--repo`, `--workspace`, `--show-repo`)
+//! - Opt-in, incremental reuse of the shortlog cache (`--cache`, `--no-cache`)
+//! - Per-period timelines, in total or `--by-author` (`--yearly`, `--monthly`, `--weekly`,
+//!   `--csv`, `--fill-gaps`)
+//! - The totals line (`--oneline-total`)
+//! - Skipping unreadable commit objects, or aborting with `--strict`
+//! - Counting every local branch with `--all`, each shared commit once
+//! - Merging identities listed in a contributors alias file (`--aliases`)
+//! - Reading commits on several threads without changing the report (`--jobs`)
 //! - Grouping logic (merging authors with same name but different emails when `-e` is absent)
 
+use std::{collections::BTreeMap, str::FromStr};
+
 use clap::Parser;
 use git_internal::{
     hash::ObjectHash,
     internal::object::{
+        blob::Blob,
         commit::Commit,
         signature::{Signature, SignatureType},
+        tree::{Tree, TreeItem, TreeItemMode},
     },
 };
-use libra::internal::log::date_parser::parse_date;
+use libra::internal::log::{date_parser::parse_date, formatter::format_timestamp};
 
 use super::*;
 
-fn create_signature(signature_type: SignatureType, name: &str) -> Signature {
+pub(super) fn create_signature(signature_type: SignatureType, name: &str) -> Signature {
     Signature::from_data(
         format!(
             "{} {} <{}@oa.org> {} +0800",
@@ -48,7 +68,7 @@ fn create_signature(signature_type: SignatureType, name: &str) -> Signature {
 ///              \                                                /
 ///               11(LEAVE) -- 12(LEAVE) -- 13(SHY) ---- ---- ---
 /// The time of commit and the commit number should be in the same order.
-async fn create_test_commit_tree() -> String {
+pub(super) async fn create_test_commit_tree() -> String {
     let mut commit_1 = Commit::new(
         create_signature(SignatureType::Author, "LEAVE"),
         create_signature(SignatureType::Committer, "LEAVE"),
@@ -295,6 +315,107 @@ async fn test_shortlog_numbered() {
     assert_eq!(out_lines, exp_lines);
 }
 
+#[tokio::test]
+#[serial]
+async fn test_shortlog_subjects_reverse() {
+    let temp_path = tempdir().unwrap();
+    test::setup_with_new_libra_in(temp_path.path()).await;
+    let _guard = ChangeDirGuard::new(temp_path.path());
+    let _ = create_test_commit_tree().await;
+
+    // Subjects flip to oldest first within each author; headers keep the -n order.
+    let args = ShortlogArgs::try_parse_from(["libra", "-n", "--subjects-reverse"]).unwrap();
+    let mut buf = Vec::new();
+    shortlog::execute_to(args, &mut buf).await.unwrap();
+    let output = String::from_utf8(buf).unwrap();
+
+    let expected = r#"   5  LEAVE
+      Commit_1
+      Commit_2
+      Commit_4
+      Commit_11
+      Commit_12
+   2  SHY
+      Commit_5
+      Commit_13
+   2  SunZo
+      Commit_9
+      Commit_14
+   1  GUXUE
+      Commit_7
+   1  LENGSA
+      Commit_8
+   1  MMONK
+      Commit_10
+"#;
+
+    let out_lines: Vec<_> = output.lines().collect();
+    let exp_lines: Vec<_> = expected.lines().collect();
+    assert_eq!(out_lines, exp_lines);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_shortlog_body() {
+    let temp_path = tempdir().unwrap();
+    test::setup_with_new_libra_in(temp_path.path()).await;
+    let _guard = ChangeDirGuard::new(temp_path.path());
+
+    let messages = [
+        "Add parser\n\nThe parser reads the config.\nIt rejects unknown keys.\nFixes #12.\nSee also #7.",
+        "Fix typo",
+    ];
+    let mut parents = vec![];
+    for (day, message) in messages.iter().enumerate() {
+        let mut commit = Commit::new(
+            create_signature(SignatureType::Author, "LEAVE"),
+            create_signature(SignatureType::Committer, "LEAVE"),
+            ObjectHash::new(&[day as u8 + 1; 20]),
+            parents,
+            &format_commit_msg(message, None),
+        );
+        commit.author.timestamp = parse_date(&format!("2026-01-0{}", day + 1)).unwrap() as usize;
+        commit.committer.timestamp = commit.author.timestamp;
+        save_object(&commit, &commit.id).unwrap();
+        parents = vec![commit.id];
+    }
+    let branch_name = match Head::current().await {
+        Head::Branch(name) => name,
+        _ => panic!("should be branch"),
+    };
+    Branch::update_branch(&branch_name, &parents[0].to_string(), None).await;
+
+    let shortlog = |argv: &'static [&'static str]| async move {
+        let args = ShortlogArgs::try_parse_from(argv).unwrap();
+        let mut buf = Vec::new();
+        shortlog::execute_to(args, &mut buf).await.unwrap();
+        String::from_utf8(buf).unwrap()
+    };
+
+    let output = shortlog(&["libra", "--body=2"]).await;
+    assert_eq!(
+        output.lines().collect::<Vec<_>>(),
+        [
+            "   2  LEAVE",
+            "      Fix typo",
+            "      Add parser",
+            "          The parser reads the config.",
+            "          It rejects unknown keys.",
+        ]
+    );
+
+    // Without a count three lines are shown.
+    let output = shortlog(&["libra", "--body"]).await;
+    assert!(
+        output.ends_with("          It rejects unknown keys.\n          Fixes #12.\n"),
+        "{output}"
+    );
+
+    // The default stays subjects only.
+    let output = shortlog(&["libra"]).await;
+    assert_eq!(output, "   2  LEAVE\n      Fix typo\n      Add parser\n");
+}
+
 #[tokio::test]
 #[serial]
 async fn test_shortlog_summary() {
@@ -322,6 +443,36 @@ async fn test_shortlog_summary() {
     assert_eq!(out_lines, exp_lines);
 }
 
+#[tokio::test]
+#[serial]
+async fn test_shortlog_oneline_total() {
+    let temp_path = tempdir().unwrap();
+    test::setup_with_new_libra_in(temp_path.path()).await;
+    let _guard = ChangeDirGuard::new(temp_path.path());
+    let _ = create_test_commit_tree().await;
+
+    let run = |flags: &[&str]| {
+        let args = ShortlogArgs::try_parse_from(["libra"].iter().chain(flags)).unwrap();
+        async move {
+            let mut buf = Vec::new();
+            shortlog::execute_to(args, &mut buf).await.unwrap();
+            String::from_utf8(buf).unwrap()
+        }
+    };
+
+    assert_eq!(run(&["--oneline-total"]).await, "12 commits by 6 authors\n");
+    assert_eq!(
+        run(&["--oneline-total", "-c", "-s"]).await,
+        "12 commits by 6 committers\n"
+    );
+    // Filters still apply: no commit is newer than the far future.
+    assert_eq!(
+        run(&["--oneline-total", "--since", "2999-01-01"]).await,
+        "0 commits by 0 authors\n"
+    );
+    assert!(ShortlogArgs::try_parse_from(["libra", "--oneline-total", "--pairs"]).is_err());
+}
+
 #[tokio::test]
 #[serial]
 async fn test_shortlog_email() {
@@ -531,13 +682,9 @@ async fn test_shortlog_date_filter() {
     assert_eq!(out_lines, exp_lines);
 }
 
-#[tokio::test]
-#[serial]
-async fn test_shortlog_committer_date_filter() {
-    let temp_path = tempdir().unwrap();
-    test::setup_with_new_libra_in(temp_path.path()).await;
-    let _guard = ChangeDirGuard::new(temp_path.path());
-
+/// Point the current branch at a single commit authored on 2026-01-01 but committed on
+/// 2026-02-01.
+async fn create_divergent_date_commit() {
     // Create a commit with different author and committer dates
     let mut commit = Commit::new(
         create_signature(SignatureType::Author, "TEST"),
@@ -558,6 +705,15 @@ async fn test_shortlog_committer_date_filter() {
         _ => panic!("should be branch"),
     };
     Branch::update_branch(&branch_name, &commit.id.to_string(), None).await;
+}
+
+#[tokio::test]
+#[serial]
+async fn test_shortlog_committer_date_filter() {
+    let temp_path = tempdir().unwrap();
+    test::setup_with_new_libra_in(temp_path.path()).await;
+    let _guard = ChangeDirGuard::new(temp_path.path());
+    create_divergent_date_commit().await;
 
     // Filter since 2026-01-15
     // Should exclude if using author date (Jan 1 < Jan 15)
@@ -572,3 +728,1021 @@ async fn test_shortlog_committer_date_filter() {
     assert!(output.contains("TEST"));
     assert!(output.contains("Test Commit"));
 }
+
+#[tokio::test]
+#[serial]
+async fn test_shortlog_date_match() {
+    let temp_path = tempdir().unwrap();
+    test::setup_with_new_libra_in(temp_path.path()).await;
+    let _guard = ChangeDirGuard::new(temp_path.path());
+    create_divergent_date_commit().await;
+
+    // The first window holds only the author date (2026-01-01), the second neither date.
+    let author_window = ["--since", "2025-12-25", "--until", "2026-01-15"];
+    let empty_window = ["--since", "2026-01-10", "--until", "2026-01-20"];
+    for (window, date_match, included) in [
+        (author_window, None, false),
+        (author_window, Some("committer"), false),
+        (author_window, Some("author"), true),
+        (author_window, Some("any"), true),
+        (empty_window, Some("any"), false),
+    ] {
+        let mut argv = vec!["libra"];
+        argv.extend(window);
+        if let Some(date_match) = date_match {
+            argv.extend(["--date-match", date_match]);
+        }
+        let args = ShortlogArgs::try_parse_from(&argv).unwrap();
+        let mut buf = Vec::new();
+        shortlog::execute_to(args, &mut buf).await.unwrap();
+        let output = String::from_utf8(buf).unwrap();
+
+        assert_eq!(
+            output.contains("Test Commit"),
+            included,
+            "{argv:?}: {output}"
+        );
+    }
+}
+
+/// Save a (possibly nested) tree holding `files` as `(path, content)` pairs and return its id.
+fn save_tree(files: &[(&str, &str)]) -> ObjectHash {
+    let mut blobs = Vec::new();
+    let mut dirs: BTreeMap<&str, Vec<(&str, &str)>> = BTreeMap::new();
+    for (path, content) in files {
+        match path.split_once('/') {
+            Some((dir, rest)) => dirs.entry(dir).or_default().push((rest, content)),
+            None => blobs.push((*path, *content)),
+        }
+    }
+
+    let mut items = Vec::new();
+    for (name, content) in blobs {
+        let blob = Blob::from_content(content);
+        save_object(&blob, &blob.id).unwrap();
+        items.push(TreeItem::new(TreeItemMode::Blob, blob.id, name.to_string()));
+    }
+    for (name, entries) in dirs {
+        items.push(TreeItem::new(
+            TreeItemMode::Tree,
+            save_tree(&entries),
+            name.to_string(),
+        ));
+    }
+    let tree = Tree::from_tree_items(items).unwrap();
+    save_object(&tree, &tree.id).unwrap();
+    tree.id
+}
+
+/// (author, subject, files in the commit's tree)
+type HistoryEntry = (
+    &'static str,
+    &'static str,
+    &'static [(&'static str, &'static str)],
+);
+
+#[tokio::test]
+#[serial]
+async fn test_shortlog_path_filter() {
+    let temp_path = tempdir().unwrap();
+    test::setup_with_new_libra_in(temp_path.path()).await;
+    let _guard = ChangeDirGuard::new(temp_path.path());
+
+    let history: [HistoryEntry; 4] = [
+        ("LEAVE", "Add guide", &[("docs/guide.md", "v1")]),
+        (
+            "SHY",
+            "Add agent",
+            &[("docs/guide.md", "v1"), ("src/ai/agent.rs", "v1")],
+        ),
+        (
+            "SHY",
+            "Tweak agent",
+            &[("docs/guide.md", "v1"), ("src/ai/agent.rs", "v2")],
+        ),
+        (
+            "LEAVE",
+            "Add main and update guide",
+            &[
+                ("docs/guide.md", "v2"),
+                ("src/ai/agent.rs", "v2"),
+                ("src/main.rs", "v1"),
+            ],
+        ),
+    ];
+
+    let mut parents = vec![];
+    for (day, (author, subject, files)) in history.iter().enumerate() {
+        let mut commit = Commit::new(
+            create_signature(SignatureType::Author, author),
+            create_signature(SignatureType::Committer, author),
+            save_tree(files),
+            parents,
+            &format_commit_msg(subject, None),
+        );
+        commit.author.timestamp = parse_date(&format!("2026-01-0{}", day + 1)).unwrap() as usize;
+        commit.committer.timestamp = commit.author.timestamp;
+        save_object(&commit, &commit.id).unwrap();
+        parents = vec![commit.id];
+    }
+    let branch_name = match Head::current().await {
+        Head::Branch(name) => name,
+        _ => panic!("should be branch"),
+    };
+    Branch::update_branch(&branch_name, &parents[0].to_string(), None).await;
+
+    let shortlog_summary = |argv: &'static [&'static str]| async move {
+        let args = ShortlogArgs::try_parse_from(argv).unwrap();
+        let mut buf = Vec::new();
+        shortlog::execute_to(args, &mut buf).await.unwrap();
+        String::from_utf8(buf).unwrap()
+    };
+
+    // A directory covers everything below it.
+    let output = shortlog_summary(&["libra", "-s", "--path", "src/ai/"]).await;
+    assert_eq!(output.lines().collect::<Vec<_>>(), ["   2  SHY"]);
+
+    // Globs match individual files.
+    let output = shortlog_summary(&["libra", "-s", "--path", "src/*.rs"]).await;
+    assert_eq!(output.lines().collect::<Vec<_>>(), ["   1  LEAVE"]);
+
+    // The option is repeatable; a commit counts once even if it matches several globs.
+    let output = shortlog_summary(&["libra", "-s", "--path", "docs", "--path", "src/**"]).await;
+    assert_eq!(
+        output.lines().collect::<Vec<_>>(),
+        ["   2  LEAVE", "   2  SHY"]
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn test_shortlog_selects_same_commits_as_log() {
+    let temp_path = tempdir().unwrap();
+    test::setup_with_new_libra_in(temp_path.path()).await;
+    let _guard = ChangeDirGuard::new(temp_path.path());
+
+    let history: [HistoryEntry; 5] = [
+        ("LEAVE", "Add guide", &[("docs/guide.md", "v1")]),
+        (
+            "SHY",
+            "Add agent",
+            &[("docs/guide.md", "v1"), ("src/agent.rs", "v1")],
+        ),
+        (
+            "LEAVE",
+            "Tweak agent",
+            &[("docs/guide.md", "v1"), ("src/agent.rs", "v2")],
+        ),
+        (
+            "LEAVE",
+            "Update guide",
+            &[("docs/guide.md", "v2"), ("src/agent.rs", "v2")],
+        ),
+        (
+            "LEAVE",
+            "Tweak agent again",
+            &[("docs/guide.md", "v2"), ("src/agent.rs", "v3")],
+        ),
+    ];
+
+    let mut parents = vec![];
+    for (day, (author, subject, files)) in history.iter().enumerate() {
+        let mut commit = Commit::new(
+            create_signature(SignatureType::Author, author),
+            create_signature(SignatureType::Committer, author),
+            save_tree(files),
+            parents,
+            &format_commit_msg(subject, None),
+        );
+        commit.author.timestamp = parse_date(&format!("2026-01-0{}", day + 1)).unwrap() as usize;
+        commit.committer.timestamp = commit.author.timestamp;
+        save_object(&commit, &commit.id).unwrap();
+        parents = vec![commit.id];
+    }
+    let branch_name = match Head::current().await {
+        Head::Branch(name) => name,
+        _ => panic!("should be branch"),
+    };
+    Branch::update_branch(&branch_name, &parents[0].to_string(), None).await;
+
+    let args = ShortlogArgs::try_parse_from([
+        "libra",
+        "--author",
+        "leave",
+        "--since",
+        "2026-01-02",
+        "--path",
+        "src",
+    ])
+    .unwrap();
+    let mut buf = Vec::new();
+    shortlog::execute_to(args, &mut buf).await.unwrap();
+    let mut shortlog_subjects: Vec<String> = String::from_utf8(buf)
+        .unwrap()
+        .lines()
+        .filter(|line| line.starts_with("      "))
+        .map(|line| line.trim().to_string())
+        .collect();
+
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_libra"))
+        .current_dir(temp_path.path())
+        .args([
+            "log",
+            "--oneline",
+            "--author",
+            "leave",
+            "--since",
+            "2026-01-02",
+            "src",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let mut log_subjects: Vec<String> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .map(|(_, subject)| subject.trim().to_string())
+        .collect();
+
+    shortlog_subjects.sort();
+    log_subjects.sort();
+    assert_eq!(shortlog_subjects, ["Tweak agent", "Tweak agent again"]);
+    assert_eq!(log_subjects, shortlog_subjects);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_shortlog_abbrev() {
+    let temp_path = tempdir().unwrap();
+    test::setup_with_new_libra_in(temp_path.path()).await;
+    let _guard = ChangeDirGuard::new(temp_path.path());
+    let head_hash = create_test_commit_tree().await;
+
+    for (argv, len) in [
+        (&["libra", "--abbrev"][..], 7),
+        (&["libra", "--abbrev=12"][..], 12),
+    ] {
+        let args = ShortlogArgs::try_parse_from(argv).unwrap();
+        let mut buf = Vec::new();
+        shortlog::execute_to(args, &mut buf).await.unwrap();
+        let output = String::from_utf8(buf).unwrap();
+
+        let subjects: Vec<&str> = output
+            .lines()
+            .filter(|line| line.starts_with("      "))
+            .map(str::trim_start)
+            .collect();
+        assert_eq!(subjects.len(), 12);
+        for subject in &subjects {
+            let (hash, rest) = subject.split_once(' ').unwrap();
+            assert_eq!(hash.len(), len, "{subject:?}");
+            assert!(hash.chars().all(|c| c.is_ascii_hexdigit()), "{subject:?}");
+            assert!(rest.starts_with("Commit_"), "{subject:?}");
+        }
+
+        let head_line = format!("{} Commit_14", &head_hash[..len]);
+        assert!(subjects.contains(&head_line.as_str()), "{output}");
+    }
+
+    // Default output carries no hashes.
+    let args = ShortlogArgs::try_parse_from(["libra"]).unwrap();
+    let mut buf = Vec::new();
+    shortlog::execute_to(args, &mut buf).await.unwrap();
+    assert!(
+        String::from_utf8(buf)
+            .unwrap()
+            .contains("      Commit_14\n")
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn test_shortlog_pairs() {
+    let temp_path = tempdir().unwrap();
+    test::setup_with_new_libra_in(temp_path.path()).await;
+    let _guard = ChangeDirGuard::new(temp_path.path());
+
+    // (author, message); SHY crediting themselves must not form a pair.
+    let history = [
+        (
+            "LEAVE",
+            "Pair on parser\n\nCo-authored-by: SHY <shy@oa.org>\nCo-authored-by: GUXUE <guxue@oa.org>",
+        ),
+        ("SHY", "Fix parser\n\nCo-authored-by: LEAVE <leave@oa.org>"),
+        (
+            "SHY",
+            "Add tests\n\nCo-authored-by: GUXUE <guxue@oa.org>\nCo-authored-by: SHY <shy@oa.org>",
+        ),
+        ("GUXUE", "Solo work"),
+    ];
+
+    let mut parents = vec![];
+    for (day, (author, message)) in history.iter().enumerate() {
+        let mut commit = Commit::new(
+            create_signature(SignatureType::Author, author),
+            create_signature(SignatureType::Committer, author),
+            ObjectHash::new(&[day as u8 + 1; 20]),
+            parents,
+            &format_commit_msg(message, None),
+        );
+        commit.author.timestamp = parse_date(&format!("2026-01-0{}", day + 1)).unwrap() as usize;
+        commit.committer.timestamp = commit.author.timestamp;
+        save_object(&commit, &commit.id).unwrap();
+        parents = vec![commit.id];
+    }
+    let branch_name = match Head::current().await {
+        Head::Branch(name) => name,
+        _ => panic!("should be branch"),
+    };
+    Branch::update_branch(&branch_name, &parents[0].to_string(), None).await;
+
+    let shortlog = |argv: &'static [&'static str]| async move {
+        let args = ShortlogArgs::try_parse_from(argv).unwrap();
+        let mut buf = Vec::new();
+        shortlog::execute_to(args, &mut buf).await.unwrap();
+        String::from_utf8(buf).unwrap()
+    };
+
+    let output = shortlog(&["libra", "--pairs", "-s"]).await;
+    assert_eq!(
+        output.lines().collect::<Vec<_>>(),
+        [
+            "   1  GUXUE + LEAVE",
+            "   2  GUXUE + SHY",
+            "   2  LEAVE + SHY"
+        ]
+    );
+
+    let output = shortlog(&["libra", "--pairs", "-s", "-n"]).await;
+    assert_eq!(
+        output.lines().collect::<Vec<_>>(),
+        [
+            "   2  GUXUE + SHY",
+            "   2  LEAVE + SHY",
+            "   1  GUXUE + LEAVE"
+        ]
+    );
+
+    let output = shortlog(&["libra", "--pairs", "-s", "-e"]).await;
+    assert!(
+        output
+            .lines()
+            .any(|line| line == "   2  LEAVE <leave@oa.org> + SHY <shy@oa.org>"),
+        "{output}"
+    );
+
+    // Detailed mode lists the shared commits under each pair.
+    let output = shortlog(&["libra", "--pairs"]).await;
+    assert!(
+        output.contains("   2  LEAVE + SHY\n      Fix parser\n      Pair on parser\n"),
+        "{output}"
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn test_shortlog_dates() {
+    let temp_path = tempdir().unwrap();
+    test::setup_with_new_libra_in(temp_path.path()).await;
+    let _guard = ChangeDirGuard::new(temp_path.path());
+    let _ = create_test_commit_tree().await;
+
+    let args = ShortlogArgs::try_parse_from(["libra", "-s", "--dates"]).unwrap();
+    let mut buf = Vec::new();
+    shortlog::execute_to(args, &mut buf).await.unwrap();
+    let output = String::from_utf8(buf).unwrap();
+
+    // LEAVE authored commits 1, 2, 4, 11 and 12.
+    let expected = format!(
+        "   5  LEAVE ({} .. {})",
+        format_timestamp(parse_date("2026-01-01").unwrap()),
+        format_timestamp(parse_date("2026-01-12").unwrap())
+    );
+    assert!(output.lines().any(|line| line == expected), "{output}");
+
+    let guxue = format_timestamp(parse_date("2026-01-07").unwrap());
+    assert!(
+        output
+            .lines()
+            .any(|line| line == format!("   1  GUXUE ({guxue} .. {guxue})")),
+        "{output}"
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn test_shortlog_no_pager_writes_directly() {
+    let temp_path = tempdir().unwrap();
+    test::setup_with_new_libra_in(temp_path.path()).await;
+    let _guard = ChangeDirGuard::new(temp_path.path());
+    let _ = create_test_commit_tree().await;
+
+    // Even for a terminal, `--no-pager` must not spawn anything that waits for input.
+    let args = ShortlogArgs::try_parse_from(["libra", "-s", "--pager", "--no-pager"]).unwrap();
+    let mut buf = Vec::new();
+    shortlog::execute_paged_to(args, &mut buf, true)
+        .await
+        .unwrap();
+    let output = String::from_utf8(buf).unwrap();
+
+    assert!(output.lines().any(|line| line == "   5  LEAVE"), "{output}");
+    assert_eq!(output.lines().count(), 6, "{output}");
+}
+
+#[tokio::test]
+#[serial]
+async fn test_shortlog_merges_repositories() {
+    let workspace = tempdir().unwrap();
+    for name in ["alpha", "beta"] {
+        let repo = workspace.path().join(name);
+        std::fs::create_dir(&repo).unwrap();
+        test::setup_with_new_libra_in(&repo).await;
+        let _guard = ChangeDirGuard::new(&repo);
+        let _ = create_test_commit_tree().await;
+    }
+    let _guard = ChangeDirGuard::new(workspace.path());
+
+    // Both histories share author names, so the counts add up; a missing repository is
+    // skipped instead of failing the report.
+    let args = ShortlogArgs::try_parse_from([
+        "libra", "-s", "-n", "--repo", "alpha", "--repo", "missing", "--repo", "beta",
+    ])
+    .unwrap();
+    let mut buf = Vec::new();
+    shortlog::execute_to(args, &mut buf).await.unwrap();
+    let output = String::from_utf8(buf).unwrap();
+    let expected = [
+        "  10  LEAVE",
+        "   4  SHY",
+        "   4  SunZo",
+        "   2  GUXUE",
+        "   2  LENGSA",
+        "   2  MMONK",
+    ];
+    assert_eq!(output.lines().collect::<Vec<_>>(), expected);
+
+    std::fs::write(
+        workspace.path().join(".libra-workspace"),
+        "# team repositories\nalpha\n\nbeta\n",
+    )
+    .unwrap();
+    let args = ShortlogArgs::try_parse_from([
+        "libra",
+        "--workspace",
+        "--show-repo",
+        "--since",
+        "2026-01-14",
+    ])
+    .unwrap();
+    let mut buf = Vec::new();
+    shortlog::execute_to(args, &mut buf).await.unwrap();
+    let output = String::from_utf8(buf).unwrap();
+    let lines: Vec<_> = output.lines().collect();
+    assert_eq!(lines[0], "   2  SunZo");
+    let mut subjects = lines[1..].to_vec();
+    subjects.sort();
+    assert_eq!(
+        subjects,
+        ["      [alpha] Commit_14", "      [beta] Commit_14"]
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn test_shortlog_path_filter_reads_each_repository() {
+    let workspace = tempdir().unwrap();
+    for (name, author, file) in [("alpha", "LEAVE", "docs/a.md"), ("beta", "SHY", "src/b.rs")] {
+        let repo = workspace.path().join(name);
+        std::fs::create_dir(&repo).unwrap();
+        test::setup_with_new_libra_in(&repo).await;
+        let _guard = ChangeDirGuard::new(&repo);
+        let commit = Commit::new(
+            create_signature(SignatureType::Author, author),
+            create_signature(SignatureType::Committer, author),
+            save_tree(&[(file, "v1")]),
+            vec![],
+            &format_commit_msg("Add file", None),
+        );
+        save_object(&commit, &commit.id).unwrap();
+        let Head::Branch(branch_name) = Head::current().await else {
+            panic!("should be branch");
+        };
+        Branch::update_branch(&branch_name, &commit.id.to_string(), None).await;
+    }
+    let _guard = ChangeDirGuard::new(workspace.path());
+    let cwd = std::env::current_dir().unwrap();
+
+    // Trees are read from each repository's own object store, without leaving the
+    // directory the command was started in.
+    for (glob, expected) in [("docs/**", "   1  LEAVE"), ("src/**", "   1  SHY")] {
+        let args = ShortlogArgs::try_parse_from([
+            "libra", "-s", "--repo", "alpha", "--repo", "beta", "--path", glob,
+        ])
+        .unwrap();
+        let mut buf = Vec::new();
+        shortlog::execute_to(args, &mut buf).await.unwrap();
+        let output = String::from_utf8(buf).unwrap();
+        assert_eq!(output.lines().collect::<Vec<_>>(), [expected]);
+        assert_eq!(std::env::current_dir().unwrap(), cwd);
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn test_shortlog_cache_reads_only_new_commits() {
+    let temp_path = tempdir().unwrap();
+    test::setup_with_new_libra_in(temp_path.path()).await;
+    let _guard = ChangeDirGuard::new(temp_path.path());
+    let tip = create_test_commit_tree().await;
+
+    let run = |flags: &[&str]| {
+        let args = ShortlogArgs::try_parse_from(["libra", "-n"].iter().chain(flags)).unwrap();
+        async move {
+            let mut buf = Vec::new();
+            shortlog::execute_to(args, &mut buf).await.unwrap();
+            String::from_utf8(buf).unwrap()
+        }
+    };
+    // The cache is opt-in.
+    let cache_file = temp_path.path().join(".libra/info/shortlog-cache");
+    let plain = run(&[]).await;
+    assert!(!cache_file.exists());
+    let status = std::process::Command::new(env!("CARGO_BIN_EXE_libra"))
+        .current_dir(temp_path.path())
+        .args(["shortlog", "-n"])
+        .env("LIBRA_CONFIG_SHORTLOG_CACHE", "true")
+        .stdout(std::process::Stdio::null())
+        .status()
+        .unwrap();
+    assert!(status.success() && cache_file.exists());
+    std::fs::remove_file(&cache_file).unwrap();
+    let warm = run(&["--cache"]).await;
+    assert!(cache_file.exists());
+    assert_eq!(warm, plain);
+    assert_eq!(run(&["--cache", "--no-cache"]).await, plain);
+
+    // Two new commits on top of the cached tip.
+    let mut parent = ObjectHash::from_str(&tip).unwrap();
+    for (n, author) in [(15, "LEAVE"), (16, "GUXUE")] {
+        let mut commit = Commit::new(
+            create_signature(SignatureType::Author, author),
+            create_signature(SignatureType::Committer, author),
+            ObjectHash::new(&[n; 20]),
+            vec![parent],
+            &format_commit_msg(&format!("Commit_{n}"), None),
+        );
+        commit.author.timestamp = parse_date(&format!("2026-01-{n}")).unwrap() as usize;
+        commit.committer.timestamp = commit.author.timestamp;
+        save_object(&commit, &commit.id).unwrap();
+        parent = commit.id;
+    }
+    Branch::update_branch("master", &parent.to_string(), None).await;
+    let uncached = run(&["--no-cache"]).await;
+    assert!(uncached.starts_with("   6  LEAVE\n      Commit_15\n"));
+
+    // The cached commits are no longer readable, so the next run must not touch them.
+    let storage = libra::utils::util::try_get_storage_path(None).unwrap();
+    for commit in libra::command::log::get_reachable_commits(tip, None).await {
+        let id = commit.id.to_string();
+        std::fs::remove_file(storage.join("objects").join(&id[..2]).join(&id[2..])).unwrap();
+    }
+    assert_eq!(run(&["--cache"]).await, uncached);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_shortlog_timeline() {
+    let temp_path = tempdir().unwrap();
+    test::setup_with_new_libra_in(temp_path.path()).await;
+    let _guard = ChangeDirGuard::new(temp_path.path());
+    let tip = create_test_commit_tree().await;
+
+    // 20:00 UTC on March 31st is already April 1st in the +0800 zone of the signatures.
+    let late_march = chrono::DateTime::parse_from_rfc3339("2026-03-31T20:00:00Z")
+        .unwrap()
+        .timestamp() as usize;
+    let mut parent = ObjectHash::from_str(&tip).unwrap();
+    for (n, author, timestamp) in [
+        (15, "Doe, Jane", parse_date("2026-03-03").unwrap() as usize),
+        (16, "LEAVE", late_march),
+    ] {
+        let mut commit = Commit::new(
+            create_signature(SignatureType::Author, author),
+            create_signature(SignatureType::Committer, author),
+            ObjectHash::new(&[n; 20]),
+            vec![parent],
+            &format_commit_msg(&format!("Commit_{n}"), None),
+        );
+        commit.author.timestamp = timestamp;
+        commit.committer.timestamp = timestamp;
+        save_object(&commit, &commit.id).unwrap();
+        parent = commit.id;
+    }
+    Branch::update_branch("master", &parent.to_string(), None).await;
+
+    let run = |flags: &[&str]| {
+        let args = ShortlogArgs::try_parse_from(["libra", "-n"].iter().chain(flags)).unwrap();
+        async move {
+            let mut buf = Vec::new();
+            shortlog::execute_to(args, &mut buf).await.unwrap();
+            String::from_utf8(buf).unwrap()
+        }
+    };
+
+    assert_eq!(
+        run(&["--monthly", "--fill-gaps"]).await,
+        "2026-01  12\n2026-02  0\n2026-03  1\n2026-04  1\n"
+    );
+
+    let output = run(&["--monthly", "--by-author"]).await;
+    let lines: Vec<_> = output.lines().take(5).collect();
+    assert_eq!(
+        lines,
+        [
+            "   6  LEAVE",
+            "      2026-01  5",
+            "      2026-04  1",
+            "   2  SHY",
+            "      2026-01  2",
+        ]
+    );
+    assert!(output.contains("   1  Doe, Jane\n      2026-03  1\n"));
+
+    let output = run(&["--monthly", "--by-author", "--fill-gaps"]).await;
+    let lines: Vec<_> = output.lines().take(5).collect();
+    assert_eq!(
+        lines,
+        [
+            "   6  LEAVE",
+            "      2026-01  5",
+            "      2026-02  0",
+            "      2026-03  0",
+            "      2026-04  1",
+        ]
+    );
+
+    // the trailing machine_id column is covered by test_shortlog_csv_machine_id_is_stable
+    let output = run(&["--monthly", "--by-author", "--csv"]).await;
+    let lines: Vec<_> = output
+        .lines()
+        .take(4)
+        .map(|line| line.rsplit_once(',').unwrap().0)
+        .collect();
+    assert_eq!(
+        lines,
+        [
+            "author,period,count",
+            "LEAVE,2026-01,5",
+            "LEAVE,2026-04,1",
+            "SHY,2026-01,2",
+        ]
+    );
+    assert!(output.contains("\n\"Doe, Jane\",2026-03,1,"));
+
+    let output = run(&["--weekly", "--by-author", "--csv"]).await;
+    assert!(output.contains("\n\"Doe, Jane\",2026-W10,1,"));
+
+    // The output modes need a timeline to apply to.
+    assert!(ShortlogArgs::try_parse_from(["libra", "--csv"]).is_err());
+    assert!(ShortlogArgs::try_parse_from(["libra", "--by-author"]).is_err());
+    // A commit counts for every pair it credits, so pair timelines are per pair only.
+    assert_eq!(run(&["--monthly", "--pairs"]).await, "");
+    assert!(ShortlogArgs::try_parse_from(["libra", "--monthly", "--weekly"]).is_err());
+}
+
+#[tokio::test]
+#[serial]
+async fn test_shortlog_yearly_timeline() {
+    let temp_path = tempdir().unwrap();
+    test::setup_with_new_libra_in(temp_path.path()).await;
+    let _guard = ChangeDirGuard::new(temp_path.path());
+    let tip = create_test_commit_tree().await;
+
+    // 20:00 UTC on December 31st 2025 is already 2026 in the +0800 zone of the signatures.
+    let new_year = chrono::DateTime::parse_from_rfc3339("2025-12-31T20:00:00Z")
+        .unwrap()
+        .timestamp() as usize;
+    let mut parent = ObjectHash::from_str(&tip).unwrap();
+    for (n, author, timestamp) in [
+        (15, "SHY", parse_date("2025-06-01").unwrap() as usize),
+        (16, "SHY", parse_date("2025-11-20").unwrap() as usize),
+        (17, "LEAVE", new_year),
+    ] {
+        let mut commit = Commit::new(
+            create_signature(SignatureType::Author, author),
+            create_signature(SignatureType::Committer, author),
+            ObjectHash::new(&[n; 20]),
+            vec![parent],
+            &format_commit_msg(&format!("Commit_{n}"), None),
+        );
+        commit.author.timestamp = timestamp;
+        commit.committer.timestamp = timestamp;
+        save_object(&commit, &commit.id).unwrap();
+        parent = commit.id;
+    }
+    Branch::update_branch("master", &parent.to_string(), None).await;
+
+    let run = |flags: &[&str]| {
+        let args = ShortlogArgs::try_parse_from(["libra", "-n"].iter().chain(flags)).unwrap();
+        async move {
+            let mut buf = Vec::new();
+            shortlog::execute_to(args, &mut buf).await.unwrap();
+            String::from_utf8(buf).unwrap()
+        }
+    };
+
+    // One count per year by default, every author together.
+    assert_eq!(run(&["--yearly"]).await, "2025  2\n2026  13\n");
+    assert_eq!(
+        run(&["--yearly", "--csv"]).await,
+        "period,count\n2025,2\n2026,13\n"
+    );
+
+    let output = run(&["--yearly", "--by-author"]).await;
+    let lines: Vec<_> = output.lines().take(5).collect();
+    assert_eq!(
+        lines,
+        [
+            "   6  LEAVE",
+            "      2026  6",
+            "   4  SHY",
+            "      2025  2",
+            "      2026  2",
+        ]
+    );
+
+    let output = run(&["--yearly", "--by-author", "--csv", "--fill-gaps"]).await;
+    let lines: Vec<_> = output
+        .lines()
+        .take(5)
+        .map(|line| line.rsplit_once(',').unwrap().0)
+        .collect();
+    assert_eq!(
+        lines,
+        [
+            "author,period,count",
+            "LEAVE,2025,0",
+            "LEAVE,2026,6",
+            "SHY,2025,2",
+            "SHY,2026,2",
+        ]
+    );
+    assert!(ShortlogArgs::try_parse_from(["libra", "--yearly", "--monthly"]).is_err());
+}
+
+#[tokio::test]
+#[serial]
+async fn test_shortlog_csv_machine_id_is_stable() {
+    let temp_path = tempdir().unwrap();
+    test::setup_with_new_libra_in(temp_path.path()).await;
+    let _guard = ChangeDirGuard::new(temp_path.path());
+    let tip = create_test_commit_tree().await;
+
+    // "Ada" and " ADA" are one identity (ada@oa.org) written with different casing.
+    let mut parent = ObjectHash::from_str(&tip).unwrap();
+    for (n, author) in [(15, "Ada"), (16, " ADA")] {
+        let commit = Commit::new(
+            create_signature(SignatureType::Author, author),
+            create_signature(SignatureType::Committer, author),
+            ObjectHash::new(&[n; 20]),
+            vec![parent],
+            &format_commit_msg(&format!("Commit_{n}"), None),
+        );
+        save_object(&commit, &commit.id).unwrap();
+        parent = commit.id;
+    }
+    Branch::update_branch("master", &parent.to_string(), None).await;
+
+    let run = || async {
+        let args =
+            ShortlogArgs::try_parse_from(["libra", "--yearly", "--by-author", "--csv"]).unwrap();
+        let mut buf = Vec::new();
+        shortlog::execute_to(args, &mut buf).await.unwrap();
+        String::from_utf8(buf).unwrap()
+    };
+    let ids = |output: &str| -> BTreeMap<String, String> {
+        output
+            .lines()
+            .skip(1)
+            .map(|line| {
+                let (rest, id) = line.rsplit_once(',').unwrap();
+                let author = rest.split(',').next().unwrap().trim().to_string();
+                (author, id.to_string())
+            })
+            .collect()
+    };
+
+    let first = run().await;
+    assert!(first.starts_with("author,period,count,machine_id\n"));
+    let first = ids(&first);
+    let second = ids(&run().await);
+    assert_eq!(first, second);
+
+    let ada = &first["Ada"];
+    assert_eq!(ada.len(), 16);
+    assert!(ada.chars().all(|c| c.is_ascii_hexdigit()));
+    assert_eq!(&first["ADA"], ada);
+    assert_ne!(&first["SHY"], ada);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_shortlog_skips_unreadable_commits() {
+    let temp_path = tempdir().unwrap();
+    test::setup_with_new_libra_in(temp_path.path()).await;
+    let _guard = ChangeDirGuard::new(temp_path.path());
+    let tip = create_test_commit_tree().await;
+
+    // Corrupt Commit_13; Commit_11 and Commit_12 are only reachable through it.
+    let corrupt = libra::command::log::get_reachable_commits(tip, None)
+        .await
+        .into_iter()
+        .find(|commit| commit.message.trim() == "Commit_13")
+        .unwrap()
+        .id
+        .to_string();
+    let storage = libra::utils::util::try_get_storage_path(None).unwrap();
+    let object = storage
+        .join("objects")
+        .join(&corrupt[..2])
+        .join(&corrupt[2..]);
+    std::fs::remove_file(&object).unwrap();
+    std::fs::write(&object, b"not a zlib stream").unwrap();
+
+    let run = |flags: &[&str]| {
+        let args = ShortlogArgs::try_parse_from(["libra"].iter().chain(flags)).unwrap();
+        async move {
+            let mut buf = Vec::new();
+            shortlog::execute_to(args, &mut buf).await.unwrap();
+            String::from_utf8(buf).unwrap()
+        }
+    };
+
+    // The rest of the history is still counted, with or without the cache.
+    let expected = "9 commits by 6 authors\n";
+    assert_eq!(run(&["--oneline-total", "--no-cache"]).await, expected);
+    assert_eq!(run(&["--oneline-total"]).await, expected);
+    // `--strict` aborts instead, before writing any report.
+    assert_eq!(
+        run(&["--oneline-total", "--no-cache", "--strict"]).await,
+        ""
+    );
+
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_libra"))
+        .current_dir(temp_path.path())
+        .args(["shortlog", "--oneline-total", "--no-cache"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("9 commits by 6 authors"), "{stdout}");
+    assert!(
+        stdout.contains("WARN") && stdout.contains(&corrupt),
+        "missing warning in: {stdout}"
+    );
+
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_libra"))
+        .current_dir(temp_path.path())
+        .args(["shortlog", "--no-cache", "--strict"])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains(&format!("fatal: unreadable commit object {corrupt}")),
+        "{stderr}"
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn test_shortlog_all_counts_shared_commits_once() {
+    let temp_path = tempdir().unwrap();
+    test::setup_with_new_libra_in(temp_path.path()).await;
+    let _guard = ChangeDirGuard::new(temp_path.path());
+    let tip = create_test_commit_tree().await;
+
+    // `side` points at the same commit as master; `feature` adds one commit on top of it.
+    Branch::update_branch("side", &tip, None).await;
+    let mut newbie = Commit::new(
+        create_signature(SignatureType::Author, "NEWBIE"),
+        create_signature(SignatureType::Committer, "NEWBIE"),
+        ObjectHash::new(&[15; 20]),
+        vec![ObjectHash::from_str(&tip).unwrap()],
+        &format_commit_msg("Commit_15", None),
+    );
+    newbie.author.timestamp = parse_date("2026-01-15").unwrap() as usize;
+    newbie.committer.timestamp = newbie.author.timestamp;
+    save_object(&newbie, &newbie.id).unwrap();
+    Branch::update_branch("feature", &newbie.id.to_string(), None).await;
+
+    let run = |flags: &[&str]| {
+        let args = ShortlogArgs::try_parse_from(["libra"].iter().chain(flags)).unwrap();
+        async move {
+            let mut buf = Vec::new();
+            shortlog::execute_to(args, &mut buf).await.unwrap();
+            String::from_utf8(buf).unwrap()
+        }
+    };
+
+    assert_eq!(run(&["--oneline-total"]).await, "12 commits by 6 authors\n");
+    assert_eq!(
+        run(&["--oneline-total", "--all"]).await,
+        "13 commits by 7 authors\n"
+    );
+
+    // Commit_7 is reachable from all three tips and still counted once.
+    let output = run(&["-s", "--all"]).await;
+    let lines: Vec<_> = output.lines().collect();
+    assert!(lines.contains(&"   1  GUXUE"), "{output}");
+    assert!(lines.contains(&"   1  NEWBIE"), "{output}");
+    assert!(lines.contains(&"   5  LEAVE"), "{output}");
+}
+
+#[tokio::test]
+#[serial]
+async fn test_shortlog_aliases_merge_identities() {
+    let temp_path = tempdir().unwrap();
+    test::setup_with_new_libra_in(temp_path.path()).await;
+    let _guard = ChangeDirGuard::new(temp_path.path());
+    create_test_commit_tree().await;
+
+    // GUXUE is matched by name, MMONK by email; both count as SHY.
+    let aliases = temp_path.path().join("contributors");
+    std::fs::write(
+        &aliases,
+        "# canonical = aliases\nShy Team <team@oa.org> = SHY, guxue, mmonk@oa.org\n",
+    )
+    .unwrap();
+
+    let run = |flags: &[&str]| {
+        let args = ShortlogArgs::try_parse_from(["libra"].iter().chain(flags)).unwrap();
+        async move {
+            let mut buf = Vec::new();
+            shortlog::execute_to(args, &mut buf).await.unwrap();
+            String::from_utf8(buf).unwrap()
+        }
+    };
+
+    let before = run(&["-s", "-n"]).await;
+    assert!(before.lines().any(|l| l == "   2  SHY"), "{before}");
+    let aliases = aliases.to_str().unwrap();
+    let output = run(&["-s", "-n", "--aliases", aliases]).await;
+    let lines: Vec<_> = output.lines().collect();
+    assert_eq!(lines[..2], ["   5  LEAVE", "   4  Shy Team"], "{output}");
+    assert!(
+        !output.contains("GUXUE") && !output.contains("MMONK"),
+        "{output}"
+    );
+
+    // With a canonical email, `-e` merges the identities too.
+    let output = run(&["-s", "-e", "--aliases", aliases]).await;
+    assert!(
+        output.lines().any(|l| l == "   4  Shy Team <team@oa.org>"),
+        "{output}"
+    );
+
+    let output = run(&["--oneline-total", "--aliases", aliases]).await;
+    assert_eq!(output, "12 commits by 4 authors\n");
+
+    // Editing the file invalidates cached aggregates built from it.
+    let cached = run(&["-s", "-n", "--cache", "--aliases", aliases]).await;
+    assert!(cached.lines().any(|l| l == "   4  Shy Team"), "{cached}");
+    std::fs::write(aliases, "Shy Team = SHY\n").unwrap();
+    let cached = run(&["-s", "-n", "--cache", "--aliases", aliases]).await;
+    assert_eq!(cached, run(&["-s", "-n", "--aliases", aliases]).await);
+    assert!(cached.lines().any(|l| l == "   2  Shy Team"), "{cached}");
+}
+
+#[tokio::test]
+#[serial]
+async fn test_shortlog_jobs_do_not_change_output() {
+    let temp_path = tempdir().unwrap();
+    test::setup_with_new_libra_in(temp_path.path()).await;
+    let _guard = ChangeDirGuard::new(temp_path.path());
+    let tip = create_test_commit_tree().await;
+    Branch::update_branch("side", &tip, None).await;
+
+    let run = |flags: &[&str]| {
+        let args = ShortlogArgs::try_parse_from(["libra"].iter().chain(flags)).unwrap();
+        async move {
+            let mut buf = Vec::new();
+            shortlog::execute_to(args, &mut buf).await.unwrap();
+            String::from_utf8(buf).unwrap()
+        }
+    };
+
+    // Most commits share a timestamp, so subjects are listed in the order they were read.
+    for flags in [
+        &["--no-cache", "--abbrev"][..],
+        &["--no-cache", "--all", "-e", "--body"],
+        &["--abbrev"],
+    ] {
+        let sequential = run(&[flags, &["--jobs", "1"]].concat()).await;
+        assert!(sequential.contains("Commit_7"), "{sequential}");
+        for jobs in ["2", "8"] {
+            let parallel = run(&[flags, &["--jobs", jobs]].concat()).await;
+            assert_eq!(parallel, sequential, "--jobs {jobs} with {flags:?}");
+        }
+    }
+
+    assert_eq!(run(&["--jobs", "0"]).await, "");
+}
@@ -0,0 +1,101 @@
+//! Tests for the `stash` command, focusing on the AI state recorded by `stash push --include-ai`.
+
+use std::{fs, path::Path};
+
+use libra::{
+    cli::Stash,
+    command::stash,
+    internal::ai::{
+        intent::{clear_active_intent, read_active_intent, write_active_intent},
+        session::{SessionState, SessionStore},
+    },
+};
+
+use super::*;
+
+/// Commit `file.txt` and then modify it so there is something to stash.
+async fn commit_and_modify(repo: &Path) {
+    fs::write(repo.join("file.txt"), "original").unwrap();
+    add::execute(AddArgs {
+        pathspec: vec![String::from("file.txt")],
+        all: false,
+        update: false,
+        refresh: false,
+        force: false,
+        verbose: false,
+        dry_run: false,
+        ignore_errors: false,
+    })
+    .await;
+    commit::execute(CommitArgs {
+        message: Some("initial".to_string()),
+        disable_pre: true,
+        ..Default::default()
+    })
+    .await;
+    fs::write(repo.join("file.txt"), "modified").unwrap();
+}
+
+#[tokio::test]
+#[serial]
+/// Stashing with `--include-ai` records the intent pointer and session, and pop restores them.
+async fn test_stash_include_ai_restores_intent_and_session() {
+    let repo = tempdir().unwrap();
+    test::setup_with_new_libra_in(repo.path()).await;
+    let _guard = ChangeDirGuard::new(repo.path());
+    let storage = repo.path().join(".libra");
+
+    commit_and_modify(repo.path()).await;
+
+    let session = SessionState::new(&repo.path().to_string_lossy());
+    SessionStore::new(repo.path()).save(&session).unwrap();
+    write_active_intent(&storage, "intent-123").unwrap();
+
+    stash::execute(Stash::Push {
+        message: None,
+        include_ai: true,
+    })
+    .await;
+    assert_eq!(
+        fs::read_to_string(repo.path().join("file.txt")).unwrap(),
+        "original"
+    );
+
+    clear_active_intent(&storage).unwrap();
+    assert_eq!(read_active_intent(&storage).unwrap(), None);
+
+    let ai_state = stash::pop(None).await.unwrap().expect("AI state recorded");
+    assert_eq!(ai_state.intent_id.as_deref(), Some("intent-123"));
+    assert_eq!(ai_state.session_id.as_deref(), Some(session.id.as_str()));
+    assert_eq!(
+        read_active_intent(&storage).unwrap().as_deref(),
+        Some("intent-123")
+    );
+    assert_eq!(
+        fs::read_to_string(repo.path().join("file.txt")).unwrap(),
+        "modified"
+    );
+}
+
+#[tokio::test]
+#[serial]
+/// A plain stash carries no AI state, so pop leaves the intent pointer alone.
+async fn test_stash_without_include_ai_ignores_ai_state() {
+    let repo = tempdir().unwrap();
+    test::setup_with_new_libra_in(repo.path()).await;
+    let _guard = ChangeDirGuard::new(repo.path());
+    let storage = repo.path().join(".libra");
+
+    commit_and_modify(repo.path()).await;
+    write_active_intent(&storage, "intent-123").unwrap();
+
+    stash::execute(Stash::Push {
+        message: None,
+        include_ai: false,
+    })
+    .await;
+    clear_active_intent(&storage).unwrap();
+
+    assert_eq!(stash::pop(None).await.unwrap(), None);
+    assert_eq!(read_active_intent(&storage).unwrap(), None);
+}
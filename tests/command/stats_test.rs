@@ -0,0 +1,136 @@
+//! Tests repository statistics: history totals, ref and object counts, the largest blobs,
+//! merged contributor identities, and the `--json --fast` report.
+
+use std::{fs, path::Path};
+
+use serde_json::Value;
+
+use super::*;
+
+/// Three commits by three identities, two of them the same person, plus a branch with one
+/// more commit, a lightweight tag and an annotated tag.
+fn build_fixture(dir: &Path) {
+    run_libra(dir, &["init"]);
+    run_libra(dir, &["config", "user.name", "Tagger"]);
+    run_libra(dir, &["config", "user.email", "tagger@example.com"]);
+    commit_files(
+        dir,
+        Some("Alice <alice@example.com>"),
+        "initial",
+        &[("README.md", "hello\n"), ("src/lib.rs", "fn main() {}\n")],
+    );
+    commit_files(
+        dir,
+        Some("Bob <bob@example.com>"),
+        "logo",
+        &[("assets/logo.svg", &"<svg/>\n".repeat(10))],
+    );
+    run_libra(dir, &["tag", "v1"]);
+    commit_files(
+        dir,
+        Some("Robert <bob@example.com>"),
+        "readme",
+        &[("README.md", "hello world\n")],
+    );
+    run_libra(dir, &["tag", "-m", "second release", "v2"]);
+    run_libra(dir, &["switch", "-c", "feature"]);
+    commit_files(
+        dir,
+        Some("Alice <alice@example.com>"),
+        "run",
+        &[("src/lib.rs", "fn main() { run() }\n")],
+    );
+    run_libra(dir, &["switch", "master"]);
+}
+
+fn json(dir: &Path, args: &[&str]) -> Value {
+    let output = run_libra(dir, args);
+    serde_json::from_str(&output).unwrap_or_else(|e| panic!("invalid JSON ({e}): {output}"))
+}
+
+#[test]
+fn test_stats_counts_history_and_objects() {
+    let temp = tempdir().unwrap();
+    let dir = temp.path();
+    build_fixture(dir);
+
+    let stats = json(dir, &["stats", "--json"]);
+    assert_eq!(stats["commits"], 4, "{stats}");
+    assert_eq!(stats["contributors"], 3, "{stats}");
+    assert_eq!(stats["branches"], 2, "{stats}");
+    assert_eq!(stats["tags"], 2, "{stats}");
+    assert_eq!(stats["commits_per_week"], 0.08, "{stats}");
+    assert!(stats["first_commit"].as_str().unwrap() <= stats["last_commit"].as_str().unwrap());
+
+    let objects = &stats["objects"];
+    assert_eq!(objects["commits"]["count"], 4, "{stats}");
+    // The root tree of each commit, `src` in the first and last, `assets` once.
+    assert_eq!(objects["trees"]["count"], 7, "{stats}");
+    // README.md and src/lib.rs twice each, and the logo.
+    assert_eq!(objects["blobs"]["count"], 5, "{stats}");
+    assert_eq!(objects["tags"]["count"], 1, "{stats}");
+    for kind in ["commits", "trees", "blobs", "tags"] {
+        assert!(
+            objects[kind]["size"].as_u64().unwrap() > 0,
+            "{kind}: {stats}"
+        );
+    }
+
+    let largest = stats["largest_blobs"].as_array().unwrap();
+    let paths: Vec<_> = largest
+        .iter()
+        .map(|blob| blob["path"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        paths,
+        [
+            "assets/logo.svg",
+            "src/lib.rs",
+            "src/lib.rs",
+            "README.md",
+            "README.md"
+        ]
+    );
+    assert_eq!(largest[0]["size"], 70);
+    assert_eq!(
+        objects["blobs"]["size"],
+        largest
+            .iter()
+            .map(|blob| blob["size"].as_u64().unwrap())
+            .sum::<u64>()
+    );
+
+    let top = json(dir, &["stats", "--json", "--top", "1"]);
+    assert_eq!(top["largest_blobs"].as_array().unwrap().len(), 1);
+
+    let text = run_libra(dir, &["stats"]);
+    assert!(text.contains("Commits:          4\n"), "{text}");
+    assert!(text.contains("Largest blobs\n"), "{text}");
+    assert!(text.contains("  assets/logo.svg\n"), "{text}");
+}
+
+#[test]
+fn test_stats_fast_skips_blobs() {
+    let temp = tempdir().unwrap();
+    let dir = temp.path();
+    build_fixture(dir);
+
+    let stats = json(dir, &["stats", "--json", "--fast"]);
+    assert_eq!(stats["objects"]["blobs"]["count"], 5, "{stats}");
+    assert!(stats["objects"]["blobs"].get("size").is_none(), "{stats}");
+    assert!(stats.get("largest_blobs").is_none(), "{stats}");
+
+    let text = run_libra(dir, &["stats", "--fast"]);
+    assert!(!text.contains("Largest blobs"), "{text}");
+}
+
+#[test]
+fn test_stats_merges_aliased_contributors() {
+    let temp = tempdir().unwrap();
+    let dir = temp.path();
+    build_fixture(dir);
+    fs::write(dir.join("AUTHORS"), "Bob = Robert\n").unwrap();
+
+    let stats = json(dir, &["stats", "--json", "--aliases", "AUTHORS"]);
+    assert_eq!(stats["contributors"], 2, "{stats}");
+}
@@ -1,7 +1,8 @@
 //! Tests status reporting for staged, unstaged, ignored files and path filtering.
 
-use std::{fs, io::Write};
+use std::{fs, io::Write, path::PathBuf, process::Command, thread, time::Duration};
 
+use git_internal::internal::index::Index;
 use libra::{
     cli::Stash,
     command::{
@@ -11,6 +12,7 @@ use libra::{
             output_porcelain,
         },
     },
+    utils::{path, stat_cache},
 };
 
 use super::*;
@@ -1184,10 +1186,12 @@ async fn test_status_short_format_with_branch() {
         StatusArgs {
             porcelain: None,
             short: true,
+            no_short: false,
             branch: true,
             show_stash: false,
             ignored: false,
             untracked_files: UntrackedFiles::Normal,
+            force_unlock: false,
         },
         &mut output,
     )
@@ -1245,10 +1249,12 @@ async fn test_status_porcelain_format_with_branch() {
         StatusArgs {
             porcelain: Some(PorcelainVersion::V1),
             short: false,
+            no_short: false,
             branch: true,
             show_stash: false,
             ignored: false,
             untracked_files: UntrackedFiles::Normal,
+            force_unlock: false,
         },
         &mut output,
     )
@@ -1314,6 +1320,7 @@ async fn test_status_show_stash_with_existing_stash() {
 
     stash::execute(Stash::Push {
         message: Some("test stash".to_string()),
+        include_ai: false,
     })
     .await;
 
@@ -1323,10 +1330,12 @@ async fn test_status_show_stash_with_existing_stash() {
         StatusArgs {
             porcelain: None,
             short: false,
+            no_short: false,
             branch: false,
             show_stash: true,
             ignored: false,
             untracked_files: UntrackedFiles::Normal,
+            force_unlock: false,
         },
         &mut output,
     )
@@ -1349,10 +1358,12 @@ async fn test_status_show_stash_with_existing_stash() {
         StatusArgs {
             porcelain: Some(PorcelainVersion::V1),
             short: false,
+            no_short: false,
             branch: false,
             show_stash: true,
             ignored: false,
             untracked_files: UntrackedFiles::Normal,
+            force_unlock: false,
         },
         &mut output,
     )
@@ -1375,10 +1386,12 @@ async fn test_status_show_stash_with_existing_stash() {
         StatusArgs {
             porcelain: None,
             short: true,
+            no_short: false,
             branch: false,
             show_stash: true,
             ignored: false,
             untracked_files: UntrackedFiles::Normal,
+            force_unlock: false,
         },
         &mut output,
     )
@@ -1428,10 +1441,12 @@ async fn test_status_show_stash_without_stash() {
         StatusArgs {
             porcelain: None,
             short: false,
+            no_short: false,
             branch: false,
             show_stash: true,
             ignored: false,
             untracked_files: UntrackedFiles::Normal,
+            force_unlock: false,
         },
         &mut output,
     )
@@ -1510,10 +1525,12 @@ async fn test_status_branch_detached_head() {
         StatusArgs {
             porcelain: None,
             short: true,
+            no_short: false,
             branch: true,
             show_stash: false,
             ignored: false,
             untracked_files: UntrackedFiles::Normal,
+            force_unlock: false,
         },
         &mut output,
     )
@@ -1902,3 +1919,217 @@ async fn test_status_after_add() {
         "Added file should appear in changes_to_be_committed"
     );
 }
+
+#[tokio::test]
+#[serial]
+/// A touched-but-unchanged file is hashed once; the refreshed stat data lets the next status
+/// skip hashing entirely.
+async fn test_status_skips_hashing_untouched_files_on_second_run() {
+    let test_dir = tempdir().unwrap();
+    test::setup_with_new_libra_in(test_dir.path()).await;
+    let _guard = test::ChangeDirGuard::new(test_dir.path());
+
+    for name in ["a.txt", "b.txt", "c.txt"] {
+        fs::write(name, format!("content of {name}\n")).unwrap();
+    }
+    thread::sleep(Duration::from_millis(20));
+    add::execute(AddArgs {
+        pathspec: vec![String::from(".")],
+        all: true,
+        update: false,
+        verbose: false,
+        dry_run: false,
+        ignore_errors: false,
+        refresh: false,
+        force: false,
+    })
+    .await;
+
+    // rewrite one file with identical content: new mtime, same blob
+    thread::sleep(Duration::from_millis(20));
+    fs::write("b.txt", "content of b.txt\n").unwrap();
+    thread::sleep(Duration::from_millis(20));
+
+    let hashes = stat_cache::content_hashes();
+    let mut output = Vec::new();
+    status_execute(StatusArgs::default(), &mut output).await;
+    assert_eq!(stat_cache::content_hashes() - hashes, 1);
+    assert!(!String::from_utf8(output).unwrap().contains("modified"));
+
+    let hashes = stat_cache::content_hashes();
+    status_execute(StatusArgs::default(), &mut Vec::new()).await;
+    assert_eq!(
+        stat_cache::content_hashes() - hashes,
+        0,
+        "untouched files must not be hashed on the second status"
+    );
+
+    // a real edit is still detected
+    fs::write("c.txt", "changed\n").unwrap();
+    assert_eq!(
+        changes_to_be_staged().modified,
+        vec![PathBuf::from("c.txt")]
+    );
+}
+
+#[tokio::test]
+#[serial]
+/// Concurrent `add` and `status` processes never leave a torn index: every add either lands or
+/// is refused with the lock error, and the index always parses.
+async fn test_concurrent_add_and_status_keep_index_intact() {
+    let test_dir = tempdir().unwrap();
+    test::setup_with_new_libra_in(test_dir.path()).await;
+    let _guard = test::ChangeDirGuard::new(test_dir.path());
+
+    let names: Vec<String> = (0..8).map(|i| format!("file{i}.txt")).collect();
+    for name in &names {
+        fs::write(name, format!("{name}\n").repeat(100)).unwrap();
+    }
+
+    for _ in 0..3 {
+        let adds: Vec<_> = names
+            .iter()
+            .map(|name| {
+                let child = Command::new(env!("CARGO_BIN_EXE_libra"))
+                    .args(["add", name])
+                    .stdout(std::process::Stdio::null())
+                    .stderr(std::process::Stdio::piped())
+                    .spawn()
+                    .unwrap();
+                (name, child)
+            })
+            .collect();
+        let statuses: Vec<_> = (0..4)
+            .map(|_| {
+                Command::new(env!("CARGO_BIN_EXE_libra"))
+                    .args(["status", "--porcelain"])
+                    .output()
+                    .unwrap()
+            })
+            .collect();
+
+        let mut added = Vec::new();
+        for (name, child) in adds {
+            let output = child.wait_with_output().unwrap();
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            if stderr.is_empty() {
+                added.push(name);
+            } else {
+                assert!(
+                    stderr.contains("another libra process holds the index lock"),
+                    "unexpected add failure: {stderr}"
+                );
+            }
+        }
+        for output in statuses {
+            assert!(
+                output.status.success() && output.stderr.is_empty(),
+                "status failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+
+        let index = Index::load(path::index()).expect("index must never be torn");
+        for name in added {
+            assert!(index.tracked(name, 0), "{name} was added but is missing");
+        }
+        assert!(!test_dir.path().join(".libra/index.lock").exists());
+    }
+}
+
+#[tokio::test]
+#[serial]
+/// `commit -a` restages tracked files while `add` processes stage new ones: an add that
+/// reports success must still be in the index afterwards, not overwritten by the commit's
+/// copy of the index loaded before it.
+async fn test_concurrent_add_and_commit_all_lose_no_entries() {
+    let test_dir = tempdir().unwrap();
+    test::setup_with_new_libra_in(test_dir.path()).await;
+    let _guard = test::ChangeDirGuard::new(test_dir.path());
+
+    fs::write("tracked.txt", "0\n").unwrap();
+    let libra = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_libra"))
+            .args(args)
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .unwrap()
+    };
+    let lock_refused = |stderr: &str| stderr.contains("another libra process holds the index lock");
+    assert!(libra(&["add", "tracked.txt"]).wait().unwrap().success());
+    assert!(libra(&["commit", "-m", "init"]).wait().unwrap().success());
+
+    for round in 0..15 {
+        fs::write("tracked.txt", format!("{round}\n").repeat(1000)).unwrap();
+        let names: Vec<String> = (0..6).map(|i| format!("new{round}_{i}.txt")).collect();
+        for name in &names {
+            fs::write(name, format!("{name}\n").repeat(100)).unwrap();
+        }
+
+        let commit = libra(&["commit", "-a", "-m", &format!("round {round}")]);
+        let adds: Vec<_> = names
+            .iter()
+            .map(|name| (name, libra(&["add", name])))
+            .collect();
+
+        let output = commit.wait_with_output().unwrap();
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            output.status.success() || lock_refused(&stderr),
+            "unexpected commit failure: {stderr}"
+        );
+        let mut added = Vec::new();
+        for (name, child) in adds {
+            let output = child.wait_with_output().unwrap();
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            if stderr.is_empty() {
+                added.push(name);
+            } else {
+                assert!(lock_refused(&stderr), "unexpected add failure: {stderr}");
+            }
+        }
+
+        let index = Index::load(path::index()).unwrap();
+        for name in added {
+            assert!(index.tracked(name, 0), "{name} was added but is missing");
+        }
+        assert!(!test_dir.path().join(".libra/index.lock").exists());
+    }
+}
+
+#[tokio::test]
+#[serial]
+/// A lock left behind by a killed writer blocks index writes with its age, until it is removed
+/// with `status --force-unlock`.
+async fn test_stale_index_lock_needs_force_unlock() {
+    let test_dir = tempdir().unwrap();
+    test::setup_with_new_libra_in(test_dir.path()).await;
+    let _guard = test::ChangeDirGuard::new(test_dir.path());
+    fs::write("a.txt", "a\n").unwrap();
+    fs::write(".libra/index.lock", "DIRC half-written").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_libra"))
+        .args(["add", "a.txt"])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("another libra process holds the index lock (created"));
+    assert!(stderr.contains("seconds ago"));
+    assert!(stderr.contains("libra status --force-unlock"));
+    assert!(!Index::load(path::index()).unwrap().tracked("a.txt", 0));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_libra"))
+        .args(["status", "--force-unlock"])
+        .output()
+        .unwrap();
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Removed stale index lock"));
+    assert!(!test_dir.path().join(".libra/index.lock").exists());
+
+    let output = Command::new(env!("CARGO_BIN_EXE_libra"))
+        .args(["add", "a.txt"])
+        .output()
+        .unwrap();
+    assert!(output.stderr.is_empty());
+    assert!(Index::load(path::index()).unwrap().tracked("a.txt", 0));
+}